@@ -1,16 +1,21 @@
 use std::sync::Arc;
 
 use hb_api_websocket::broadcaster::WebSocketBroadcaster;
-use hb_dao::Db;
+use hb_dao::{token_usage::TokenUsage, Db};
 
 pub struct ApiMqttCtx {
     dao: ApiMqttDaoCtx,
     websocket: ApiMqttWsCtx,
+    read_only: bool,
 }
 
 impl ApiMqttCtx {
-    pub fn new(dao: ApiMqttDaoCtx, websocket: ApiMqttWsCtx) -> Self {
-        Self { dao, websocket }
+    pub fn new(dao: ApiMqttDaoCtx, websocket: ApiMqttWsCtx, read_only: bool) -> Self {
+        Self {
+            dao,
+            websocket,
+            read_only,
+        }
     }
 
     pub fn dao(&self) -> &ApiMqttDaoCtx {
@@ -20,20 +25,29 @@ impl ApiMqttCtx {
     pub fn websocket(&self) -> &ApiMqttWsCtx {
         &self.websocket
     }
+
+    pub fn read_only(&self) -> &bool {
+        &self.read_only
+    }
 }
 
 pub struct ApiMqttDaoCtx {
     db: Arc<Db>,
+    token_usage: Arc<TokenUsage>,
 }
 
 impl ApiMqttDaoCtx {
-    pub fn new(db: Arc<Db>) -> Self {
-        Self { db }
+    pub fn new(db: Arc<Db>, token_usage: Arc<TokenUsage>) -> Self {
+        Self { db, token_usage }
     }
 
     pub fn db(&self) -> &Db {
         &self.db
     }
+
+    pub fn token_usage(&self) -> &TokenUsage {
+        &self.token_usage
+    }
 }
 
 pub struct ApiMqttWsCtx {
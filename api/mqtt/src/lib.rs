@@ -1,129 +1,404 @@
 use std::{
-    sync::Arc,
-    time::{Duration, Instant},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
+use anyhow::{Error, Result};
 use context::ApiMqttCtx;
-use model::payload::Payload;
+use hb_api_websocket::message::RecordEvent;
+use model::{
+    payload::{Payload, PayloadJson},
+    response::{Response, ResponseJson},
+};
+use rand::Rng;
 use rumqttc::v5::{
     mqttbytes::{v5::Packet, QoS},
     AsyncClient, Event, EventLoop, MqttOptions,
 };
-use service::Service;
+use serde_json::Value;
+use service::{send_response, Service};
 use tokio::{sync::mpsc, task::JoinHandle};
 use tokio_util::sync::CancellationToken;
+use topic::MqttTopic;
 use uuid::Uuid;
 
 pub mod context;
 mod model;
 mod service;
+pub mod topic;
 mod util;
 
+// Wait before the first reconnect attempt, doubled on every attempt after it
+const RECONNECT_INITIAL_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct ApiMqttClient {
     client: AsyncClient,
     eventloop: EventLoop,
-    topic: String,
-    timeout: Duration,
+    topic: MqttTopic,
+    reconnect_max_retries: Option<u32>,
+    reconnect_max_interval: Duration,
+    max_batch_items: usize,
+    connected: Arc<AtomicBool>,
     service: Service,
     payload_sender: mpsc::UnboundedSender<Payload>,
+    response_sender: mpsc::Sender<Response>,
+    response_receiver: mpsc::Receiver<Response>,
+    publish: Option<ApiMqttPublish>,
 }
 
 impl ApiMqttClient {
     pub fn new(
         host: &str,
         port: &u16,
-        topic: &str,
+        topic: MqttTopic,
         username: &str,
         password: &str,
         channel_capacity: &usize,
         timeout: &Duration,
+        reconnect_max_retries: &Option<u32>,
+        reconnect_max_interval: &Duration,
+        max_batch_items: &usize,
+        connected: Arc<AtomicBool>,
+        publish: Option<ApiMqttPublish>,
         ctx: ApiMqttCtx,
     ) -> Self {
         hb_log::info(Some("⚡"), "[ApiMqttClient] Initializing component");
 
         let mut mqtt_opts = MqttOptions::new(format!("hyperbase-{}", Uuid::now_v7()), host, *port);
         mqtt_opts.set_credentials(username, password);
+        mqtt_opts.set_connection_timeout(timeout.as_secs().max(1));
 
         let (client, eventloop) = AsyncClient::new(mqtt_opts, *channel_capacity);
 
-        let (service, payload_sender) = Service::new(Arc::new(ctx));
+        let (response_sender, response_receiver) = mpsc::channel(*channel_capacity);
+        let (service, payload_sender) = Service::new(Arc::new(ctx), response_sender.clone());
 
         Self {
             client,
             eventloop,
-            topic: topic.to_owned(),
-            timeout: *timeout,
+            topic,
+            reconnect_max_retries: *reconnect_max_retries,
+            reconnect_max_interval: *reconnect_max_interval,
+            max_batch_items: *max_batch_items,
+            connected,
             service,
             payload_sender,
+            response_sender,
+            response_receiver,
+            publish,
         }
     }
 
-    pub fn run_none() -> JoinHandle<()> {
+    pub fn run_none(cancel_token: CancellationToken) -> JoinHandle<Result<()>> {
         hb_log::info(Some("⏩"), "[ApiMqttClient] Skipping component");
 
-        tokio::spawn((|| async {})())
+        tokio::spawn((|| async move {
+            cancel_token.cancelled().await;
+            Ok(())
+        })())
     }
 
-    pub fn run(self, cancel_token: CancellationToken) -> JoinHandle<()> {
+    pub fn run(self, cancel_token: CancellationToken) -> JoinHandle<Result<()>> {
         hb_log::info(Some("💫"), "[ApiMqttClient] Running component");
 
         tokio::spawn((|| async move {
             let service = self.service.run();
+            let publish = Self::publish(self.client.clone(), self.publish);
 
-            self.client
-                .subscribe(self.topic, QoS::ExactlyOnce)
-                .await
-                .unwrap();
-
-            tokio::select! {
-                _ = cancel_token.cancelled() => {}
-                _ = tokio::signal::ctrl_c() => {}
-                s = service => {
-                    if let Err(err) = s {
-                        hb_log::panic(None, &format!("[ApiMqttClient] Receiver service error: {err}"));
-                    }
-                }
-                _ = Self::poll(self.eventloop, self.timeout, self.payload_sender) => {}
-            }
+            let result = tokio::select! {
+                _ = cancel_token.cancelled() => Ok(()),
+                _ = tokio::signal::ctrl_c() => Ok(()),
+                s = service => match s {
+                    Ok(_) => Err(Error::msg("Receiver service stopped")),
+                    Err(err) => Err(Error::msg(format!("Receiver service error: {err}"))),
+                },
+                p = Self::poll(
+                    self.eventloop,
+                    self.client.clone(),
+                    self.topic,
+                    self.reconnect_max_retries,
+                    self.reconnect_max_interval,
+                    self.max_batch_items,
+                    self.connected,
+                    self.payload_sender,
+                    self.response_sender,
+                ) => p,
+                p = publish => p,
+                r = Self::respond(self.client.clone(), self.response_receiver) => r,
+            };
 
             hb_log::info(None, "[ApiMqttClient] Shutting down component");
             let _ = self.client.disconnect().await;
+
+            result
         })())
     }
 
+    // Events are published one at a time so the changes of a record arrive in order. The REST and
+    // MQTT handlers only ever queue them, a broker that is slow or down never holds them up.
+    async fn publish(client: AsyncClient, publish: Option<ApiMqttPublish>) -> Result<()> {
+        let mut publish = match publish {
+            Some(publish) => publish,
+            None => return std::future::pending().await,
+        };
+
+        let qos = match publish.qos {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            2 => QoS::ExactlyOnce,
+            qos => {
+                return Err(Error::msg(format!(
+                    "Invalid publish QoS {qos}, it must be 0, 1 or 2"
+                )))
+            }
+        };
+
+        while let Some(event) = publish.event_rx.recv().await {
+            let topic = format!(
+                "{}/{}/{}",
+                publish.topic_prefix.trim_end_matches('/'),
+                event.project_id(),
+                event.collection_id()
+            );
+            let payload = match serde_json::to_vec(&event) {
+                Ok(payload) => payload,
+                Err(err) => {
+                    hb_log::error(
+                        None,
+                        &format!("[ApiMqttClient] Event serialize error: {err}"),
+                    );
+                    continue;
+                }
+            };
+            if let Err(err) = client.publish(topic.clone(), qos, false, payload).await {
+                hb_log::error(
+                    None,
+                    &format!("[ApiMqttClient] Failed to publish to topic '{topic}': {err}"),
+                );
+            }
+        }
+
+        Err(Error::msg("Publisher channel closed"))
+    }
+
+    async fn respond(client: AsyncClient, mut response_rx: mpsc::Receiver<Response>) -> Result<()> {
+        while let Some(response) = response_rx.recv().await {
+            let payload = match serde_json::to_vec(response.json()) {
+                Ok(payload) => payload,
+                Err(err) => {
+                    hb_log::error(
+                        None,
+                        &format!("[ApiMqttClient] Response serialize error: {err}"),
+                    );
+                    continue;
+                }
+            };
+            if let Err(err) = client
+                .publish(response.topic(), QoS::AtLeastOnce, false, payload)
+                .await
+            {
+                hb_log::error(
+                    None,
+                    &format!(
+                        "[ApiMqttClient] Failed to publish response to topic '{}': {err}",
+                        response.topic()
+                    ),
+                );
+            }
+        }
+
+        Err(Error::msg("Response channel closed"))
+    }
+
+    // The event loop connects again on the next poll after an error, so a broker that goes away
+    // is retried with a growing, jittered wait until it's back or the retries run out
     async fn poll(
         mut eventloop: EventLoop,
-        timeout: Duration,
+        client: AsyncClient,
+        topic: MqttTopic,
+        reconnect_max_retries: Option<u32>,
+        reconnect_max_interval: Duration,
+        max_batch_items: usize,
+        connected: Arc<AtomicBool>,
         payload_sender: mpsc::UnboundedSender<Payload>,
-    ) {
-        let mut now = Instant::now();
+        response_sender: mpsc::Sender<Response>,
+    ) -> Result<()> {
+        let mut retries = 0;
 
         loop {
-            if let Ok(event) = eventloop.poll().await {
-                now = Instant::now();
-                if let Event::Incoming(packet) = &event {
-                    if let Packet::Publish(publish) = packet {
-                        match serde_json::from_slice::<Payload>(&publish.payload) {
-                            Ok(payload) => {
-                                if let Err(err) = payload_sender.send(payload) {
-                                    hb_log::error(
-                                        None,
-                                        &format!("[ApiMqttClient] Send payload error: {err}"),
-                                    );
-                                }
-                            }
-                            Err(err) => hb_log::error(
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    connected.store(true, Ordering::Relaxed);
+                    if retries > 0 {
+                        hb_log::info(
+                            None,
+                            &format!(
+                                "[ApiMqttClient] Reconnected to MQTT broker after {retries} retries"
+                            ),
+                        );
+                    } else {
+                        hb_log::info(None, "[ApiMqttClient] Connected to MQTT broker");
+                    }
+                    retries = 0;
+
+                    // Every connection starts a new session, which has no subscription yet
+                    if let Err(err) = client.try_subscribe(topic.subscription(), QoS::ExactlyOnce) {
+                        return Err(Error::msg(format!("Failed to subscribe to topic: {err}")));
+                    }
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    // MQTT 5 clients can ask for a response through the properties of the publish
+                    // instead of the payload
+                    let properties = publish.properties.as_ref();
+                    let mut response_topic =
+                        properties.and_then(|properties| properties.response_topic.clone());
+                    let mut correlation_id = properties
+                        .and_then(|properties| properties.correlation_data.as_ref())
+                        .map(|data| Value::String(String::from_utf8_lossy(data).into_owned()));
+
+                    let publish_topic = String::from_utf8_lossy(&publish.topic);
+                    let (project_id, collection_id) = match topic.ids(&publish_topic) {
+                        Ok(ids) => ids,
+                        Err(err) => {
+                            hb_log::error(
+                                None,
+                                &format!(
+                                    "[ApiMqttClient] Failed to parse topic '{publish_topic}': {err}"
+                                ),
+                            );
+                            Self::respond_error(
+                                &response_sender,
+                                &response_topic,
+                                &correlation_id,
+                                &err,
+                            );
+                            continue;
+                        }
+                    };
+                    let payload = match serde_json::from_slice::<PayloadJson>(&publish.payload) {
+                        Ok(payload) => payload,
+                        Err(err) => {
+                            hb_log::error(
                                 None,
                                 &format!("[ApiMqttClient] Payload deserialize error: {err}"),
-                            ),
-                        };
+                            );
+                            Self::respond_error(
+                                &response_sender,
+                                &response_topic,
+                                &correlation_id,
+                                &err.into(),
+                            );
+                            continue;
+                        }
+                    };
+                    if payload.response_topic().is_some() {
+                        response_topic = payload.response_topic().clone();
+                    }
+                    if payload.correlation_id().is_some() {
+                        correlation_id = payload.correlation_id().clone();
+                    }
+                    match payload.into_payload(
+                        &project_id,
+                        &collection_id,
+                        &response_topic,
+                        &correlation_id,
+                        &max_batch_items,
+                    ) {
+                        Ok(payload) => {
+                            if let Err(err) = payload_sender.send(payload) {
+                                hb_log::error(
+                                    None,
+                                    &format!("[ApiMqttClient] Send payload error: {err}"),
+                                );
+                            }
+                        }
+                        Err(err) => {
+                            hb_log::error(
+                                None,
+                                &format!(
+                                    "[ApiMqttClient] Invalid payload published to topic '{publish_topic}': {err}"
+                                ),
+                            );
+                            Self::respond_error(
+                                &response_sender,
+                                &response_topic,
+                                &correlation_id,
+                                &err,
+                            );
+                        }
                     }
                 }
-                continue;
-            }
-            if Instant::now().duration_since(now) > timeout {
-                hb_log::panic(None, "[ApiMqttClient] Failed to connect to MQTT broker");
+                Ok(_) => (),
+                Err(err) => {
+                    connected.store(false, Ordering::Relaxed);
+                    if let Some(reconnect_max_retries) = reconnect_max_retries {
+                        if retries >= reconnect_max_retries {
+                            return Err(Error::msg(format!(
+                                "Failed to connect to MQTT broker after {retries} retries: {err}"
+                            )));
+                        }
+                    }
+                    retries += 1;
+
+                    let interval = RECONNECT_INITIAL_INTERVAL
+                        .saturating_mul(2_u32.saturating_pow(retries - 1))
+                        .min(reconnect_max_interval);
+                    let interval = interval.mul_f64(rand::thread_rng().gen_range(0.5..1.0));
+                    hb_log::warn(
+                        None,
+                        &format!(
+                            "[ApiMqttClient] Connection to MQTT broker failed: {err}, retrying in {interval:?} (retry {retries})"
+                        ),
+                    );
+                    tokio::time::sleep(interval).await;
+                }
             }
         }
     }
+
+    fn respond_error(
+        response_sender: &mpsc::Sender<Response>,
+        response_topic: &Option<String>,
+        correlation_id: &Option<Value>,
+        err: &Error,
+    ) {
+        if let Some(response_topic) = response_topic {
+            send_response(
+                response_sender,
+                Response::new(
+                    response_topic,
+                    ResponseJson::error(correlation_id, &err.to_string()),
+                ),
+            );
+        }
+    }
+}
+
+// Where the record changes of the collections with opt_mqtt_publish enabled are published
+pub struct ApiMqttPublish {
+    topic_prefix: String,
+    qos: u8,
+    event_rx: mpsc::Receiver<RecordEvent>,
+}
+
+impl ApiMqttPublish {
+    pub fn new(
+        topic_prefix: &str,
+        qos: &u8,
+        channel_capacity: &usize,
+    ) -> (Self, mpsc::Sender<RecordEvent>) {
+        let (event_tx, event_rx) = mpsc::channel(*channel_capacity);
+
+        (
+            Self {
+                topic_prefix: topic_prefix.to_owned(),
+                qos: *qos,
+                event_rx,
+            },
+            event_tx,
+        )
+    }
 }
@@ -1,9 +1,99 @@
 use ahash::HashMap;
+use anyhow::{Error, Result};
 use serde::Deserialize;
 use serde_json::Value;
 use uuid::Uuid;
 
-#[derive(Deserialize, Clone)]
+// Payload as it is published, the project and collection can be left out when the topic already
+// carries them
+#[derive(Deserialize)]
+pub struct PayloadJson {
+    project_id: Option<Uuid>,
+
+    token_id: Uuid,
+    user: Option<UserPayload>,
+
+    collection_id: Option<Uuid>,
+    data: PayloadDataJson,
+
+    // Where the outcome is published back to, echoing the correlation id
+    response_topic: Option<String>,
+    correlation_id: Option<Value>,
+}
+
+impl PayloadJson {
+    pub fn response_topic(&self) -> &Option<String> {
+        &self.response_topic
+    }
+
+    pub fn correlation_id(&self) -> &Option<Value> {
+        &self.correlation_id
+    }
+
+    // The response topic and correlation id are the ones resolved by the caller, since they can
+    // also come from the properties of the publish
+    pub fn into_payload(
+        self,
+        topic_project_id: &Option<Uuid>,
+        topic_collection_id: &Option<Uuid>,
+        response_topic: &Option<String>,
+        correlation_id: &Option<Value>,
+        max_batch_items: &usize,
+    ) -> Result<Payload> {
+        let (data, is_batch) = match self.data {
+            PayloadDataJson::One(data) => (Vec::from([data]), false),
+            PayloadDataJson::Many(data) => {
+                if data.is_empty() {
+                    return Err(Error::msg("Field 'data' must not be an empty array"));
+                }
+                if data.len() > *max_batch_items {
+                    return Err(Error::msg(format!(
+                        "Field 'data' can't hold more than {max_batch_items} records"
+                    )));
+                }
+                (data, true)
+            }
+        };
+
+        Ok(Payload {
+            project_id: Self::resolve_id("project_id", topic_project_id, &self.project_id)?,
+            token_id: self.token_id,
+            user: self.user,
+            collection_id: Self::resolve_id(
+                "collection_id",
+                topic_collection_id,
+                &self.collection_id,
+            )?,
+            data,
+            is_batch,
+            response_topic: response_topic.clone(),
+            correlation_id: correlation_id.clone(),
+        })
+    }
+
+    // The id in the topic is the one the broker authorized, so the payload can't point elsewhere
+    fn resolve_id(field: &str, topic_id: &Option<Uuid>, payload_id: &Option<Uuid>) -> Result<Uuid> {
+        match (topic_id, payload_id) {
+            (Some(topic_id), Some(payload_id)) if topic_id != payload_id => Err(Error::msg(
+                format!("Field '{field}' of the payload doesn't match the topic"),
+            )),
+            (Some(id), _) | (None, Some(id)) => Ok(*id),
+            (None, None) => Err(Error::msg(format!(
+                "Field '{field}' is required when the topic doesn't carry it"
+            ))),
+        }
+    }
+}
+
+// Devices batching their readings send an array of records instead of a single one
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PayloadDataJson {
+    One(HashMap<String, Value>),
+    Many(Vec<HashMap<String, Value>>),
+}
+
+#[derive(Clone)]
 pub struct Payload {
     project_id: Uuid,
 
@@ -11,7 +101,11 @@ pub struct Payload {
     user: Option<UserPayload>,
 
     collection_id: Uuid,
-    data: HashMap<String, Value>,
+    data: Vec<HashMap<String, Value>>,
+    is_batch: bool,
+
+    response_topic: Option<String>,
+    correlation_id: Option<Value>,
 }
 
 impl Payload {
@@ -31,9 +125,21 @@ impl Payload {
         &self.collection_id
     }
 
-    pub fn data(&self) -> &HashMap<String, Value> {
+    pub fn data(&self) -> &Vec<HashMap<String, Value>> {
         &self.data
     }
+
+    pub fn is_batch(&self) -> &bool {
+        &self.is_batch
+    }
+
+    pub fn response_topic(&self) -> &Option<String> {
+        &self.response_topic
+    }
+
+    pub fn correlation_id(&self) -> &Option<Value> {
+        &self.correlation_id
+    }
 }
 
 #[derive(Deserialize, Clone)]
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+// Outcome of a payload, published back on the response topic the device asked for
+pub struct Response {
+    topic: String,
+    json: ResponseJson,
+}
+
+impl Response {
+    pub fn new(topic: &str, json: ResponseJson) -> Self {
+        Self {
+            topic: topic.to_owned(),
+            json,
+        }
+    }
+
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    pub fn json(&self) -> &ResponseJson {
+        &self.json
+    }
+}
+
+#[derive(Serialize)]
+pub struct ResponseJson {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    record_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    records: Option<Vec<ResponseRecordJson>>,
+}
+
+impl ResponseJson {
+    pub fn ok(correlation_id: &Option<Value>, record_id: &Uuid) -> Self {
+        Self {
+            correlation_id: correlation_id.clone(),
+            record_id: Some(*record_id),
+            error: None,
+            records: None,
+        }
+    }
+
+    pub fn error(correlation_id: &Option<Value>, error: &str) -> Self {
+        Self {
+            correlation_id: correlation_id.clone(),
+            record_id: None,
+            error: Some(error.to_owned()),
+            records: None,
+        }
+    }
+
+    // One entry per record of a batch, in the order they were published
+    pub fn batch(correlation_id: &Option<Value>, results: &[Result<Uuid>]) -> Self {
+        Self {
+            correlation_id: correlation_id.clone(),
+            record_id: None,
+            error: None,
+            records: Some(
+                results
+                    .iter()
+                    .map(|result| match result {
+                        Ok(record_id) => ResponseRecordJson {
+                            record_id: Some(*record_id),
+                            error: None,
+                        },
+                        Err(err) => ResponseRecordJson {
+                            record_id: None,
+                            error: Some(err.to_string()),
+                        },
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ResponseRecordJson {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    record_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
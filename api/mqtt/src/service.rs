@@ -2,7 +2,13 @@ use std::sync::Arc;
 
 use tokio::{sync::mpsc, task::JoinHandle};
 
-use crate::{context::ApiMqttCtx, model::payload::Payload};
+use crate::{
+    context::ApiMqttCtx,
+    model::{
+        payload::Payload,
+        response::{Response, ResponseJson},
+    },
+};
 
 use self::record::record_service;
 
@@ -11,21 +17,58 @@ pub mod record;
 pub struct Service {
     context: Arc<ApiMqttCtx>,
     rx: mpsc::UnboundedReceiver<Payload>,
+    response_tx: mpsc::Sender<Response>,
 }
 
 impl Service {
-    pub fn new(context: Arc<ApiMqttCtx>) -> (Self, mpsc::UnboundedSender<Payload>) {
+    pub fn new(
+        context: Arc<ApiMqttCtx>,
+        response_tx: mpsc::Sender<Response>,
+    ) -> (Self, mpsc::UnboundedSender<Payload>) {
         let (tx, rx) = mpsc::unbounded_channel();
-        (Self { context, rx }, tx)
+        (
+            Self {
+                context,
+                rx,
+                response_tx,
+            },
+            tx,
+        )
     }
 
     pub fn run(mut self) -> JoinHandle<()> {
         tokio::spawn((|| async move {
-            loop {
-                if let Some(payload) = self.rx.recv().await {
-                    record_service(&self.context, &payload).await;
+            while let Some(payload) = self.rx.recv().await {
+                let result = record_service(&self.context, &payload).await;
+                if let Some(response_topic) = payload.response_topic() {
+                    let json = match result {
+                        Ok(results) if *payload.is_batch() => {
+                            ResponseJson::batch(payload.correlation_id(), &results)
+                        }
+                        Ok(mut results) => match results.pop() {
+                            Some(Ok(record_id)) => {
+                                ResponseJson::ok(payload.correlation_id(), &record_id)
+                            }
+                            Some(Err(err)) => {
+                                ResponseJson::error(payload.correlation_id(), &err.to_string())
+                            }
+                            None => continue,
+                        },
+                        Err(err) => ResponseJson::error(payload.correlation_id(), &err.to_string()),
+                    };
+                    send_response(&self.response_tx, Response::new(response_topic, json));
                 }
             }
         })())
     }
 }
+
+// Responses are dropped rather than waited on when the client can't publish them fast enough
+pub fn send_response(response_tx: &mpsc::Sender<Response>, response: Response) {
+    if let Err(err) = response_tx.try_send(response) {
+        hb_log::error(
+            None,
+            &format!("[ApiMqttClient] Failed to queue response: {err}"),
+        );
+    }
+}
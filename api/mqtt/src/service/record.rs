@@ -1,16 +1,19 @@
 use std::sync::Arc;
 
-use ahash::{HashMap, HashMapExt, HashSet};
+use ahash::{HashMap, HashSet};
 use anyhow::{Error, Result};
 use hb_api_websocket::message::{MessageKind as WebSocketMessageKind, Target as WebSocketTarget};
 use hb_dao::{
+    admin_project_member::AdminProjectMemberDao,
     collection::CollectionDao,
+    collection_rule::CollectionRuleDao,
     log::{LogDao, LogKind},
     project::ProjectDao,
     record::RecordDao,
     token::TokenDao,
-    value::ColumnValue,
+    value::{ColumnKind, ColumnValue},
 };
+use serde_json::Value;
 use uuid::Uuid;
 
 use crate::{
@@ -19,15 +22,36 @@ use crate::{
     util::ws_broadcast::websocket_broadcast,
 };
 
-pub async fn record_service(ctx: &Arc<ApiMqttCtx>, payload: &Payload) {
-    let result = match insert_one(ctx.clone(), payload).await {
-        Ok(_) => {
+// The outer error rejects the whole payload, while the inner ones are the outcome of each of its
+// records
+pub async fn record_service(ctx: &Arc<ApiMqttCtx>, payload: &Payload) -> Result<Vec<Result<Uuid>>> {
+    let outcome = insert_many(ctx.clone(), payload).await;
+    let result = match &outcome {
+        Ok(results) => {
+            let failures = results
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, result)| {
+                    result
+                        .as_ref()
+                        .err()
+                        .map(|err| format!("record {idx}: {err}"))
+                })
+                .collect::<Vec<_>>();
             let msg = format!(
-                "Successfully insert one payload to collection id {}",
+                "Successfully insert {} of {} payload records to collection id {}",
+                results.len() - failures.len(),
+                results.len(),
                 payload.collection_id()
             );
-            hb_log::info(None, &format!("[ApiMqttClient] {msg}"));
-            (LogKind::Info, msg)
+            if failures.is_empty() {
+                hb_log::info(None, &format!("[ApiMqttClient] {msg}"));
+                (LogKind::Info, msg)
+            } else {
+                let msg = format!("{msg}, failed {}", failures.join("; "));
+                hb_log::warn(None, &format!("[ApiMqttClient] {msg}"));
+                (LogKind::Warn, msg)
+            }
         }
         Err(err) => {
             hb_log::error(
@@ -50,7 +74,7 @@ pub async fn record_service(ctx: &Arc<ApiMqttCtx>, payload: &Payload) {
                 None,
                 &format!("[ApiMqttClient] Failed to get token data: {err}"),
             );
-            return;
+            return outcome;
         }
     };
     match log_data.db_insert(ctx.dao().db()).await {
@@ -73,17 +97,27 @@ pub async fn record_service(ctx: &Arc<ApiMqttCtx>, payload: &Payload) {
             &format!("[ApiMqttClient] Error when inserting log data: {err}"),
         ),
     }
+
+    outcome
 }
 
-async fn insert_one(ctx: Arc<ApiMqttCtx>, payload: &Payload) -> Result<()> {
-    let token_data = match TokenDao::db_select(ctx.dao().db(), payload.token_id()).await {
+async fn insert_many(ctx: Arc<ApiMqttCtx>, payload: &Payload) -> Result<Vec<Result<Uuid>>> {
+    if *ctx.read_only() {
+        return Err(Error::msg("This server is read-only"));
+    }
+
+    let token_data = match TokenDao::db_select_unexpired(ctx.dao().db(), payload.token_id()).await {
         Ok(data) => data,
         Err(err) => return Err(Error::msg(format!("Failed to get token data: {err}"))),
     };
+    ctx.dao().token_usage().record(token_data.id());
 
-    if !token_data
-        .is_allow_insert_record(ctx.dao().db(), payload.collection_id())
-        .await
+    let collection_rule_data = token_data
+        .collection_rule(ctx.dao().db(), payload.collection_id())
+        .await;
+    if !collection_rule_data
+        .as_ref()
+        .is_some_and(|collection_rule_data| *collection_rule_data.insert_one())
     {
         return Err(Error::msg(format!(
             "Token id '{}' doesn't have permission to write data to collection id {}",
@@ -97,7 +131,16 @@ async fn insert_one(ctx: Arc<ApiMqttCtx>, payload: &Payload) -> Result<()> {
         CollectionDao::db_select(ctx.dao().db(), payload.collection_id())
     )?;
 
-    if token_data.admin_id() != project_data.admin_id() {
+    // Tokens of admins the project is shared with keep working while they're still members
+    if token_data.admin_id() != project_data.admin_id()
+        && AdminProjectMemberDao::db_select_by_project_id_and_admin_id(
+            ctx.dao().db(),
+            project_data.id(),
+            token_data.admin_id(),
+        )
+        .await
+        .is_err()
+    {
         return Err(Error::msg(format!(
             "This project id '{}' doesn't belong to you (token id '{}'",
             payload.project_id(),
@@ -112,15 +155,6 @@ async fn insert_one(ctx: Arc<ApiMqttCtx>, payload: &Payload) -> Result<()> {
         )));
     }
 
-    for field_name in payload.data().keys() {
-        if !collection_data.schema_fields().contains_key(field_name) {
-            return Err(Error::msg(format!(
-                "Field '{field_name}' is not exist in the collection ({})",
-                payload.collection_id()
-            )));
-        }
-    }
-
     let created_by = if let Some(user_claim) = payload.user() {
         let collection_data =
             match CollectionDao::db_select(ctx.dao().db(), user_claim.collection_id()).await {
@@ -161,13 +195,100 @@ async fn insert_one(ctx: Arc<ApiMqttCtx>, payload: &Payload) -> Result<()> {
         )));
     };
 
-    let mut record_data = RecordDao::new(&created_by, collection_data.id(), &payload.data().len());
+    let mut results = Vec::with_capacity(payload.data().len());
+    let mut indexes = Vec::with_capacity(payload.data().len());
+    let mut records = Vec::with_capacity(payload.data().len());
+    for data in payload.data() {
+        match build_record(
+            &ctx,
+            &collection_data,
+            &collection_rule_data,
+            &created_by,
+            payload.token_id(),
+            data,
+        )
+        .await
+        {
+            Ok(record_data) => {
+                indexes.push(results.len());
+                records.push(record_data);
+                results.push(Err(Error::msg("Record wasn't inserted")));
+            }
+            Err(err) => results.push(Err(err)),
+        }
+    }
+
+    // Every record has a value for each field of the schema, so the first one tells how many fit
+    // in a single insert
+    let batch_size = match records.first() {
+        Some(record_data) => RecordDao::insert_many_limit(&record_data.len()),
+        None => 1,
+    };
+    let collection_id = *collection_data.id();
+    let collection_data = Some(collection_data);
+    for (batch, batch_indexes) in records.chunks(batch_size).zip(indexes.chunks(batch_size)) {
+        let batch_result = RecordDao::db_insert_many(ctx.dao().db(), batch, &collection_data).await;
+        for (idx, record_data) in batch_indexes.iter().zip(batch) {
+            results[*idx] = match &batch_result {
+                Ok(_) => inserted_record(&ctx, &collection_id, record_data),
+                // Retry the failed batch one record at a time to find out which ones are at fault
+                Err(err) if batch.len() == 1 => Err(Error::msg(err.to_string())),
+                Err(_) => match record_data
+                    .db_insert(ctx.dao().db(), &collection_data)
+                    .await
+                {
+                    Ok(_) => inserted_record(&ctx, &collection_id, record_data),
+                    Err(err) => Err(err),
+                },
+            };
+        }
+    }
+
+    Ok(results)
+}
+
+async fn build_record(
+    ctx: &ApiMqttCtx,
+    collection_data: &CollectionDao,
+    collection_rule_data: &Option<CollectionRuleDao>,
+    created_by: &Uuid,
+    token_id: &Uuid,
+    data: &HashMap<String, Value>,
+) -> Result<RecordDao> {
+    for field_name in data.keys() {
+        if !collection_data.schema_fields().contains_key(field_name) {
+            return Err(Error::msg(format!(
+                "Field '{field_name}' is not exist in the collection ({})",
+                collection_data.id()
+            )));
+        }
+        if let Some(collection_rule_data) = collection_rule_data {
+            if !collection_rule_data.is_writable_field(field_name) {
+                return Err(Error::msg(format!(
+                    "Token id '{token_id}' doesn't have permission to write field '{field_name}'"
+                )));
+            }
+        }
+    }
+
+    let mut record_data = RecordDao::new(created_by, collection_data.id(), &data.len());
     for (field_name, field_props) in collection_data.schema_fields() {
-        if let Some(value) = payload.data().get(field_name) {
+        if *field_props.kind() == ColumnKind::Counter {
+            if let Some(value) = data.get(field_name) {
+                if !value.is_null() {
+                    return Err(Error::msg(format!(
+                        "Field '{field_name}' is a counter and can only be changed using the counters endpoint"
+                    )));
+                }
+            }
+            record_data.upsert(field_name, &ColumnValue::none(field_props.kind()));
+            continue;
+        }
+        if let Some(value) = data.get(field_name) {
             if !value.is_null() {
                 record_data.upsert(
                     field_name,
-                    &match ColumnValue::from_serde_json(field_props.kind(), value) {
+                    &match field_props.value_from_serde_json(value) {
                         Ok(value) => value,
                         Err(err) => {
                             return Err(Error::msg(
@@ -179,53 +300,60 @@ async fn insert_one(ctx: Arc<ApiMqttCtx>, payload: &Payload) -> Result<()> {
                 continue;
             }
         }
-        if *field_props.required() {
+        if let Some(default) = field_props.default() {
+            record_data.upsert(field_name, default);
+        } else if *field_props.required() {
             return Err(Error::msg(format!("Value for '{field_name}' is required")));
         } else {
             record_data.upsert(field_name, &ColumnValue::none(field_props.kind()));
         }
     }
 
-    let collection_id = collection_data.id().to_owned();
-
     record_data
-        .db_insert(ctx.dao().db(), &Some(collection_data))
+        .db_check_references(ctx.dao().db(), collection_data)
         .await?;
 
-    let ws_broadcaster_chan = ctx.websocket().broadcaster().clone();
-    tokio::spawn((|| async move {
-        let mut record = HashMap::with_capacity(record_data.len());
-        for (key, value) in record_data.data() {
-            let value = match value.to_serde_json() {
-                Ok(value) => value,
-                Err(err) => {
-                    hb_log::error(
-                        None,
-                        &format!("[ApiMqttClient] Error when serializing record: {err}"),
-                    );
-                    return;
-                }
-            };
-            record.insert(key.to_owned(), value);
-        }
+    Ok(record_data)
+}
 
-        let created_by = Uuid::parse_str(record["_created_by"].as_str().unwrap()).unwrap();
+// Broadcasts a record once it's written, giving back its id
+fn inserted_record(
+    ctx: &ApiMqttCtx,
+    collection_id: &Uuid,
+    record_data: &RecordDao,
+) -> Result<Uuid> {
+    let record_id = match record_data.id() {
+        Some(id) => *id,
+        None => return Err(Error::msg("Record doesn't have an id")),
+    };
 
-        if let Err(err) = websocket_broadcast(
-            &ws_broadcaster_chan,
-            WebSocketTarget::Collection(collection_id),
-            Some(created_by),
-            WebSocketMessageKind::InsertOne,
-            record,
-        ) {
+    let record = match record_data.to_serde_json() {
+        Ok(data) => data,
+        Err(err) => {
             hb_log::error(
                 None,
-                &format!(
-                    "[ApiMqttClient] Error when broadcasting insert_one record to websocket: {err}"
-                ),
+                &format!("[ApiMqttClient] Error when serializing record: {err}"),
             );
+            return Ok(record_id);
         }
-    })());
+    };
+
+    let created_by = Uuid::parse_str(record["_created_by"].as_str().unwrap()).unwrap();
+
+    if let Err(err) = websocket_broadcast(
+        ctx.websocket().broadcaster(),
+        WebSocketTarget::Collection(*collection_id),
+        Some(created_by),
+        WebSocketMessageKind::InsertOne,
+        record,
+    ) {
+        hb_log::error(
+            None,
+            &format!(
+                "[ApiMqttClient] Error when broadcasting insert_one record to websocket: {err}"
+            ),
+        );
+    }
 
-    Ok(())
+    Ok(record_id)
 }
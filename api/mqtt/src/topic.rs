@@ -0,0 +1,117 @@
+use anyhow::{Error, Result};
+use uuid::Uuid;
+
+const PROJECT_ID: &str = "{project_id}";
+const COLLECTION_ID: &str = "{collection_id}";
+
+// Topic the client subscribes to. Levels written as {project_id} or {collection_id} are
+// subscribed to with a wildcard, and the ids are read from the topic each payload is published
+// to, so devices of different projects can be kept apart by the broker.
+#[derive(Clone)]
+pub struct MqttTopic {
+    template: String,
+    levels: Vec<TopicLevel>,
+    subscription: String,
+}
+
+#[derive(Clone, PartialEq)]
+enum TopicLevel {
+    Literal(String),
+    ProjectId,
+    CollectionId,
+}
+
+impl MqttTopic {
+    // A shared group makes the broker hand every payload to only one of the nodes subscribed in
+    // the group, so more nodes can be added to split the load
+    pub fn new(template: &str, shared_group: &Option<String>) -> Result<Self> {
+        let mut levels = Vec::new();
+        for level_str in template.split('/') {
+            let level = match level_str {
+                PROJECT_ID => TopicLevel::ProjectId,
+                COLLECTION_ID => TopicLevel::CollectionId,
+                _ if level_str.contains('{') || level_str.contains('}') => {
+                    return Err(Error::msg(format!(
+                        "Topic level '{level_str}' must be either '{PROJECT_ID}' or '{COLLECTION_ID}' as a whole"
+                    )))
+                }
+                _ => TopicLevel::Literal(level_str.to_owned()),
+            };
+            if !matches!(level, TopicLevel::Literal(_)) && levels.contains(&level) {
+                return Err(Error::msg(format!(
+                    "Topic level '{level_str}' is used more than once"
+                )));
+            }
+            levels.push(level);
+        }
+
+        let filter = levels
+            .iter()
+            .map(|level| match level {
+                TopicLevel::Literal(level) => level.as_str(),
+                TopicLevel::ProjectId | TopicLevel::CollectionId => "+",
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        let subscription = match shared_group {
+            Some(shared_group) => format!("$share/{shared_group}/{filter}"),
+            None => filter,
+        };
+
+        Ok(Self {
+            template: template.to_owned(),
+            levels,
+            subscription,
+        })
+    }
+
+    pub fn subscription(&self) -> &str {
+        &self.subscription
+    }
+
+    // Project and collection ids in the topic a payload was published to, none of them if the
+    // template has no placeholder for it
+    pub(crate) fn ids(&self, topic: &str) -> Result<(Option<Uuid>, Option<Uuid>)> {
+        let topic_levels = topic.split('/').collect::<Vec<_>>();
+        let mut project_id = None;
+        let mut collection_id = None;
+
+        let mut multi_level = false;
+        for (idx, level) in self.levels.iter().enumerate() {
+            if *level == TopicLevel::Literal("#".to_owned()) {
+                multi_level = true;
+                break;
+            }
+            let topic_level = match topic_levels.get(idx) {
+                Some(topic_level) => *topic_level,
+                None => return Err(self.mismatch()),
+            };
+            match level {
+                TopicLevel::Literal(level) => {
+                    if level != "+" && level != topic_level {
+                        return Err(self.mismatch());
+                    }
+                }
+                TopicLevel::ProjectId => {
+                    project_id = Some(Uuid::parse_str(topic_level).map_err(|err| {
+                        Error::msg(format!("Invalid project id '{topic_level}': {err}"))
+                    })?)
+                }
+                TopicLevel::CollectionId => {
+                    collection_id = Some(Uuid::parse_str(topic_level).map_err(|err| {
+                        Error::msg(format!("Invalid collection id '{topic_level}': {err}"))
+                    })?)
+                }
+            }
+        }
+        if !multi_level && topic_levels.len() != self.levels.len() {
+            return Err(self.mismatch());
+        }
+
+        Ok((project_id, collection_id))
+    }
+
+    fn mismatch(&self) -> Error {
+        Error::msg(format!("Topic doesn't match '{}'", self.template))
+    }
+}
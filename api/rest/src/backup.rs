@@ -0,0 +1,263 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Error, Result};
+use chrono::{Datelike, Timelike, Utc};
+use hb_dao::{project::ProjectDao, Db};
+use hb_mailer::MailPayload;
+use tokio::{
+    fs,
+    io::{AsyncWriteExt, BufWriter},
+    sync::{mpsc, Mutex},
+    task::JoinHandle,
+    time::{self, Instant, MissedTickBehavior},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::util::project_export::{ProjectExport, ARCHIVE_CHANNEL_CAPACITY};
+
+const RUN_PREFIX: &str = "backup-";
+const PARTIAL_SUFFIX: &str = ".partial";
+
+// Writes the archive of every project on a schedule, the same one the project export returns.
+// Every run gets a directory of its own named after the time it started, and only the latest
+// `retention` runs are kept. A run is written under a temporary name and renamed once it's
+// complete, so a failed one never takes the place of a good one.
+pub struct Backup {
+    db: Arc<Db>,
+    path: PathBuf,
+    interval: Duration,
+    retention: usize,
+    mailer_sender: Option<mpsc::Sender<MailPayload>>,
+    notify_email: Option<String>,
+    // Held for the whole of a run, so a scheduled run and one started through the API don't
+    // overlap
+    running: Mutex<()>,
+}
+
+impl Backup {
+    pub fn new(
+        db: Arc<Db>,
+        path: &str,
+        interval: &Duration,
+        retention: &usize,
+        mailer_sender: Option<mpsc::Sender<MailPayload>>,
+        notify_email: &Option<String>,
+    ) -> Self {
+        hb_log::info(Some("⚡"), "[Backup] Initializing component");
+
+        Self {
+            db,
+            path: PathBuf::from(path),
+            interval: *interval,
+            retention: (*retention).max(1),
+            mailer_sender,
+            notify_email: notify_email.to_owned(),
+            running: Mutex::new(()),
+        }
+    }
+
+    pub fn run_none(cancel_token: CancellationToken) -> JoinHandle<Result<()>> {
+        hb_log::info(Some("⏩"), "[Backup] Skipping component");
+
+        tokio::spawn((|| async move {
+            cancel_token.cancelled().await;
+            Ok(())
+        })())
+    }
+
+    pub fn run(self: Arc<Self>, cancel_token: CancellationToken) -> JoinHandle<Result<()>> {
+        hb_log::info(Some("💫"), "[Backup] Running component");
+
+        tokio::spawn((|| async move {
+            // The first run comes a whole interval after startup rather than with it
+            let mut interval = time::interval_at(Instant::now() + self.interval, self.interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        break;
+                    }
+                    _ = tokio::signal::ctrl_c() => {
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        let _ = self.backup().await;
+                    }
+                }
+            }
+
+            hb_log::info(None, "[Backup] Shutting down component");
+
+            Ok(())
+        })())
+    }
+
+    // Runs a backup right away, waiting for the one in progress if there is one. The outcome is
+    // logged either way, and a failure is emailed when there's an address to send it to.
+    pub async fn backup(&self) -> Result<BackupRun> {
+        let _running = self.running.lock().await;
+
+        hb_log::info(Some("💾"), "[Backup] Starting a backup run");
+
+        let run = match self.write_run().await {
+            Ok(run) => run,
+            Err(err) => {
+                hb_log::error(None, &format!("[Backup] Backup run failed: {err}"));
+                self.notify_failure(&err).await;
+                return Err(err);
+            }
+        };
+
+        hb_log::info(
+            Some("💾"),
+            &format!(
+                "[Backup] Backed up {} project(s) to {}",
+                run.project_count(),
+                run.path().display()
+            ),
+        );
+
+        if let Err(err) = self.prune().await {
+            hb_log::warn(
+                None,
+                &format!("[Backup] Failed to remove old backup runs: {err}"),
+            );
+        }
+
+        Ok(run)
+    }
+
+    async fn write_run(&self) -> Result<BackupRun> {
+        let now = Utc::now();
+        let name = format!(
+            "{RUN_PREFIX}{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+            now.year(),
+            now.month(),
+            now.day(),
+            now.hour(),
+            now.minute(),
+            now.second()
+        );
+        let path = self.path.join(&name);
+        let partial_path = self.path.join(format!("{name}{PARTIAL_SUFFIX}"));
+
+        fs::create_dir_all(&partial_path).await?;
+        let project_count = match self.write_projects(&partial_path).await {
+            Ok(project_count) => project_count,
+            Err(err) => {
+                let _ = fs::remove_dir_all(&partial_path).await;
+                return Err(err);
+            }
+        };
+        fs::rename(&partial_path, &path).await?;
+
+        Ok(BackupRun {
+            name,
+            path,
+            project_count,
+        })
+    }
+
+    async fn write_projects(&self, run_path: &Path) -> Result<usize> {
+        let projects_data = ProjectDao::db_select_many(&self.db).await?;
+        for project_data in &projects_data {
+            let archive_path = run_path.join(format!("project-{}.tar", project_data.id()));
+            if let Err(err) = self.write_project(project_data, &archive_path).await {
+                return Err(Error::msg(format!(
+                    "Failed to back up project id '{}': {err}",
+                    project_data.id()
+                )));
+            }
+        }
+        Ok(projects_data.len())
+    }
+
+    async fn write_project(&self, project_data: &ProjectDao, archive_path: &Path) -> Result<()> {
+        let project_export = ProjectExport::new(&self.db, project_data).await?;
+        let mut file = BufWriter::new(fs::File::create(archive_path).await?);
+
+        // The receiver is dropped as soon as saving fails, which stops the export with it
+        let (tx, mut rx) = mpsc::channel::<Result<Vec<u8>>>(ARCHIVE_CHANNEL_CAPACITY);
+        let save = async move {
+            while let Some(chunk) = rx.recv().await {
+                file.write_all(&chunk?).await?;
+            }
+            file.flush().await?;
+            Ok::<_, Error>(())
+        };
+        let (_, saved) = tokio::join!(project_export.write(&self.db, tx), save);
+        saved
+    }
+
+    // Runs past the retention are removed oldest first, along with any run a crash left
+    // unfinished. Their names sort in the order they were started.
+    async fn prune(&self) -> Result<()> {
+        let mut runs = Vec::new();
+        let mut entries = fs::read_dir(&self.path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            if !name.starts_with(RUN_PREFIX) || !entry.file_type().await?.is_dir() {
+                continue;
+            }
+            if name.ends_with(PARTIAL_SUFFIX) {
+                fs::remove_dir_all(entry.path()).await?;
+            } else {
+                runs.push(name);
+            }
+        }
+
+        runs.sort_unstable();
+        for name in &runs[..runs.len().saturating_sub(self.retention)] {
+            fs::remove_dir_all(self.path.join(name)).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn notify_failure(&self, err: &Error) {
+        if let (Some(mailer_sender), Some(notify_email)) = (&self.mailer_sender, &self.notify_email)
+        {
+            if let Err(err) = mailer_sender
+                .send(MailPayload::new(
+                    notify_email,
+                    "Backup Failed",
+                    &format!("The backup run failed and no new backup was written: {err}"),
+                ))
+                .await
+            {
+                hb_log::error(
+                    None,
+                    &format!("[Backup] Error when sending the failure email: {err}"),
+                );
+            }
+        }
+    }
+}
+
+pub struct BackupRun {
+    name: String,
+    path: PathBuf,
+    project_count: usize,
+}
+
+impl BackupRun {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn project_count(&self) -> &usize {
+        &self.project_count
+    }
+}
@@ -1,11 +1,19 @@
-use std::sync::Arc;
+use std::sync::{atomic::AtomicBool, Arc};
 
 use hb_api_websocket::handler::WebSocketHandler;
-use hb_dao::Db;
+use hb_dao::{token_usage::TokenUsage, warmup::Warmup, Db};
 use hb_hash_argon2::argon2::Argon2Hash;
 use hb_mailer::MailPayload;
 use hb_token_jwt::token::JwtToken;
-use tokio::sync::mpsc;
+use hb_token_totp::token::TotpToken;
+use tokio::sync::{mpsc, oneshot, watch};
+use uuid::Uuid;
+
+use crate::backup::Backup;
+
+// Asks for the config to be read again. The reply holds either the changed keys that only take
+// effect after a restart, or the problems found in the new config.
+pub type ConfigReloadReply = oneshot::Sender<Result<Vec<String>, Vec<String>>>;
 
 pub struct ApiRestCtx {
     hash: ApiRestHashCtx,
@@ -18,7 +26,19 @@ pub struct ApiRestCtx {
     access_token_length: usize,
     registration_ttl: u32,
     reset_password_ttl: u32,
+    sign_in_max_attempts: u32,
+    sign_in_lockout_duration: u32,
+    password_policy: PasswordPolicy,
     bucket_path: String,
+    warmup: Option<Arc<Warmup>>,
+    import_batch_size: usize,
+    presign_max_expiry: u32,
+    image_resize_max_dimension: u32,
+    read_only: bool,
+    mqtt_connected: Option<Arc<AtomicBool>>,
+    backup: Option<Arc<Backup>>,
+    config_reload_sender: mpsc::Sender<ConfigReloadReply>,
+    operator_admin_ids: Vec<Uuid>,
 }
 
 impl ApiRestCtx {
@@ -33,7 +53,19 @@ impl ApiRestCtx {
         access_token_length: usize,
         registration_ttl: u32,
         reset_password_ttl: u32,
+        sign_in_max_attempts: u32,
+        sign_in_lockout_duration: u32,
+        password_policy: PasswordPolicy,
         bucket_path: String,
+        warmup: Option<Arc<Warmup>>,
+        import_batch_size: usize,
+        presign_max_expiry: u32,
+        image_resize_max_dimension: u32,
+        read_only: bool,
+        mqtt_connected: Option<Arc<AtomicBool>>,
+        backup: Option<Arc<Backup>>,
+        config_reload_sender: mpsc::Sender<ConfigReloadReply>,
+        operator_admin_ids: Vec<Uuid>,
     ) -> Self {
         Self {
             hash,
@@ -46,7 +78,19 @@ impl ApiRestCtx {
             access_token_length,
             registration_ttl,
             reset_password_ttl,
+            sign_in_max_attempts,
+            sign_in_lockout_duration,
+            password_policy,
             bucket_path,
+            warmup,
+            import_batch_size,
+            presign_max_expiry,
+            image_resize_max_dimension,
+            read_only,
+            mqtt_connected,
+            backup,
+            config_reload_sender,
+            operator_admin_ids,
         }
     }
 
@@ -90,9 +134,57 @@ impl ApiRestCtx {
         &self.reset_password_ttl
     }
 
+    pub fn sign_in_max_attempts(&self) -> &u32 {
+        &self.sign_in_max_attempts
+    }
+
+    pub fn sign_in_lockout_duration(&self) -> &u32 {
+        &self.sign_in_lockout_duration
+    }
+
+    pub fn password_policy(&self) -> &PasswordPolicy {
+        &self.password_policy
+    }
+
     pub fn bucket_path(&self) -> &String {
         &self.bucket_path
     }
+
+    pub fn warmup(&self) -> &Option<Arc<Warmup>> {
+        &self.warmup
+    }
+
+    pub fn import_batch_size(&self) -> &usize {
+        &self.import_batch_size
+    }
+
+    pub fn presign_max_expiry(&self) -> &u32 {
+        &self.presign_max_expiry
+    }
+
+    pub fn image_resize_max_dimension(&self) -> &u32 {
+        &self.image_resize_max_dimension
+    }
+
+    pub fn read_only(&self) -> &bool {
+        &self.read_only
+    }
+
+    pub fn mqtt_connected(&self) -> &Option<Arc<AtomicBool>> {
+        &self.mqtt_connected
+    }
+
+    pub fn backup(&self) -> &Option<Arc<Backup>> {
+        &self.backup
+    }
+
+    pub fn config_reload_sender(&self) -> &mpsc::Sender<ConfigReloadReply> {
+        &self.config_reload_sender
+    }
+
+    pub fn operator_admin_ids(&self) -> &Vec<Uuid> {
+        &self.operator_admin_ids
+    }
 }
 
 pub struct ApiRestHashCtx {
@@ -110,17 +202,22 @@ impl ApiRestHashCtx {
 }
 
 pub struct ApiRestTokenCtx {
-    jwt: JwtToken,
+    jwt: Arc<JwtToken>,
+    totp: Option<TotpToken>,
 }
 
 impl ApiRestTokenCtx {
-    pub fn new(jwt: JwtToken) -> Self {
-        Self { jwt }
+    pub fn new(jwt: Arc<JwtToken>, totp: Option<TotpToken>) -> Self {
+        Self { jwt, totp }
     }
 
     pub fn jwt(&self) -> &JwtToken {
         &self.jwt
     }
+
+    pub fn totp(&self) -> &Option<TotpToken> {
+        &self.totp
+    }
 }
 
 pub struct ApiRestMailerCtx {
@@ -139,30 +236,53 @@ impl ApiRestMailerCtx {
 
 pub struct ApiRestDaoCtx {
     db: Arc<Db>,
+    token_usage: Arc<TokenUsage>,
 }
 
 impl ApiRestDaoCtx {
-    pub fn new(db: Arc<Db>) -> Self {
-        Self { db }
+    pub fn new(db: Arc<Db>, token_usage: Arc<TokenUsage>) -> Self {
+        Self { db, token_usage }
     }
 
     pub fn db(&self) -> &Db {
         &self.db
     }
+
+    pub fn token_usage(&self) -> &TokenUsage {
+        &self.token_usage
+    }
 }
 
 pub struct ApiRestWsCtx {
     handler: WebSocketHandler,
+    check_origin: bool,
+    allowed_origin: watch::Receiver<Option<String>>,
 }
 
 impl ApiRestWsCtx {
-    pub fn new(handler: WebSocketHandler) -> Self {
-        Self { handler }
+    pub fn new(
+        handler: WebSocketHandler,
+        check_origin: bool,
+        allowed_origin: watch::Receiver<Option<String>>,
+    ) -> Self {
+        Self {
+            handler,
+            check_origin,
+            allowed_origin,
+        }
     }
 
     pub fn handler(&self) -> &WebSocketHandler {
         &self.handler
     }
+
+    pub fn check_origin(&self) -> &bool {
+        &self.check_origin
+    }
+
+    pub fn allowed_origin(&self) -> Option<String> {
+        self.allowed_origin.borrow().clone()
+    }
 }
 
 pub struct MqttAdminCredential {
@@ -192,3 +312,49 @@ impl MqttAdminCredential {
         &self.topic
     }
 }
+
+pub struct PasswordPolicy {
+    min_length: usize,
+    require_lowercase: bool,
+    require_uppercase: bool,
+    require_digit: bool,
+    require_symbol: bool,
+}
+
+impl PasswordPolicy {
+    pub fn new(
+        min_length: &usize,
+        require_lowercase: &bool,
+        require_uppercase: &bool,
+        require_digit: &bool,
+        require_symbol: &bool,
+    ) -> Self {
+        Self {
+            min_length: *min_length,
+            require_lowercase: *require_lowercase,
+            require_uppercase: *require_uppercase,
+            require_digit: *require_digit,
+            require_symbol: *require_symbol,
+        }
+    }
+
+    pub fn min_length(&self) -> &usize {
+        &self.min_length
+    }
+
+    pub fn require_lowercase(&self) -> &bool {
+        &self.require_lowercase
+    }
+
+    pub fn require_uppercase(&self) -> &bool {
+        &self.require_uppercase
+    }
+
+    pub fn require_digit(&self) -> &bool {
+        &self.require_digit
+    }
+
+    pub fn require_symbol(&self) -> &bool {
+        &self.require_symbol
+    }
+}
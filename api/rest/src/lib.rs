@@ -2,17 +2,29 @@ use std::net::SocketAddr;
 
 use actix_cors::Cors;
 use actix_web::{
+    dev::Service,
+    http::StatusCode,
     middleware::{ErrorHandlers, Logger},
     web, App, HttpServer,
 };
+use anyhow::{Error, Result};
 use configure::configure;
 use context::ApiRestCtx;
 use error_handler::default_error_handler;
-use hb_config::app::AppConfigMode;
+use futures::future::{ready, Either};
+use hb_config::{api::ApiRestTlsConfig, app::AppConfigMode};
 use logger::logger_format;
-use tokio::task::JoinHandle;
+use model::Response;
+use tokio::{sync::watch, task::JoinHandle};
 use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+use util::{
+    client_ip::{client_ip, TrustedProxies},
+    read_only::is_write_request,
+    tls::Tls,
+};
 
+pub mod backup;
 mod configure;
 pub mod context;
 mod error_handler;
@@ -24,7 +36,9 @@ mod util;
 pub struct ApiRestServer {
     app_mode: AppConfigMode,
     address: SocketAddr,
-    allowed_origin: Option<String>,
+    allowed_origin: watch::Receiver<Option<String>>,
+    trusted_proxies: web::Data<TrustedProxies>,
+    tls: Option<Tls>,
     context: web::Data<ApiRestCtx>,
 }
 
@@ -33,62 +47,155 @@ impl ApiRestServer {
         app_mode: &AppConfigMode,
         host: &str,
         port: &u16,
-        allowed_origin: &Option<String>,
+        allowed_origin: watch::Receiver<Option<String>>,
+        trusted_proxies: &Vec<String>,
+        tls: &Option<ApiRestTlsConfig>,
         ctx: ApiRestCtx,
     ) -> Self {
         hb_log::info(Some("⚡"), "[ApiRestServer] Initializing component");
 
         let address = format!("{host}:{port}").parse().unwrap();
+        let trusted_proxies = match TrustedProxies::new(trusted_proxies) {
+            Ok(trusted_proxies) => web::Data::new(trusted_proxies),
+            Err(err) => {
+                hb_log::panic(None, &format!("[ApiRestServer] {err}"));
+                unreachable!()
+            }
+        };
+        let tls = tls.as_ref().map(|tls| match Tls::new(tls) {
+            Ok(tls) => tls,
+            Err(err) => {
+                hb_log::panic(None, &format!("[ApiRestServer] {err}"));
+                unreachable!()
+            }
+        });
         let context = web::Data::new(ctx);
 
         Self {
             app_mode: *app_mode,
             address,
-            allowed_origin: allowed_origin.to_owned(),
+            allowed_origin,
+            trusted_proxies,
+            tls,
             context,
         }
     }
 
-    pub fn run(self, cancel_token: CancellationToken) -> JoinHandle<()> {
+    pub fn run(self, cancel_token: CancellationToken) -> JoinHandle<Result<()>> {
         hb_log::info(Some("💫"), "[ApiRestServer] Running component");
 
         tokio::spawn((|| async move {
             let server = HttpServer::new(move || {
+                let read_only = *self.context.read_only();
                 App::new()
+                    .wrap_fn(move |req, srv| {
+                        if read_only && is_write_request(&req) {
+                            Either::Left(ready(Ok(req.into_response(Response::error_raw(
+                                &StatusCode::FORBIDDEN,
+                                "This server is read-only",
+                            )))))
+                        } else {
+                            Either::Right(srv.call(req))
+                        }
+                    })
                     .wrap((|| -> Cors {
                         if matches!(self.app_mode, AppConfigMode::Production) {
-                            let cors = Cors::default().allow_any_header().allow_any_method();
-                            if let Some(origin) = &self.allowed_origin {
-                                cors.allowed_origin(origin)
-                            } else {
-                                cors
-                            }
+                            // Checked on every request, since the origin can change when the
+                            // config is reloaded
+                            let allowed_origin = self.allowed_origin.clone();
+                            Cors::default()
+                                .allow_any_header()
+                                .allow_any_method()
+                                .allowed_origin_fn(move |origin, _| {
+                                    match &*allowed_origin.borrow() {
+                                        Some(allowed_origin) => {
+                                            origin.as_bytes() == allowed_origin.as_bytes()
+                                        }
+                                        None => false,
+                                    }
+                                })
                         } else {
                             Cors::permissive()
                         }
                     })())
-                    .wrap(Logger::new(logger_format()))
+                    .wrap(
+                        Logger::new(logger_format()).custom_request_replace("client_ip", |req| {
+                            match client_ip(req.request()) {
+                                Some(ip) => ip.to_string(),
+                                None => "-".to_owned(),
+                            }
+                        }),
+                    )
                     .wrap(ErrorHandlers::new().default_handler(default_error_handler))
+                    .wrap_fn(|req, srv| {
+                        // Spans are only made when traces are exported, and continue the trace of
+                        // the caller when it sends a traceparent header
+                        let header = |name: &str| {
+                            req.headers()
+                                .get(name)
+                                .and_then(|value| value.to_str().ok())
+                        };
+                        let span = hb_log::http_span(
+                            req.method().as_str(),
+                            req.path(),
+                            header("traceparent"),
+                            header("tracestate"),
+                        );
+                        let method = req.method().clone();
+                        let response_span = span.clone();
+                        let res = srv.call(req);
+                        async move {
+                            let res = res.await;
+                            if let (false, Ok(res)) = (response_span.is_disabled(), &res) {
+                                hb_log::record_http_response(
+                                    &response_span,
+                                    method.as_str(),
+                                    res.request().match_pattern().as_deref(),
+                                    res.status().as_u16(),
+                                );
+                            }
+                            res
+                        }
+                        .instrument(span)
+                    })
+                    .app_data(self.trusted_proxies.clone())
                     .app_data(self.context.clone())
                     .configure(configure)
-            })
-            .bind(self.address)
-            .unwrap()
-            .run();
+            });
+            // HttpServer isn't Send, so it's run right away instead of being held in a binding
+            // across the awaits below
+            let server = match &self.tls {
+                Some(tls) => server.bind_rustls_0_23(self.address, tls.server_config().clone()),
+                None => server.bind(self.address),
+            }
+            .map(|server| server.run());
+            let server = match server {
+                Ok(server) => server,
+                Err(err) => {
+                    return Err(Error::msg(format!(
+                        "Failed to bind to address {}: {err}",
+                        self.address
+                    )))
+                }
+            };
 
             let server_handle = server.handle();
+            if let Some(tls) = &self.tls {
+                tls.watch(cancel_token.clone());
+            }
 
-            tokio::select! {
-                _ = cancel_token.cancelled() => {}
-                s = server => {
-                    if let Err(err) = s {
-                        hb_log::panic(None, &format!("[ApiRestServer] Server returned: {err}"));
-                    }
+            let result = tokio::select! {
+                _ = cancel_token.cancelled() => Ok(()),
+                s = server => match s {
+                    Ok(_) => Ok(()),
+                    Err(err) => Err(Error::msg(format!("Server returned: {err}"))),
                 }
-            }
+            };
 
             hb_log::info(None, "[ApiRestServer] Shutting down component");
             server_handle.stop(true).await;
+
+            result
         })())
     }
 }
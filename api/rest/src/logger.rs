@@ -1,3 +1,3 @@
 pub fn logger_format() -> &'static str {
-    return "🌐 [ApiRestServer] %a \"%r\" %s %b \"%{Referer}i\" \"%{User-Agent}i\" %T";
+    return "🌐 [ApiRestServer] %{client_ip}xi \"%r\" %s %b \"%{Referer}i\" \"%{User-Agent}i\" %T";
 }
@@ -1,18 +1,23 @@
 use actix_web::{http::StatusCode, HttpResponse, HttpResponseBuilder};
+use hb_dao::error::DaoError;
 use hb_error::Error;
 use serde::Serialize;
 
 pub mod admin;
+pub mod audit;
 pub mod auth;
 pub mod bucket;
 pub mod bucket_rule;
 pub mod collection;
 pub mod collection_rule;
 pub mod file;
+pub mod health;
 pub mod log;
+pub mod member;
 pub mod project;
 pub mod record;
 pub mod token;
+pub mod webhook;
 
 #[derive(Serialize)]
 pub struct Response {
@@ -53,6 +58,25 @@ impl Response {
         Self::error_raw(status_code, message)
     }
 
+    // Lookups that found nothing are answered with 404, writes that break a unique index with 409,
+    // content that doesn't match its checksum with 422, queries that ran past the statement timeout
+    // with 504 and any other error with the given status
+    pub fn error_dao(status_code: &StatusCode, err: &anyhow::Error) -> HttpResponse {
+        if DaoError::is_not_found(err) {
+            Self::error_raw(&StatusCode::NOT_FOUND, &err.to_string())
+        } else if DaoError::is_conflict(err) {
+            Self::error_raw(&StatusCode::CONFLICT, &err.to_string())
+        } else if DaoError::is_checksum_mismatch(err) {
+            Self::error_raw(&StatusCode::UNPROCESSABLE_ENTITY, &err.to_string())
+        } else if DaoError::is_timeout(err) {
+            Self::error_raw(&StatusCode::GATEWAY_TIMEOUT, &err.to_string())
+        } else if DaoError::is_quota_exceeded(err) {
+            Self::error_raw(&StatusCode::INSUFFICIENT_STORAGE, &err.to_string())
+        } else {
+            Self::error_raw(status_code, &err.to_string())
+        }
+    }
+
     pub fn error_raw(status_code: &StatusCode, message: &str) -> HttpResponse {
         hb_log::error(None, message);
 
@@ -90,3 +114,24 @@ impl PaginationRes {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every record endpoint reports its DAO errors with BAD_REQUEST, so a missing record has to be
+    // turned into a 404 here
+    #[test]
+    fn not_found_dao_error_is_404() {
+        let err = DaoError::NotFound("Record is not found".to_owned()).into();
+        assert_eq!(
+            Response::error_dao(&StatusCode::BAD_REQUEST, &err).status(),
+            StatusCode::NOT_FOUND
+        );
+        let err = anyhow::Error::msg("Invalid record");
+        assert_eq!(
+            Response::error_dao(&StatusCode::BAD_REQUEST, &err).status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -52,3 +52,32 @@ impl DeleteAdminResJson {
         Self { id: *id }
     }
 }
+
+#[derive(Serialize)]
+pub struct BackupResJson {
+    // Name of the run's directory under backup.path, the path itself isn't given out
+    name: String,
+    project_count: usize,
+}
+
+impl BackupResJson {
+    pub fn new(name: &str, project_count: &usize) -> Self {
+        Self {
+            name: name.to_owned(),
+            project_count: *project_count,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ConfigReloadResJson {
+    restart_required: Vec<String>,
+}
+
+impl ConfigReloadResJson {
+    pub fn new(restart_required: &[String]) -> Self {
+        Self {
+            restart_required: restart_required.to_vec(),
+        }
+    }
+}
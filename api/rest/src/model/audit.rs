@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct FindManyAuditReqQuery {
+    project_id: Uuid,
+    entity_type: Option<String>,
+    entity_id: Option<Uuid>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    before_id: Option<Uuid>,
+    limit: Option<i32>,
+}
+
+impl FindManyAuditReqQuery {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn entity_type(&self) -> &Option<String> {
+        &self.entity_type
+    }
+
+    pub fn entity_id(&self) -> &Option<Uuid> {
+        &self.entity_id
+    }
+
+    pub fn from(&self) -> &Option<DateTime<Utc>> {
+        &self.from
+    }
+
+    pub fn to(&self) -> &Option<DateTime<Utc>> {
+        &self.to
+    }
+
+    pub fn before_id(&self) -> &Option<Uuid> {
+        &self.before_id
+    }
+
+    pub fn limit(&self) -> &Option<i32> {
+        &self.limit
+    }
+}
+
+#[derive(Serialize)]
+pub struct AuditResJson {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    admin_id: Uuid,
+    project_id: Uuid,
+    action: String,
+    entity_type: String,
+    entity_id: Uuid,
+    diff: serde_json::Value,
+    source_ip: Option<String>,
+}
+
+impl AuditResJson {
+    pub fn new(
+        id: &Uuid,
+        created_at: &DateTime<Utc>,
+        admin_id: &Uuid,
+        project_id: &Uuid,
+        action: &str,
+        entity_type: &str,
+        entity_id: &Uuid,
+        diff: &serde_json::Value,
+        source_ip: &Option<String>,
+    ) -> Self {
+        Self {
+            id: *id,
+            created_at: *created_at,
+            admin_id: *admin_id,
+            project_id: *project_id,
+            action: action.to_owned(),
+            entity_type: entity_type.to_owned(),
+            entity_id: *entity_id,
+            diff: diff.clone(),
+            source_ip: source_ip.clone(),
+        }
+    }
+}
@@ -150,6 +150,70 @@ impl ConfirmPasswordResetReqJson {
     }
 }
 
+#[derive(Deserialize)]
+pub struct ConfirmTotpReqJson {
+    code: String,
+}
+
+impl ConfirmTotpReqJson {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DisableTotpReqJson {
+    password: String,
+    code: String,
+}
+
+impl DisableTotpReqJson {
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+}
+
+#[derive(Deserialize)]
+pub struct VerifyTotpReqJson {
+    challenge: String,
+    code: String,
+}
+
+impl VerifyTotpReqJson {
+    pub fn challenge(&self) -> &str {
+        &self.challenge
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordReqJson {
+    old_password: String,
+    new_password: String,
+    field: Option<String>,
+}
+
+impl ChangePasswordReqJson {
+    pub fn old_password(&self) -> &str {
+        &self.old_password
+    }
+
+    pub fn new_password(&self) -> &str {
+        &self.new_password
+    }
+
+    pub fn field(&self) -> &Option<String> {
+        &self.field
+    }
+}
+
 #[derive(Serialize)]
 pub struct RegisterResJson {
     id: Uuid,
@@ -234,3 +298,66 @@ impl ConfirmPasswordResetResJson {
         Self { id: *id }
     }
 }
+
+#[derive(Serialize)]
+pub struct TotpChallengeResJson {
+    challenge: String,
+    expires_at: i64,
+}
+
+impl TotpChallengeResJson {
+    pub fn new(challenge: &str, expires_at: &i64) -> Self {
+        Self {
+            challenge: challenge.to_owned(),
+            expires_at: *expires_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SetupTotpResJson {
+    uri: String,
+}
+
+impl SetupTotpResJson {
+    pub fn new(uri: &str) -> Self {
+        Self {
+            uri: uri.to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ConfirmTotpResJson {
+    backup_codes: Vec<String>,
+}
+
+impl ConfirmTotpResJson {
+    pub fn new(backup_codes: &[String]) -> Self {
+        Self {
+            backup_codes: backup_codes.to_vec(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DisableTotpResJson {
+    id: Uuid,
+}
+
+impl DisableTotpResJson {
+    pub fn new(id: &Uuid) -> Self {
+        Self { id: *id }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ChangePasswordResJson {
+    id: Uuid,
+}
+
+impl ChangePasswordResJson {
+    pub fn new(id: &Uuid) -> Self {
+        Self { id: *id }
+    }
+}
@@ -17,6 +17,9 @@ impl InsertOneBucketReqPath {
 pub struct InsertOneBucketReqJson {
     name: String,
     opt_ttl: Option<i64>,
+    max_file_size: Option<i64>,
+    allowed_content_types: Option<Vec<String>>,
+    quota_bytes: Option<i64>,
 }
 
 impl InsertOneBucketReqJson {
@@ -27,6 +30,18 @@ impl InsertOneBucketReqJson {
     pub fn opt_ttl(&self) -> &Option<i64> {
         &self.opt_ttl
     }
+
+    pub fn max_file_size(&self) -> &Option<i64> {
+        &self.max_file_size
+    }
+
+    pub fn allowed_content_types(&self) -> &Option<Vec<String>> {
+        &self.allowed_content_types
+    }
+
+    pub fn quota_bytes(&self) -> &Option<i64> {
+        &self.quota_bytes
+    }
 }
 
 #[derive(Deserialize)]
@@ -45,6 +60,45 @@ impl FindOneBucketReqPath {
     }
 }
 
+#[derive(Deserialize)]
+pub struct SubscribeBucketReqPath {
+    project_id: Uuid,
+    bucket_id: Uuid,
+}
+
+impl SubscribeBucketReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn bucket_id(&self) -> &Uuid {
+        &self.bucket_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SubscribeBucketReqQuery {
+    token: String,
+    // Sequence of the last message received before reconnecting, the newer ones are replayed
+    last_seq: Option<u64>,
+    // "json" by default, or "msgpack" to get the messages as binary MessagePack
+    format: Option<String>,
+}
+
+impl SubscribeBucketReqQuery {
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn last_seq(&self) -> &Option<u64> {
+        &self.last_seq
+    }
+
+    pub fn format(&self) -> &Option<String> {
+        &self.format
+    }
+}
+
 #[derive(Deserialize)]
 pub struct UpdateOneBucketReqPath {
     project_id: Uuid,
@@ -70,6 +124,24 @@ pub struct UpdateOneBucketReqJson {
         with = "::serde_with::rust::double_option"
     )]
     opt_ttl: Option<Option<i64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    max_file_size: Option<Option<i64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    allowed_content_types: Option<Option<Vec<String>>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    quota_bytes: Option<Option<i64>>,
 }
 
 impl UpdateOneBucketReqJson {
@@ -81,8 +153,24 @@ impl UpdateOneBucketReqJson {
         &self.opt_ttl
     }
 
+    pub fn max_file_size(&self) -> &Option<Option<i64>> {
+        &self.max_file_size
+    }
+
+    pub fn allowed_content_types(&self) -> &Option<Option<Vec<String>>> {
+        &self.allowed_content_types
+    }
+
+    pub fn quota_bytes(&self) -> &Option<Option<i64>> {
+        &self.quota_bytes
+    }
+
     pub fn is_all_none(&self) -> bool {
-        self.name.is_none() && self.opt_ttl.is_none()
+        self.name.is_none()
+            && self.opt_ttl.is_none()
+            && self.max_file_size.is_none()
+            && self.allowed_content_types.is_none()
+            && self.quota_bytes.is_none()
     }
 }
 
@@ -102,6 +190,22 @@ impl DeleteOneBucketReqPath {
     }
 }
 
+#[derive(Deserialize)]
+pub struct RecalculateUsageBucketReqPath {
+    project_id: Uuid,
+    bucket_id: Uuid,
+}
+
+impl RecalculateUsageBucketReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn bucket_id(&self) -> &Uuid {
+        &self.bucket_id
+    }
+}
+
 #[derive(Deserialize)]
 pub struct FindManyBucketReqPath {
     project_id: Uuid,
@@ -121,6 +225,10 @@ pub struct BucketResJson {
     project_id: Uuid,
     name: String,
     opt_ttl: Option<i64>,
+    max_file_size: Option<i64>,
+    allowed_content_types: Option<Vec<String>>,
+    quota_bytes: Option<i64>,
+    used_bytes: i64,
 }
 
 impl BucketResJson {
@@ -131,6 +239,10 @@ impl BucketResJson {
         project_id: &Uuid,
         name: &str,
         opt_ttl: &Option<i64>,
+        max_file_size: &Option<i64>,
+        allowed_content_types: &Option<Vec<String>>,
+        quota_bytes: &Option<i64>,
+        used_bytes: &i64,
     ) -> Self {
         Self {
             id: *id,
@@ -139,6 +251,10 @@ impl BucketResJson {
             project_id: *project_id,
             name: name.to_owned(),
             opt_ttl: *opt_ttl,
+            max_file_size: *max_file_size,
+            allowed_content_types: allowed_content_types.clone(),
+            quota_bytes: *quota_bytes,
+            used_bytes: *used_bytes,
         }
     }
 }
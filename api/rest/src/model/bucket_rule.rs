@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use hb_dao::bucket_rule::ALL_BUCKETS_ID;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -20,7 +21,7 @@ impl InsertOneBucketRuleReqPath {
 
 #[derive(Deserialize)]
 pub struct InsertOneBucketRuleReqJson {
-    bucket_id: Uuid,
+    bucket_id: String,
     find_one: String,
     find_many: String,
     insert_one: bool,
@@ -29,7 +30,7 @@ pub struct InsertOneBucketRuleReqJson {
 }
 
 impl InsertOneBucketRuleReqJson {
-    pub fn bucket_id(&self) -> &Uuid {
+    pub fn bucket_id(&self) -> &str {
         &self.bucket_id
     }
 
@@ -159,7 +160,7 @@ pub struct BucketRuleResJson {
     updated_at: DateTime<Utc>,
     project_id: Uuid,
     token_id: Uuid,
-    bucket_id: Uuid,
+    bucket_id: String,
     find_one: String,
     find_many: String,
     insert_one: bool,
@@ -187,7 +188,12 @@ impl BucketRuleResJson {
             updated_at: *updated_at,
             project_id: *project_id,
             token_id: *token_id,
-            bucket_id: *bucket_id,
+            // The wildcard rule is shown the way it's created
+            bucket_id: if *bucket_id == ALL_BUCKETS_ID {
+                "*".to_owned()
+            } else {
+                bucket_id.to_string()
+            },
             find_one: find_one.to_owned(),
             find_many: find_many.to_owned(),
             insert_one: *insert_one,
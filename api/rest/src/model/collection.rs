@@ -1,6 +1,7 @@
 use ahash::HashMap;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use uuid::Uuid;
 
 #[derive(Deserialize)]
@@ -18,8 +19,12 @@ impl InsertOneCollectionReqPath {
 pub struct InsertOneCollectionReqJson {
     name: String,
     schema_fields: HashMap<String, SchemaFieldPropsJson>,
+    unique_indexes: Option<Vec<Vec<String>>>,
     opt_auth_column_id: bool,
     opt_ttl: Option<i64>,
+    opt_soft_delete: Option<bool>,
+    opt_per_record_ttl: Option<bool>,
+    opt_mqtt_publish: Option<bool>,
 }
 
 impl InsertOneCollectionReqJson {
@@ -31,6 +36,10 @@ impl InsertOneCollectionReqJson {
         &self.schema_fields
     }
 
+    pub fn unique_indexes(&self) -> &Option<Vec<Vec<String>>> {
+        &self.unique_indexes
+    }
+
     pub fn opt_auth_column_id(&self) -> &bool {
         &self.opt_auth_column_id
     }
@@ -38,6 +47,18 @@ impl InsertOneCollectionReqJson {
     pub fn opt_ttl(&self) -> &Option<i64> {
         &self.opt_ttl
     }
+
+    pub fn opt_soft_delete(&self) -> &Option<bool> {
+        &self.opt_soft_delete
+    }
+
+    pub fn opt_per_record_ttl(&self) -> &Option<bool> {
+        &self.opt_per_record_ttl
+    }
+
+    pub fn opt_mqtt_publish(&self) -> &Option<bool> {
+        &self.opt_mqtt_publish
+    }
 }
 
 #[derive(Deserialize)]
@@ -75,12 +96,30 @@ impl SubscribeCollectionReqPath {
 #[derive(Deserialize)]
 pub struct SubscribeCollectionReqQuery {
     token: String,
+    // Sequence of the last message received before reconnecting, the newer ones are replayed
+    last_seq: Option<u64>,
+    // Sends the current records before any live message
+    with_snapshot: Option<bool>,
+    // "json" by default, or "msgpack" to get the messages as binary MessagePack
+    format: Option<String>,
 }
 
 impl SubscribeCollectionReqQuery {
     pub fn token(&self) -> &str {
         &self.token
     }
+
+    pub fn last_seq(&self) -> &Option<u64> {
+        &self.last_seq
+    }
+
+    pub fn with_snapshot(&self) -> &Option<bool> {
+        &self.with_snapshot
+    }
+
+    pub fn format(&self) -> &Option<String> {
+        &self.format
+    }
 }
 
 #[derive(Deserialize)]
@@ -103,6 +142,9 @@ impl UpdateOneCollectionReqPath {
 pub struct UpdateOneCollectionReqJson {
     name: Option<String>,
     schema_fields: Option<HashMap<String, SchemaFieldPropsJson>>,
+    unique_indexes: Option<Vec<Vec<String>>>,
+    rename_fields: Option<HashMap<String, String>>,
+    backfill_defaults: Option<bool>,
     opt_auth_column_id: Option<bool>,
     #[serde(
         default,
@@ -110,6 +152,9 @@ pub struct UpdateOneCollectionReqJson {
         with = "::serde_with::rust::double_option"
     )]
     opt_ttl: Option<Option<i64>>,
+    opt_soft_delete: Option<bool>,
+    opt_per_record_ttl: Option<bool>,
+    opt_mqtt_publish: Option<bool>,
 }
 
 impl UpdateOneCollectionReqJson {
@@ -121,6 +166,18 @@ impl UpdateOneCollectionReqJson {
         &self.schema_fields
     }
 
+    pub fn unique_indexes(&self) -> &Option<Vec<Vec<String>>> {
+        &self.unique_indexes
+    }
+
+    pub fn rename_fields(&self) -> &Option<HashMap<String, String>> {
+        &self.rename_fields
+    }
+
+    pub fn backfill_defaults(&self) -> &Option<bool> {
+        &self.backfill_defaults
+    }
+
     pub fn opt_auth_column_id(&self) -> &Option<bool> {
         &self.opt_auth_column_id
     }
@@ -129,11 +186,119 @@ impl UpdateOneCollectionReqJson {
         &self.opt_ttl
     }
 
+    pub fn opt_soft_delete(&self) -> &Option<bool> {
+        &self.opt_soft_delete
+    }
+
+    pub fn opt_per_record_ttl(&self) -> &Option<bool> {
+        &self.opt_per_record_ttl
+    }
+
+    pub fn opt_mqtt_publish(&self) -> &Option<bool> {
+        &self.opt_mqtt_publish
+    }
+
     pub fn is_all_none(&self) -> bool {
         self.name.is_none()
             && self.schema_fields.is_none()
+            && self.unique_indexes.is_none()
+            && self.rename_fields.is_none()
             && self.opt_auth_column_id.is_none()
             && self.opt_ttl.is_none()
+            && self.opt_soft_delete.is_none()
+            && self.opt_per_record_ttl.is_none()
+            && self.opt_mqtt_publish.is_none()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DuplicateOneCollectionReqPath {
+    project_id: Uuid,
+    collection_id: Uuid,
+}
+
+impl DuplicateOneCollectionReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DuplicateOneCollectionReqJson {
+    new_name: String,
+    include_records: Option<bool>,
+    preserve_ids: Option<bool>,
+}
+
+impl DuplicateOneCollectionReqJson {
+    pub fn new_name(&self) -> &str {
+        &self.new_name
+    }
+
+    pub fn include_records(&self) -> &Option<bool> {
+        &self.include_records
+    }
+
+    pub fn preserve_ids(&self) -> &Option<bool> {
+        &self.preserve_ids
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportSchemaCollectionReqPath {
+    project_id: Uuid,
+    collection_id: Uuid,
+}
+
+impl ExportSchemaCollectionReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct StatsCollectionReqPath {
+    project_id: Uuid,
+    collection_id: Uuid,
+}
+
+impl StatsCollectionReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ApplySchemaCollectionReqPath {
+    project_id: Uuid,
+}
+
+impl ApplySchemaCollectionReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ApplySchemaCollectionReqQuery {
+    dry_run: Option<bool>,
+}
+
+impl ApplySchemaCollectionReqQuery {
+    pub fn dry_run(&self) -> &Option<bool> {
+        &self.dry_run
     }
 }
 
@@ -164,6 +329,32 @@ impl FindManyCollectionReqPath {
     }
 }
 
+#[derive(Deserialize)]
+pub struct FindManyCollectionReqQuery {
+    limit: Option<i32>,
+    after_id: Option<Uuid>,
+    search: Option<String>,
+    order_by: Option<String>,
+}
+
+impl FindManyCollectionReqQuery {
+    pub fn limit(&self) -> &Option<i32> {
+        &self.limit
+    }
+
+    pub fn after_id(&self) -> &Option<Uuid> {
+        &self.after_id
+    }
+
+    pub fn search(&self) -> &Option<String> {
+        &self.search
+    }
+
+    pub fn order_by(&self) -> &Option<String> {
+        &self.order_by
+    }
+}
+
 #[derive(Serialize)]
 pub struct CollectionResJson {
     id: Uuid,
@@ -172,8 +363,12 @@ pub struct CollectionResJson {
     project_id: Uuid,
     name: String,
     schema_fields: HashMap<String, SchemaFieldPropsJson>,
+    unique_indexes: Vec<Vec<String>>,
     opt_auth_column_id: bool,
     opt_ttl: Option<i64>,
+    opt_soft_delete: bool,
+    opt_per_record_ttl: bool,
+    opt_mqtt_publish: bool,
 }
 
 impl CollectionResJson {
@@ -184,8 +379,12 @@ impl CollectionResJson {
         project_id: &Uuid,
         name: &str,
         schema_fields: &HashMap<String, SchemaFieldPropsJson>,
+        unique_indexes: &Vec<Vec<String>>,
         opt_auth_column_id: &bool,
         opt_ttl: &Option<i64>,
+        opt_soft_delete: &bool,
+        opt_per_record_ttl: &bool,
+        opt_mqtt_publish: &bool,
     ) -> Self {
         Self {
             id: *id,
@@ -194,8 +393,145 @@ impl CollectionResJson {
             project_id: *project_id,
             name: name.to_owned(),
             schema_fields: schema_fields.clone(),
+            unique_indexes: unique_indexes.clone(),
             opt_auth_column_id: *opt_auth_column_id,
             opt_ttl: *opt_ttl,
+            opt_soft_delete: *opt_soft_delete,
+            opt_per_record_ttl: *opt_per_record_ttl,
+            opt_mqtt_publish: *opt_mqtt_publish,
+        }
+    }
+}
+
+// Collections are matched by name when a schema is applied, so the document leaves out the id of
+// the collection it was exported from
+#[derive(Deserialize, Serialize)]
+pub struct CollectionSchemaJson {
+    name: String,
+    schema_fields: HashMap<String, SchemaFieldPropsJson>,
+    #[serde(default)]
+    unique_indexes: Vec<Vec<String>>,
+    opt_auth_column_id: bool,
+    opt_ttl: Option<i64>,
+    opt_soft_delete: bool,
+    #[serde(default)]
+    opt_per_record_ttl: bool,
+    #[serde(default)]
+    opt_mqtt_publish: bool,
+}
+
+impl CollectionSchemaJson {
+    pub fn new(
+        name: &str,
+        schema_fields: &HashMap<String, SchemaFieldPropsJson>,
+        unique_indexes: &Vec<Vec<String>>,
+        opt_auth_column_id: &bool,
+        opt_ttl: &Option<i64>,
+        opt_soft_delete: &bool,
+        opt_per_record_ttl: &bool,
+        opt_mqtt_publish: &bool,
+    ) -> Self {
+        Self {
+            name: name.to_owned(),
+            schema_fields: schema_fields.clone(),
+            unique_indexes: unique_indexes.clone(),
+            opt_auth_column_id: *opt_auth_column_id,
+            opt_ttl: *opt_ttl,
+            opt_soft_delete: *opt_soft_delete,
+            opt_per_record_ttl: *opt_per_record_ttl,
+            opt_mqtt_publish: *opt_mqtt_publish,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn schema_fields(&self) -> &HashMap<String, SchemaFieldPropsJson> {
+        &self.schema_fields
+    }
+
+    pub fn unique_indexes(&self) -> &Vec<Vec<String>> {
+        &self.unique_indexes
+    }
+
+    pub fn opt_auth_column_id(&self) -> &bool {
+        &self.opt_auth_column_id
+    }
+
+    pub fn opt_ttl(&self) -> &Option<i64> {
+        &self.opt_ttl
+    }
+
+    pub fn opt_soft_delete(&self) -> &bool {
+        &self.opt_soft_delete
+    }
+
+    pub fn opt_per_record_ttl(&self) -> &bool {
+        &self.opt_per_record_ttl
+    }
+
+    pub fn opt_mqtt_publish(&self) -> &bool {
+        &self.opt_mqtt_publish
+    }
+}
+
+#[derive(Serialize)]
+pub struct ApplySchemaCollectionResJson {
+    collection_id: Option<Uuid>,
+    created: bool,
+    dry_run: bool,
+    added_fields: Vec<String>,
+    removed_fields: Vec<String>,
+    changed_fields: Vec<String>,
+    changed_options: Vec<String>,
+}
+
+impl ApplySchemaCollectionResJson {
+    pub fn new(
+        collection_id: &Option<Uuid>,
+        created: &bool,
+        dry_run: &bool,
+        added_fields: &Vec<String>,
+        removed_fields: &Vec<String>,
+        changed_fields: &Vec<String>,
+        changed_options: &Vec<String>,
+    ) -> Self {
+        Self {
+            collection_id: *collection_id,
+            created: *created,
+            dry_run: *dry_run,
+            added_fields: added_fields.clone(),
+            removed_fields: removed_fields.clone(),
+            changed_fields: changed_fields.clone(),
+            changed_options: changed_options.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct StatsCollectionResJson {
+    record_count: i64,
+    size_bytes: Option<i64>,
+    oldest_record_created_at: Option<DateTime<Utc>>,
+    newest_record_created_at: Option<DateTime<Utc>>,
+    indexes: Vec<String>,
+}
+
+impl StatsCollectionResJson {
+    pub fn new(
+        record_count: &i64,
+        size_bytes: &Option<i64>,
+        oldest_record_created_at: &Option<DateTime<Utc>>,
+        newest_record_created_at: &Option<DateTime<Utc>>,
+        indexes: &Vec<String>,
+    ) -> Self {
+        Self {
+            record_count: *record_count,
+            size_bytes: *size_bytes,
+            oldest_record_created_at: *oldest_record_created_at,
+            newest_record_created_at: *newest_record_created_at,
+            indexes: indexes.clone(),
         }
     }
 }
@@ -220,6 +556,18 @@ pub struct SchemaFieldPropsJson {
     auth_column: Option<bool>,
     hashed: Option<bool>,
     hidden: Option<bool>,
+    default: Option<Value>,
+    min: Option<f64>,
+    max: Option<f64>,
+    max_length: Option<i64>,
+    regex: Option<String>,
+    #[serde(rename = "enum")]
+    enum_values: Option<Vec<Value>>,
+    reference_collection_id: Option<Uuid>,
+    on_delete: Option<String>,
+    // Only read when the field is added to a collection, to fill it in the existing records
+    #[serde(default, skip_serializing)]
+    backfill_value: Option<Value>,
 }
 
 impl SchemaFieldPropsJson {
@@ -231,6 +579,14 @@ impl SchemaFieldPropsJson {
         auth_column: &Option<bool>,
         hashed: &Option<bool>,
         hidden: &Option<bool>,
+        default: &Option<Value>,
+        min: &Option<f64>,
+        max: &Option<f64>,
+        max_length: &Option<i64>,
+        regex: &Option<String>,
+        enum_values: &Option<Vec<Value>>,
+        reference_collection_id: &Option<Uuid>,
+        on_delete: &Option<String>,
     ) -> Self {
         Self {
             kind: kind.to_owned(),
@@ -240,6 +596,15 @@ impl SchemaFieldPropsJson {
             auth_column: *auth_column,
             hashed: *hashed,
             hidden: *hidden,
+            default: default.clone(),
+            min: *min,
+            max: *max,
+            max_length: *max_length,
+            regex: regex.clone(),
+            enum_values: enum_values.clone(),
+            reference_collection_id: *reference_collection_id,
+            on_delete: on_delete.clone(),
+            backfill_value: None,
         }
     }
 
@@ -270,4 +635,40 @@ impl SchemaFieldPropsJson {
     pub fn hidden(&self) -> &Option<bool> {
         &self.hidden
     }
+
+    pub fn default(&self) -> &Option<Value> {
+        &self.default
+    }
+
+    pub fn min(&self) -> &Option<f64> {
+        &self.min
+    }
+
+    pub fn max(&self) -> &Option<f64> {
+        &self.max
+    }
+
+    pub fn max_length(&self) -> &Option<i64> {
+        &self.max_length
+    }
+
+    pub fn regex(&self) -> &Option<String> {
+        &self.regex
+    }
+
+    pub fn enum_values(&self) -> &Option<Vec<Value>> {
+        &self.enum_values
+    }
+
+    pub fn reference_collection_id(&self) -> &Option<Uuid> {
+        &self.reference_collection_id
+    }
+
+    pub fn on_delete(&self) -> &Option<String> {
+        &self.on_delete
+    }
+
+    pub fn backfill_value(&self) -> &Option<Value> {
+        &self.backfill_value
+    }
 }
@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use hb_dao::collection_rule::ALL_COLLECTIONS_ID;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -20,16 +21,18 @@ impl InsertOneCollectionRuleReqPath {
 
 #[derive(Deserialize)]
 pub struct InsertOneCollectionRuleReqJson {
-    collection_id: Uuid,
+    collection_id: String,
     find_one: String,
     find_many: String,
     insert_one: bool,
     update_one: String,
     delete_one: String,
+    readable_fields: Option<Vec<String>>,
+    writable_fields: Option<Vec<String>>,
 }
 
 impl InsertOneCollectionRuleReqJson {
-    pub fn collection_id(&self) -> &Uuid {
+    pub fn collection_id(&self) -> &str {
         &self.collection_id
     }
 
@@ -52,6 +55,14 @@ impl InsertOneCollectionRuleReqJson {
     pub fn delete_one(&self) -> &str {
         &self.delete_one
     }
+
+    pub fn readable_fields(&self) -> &Option<Vec<String>> {
+        &self.readable_fields
+    }
+
+    pub fn writable_fields(&self) -> &Option<Vec<String>> {
+        &self.writable_fields
+    }
 }
 
 #[derive(Deserialize)]
@@ -93,6 +104,18 @@ pub struct UpdateOneCollectionRuleReqJson {
     insert_one: Option<bool>,
     update_one: Option<String>,
     delete_one: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    readable_fields: Option<Option<Vec<String>>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    writable_fields: Option<Option<Vec<String>>>,
 }
 
 impl UpdateOneCollectionRuleReqJson {
@@ -116,12 +139,22 @@ impl UpdateOneCollectionRuleReqJson {
         &self.delete_one
     }
 
+    pub fn readable_fields(&self) -> &Option<Option<Vec<String>>> {
+        &self.readable_fields
+    }
+
+    pub fn writable_fields(&self) -> &Option<Option<Vec<String>>> {
+        &self.writable_fields
+    }
+
     pub fn is_all_none(&self) -> bool {
         self.find_one.is_none()
             && self.find_many.is_none()
             && self.insert_one.is_none()
             && self.update_one.is_none()
             && self.delete_one.is_none()
+            && self.readable_fields.is_none()
+            && self.writable_fields.is_none()
     }
 }
 
@@ -159,12 +192,14 @@ pub struct CollectionRuleResJson {
     updated_at: DateTime<Utc>,
     project_id: Uuid,
     token_id: Uuid,
-    collection_id: Uuid,
+    collection_id: String,
     find_one: String,
     find_many: String,
     insert_one: bool,
     update_one: String,
     delete_one: String,
+    readable_fields: Option<Vec<String>>,
+    writable_fields: Option<Vec<String>>,
 }
 
 impl CollectionRuleResJson {
@@ -180,6 +215,8 @@ impl CollectionRuleResJson {
         insert_one: &bool,
         update_one: &str,
         delete_one: &str,
+        readable_fields: &Option<Vec<String>>,
+        writable_fields: &Option<Vec<String>>,
     ) -> Self {
         Self {
             id: *id,
@@ -187,12 +224,19 @@ impl CollectionRuleResJson {
             updated_at: *updated_at,
             project_id: *project_id,
             token_id: *token_id,
-            collection_id: *collection_id,
+            // The wildcard rule is shown the way it's created
+            collection_id: if *collection_id == ALL_COLLECTIONS_ID {
+                "*".to_owned()
+            } else {
+                collection_id.to_string()
+            },
             find_one: find_one.to_owned(),
             find_many: find_many.to_owned(),
             insert_one: *insert_one,
             update_one: update_one.to_owned(),
             delete_one: delete_one.to_owned(),
+            readable_fields: readable_fields.clone(),
+            writable_fields: writable_fields.clone(),
         }
     }
 }
@@ -60,6 +60,111 @@ impl InsertOneFileReqForm {
     }
 }
 
+#[derive(Deserialize)]
+pub struct InsertOneFileUploadReqPath {
+    project_id: Uuid,
+    bucket_id: Uuid,
+}
+
+impl InsertOneFileUploadReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn bucket_id(&self) -> &Uuid {
+        &self.bucket_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct InsertOneFileUploadReqJson {
+    file_name: Option<String>,
+    content_type: Option<String>,
+    size: i64,
+    public: Option<bool>,
+}
+
+impl InsertOneFileUploadReqJson {
+    pub fn file_name(&self) -> &Option<String> {
+        &self.file_name
+    }
+
+    pub fn content_type(&self) -> &Option<String> {
+        &self.content_type
+    }
+
+    pub fn size(&self) -> &i64 {
+        &self.size
+    }
+
+    pub fn public(&self) -> &Option<bool> {
+        &self.public
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FindOneFileUploadReqPath {
+    project_id: Uuid,
+    bucket_id: Uuid,
+    upload_id: Uuid,
+}
+
+impl FindOneFileUploadReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn bucket_id(&self) -> &Uuid {
+        &self.bucket_id
+    }
+
+    pub fn upload_id(&self) -> &Uuid {
+        &self.upload_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UpdateOneFileUploadReqPath {
+    project_id: Uuid,
+    bucket_id: Uuid,
+    upload_id: Uuid,
+}
+
+impl UpdateOneFileUploadReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn bucket_id(&self) -> &Uuid {
+        &self.bucket_id
+    }
+
+    pub fn upload_id(&self) -> &Uuid {
+        &self.upload_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CompleteFileUploadReqPath {
+    project_id: Uuid,
+    bucket_id: Uuid,
+    upload_id: Uuid,
+}
+
+impl CompleteFileUploadReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn bucket_id(&self) -> &Uuid {
+        &self.bucket_id
+    }
+
+    pub fn upload_id(&self) -> &Uuid {
+        &self.upload_id
+    }
+}
+
 #[derive(Deserialize)]
 pub struct HeadFindOneFileReqPath {
     project_id: Uuid,
@@ -84,12 +189,22 @@ impl HeadFindOneFileReqPath {
 #[derive(Deserialize)]
 pub struct HeadFindOneFileReqQuery {
     token: Option<String>,
+    signature: Option<String>,
+    expires: Option<i64>,
 }
 
 impl HeadFindOneFileReqQuery {
     pub fn token(&self) -> &Option<String> {
         &self.token
     }
+
+    pub fn signature(&self) -> &Option<String> {
+        &self.signature
+    }
+
+    pub fn expires(&self) -> &Option<i64> {
+        &self.expires
+    }
 }
 
 #[derive(Deserialize)]
@@ -116,7 +231,13 @@ impl FindOneFileReqPath {
 #[derive(Deserialize)]
 pub struct FindOneFileReqQuery {
     token: Option<String>,
+    signature: Option<String>,
+    expires: Option<i64>,
     data: Option<u8>,
+    width: Option<u32>,
+    height: Option<u32>,
+    fit: Option<String>,
+    format: Option<String>,
 }
 
 impl FindOneFileReqQuery {
@@ -124,9 +245,69 @@ impl FindOneFileReqQuery {
         &self.token
     }
 
+    pub fn signature(&self) -> &Option<String> {
+        &self.signature
+    }
+
+    pub fn expires(&self) -> &Option<i64> {
+        &self.expires
+    }
+
     pub fn data(&self) -> &Option<u8> {
         &self.data
     }
+
+    pub fn width(&self) -> &Option<u32> {
+        &self.width
+    }
+
+    pub fn height(&self) -> &Option<u32> {
+        &self.height
+    }
+
+    pub fn fit(&self) -> &Option<String> {
+        &self.fit
+    }
+
+    pub fn format(&self) -> &Option<String> {
+        &self.format
+    }
+
+    pub fn is_resize(&self) -> bool {
+        self.width.is_some() || self.height.is_some() || self.fit.is_some() || self.format.is_some()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PresignOneFileReqPath {
+    project_id: Uuid,
+    bucket_id: Uuid,
+    file_id: Uuid,
+}
+
+impl PresignOneFileReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn bucket_id(&self) -> &Uuid {
+        &self.bucket_id
+    }
+
+    pub fn file_id(&self) -> &Uuid {
+        &self.file_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PresignOneFileReqJson {
+    expires_in: Option<u32>,
+}
+
+impl PresignOneFileReqJson {
+    pub fn expires_in(&self) -> &Option<u32> {
+        &self.expires_in
+    }
 }
 
 #[derive(Deserialize)]
@@ -175,6 +356,46 @@ impl UpdateOneFileReqJson {
     }
 }
 
+#[derive(Deserialize)]
+pub struct ReplaceOneFileContentReqPath {
+    project_id: Uuid,
+    bucket_id: Uuid,
+    file_id: Uuid,
+}
+
+impl ReplaceOneFileContentReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn bucket_id(&self) -> &Uuid {
+        &self.bucket_id
+    }
+
+    pub fn file_id(&self) -> &Uuid {
+        &self.file_id
+    }
+}
+
+#[derive(MultipartForm)]
+pub struct ReplaceOneFileContentReqForm {
+    file: TempFile,
+}
+
+impl ReplaceOneFileContentReqForm {
+    pub fn file_path(&self) -> &Path {
+        self.file.file.path()
+    }
+
+    pub fn content_type(&self) -> &Option<Mime> {
+        &self.file.content_type
+    }
+
+    pub fn size(&self) -> &usize {
+        &self.file.size
+    }
+}
+
 #[derive(Deserialize)]
 pub struct DeleteOneFileReqPath {
     project_id: Uuid,
@@ -216,6 +437,13 @@ impl FindManyFileReqPath {
 pub struct FindManyFileReqQuery {
     before_id: Option<Uuid>,
     limit: Option<i32>,
+    file_name: Option<String>,
+    content_type: Option<String>,
+    public: Option<bool>,
+    created_by: Option<Uuid>,
+    min_size: Option<i64>,
+    max_size: Option<i64>,
+    order_by: Option<String>,
 }
 
 impl FindManyFileReqQuery {
@@ -226,6 +454,100 @@ impl FindManyFileReqQuery {
     pub fn limit(&self) -> &Option<i32> {
         &self.limit
     }
+
+    pub fn file_name(&self) -> &Option<String> {
+        &self.file_name
+    }
+
+    pub fn content_type(&self) -> &Option<String> {
+        &self.content_type
+    }
+
+    pub fn public(&self) -> &Option<bool> {
+        &self.public
+    }
+
+    pub fn created_by(&self) -> &Option<Uuid> {
+        &self.created_by
+    }
+
+    pub fn min_size(&self) -> &Option<i64> {
+        &self.min_size
+    }
+
+    pub fn max_size(&self) -> &Option<i64> {
+        &self.max_size
+    }
+
+    pub fn order_by(&self) -> &Option<String> {
+        &self.order_by
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeleteManyFileReqPath {
+    project_id: Uuid,
+    bucket_id: Uuid,
+}
+
+impl DeleteManyFileReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn bucket_id(&self) -> &Uuid {
+        &self.bucket_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeleteManyFileReqJson {
+    ids: Option<Vec<Uuid>>,
+    file_name: Option<String>,
+    content_type: Option<String>,
+    public: Option<bool>,
+    created_by: Option<Uuid>,
+    min_size: Option<i64>,
+    max_size: Option<i64>,
+}
+
+impl DeleteManyFileReqJson {
+    pub fn ids(&self) -> &Option<Vec<Uuid>> {
+        &self.ids
+    }
+
+    pub fn file_name(&self) -> &Option<String> {
+        &self.file_name
+    }
+
+    pub fn content_type(&self) -> &Option<String> {
+        &self.content_type
+    }
+
+    pub fn public(&self) -> &Option<bool> {
+        &self.public
+    }
+
+    pub fn created_by(&self) -> &Option<Uuid> {
+        &self.created_by
+    }
+
+    pub fn min_size(&self) -> &Option<i64> {
+        &self.min_size
+    }
+
+    pub fn max_size(&self) -> &Option<i64> {
+        &self.max_size
+    }
+
+    pub fn is_filter_none(&self) -> bool {
+        self.file_name.is_none()
+            && self.content_type.is_none()
+            && self.public.is_none()
+            && self.created_by.is_none()
+            && self.min_size.is_none()
+            && self.max_size.is_none()
+    }
 }
 
 #[derive(Serialize)]
@@ -239,6 +561,8 @@ pub struct FileResJson {
     content_type: String,
     size: i64,
     public: bool,
+    pending: bool,
+    checksum: Option<String>,
 }
 
 impl FileResJson {
@@ -252,6 +576,8 @@ impl FileResJson {
         content_type: &str,
         size: &i64,
         public: &bool,
+        pending: &bool,
+        checksum: &Option<String>,
     ) -> Self {
         Self {
             id: *id,
@@ -263,6 +589,8 @@ impl FileResJson {
             content_type: content_type.to_owned(),
             size: *size,
             public: *public,
+            pending: *pending,
+            checksum: checksum.clone(),
         }
     }
 }
@@ -277,3 +605,67 @@ impl DeleteFileResJson {
         Self { id: *id }
     }
 }
+
+#[derive(Serialize)]
+pub struct DeleteManyFileResJson {
+    deleted: usize,
+    skipped: usize,
+    errors: Vec<DeleteManyFileErrorResJson>,
+}
+
+impl DeleteManyFileResJson {
+    pub fn new(deleted: &usize, skipped: &usize, errors: &Vec<DeleteManyFileErrorResJson>) -> Self {
+        Self {
+            deleted: *deleted,
+            skipped: *skipped,
+            errors: errors.to_vec(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct DeleteManyFileErrorResJson {
+    id: Uuid,
+    reason: String,
+}
+
+impl DeleteManyFileErrorResJson {
+    pub fn new(id: &Uuid, reason: &str) -> Self {
+        Self {
+            id: *id,
+            reason: reason.to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct FileUploadResJson {
+    id: Uuid,
+    size: i64,
+    uploaded_size: i64,
+}
+
+impl FileUploadResJson {
+    pub fn new(id: &Uuid, size: &i64, uploaded_size: &i64) -> Self {
+        Self {
+            id: *id,
+            size: *size,
+            uploaded_size: *uploaded_size,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PresignFileResJson {
+    url: String,
+    expires: i64,
+}
+
+impl PresignFileResJson {
+    pub fn new(url: &str, expires: &i64) -> Self {
+        Self {
+            url: url.to_owned(),
+            expires: *expires,
+        }
+    }
+}
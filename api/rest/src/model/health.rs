@@ -0,0 +1,138 @@
+use hb_dao::DbPoolStatus;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct WarmupResJson {
+    finished: bool,
+    collections_loaded: usize,
+    tables_total: usize,
+    tables_prepared: usize,
+    tables_failed: usize,
+}
+
+impl WarmupResJson {
+    pub fn new(
+        finished: &bool,
+        collections_loaded: &usize,
+        tables_total: &usize,
+        tables_prepared: &usize,
+        tables_failed: &usize,
+    ) -> Self {
+        Self {
+            finished: *finished,
+            collections_loaded: *collections_loaded,
+            tables_total: *tables_total,
+            tables_prepared: *tables_prepared,
+            tables_failed: *tables_failed,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct HealthResJson {
+    status: &'static str,
+    components: Vec<HealthComponentResJson>,
+    slow_queries: u64,
+    pool: PoolStatusResJson,
+}
+
+impl HealthResJson {
+    pub fn new(
+        status: &'static str,
+        components: Vec<HealthComponentResJson>,
+        slow_queries: &u64,
+        pool: PoolStatusResJson,
+    ) -> Self {
+        Self {
+            status,
+            components,
+            slow_queries: *slow_queries,
+            pool,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PoolStatusResJson {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    idle: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    waiting: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nodes: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connected_nodes: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connections: Option<u32>,
+}
+
+impl PoolStatusResJson {
+    pub fn new(pool_status: &DbPoolStatus) -> Self {
+        match pool_status {
+            DbPoolStatus::Sql {
+                size,
+                idle,
+                waiting,
+            } => Self {
+                size: Some(*size),
+                idle: Some(*idle),
+                waiting: Some(*waiting),
+                nodes: None,
+                connected_nodes: None,
+                connections: None,
+            },
+            DbPoolStatus::Scylla {
+                nodes,
+                connected_nodes,
+                connections,
+            } => Self {
+                size: None,
+                idle: None,
+                waiting: None,
+                nodes: Some(*nodes),
+                connected_nodes: Some(*connected_nodes),
+                connections: Some(*connections),
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct HealthComponentResJson {
+    name: &'static str,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl HealthComponentResJson {
+    pub fn new(name: &'static str, latency_ms: &f64, error: &Option<String>) -> Self {
+        Self {
+            name,
+            status: if error.is_some() { "error" } else { "ok" },
+            latency_ms: Some(*latency_ms),
+            error: error.clone(),
+        }
+    }
+
+    pub fn disabled(name: &'static str) -> Self {
+        Self {
+            name,
+            status: "disabled",
+            latency_ms: None,
+            error: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.error.is_some()
+    }
+}
@@ -15,11 +15,32 @@ impl FindManyLogReqPath {
 
 #[derive(Deserialize)]
 pub struct FindManyLogReqQuery {
+    kind: Option<String>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    // Part of the message to look for
+    q: Option<String>,
     before_id: Option<Uuid>,
     limit: Option<i32>,
 }
 
 impl FindManyLogReqQuery {
+    pub fn kind(&self) -> &Option<String> {
+        &self.kind
+    }
+
+    pub fn from(&self) -> &Option<DateTime<Utc>> {
+        &self.from
+    }
+
+    pub fn to(&self) -> &Option<DateTime<Utc>> {
+        &self.to
+    }
+
+    pub fn q(&self) -> &Option<String> {
+        &self.q
+    }
+
     pub fn before_id(&self) -> &Option<Uuid> {
         &self.before_id
     }
@@ -43,12 +64,24 @@ impl SubscribeLogReqPath {
 #[derive(Deserialize)]
 pub struct SubscribeLogReqQuery {
     token: String,
+    // Sequence of the last message received before reconnecting, the newer ones are replayed
+    last_seq: Option<u64>,
+    // "json" by default, or "msgpack" to get the messages as binary MessagePack
+    format: Option<String>,
 }
 
 impl SubscribeLogReqQuery {
     pub fn token(&self) -> &str {
         &self.token
     }
+
+    pub fn last_seq(&self) -> &Option<u64> {
+        &self.last_seq
+    }
+
+    pub fn format(&self) -> &Option<String> {
+        &self.format
+    }
 }
 
 #[derive(Serialize)]
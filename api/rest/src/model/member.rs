@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Deserialize)]
+pub struct InsertOneMemberReqPath {
+    project_id: Uuid,
+}
+
+impl InsertOneMemberReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct InsertOneMemberReqJson {
+    #[validate(email)]
+    admin_email: String,
+    role: String,
+}
+
+impl InsertOneMemberReqJson {
+    pub fn admin_email(&self) -> &str {
+        &self.admin_email
+    }
+
+    pub fn role(&self) -> &str {
+        &self.role
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UpdateOneMemberReqPath {
+    project_id: Uuid,
+    member_id: Uuid,
+}
+
+impl UpdateOneMemberReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn member_id(&self) -> &Uuid {
+        &self.member_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UpdateOneMemberReqJson {
+    role: String,
+}
+
+impl UpdateOneMemberReqJson {
+    pub fn role(&self) -> &str {
+        &self.role
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeleteOneMemberReqPath {
+    project_id: Uuid,
+    member_id: Uuid,
+}
+
+impl DeleteOneMemberReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn member_id(&self) -> &Uuid {
+        &self.member_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FindManyMemberReqPath {
+    project_id: Uuid,
+}
+
+impl FindManyMemberReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+}
+
+#[derive(Serialize)]
+pub struct MemberResJson {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    project_id: Uuid,
+    admin_id: Uuid,
+    admin_email: String,
+    role: String,
+}
+
+impl MemberResJson {
+    pub fn new(
+        id: &Uuid,
+        created_at: &DateTime<Utc>,
+        updated_at: &DateTime<Utc>,
+        project_id: &Uuid,
+        admin_id: &Uuid,
+        admin_email: &str,
+        role: &str,
+    ) -> Self {
+        Self {
+            id: *id,
+            created_at: *created_at,
+            updated_at: *updated_at,
+            project_id: *project_id,
+            admin_id: *admin_id,
+            admin_email: admin_email.to_owned(),
+            role: role.to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MemberIDResJson {
+    id: Uuid,
+}
+
+impl MemberIDResJson {
+    pub fn new(id: &Uuid) -> Self {
+        Self { id: *id }
+    }
+}
@@ -1,8 +1,13 @@
+use std::path::Path;
+
+use actix_multipart::form::{tempfile::TempFile, MultipartForm};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::model::collection::CollectionSchemaJson;
+
 #[derive(Deserialize)]
 pub struct InsertOneProjectReqJson {
     name: String,
@@ -25,6 +30,40 @@ impl FindOneProjectReqPath {
     }
 }
 
+#[derive(Deserialize)]
+pub struct SubscribeProjectReqPath {
+    project_id: Uuid,
+}
+
+impl SubscribeProjectReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SubscribeProjectReqQuery {
+    token: String,
+    // Sequence of the last message received before reconnecting, the newer ones are replayed
+    last_seq: Option<u64>,
+    // "json" by default, or "msgpack" to get the messages as binary MessagePack
+    format: Option<String>,
+}
+
+impl SubscribeProjectReqQuery {
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn last_seq(&self) -> &Option<u64> {
+        &self.last_seq
+    }
+
+    pub fn format(&self) -> &Option<String> {
+        &self.format
+    }
+}
+
 #[derive(Deserialize)]
 pub struct UpdateOneProjectReqPath {
     project_id: Uuid,
@@ -112,6 +151,40 @@ impl DuplicateOneProjectReqJson {
     }
 }
 
+#[derive(Deserialize)]
+pub struct ExportOneProjectReqPath {
+    project_id: Uuid,
+}
+
+impl ExportOneProjectReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+}
+
+#[derive(MultipartForm)]
+pub struct ImportProjectReqForm {
+    file: TempFile,
+}
+
+impl ImportProjectReqForm {
+    pub fn file_path(&self) -> &Path {
+        self.file.file.path()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ImportProjectReqQuery {
+    // Keeps the ids from the archive instead of generating new ones
+    preserve_ids: Option<bool>,
+}
+
+impl ImportProjectReqQuery {
+    pub fn preserve_ids(&self) -> &Option<bool> {
+        &self.preserve_ids
+    }
+}
+
 #[derive(Serialize)]
 pub struct ProjectResJson {
     id: Uuid,
@@ -146,3 +219,423 @@ impl ProjectIDResJson {
         Self { id: *id }
     }
 }
+
+#[derive(Serialize)]
+pub struct ImportProjectResJson {
+    project: Option<ProjectResJson>,
+    errors: Vec<String>,
+}
+
+impl ImportProjectResJson {
+    pub fn new(project: Option<ProjectResJson>, errors: &Vec<String>) -> Self {
+        Self {
+            project,
+            errors: errors.to_vec(),
+        }
+    }
+}
+
+// The manifest at the start of a project archive. Records follow it as JSON Lines chunks under
+// records/{collection_id}/, and file contents as files/{file_id}.
+#[derive(Deserialize, Serialize)]
+pub struct ProjectArchiveJson {
+    version: u32,
+    project: ProjectArchiveProjectJson,
+    collections: Vec<ProjectArchiveCollectionJson>,
+    buckets: Vec<ProjectArchiveBucketJson>,
+    files: Vec<ProjectArchiveFileJson>,
+    tokens: Vec<ProjectArchiveTokenJson>,
+}
+
+impl ProjectArchiveJson {
+    pub fn new(
+        version: &u32,
+        project: ProjectArchiveProjectJson,
+        collections: Vec<ProjectArchiveCollectionJson>,
+        buckets: Vec<ProjectArchiveBucketJson>,
+        files: Vec<ProjectArchiveFileJson>,
+        tokens: Vec<ProjectArchiveTokenJson>,
+    ) -> Self {
+        Self {
+            version: *version,
+            project,
+            collections,
+            buckets,
+            files,
+            tokens,
+        }
+    }
+
+    pub fn version(&self) -> &u32 {
+        &self.version
+    }
+
+    pub fn project(&self) -> &ProjectArchiveProjectJson {
+        &self.project
+    }
+
+    pub fn collections(&self) -> &Vec<ProjectArchiveCollectionJson> {
+        &self.collections
+    }
+
+    pub fn buckets(&self) -> &Vec<ProjectArchiveBucketJson> {
+        &self.buckets
+    }
+
+    pub fn files(&self) -> &Vec<ProjectArchiveFileJson> {
+        &self.files
+    }
+
+    pub fn tokens(&self) -> &Vec<ProjectArchiveTokenJson> {
+        &self.tokens
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ProjectArchiveProjectJson {
+    id: Uuid,
+    admin_id: Uuid,
+    name: String,
+}
+
+impl ProjectArchiveProjectJson {
+    pub fn new(id: &Uuid, admin_id: &Uuid, name: &str) -> Self {
+        Self {
+            id: *id,
+            admin_id: *admin_id,
+            name: name.to_owned(),
+        }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn admin_id(&self) -> &Uuid {
+        &self.admin_id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ProjectArchiveCollectionJson {
+    id: Uuid,
+    #[serde(flatten)]
+    schema: CollectionSchemaJson,
+}
+
+impl ProjectArchiveCollectionJson {
+    pub fn new(id: &Uuid, schema: CollectionSchemaJson) -> Self {
+        Self { id: *id, schema }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn schema(&self) -> &CollectionSchemaJson {
+        &self.schema
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ProjectArchiveBucketJson {
+    id: Uuid,
+    name: String,
+    opt_ttl: Option<i64>,
+    max_file_size: Option<i64>,
+    allowed_content_types: Option<Vec<String>>,
+    quota_bytes: Option<i64>,
+}
+
+impl ProjectArchiveBucketJson {
+    pub fn new(
+        id: &Uuid,
+        name: &str,
+        opt_ttl: &Option<i64>,
+        max_file_size: &Option<i64>,
+        allowed_content_types: &Option<Vec<String>>,
+        quota_bytes: &Option<i64>,
+    ) -> Self {
+        Self {
+            id: *id,
+            name: name.to_owned(),
+            opt_ttl: *opt_ttl,
+            max_file_size: *max_file_size,
+            allowed_content_types: allowed_content_types.clone(),
+            quota_bytes: *quota_bytes,
+        }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn opt_ttl(&self) -> &Option<i64> {
+        &self.opt_ttl
+    }
+
+    pub fn max_file_size(&self) -> &Option<i64> {
+        &self.max_file_size
+    }
+
+    pub fn allowed_content_types(&self) -> &Option<Vec<String>> {
+        &self.allowed_content_types
+    }
+
+    pub fn quota_bytes(&self) -> &Option<i64> {
+        &self.quota_bytes
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ProjectArchiveFileJson {
+    id: Uuid,
+    bucket_id: Uuid,
+    created_by: Uuid,
+    file_name: String,
+    content_type: String,
+    size: i64,
+    public: bool,
+    checksum: Option<String>,
+}
+
+impl ProjectArchiveFileJson {
+    pub fn new(
+        id: &Uuid,
+        bucket_id: &Uuid,
+        created_by: &Uuid,
+        file_name: &str,
+        content_type: &str,
+        size: &i64,
+        public: &bool,
+        checksum: &Option<String>,
+    ) -> Self {
+        Self {
+            id: *id,
+            bucket_id: *bucket_id,
+            created_by: *created_by,
+            file_name: file_name.to_owned(),
+            content_type: content_type.to_owned(),
+            size: *size,
+            public: *public,
+            checksum: checksum.clone(),
+        }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn bucket_id(&self) -> &Uuid {
+        &self.bucket_id
+    }
+
+    pub fn created_by(&self) -> &Uuid {
+        &self.created_by
+    }
+
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    pub fn size(&self) -> &i64 {
+        &self.size
+    }
+
+    pub fn public(&self) -> &bool {
+        &self.public
+    }
+
+    pub fn checksum(&self) -> &Option<String> {
+        &self.checksum
+    }
+}
+
+// Tokens get a new secret when imported, so only their settings are kept
+#[derive(Deserialize, Serialize)]
+pub struct ProjectArchiveTokenJson {
+    id: Uuid,
+    name: String,
+    allow_anonymous: bool,
+    expired_at: Option<DateTime<Utc>>,
+    collection_rules: Vec<ProjectArchiveCollectionRuleJson>,
+    bucket_rules: Vec<ProjectArchiveBucketRuleJson>,
+}
+
+impl ProjectArchiveTokenJson {
+    pub fn new(
+        id: &Uuid,
+        name: &str,
+        allow_anonymous: &bool,
+        expired_at: &Option<DateTime<Utc>>,
+        collection_rules: Vec<ProjectArchiveCollectionRuleJson>,
+        bucket_rules: Vec<ProjectArchiveBucketRuleJson>,
+    ) -> Self {
+        Self {
+            id: *id,
+            name: name.to_owned(),
+            allow_anonymous: *allow_anonymous,
+            expired_at: *expired_at,
+            collection_rules,
+            bucket_rules,
+        }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn allow_anonymous(&self) -> &bool {
+        &self.allow_anonymous
+    }
+
+    pub fn expired_at(&self) -> &Option<DateTime<Utc>> {
+        &self.expired_at
+    }
+
+    pub fn collection_rules(&self) -> &Vec<ProjectArchiveCollectionRuleJson> {
+        &self.collection_rules
+    }
+
+    pub fn bucket_rules(&self) -> &Vec<ProjectArchiveBucketRuleJson> {
+        &self.bucket_rules
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ProjectArchiveCollectionRuleJson {
+    collection_id: Uuid,
+    find_one: String,
+    find_many: String,
+    insert_one: bool,
+    update_one: String,
+    delete_one: String,
+    readable_fields: Option<Vec<String>>,
+    writable_fields: Option<Vec<String>>,
+}
+
+impl ProjectArchiveCollectionRuleJson {
+    pub fn new(
+        collection_id: &Uuid,
+        find_one: &str,
+        find_many: &str,
+        insert_one: &bool,
+        update_one: &str,
+        delete_one: &str,
+        readable_fields: &Option<Vec<String>>,
+        writable_fields: &Option<Vec<String>>,
+    ) -> Self {
+        Self {
+            collection_id: *collection_id,
+            find_one: find_one.to_owned(),
+            find_many: find_many.to_owned(),
+            insert_one: *insert_one,
+            update_one: update_one.to_owned(),
+            delete_one: delete_one.to_owned(),
+            readable_fields: readable_fields.clone(),
+            writable_fields: writable_fields.clone(),
+        }
+    }
+
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+
+    pub fn find_one(&self) -> &str {
+        &self.find_one
+    }
+
+    pub fn find_many(&self) -> &str {
+        &self.find_many
+    }
+
+    pub fn insert_one(&self) -> &bool {
+        &self.insert_one
+    }
+
+    pub fn update_one(&self) -> &str {
+        &self.update_one
+    }
+
+    pub fn delete_one(&self) -> &str {
+        &self.delete_one
+    }
+
+    pub fn readable_fields(&self) -> &Option<Vec<String>> {
+        &self.readable_fields
+    }
+
+    pub fn writable_fields(&self) -> &Option<Vec<String>> {
+        &self.writable_fields
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ProjectArchiveBucketRuleJson {
+    bucket_id: Uuid,
+    find_one: String,
+    find_many: String,
+    insert_one: bool,
+    update_one: String,
+    delete_one: String,
+}
+
+impl ProjectArchiveBucketRuleJson {
+    pub fn new(
+        bucket_id: &Uuid,
+        find_one: &str,
+        find_many: &str,
+        insert_one: &bool,
+        update_one: &str,
+        delete_one: &str,
+    ) -> Self {
+        Self {
+            bucket_id: *bucket_id,
+            find_one: find_one.to_owned(),
+            find_many: find_many.to_owned(),
+            insert_one: *insert_one,
+            update_one: update_one.to_owned(),
+            delete_one: delete_one.to_owned(),
+        }
+    }
+
+    pub fn bucket_id(&self) -> &Uuid {
+        &self.bucket_id
+    }
+
+    pub fn find_one(&self) -> &str {
+        &self.find_one
+    }
+
+    pub fn find_many(&self) -> &str {
+        &self.find_many
+    }
+
+    pub fn insert_one(&self) -> &bool {
+        &self.insert_one
+    }
+
+    pub fn update_one(&self) -> &str {
+        &self.update_one
+    }
+
+    pub fn delete_one(&self) -> &str {
+        &self.delete_one
+    }
+}
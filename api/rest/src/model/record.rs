@@ -1,3 +1,6 @@
+use std::path::Path;
+
+use actix_multipart::form::{tempfile::TempFile, text::Text, MultipartForm};
 use ahash::{HashMap, HashSet};
 use anyhow::{Error, Result};
 use hb_dao::{
@@ -51,12 +54,17 @@ impl FindOneRecordReqPath {
 #[derive(Deserialize)]
 pub struct FindOneRecordReqQuery {
     fields: Option<HashSet<String>>,
+    includes: Option<HashSet<String>>,
 }
 
 impl FindOneRecordReqQuery {
     pub fn fields(&self) -> &Option<HashSet<String>> {
         &self.fields
     }
+
+    pub fn includes(&self) -> &Option<HashSet<String>> {
+        &self.includes
+    }
 }
 
 #[derive(Deserialize)]
@@ -82,6 +90,43 @@ impl UpdateOneRecordReqPath {
 
 pub type UpdateOneRecordReqJson = HashMap<String, Value>;
 
+#[derive(Deserialize)]
+pub struct IncrementCounterRecordReqPath {
+    project_id: Uuid,
+    collection_id: Uuid,
+    record_id: Uuid,
+    field: String,
+}
+
+impl IncrementCounterRecordReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+
+    pub fn record_id(&self) -> &Uuid {
+        &self.record_id
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+}
+
+#[derive(Deserialize)]
+pub struct IncrementCounterRecordReqJson {
+    delta: i64,
+}
+
+impl IncrementCounterRecordReqJson {
+    pub fn delta(&self) -> &i64 {
+        &self.delta
+    }
+}
+
 #[derive(Deserialize)]
 pub struct DeleteOneRecordReqPath {
     project_id: Uuid,
@@ -103,6 +148,27 @@ impl DeleteOneRecordReqPath {
     }
 }
 
+#[derive(Deserialize)]
+pub struct RestoreOneRecordReqPath {
+    project_id: Uuid,
+    collection_id: Uuid,
+    record_id: Uuid,
+}
+
+impl RestoreOneRecordReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+
+    pub fn record_id(&self) -> &Uuid {
+        &self.record_id
+    }
+}
+
 #[derive(Deserialize)]
 pub struct FindManyRecordReqPath {
     project_id: Uuid,
@@ -126,6 +192,7 @@ pub struct FindManyRecordReqJson {
     groups: Option<Vec<String>>,
     orders: Option<Vec<FindManyRecordOrderReqJson>>,
     limit: Option<i32>,
+    includes: Option<HashSet<String>>,
 }
 
 impl FindManyRecordReqJson {
@@ -148,6 +215,10 @@ impl FindManyRecordReqJson {
     pub fn limit(&self) -> &Option<i32> {
         &self.limit
     }
+
+    pub fn includes(&self) -> &Option<HashSet<String>> {
+        &self.includes
+    }
 }
 
 #[derive(Deserialize)]
@@ -162,12 +233,28 @@ impl FindManyRecordFiltersReqJson {
             } else if f.children.is_none() && f.field.is_none() {
                 return Err(Error::msg("Wrong filter format. If 'children' field does not exist, then 'field' field must exist"));
             }
-            let schema_field_kind = match &f.field {
+            let (field, path) = match &f.field {
+                Some(field) => {
+                    let (field, path) = field.split()?;
+                    (Some(field), path)
+                }
+                None => (None, None),
+            };
+            if let Some(path) = &path {
+                filters.push(Self::json_path_to_dao(
+                    f,
+                    field.as_ref().unwrap(),
+                    path,
+                    collection_data,
+                )?);
+                continue;
+            }
+            let schema_field_kind = match &field {
                 Some(field) => match collection_data.schema_fields().get(field) {
                     Some(field) => Some(field.kind()),
                     None => match field.as_str() {
                         "_id" | "_created_by" => Some(&ColumnKind::Uuid),
-                        "_updated_at" => Some(&ColumnKind::Timestamp),
+                        "_created_at" | "_updated_at" => Some(&ColumnKind::Timestamp),
                         _ => {
                             return Err(Error::msg(format!(
                                 "Field '{field}' is not exist in the collection",
@@ -178,16 +265,47 @@ impl FindManyRecordFiltersReqJson {
                 None => None,
             };
 
+            if f.op.to_uppercase() == "$NEAR" {
+                filters.push(Self::near_to_dao(
+                    field.as_ref().unwrap(),
+                    f,
+                    schema_field_kind,
+                )?);
+                continue;
+            }
+
+            // IN and the BETWEEN operators take their values as an array, converted one by one
+            if let (Some(field), Some(kind), Some(Value::Array(values))) =
+                (&field, schema_field_kind, &f.value)
+            {
+                if RecordFilter::takes_values(&f.op) && field != "_created_at" {
+                    let mut filter_values = Vec::with_capacity(values.len());
+                    for value in values {
+                        match ColumnValue::from_serde_json(kind, value) {
+                            Ok(value) => filter_values.push(value),
+                            Err(err) => {
+                                return Err(Error::msg(format!("Error in field '{field}': {err}")))
+                            }
+                        }
+                    }
+                    filters.push(RecordFilter::many(field, &f.op, &filter_values)?);
+                    continue;
+                }
+            }
+
             let value = if schema_field_kind.is_some() && f.value.is_some() {
-                match ColumnValue::from_serde_json(
-                    schema_field_kind.unwrap(),
-                    f.value.as_ref().unwrap(),
-                ) {
+                let mut schema_field_kind = *schema_field_kind.unwrap();
+                if f.op.to_uppercase() == "CONTAINS" {
+                    if let Some(element_kind) = schema_field_kind.element_kind() {
+                        schema_field_kind = element_kind;
+                    }
+                }
+                match ColumnValue::from_serde_json(&schema_field_kind, f.value.as_ref().unwrap()) {
                     Ok(value) => Some(value),
                     Err(err) => {
                         return Err(Error::msg(format!(
                             "Error in field '{}': {}",
-                            f.field.as_ref().unwrap(),
+                            field.as_ref().unwrap(),
                             err
                         )));
                     }
@@ -195,8 +313,12 @@ impl FindManyRecordFiltersReqJson {
             } else {
                 None
             };
+            if field.as_deref() == Some("_created_at") {
+                filters.push(RecordFilter::created_at(&f.op, &value)?);
+                continue;
+            }
             filters.push(RecordFilter::new(
-                &f.field,
+                &field,
                 &f.op,
                 &value,
                 &if let Some(children) = &f.children {
@@ -204,24 +326,141 @@ impl FindManyRecordFiltersReqJson {
                 } else {
                     None
                 },
-            ));
+            )?);
         }
         Ok(RecordFilters::new(&filters))
     }
+
+    fn json_path_to_dao(
+        f: &FindManyRecordFilterReqJson,
+        field: &str,
+        path: &Vec<String>,
+        collection_data: &CollectionDao,
+    ) -> Result<RecordFilter> {
+        match collection_data.schema_fields().get(field) {
+            Some(schema_field) => {
+                if *schema_field.kind() != ColumnKind::Json {
+                    return Err(Error::msg(format!(
+                        "JSON path filter can only be used on a field of type 'json', but field '{field}' is not"
+                    )));
+                }
+            }
+            None => {
+                return Err(Error::msg(format!(
+                    "Field '{field}' is not exist in the collection",
+                )))
+            }
+        }
+        if path.is_empty() || path.iter().any(|key| key.is_empty()) {
+            return Err(Error::msg(format!(
+                "Error in field '{field}': JSON path must not be empty"
+            )));
+        }
+        let value = match &f.value {
+            Some(Value::String(value)) => Some(value.to_owned()),
+            Some(Value::Null) | None => None,
+            Some(Value::Array(_)) | Some(Value::Object(_)) => {
+                return Err(Error::msg(format!(
+                    "Error in field '{field}': JSON path filter value must be a string, number, or boolean"
+                )))
+            }
+            Some(value) => Some(value.to_string()),
+        };
+        RecordFilter::json_path(field, path, &f.op, &value)
+    }
+
+    fn near_to_dao(
+        field: &str,
+        f: &FindManyRecordFilterReqJson,
+        schema_field_kind: Option<&ColumnKind>,
+    ) -> Result<RecordFilter> {
+        if schema_field_kind != Some(&ColumnKind::Point) {
+            return Err(Error::msg(format!(
+                "Operator '$near' can only be used on a field of type 'point', but field '{field}' is not"
+            )));
+        }
+        let value = match &f.value {
+            Some(value) => match serde_json::from_value::<NearReqJson>(value.clone()) {
+                Ok(value) => value,
+                Err(err) => return Err(Error::msg(format!("Error in field '{field}': {err}"))),
+            },
+            None => {
+                return Err(Error::msg(format!(
+                    "Error in field '{field}': The 'value' field is required"
+                )))
+            }
+        };
+        let (lat, lng) = match ColumnValue::validate_point(value.lat, value.lng) {
+            Ok(point) => point,
+            Err(err) => return Err(Error::msg(format!("Error in field '{field}': {err}"))),
+        };
+        if !value.radius.is_finite() || value.radius < 0.0 {
+            return Err(Error::msg(format!(
+                "Error in field '{field}': 'radius' must be a non-negative number of meters"
+            )));
+        }
+        Ok(RecordFilter::near(field, &lat, &lng, &value.radius))
+    }
+}
+
+#[derive(Deserialize)]
+struct NearReqJson {
+    lat: f64,
+    lng: f64,
+    radius: f64,
 }
 
 #[derive(Deserialize)]
 pub struct FindManyRecordFilterReqJson {
-    field: Option<String>,
+    field: Option<FindManyRecordFilterFieldReqJson>,
     op: String,
     value: Option<Value>,
     children: Option<FindManyRecordFiltersReqJson>,
 }
 
+// A filter field is either a plain name, which may use the `metadata->'address'->>'city'`
+// path syntax, or the structured `{"field": "metadata", "path": ["address", "city"]}` form
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum FindManyRecordFilterFieldReqJson {
+    Name(String),
+    Path { field: String, path: Vec<String> },
+}
+
+impl FindManyRecordFilterFieldReqJson {
+    fn split(&self) -> Result<(String, Option<Vec<String>>)> {
+        match self {
+            Self::Name(name) => {
+                let mut segments = name.split("->");
+                let field = segments.next().unwrap().trim().to_owned();
+                let mut path = Vec::new();
+                for segment in segments {
+                    let key = segment.strip_prefix('>').unwrap_or(segment).trim();
+                    match key.strip_prefix('\'').and_then(|k| k.strip_suffix('\'')) {
+                        Some(key) => path.push(key.to_owned()),
+                        None => {
+                            return Err(Error::msg(format!(
+                                "Wrong filter format. JSON path keys in field '{name}' must be quoted, for example metadata->>'city'"
+                            )))
+                        }
+                    }
+                }
+                if path.is_empty() {
+                    Ok((field, None))
+                } else {
+                    Ok((field, Some(path)))
+                }
+            }
+            Self::Path { field, path } => Ok((field.to_owned(), Some(path.to_owned()))),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct FindManyRecordOrderReqJson {
     field: String,
     kind: String,
+    near: Option<FindManyRecordOrderNearReqJson>,
 }
 
 impl FindManyRecordOrderReqJson {
@@ -232,6 +471,118 @@ impl FindManyRecordOrderReqJson {
     pub fn kind(&self) -> &str {
         &self.kind
     }
+
+    pub fn near(&self) -> &Option<FindManyRecordOrderNearReqJson> {
+        &self.near
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FindManyRecordOrderNearReqJson {
+    lat: f64,
+    lng: f64,
+}
+
+impl FindManyRecordOrderNearReqJson {
+    pub fn lat(&self) -> &f64 {
+        &self.lat
+    }
+
+    pub fn lng(&self) -> &f64 {
+        &self.lng
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportRecordReqPath {
+    project_id: Uuid,
+    collection_id: Uuid,
+}
+
+impl ExportRecordReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportRecordReqQuery {
+    format: Option<String>,
+}
+
+impl ExportRecordReqQuery {
+    pub fn format(&self) -> &Option<String> {
+        &self.format
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportRecordReqJson {
+    fields: Option<Vec<String>>,
+    filters: Option<FindManyRecordFiltersReqJson>,
+    orders: Option<Vec<FindManyRecordOrderReqJson>>,
+    limit: Option<i32>,
+}
+
+impl ExportRecordReqJson {
+    pub fn fields(&self) -> &Option<Vec<String>> {
+        &self.fields
+    }
+
+    pub fn filters(&self) -> &Option<FindManyRecordFiltersReqJson> {
+        &self.filters
+    }
+
+    pub fn orders(&self) -> &Option<Vec<FindManyRecordOrderReqJson>> {
+        &self.orders
+    }
+
+    pub fn limit(&self) -> &Option<i32> {
+        &self.limit
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ImportRecordReqPath {
+    project_id: Uuid,
+    collection_id: Uuid,
+}
+
+impl ImportRecordReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+}
+
+#[derive(MultipartForm)]
+pub struct ImportRecordReqForm {
+    file: TempFile,
+    format: Option<Text<String>>,
+}
+
+impl ImportRecordReqForm {
+    pub fn file_path(&self) -> &Path {
+        self.file.file.path()
+    }
+
+    pub fn file_name(&self) -> &Option<String> {
+        &self.file.file_name
+    }
+
+    pub fn format(&self) -> Option<String> {
+        match &self.format {
+            Some(format) => Some(format.0.to_owned()),
+            None => None,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -256,3 +607,37 @@ impl DeleteRecordResJson {
         Self { id: *id }
     }
 }
+
+#[derive(Serialize)]
+pub struct ImportRecordResJson {
+    imported: usize,
+    errors: Vec<ImportRecordErrorResJson>,
+}
+
+impl ImportRecordResJson {
+    pub fn new(imported: &usize, errors: &Vec<ImportRecordErrorResJson>) -> Self {
+        Self {
+            imported: *imported,
+            errors: errors.to_vec(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct ImportRecordErrorResJson {
+    line: usize,
+    reason: String,
+}
+
+impl ImportRecordErrorResJson {
+    pub fn new(line: &usize, reason: &str) -> Self {
+        Self {
+            line: *line,
+            reason: reason.to_owned(),
+        }
+    }
+
+    pub fn line(&self) -> &usize {
+        &self.line
+    }
+}
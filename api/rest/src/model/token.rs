@@ -96,6 +96,22 @@ impl UpdateOneTokenReqJson {
     }
 }
 
+#[derive(Deserialize)]
+pub struct RegenerateOneTokenReqPath {
+    project_id: Uuid,
+    token_id: Uuid,
+}
+
+impl RegenerateOneTokenReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn token_id(&self) -> &Uuid {
+        &self.token_id
+    }
+}
+
 #[derive(Deserialize)]
 pub struct DeleteOneTokenReqPath {
     project_id: Uuid,
@@ -123,6 +139,19 @@ impl FindManyTokenReqPath {
     }
 }
 
+#[derive(Deserialize)]
+pub struct FindManyTokenReqQuery {
+    // Only lists the tokens that haven't been used for at least this many days, including the
+    // ones that were never used
+    unused_for_days: Option<u32>,
+}
+
+impl FindManyTokenReqQuery {
+    pub fn unused_for_days(&self) -> &Option<u32> {
+        &self.unused_for_days
+    }
+}
+
 #[derive(Serialize)]
 pub struct TokenResJson {
     id: Uuid,
@@ -130,9 +159,13 @@ pub struct TokenResJson {
     updated_at: DateTime<Utc>,
     project_id: Uuid,
     name: String,
-    token: String,
+    // Only returned when the token is created, afterwards only its hash is stored
+    token: Option<String>,
     allow_anonymous: bool,
     expired_at: Option<DateTime<Utc>>,
+    days_remaining: Option<i64>,
+    last_used_at: Option<DateTime<Utc>>,
+    request_count: i64,
 }
 
 impl TokenResJson {
@@ -142,9 +175,11 @@ impl TokenResJson {
         updated_at: &DateTime<Utc>,
         project_id: &Uuid,
         name: &str,
-        token: &str,
+        token: &Option<String>,
         allow_anonymous: &bool,
         expired_at: &Option<DateTime<Utc>>,
+        last_used_at: &Option<DateTime<Utc>>,
+        request_count: &i64,
     ) -> Self {
         Self {
             id: *id,
@@ -155,6 +190,10 @@ impl TokenResJson {
             token: token.to_owned(),
             allow_anonymous: *allow_anonymous,
             expired_at: *expired_at,
+            days_remaining: expired_at
+                .map(|expired_at| (expired_at - Utc::now()).num_days().max(0)),
+            last_used_at: *last_used_at,
+            request_count: *request_count,
         }
     }
 }
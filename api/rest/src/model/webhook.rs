@@ -0,0 +1,187 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct InsertOneWebhookReqPath {
+    project_id: Uuid,
+}
+
+impl InsertOneWebhookReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct InsertOneWebhookReqJson {
+    collection_id: Uuid,
+    url: String,
+    events: Vec<String>,
+    secret: String,
+    enabled: Option<bool>,
+}
+
+impl InsertOneWebhookReqJson {
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn events(&self) -> &Vec<String> {
+        &self.events
+    }
+
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    pub fn enabled(&self) -> &Option<bool> {
+        &self.enabled
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FindOneWebhookReqPath {
+    project_id: Uuid,
+    webhook_id: Uuid,
+}
+
+impl FindOneWebhookReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn webhook_id(&self) -> &Uuid {
+        &self.webhook_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UpdateOneWebhookReqPath {
+    project_id: Uuid,
+    webhook_id: Uuid,
+}
+
+impl UpdateOneWebhookReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn webhook_id(&self) -> &Uuid {
+        &self.webhook_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UpdateOneWebhookReqJson {
+    url: Option<String>,
+    events: Option<Vec<String>>,
+    secret: Option<String>,
+    enabled: Option<bool>,
+}
+
+impl UpdateOneWebhookReqJson {
+    pub fn url(&self) -> &Option<String> {
+        &self.url
+    }
+
+    pub fn events(&self) -> &Option<Vec<String>> {
+        &self.events
+    }
+
+    pub fn secret(&self) -> &Option<String> {
+        &self.secret
+    }
+
+    pub fn enabled(&self) -> &Option<bool> {
+        &self.enabled
+    }
+
+    pub fn is_all_none(&self) -> bool {
+        self.url.is_none()
+            && self.events.is_none()
+            && self.secret.is_none()
+            && self.enabled.is_none()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeleteOneWebhookReqPath {
+    project_id: Uuid,
+    webhook_id: Uuid,
+}
+
+impl DeleteOneWebhookReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn webhook_id(&self) -> &Uuid {
+        &self.webhook_id
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FindManyWebhookReqPath {
+    project_id: Uuid,
+}
+
+impl FindManyWebhookReqPath {
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+}
+
+#[derive(Serialize)]
+pub struct WebhookResJson {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    project_id: Uuid,
+    collection_id: Uuid,
+    url: String,
+    events: Vec<String>,
+    secret: String,
+    enabled: bool,
+}
+
+impl WebhookResJson {
+    pub fn new(
+        id: &Uuid,
+        created_at: &DateTime<Utc>,
+        updated_at: &DateTime<Utc>,
+        project_id: &Uuid,
+        collection_id: &Uuid,
+        url: &str,
+        events: &Vec<String>,
+        secret: &str,
+        enabled: &bool,
+    ) -> Self {
+        Self {
+            id: *id,
+            created_at: *created_at,
+            updated_at: *updated_at,
+            project_id: *project_id,
+            collection_id: *collection_id,
+            url: url.to_owned(),
+            events: events.to_vec(),
+            secret: secret.to_owned(),
+            enabled: *enabled,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DeleteWebhookResJson {
+    id: Uuid,
+}
+
+impl DeleteWebhookResJson {
+    pub fn new(id: &Uuid) -> Self {
+        Self { id: *id }
+    }
+}
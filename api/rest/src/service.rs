@@ -1,14 +1,18 @@
 pub mod admin;
+pub mod audit;
 pub mod auth;
 pub mod bucket;
 pub mod bucket_rule;
 pub mod collection;
 pub mod collection_rule;
 pub mod file;
+pub mod health;
 pub mod info;
 pub mod log;
+pub mod member;
 pub mod project;
 pub mod record;
 pub mod root;
 pub mod token;
 pub mod user;
+pub mod webhook;
@@ -1,23 +1,29 @@
 use actix_web::{http::StatusCode, web, HttpResponse};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
 use hb_dao::admin::AdminDao;
 use hb_token_jwt::claim::ClaimId;
+use tokio::sync::oneshot;
 
 use crate::{
     context::ApiRestCtx,
     model::{
-        admin::{AdminResJson, DeleteAdminResJson, UpdateOneAdminReqJson},
+        admin::{
+            AdminResJson, BackupResJson, ConfigReloadResJson, DeleteAdminResJson,
+            UpdateOneAdminReqJson,
+        },
         Response,
     },
+    util::api_auth::ApiAuth,
 };
 
 pub fn admin_api(cfg: &mut web::ServiceConfig) {
     cfg.route("/admin", web::get().to(find_one))
         .route("/admin", web::patch().to(update_one))
-        .route("/admin", web::delete().to(delete_one));
+        .route("/admin", web::delete().to(delete_one))
+        .route("/admin/backup/run", web::post().to(run_backup))
+        .route("/admin/config/reload", web::post().to(reload_config));
 }
 
-async fn find_one(ctx: web::Data<ApiRestCtx>, auth: BearerAuth) -> HttpResponse {
+async fn find_one(ctx: web::Data<ApiRestCtx>, auth: ApiAuth) -> HttpResponse {
     let token = auth.token();
 
     let token_claim = match ctx.token().jwt().decode(token) {
@@ -57,7 +63,7 @@ async fn find_one(ctx: web::Data<ApiRestCtx>, auth: BearerAuth) -> HttpResponse
 
 async fn update_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     data: web::Json<UpdateOneAdminReqJson>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -116,7 +122,7 @@ async fn update_one(
     )
 }
 
-async fn delete_one(ctx: web::Data<ApiRestCtx>, auth: BearerAuth) -> HttpResponse {
+async fn delete_one(ctx: web::Data<ApiRestCtx>, auth: ApiAuth) -> HttpResponse {
     let token = auth.token();
 
     let token_claim = match ctx.token().jwt().decode(token) {
@@ -152,3 +158,116 @@ async fn delete_one(ctx: web::Data<ApiRestCtx>, auth: BearerAuth) -> HttpRespons
         &DeleteAdminResJson::new(admin_data.id()),
     )
 }
+
+// Backs up every project right away instead of waiting for the schedule, returning once it's
+// written
+async fn run_backup(ctx: web::Data<ApiRestCtx>, auth: ApiAuth) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    match token_claim.id() {
+        ClaimId::Admin(id) => {
+            if let Err(err) = AdminDao::db_select(ctx.dao().db(), id).await {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                );
+            }
+            if !ctx.operator_admin_ids().contains(id) {
+                return Response::error_raw(
+                    &StatusCode::FORBIDDEN,
+                    "Only admins listed in auth.operator_admin_ids can do this",
+                );
+            }
+        }
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    }
+
+    let backup = match ctx.backup() {
+        Some(backup) => backup,
+        None => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Backups aren't configured on this server",
+            )
+        }
+    };
+
+    match backup.backup().await {
+        Ok(run) => Response::data(
+            &StatusCode::OK,
+            &None,
+            &BackupResJson::new(run.name(), run.project_count()),
+        ),
+        Err(err) => Response::error_raw(
+            &StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("Backup run failed: {err}"),
+        ),
+    }
+}
+
+// Reads the config again, the same as a SIGHUP, and applies what can change without a restart
+async fn reload_config(ctx: web::Data<ApiRestCtx>, auth: ApiAuth) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    match token_claim.id() {
+        ClaimId::Admin(id) => {
+            if let Err(err) = AdminDao::db_select(ctx.dao().db(), id).await {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                );
+            }
+            if !ctx.operator_admin_ids().contains(id) {
+                return Response::error_raw(
+                    &StatusCode::FORBIDDEN,
+                    "Only admins listed in auth.operator_admin_ids can do this",
+                );
+            }
+        }
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    }
+
+    let (reply_sender, reply_receiver) = oneshot::channel();
+    if ctx.config_reload_sender().send(reply_sender).await.is_err() {
+        return Response::error_raw(
+            &StatusCode::INTERNAL_SERVER_ERROR,
+            "Config reload isn't running",
+        );
+    }
+
+    match reply_receiver.await {
+        Ok(Ok(restart_required)) => Response::data(
+            &StatusCode::OK,
+            &None,
+            &ConfigReloadResJson::new(&restart_required),
+        ),
+        Ok(Err(problems)) => Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            &format!("Invalid configuration: {}", problems.join("; ")),
+        ),
+        Err(_) => Response::error_raw(
+            &StatusCode::INTERNAL_SERVER_ERROR,
+            "Config reload stopped before replying",
+        ),
+    }
+}
@@ -0,0 +1,123 @@
+use actix_web::{http::StatusCode, web, HttpResponse};
+use hb_dao::{
+    admin::AdminDao,
+    audit::{AuditDao, AuditEntity},
+    project::ProjectDao,
+};
+use hb_token_jwt::claim::ClaimId;
+
+use crate::{
+    context::ApiRestCtx,
+    model::{
+        audit::{AuditResJson, FindManyAuditReqQuery},
+        PaginationRes, Response,
+    },
+    util::{
+        api_auth::ApiAuth,
+        project_access::{authorize_project, ProjectAccess},
+    },
+};
+
+pub fn audit_api(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/audit", web::get().to(find_many));
+}
+
+// Every admin that can read the project sees the changes made to it by any of its admins
+async fn find_many(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    query: web::Query<FindManyAuditReqQuery>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let project_data = match ProjectDao::db_select(ctx.dao().db(), query.project_id()).await {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
+    }
+
+    let entity_type = match query.entity_type() {
+        Some(entity_type) => match AuditEntity::from_str(entity_type) {
+            Ok(entity_type) => Some(entity_type),
+            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        },
+        None => None,
+    };
+
+    let (audits_data, total) = match AuditDao::db_select_many_by_project_id(
+        ctx.dao().db(),
+        query.project_id(),
+        &entity_type,
+        query.entity_id(),
+        query.from(),
+        query.to(),
+        query.before_id(),
+        query.limit(),
+    )
+    .await
+    {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    let total = match usize::try_from(total) {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    Response::data(
+        &StatusCode::OK,
+        &Some(PaginationRes::new(&audits_data.len(), &total)),
+        &audits_data
+            .iter()
+            .map(|data| {
+                AuditResJson::new(
+                    data.id(),
+                    data.created_at(),
+                    data.admin_id(),
+                    data.project_id(),
+                    data.action().to_str(),
+                    data.entity_type().to_str(),
+                    data.entity_id(),
+                    data.diff(),
+                    data.source_ip(),
+                )
+            })
+            .collect::<Vec<_>>(),
+    )
+}
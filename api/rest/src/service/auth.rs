@@ -1,16 +1,25 @@
-use std::str::FromStr;
+use std::{net::IpAddr, str::FromStr};
 
-use actix_web::{http::StatusCode, web, HttpResponse, HttpResponseBuilder};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
+use actix_web::{
+    http::{
+        header::{self, HeaderValue},
+        StatusCode,
+    },
+    web, HttpRequest, HttpResponse, HttpResponseBuilder,
+};
 use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
+use chrono::Utc;
 use hb_api_websocket::message::{MessageKind as WebSocketMessageKind, Target as WebSocketTarget};
 use hb_dao::{
     admin::AdminDao,
     admin_password_reset::AdminPasswordResetDao,
     collection::CollectionDao,
+    error::DaoError,
     log::{LogDao, LogKind},
+    project::ProjectDao,
     record::{RecordDao, RecordFilter, RecordFilters, RecordPagination},
     registration::RegistrationDao,
+    sign_in_attempt::SignInAttemptDao,
     token::TokenDao,
     value::ColumnValue,
 };
@@ -22,19 +31,27 @@ use validator::Validate;
 use crate::{
     model::{
         auth::{
-            AuthTokenResJson, ConfirmPasswordResetReqJson, ConfirmPasswordResetResJson,
-            MqttAuthenticationReqJson, MqttAuthenticationResJson, MqttAuthorizationReqJson,
-            MqttAuthorizationResJson, PasswordBasedReqJson, RegisterReqJson, RegisterResJson,
-            RequestPasswordResetReqJson, RequestPasswordResetResJson, TokenBasedReqJson,
-            VerifyRegistrationReqJson, VerifyRegistrationResJson,
+            AuthTokenResJson, ChangePasswordReqJson, ChangePasswordResJson,
+            ConfirmPasswordResetReqJson, ConfirmPasswordResetResJson, ConfirmTotpReqJson,
+            ConfirmTotpResJson, DisableTotpReqJson, DisableTotpResJson, MqttAuthenticationReqJson,
+            MqttAuthenticationResJson, MqttAuthorizationReqJson, MqttAuthorizationResJson,
+            PasswordBasedReqJson, RegisterReqJson, RegisterResJson, RequestPasswordResetReqJson,
+            RequestPasswordResetResJson, SetupTotpResJson, TokenBasedReqJson, TotpChallengeResJson,
+            VerifyRegistrationReqJson, VerifyRegistrationResJson, VerifyTotpReqJson,
         },
         log::LogResJson,
         Response,
     },
-    util::ws_broadcast::websocket_broadcast,
+    util::{
+        api_auth::ApiAuth, client_ip::client_ip, password_policy::check_password_strength,
+        ws_broadcast::websocket_broadcast,
+    },
     ApiRestCtx,
 };
 
+// Seconds the client has to complete the two-factor challenge after signing in with a password
+const TOTP_CHALLENGE_TTL: i64 = 300;
+
 pub fn auth_api(cfg: &mut web::ServiceConfig) {
     cfg.route("/auth/token", web::get().to(token))
         .route("/auth/register", web::post().to(register))
@@ -43,6 +60,10 @@ pub fn auth_api(cfg: &mut web::ServiceConfig) {
             web::post().to(verify_registration),
         )
         .route("/auth/password-based", web::post().to(password_based))
+        .route("/auth/2fa/setup", web::post().to(setup_totp))
+        .route("/auth/2fa/confirm", web::post().to(confirm_totp))
+        .route("/auth/2fa/disable", web::post().to(disable_totp))
+        .route("/auth/2fa/verify", web::post().to(verify_totp))
         .route("/auth/token-based", web::post().to(token_based))
         .route(
             "/auth/mqtt_authentication",
@@ -59,10 +80,11 @@ pub fn auth_api(cfg: &mut web::ServiceConfig) {
         .route(
             "/auth/confirm-password-reset",
             web::post().to(confirm_password_reset),
-        );
+        )
+        .route("/auth/password/change", web::post().to(change_password));
 }
 
-async fn token(ctx: web::Data<ApiRestCtx>, auth: BearerAuth) -> HttpResponse {
+async fn token(ctx: web::Data<ApiRestCtx>, auth: ApiAuth) -> HttpResponse {
     let token = auth.token();
 
     let token_claim = match ctx.token().jwt().decode(token) {
@@ -80,7 +102,10 @@ async fn token(ctx: web::Data<ApiRestCtx>, auth: BearerAuth) -> HttpResponse {
             }
         }
         ClaimId::Token(id, _) => {
-            if let Err(err) = TokenDao::db_select(ctx.dao().db(), id).await {
+            if let Err(err) = TokenDao::db_select_unexpired(ctx.dao().db(), id).await {
+                if DaoError::is_expired(&err) {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string());
+                }
                 return Response::error_raw(
                     &StatusCode::BAD_REQUEST,
                     &format!("Failed to get token data: {err}"),
@@ -131,6 +156,10 @@ async fn register(ctx: web::Data<ApiRestCtx>, data: web::Json<RegisterReqJson>)
         return Response::error_raw(&StatusCode::BAD_REQUEST, "Account has been registered");
     };
 
+    if let Err(res) = check_password_strength(ctx.password_policy(), data.password()) {
+        return res;
+    }
+
     let password_hash = match ctx
         .hash()
         .argon2()
@@ -235,6 +264,7 @@ async fn verify_registration(
 
 async fn password_based(
     ctx: web::Data<ApiRestCtx>,
+    req: HttpRequest,
     data: web::Json<PasswordBasedReqJson>,
 ) -> HttpResponse {
     if let Err(err) = data.validate() {
@@ -242,12 +272,203 @@ async fn password_based(
     }
 
     let email = data.email().to_lowercase();
+    let ip = client_ip(&req);
 
-    let admin_data = match AdminDao::db_select_by_email(ctx.dao().db(), &email).await {
+    let mut attempts_data = match select_sign_in_attempts(&ctx, &email, &ip).await {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(res) => return res,
+    };
+
+    let mut admin_data = match AdminDao::db_select_by_email(ctx.dao().db(), &email).await {
+        Ok(data) => data,
+        Err(err) => {
+            return sign_in_failed(
+                &ctx,
+                &mut attempts_data,
+                None,
+                &ip,
+                Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+            )
+            .await
+        }
+    };
+
+    if let Err(err) = ctx
+        .hash()
+        .argon2()
+        .verify_password(data.password(), admin_data.password_hash())
+    {
+        return sign_in_failed(
+            &ctx,
+            &mut attempts_data,
+            Some(&admin_data),
+            &ip,
+            Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        )
+        .await;
+    }
+
+    // Passwords hashed with the configured salt are moved over to a salt of their own
+    if ctx.hash().argon2().needs_rehash(admin_data.password_hash()) {
+        match ctx
+            .hash()
+            .argon2()
+            .hash_password(data.password().as_bytes())
+        {
+            Ok(password_hash) => {
+                admin_data.set_password_hash(&password_hash);
+                if let Err(err) = admin_data.db_update(ctx.dao().db()).await {
+                    hb_log::error(
+                        None,
+                        &format!("[ApiRestServer] Error when rehashing admin password: {err}"),
+                    );
+                }
+            }
+            Err(err) => hb_log::error(
+                None,
+                &format!("[ApiRestServer] Error when rehashing admin password: {err}"),
+            ),
+        }
+    }
+
+    // The token is only issued once the challenge is completed with a code from /auth/2fa/verify
+    if admin_data.is_totp_enabled() {
+        let expires_at = Utc::now().timestamp() + TOTP_CHALLENGE_TTL;
+        let signature = match ctx
+            .token()
+            .jwt()
+            .sign(&totp_challenge_message(admin_data.id(), &expires_at))
+        {
+            Ok(signature) => signature,
+            Err(err) => {
+                return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+            }
+        };
+
+        return Response::data(
+            &StatusCode::OK,
+            &None,
+            &TotpChallengeResJson::new(
+                &format!("{}.{expires_at}.{signature}", admin_data.id()),
+                &expires_at,
+            ),
+        );
+    }
+
+    if let Err(res) = sign_in_succeeded(&ctx, &email).await {
+        return res;
+    }
+
+    let token = match ctx.token().jwt().encode(&ClaimId::Admin(*admin_data.id())) {
+        Ok(token) => token,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    Response::data(&StatusCode::OK, &None, &AuthTokenResJson::new(&token))
+}
+
+async fn setup_totp(ctx: web::Data<ApiRestCtx>, auth: ApiAuth) -> HttpResponse {
+    let mut admin_data = match admin_from_auth(&ctx, &auth).await {
+        Ok(data) => data,
+        Err(res) => return res,
+    };
+
+    let totp = match ctx.token().totp() {
+        Some(totp) => totp,
+        None => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Two-factor authentication is not configured on this server",
+            )
+        }
+    };
+
+    if admin_data.is_totp_enabled() {
+        return Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Two-factor authentication is already enabled",
+        );
+    }
+
+    let secret = match totp.generate_secret() {
+        Ok(secret) => secret,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    let encrypted_secret = match totp.encrypt_secret(&secret) {
+        Ok(secret) => secret,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    // Setting up again before confirming replaces the pending secret
+    admin_data.set_totp_secret(&encrypted_secret);
+    if let Err(err) = admin_data.db_update(ctx.dao().db()).await {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &SetupTotpResJson::new(&totp.uri(&secret, admin_data.email())),
+    )
+}
+
+async fn confirm_totp(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    data: web::Json<ConfirmTotpReqJson>,
+) -> HttpResponse {
+    let mut admin_data = match admin_from_auth(&ctx, &auth).await {
+        Ok(data) => data,
+        Err(res) => return res,
     };
 
+    if admin_data.is_totp_enabled() {
+        return Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Two-factor authentication is already enabled",
+        );
+    }
+
+    if let Err(res) = verify_totp_code(&ctx, &mut admin_data, data.code()) {
+        return res;
+    }
+
+    let backup_codes = admin_data.enable_totp();
+    if let Err(err) = admin_data.db_update(ctx.dao().db()).await {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &ConfirmTotpResJson::new(&backup_codes),
+    )
+}
+
+async fn disable_totp(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    data: web::Json<DisableTotpReqJson>,
+) -> HttpResponse {
+    let mut admin_data = match admin_from_auth(&ctx, &auth).await {
+        Ok(data) => data,
+        Err(res) => return res,
+    };
+
+    if !admin_data.is_totp_enabled() {
+        return Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Two-factor authentication is not enabled",
+        );
+    }
+
     if let Err(err) = ctx
         .hash()
         .argon2()
@@ -256,6 +477,94 @@ async fn password_based(
         return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string());
     }
 
+    if let Err(res) = verify_totp_code(&ctx, &mut admin_data, data.code()) {
+        return res;
+    }
+
+    admin_data.disable_totp();
+    if let Err(err) = admin_data.db_update(ctx.dao().db()).await {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &DisableTotpResJson::new(admin_data.id()),
+    )
+}
+
+async fn verify_totp(
+    ctx: web::Data<ApiRestCtx>,
+    req: HttpRequest,
+    data: web::Json<VerifyTotpReqJson>,
+) -> HttpResponse {
+    let mut challenge = data.challenge().splitn(3, '.');
+    let (admin_id, expires_at, signature) =
+        match (challenge.next(), challenge.next(), challenge.next()) {
+            (Some(admin_id), Some(expires_at), Some(signature)) => {
+                match (Uuid::from_str(admin_id), expires_at.parse::<i64>()) {
+                    (Ok(admin_id), Ok(expires_at)) => (admin_id, expires_at, signature),
+                    _ => {
+                        return Response::error_raw(
+                            &StatusCode::BAD_REQUEST,
+                            "Challenge is malformed",
+                        )
+                    }
+                }
+            }
+            _ => return Response::error_raw(&StatusCode::BAD_REQUEST, "Challenge is malformed"),
+        };
+
+    if expires_at < Utc::now().timestamp() {
+        return Response::error_raw(&StatusCode::UNAUTHORIZED, "Challenge has expired");
+    }
+
+    if !ctx
+        .token()
+        .jwt()
+        .verify_signature(&totp_challenge_message(&admin_id, &expires_at), signature)
+    {
+        return Response::error_raw(&StatusCode::UNAUTHORIZED, "Challenge signature is invalid");
+    }
+
+    let mut admin_data = match AdminDao::db_select(ctx.dao().db(), &admin_id).await {
+        Ok(data) => data,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    if !admin_data.is_totp_enabled() {
+        return Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Two-factor authentication is not enabled",
+        );
+    }
+
+    // Wrong codes count as failed sign-ins too, otherwise a known password would leave the code
+    // open to guessing
+    let ip = client_ip(&req);
+    let mut attempts_data = match select_sign_in_attempts(&ctx, admin_data.email(), &ip).await {
+        Ok(data) => data,
+        Err(res) => return res,
+    };
+
+    let used_backup_code = match verify_totp_code(&ctx, &mut admin_data, data.code()) {
+        Ok(used_backup_code) => used_backup_code,
+        Err(res) if res.status() == StatusCode::BAD_REQUEST => {
+            return sign_in_failed(&ctx, &mut attempts_data, Some(&admin_data), &ip, res).await
+        }
+        Err(res) => return res,
+    };
+
+    if used_backup_code {
+        if let Err(err) = admin_data.db_update(ctx.dao().db()).await {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+        }
+    }
+
+    if let Err(res) = sign_in_succeeded(&ctx, admin_data.email()).await {
+        return res;
+    }
+
     let token = match ctx.token().jwt().encode(&ClaimId::Admin(*admin_data.id())) {
         Ok(token) => token,
         Err(err) => {
@@ -270,15 +579,22 @@ async fn token_based(
     ctx: web::Data<ApiRestCtx>,
     data: web::Json<TokenBasedReqJson>,
 ) -> HttpResponse {
-    let token_data = match TokenDao::db_select(ctx.dao().db(), data.token_id()).await {
+    let mut token_data = match TokenDao::db_select(ctx.dao().db(), data.token_id()).await {
         Ok(data) => data,
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if token_data.token() != data.token() {
+    if !token_data.is_token_matched(data.token()) {
         return Response::error_raw(&StatusCode::BAD_REQUEST, "Token doesn't match");
     }
 
+    if let Err(err) = token_data.check_expired(ctx.dao().db()).await {
+        if DaoError::is_expired(&err) {
+            return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string());
+        }
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+
     let token = if let Some(collection_id) = data.collection_id() {
         if data.data().is_none() {
             return Response::error_raw(&StatusCode::BAD_REQUEST, "Field data must exist");
@@ -329,12 +645,20 @@ async fn token_based(
                                     )
                                 }
                             };
-                        record_filter_childs.push(RecordFilter::new(
+                        match RecordFilter::new(
                             &Some(field.to_owned()),
                             "=",
                             &Some(column_value),
                             &None,
-                        ));
+                        ) {
+                            Ok(filter) => record_filter_childs.push(filter),
+                            Err(err) => {
+                                return Response::error_raw(
+                                    &StatusCode::BAD_REQUEST,
+                                    &err.to_string(),
+                                )
+                            }
+                        }
                     }
                 } else {
                     return Response::error_raw(
@@ -345,12 +669,17 @@ async fn token_based(
             }
         }
 
-        let record_filter = RecordFilters::new(&Vec::from([RecordFilter::new(
+        let record_filter = match RecordFilter::new(
             &None,
             "AND",
             &None,
             &Some(RecordFilters::new(&record_filter_childs)),
-        )]));
+        ) {
+            Ok(filter) => RecordFilters::new(&Vec::from([filter])),
+            Err(err) => {
+                return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+            }
+        };
 
         let (records_data, total) = match RecordDao::db_select_many(
             ctx.dao().db(),
@@ -378,6 +707,7 @@ async fn token_based(
             );
         }
 
+        let mut legacy_hashed_fields = Vec::new();
         for (field, value) in hashed_fields {
             if let Some(data_value) = records_data[0].get(field) {
                 if let ColumnValue::String(data_value) = data_value {
@@ -388,6 +718,9 @@ async fn token_based(
                             .verify_password(value, data_value)
                             .is_ok()
                         {
+                            if ctx.hash().argon2().needs_rehash(data_value) {
+                                legacy_hashed_fields.push((field, value));
+                            }
                             continue;
                         }
                     }
@@ -409,6 +742,10 @@ async fn token_based(
             );
         };
 
+        if !legacy_hashed_fields.is_empty() {
+            rehash_record_fields(&ctx, &collection_data, record_id, &legacy_hashed_fields).await;
+        }
+
         match ctx.token().jwt().encode(&ClaimId::Token(
             *token_data.id(),
             Some(UserClaim::new(collection_id, record_id)),
@@ -474,7 +811,7 @@ async fn mqtt_authentication(
         }
     };
 
-    let token_data = match TokenDao::db_select(ctx.dao().db(), &token_id).await {
+    let mut token_data = match TokenDao::db_select(ctx.dao().db(), &token_id).await {
         Ok(data) => data,
         Err(err) => {
             hb_log::error(None, &format!("Failed to get token data: {err}"));
@@ -483,12 +820,8 @@ async fn mqtt_authentication(
         }
     };
 
-    if token_data.token() != data.password() {
-        let err_msg = format!(
-            "Token id '{}' doesn't match with token '{}'",
-            token_id,
-            data.password()
-        );
+    if !token_data.is_token_matched(data.password()) {
+        let err_msg = format!("Token id '{token_id}' doesn't match with the given token");
         let log_data = LogDao::new(
             token_data.admin_id(),
             token_data.project_id(),
@@ -530,15 +863,24 @@ async fn mqtt_authentication(
             .json(MqttAuthenticationResJson::new("deny", &false));
     }
 
+    if let Err(err) = token_data.check_expired(ctx.dao().db()).await {
+        hb_log::error(None, &format!("Failed to authenticate MQTT client: {err}"));
+        return HttpResponseBuilder::new(if DaoError::is_expired(&err) {
+            StatusCode::UNAUTHORIZED
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+        .json(MqttAuthenticationResJson::new("deny", &false));
+    }
+
     tokio::spawn((|| async move {
         let log_data = LogDao::new(
             token_data.admin_id(),
             token_data.project_id(),
             &LogKind::Info,
             &format!(
-                "MQTT: Client is authenticated using token id '{}' and token '{}'",
-                token_data.id(),
-                token_data.token()
+                "MQTT: Client is authenticated using token id '{}'",
+                token_data.id()
             ),
         );
         match log_data.db_insert(ctx.dao().db()).await {
@@ -592,7 +934,8 @@ async fn mqtt_authorization(
     } else if data
         .topic()
         .chars()
-        .all(|c| c.is_alphabetic() || c == '/' || c == '-')
+        // Digits are allowed for topics that carry the project and collection ids
+        .all(|c| c.is_alphanumeric() || c == '/' || c == '-')
     {
         is_allow = true
     }
@@ -739,6 +1082,10 @@ async fn confirm_password_reset(
         return Response::error_raw(&StatusCode::BAD_REQUEST, "Wrong code");
     }
 
+    if let Err(res) = check_password_strength(ctx.password_policy(), data.password()) {
+        return res;
+    }
+
     let mut admin_data =
         match AdminDao::db_select(ctx.dao().db(), password_reset_data.admin_id()).await {
             Ok(data) => data,
@@ -782,3 +1129,528 @@ async fn confirm_password_reset(
         &ConfirmPasswordResetResJson::new(admin_data.id()),
     )
 }
+
+async fn change_password(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    data: web::Json<ChangePasswordReqJson>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    if let Err(res) = check_password_strength(ctx.password_policy(), data.new_password()) {
+        return res;
+    }
+
+    match token_claim.id() {
+        ClaimId::Admin(id) => {
+            let mut admin_data = match AdminDao::db_select(ctx.dao().db(), id).await {
+                Ok(data) => data,
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::UNAUTHORIZED,
+                        &format!("Failed to get admin data: {err}"),
+                    )
+                }
+            };
+
+            if let Err(err) = ctx
+                .hash()
+                .argon2()
+                .verify_password(data.old_password(), admin_data.password_hash())
+            {
+                return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string());
+            }
+
+            let password_hash = match ctx
+                .hash()
+                .argon2()
+                .hash_password(data.new_password().as_bytes())
+            {
+                Ok(hash) => hash,
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::INTERNAL_SERVER_ERROR,
+                        &err.to_string(),
+                    )
+                }
+            };
+
+            admin_data.set_password_hash(&password_hash.to_string());
+
+            if let Err(err) = admin_data.db_update(ctx.dao().db()).await {
+                return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+            }
+
+            if let Some(mailer) = ctx.mailer() {
+                if let Err(err) = mailer
+                    .sender()
+                    .send(MailPayload::new(
+                        admin_data.email(),
+                        "Your Password Has Been Changed",
+                        "Your account password has been successfully changed",
+                    ))
+                    .await
+                {
+                    return Response::error_raw(
+                        &StatusCode::INTERNAL_SERVER_ERROR,
+                        &err.to_string(),
+                    );
+                }
+            }
+
+            Response::data(
+                &StatusCode::OK,
+                &None,
+                &ChangePasswordResJson::new(admin_data.id()),
+            )
+        }
+        ClaimId::Token(token_id, user_claim) => {
+            let user_claim = match user_claim {
+                Some(user_claim) => user_claim,
+                None => {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        "Must be logged in as a user of an auth collection",
+                    )
+                }
+            };
+
+            if let Err(err) = TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
+                if DaoError::is_expired(&err) {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string());
+                }
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    &format!("Failed to get token data: {err}"),
+                );
+            }
+
+            let collection_data =
+                match CollectionDao::db_select(ctx.dao().db(), user_claim.collection_id()).await {
+                    Ok(data) => data,
+                    Err(err) => {
+                        return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string())
+                    }
+                };
+
+            // The password is a hashed auth column, it has to be named when there are several
+            let password_fields = collection_data
+                .schema_fields()
+                .iter()
+                .filter(|(_, props)| *props.auth_column() && *props.hashed())
+                .map(|(field, _)| field.as_str())
+                .collect::<Vec<_>>();
+            let field = match data.field() {
+                Some(field) => {
+                    if !password_fields.contains(&field.as_str()) {
+                        return Response::error_raw(
+                            &StatusCode::BAD_REQUEST,
+                            &format!("Field {field} isn't a hashed auth column of the collection"),
+                        );
+                    }
+                    field.as_str()
+                }
+                None => {
+                    match password_fields.as_slice() {
+                        [field] => *field,
+                        [] => {
+                            return Response::error_raw(
+                                &StatusCode::BAD_REQUEST,
+                                "Collection doesn't have a hashed auth column",
+                            )
+                        }
+                        _ => return Response::error_raw(
+                            &StatusCode::BAD_REQUEST,
+                            "Collection has multiple hashed auth columns, field must be specified",
+                        ),
+                    }
+                }
+            };
+
+            let mut record_data = match RecordDao::db_select(
+                ctx.dao().db(),
+                user_claim.id(),
+                &None,
+                &HashSet::new(),
+                &collection_data,
+                &true,
+            )
+            .await
+            {
+                Ok(data) => data,
+                Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+            };
+
+            let is_matched = match record_data.get(field) {
+                Some(ColumnValue::String(Some(password_hash))) => ctx
+                    .hash()
+                    .argon2()
+                    .verify_password(data.old_password(), password_hash)
+                    .is_ok(),
+                _ => false,
+            };
+            if !is_matched {
+                return Response::error_raw(&StatusCode::BAD_REQUEST, "Old password doesn't match");
+            }
+
+            let password_hash = match ctx
+                .hash()
+                .argon2()
+                .hash_password(data.new_password().as_bytes())
+            {
+                Ok(hash) => hash,
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::INTERNAL_SERVER_ERROR,
+                        &err.to_string(),
+                    )
+                }
+            };
+
+            record_data.upsert(field, &ColumnValue::String(Some(password_hash.to_string())));
+
+            if let Err(err) = record_data.db_update(ctx.dao().db()).await {
+                return Response::error_dao(&StatusCode::INTERNAL_SERVER_ERROR, &err);
+            }
+
+            Response::data(
+                &StatusCode::OK,
+                &None,
+                &ChangePasswordResJson::new(user_claim.id()),
+            )
+        }
+    }
+}
+
+async fn admin_from_auth(ctx: &ApiRestCtx, auth: &ApiAuth) -> Result<AdminDao, HttpResponse> {
+    let token_claim = match ctx.token().jwt().decode(auth.token()) {
+        Ok(token) => token,
+        Err(err) => {
+            return Err(Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &err.to_string(),
+            ))
+        }
+    };
+
+    match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => Ok(data),
+            Err(err) => Err(Response::error_raw(
+                &StatusCode::UNAUTHORIZED,
+                &format!("Failed to get admin data: {err}"),
+            )),
+        },
+        ClaimId::Token(_, _) => Err(Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Must be logged in using password-based login",
+        )),
+    }
+}
+
+// Accepts a code from the authenticator app, or consumes one of the backup codes once two-factor
+// authentication is enabled. Returns whether a backup code was used, so the caller can persist it.
+fn verify_totp_code(
+    ctx: &ApiRestCtx,
+    admin_data: &mut AdminDao,
+    code: &str,
+) -> Result<bool, HttpResponse> {
+    let totp = match ctx.token().totp() {
+        Some(totp) => totp,
+        None => {
+            return Err(Response::error_raw(
+                &StatusCode::INTERNAL_SERVER_ERROR,
+                "Two-factor authentication is not configured on this server",
+            ))
+        }
+    };
+
+    let encrypted_secret = match admin_data.totp_secret() {
+        Some(secret) => secret,
+        None => {
+            return Err(Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Two-factor authentication has not been set up",
+            ))
+        }
+    };
+
+    let secret = match totp.decrypt_secret(encrypted_secret) {
+        Ok(secret) => secret,
+        Err(err) => {
+            return Err(Response::error_raw(
+                &StatusCode::INTERNAL_SERVER_ERROR,
+                &err.to_string(),
+            ))
+        }
+    };
+
+    match totp.verify(&secret, code) {
+        Ok(true) => return Ok(false),
+        Ok(false) => (),
+        Err(err) => {
+            return Err(Response::error_raw(
+                &StatusCode::INTERNAL_SERVER_ERROR,
+                &err.to_string(),
+            ))
+        }
+    }
+
+    if admin_data.is_totp_enabled() && admin_data.use_totp_backup_code(code) {
+        return Ok(true);
+    }
+
+    Err(Response::error_raw(
+        &StatusCode::BAD_REQUEST,
+        "Two-factor authentication code is invalid",
+    ))
+}
+
+fn totp_challenge_message(admin_id: &Uuid, expires_at: &i64) -> String {
+    format!("totp:{admin_id}:{expires_at}")
+}
+
+// Failed sign-ins are counted per admin email and per source IP, so neither guessing many
+// passwords of one account nor trying a password across many accounts goes unchecked. Returns the
+// counts when none of them is locked.
+async fn select_sign_in_attempts(
+    ctx: &ApiRestCtx,
+    email: &str,
+    ip: &Option<IpAddr>,
+) -> Result<Vec<SignInAttemptDao>, HttpResponse> {
+    let mut keys = vec![sign_in_email_key(email)];
+    if let Some(ip) = ip {
+        keys.push(sign_in_ip_key(ip));
+    }
+
+    let mut attempts_data = Vec::with_capacity(keys.len());
+    for key in &keys {
+        match SignInAttemptDao::db_select_or_new(ctx.dao().db(), key).await {
+            Ok(data) => attempts_data.push(data),
+            Err(err) => {
+                return Err(Response::error_raw(
+                    &StatusCode::INTERNAL_SERVER_ERROR,
+                    &err.to_string(),
+                ))
+            }
+        }
+    }
+
+    if let Some(retry_after) = attempts_data
+        .iter()
+        .filter_map(|attempt_data| attempt_data.locked_for())
+        .max()
+    {
+        return Err(too_many_sign_in_attempts(&retry_after));
+    }
+
+    Ok(attempts_data)
+}
+
+// Counts the failure on every key and returns the given response, or a 429 once it locks any
+async fn sign_in_failed(
+    ctx: &web::Data<ApiRestCtx>,
+    attempts_data: &mut [SignInAttemptDao],
+    admin_data: Option<&AdminDao>,
+    ip: &Option<IpAddr>,
+    res: HttpResponse,
+) -> HttpResponse {
+    let ip = match ip {
+        Some(ip) => ip.to_string(),
+        None => "an unknown address".to_owned(),
+    };
+
+    let mut retry_after = None;
+    for attempt_data in attempts_data.iter_mut() {
+        let locked = match attempt_data
+            .record_failure(ctx.sign_in_max_attempts(), ctx.sign_in_lockout_duration())
+        {
+            Ok(locked) => locked,
+            Err(err) => {
+                return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+            }
+        };
+
+        if let Err(err) = attempt_data.db_upsert(ctx.dao().db()).await {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+        }
+
+        if locked {
+            let message = format!(
+                "Sign-in is locked for {} seconds after {} failed attempts from {ip}",
+                ctx.sign_in_lockout_duration(),
+                attempt_data.failed_count()
+            );
+            hb_log::warn(
+                None,
+                &format!("[ApiRestServer] {message} ('{}')", attempt_data.key()),
+            );
+            if let Some(admin_data) = admin_data {
+                if attempt_data.key() == sign_in_email_key(admin_data.email()) {
+                    notify_sign_in_lockout(
+                        ctx.clone(),
+                        admin_data.id(),
+                        admin_data.email(),
+                        &message,
+                    );
+                }
+            }
+            retry_after = attempt_data.locked_for();
+        }
+    }
+
+    match retry_after {
+        Some(retry_after) => too_many_sign_in_attempts(&retry_after),
+        None => res,
+    }
+}
+
+// Only the count of the email is reset, the one of the IP keeps counting so signing in to an own
+// account can't be used to keep guessing the passwords of others
+async fn sign_in_succeeded(ctx: &ApiRestCtx, email: &str) -> Result<(), HttpResponse> {
+    if let Err(err) = SignInAttemptDao::db_delete(ctx.dao().db(), &sign_in_email_key(email)).await {
+        return Err(Response::error_raw(
+            &StatusCode::INTERNAL_SERVER_ERROR,
+            &err.to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Warns in the logs of every project the admin owns and, when a mailer is set up, by email
+fn notify_sign_in_lockout(ctx: web::Data<ApiRestCtx>, admin_id: &Uuid, email: &str, message: &str) {
+    let admin_id = *admin_id;
+    let email = email.to_owned();
+    let message = message.to_owned();
+    tokio::spawn((|| async move {
+        match ProjectDao::db_select_many_by_admin_id(ctx.dao().db(), &admin_id).await {
+            Ok(projects_data) => {
+                for project_data in &projects_data {
+                    let log_data =
+                        LogDao::new(&admin_id, project_data.id(), &LogKind::Warn, &message);
+                    match log_data.db_insert(ctx.dao().db()).await {
+                        Ok(_) => {
+                            if let Err(err) = websocket_broadcast(
+                                ctx.websocket().handler(),
+                                WebSocketTarget::Log,
+                                None,
+                                WebSocketMessageKind::InsertOne,
+                                LogResJson::new(
+                                    log_data.id(),
+                                    log_data.created_at(),
+                                    log_data.kind().to_str(),
+                                    log_data.message(),
+                                ),
+                            ) {
+                                hb_log::error(
+                                    None,
+                                    &format!(
+                                        "[ApiRestServer] Error when serializing websocket data: {err}"
+                                    ),
+                                );
+                            }
+                        }
+                        Err(err) => hb_log::error(
+                            None,
+                            &format!("[ApiRestServer] Error when inserting log data: {err}"),
+                        ),
+                    }
+                }
+            }
+            Err(err) => hb_log::error(
+                None,
+                &format!("[ApiRestServer] Error when getting projects data: {err}"),
+            ),
+        }
+
+        if let Some(mailer) = ctx.mailer() {
+            if let Err(err) = mailer
+                .sender()
+                .send(MailPayload::new(
+                    &email,
+                    "Your Account Has Been Temporarily Locked",
+                    &message,
+                ))
+                .await
+            {
+                hb_log::error(
+                    None,
+                    &format!("[ApiRestServer] Error when sending lockout email: {err}"),
+                );
+            }
+        }
+    })());
+}
+
+fn too_many_sign_in_attempts(retry_after: &i64) -> HttpResponse {
+    let mut res = Response::error_raw(
+        &StatusCode::TOO_MANY_REQUESTS,
+        "Too many failed sign-in attempts, try again later",
+    );
+    res.headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(*retry_after));
+    res
+}
+
+fn sign_in_email_key(email: &str) -> String {
+    format!("email:{email}")
+}
+
+fn sign_in_ip_key(ip: &IpAddr) -> String {
+    format!("ip:{ip}")
+}
+
+// Same as for admins, a failure here doesn't stop the sign-in, the hash is replaced next time
+async fn rehash_record_fields(
+    ctx: &ApiRestCtx,
+    collection_data: &CollectionDao,
+    record_id: &Uuid,
+    fields: &[(&str, &str)],
+) {
+    let mut record_data = match RecordDao::db_select(
+        ctx.dao().db(),
+        record_id,
+        &None,
+        &HashSet::new(),
+        collection_data,
+        &true,
+    )
+    .await
+    {
+        Ok(data) => data,
+        Err(err) => {
+            hb_log::error(
+                None,
+                &format!("[ApiRestServer] Error when rehashing record fields: {err}"),
+            );
+            return;
+        }
+    };
+
+    for (field, value) in fields {
+        match ctx.hash().argon2().hash_password(value.as_bytes()) {
+            Ok(value_hashed) => {
+                record_data.upsert(field, &ColumnValue::String(Some(value_hashed)));
+            }
+            Err(err) => {
+                hb_log::error(
+                    None,
+                    &format!("[ApiRestServer] Error when rehashing record fields: {err}"),
+                );
+                return;
+            }
+        }
+    }
+
+    if let Err(err) = record_data.db_update(ctx.dao().db()).await {
+        hb_log::error(
+            None,
+            &format!("[ApiRestServer] Error when rehashing record fields: {err}"),
+        );
+    }
+}
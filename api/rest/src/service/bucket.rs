@@ -1,7 +1,19 @@
-use actix_web::{http::StatusCode, web, HttpResponse};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
-use hb_dao::{admin::AdminDao, bucket::BucketDao, project::ProjectDao};
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
+use hb_api_websocket::{
+    message::{MessageFormat, MessageKind, Target},
+    session::{UserSession, UserSessionId},
+};
+use hb_dao::{
+    admin::AdminDao,
+    audit::{AuditAction, AuditEntity},
+    bucket::BucketDao,
+    bucket_rule::BucketPermission,
+    error::DaoError,
+    project::ProjectDao,
+    token::TokenDao,
+};
 use hb_token_jwt::claim::ClaimId;
+use serde_json::Value;
 
 use crate::{
     context::ApiRestCtx,
@@ -9,10 +21,18 @@ use crate::{
         bucket::{
             BucketResJson, DeleteBucketResJson, DeleteOneBucketReqPath, FindManyBucketReqPath,
             FindOneBucketReqPath, InsertOneBucketReqJson, InsertOneBucketReqPath,
+            RecalculateUsageBucketReqPath, SubscribeBucketReqPath, SubscribeBucketReqQuery,
             UpdateOneBucketReqJson, UpdateOneBucketReqPath,
         },
         PaginationRes, Response,
     },
+    util::{
+        api_auth::ApiAuth,
+        audit::{audit, audit_state},
+        project_access::{authorize_project, ProjectAccess},
+        ws_broadcast::websocket_broadcast_project,
+        ws_origin::validate_websocket_origin,
+    },
 };
 
 pub fn bucket_api(cfg: &mut web::ServiceConfig) {
@@ -21,6 +41,10 @@ pub fn bucket_api(cfg: &mut web::ServiceConfig) {
             "/project/{project_id}/bucket/{bucket_id}",
             web::get().to(find_one),
         )
+        .route(
+            "/project/{project_id}/bucket/{bucket_id}/subscribe",
+            web::get().to(subscribe),
+        )
         .route(
             "/project/{project_id}/bucket/{bucket_id}",
             web::patch().to(update_one),
@@ -29,12 +53,17 @@ pub fn bucket_api(cfg: &mut web::ServiceConfig) {
             "/project/{project_id}/bucket/{bucket_id}",
             web::delete().to(delete_one),
         )
+        .route(
+            "/project/{project_id}/bucket/{bucket_id}/recalculate-usage",
+            web::post().to(recalculate_usage),
+        )
         .route("/project/{project_id}/buckets", web::get().to(find_many));
 }
 
 async fn insert_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<InsertOneBucketReqPath>,
     data: web::Json<InsertOneBucketReqJson>,
 ) -> HttpResponse {
@@ -68,11 +97,15 @@ async fn insert_one(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
     }
 
     let bucket_data = match BucketDao::new(
@@ -80,6 +113,9 @@ async fn insert_one(
         data.name(),
         ctx.bucket_path(),
         data.opt_ttl(),
+        data.max_file_size(),
+        data.allowed_content_types(),
+        data.quota_bytes(),
     )
     .await
     {
@@ -90,6 +126,24 @@ async fn insert_one(
     if let Err(err) = bucket_data.db_insert(ctx.dao().db()).await {
         return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
     }
+    websocket_broadcast_project(
+        ctx.websocket().handler(),
+        bucket_data.project_id(),
+        MessageKind::InsertOne,
+        "bucket",
+        bucket_data.id(),
+    );
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        project_data.id(),
+        &AuditAction::Create,
+        &AuditEntity::Bucket,
+        bucket_data.id(),
+        &None,
+        &bucket_state(&bucket_data),
+    );
 
     Response::data(
         &StatusCode::CREATED,
@@ -101,13 +155,17 @@ async fn insert_one(
             bucket_data.project_id(),
             bucket_data.name(),
             bucket_data.opt_ttl(),
+            bucket_data.max_file_size(),
+            bucket_data.allowed_content_types(),
+            bucket_data.quota_bytes(),
+            bucket_data.used_bytes(),
         ),
     )
 }
 
 async fn find_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     path: web::Path<FindOneBucketReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -143,11 +201,15 @@ async fn find_one(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
     }
 
     if bucket_data.project_id() != project_data.id() {
@@ -164,13 +226,149 @@ async fn find_one(
             bucket_data.project_id(),
             bucket_data.name(),
             bucket_data.opt_ttl(),
+            bucket_data.max_file_size(),
+            bucket_data.allowed_content_types(),
+            bucket_data.quota_bytes(),
+            bucket_data.used_bytes(),
         ),
     )
 }
 
+async fn subscribe(
+    ctx: web::Data<ApiRestCtx>,
+    req: HttpRequest,
+    stream: web::Payload,
+    path: web::Path<SubscribeBucketReqPath>,
+    query: web::Query<SubscribeBucketReqQuery>,
+) -> HttpResponse {
+    if let Err(err) = validate_websocket_origin(ctx.websocket(), &req) {
+        hb_log::warn(
+            None,
+            &format!("[ApiRestServer] Rejected websocket connection to subscribe bucket: {err}"),
+        );
+        return Response::error_raw(&StatusCode::FORBIDDEN, &err.to_string());
+    }
+
+    let format = match MessageFormat::from_str(query.format().as_deref().unwrap_or("json")) {
+        Ok(format) => format,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let token = query.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let (admin_id, token) = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => (*data.id(), None),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(token_id, user) => {
+            let token_data = match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
+                Ok(data) => data,
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                }
+                Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+            };
+            // Checked once here so the connection is refused outright, each message is still
+            // filtered again on broadcast since the bucket rule can change while it is open
+            match token_data
+                .is_allow_find_many_files(ctx.dao().db(), path.bucket_id())
+                .await
+            {
+                Some(permission) if permission != BucketPermission::None => (),
+                _ => {
+                    return Response::error_raw(
+                        &StatusCode::FORBIDDEN,
+                        "This token doesn't have permission to read files in this bucket",
+                    )
+                }
+            }
+            // Subscriptions authenticate with the query instead of ApiAuth, so they are counted
+            // here
+            ctx.dao().token_usage().record(token_id);
+            (
+                *token_data.admin_id(),
+                Some((
+                    *token_id,
+                    match user {
+                        Some(user) => Some(*user.id()),
+                        None => None,
+                    },
+                )),
+            )
+        }
+    };
+
+    let (project_data, bucket_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        BucketDao::db_select(ctx.dao().db(), path.bucket_id()),
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if project_data.id() != bucket_data.project_id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    let (res, session, msg_stream) = match actix_ws_ng::handle(&req, stream) {
+        Ok(res) => res,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    tokio::task::spawn_local((|| async move {
+        let _ = ctx
+            .websocket()
+            .handler()
+            .clone()
+            .connection(
+                UserSession::new(
+                    match token {
+                        Some((token_id, user_id)) => UserSessionId::Token(token_id, user_id),
+                        None => UserSessionId::Admin(admin_id),
+                    },
+                    token_claim.exp(),
+                ),
+                Target::Bucket(*bucket_data.id()),
+                *query.last_seq(),
+                false,
+                format,
+                session,
+                msg_stream,
+            )
+            .await;
+    })());
+
+    res
+}
+
 async fn update_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<UpdateOneBucketReqPath>,
     data: web::Json<UpdateOneBucketReqJson>,
 ) -> HttpResponse {
@@ -207,17 +405,23 @@ async fn update_one(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
     }
 
     if bucket_data.project_id() != project_data.id() {
         return Response::error_raw(&StatusCode::FORBIDDEN, "This bucket does not belong to you");
     }
 
+    let before = bucket_state(&bucket_data);
+
     if let Some(name) = data.name() {
         bucket_data.set_name(name);
     }
@@ -226,10 +430,42 @@ async fn update_one(
         bucket_data.set_opt_ttl(opt_ttl);
     }
 
+    if let Some(max_file_size) = data.max_file_size() {
+        bucket_data.set_max_file_size(max_file_size);
+    }
+
+    if let Some(quota_bytes) = data.quota_bytes() {
+        bucket_data.set_quota_bytes(quota_bytes);
+    }
+
+    if let Some(allowed_content_types) = data.allowed_content_types() {
+        if let Err(err) = bucket_data.set_allowed_content_types(allowed_content_types) {
+            return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string());
+        }
+    }
+
     if !data.is_all_none() {
         if let Err(err) = bucket_data.db_update(ctx.dao().db()).await {
             return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
         }
+        websocket_broadcast_project(
+            ctx.websocket().handler(),
+            bucket_data.project_id(),
+            MessageKind::UpdateOne,
+            "bucket",
+            bucket_data.id(),
+        );
+        audit(
+            &ctx,
+            &req,
+            &admin_id,
+            project_data.id(),
+            &AuditAction::Update,
+            &AuditEntity::Bucket,
+            bucket_data.id(),
+            &before,
+            &bucket_state(&bucket_data),
+        );
     }
 
     Response::data(
@@ -242,13 +478,18 @@ async fn update_one(
             bucket_data.project_id(),
             bucket_data.name(),
             bucket_data.opt_ttl(),
+            bucket_data.max_file_size(),
+            bucket_data.allowed_content_types(),
+            bucket_data.quota_bytes(),
+            bucket_data.used_bytes(),
         ),
     )
 }
 
 async fn delete_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<DeleteOneBucketReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -284,11 +525,15 @@ async fn delete_one(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
     }
 
     if bucket_data.project_id() != project_data.id() {
@@ -298,6 +543,24 @@ async fn delete_one(
     if let Err(err) = BucketDao::db_delete(ctx.dao().db(), path.bucket_id()).await {
         return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
     }
+    websocket_broadcast_project(
+        ctx.websocket().handler(),
+        bucket_data.project_id(),
+        MessageKind::DeleteOne,
+        "bucket",
+        bucket_data.id(),
+    );
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        project_data.id(),
+        &AuditAction::Delete,
+        &AuditEntity::Bucket,
+        bucket_data.id(),
+        &bucket_state(&bucket_data),
+        &None,
+    );
 
     Response::data(
         &StatusCode::OK,
@@ -306,9 +569,91 @@ async fn delete_one(
     )
 }
 
+async fn recalculate_usage(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<RecalculateUsageBucketReqPath>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let (project_data, bucket_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        BucketDao::db_select(ctx.dao().db(), path.bucket_id())
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if bucket_data.project_id() != project_data.id() {
+        return Response::error_raw(&StatusCode::FORBIDDEN, "This bucket does not belong to you");
+    }
+
+    if let Err(err) = BucketDao::db_recalculate_used_bytes(ctx.dao().db(), bucket_data.id()).await {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+
+    let bucket_data = match BucketDao::db_select(ctx.dao().db(), bucket_data.id()).await {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &BucketResJson::new(
+            bucket_data.id(),
+            bucket_data.created_at(),
+            bucket_data.updated_at(),
+            bucket_data.project_id(),
+            bucket_data.name(),
+            bucket_data.opt_ttl(),
+            bucket_data.max_file_size(),
+            bucket_data.allowed_content_types(),
+            bucket_data.quota_bytes(),
+            bucket_data.used_bytes(),
+        ),
+    )
+}
+
 async fn find_many(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     path: web::Path<FindManyBucketReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -344,11 +689,15 @@ async fn find_many(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
     }
 
     Response::data(
@@ -364,8 +713,28 @@ async fn find_many(
                     data.project_id(),
                     data.name(),
                     data.opt_ttl(),
+                    data.max_file_size(),
+                    data.allowed_content_types(),
+                    data.quota_bytes(),
+                    data.used_bytes(),
                 )
             })
             .collect::<Vec<_>>(),
     )
 }
+
+// The bucket as the API returns it, which is what the audit log compares
+fn bucket_state(bucket_data: &BucketDao) -> Option<Value> {
+    audit_state(&BucketResJson::new(
+        bucket_data.id(),
+        bucket_data.created_at(),
+        bucket_data.updated_at(),
+        bucket_data.project_id(),
+        bucket_data.name(),
+        bucket_data.opt_ttl(),
+        bucket_data.max_file_size(),
+        bucket_data.allowed_content_types(),
+        bucket_data.quota_bytes(),
+        bucket_data.used_bytes(),
+    ))
+}
@@ -1,13 +1,16 @@
-use actix_web::{http::StatusCode, web, HttpResponse};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
+use hb_api_websocket::message::MessageKind;
 use hb_dao::{
     admin::AdminDao,
+    audit::{AuditAction, AuditEntity},
     bucket::BucketDao,
-    bucket_rule::{BucketPermission, BucketRuleDao},
+    bucket_rule::{BucketPermission, BucketRuleDao, ALL_BUCKETS_ID},
     project::ProjectDao,
     token::TokenDao,
 };
 use hb_token_jwt::claim::ClaimId;
+use serde_json::Value;
+use uuid::Uuid;
 
 use crate::{
     context::ApiRestCtx,
@@ -19,6 +22,12 @@ use crate::{
         },
         PaginationRes, Response,
     },
+    util::{
+        api_auth::ApiAuth,
+        audit::{audit, audit_state},
+        project_access::{authorize_project, ProjectAccess},
+        ws_broadcast::{websocket_broadcast_project, websocket_invalidate_token},
+    },
 };
 
 pub fn bucket_rule_api(cfg: &mut web::ServiceConfig) {
@@ -46,7 +55,8 @@ pub fn bucket_rule_api(cfg: &mut web::ServiceConfig) {
 
 async fn insert_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<InsertOneBucketRuleReqPath>,
     data: web::Json<InsertOneBucketRuleReqJson>,
 ) -> HttpResponse {
@@ -75,26 +85,44 @@ async fn insert_one(
         }
     };
 
-    let (project_data, token_data, bucket_data) = match tokio::try_join!(
+    // "*" makes a rule for every bucket in the project
+    let bucket_id = if data.bucket_id() == "*" {
+        ALL_BUCKETS_ID
+    } else {
+        match Uuid::parse_str(data.bucket_id()) {
+            Ok(id) => id,
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    &format!("Bucket id must be a uuid or '*': {err}"),
+                )
+            }
+        }
+    };
+
+    let (project_data, token_data) = match tokio::try_join!(
         ProjectDao::db_select(ctx.dao().db(), path.project_id()),
         TokenDao::db_select(ctx.dao().db(), path.token_id()),
-        BucketDao::db_select(ctx.dao().db(), data.bucket_id())
     ) {
         Ok(data) => data,
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
     }
 
     if let Ok(_) = BucketRuleDao::db_select_by_token_id_and_bucket_id(
         ctx.dao().db(),
         token_data.id(),
-        bucket_data.id(),
+        &bucket_id,
     )
     .await
     {
@@ -105,8 +133,17 @@ async fn insert_one(
         return Response::error_raw(&StatusCode::FORBIDDEN, "This token does not belong to you");
     }
 
-    if bucket_data.project_id() != project_data.id() {
-        return Response::error_raw(&StatusCode::FORBIDDEN, "This bucket does not belong to you");
+    if bucket_id != ALL_BUCKETS_ID {
+        let bucket_data = match BucketDao::db_select(ctx.dao().db(), &bucket_id).await {
+            Ok(data) => data,
+            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        };
+        if bucket_data.project_id() != project_data.id() {
+            return Response::error_raw(
+                &StatusCode::FORBIDDEN,
+                "This bucket does not belong to you",
+            );
+        }
     }
 
     let rule_find_one = match BucketPermission::from_str(data.find_one()) {
@@ -129,7 +166,7 @@ async fn insert_one(
     let bucket_rule_data = BucketRuleDao::new(
         project_data.id(),
         token_data.id(),
-        bucket_data.id(),
+        &bucket_id,
         &rule_find_one,
         &rule_find_many,
         data.insert_one(),
@@ -140,6 +177,25 @@ async fn insert_one(
     if let Err(err) = bucket_rule_data.db_insert(ctx.dao().db()).await {
         return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
     }
+    websocket_invalidate_token(ctx.websocket().handler(), token_data.id());
+    websocket_broadcast_project(
+        ctx.websocket().handler(),
+        bucket_rule_data.project_id(),
+        MessageKind::InsertOne,
+        "bucket_rule",
+        bucket_rule_data.id(),
+    );
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        project_data.id(),
+        &AuditAction::Create,
+        &AuditEntity::BucketRule,
+        bucket_rule_data.id(),
+        &None,
+        &bucket_rule_state(&bucket_rule_data),
+    );
 
     Response::data(
         &StatusCode::CREATED,
@@ -162,7 +218,7 @@ async fn insert_one(
 
 async fn find_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     path: web::Path<FindOneBucketRuleReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -230,7 +286,8 @@ async fn find_one(
 
 async fn update_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<UpdateOneBucketRuleReqPath>,
     data: web::Json<UpdateOneBucketRuleReqJson>,
 ) -> HttpResponse {
@@ -278,6 +335,8 @@ async fn update_one(
         );
     }
 
+    let before = bucket_rule_state(&bucket_rule_data);
+
     if let Some(find_one) = data.find_one() {
         let find_one = match BucketPermission::from_str(find_one) {
             Ok(rule) => rule,
@@ -318,6 +377,25 @@ async fn update_one(
         if let Err(err) = bucket_rule_data.db_update(ctx.dao().db()).await {
             return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
         }
+        websocket_invalidate_token(ctx.websocket().handler(), token_data.id());
+        websocket_broadcast_project(
+            ctx.websocket().handler(),
+            bucket_rule_data.project_id(),
+            MessageKind::UpdateOne,
+            "bucket_rule",
+            bucket_rule_data.id(),
+        );
+        audit(
+            &ctx,
+            &req,
+            &admin_id,
+            bucket_rule_data.project_id(),
+            &AuditAction::Update,
+            &AuditEntity::BucketRule,
+            bucket_rule_data.id(),
+            &before,
+            &bucket_rule_state(&bucket_rule_data),
+        );
     }
 
     Response::data(
@@ -341,7 +419,8 @@ async fn update_one(
 
 async fn delete_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<DeleteOneBucketRuleReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -391,6 +470,25 @@ async fn delete_one(
     if let Err(err) = BucketRuleDao::db_delete(ctx.dao().db(), path.rule_id()).await {
         return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
     }
+    websocket_invalidate_token(ctx.websocket().handler(), token_data.id());
+    websocket_broadcast_project(
+        ctx.websocket().handler(),
+        bucket_rule_data.project_id(),
+        MessageKind::DeleteOne,
+        "bucket_rule",
+        bucket_rule_data.id(),
+    );
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        bucket_rule_data.project_id(),
+        &AuditAction::Delete,
+        &AuditEntity::BucketRule,
+        bucket_rule_data.id(),
+        &bucket_rule_state(&bucket_rule_data),
+        &None,
+    );
 
     Response::data(
         &StatusCode::OK,
@@ -401,7 +499,7 @@ async fn delete_one(
 
 async fn find_many(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     path: web::Path<FindManyBucketRuleReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -467,3 +565,20 @@ async fn find_many(
             .collect::<Vec<_>>(),
     )
 }
+
+// The bucket rule as the API returns it, which is what the audit log compares
+fn bucket_rule_state(bucket_rule_data: &BucketRuleDao) -> Option<Value> {
+    audit_state(&BucketRuleResJson::new(
+        bucket_rule_data.id(),
+        bucket_rule_data.created_at(),
+        bucket_rule_data.updated_at(),
+        bucket_rule_data.project_id(),
+        bucket_rule_data.token_id(),
+        bucket_rule_data.bucket_id(),
+        bucket_rule_data.find_one().to_str(),
+        bucket_rule_data.find_many().to_str(),
+        bucket_rule_data.insert_one(),
+        bucket_rule_data.update_one().to_str(),
+        bucket_rule_data.delete_one().to_str(),
+    ))
+}
@@ -1,27 +1,45 @@
 use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
 use ahash::{HashMap, HashMapExt};
-use hb_api_websocket::{message::Target, session::UserSession};
+use hb_api_websocket::{
+    message::{MessageFormat, MessageKind, Target},
+    session::{UserSession, UserSessionId},
+};
 use hb_dao::{
     admin::AdminDao,
-    collection::{CollectionDao, SchemaFieldProps},
+    audit::{AuditAction, AuditEntity},
+    collection::{CollectionDao, CollectionOrder, ReferenceAction, SchemaFieldProps},
+    error::DaoError,
     project::ProjectDao,
+    record::RecordDao,
     token::TokenDao,
-    value::ColumnKind,
+    value::{ColumnKind, ColumnValue},
 };
 use hb_token_jwt::claim::ClaimId;
+use serde_json::Value;
 
 use crate::{
     context::ApiRestCtx,
     model::{
         collection::{
-            CollectionResJson, DeleteCollectionResJson, DeleteOneCollectionReqPath,
-            FindManyCollectionReqPath, FindOneCollectionReqPath, InsertOneCollectionReqJson,
-            InsertOneCollectionReqPath, SchemaFieldPropsJson, SubscribeCollectionReqPath,
+            ApplySchemaCollectionReqPath, ApplySchemaCollectionReqQuery,
+            ApplySchemaCollectionResJson, CollectionResJson, CollectionSchemaJson,
+            DeleteCollectionResJson, DeleteOneCollectionReqPath, DuplicateOneCollectionReqJson,
+            DuplicateOneCollectionReqPath, ExportSchemaCollectionReqPath,
+            FindManyCollectionReqPath, FindManyCollectionReqQuery, FindOneCollectionReqPath,
+            InsertOneCollectionReqJson, InsertOneCollectionReqPath, SchemaFieldPropsJson,
+            StatsCollectionReqPath, StatsCollectionResJson, SubscribeCollectionReqPath,
             SubscribeCollectionReqQuery, UpdateOneCollectionReqJson, UpdateOneCollectionReqPath,
         },
         PaginationRes, Response,
     },
+    util::{
+        api_auth::ApiAuth,
+        audit::{audit, audit_state},
+        project_access::{authorize_project, ProjectAccess},
+        schema::{collection_schema_json, schema_fields_from_json},
+        ws_broadcast::websocket_broadcast_project,
+        ws_origin::validate_websocket_origin,
+    },
 };
 
 pub fn collection_api(cfg: &mut web::ServiceConfig) {
@@ -45,6 +63,22 @@ pub fn collection_api(cfg: &mut web::ServiceConfig) {
         "/project/{project_id}/collection/{collection_id}",
         web::delete().to(delete_one),
     )
+    .route(
+        "/project/{project_id}/collection/{collection_id}/duplicate",
+        web::post().to(duplicate_one),
+    )
+    .route(
+        "/project/{project_id}/collection/{collection_id}/schema",
+        web::get().to(export_schema),
+    )
+    .route(
+        "/project/{project_id}/collection/{collection_id}/stats",
+        web::get().to(stats),
+    )
+    .route(
+        "/project/{project_id}/collections/apply",
+        web::post().to(apply_schema),
+    )
     .route(
         "/project/{project_id}/collections",
         web::get().to(find_many),
@@ -53,7 +87,8 @@ pub fn collection_api(cfg: &mut web::ServiceConfig) {
 
 async fn insert_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<InsertOneCollectionReqPath>,
     data: web::Json<InsertOneCollectionReqJson>,
 ) -> HttpResponse {
@@ -84,14 +119,18 @@ async fn insert_one(
 
     let project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
     }
 
     let mut schema_fields = HashMap::with_capacity(data.schema_fields().len());
@@ -117,7 +156,10 @@ async fn insert_one(
             );
         }
         let schema_field_props = match SchemaFieldProps::new(
-            &match ColumnKind::from_str(props.kind()) {
+            &match ColumnKind::from_str_with_reference(
+                props.kind(),
+                props.reference_collection_id(),
+            ) {
                 Ok(kind) => kind,
                 Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
             },
@@ -127,6 +169,21 @@ async fn insert_one(
             &props.auth_column().unwrap_or(false),
             &props.hashed().unwrap_or(false),
             &props.hidden().unwrap_or(false),
+            props.default(),
+            props.min(),
+            props.max(),
+            props.max_length(),
+            props.regex(),
+            props.enum_values(),
+            &match props.on_delete() {
+                Some(on_delete) => match ReferenceAction::from_str(on_delete) {
+                    Ok(on_delete) => Some(on_delete),
+                    Err(err) => {
+                        return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string())
+                    }
+                },
+                None => None,
+            },
         ) {
             Ok(props) => props,
             Err(err) => {
@@ -139,16 +196,63 @@ async fn insert_one(
         schema_fields.insert(field.to_owned(), schema_field_props);
     }
 
+    for (field, props) in &schema_fields {
+        if let Some(reference_collection_id) = props.kind().reference_collection_id() {
+            match CollectionDao::db_select(ctx.dao().db(), reference_collection_id).await {
+                Ok(reference_collection_data) => {
+                    if reference_collection_data.project_id() != project_data.id() {
+                        return Response::error_raw(
+                            &StatusCode::BAD_REQUEST,
+                            &format!(
+                                "Field '{field}' can't reference a collection of another project"
+                            ),
+                        );
+                    }
+                }
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!(
+                            "Field '{field}' references a collection that can't be found: {err}"
+                        ),
+                    )
+                }
+            }
+        }
+    }
+
     let collection_data = CollectionDao::new(
         path.project_id(),
         data.name(),
         &schema_fields,
+        &data.unique_indexes().clone().unwrap_or_default(),
         data.opt_auth_column_id(),
         data.opt_ttl(),
+        &data.opt_soft_delete().unwrap_or(false),
+        &data.opt_per_record_ttl().unwrap_or(false),
+        &data.opt_mqtt_publish().unwrap_or(false),
     );
     if let Err(err) = collection_data.db_insert(ctx.dao().db()).await {
         return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
     }
+    websocket_broadcast_project(
+        ctx.websocket().handler(),
+        collection_data.project_id(),
+        MessageKind::InsertOne,
+        "collection",
+        collection_data.id(),
+    );
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        project_data.id(),
+        &AuditAction::Create,
+        &AuditEntity::Collection,
+        collection_data.id(),
+        &None,
+        &collection_state(&collection_data),
+    );
 
     Response::data(
         &StatusCode::CREATED,
@@ -173,19 +277,41 @@ async fn insert_one(
                             &Some(*props.auth_column()),
                             &Some(*props.hashed()),
                             &Some(*props.hidden()),
+                            &props
+                                .default()
+                                .as_ref()
+                                .and_then(|default| default.to_serde_json().ok()),
+                            props.min(),
+                            props.max(),
+                            props.max_length(),
+                            props.regex(),
+                            &props.enum_values().as_ref().map(|enum_values| {
+                                enum_values
+                                    .iter()
+                                    .filter_map(|value| value.to_serde_json().ok())
+                                    .collect()
+                            }),
+                            &props.kind().reference_collection_id().copied(),
+                            &props
+                                .on_delete()
+                                .map(|on_delete| on_delete.to_str().to_owned()),
                         ),
                     )
                 })
                 .collect(),
+            collection_data.unique_indexes(),
             collection_data.opt_auth_column_id(),
             collection_data.opt_ttl(),
+            collection_data.opt_soft_delete(),
+            collection_data.opt_per_record_ttl(),
+            collection_data.opt_mqtt_publish(),
         ),
     )
 }
 
 async fn find_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     path: web::Path<FindOneCollectionReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -218,14 +344,18 @@ async fn find_one(
         CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
     ) {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
     }
 
     if project_data.id() != collection_data.project_id() {
@@ -255,12 +385,34 @@ async fn find_one(
                             &Some(*value.auth_column()),
                             &Some(*value.hashed()),
                             &Some(*value.hidden()),
+                            &value
+                                .default()
+                                .as_ref()
+                                .and_then(|default| default.to_serde_json().ok()),
+                            value.min(),
+                            value.max(),
+                            value.max_length(),
+                            value.regex(),
+                            &value.enum_values().as_ref().map(|enum_values| {
+                                enum_values
+                                    .iter()
+                                    .filter_map(|value| value.to_serde_json().ok())
+                                    .collect()
+                            }),
+                            &value.kind().reference_collection_id().copied(),
+                            &value
+                                .on_delete()
+                                .map(|on_delete| on_delete.to_str().to_owned()),
                         ),
                     )
                 })
                 .collect(),
+            collection_data.unique_indexes(),
             collection_data.opt_auth_column_id(),
             collection_data.opt_ttl(),
+            collection_data.opt_soft_delete(),
+            collection_data.opt_per_record_ttl(),
+            collection_data.opt_mqtt_publish(),
         ),
     )
 }
@@ -272,6 +424,21 @@ async fn subscribe(
     path: web::Path<SubscribeCollectionReqPath>,
     query: web::Query<SubscribeCollectionReqQuery>,
 ) -> HttpResponse {
+    if let Err(err) = validate_websocket_origin(ctx.websocket(), &req) {
+        hb_log::warn(
+            None,
+            &format!(
+                "[ApiRestServer] Rejected websocket connection to subscribe collection: {err}"
+            ),
+        );
+        return Response::error_raw(&StatusCode::FORBIDDEN, &err.to_string());
+    }
+
+    let format = match MessageFormat::from_str(query.format().as_deref().unwrap_or("json")) {
+        Ok(format) => format,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
     let token = query.token();
 
     let token_claim = match ctx.token().jwt().decode(token) {
@@ -290,10 +457,16 @@ async fn subscribe(
             }
         },
         ClaimId::Token(token_id, user) => {
-            let token_data = match TokenDao::db_select(ctx.dao().db(), token_id).await {
+            let token_data = match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
                 Ok(data) => data,
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                }
                 Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
             };
+            // Subscriptions authenticate with the query instead of ApiAuth, so they are counted
+            // here
+            ctx.dao().token_usage().record(token_id);
             (
                 *token_data.admin_id(),
                 Some((
@@ -312,14 +485,18 @@ async fn subscribe(
         CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
     ) {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
     }
 
     if project_data.id() != collection_data.project_id() {
@@ -339,11 +516,17 @@ async fn subscribe(
             .handler()
             .clone()
             .connection(
-                match token {
-                    Some((token_id, user_id)) => UserSession::Token(token_id, user_id),
-                    None => UserSession::Admin(admin_id),
-                },
+                UserSession::new(
+                    match token {
+                        Some((token_id, user_id)) => UserSessionId::Token(token_id, user_id),
+                        None => UserSessionId::Admin(admin_id),
+                    },
+                    token_claim.exp(),
+                ),
                 Target::Collection(*collection_data.id()),
+                *query.last_seq(),
+                query.with_snapshot().unwrap_or(false),
+                format,
                 session,
                 msg_stream,
             )
@@ -355,7 +538,8 @@ async fn subscribe(
 
 async fn update_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<UpdateOneCollectionReqPath>,
     data: web::Json<UpdateOneCollectionReqJson>,
 ) -> HttpResponse {
@@ -389,24 +573,44 @@ async fn update_one(
         CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
     ) {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
     }
 
     if project_data.id() != collection_data.project_id() {
         return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
     }
+    let before = collection_state(&collection_data);
 
     if let Some(name) = data.name() {
         collection_data.set_name(name);
     }
 
+    // Renames go first, so schema_fields in the same request uses the new names
+    if let Some(rename_fields) = data.rename_fields() {
+        for field in rename_fields.values() {
+            if field.is_empty() || !field.chars().all(|c| c == '_' || ('a'..='z').contains(&c)) {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    &format!("Field '{field}' should only have lowercase English letters and an optional underscore (_) after the first character"),
+                );
+            }
+        }
+        if let Err(err) = collection_data.rename_schema_fields(rename_fields) {
+            return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string());
+        }
+    }
+
     if let Some(schema_field) = data.schema_fields() {
         let mut schema_fields = HashMap::with_capacity(schema_field.len());
         for (field, props) in schema_field.iter() {
@@ -424,7 +628,10 @@ async fn update_one(
                     &format!("Field '{field}' should only have lowercase English letters and an optional underscore (_) after the first character"),
                 );
             }
-            let schema_field_kind = match ColumnKind::from_str(props.kind()) {
+            let schema_field_kind = match ColumnKind::from_str_with_reference(
+                props.kind(),
+                props.reference_collection_id(),
+            ) {
                 Ok(kind) => kind,
                 Err(err) => {
                     return Response::error_raw(
@@ -441,6 +648,21 @@ async fn update_one(
                 &props.auth_column().unwrap_or(false),
                 &props.hashed().unwrap_or(false),
                 &props.hidden().unwrap_or(false),
+                props.default(),
+                props.min(),
+                props.max(),
+                props.max_length(),
+                props.regex(),
+                props.enum_values(),
+                &match props.on_delete() {
+                    Some(on_delete) => match ReferenceAction::from_str(on_delete) {
+                        Ok(on_delete) => Some(on_delete),
+                        Err(err) => {
+                            return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string())
+                        }
+                    },
+                    None => None,
+                },
             ) {
                 Ok(props) => props,
                 Err(err) => {
@@ -452,7 +674,49 @@ async fn update_one(
             };
             schema_fields.insert(field.to_owned(), schema_field_props);
         }
-        collection_data.update_schema_fields(&schema_fields);
+        for (field, props) in &schema_fields {
+            if let Some(reference_collection_id) = props.kind().reference_collection_id() {
+                if reference_collection_id == collection_data.id() {
+                    continue;
+                }
+                match CollectionDao::db_select(ctx.dao().db(), reference_collection_id).await {
+                    Ok(reference_collection_data) => {
+                        if reference_collection_data.project_id() != project_data.id() {
+                            return Response::error_raw(
+                                &StatusCode::BAD_REQUEST,
+                                &format!("Field '{field}' can't reference a collection of another project"),
+                            );
+                        }
+                    }
+                    Err(err) => {
+                        return Response::error_raw(
+                            &StatusCode::BAD_REQUEST,
+                            &format!(
+                            "Field '{field}' references a collection that can't be found: {err}"
+                        ),
+                        )
+                    }
+                }
+            }
+        }
+        let backfill_defaults = data.backfill_defaults().unwrap_or(false);
+        let backfill_values = match required_fields_backfill_values(
+            &ctx,
+            &collection_data,
+            schema_field,
+            &schema_fields,
+            &backfill_defaults,
+        )
+        .await
+        {
+            Ok(backfill_values) => backfill_values,
+            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err),
+        };
+        collection_data.update_schema_fields(&schema_fields, &backfill_defaults, &backfill_values);
+    }
+
+    if let Some(unique_indexes) = data.unique_indexes() {
+        collection_data.set_unique_indexes(unique_indexes);
     }
 
     if let Some(opt_auth_column_id) = data.opt_auth_column_id() {
@@ -463,10 +727,40 @@ async fn update_one(
         collection_data.set_opt_ttl(opt_ttl);
     }
 
+    if let Some(opt_soft_delete) = data.opt_soft_delete() {
+        collection_data.set_opt_soft_delete(opt_soft_delete);
+    }
+
+    if let Some(opt_per_record_ttl) = data.opt_per_record_ttl() {
+        collection_data.set_opt_per_record_ttl(opt_per_record_ttl);
+    }
+
+    if let Some(opt_mqtt_publish) = data.opt_mqtt_publish() {
+        collection_data.set_opt_mqtt_publish(opt_mqtt_publish);
+    }
+
     if !data.is_all_none() {
         if let Err(err) = collection_data.db_update(ctx.dao().db()).await {
             return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
         }
+        websocket_broadcast_project(
+            ctx.websocket().handler(),
+            collection_data.project_id(),
+            MessageKind::UpdateOne,
+            "collection",
+            collection_data.id(),
+        );
+        audit(
+            &ctx,
+            &req,
+            &admin_id,
+            project_data.id(),
+            &AuditAction::Update,
+            &AuditEntity::Collection,
+            collection_data.id(),
+            &before,
+            &collection_state(&collection_data),
+        );
     }
 
     Response::data(
@@ -492,19 +786,42 @@ async fn update_one(
                             &Some(*value.auth_column()),
                             &Some(*value.hashed()),
                             &Some(*value.hidden()),
+                            &value
+                                .default()
+                                .as_ref()
+                                .and_then(|default| default.to_serde_json().ok()),
+                            value.min(),
+                            value.max(),
+                            value.max_length(),
+                            value.regex(),
+                            &value.enum_values().as_ref().map(|enum_values| {
+                                enum_values
+                                    .iter()
+                                    .filter_map(|value| value.to_serde_json().ok())
+                                    .collect()
+                            }),
+                            &value.kind().reference_collection_id().copied(),
+                            &value
+                                .on_delete()
+                                .map(|on_delete| on_delete.to_str().to_owned()),
                         ),
                     )
                 })
                 .collect(),
+            collection_data.unique_indexes(),
             collection_data.opt_auth_column_id(),
             collection_data.opt_ttl(),
+            collection_data.opt_soft_delete(),
+            collection_data.opt_per_record_ttl(),
+            collection_data.opt_mqtt_publish(),
         ),
     )
 }
 
 async fn delete_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<DeleteOneCollectionReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -537,23 +854,78 @@ async fn delete_one(
         CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
     ) {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
     }
 
     if project_data.id() != collection_data.project_id() {
         return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
     }
 
+    let collections_data = match CollectionDao::db_select_many_by_project_id(
+        ctx.dao().db(),
+        project_data.id(),
+        &None,
+        &None,
+        &CollectionOrder::CreatedAtDesc,
+        &None,
+    )
+    .await
+    {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+    for other_collection_data in &collections_data {
+        if other_collection_data.id() == collection_data.id() {
+            continue;
+        }
+        if let Some((field, _)) = other_collection_data
+            .reference_fields(collection_data.id())
+            .first()
+        {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &format!(
+                    "Collection is referenced by field '{field}' of collection '{}'",
+                    other_collection_data.name()
+                ),
+            );
+        }
+    }
+
     if let Err(err) = CollectionDao::db_delete(ctx.dao().db(), path.collection_id()).await {
         return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
     }
+    websocket_broadcast_project(
+        ctx.websocket().handler(),
+        collection_data.project_id(),
+        MessageKind::DeleteOne,
+        "collection",
+        collection_data.id(),
+    );
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        project_data.id(),
+        &AuditAction::Delete,
+        &AuditEntity::Collection,
+        collection_data.id(),
+        &collection_state(&collection_data),
+        &None,
+    );
 
     Response::data(
         &StatusCode::OK,
@@ -562,10 +934,12 @@ async fn delete_one(
     )
 }
 
-async fn find_many(
+async fn duplicate_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
-    path: web::Path<FindManyCollectionReqPath>,
+    req: HttpRequest,
+    auth: ApiAuth,
+    path: web::Path<DuplicateOneCollectionReqPath>,
+    data: web::Json<DuplicateOneCollectionReqJson>,
 ) -> HttpResponse {
     let token = auth.token();
 
@@ -592,34 +966,600 @@ async fn find_many(
         }
     };
 
-    let project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
+    let (project_data, collection_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
+    ) {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if project_data.admin_id() != &admin_id {
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if project_data.id() != collection_data.project_id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    let include_records = data.include_records().unwrap_or(false);
+    let preserve_ids = data.preserve_ids().unwrap_or(false);
+    if preserve_ids && !include_records {
         return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
+            &StatusCode::BAD_REQUEST,
+            "'preserve_ids' can only be set when 'include_records' is true",
         );
     }
 
-    let collections_data = match CollectionDao::db_select_many_by_project_id(
+    let collection_data = match collection_data
+        .db_duplicate(
+            ctx.dao().db(),
+            data.new_name(),
+            &include_records,
+            &preserve_ids,
+        )
+        .await
+    {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+    websocket_broadcast_project(
+        ctx.websocket().handler(),
+        collection_data.project_id(),
+        MessageKind::InsertOne,
+        "collection",
+        collection_data.id(),
+    );
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        project_data.id(),
+        &AuditAction::Create,
+        &AuditEntity::Collection,
+        collection_data.id(),
+        &None,
+        &collection_state(&collection_data),
+    );
+
+    Response::data(
+        &StatusCode::CREATED,
+        &None,
+        &CollectionResJson::new(
+            collection_data.id(),
+            collection_data.created_at(),
+            collection_data.updated_at(),
+            collection_data.project_id(),
+            collection_data.name(),
+            &collection_data
+                .schema_fields()
+                .iter()
+                .map(|(field, props)| {
+                    (
+                        field.clone(),
+                        SchemaFieldPropsJson::new(
+                            props.kind().to_str(),
+                            &Some(*props.required()),
+                            &Some(*props.unique()),
+                            &Some(*props.indexed()),
+                            &Some(*props.auth_column()),
+                            &Some(*props.hashed()),
+                            &Some(*props.hidden()),
+                            &props
+                                .default()
+                                .as_ref()
+                                .and_then(|default| default.to_serde_json().ok()),
+                            props.min(),
+                            props.max(),
+                            props.max_length(),
+                            props.regex(),
+                            &props.enum_values().as_ref().map(|enum_values| {
+                                enum_values
+                                    .iter()
+                                    .filter_map(|value| value.to_serde_json().ok())
+                                    .collect()
+                            }),
+                            &props.kind().reference_collection_id().copied(),
+                            &props
+                                .on_delete()
+                                .map(|on_delete| on_delete.to_str().to_owned()),
+                        ),
+                    )
+                })
+                .collect(),
+            collection_data.unique_indexes(),
+            collection_data.opt_auth_column_id(),
+            collection_data.opt_ttl(),
+            collection_data.opt_soft_delete(),
+            collection_data.opt_per_record_ttl(),
+            collection_data.opt_mqtt_publish(),
+        ),
+    )
+}
+
+async fn export_schema(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<ExportSchemaCollectionReqPath>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let (project_data, collection_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
         ctx.dao().db(),
-        path.project_id(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if project_data.id() != collection_data.project_id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &collection_schema_json(&collection_data),
+    )
+}
+
+// Creates the collection described by the document, or migrates the collection of the same name in
+// the project to match it. A dry run only reports the changes.
+async fn stats(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<StatsCollectionReqPath>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let (project_data, collection_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if project_data.id() != collection_data.project_id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    let stats_data = match RecordDao::db_stats(ctx.dao().db(), collection_data.id()).await {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &StatsCollectionResJson::new(
+            stats_data.count(),
+            stats_data.size(),
+            stats_data.oldest_created_at(),
+            stats_data.newest_created_at(),
+            stats_data.indexes(),
+        ),
+    )
+}
+
+async fn apply_schema(
+    ctx: web::Data<ApiRestCtx>,
+    req: HttpRequest,
+    auth: ApiAuth,
+    path: web::Path<ApplySchemaCollectionReqPath>,
+    query: web::Query<ApplySchemaCollectionReqQuery>,
+    data: web::Json<CollectionSchemaJson>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
+    }
+
+    let schema_fields = match schema_fields_from_json(data.schema_fields(), &HashMap::new()) {
+        Ok(schema_fields) => schema_fields,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    let collection_data = match CollectionDao::db_select_many_by_project_id(
+        ctx.dao().db(),
+        project_data.id(),
+        &None,
+        &None,
+        &CollectionOrder::CreatedAtDesc,
+        &None,
+    )
+    .await
+    {
+        Ok(collections_data) => collections_data
+            .into_iter()
+            .find(|collection_data| collection_data.name() == data.name()),
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    for (field, props) in &schema_fields {
+        if let Some(reference_collection_id) = props.kind().reference_collection_id() {
+            if collection_data
+                .as_ref()
+                .is_some_and(|collection_data| collection_data.id() == reference_collection_id)
+            {
+                continue;
+            }
+            match CollectionDao::db_select(ctx.dao().db(), reference_collection_id).await {
+                Ok(reference_collection_data) => {
+                    if reference_collection_data.project_id() != project_data.id() {
+                        return Response::error_raw(
+                            &StatusCode::BAD_REQUEST,
+                            &format!(
+                                "Field '{field}' can't reference a collection of another project"
+                            ),
+                        );
+                    }
+                }
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!(
+                            "Field '{field}' references a collection that can't be found: {err}"
+                        ),
+                    )
+                }
+            }
+        }
+    }
+
+    let dry_run = query.dry_run().unwrap_or(false);
+    let opt_ttl = data.opt_ttl().filter(|opt_ttl| *opt_ttl > 0);
+
+    let mut collection_data = match collection_data {
+        Some(collection_data) => collection_data,
+        None => {
+            let mut added_fields = schema_fields.keys().cloned().collect::<Vec<_>>();
+            added_fields.sort_unstable();
+            let collection_id = if dry_run {
+                None
+            } else {
+                let collection_data = CollectionDao::new(
+                    project_data.id(),
+                    data.name(),
+                    &schema_fields,
+                    data.unique_indexes(),
+                    data.opt_auth_column_id(),
+                    &opt_ttl,
+                    data.opt_soft_delete(),
+                    data.opt_per_record_ttl(),
+                    data.opt_mqtt_publish(),
+                );
+                if let Err(err) = collection_data.db_insert(ctx.dao().db()).await {
+                    return Response::error_raw(
+                        &StatusCode::INTERNAL_SERVER_ERROR,
+                        &err.to_string(),
+                    );
+                }
+                websocket_broadcast_project(
+                    ctx.websocket().handler(),
+                    collection_data.project_id(),
+                    MessageKind::InsertOne,
+                    "collection",
+                    collection_data.id(),
+                );
+                audit(
+                    &ctx,
+                    &req,
+                    &admin_id,
+                    project_data.id(),
+                    &AuditAction::Create,
+                    &AuditEntity::Collection,
+                    collection_data.id(),
+                    &None,
+                    &collection_state(&collection_data),
+                );
+                Some(*collection_data.id())
+            };
+            return Response::data(
+                &StatusCode::CREATED,
+                &None,
+                &ApplySchemaCollectionResJson::new(
+                    &collection_id,
+                    &true,
+                    &dry_run,
+                    &added_fields,
+                    &Vec::new(),
+                    &Vec::new(),
+                    &Vec::new(),
+                ),
+            );
+        }
+    };
+
+    let changes = collection_data.schema_fields_changes(&schema_fields);
+    let backfill_values = match required_fields_backfill_values(
+        &ctx,
+        &collection_data,
+        data.schema_fields(),
+        &schema_fields,
+        &false,
+    )
+    .await
+    {
+        Ok(backfill_values) => backfill_values,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err),
+    };
+    let mut changed_options = Vec::new();
+    if collection_data.unique_indexes() != data.unique_indexes() {
+        changed_options.push("unique_indexes".to_owned());
+    }
+    if collection_data.opt_auth_column_id() != data.opt_auth_column_id() {
+        changed_options.push("opt_auth_column_id".to_owned());
+    }
+    if collection_data.opt_ttl() != &opt_ttl {
+        changed_options.push("opt_ttl".to_owned());
+    }
+    if collection_data.opt_soft_delete() != data.opt_soft_delete() {
+        changed_options.push("opt_soft_delete".to_owned());
+    }
+    if collection_data.opt_per_record_ttl() != data.opt_per_record_ttl() {
+        changed_options.push("opt_per_record_ttl".to_owned());
+    }
+    if collection_data.opt_mqtt_publish() != data.opt_mqtt_publish() {
+        changed_options.push("opt_mqtt_publish".to_owned());
+    }
+
+    if !dry_run && (!changes.is_empty() || !changed_options.is_empty()) {
+        let before = collection_state(&collection_data);
+        if !changes.is_empty() {
+            collection_data.update_schema_fields(&schema_fields, &false, &backfill_values);
+        }
+        if collection_data.unique_indexes() != data.unique_indexes() {
+            collection_data.set_unique_indexes(data.unique_indexes());
+        }
+        collection_data.set_opt_auth_column_id(data.opt_auth_column_id());
+        collection_data.set_opt_ttl(&opt_ttl);
+        if collection_data.opt_soft_delete() != data.opt_soft_delete() {
+            collection_data.set_opt_soft_delete(data.opt_soft_delete());
+        }
+        if collection_data.opt_per_record_ttl() != data.opt_per_record_ttl() {
+            collection_data.set_opt_per_record_ttl(data.opt_per_record_ttl());
+        }
+        collection_data.set_opt_mqtt_publish(data.opt_mqtt_publish());
+        if let Err(err) = collection_data.db_update(ctx.dao().db()).await {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+        }
+        websocket_broadcast_project(
+            ctx.websocket().handler(),
+            collection_data.project_id(),
+            MessageKind::UpdateOne,
+            "collection",
+            collection_data.id(),
+        );
+        audit(
+            &ctx,
+            &req,
+            &admin_id,
+            project_data.id(),
+            &AuditAction::Update,
+            &AuditEntity::Collection,
+            collection_data.id(),
+            &before,
+            &collection_state(&collection_data),
+        );
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &ApplySchemaCollectionResJson::new(
+            &Some(*collection_data.id()),
+            &false,
+            &dry_run,
+            changes.added(),
+            changes.removed(),
+            changes.changed(),
+            &changed_options,
+        ),
+    )
+}
+
+async fn find_many(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<FindManyCollectionReqPath>,
+    query: web::Query<FindManyCollectionReqQuery>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
     )
     .await
     {
+        return err;
+    }
+
+    let order = match query.order_by() {
+        Some(order_by) => match CollectionOrder::from_str(order_by) {
+            Ok(order) => order,
+            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        },
+        None => CollectionOrder::CreatedAtDesc,
+    };
+
+    let (collections_data, total) = match tokio::try_join!(
+        CollectionDao::db_select_many_by_project_id(
+            ctx.dao().db(),
+            path.project_id(),
+            query.after_id(),
+            query.search(),
+            &order,
+            query.limit(),
+        ),
+        CollectionDao::db_count_many_by_project_id(
+            ctx.dao().db(),
+            path.project_id(),
+            query.search(),
+        )
+    ) {
         Ok(data) => data,
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
+    let total = match usize::try_from(total) {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
     Response::data(
         &StatusCode::OK,
-        &Some(PaginationRes::new(
-            &collections_data.len(),
-            &collections_data.len(),
-        )),
+        &Some(PaginationRes::new(&collections_data.len(), &total)),
         &collections_data
             .iter()
             .map(|data| {
@@ -643,14 +1583,150 @@ async fn find_many(
                                     &Some(*value.auth_column()),
                                     &Some(*value.hashed()),
                                     &Some(*value.hidden()),
+                                    &value
+                                        .default()
+                                        .as_ref()
+                                        .and_then(|default| default.to_serde_json().ok()),
+                                    value.min(),
+                                    value.max(),
+                                    value.max_length(),
+                                    value.regex(),
+                                    &value.enum_values().as_ref().map(|enum_values| {
+                                        enum_values
+                                            .iter()
+                                            .filter_map(|value| value.to_serde_json().ok())
+                                            .collect()
+                                    }),
+                                    &value.kind().reference_collection_id().copied(),
+                                    &value
+                                        .on_delete()
+                                        .map(|on_delete| on_delete.to_str().to_owned()),
                                 ),
                             )
                         })
                         .collect(),
+                    data.unique_indexes(),
                     data.opt_auth_column_id(),
                     data.opt_ttl(),
+                    data.opt_soft_delete(),
+                    data.opt_per_record_ttl(),
+                    data.opt_mqtt_publish(),
                 )
             })
             .collect::<Vec<_>>(),
     )
 }
+
+// Records that exist before a required field is added need a value for it, taken from the
+// 'backfill_value' of the field or from its default when 'backfill_defaults' is set. Returns the
+// values to backfill, or the reason the fields can't be added instead of a response.
+async fn required_fields_backfill_values(
+    ctx: &ApiRestCtx,
+    collection_data: &CollectionDao,
+    schema_fields_json: &HashMap<String, SchemaFieldPropsJson>,
+    schema_fields: &HashMap<String, SchemaFieldProps>,
+    backfill_defaults: &bool,
+) -> Result<HashMap<String, ColumnValue>, String> {
+    let mut backfill_values = HashMap::new();
+    let mut unfilled_fields = Vec::new();
+    for (field, props) in schema_fields {
+        if collection_data.schema_fields().contains_key(field) {
+            continue;
+        }
+        match schema_fields_json
+            .get(field)
+            .and_then(|props_json| props_json.backfill_value().as_ref())
+        {
+            Some(value) => {
+                if *props.hashed() || *props.kind() == ColumnKind::Counter {
+                    return Err(format!(
+                        "Field '{field}' can't have a 'backfill_value' because it is hashed or a counter"
+                    ));
+                }
+                match props.value_from_serde_json(value) {
+                    Ok(value) => {
+                        backfill_values.insert(field.to_owned(), value);
+                    }
+                    Err(err) => {
+                        return Err(format!(
+                            "Error in 'backfill_value' of field '{field}': {err}"
+                        ))
+                    }
+                }
+            }
+            None => {
+                if *props.required() && !(*backfill_defaults && props.default().is_some()) {
+                    unfilled_fields.push(field.as_str());
+                }
+            }
+        }
+    }
+
+    if !unfilled_fields.is_empty() {
+        let count = RecordDao::db_count(ctx.dao().db(), collection_data.id())
+            .await
+            .map_err(|err| format!("Failed to count the records of the collection: {err}"))?;
+        if count > 0 {
+            unfilled_fields.sort_unstable();
+            return Err(format!(
+                "Required field(s) '{}' can't be added because {count} existing record(s) would have no value for them, set their 'backfill_value' or a 'default' together with 'backfill_defaults'",
+                unfilled_fields.join("', '")
+            ));
+        }
+    }
+
+    Ok(backfill_values)
+}
+
+// The collection as the API returns it, which is what the audit log compares
+fn collection_state(collection_data: &CollectionDao) -> Option<Value> {
+    audit_state(&CollectionResJson::new(
+        collection_data.id(),
+        collection_data.created_at(),
+        collection_data.updated_at(),
+        collection_data.project_id(),
+        collection_data.name(),
+        &collection_data
+            .schema_fields()
+            .iter()
+            .map(|(field, props)| {
+                (
+                    field.clone(),
+                    SchemaFieldPropsJson::new(
+                        props.kind().to_str(),
+                        &Some(*props.required()),
+                        &Some(*props.unique()),
+                        &Some(*props.indexed()),
+                        &Some(*props.auth_column()),
+                        &Some(*props.hashed()),
+                        &Some(*props.hidden()),
+                        &props
+                            .default()
+                            .as_ref()
+                            .and_then(|default| default.to_serde_json().ok()),
+                        props.min(),
+                        props.max(),
+                        props.max_length(),
+                        props.regex(),
+                        &props.enum_values().as_ref().map(|enum_values| {
+                            enum_values
+                                .iter()
+                                .filter_map(|value| value.to_serde_json().ok())
+                                .collect()
+                        }),
+                        &props.kind().reference_collection_id().copied(),
+                        &props
+                            .on_delete()
+                            .map(|on_delete| on_delete.to_str().to_owned()),
+                    ),
+                )
+            })
+            .collect(),
+        collection_data.unique_indexes(),
+        collection_data.opt_auth_column_id(),
+        collection_data.opt_ttl(),
+        collection_data.opt_soft_delete(),
+        collection_data.opt_per_record_ttl(),
+        collection_data.opt_mqtt_publish(),
+    ))
+}
@@ -1,13 +1,16 @@
-use actix_web::{http::StatusCode, web, HttpResponse};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
+use hb_api_websocket::message::MessageKind;
 use hb_dao::{
     admin::AdminDao,
+    audit::{AuditAction, AuditEntity},
     collection::CollectionDao,
-    collection_rule::{CollectionPermission, CollectionRuleDao},
+    collection_rule::{CollectionPermission, CollectionRuleDao, ALL_COLLECTIONS_ID},
     project::ProjectDao,
     token::TokenDao,
 };
 use hb_token_jwt::claim::ClaimId;
+use serde_json::Value;
+use uuid::Uuid;
 
 use crate::{
     context::ApiRestCtx,
@@ -20,6 +23,12 @@ use crate::{
         },
         PaginationRes, Response,
     },
+    util::{
+        api_auth::ApiAuth,
+        audit::{audit, audit_state},
+        project_access::{authorize_project, ProjectAccess},
+        ws_broadcast::{websocket_broadcast_project, websocket_invalidate_token},
+    },
 };
 
 pub fn collection_rule_api(cfg: &mut web::ServiceConfig) {
@@ -47,7 +56,8 @@ pub fn collection_rule_api(cfg: &mut web::ServiceConfig) {
 
 async fn insert_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<InsertOneCollectionRuleReqPath>,
     data: web::Json<InsertOneCollectionRuleReqJson>,
 ) -> HttpResponse {
@@ -76,37 +86,66 @@ async fn insert_one(
         }
     };
 
-    let (project_data, token_data, collection_data) = match tokio::try_join!(
+    // "*" makes a rule for every collection in the project
+    let collection_id = if data.collection_id() == "*" {
+        ALL_COLLECTIONS_ID
+    } else {
+        match Uuid::parse_str(data.collection_id()) {
+            Ok(id) => id,
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    &format!("Collection id must be a uuid or '*': {err}"),
+                )
+            }
+        }
+    };
+
+    let (project_data, token_data) = match tokio::try_join!(
         ProjectDao::db_select(ctx.dao().db(), path.project_id()),
         TokenDao::db_select(ctx.dao().db(), path.token_id()),
-        CollectionDao::db_select(ctx.dao().db(), data.collection_id())
     ) {
         Ok(data) => data,
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
     }
 
     if token_data.project_id() != project_data.id() {
         return Response::error_raw(&StatusCode::FORBIDDEN, "This token does not belong to you");
     }
 
-    if collection_data.project_id() != project_data.id() {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This collection does not belong to you",
-        );
+    if collection_id != ALL_COLLECTIONS_ID {
+        let collection_data = match CollectionDao::db_select(ctx.dao().db(), &collection_id).await {
+            Ok(data) => data,
+            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        };
+        if collection_data.project_id() != project_data.id() {
+            return Response::error_raw(
+                &StatusCode::FORBIDDEN,
+                "This collection does not belong to you",
+            );
+        }
+        for fields in [data.readable_fields(), data.writable_fields()] {
+            if let Err(err) = check_rule_fields(&collection_data, fields) {
+                return err;
+            }
+        }
     }
 
     if let Ok(_) = CollectionRuleDao::db_select_by_token_id_and_collection_id(
         ctx.dao().db(),
         token_data.id(),
-        collection_data.id(),
+        &collection_id,
     )
     .await
     {
@@ -136,17 +175,38 @@ async fn insert_one(
     let collection_rule_data = CollectionRuleDao::new(
         project_data.id(),
         token_data.id(),
-        collection_data.id(),
+        &collection_id,
         &rule_find_one,
         &rule_find_many,
         data.insert_one(),
         &rule_update_one,
         &rule_delete_one,
+        data.readable_fields(),
+        data.writable_fields(),
     );
 
     if let Err(err) = collection_rule_data.db_insert(ctx.dao().db()).await {
         return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
     }
+    websocket_invalidate_token(ctx.websocket().handler(), token_data.id());
+    websocket_broadcast_project(
+        ctx.websocket().handler(),
+        collection_rule_data.project_id(),
+        MessageKind::InsertOne,
+        "collection_rule",
+        collection_rule_data.id(),
+    );
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        project_data.id(),
+        &AuditAction::Create,
+        &AuditEntity::CollectionRule,
+        collection_rule_data.id(),
+        &None,
+        &collection_rule_state(&collection_rule_data),
+    );
 
     Response::data(
         &StatusCode::CREATED,
@@ -163,13 +223,15 @@ async fn insert_one(
             collection_rule_data.insert_one(),
             collection_rule_data.update_one().to_str(),
             collection_rule_data.delete_one().to_str(),
+            collection_rule_data.readable_fields(),
+            collection_rule_data.writable_fields(),
         ),
     )
 }
 
 async fn find_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     path: web::Path<FindOneCollectionRuleReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -231,13 +293,16 @@ async fn find_one(
             collection_rule_data.insert_one(),
             collection_rule_data.update_one().to_str(),
             collection_rule_data.delete_one().to_str(),
+            collection_rule_data.readable_fields(),
+            collection_rule_data.writable_fields(),
         ),
     )
 }
 
 async fn update_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<UpdateOneCollectionRuleReqPath>,
     data: web::Json<UpdateOneCollectionRuleReqJson>,
 ) -> HttpResponse {
@@ -285,6 +350,8 @@ async fn update_one(
         );
     }
 
+    let before = collection_rule_state(&collection_rule_data);
+
     if let Some(find_one) = data.find_one() {
         let find_one = match CollectionPermission::from_str(find_one) {
             Ok(rule) => rule,
@@ -321,10 +388,60 @@ async fn update_one(
         collection_rule_data.set_delete_one(&delete_one);
     }
 
+    if data.readable_fields().is_some() || data.writable_fields().is_some() {
+        // Wildcard rules aren't tied to a schema, so their fields can't be checked up front
+        if !collection_rule_data.is_wildcard() {
+            let collection_data = match CollectionDao::db_select(
+                ctx.dao().db(),
+                collection_rule_data.collection_id(),
+            )
+            .await
+            {
+                Ok(data) => data,
+                Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+            };
+            for fields in [data.readable_fields(), data.writable_fields()]
+                .into_iter()
+                .flatten()
+            {
+                if let Err(err) = check_rule_fields(&collection_data, fields) {
+                    return err;
+                }
+            }
+        }
+
+        if let Some(readable_fields) = data.readable_fields() {
+            collection_rule_data.set_readable_fields(readable_fields);
+        }
+
+        if let Some(writable_fields) = data.writable_fields() {
+            collection_rule_data.set_writable_fields(writable_fields);
+        }
+    }
+
     if !data.is_all_none() {
         if let Err(err) = collection_rule_data.db_update(ctx.dao().db()).await {
             return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
         }
+        websocket_invalidate_token(ctx.websocket().handler(), token_data.id());
+        websocket_broadcast_project(
+            ctx.websocket().handler(),
+            collection_rule_data.project_id(),
+            MessageKind::UpdateOne,
+            "collection_rule",
+            collection_rule_data.id(),
+        );
+        audit(
+            &ctx,
+            &req,
+            &admin_id,
+            collection_rule_data.project_id(),
+            &AuditAction::Update,
+            &AuditEntity::CollectionRule,
+            collection_rule_data.id(),
+            &before,
+            &collection_rule_state(&collection_rule_data),
+        );
     }
 
     Response::data(
@@ -342,13 +459,16 @@ async fn update_one(
             collection_rule_data.insert_one(),
             collection_rule_data.update_one().to_str(),
             collection_rule_data.delete_one().to_str(),
+            collection_rule_data.readable_fields(),
+            collection_rule_data.writable_fields(),
         ),
     )
 }
 
 async fn delete_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<DeleteOneCollectionRuleReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -398,6 +518,25 @@ async fn delete_one(
     if let Err(err) = CollectionRuleDao::db_delete(ctx.dao().db(), path.rule_id()).await {
         return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
     }
+    websocket_invalidate_token(ctx.websocket().handler(), token_data.id());
+    websocket_broadcast_project(
+        ctx.websocket().handler(),
+        collection_rule_data.project_id(),
+        MessageKind::DeleteOne,
+        "collection_rule",
+        collection_rule_data.id(),
+    );
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        collection_rule_data.project_id(),
+        &AuditAction::Delete,
+        &AuditEntity::CollectionRule,
+        collection_rule_data.id(),
+        &collection_rule_state(&collection_rule_data),
+        &None,
+    );
 
     Response::data(
         &StatusCode::OK,
@@ -408,7 +547,7 @@ async fn delete_one(
 
 async fn find_many(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     path: web::Path<FindManyCollectionRuleReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -469,8 +608,46 @@ async fn find_many(
                     data.insert_one(),
                     data.update_one().to_str(),
                     data.delete_one().to_str(),
+                    data.readable_fields(),
+                    data.writable_fields(),
                 )
             })
             .collect::<Vec<_>>(),
     )
 }
+
+fn check_rule_fields(
+    collection_data: &CollectionDao,
+    fields: &Option<Vec<String>>,
+) -> Result<(), HttpResponse> {
+    if let Some(fields) = fields {
+        for field in fields {
+            if !collection_data.schema_fields().contains_key(field) {
+                return Err(Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    &format!("Field '{field}' is not exist in the collection"),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// The collection rule as the API returns it, which is what the audit log compares
+fn collection_rule_state(collection_rule_data: &CollectionRuleDao) -> Option<Value> {
+    audit_state(&CollectionRuleResJson::new(
+        collection_rule_data.id(),
+        collection_rule_data.created_at(),
+        collection_rule_data.updated_at(),
+        collection_rule_data.project_id(),
+        collection_rule_data.token_id(),
+        collection_rule_data.collection_id(),
+        collection_rule_data.find_one().to_str(),
+        collection_rule_data.find_many().to_str(),
+        collection_rule_data.insert_one(),
+        collection_rule_data.update_one().to_str(),
+        collection_rule_data.delete_one().to_str(),
+        collection_rule_data.readable_fields(),
+        collection_rule_data.writable_fields(),
+    ))
+}
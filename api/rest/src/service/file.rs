@@ -1,32 +1,55 @@
+use std::str::FromStr;
+
 use actix_files::NamedFile;
 use actix_multipart::form::MultipartForm;
 use actix_web::{
     http::{
-        header::{self, HeaderValue},
+        header::{self, EntityTag, HeaderValue, IfNoneMatch},
         StatusCode,
     },
-    web, HttpRequest, HttpResponse,
+    web, HttpMessage, HttpRequest, HttpResponse,
 };
-use actix_web_httpauth::extractors::bearer::BearerAuth;
 use ahash::HashSet;
+use chrono::Utc;
+use futures::StreamExt;
+use hb_api_websocket::message::{MessageKind as WebSocketMessageKind, Target as WebSocketTarget};
 use hb_dao::{
-    admin::AdminDao, bucket::BucketDao, bucket_rule::BucketPermission, collection::CollectionDao,
-    file::FileDao, project::ProjectDao, record::RecordDao, token::TokenDao,
+    admin::AdminDao,
+    bucket::BucketDao,
+    bucket_rule::BucketPermission,
+    collection::CollectionDao,
+    error::DaoError,
+    file::{BlobStatus, FileDao, FileFilter, FileOrder, ImageFit, ImageFormat},
+    project::ProjectDao,
+    record::RecordDao,
+    token::TokenDao,
 };
 use hb_token_jwt::claim::ClaimId;
+use mime::Mime;
+use tokio::io::AsyncWriteExt;
 use uuid::Uuid;
 
 use crate::{
     context::ApiRestCtx,
     model::{
         file::{
-            DeleteFileResJson, DeleteOneFileReqPath, FileResJson, FindManyFileReqPath,
-            FindManyFileReqQuery, FindOneFileReqPath, FindOneFileReqQuery, HeadFindOneFileReqPath,
-            HeadFindOneFileReqQuery, InsertOneFileReqForm, InsertOneFileReqPath,
-            UpdateOneFileReqJson, UpdateOneFileReqPath,
+            CompleteFileUploadReqPath, DeleteFileResJson, DeleteManyFileErrorResJson,
+            DeleteManyFileReqJson, DeleteManyFileReqPath, DeleteManyFileResJson,
+            DeleteOneFileReqPath, FileResJson, FileUploadResJson, FindManyFileReqPath,
+            FindManyFileReqQuery, FindOneFileReqPath, FindOneFileReqQuery,
+            FindOneFileUploadReqPath, HeadFindOneFileReqPath, HeadFindOneFileReqQuery,
+            InsertOneFileReqForm, InsertOneFileReqPath, InsertOneFileUploadReqJson,
+            InsertOneFileUploadReqPath, PresignFileResJson, PresignOneFileReqJson,
+            PresignOneFileReqPath, ReplaceOneFileContentReqForm, ReplaceOneFileContentReqPath,
+            UpdateOneFileReqJson, UpdateOneFileReqPath, UpdateOneFileUploadReqPath,
         },
         PaginationRes, Response,
     },
+    util::{
+        api_auth::ApiAuth,
+        project_access::{authorize_project, ProjectAccess},
+        ws_broadcast::websocket_broadcast,
+    },
 };
 
 pub fn file_api(cfg: &mut web::ServiceConfig) {
@@ -34,6 +57,22 @@ pub fn file_api(cfg: &mut web::ServiceConfig) {
         "/project/{project_id}/bucket/{bucket_id}/file",
         web::post().to(insert_one),
     )
+    .route(
+        "/project/{project_id}/bucket/{bucket_id}/file/uploads",
+        web::post().to(insert_one_upload),
+    )
+    .route(
+        "/project/{project_id}/bucket/{bucket_id}/file/uploads/{upload_id}",
+        web::get().to(find_one_upload),
+    )
+    .route(
+        "/project/{project_id}/bucket/{bucket_id}/file/uploads/{upload_id}",
+        web::put().to(update_one_upload),
+    )
+    .route(
+        "/project/{project_id}/bucket/{bucket_id}/file/uploads/{upload_id}/complete",
+        web::post().to(complete_upload),
+    )
     .route(
         "/project/{project_id}/bucket/{bucket_id}/file/{file_id}",
         web::head().to(head_find_one),
@@ -42,10 +81,18 @@ pub fn file_api(cfg: &mut web::ServiceConfig) {
         "/project/{project_id}/bucket/{bucket_id}/file/{file_id}",
         web::get().to(find_one),
     )
+    .route(
+        "/project/{project_id}/bucket/{bucket_id}/file/{file_id}/presign",
+        web::post().to(presign_one),
+    )
     .route(
         "/project/{project_id}/bucket/{bucket_id}/file/{file_id}",
         web::patch().to(update_one),
     )
+    .route(
+        "/project/{project_id}/bucket/{bucket_id}/file/{file_id}/content",
+        web::put().to(replace_content),
+    )
     .route(
         "/project/{project_id}/bucket/{bucket_id}/file/{file_id}",
         web::delete().to(delete_one),
@@ -53,15 +100,25 @@ pub fn file_api(cfg: &mut web::ServiceConfig) {
     .route(
         "/project/{project_id}/bucket/{bucket_id}/files",
         web::get().to(find_many),
+    )
+    .route(
+        "/project/{project_id}/bucket/{bucket_id}/files/delete",
+        web::post().to(delete_many),
     );
 }
 
 async fn insert_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<InsertOneFileReqPath>,
     form: MultipartForm<InsertOneFileReqForm>,
 ) -> HttpResponse {
+    let expected_checksum = match content_sha256(&req) {
+        Ok(checksum) => checksum,
+        Err(res) => return res,
+    };
+
     let token = auth.token();
 
     let token_claim = match ctx.token().jwt().decode(token) {
@@ -80,8 +137,11 @@ async fn insert_one(
             }
         },
         ClaimId::Token(token_id, user_claim) => {
-            match TokenDao::db_select(ctx.dao().db(), token_id).await {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
                 Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                }
                 Err(err) => {
                     return Response::error_raw(
                         &StatusCode::BAD_REQUEST,
@@ -112,11 +172,15 @@ async fn insert_one(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if &admin_id != project_data.admin_id() {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
     }
 
     if project_data.id() != bucket_data.project_id() {
@@ -175,7 +239,13 @@ async fn insert_one(
             )
         }
     };
-    let file_data = FileDao::new(
+    if let Err(res) = check_bucket_limits(&bucket_data, &size, &content_type) {
+        return res;
+    }
+    if let Err(res) = check_bucket_quota(&bucket_data, &size) {
+        return res;
+    }
+    let mut file_data = FileDao::new(
         &created_by,
         path.bucket_id(),
         &file_name,
@@ -185,12 +255,94 @@ async fn insert_one(
     );
 
     if let Err(err) = file_data
-        .save(ctx.dao().db(), bucket_data.path(), form.file_path())
+        .save(
+            ctx.dao().db(),
+            bucket_data.path(),
+            form.file_path(),
+            &expected_checksum,
+        )
         .await
     {
-        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+        return Response::error_dao(&StatusCode::INTERNAL_SERVER_ERROR, &err);
+    }
+
+    broadcast_file(&ctx, WebSocketMessageKind::InsertOne, &file_data);
+
+    Response::data(
+        &StatusCode::CREATED,
+        &None,
+        &FileResJson::new(
+            file_data.id(),
+            file_data.created_by(),
+            file_data.created_at(),
+            file_data.updated_at(),
+            file_data.bucket_id(),
+            file_data.file_name(),
+            &file_data.content_type().to_string(),
+            file_data.size(),
+            file_data.public(),
+            file_data.pending(),
+            file_data.checksum(),
+        ),
+    )
+}
+
+async fn insert_one_upload(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<InsertOneFileUploadReqPath>,
+    data: web::Json<InsertOneFileUploadReqJson>,
+) -> HttpResponse {
+    if *data.size() < 0 {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "File size can't be negative");
+    }
+
+    let (bucket_data, created_by, _) =
+        match authorize_upload(&ctx, &auth, path.project_id(), path.bucket_id()).await {
+            Ok(data) => data,
+            Err(res) => return res,
+        };
+
+    let file_name = match data.file_name() {
+        Some(file_name) => file_name.to_owned(),
+        None => Uuid::now_v7().to_string(),
+    };
+    let content_type = match data.content_type() {
+        Some(content_type) => match Mime::from_str(content_type) {
+            Ok(content_type) => content_type,
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    &format!("Invalid content type: {err}"),
+                )
+            }
+        },
+        None => mime::APPLICATION_OCTET_STREAM,
+    };
+    if let Err(res) = check_bucket_limits(&bucket_data, data.size(), &content_type) {
+        return res;
+    }
+    if let Err(res) = check_bucket_quota(&bucket_data, data.size()) {
+        return res;
+    }
+    let mut file_data = FileDao::new(
+        &created_by,
+        bucket_data.id(),
+        &file_name,
+        &content_type,
+        data.size(),
+        &data.public().unwrap_or(false),
+    );
+
+    if let Err(err) = file_data
+        .start_upload(ctx.dao().db(), bucket_data.path())
+        .await
+    {
+        return Response::error_dao(&StatusCode::INTERNAL_SERVER_ERROR, &err);
     }
 
+    broadcast_file(&ctx, WebSocketMessageKind::InsertOne, &file_data);
+
     Response::data(
         &StatusCode::CREATED,
         &None,
@@ -204,6 +356,242 @@ async fn insert_one(
             &file_data.content_type().to_string(),
             file_data.size(),
             file_data.public(),
+            file_data.pending(),
+            file_data.checksum(),
+        ),
+    )
+}
+
+async fn find_one_upload(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<FindOneFileUploadReqPath>,
+) -> HttpResponse {
+    let (bucket_data, created_by, is_admin) =
+        match authorize_upload(&ctx, &auth, path.project_id(), path.bucket_id()).await {
+            Ok(data) => data,
+            Err(res) => return res,
+        };
+
+    let file_data =
+        match select_upload(&ctx, &bucket_data, path.upload_id(), &created_by, &is_admin).await {
+            Ok(data) => data,
+            Err(res) => return res,
+        };
+
+    let uploaded_size = match file_data.uploaded_size(bucket_data.path()).await {
+        Ok(size) => size,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &FileUploadResJson::new(file_data.id(), file_data.size(), &uploaded_size),
+    )
+}
+
+// Appends a chunk to an upload session. Chunks must arrive in order, so a client that lost its
+// connection asks for the uploaded size and resumes from there
+async fn update_one_upload(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    req: HttpRequest,
+    path: web::Path<UpdateOneFileUploadReqPath>,
+    mut payload: web::Payload,
+) -> HttpResponse {
+    let (start, end, total) = match req
+        .headers()
+        .get(header::CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_content_range)
+    {
+        Some(content_range) => content_range,
+        None => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Content-Range header must be in the form of 'bytes <start>-<end>/<size>'",
+            )
+        }
+    };
+
+    let (bucket_data, created_by, is_admin) =
+        match authorize_upload(&ctx, &auth, path.project_id(), path.bucket_id()).await {
+            Ok(data) => data,
+            Err(res) => return res,
+        };
+
+    let mut file_data =
+        match select_upload(&ctx, &bucket_data, path.upload_id(), &created_by, &is_admin).await {
+            Ok(data) => data,
+            Err(res) => return res,
+        };
+
+    if let Some(total) = total {
+        if total != *file_data.size() {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &format!(
+                    "Content-Range size {total} doesn't match the upload size {}",
+                    file_data.size()
+                ),
+            );
+        }
+    }
+    if end >= *file_data.size() {
+        return Response::error_raw(
+            &StatusCode::RANGE_NOT_SATISFIABLE,
+            &format!(
+                "Content-Range exceeds the upload size of {} bytes",
+                file_data.size()
+            ),
+        );
+    }
+
+    let uploaded_size = match file_data.uploaded_size(bucket_data.path()).await {
+        Ok(size) => size,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+    if start != uploaded_size {
+        return Response::error_raw(
+            &StatusCode::CONFLICT,
+            &format!("Chunk must start at byte {uploaded_size}"),
+        );
+    }
+
+    let mut file = match file_data.open_upload(bucket_data.path()).await {
+        Ok(file) => file,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    // Whatever arrives before the connection drops is kept, it is still a valid part of the upload
+    let chunk_size = end - start + 1;
+    let mut received = 0;
+    while let Some(chunk) = payload.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(_) => break,
+        };
+        received += chunk.len() as i64;
+        if received > chunk_size {
+            let _ = file.set_len(start as u64).await;
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Chunk is larger than its Content-Range",
+            );
+        }
+        if let Err(err) = file.write_all(&chunk).await {
+            let _ = file.set_len(start as u64).await;
+            return Response::error_raw(
+                &StatusCode::INTERNAL_SERVER_ERROR,
+                &format!("Failed to write chunk to the bucket storage: {err}"),
+            );
+        }
+    }
+    if let Err(err) = file.sync_all().await {
+        let _ = file.set_len(start as u64).await;
+        return Response::error_raw(
+            &StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("Failed to write chunk to the bucket storage: {err}"),
+        );
+    }
+
+    // Keeps the session alive for as long as chunks keep coming
+    if let Err(err) = file_data.db_update(ctx.dao().db()).await {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+
+    let uploaded_size = start + received;
+    if received < chunk_size {
+        return Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            &format!("Chunk is incomplete, the upload can be resumed from byte {uploaded_size}"),
+        );
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &FileUploadResJson::new(file_data.id(), file_data.size(), &uploaded_size),
+    )
+}
+
+async fn complete_upload(
+    ctx: web::Data<ApiRestCtx>,
+    req: HttpRequest,
+    auth: ApiAuth,
+    path: web::Path<CompleteFileUploadReqPath>,
+) -> HttpResponse {
+    let expected_checksum = match content_sha256(&req) {
+        Ok(checksum) => checksum,
+        Err(res) => return res,
+    };
+
+    let (bucket_data, created_by, is_admin) =
+        match authorize_upload(&ctx, &auth, path.project_id(), path.bucket_id()).await {
+            Ok(data) => data,
+            Err(res) => return res,
+        };
+
+    let mut file_data =
+        match select_upload(&ctx, &bucket_data, path.upload_id(), &created_by, &is_admin).await {
+            Ok(data) => data,
+            Err(res) => return res,
+        };
+
+    // The bucket limits may have changed since the upload was started
+    if let Err(res) = check_bucket_limits(&bucket_data, file_data.size(), &file_data.content_type())
+    {
+        return res;
+    }
+
+    match file_data.uploaded_size(bucket_data.path()).await {
+        Ok(uploaded_size) => {
+            if uploaded_size != *file_data.size() {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    &format!(
+                        "File upload is incomplete, received {uploaded_size} of {} bytes",
+                        file_data.size()
+                    ),
+                );
+            }
+        }
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    }
+
+    if let Err(err) = file_data
+        .complete_upload(ctx.dao().db(), bucket_data.path(), &expected_checksum)
+        .await
+    {
+        return Response::error_dao(&StatusCode::INTERNAL_SERVER_ERROR, &err);
+    }
+
+    broadcast_file(&ctx, WebSocketMessageKind::UpdateOne, &file_data);
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &FileResJson::new(
+            file_data.id(),
+            file_data.created_by(),
+            file_data.created_at(),
+            file_data.updated_at(),
+            file_data.bucket_id(),
+            file_data.file_name(),
+            &file_data.content_type().to_string(),
+            file_data.size(),
+            file_data.public(),
+            file_data.pending(),
+            file_data.checksum(),
         ),
     )
 }
@@ -225,7 +613,11 @@ async fn head_find_one(
     };
 
     if !*file_data.public() {
-        if let Some(token) = query.token() {
+        if let (Some(signature), Some(expires)) = (query.signature(), query.expires()) {
+            if let Err(res) = verify_presigned(&ctx, &bucket_data, &file_data, signature, expires) {
+                return res;
+            }
+        } else if let Some(token) = query.token() {
             let token_claim = match ctx.token().jwt().decode(token) {
                 Ok(token) => token,
                 Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
@@ -242,8 +634,11 @@ async fn head_find_one(
                     }
                 },
                 ClaimId::Token(token_id, user_claim) => {
-                    match TokenDao::db_select(ctx.dao().db(), token_id).await {
+                    match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
                         Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                        Err(err) if DaoError::is_expired(&err) => {
+                            return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                        }
                         Err(err) => {
                             return Response::error_raw(
                                 &StatusCode::BAD_REQUEST,
@@ -276,11 +671,15 @@ async fn head_find_one(
                 Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
             };
 
-            if &admin_id != project_data.admin_id() {
-                return Response::error_raw(
-                    &StatusCode::FORBIDDEN,
-                    "This project does not belong to you",
-                );
+            if let Err(err) = authorize_project(
+                ctx.dao().db(),
+                &admin_id,
+                &project_data,
+                &ProjectAccess::Read,
+            )
+            .await
+            {
+                return err;
             }
 
             if project_data.id() != bucket_data.project_id() {
@@ -381,6 +780,41 @@ async fn head_find_one(
         }
     }
 
+    if *file_data.pending() {
+        return Response::error_raw(&StatusCode::CONFLICT, "File upload is not completed yet");
+    }
+
+    let etag = checksum_etag(&file_data);
+    if let Some(etag) = &etag {
+        if is_etag_matched(&req, etag) {
+            return HttpResponse::NotModified()
+                .insert_header(header::ETag(etag.clone()))
+                .finish();
+        }
+    }
+
+    match FileDao::check_blob(bucket_data.path(), file_data.id()).await {
+        Ok(BlobStatus::Available) => (),
+        Ok(BlobStatus::NotFound) => {
+            return Response::error_raw(
+                &StatusCode::NOT_FOUND,
+                "File content is not found in the bucket storage",
+            )
+        }
+        Ok(BlobStatus::StorageUnavailable) => {
+            return Response::error_raw(
+                &StatusCode::SERVICE_UNAVAILABLE,
+                "Bucket storage is unavailable",
+            )
+        }
+        Err(err) => {
+            return Response::error_raw(
+                &StatusCode::INTERNAL_SERVER_ERROR,
+                &format!("Failed to check file content: {err}"),
+            )
+        }
+    }
+
     let file_path = match FileDao::full_path(bucket_data.path(), file_data.id()) {
         Ok(path) => path,
         Err(err) => {
@@ -391,8 +825,8 @@ async fn head_find_one(
         }
     };
     let file = match NamedFile::open_async(&file_path).await {
-        Ok(file) => file,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Ok(file) => file.use_etag(etag.is_none()),
+        Err(err) => return Response::error_raw(&StatusCode::SERVICE_UNAVAILABLE, &err.to_string()),
     };
 
     let mut res = HttpResponse::Ok();
@@ -401,6 +835,9 @@ async fn head_find_one(
             res.append_header(header);
         }
     }
+    if let Some(etag) = etag {
+        res.insert_header(header::ETag(etag));
+    }
     res.insert_header((
         header::CONTENT_DISPOSITION,
         HeaderValue::from_str(&format!(
@@ -429,7 +866,11 @@ async fn find_one(
     };
 
     if !*file_data.public() {
-        if let Some(token) = query.token() {
+        if let (Some(signature), Some(expires)) = (query.signature(), query.expires()) {
+            if let Err(res) = verify_presigned(&ctx, &bucket_data, &file_data, signature, expires) {
+                return res;
+            }
+        } else if let Some(token) = query.token() {
             let token_claim = match ctx.token().jwt().decode(token) {
                 Ok(token) => token,
                 Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
@@ -446,8 +887,11 @@ async fn find_one(
                     }
                 },
                 ClaimId::Token(token_id, user_claim) => {
-                    match TokenDao::db_select(ctx.dao().db(), token_id).await {
+                    match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
                         Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                        Err(err) if DaoError::is_expired(&err) => {
+                            return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                        }
                         Err(err) => {
                             return Response::error_raw(
                                 &StatusCode::BAD_REQUEST,
@@ -480,11 +924,15 @@ async fn find_one(
                 Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
             };
 
-            if &admin_id != project_data.admin_id() {
-                return Response::error_raw(
-                    &StatusCode::FORBIDDEN,
-                    "This project does not belong to you",
-                );
+            if let Err(err) = authorize_project(
+                ctx.dao().db(),
+                &admin_id,
+                &project_data,
+                &ProjectAccess::Read,
+            )
+            .await
+            {
+                return err;
             }
 
             if project_data.id() != bucket_data.project_id() {
@@ -599,67 +1047,765 @@ async fn find_one(
                 &file_data.content_type().to_string(),
                 file_data.size(),
                 file_data.public(),
+                file_data.pending(),
+                file_data.checksum(),
             ),
         )
     } else {
-        let file_path = match FileDao::full_path(bucket_data.path(), file_data.id()) {
-            Ok(path) => path,
-            Err(err) => {
-                return Response::error_raw(
-                    &StatusCode::INTERNAL_SERVER_ERROR,
-                    &format!("Failed to get file path: {err}"),
+        if *file_data.pending() {
+            return Response::error_raw(&StatusCode::CONFLICT, "File upload is not completed yet");
+        }
+
+        // Resized copies are served with the ETag of their own file
+        let etag = if query.is_resize() {
+            None
+        } else {
+            checksum_etag(&file_data)
+        };
+        if let Some(etag) = &etag {
+            if is_etag_matched(&req, etag) {
+                return HttpResponse::NotModified()
+                    .insert_header(header::ETag(etag.clone()))
+                    .finish();
+            }
+        }
+
+        match FileDao::check_blob(bucket_data.path(), file_data.id()).await {
+            Ok(BlobStatus::Available) => (),
+            Ok(BlobStatus::NotFound) => {
+                return Response::error_raw(
+                    &StatusCode::NOT_FOUND,
+                    "File content is not found in the bucket storage",
+                )
+            }
+            Ok(BlobStatus::StorageUnavailable) => {
+                return Response::error_raw(
+                    &StatusCode::SERVICE_UNAVAILABLE,
+                    "Bucket storage is unavailable",
+                )
+            }
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::INTERNAL_SERVER_ERROR,
+                    &format!("Failed to check file content: {err}"),
+                )
+            }
+        }
+
+        let (file_path, content_type) = if query.is_resize() {
+            if file_data.content_type().type_() != mime::IMAGE {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    "Only image files can be resized",
+                );
+            }
+            if query.width().is_none() && query.height().is_none() {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    "Width or height is required to resize an image",
+                );
+            }
+            for dimension in [query.width(), query.height()].into_iter().flatten() {
+                if *dimension == 0 || dimension > ctx.image_resize_max_dimension() {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!(
+                            "Width and height must be between 1 and {} pixels",
+                            ctx.image_resize_max_dimension()
+                        ),
+                    );
+                }
+            }
+            let fit = match query.fit() {
+                Some(fit) => match ImageFit::from_str(fit) {
+                    Ok(fit) => fit,
+                    Err(err) => {
+                        return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string())
+                    }
+                },
+                None => ImageFit::Contain,
+            };
+            let format = match query.format() {
+                Some(format) => match ImageFormat::from_str(format) {
+                    Ok(format) => format,
+                    Err(err) => {
+                        return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string())
+                    }
+                },
+                None => ImageFormat::Webp,
+            };
+
+            match file_data
+                .resized_path(
+                    bucket_data.path(),
+                    query.width(),
+                    query.height(),
+                    &fit,
+                    &format,
+                )
+                .await
+            {
+                Ok(path) => (path, Some(format.content_type())),
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::INTERNAL_SERVER_ERROR,
+                        &format!("Failed to resize image: {err}"),
+                    )
+                }
+            }
+        } else {
+            match FileDao::full_path(bucket_data.path(), file_data.id()) {
+                Ok(path) => (path, None),
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::INTERNAL_SERVER_ERROR,
+                        &format!("Failed to get file path: {err}"),
+                    )
+                }
+            }
+        };
+        let mut file = match NamedFile::open_async(&file_path).await {
+            Ok(file) => file.use_etag(etag.is_none()),
+            Err(err) => {
+                return Response::error_raw(&StatusCode::SERVICE_UNAVAILABLE, &err.to_string())
+            }
+        };
+        if let Some(content_type) = content_type {
+            file = file.set_content_type(content_type);
+        }
+
+        let mut res = file.into_response(&req);
+        if let Some(etag) = etag {
+            res.headers_mut().insert(
+                header::ETAG,
+                HeaderValue::from_str(&etag.to_string()).unwrap(),
+            );
+        }
+        res.headers_mut().insert(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_str(&format!(
+                "attachment; filename=\"{}\"",
+                file_data.file_name()
+            ))
+            .unwrap(),
+        );
+        res
+    }
+}
+
+async fn presign_one(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<PresignOneFileReqPath>,
+    data: web::Json<PresignOneFileReqJson>,
+) -> HttpResponse {
+    let bucket_data = match BucketDao::db_select(ctx.dao().db(), path.bucket_id()).await {
+        Ok(data) => data,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let file_data = match FileDao::db_select(ctx.dao().db(), &bucket_data, path.file_id()).await {
+        Ok(data) => data,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    if let Err(res) = authorize_find_one(
+        &ctx,
+        auth.token(),
+        path.project_id(),
+        &bucket_data,
+        &file_data,
+    )
+    .await
+    {
+        return res;
+    }
+
+    let expires_in = match data.expires_in() {
+        Some(expires_in) => (*expires_in).min(*ctx.presign_max_expiry()),
+        None => *ctx.presign_max_expiry(),
+    };
+    let expires = Utc::now().timestamp() + i64::from(expires_in);
+    let signature = match ctx
+        .token()
+        .jwt()
+        .sign(&presign_message(file_data.id(), &expires))
+    {
+        Ok(signature) => signature,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &PresignFileResJson::new(
+            &format!(
+                "/api/rest/project/{}/bucket/{}/file/{}?expires={expires}&signature={signature}",
+                path.project_id(),
+                bucket_data.id(),
+                file_data.id()
+            ),
+            &expires,
+        ),
+    )
+}
+
+async fn update_one(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<UpdateOneFileReqPath>,
+    data: web::Json<UpdateOneFileReqJson>,
+) -> HttpResponse {
+    let (_, mut file_data) = match authorize_update(
+        &ctx,
+        &auth,
+        path.project_id(),
+        path.bucket_id(),
+        path.file_id(),
+    )
+    .await
+    {
+        Ok(data) => data,
+        Err(res) => return res,
+    };
+
+    if let Some(created_by) = data.created_by() {
+        file_data.set_created_by(created_by);
+    }
+
+    if let Some(file_name) = data.file_name() {
+        file_data.set_file_name(file_name);
+    }
+
+    if let Some(public) = data.public() {
+        file_data.set_public(public);
+    }
+
+    if !data.is_all_none() {
+        if let Err(err) = file_data.db_update(ctx.dao().db()).await {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+        }
+
+        broadcast_file(&ctx, WebSocketMessageKind::UpdateOne, &file_data);
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &FileResJson::new(
+            file_data.id(),
+            file_data.created_by(),
+            file_data.created_at(),
+            file_data.updated_at(),
+            file_data.bucket_id(),
+            file_data.file_name(),
+            &file_data.content_type().to_string(),
+            file_data.size(),
+            file_data.public(),
+            file_data.pending(),
+            file_data.checksum(),
+        ),
+    )
+}
+
+async fn replace_content(
+    ctx: web::Data<ApiRestCtx>,
+    req: HttpRequest,
+    auth: ApiAuth,
+    path: web::Path<ReplaceOneFileContentReqPath>,
+    form: MultipartForm<ReplaceOneFileContentReqForm>,
+) -> HttpResponse {
+    let expected_checksum = match content_sha256(&req) {
+        Ok(checksum) => checksum,
+        Err(res) => return res,
+    };
+
+    let (bucket_data, mut file_data) = match authorize_update(
+        &ctx,
+        &auth,
+        path.project_id(),
+        path.bucket_id(),
+        path.file_id(),
+    )
+    .await
+    {
+        Ok(data) => data,
+        Err(res) => return res,
+    };
+
+    if *file_data.pending() {
+        return Response::error_raw(&StatusCode::CONFLICT, "File upload is not completed yet");
+    }
+
+    let mut content_type = mime::APPLICATION_OCTET_STREAM;
+    if let Some(mime) = form.content_type() {
+        content_type = mime.clone();
+    }
+    let size = match i64::try_from(*form.size()) {
+        Ok(size) => size,
+        Err(err) => {
+            return Response::error_raw(
+                &StatusCode::INTERNAL_SERVER_ERROR,
+                &format!("Failed to save file to the bucket: {err}"),
+            )
+        }
+    };
+    if let Err(res) = check_bucket_limits(&bucket_data, &size, &content_type) {
+        return res;
+    }
+    // Only the growth of the file counts against the quota, the old content is freed
+    if let Err(res) = check_bucket_quota(&bucket_data, &(size - file_data.size())) {
+        return res;
+    }
+
+    if let Err(err) = file_data
+        .replace(
+            ctx.dao().db(),
+            bucket_data.path(),
+            form.file_path(),
+            &content_type,
+            &size,
+            &expected_checksum,
+        )
+        .await
+    {
+        return Response::error_dao(&StatusCode::INTERNAL_SERVER_ERROR, &err);
+    }
+
+    broadcast_file(&ctx, WebSocketMessageKind::UpdateOne, &file_data);
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &FileResJson::new(
+            file_data.id(),
+            file_data.created_by(),
+            file_data.created_at(),
+            file_data.updated_at(),
+            file_data.bucket_id(),
+            file_data.file_name(),
+            &file_data.content_type().to_string(),
+            file_data.size(),
+            file_data.public(),
+            file_data.pending(),
+            file_data.checksum(),
+        ),
+    )
+}
+
+async fn delete_one(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<DeleteOneFileReqPath>,
+) -> HttpResponse {
+    let (bucket_data, created_by) =
+        match authorize_delete(&ctx, &auth, path.project_id(), path.bucket_id()).await {
+            Ok(data) => data,
+            Err(res) => return res,
+        };
+
+    let file_data = match FileDao::db_select(ctx.dao().db(), &bucket_data, path.file_id()).await {
+        Ok(data) => data,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    if let Some(created_by) = &created_by {
+        if created_by != file_data.created_by() {
+            return Response::error_raw(
+                &StatusCode::FORBIDDEN,
+                "User doesn't have permission to delete this file",
+            );
+        }
+    }
+
+    if file_data.bucket_id() != bucket_data.id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Bucket id does not match");
+    }
+
+    if let Err(err) = file_data.delete(ctx.dao().db(), &bucket_data).await {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+
+    broadcast_file(&ctx, WebSocketMessageKind::DeleteOne, &file_data);
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &DeleteFileResJson::new(path.file_id()),
+    )
+}
+
+async fn delete_many(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<DeleteManyFileReqPath>,
+    data: web::Json<DeleteManyFileReqJson>,
+) -> HttpResponse {
+    if data.ids().is_some() == !data.is_filter_none() {
+        return Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Either 'ids' or at least one filter must be set, but not both",
+        );
+    }
+
+    let (bucket_data, created_by) =
+        match authorize_delete(&ctx, &auth, path.project_id(), path.bucket_id()).await {
+            Ok(data) => data,
+            Err(res) => return res,
+        };
+
+    let mut errors = Vec::new();
+    let files_data = match data.ids() {
+        Some(ids) => {
+            let files_data = match FileDao::db_select_many_by_ids(ctx.dao().db(), &bucket_data, ids)
+                .await
+            {
+                Ok(data) => data,
+                Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+            };
+            let found_ids = files_data
+                .iter()
+                .map(|file_data| file_data.id())
+                .collect::<HashSet<_>>();
+            for id in ids {
+                if !found_ids.contains(id) {
+                    errors.push(DeleteManyFileErrorResJson::new(
+                        id,
+                        "File not found in this bucket",
+                    ));
+                }
+            }
+            files_data
+        }
+        None => {
+            match FileDao::db_select_many_by_bucket_id(
+                ctx.dao().db(),
+                &bucket_data,
+                &FileFilter::new(
+                    data.file_name(),
+                    data.content_type(),
+                    data.public(),
+                    data.created_by(),
+                    data.min_size(),
+                    data.max_size(),
+                ),
+                &FileOrder::CreatedAtDesc,
+                &None,
+                &None,
+            )
+            .await
+            {
+                Ok((files_data, _)) => files_data,
+                Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+            }
+        }
+    };
+
+    // Files the requester isn't allowed to delete are skipped instead of failing the whole request
+    let (files_data, skipped_files_data): (Vec<_>, Vec<_>) =
+        files_data
+            .into_iter()
+            .partition(|file_data| match &created_by {
+                Some(created_by) => created_by == file_data.created_by(),
+                None => true,
+            });
+
+    let mut deleted = 0;
+    for (file_data, result) in files_data
+        .iter()
+        .zip(FileDao::delete_many(ctx.dao().db(), &bucket_data, &files_data).await)
+    {
+        match result {
+            Ok(_) => {
+                deleted += 1;
+                broadcast_file(&ctx, WebSocketMessageKind::DeleteOne, file_data);
+            }
+            Err(err) => errors.push(DeleteManyFileErrorResJson::new(
+                file_data.id(),
+                &err.to_string(),
+            )),
+        }
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &DeleteManyFileResJson::new(&deleted, &skipped_files_data.len(), &errors),
+    )
+}
+
+async fn find_many(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<FindManyFileReqPath>,
+    query: web::Query<FindManyFileReqQuery>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let (admin_id, token_data, user_claim) = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => (*data.id(), None, None),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(token_id, user_claim) => {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
+                Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                }
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Failed to get token data: {err}"),
+                    )
+                }
+            }
+        }
+    };
+
+    let rule_find_many = if let Some(token_data) = &token_data {
+        if let Some(rule) = token_data
+            .is_allow_find_many_files(ctx.dao().db(), path.bucket_id())
+            .await
+        {
+            Some(rule)
+        } else {
+            return Response::error_raw(
+                &StatusCode::FORBIDDEN,
+                "This token doesn't have permission to read these files",
+            );
+        }
+    } else {
+        None
+    };
+
+    let (project_data, bucket_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        BucketDao::db_select(ctx.dao().db(), path.bucket_id())
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if project_data.id() != bucket_data.project_id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    let created_by = if matches!(token_claim.id(), ClaimId::Admin(_)) {
+        None
+    } else if let Some(rule) = rule_find_many {
+        match rule {
+            BucketPermission::All => None,
+            BucketPermission::SelfMade => match user_claim {
+                Some(user_claim) => {
+                    let collection_data =
+                        match CollectionDao::db_select(ctx.dao().db(), user_claim.collection_id())
+                            .await
+                        {
+                            Ok(data) => data,
+                            Err(err) => {
+                                return Response::error_raw(
+                                    &StatusCode::BAD_REQUEST,
+                                    &err.to_string(),
+                                )
+                            }
+                        };
+                    let user_data = match RecordDao::db_select(
+                        ctx.dao().db(),
+                        user_claim.id(),
+                        &None,
+                        &HashSet::from_iter(["_id"]),
+                        &collection_data,
+                        &token_data.is_none(),
+                    )
+                    .await
+                    {
+                        Ok(data) => data,
+                        Err(err) => {
+                            return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string())
+                        }
+                    };
+
+                    if let Some(id) = user_data.id() {
+                        Some(*id)
+                    } else {
+                        return Response::error_raw(&StatusCode::BAD_REQUEST, "User not found");
+                    }
+                }
+                None => {
+                    if let Some(token_data) = token_data {
+                        Some(*token_data.id())
+                    } else {
+                        return Response::error_raw(
+                            &StatusCode::INTERNAL_SERVER_ERROR,
+                            "Cannot determine created_by",
+                        );
+                    }
+                }
+            },
+            BucketPermission::None => {
+                return Response::error_raw(
+                    &StatusCode::FORBIDDEN,
+                    "User doesn't have permission to read these files",
                 )
             }
-        };
-        let file = match NamedFile::open_async(&file_path).await {
-            Ok(file) => file,
-            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
-        };
+        }
+    } else {
+        return Response::error_raw(
+            &StatusCode::FORBIDDEN,
+            "User doesn't have permission to read these files",
+        );
+    };
+
+    let order = match query.order_by() {
+        Some(order_by) => match FileOrder::from_str(order_by) {
+            Ok(order) => order,
+            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        },
+        None => FileOrder::CreatedAtDesc,
+    };
+    let filter = FileFilter::new(
+        query.file_name(),
+        query.content_type(),
+        query.public(),
+        query.created_by(),
+        query.min_size(),
+        query.max_size(),
+    );
+
+    let (files_data, total) = match created_by {
+        Some(created_by) => {
+            match FileDao::db_select_many_by_created_by_and_bucket_id(
+                ctx.dao().db(),
+                &created_by,
+                &bucket_data,
+                &filter,
+                &order,
+                query.before_id(),
+                query.limit(),
+            )
+            .await
+            {
+                Ok(data) => data,
+                Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+            }
+        }
+        None => {
+            match FileDao::db_select_many_by_bucket_id(
+                ctx.dao().db(),
+                &bucket_data,
+                &filter,
+                &order,
+                query.before_id(),
+                query.limit(),
+            )
+            .await
+            {
+                Ok(data) => data,
+                Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+            }
+        }
+    };
 
-        let mut res = file.into_response(&req);
-        res.headers_mut().insert(
-            header::CONTENT_DISPOSITION,
-            HeaderValue::from_str(&format!(
-                "attachment; filename=\"{}\"",
-                file_data.file_name()
-            ))
-            .unwrap(),
-        );
-        res
-    }
+    let total = match usize::try_from(total) {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    Response::data(
+        &StatusCode::OK,
+        &Some(PaginationRes::new(&files_data.len(), &total)),
+        &files_data
+            .iter()
+            .map(|data| {
+                FileResJson::new(
+                    data.id(),
+                    data.created_by(),
+                    data.created_at(),
+                    data.updated_at(),
+                    data.bucket_id(),
+                    data.file_name(),
+                    &data.content_type().to_string(),
+                    data.size(),
+                    data.public(),
+                    data.pending(),
+                    data.checksum(),
+                )
+            })
+            .collect::<Vec<_>>(),
+    )
 }
 
-async fn update_one(
-    ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
-    path: web::Path<UpdateOneFileReqPath>,
-    data: web::Json<UpdateOneFileReqJson>,
-) -> HttpResponse {
+// Checks that the requester is allowed to update the file, the same way for its metadata and its
+// content
+async fn authorize_update(
+    ctx: &ApiRestCtx,
+    auth: &ApiAuth,
+    project_id: &Uuid,
+    bucket_id: &Uuid,
+    file_id: &Uuid,
+) -> Result<(BucketDao, FileDao), HttpResponse> {
     let token = auth.token();
 
     let token_claim = match ctx.token().jwt().decode(token) {
         Ok(token) => token,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => {
+            return Err(Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &err.to_string(),
+            ))
+        }
     };
 
     let (admin_id, token_data, user_claim) = match token_claim.id() {
         ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
             Ok(data) => (*data.id(), None, None),
             Err(err) => {
-                return Response::error_raw(
+                return Err(Response::error_raw(
                     &StatusCode::UNAUTHORIZED,
                     &format!("Failed to get admin data: {err}"),
-                )
+                ))
             }
         },
         ClaimId::Token(token_id, user_claim) => {
-            match TokenDao::db_select(ctx.dao().db(), token_id).await {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
                 Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Err(Response::error_raw(
+                        &StatusCode::UNAUTHORIZED,
+                        &err.to_string(),
+                    ))
+                }
                 Err(err) => {
-                    return Response::error_raw(
+                    return Err(Response::error_raw(
                         &StatusCode::BAD_REQUEST,
                         &format!("Failed to get token data: {err}"),
-                    )
+                    ))
                 }
             }
         }
@@ -667,37 +1813,46 @@ async fn update_one(
 
     let rule_update_one = if let Some(token_data) = &token_data {
         if let Some(rule) = token_data
-            .is_allow_update_file(ctx.dao().db(), path.bucket_id())
+            .is_allow_update_file(ctx.dao().db(), bucket_id)
             .await
         {
             Some(rule)
         } else {
-            return Response::error_raw(
+            return Err(Response::error_raw(
                 &StatusCode::FORBIDDEN,
                 "This token doesn't have permission to update this file",
-            );
+            ));
         }
     } else {
         None
     };
 
     let (project_data, bucket_data) = match tokio::try_join!(
-        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
-        BucketDao::db_select(ctx.dao().db(), path.bucket_id())
+        ProjectDao::db_select(ctx.dao().db(), project_id),
+        BucketDao::db_select(ctx.dao().db(), bucket_id)
     ) {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => {
+            return Err(Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &err.to_string(),
+            ))
+        }
     };
 
-    if &admin_id != project_data.admin_id() {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
-    }
+    authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await?;
 
     if project_data.id() != bucket_data.project_id() {
-        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+        return Err(Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Project id does not match",
+        ));
     }
 
     let created_by = if matches!(token_claim.id(), ClaimId::Admin(_)) {
@@ -713,10 +1868,10 @@ async fn update_one(
                         {
                             Ok(data) => data,
                             Err(err) => {
-                                return Response::error_raw(
+                                return Err(Response::error_raw(
                                     &StatusCode::BAD_REQUEST,
                                     &err.to_string(),
-                                )
+                                ))
                             }
                         };
                     let user_data = match RecordDao::db_select(
@@ -731,125 +1886,120 @@ async fn update_one(
                     {
                         Ok(data) => data,
                         Err(err) => {
-                            return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string())
+                            return Err(Response::error_raw(
+                                &StatusCode::BAD_REQUEST,
+                                &err.to_string(),
+                            ))
                         }
                     };
 
                     if let Some(id) = user_data.id() {
                         Some(*id)
                     } else {
-                        return Response::error_raw(&StatusCode::BAD_REQUEST, "User not found");
+                        return Err(Response::error_raw(
+                            &StatusCode::BAD_REQUEST,
+                            "User not found",
+                        ));
                     }
                 }
                 None => {
                     if let Some(token_data) = token_data {
                         Some(*token_data.id())
                     } else {
-                        return Response::error_raw(
+                        return Err(Response::error_raw(
                             &StatusCode::INTERNAL_SERVER_ERROR,
                             "Cannot determine created_by",
-                        );
+                        ));
                     }
                 }
             },
             BucketPermission::None => {
-                return Response::error_raw(
+                return Err(Response::error_raw(
                     &StatusCode::BAD_REQUEST,
                     "User doesn't have permission to update this file",
-                )
+                ))
             }
         }
     } else {
-        return Response::error_raw(
+        return Err(Response::error_raw(
             &StatusCode::BAD_REQUEST,
             "User doesn't have permission to update this file",
-        );
+        ));
     };
 
-    let mut file_data = match FileDao::db_select(ctx.dao().db(), &bucket_data, path.file_id()).await
-    {
+    let file_data = match FileDao::db_select(ctx.dao().db(), &bucket_data, file_id).await {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => {
+            return Err(Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &err.to_string(),
+            ))
+        }
     };
 
     if let Some(created_by) = &created_by {
         if created_by != file_data.created_by() {
-            return Response::error_raw(
+            return Err(Response::error_raw(
                 &StatusCode::FORBIDDEN,
                 "User doesn't have permission to update this file",
-            );
+            ));
         }
     }
 
     if file_data.bucket_id() != bucket_data.id() {
-        return Response::error_raw(&StatusCode::BAD_REQUEST, "Bucket id does not match");
-    }
-
-    if let Some(created_by) = data.created_by() {
-        file_data.set_created_by(created_by);
-    }
-
-    if let Some(file_name) = data.file_name() {
-        file_data.set_file_name(file_name);
-    }
-
-    if let Some(public) = data.public() {
-        file_data.set_public(public);
-    }
-
-    if !data.is_all_none() {
-        if let Err(err) = file_data.db_update(ctx.dao().db()).await {
-            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
-        }
+        return Err(Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Bucket id does not match",
+        ));
     }
 
-    Response::data(
-        &StatusCode::OK,
-        &None,
-        &FileResJson::new(
-            file_data.id(),
-            file_data.created_by(),
-            file_data.created_at(),
-            file_data.updated_at(),
-            file_data.bucket_id(),
-            file_data.file_name(),
-            &file_data.content_type().to_string(),
-            file_data.size(),
-            file_data.public(),
-        ),
-    )
+    Ok((bucket_data, file_data))
 }
 
-async fn delete_one(
-    ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
-    path: web::Path<DeleteOneFileReqPath>,
-) -> HttpResponse {
+// Checks that the requester may delete files in the bucket and returns the bucket, and the id the
+// deleted files must be created by when the requester may only delete its own files
+async fn authorize_delete(
+    ctx: &ApiRestCtx,
+    auth: &ApiAuth,
+    project_id: &Uuid,
+    bucket_id: &Uuid,
+) -> Result<(BucketDao, Option<Uuid>), HttpResponse> {
     let token = auth.token();
 
     let token_claim = match ctx.token().jwt().decode(token) {
         Ok(token) => token,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => {
+            return Err(Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &err.to_string(),
+            ))
+        }
     };
 
     let (admin_id, token_data, user_claim) = match token_claim.id() {
         ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
             Ok(data) => (*data.id(), None, None),
             Err(err) => {
-                return Response::error_raw(
+                return Err(Response::error_raw(
                     &StatusCode::UNAUTHORIZED,
                     &format!("Failed to get admin data: {err}"),
-                )
+                ))
             }
         },
         ClaimId::Token(token_id, user_claim) => {
-            match TokenDao::db_select(ctx.dao().db(), token_id).await {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
                 Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Err(Response::error_raw(
+                        &StatusCode::UNAUTHORIZED,
+                        &err.to_string(),
+                    ))
+                }
                 Err(err) => {
-                    return Response::error_raw(
+                    return Err(Response::error_raw(
                         &StatusCode::BAD_REQUEST,
                         &format!("Failed to get token data: {err}"),
-                    )
+                    ))
                 }
             }
         }
@@ -857,37 +2007,46 @@ async fn delete_one(
 
     let rule_delete_one = if let Some(token_data) = &token_data {
         if let Some(rule) = token_data
-            .is_allow_delete_file(ctx.dao().db(), path.bucket_id())
+            .is_allow_delete_file(ctx.dao().db(), bucket_id)
             .await
         {
             Some(rule)
         } else {
-            return Response::error_raw(
+            return Err(Response::error_raw(
                 &StatusCode::FORBIDDEN,
                 "This token doesn't have permission to delete this file",
-            );
+            ));
         }
     } else {
         None
     };
 
     let (project_data, bucket_data) = match tokio::try_join!(
-        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
-        BucketDao::db_select(ctx.dao().db(), path.bucket_id())
+        ProjectDao::db_select(ctx.dao().db(), project_id),
+        BucketDao::db_select(ctx.dao().db(), bucket_id)
     ) {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => {
+            return Err(Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &err.to_string(),
+            ))
+        }
     };
 
-    if &admin_id != project_data.admin_id() {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
-    }
+    authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await?;
 
     if project_data.id() != bucket_data.project_id() {
-        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+        return Err(Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Project id does not match",
+        ));
     }
 
     let created_by = if matches!(token_claim.id(), ClaimId::Admin(_)) {
@@ -903,10 +2062,10 @@ async fn delete_one(
                         {
                             Ok(data) => data,
                             Err(err) => {
-                                return Response::error_raw(
+                                return Err(Response::error_raw(
                                     &StatusCode::BAD_REQUEST,
                                     &err.to_string(),
-                                )
+                                ))
                             }
                         };
                     let user_data = match RecordDao::db_select(
@@ -921,144 +2080,338 @@ async fn delete_one(
                     {
                         Ok(data) => data,
                         Err(err) => {
-                            return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string())
+                            return Err(Response::error_raw(
+                                &StatusCode::BAD_REQUEST,
+                                &err.to_string(),
+                            ))
                         }
                     };
 
-                    if let Some(id) = user_data.id() {
-                        Some(*id)
-                    } else {
-                        return Response::error_raw(&StatusCode::BAD_REQUEST, "User not found");
-                    }
-                }
-                None => {
-                    if let Some(token_data) = token_data {
-                        Some(*token_data.id())
-                    } else {
-                        return Response::error_raw(
-                            &StatusCode::INTERNAL_SERVER_ERROR,
-                            "Cannot determine created_by",
-                        );
-                    }
+                    if let Some(id) = user_data.id() {
+                        Some(*id)
+                    } else {
+                        return Err(Response::error_raw(
+                            &StatusCode::BAD_REQUEST,
+                            "User not found",
+                        ));
+                    }
+                }
+                None => {
+                    if let Some(token_data) = token_data {
+                        Some(*token_data.id())
+                    } else {
+                        return Err(Response::error_raw(
+                            &StatusCode::INTERNAL_SERVER_ERROR,
+                            "Cannot determine created_by",
+                        ));
+                    }
+                }
+            },
+            BucketPermission::None => {
+                return Err(Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    "User doesn't have permission to delete this file",
+                ))
+            }
+        }
+    } else {
+        return Err(Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "User doesn't have permission to delete this file",
+        ));
+    };
+
+    Ok((bucket_data, created_by))
+}
+
+// Checks that the requester may write files to the bucket and returns the bucket, the id the
+// uploaded file is created by, and whether the requester is an admin
+async fn authorize_upload(
+    ctx: &ApiRestCtx,
+    auth: &ApiAuth,
+    project_id: &Uuid,
+    bucket_id: &Uuid,
+) -> Result<(BucketDao, Uuid, bool), HttpResponse> {
+    let token_claim = match ctx.token().jwt().decode(auth.token()) {
+        Ok(token) => token,
+        Err(err) => {
+            return Err(Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &err.to_string(),
+            ))
+        }
+    };
+
+    let (admin_id, token_data, user_claim) = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => (*data.id(), None, None),
+            Err(err) => {
+                return Err(Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                ))
+            }
+        },
+        ClaimId::Token(token_id, user_claim) => {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
+                Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Err(Response::error_raw(
+                        &StatusCode::UNAUTHORIZED,
+                        &err.to_string(),
+                    ))
+                }
+                Err(err) => {
+                    return Err(Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Failed to get token data: {err}"),
+                    ))
+                }
+            }
+        }
+    };
+
+    if let Some(token_data) = &token_data {
+        if !token_data
+            .is_allow_insert_file(ctx.dao().db(), bucket_id)
+            .await
+        {
+            return Err(Response::error_raw(
+                &StatusCode::FORBIDDEN,
+                "This token doesn't have permission to write data to this bucket",
+            ));
+        }
+    }
+
+    let (project_data, bucket_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), project_id),
+        BucketDao::db_select(ctx.dao().db(), bucket_id)
+    ) {
+        Ok(data) => data,
+        Err(err) => {
+            return Err(Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &err.to_string(),
+            ))
+        }
+    };
+
+    authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await?;
+
+    if project_data.id() != bucket_data.project_id() {
+        return Err(Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Project id does not match",
+        ));
+    }
+
+    let is_admin = matches!(token_claim.id(), ClaimId::Admin(_));
+    let created_by = if is_admin {
+        admin_id
+    } else if let Some(user_claim) = user_claim {
+        let collection_data =
+            match CollectionDao::db_select(ctx.dao().db(), user_claim.collection_id()).await {
+                Ok(data) => data,
+                Err(err) => {
+                    return Err(Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &err.to_string(),
+                    ))
                 }
-            },
-            BucketPermission::None => {
-                return Response::error_raw(
+            };
+        let user_data = match RecordDao::db_select(
+            ctx.dao().db(),
+            user_claim.id(),
+            &None,
+            &HashSet::from_iter(["_id"]),
+            &collection_data,
+            &token_data.is_none(),
+        )
+        .await
+        {
+            Ok(data) => data,
+            Err(err) => {
+                return Err(Response::error_raw(
                     &StatusCode::BAD_REQUEST,
-                    "User doesn't have permission to delete this file",
-                )
+                    &err.to_string(),
+                ))
             }
+        };
+
+        if let Some(user_id) = user_data.id() {
+            *user_id
+        } else {
+            return Err(Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "User doesn't found",
+            ));
         }
+    } else if let Some(token_data) = token_data {
+        *token_data.id()
     } else {
-        return Response::error_raw(
-            &StatusCode::BAD_REQUEST,
-            "User doesn't have permission to delete this file",
-        );
+        return Err(Response::error_raw(
+            &StatusCode::INTERNAL_SERVER_ERROR,
+            "Cannot determine created_by",
+        ));
     };
 
-    let file_data = match FileDao::db_select(ctx.dao().db(), &bucket_data, path.file_id()).await {
+    Ok((bucket_data, created_by, is_admin))
+}
+
+// Gets a pending file of the bucket, only the one who started the upload or an admin can continue it
+async fn select_upload(
+    ctx: &ApiRestCtx,
+    bucket_data: &BucketDao,
+    upload_id: &Uuid,
+    created_by: &Uuid,
+    is_admin: &bool,
+) -> Result<FileDao, HttpResponse> {
+    let file_data = match FileDao::db_select(ctx.dao().db(), bucket_data, upload_id).await {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => {
+            return Err(Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &err.to_string(),
+            ))
+        }
     };
 
-    if let Some(created_by) = &created_by {
-        if created_by != file_data.created_by() {
-            return Response::error_raw(
-                &StatusCode::FORBIDDEN,
-                "User doesn't have permission to delete this file",
-            );
-        }
+    if file_data.bucket_id() != bucket_data.id() {
+        return Err(Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Bucket id does not match",
+        ));
     }
 
-    if file_data.bucket_id() != bucket_data.id() {
-        return Response::error_raw(&StatusCode::BAD_REQUEST, "Bucket id does not match");
+    if !*is_admin && file_data.created_by() != created_by {
+        return Err(Response::error_raw(
+            &StatusCode::FORBIDDEN,
+            "This upload doesn't belong to you",
+        ));
     }
 
-    if let Err(err) = FileDao::delete(ctx.dao().db(), &bucket_data, path.file_id()).await {
-        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    if !*file_data.pending() {
+        return Err(Response::error_raw(
+            &StatusCode::CONFLICT,
+            "File upload is already completed",
+        ));
     }
 
-    Response::data(
-        &StatusCode::OK,
-        &None,
-        &DeleteFileResJson::new(path.file_id()),
-    )
+    Ok(file_data)
 }
 
-async fn find_many(
-    ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
-    path: web::Path<FindManyFileReqPath>,
-    query: web::Query<FindManyFileReqQuery>,
-) -> HttpResponse {
-    let token = auth.token();
+// Parses 'bytes <start>-<end>/<size>' where the size may be '*'
+fn parse_content_range(value: &str) -> Option<(i64, i64, Option<i64>)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+    if start < 0 || end < start {
+        return None;
+    }
+    let total = match total.trim() {
+        "*" => None,
+        total => Some(total.parse().ok()?),
+    };
+    Some((start, end, total))
+}
 
+// Checks that the requester may read the file, with the same rules as find_one
+async fn authorize_find_one(
+    ctx: &ApiRestCtx,
+    token: &str,
+    project_id: &Uuid,
+    bucket_data: &BucketDao,
+    file_data: &FileDao,
+) -> Result<(), HttpResponse> {
     let token_claim = match ctx.token().jwt().decode(token) {
         Ok(token) => token,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => {
+            return Err(Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &err.to_string(),
+            ))
+        }
     };
 
     let (admin_id, token_data, user_claim) = match token_claim.id() {
         ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
             Ok(data) => (*data.id(), None, None),
             Err(err) => {
-                return Response::error_raw(
+                return Err(Response::error_raw(
                     &StatusCode::UNAUTHORIZED,
                     &format!("Failed to get admin data: {err}"),
-                )
+                ))
             }
         },
         ClaimId::Token(token_id, user_claim) => {
-            match TokenDao::db_select(ctx.dao().db(), token_id).await {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
                 Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Err(Response::error_raw(
+                        &StatusCode::UNAUTHORIZED,
+                        &err.to_string(),
+                    ))
+                }
                 Err(err) => {
-                    return Response::error_raw(
+                    return Err(Response::error_raw(
                         &StatusCode::BAD_REQUEST,
                         &format!("Failed to get token data: {err}"),
-                    )
+                    ))
                 }
             }
         }
     };
 
-    let rule_find_many = if let Some(token_data) = &token_data {
+    let rule_find_one = if let Some(token_data) = &token_data {
         if let Some(rule) = token_data
-            .is_allow_find_many_files(ctx.dao().db(), path.bucket_id())
+            .is_allow_find_one_file(ctx.dao().db(), bucket_data.id())
             .await
         {
             Some(rule)
         } else {
-            return Response::error_raw(
+            return Err(Response::error_raw(
                 &StatusCode::FORBIDDEN,
-                "This token doesn't have permission to read these files",
-            );
+                "This token doesn't have permission to read this file",
+            ));
         }
     } else {
         None
     };
 
-    let (project_data, bucket_data) = match tokio::try_join!(
-        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
-        BucketDao::db_select(ctx.dao().db(), path.bucket_id())
-    ) {
+    let project_data = match ProjectDao::db_select(ctx.dao().db(), project_id).await {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => {
+            return Err(Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &err.to_string(),
+            ))
+        }
     };
 
-    if &admin_id != project_data.admin_id() {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
-    }
+    authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await?;
 
     if project_data.id() != bucket_data.project_id() {
-        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+        return Err(Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Project id does not match",
+        ));
     }
 
     let created_by = if matches!(token_claim.id(), ClaimId::Admin(_)) {
         None
-    } else if let Some(rule) = rule_find_many {
+    } else if let Some(rule) = rule_find_one {
         match rule {
             BucketPermission::All => None,
             BucketPermission::SelfMade => match user_claim {
@@ -1069,10 +2422,10 @@ async fn find_many(
                         {
                             Ok(data) => data,
                             Err(err) => {
-                                return Response::error_raw(
+                                return Err(Response::error_raw(
                                     &StatusCode::BAD_REQUEST,
                                     &err.to_string(),
-                                )
+                                ))
                             }
                         };
                     let user_data = match RecordDao::db_select(
@@ -1087,96 +2440,224 @@ async fn find_many(
                     {
                         Ok(data) => data,
                         Err(err) => {
-                            return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string())
+                            return Err(Response::error_raw(
+                                &StatusCode::BAD_REQUEST,
+                                &err.to_string(),
+                            ))
                         }
                     };
 
                     if let Some(id) = user_data.id() {
                         Some(*id)
                     } else {
-                        return Response::error_raw(&StatusCode::BAD_REQUEST, "User not found");
+                        return Err(Response::error_raw(
+                            &StatusCode::BAD_REQUEST,
+                            "User not found",
+                        ));
                     }
                 }
                 None => {
                     if let Some(token_data) = token_data {
                         Some(*token_data.id())
                     } else {
-                        return Response::error_raw(
+                        return Err(Response::error_raw(
                             &StatusCode::INTERNAL_SERVER_ERROR,
                             "Cannot determine created_by",
-                        );
+                        ));
                     }
                 }
             },
             BucketPermission::None => {
-                return Response::error_raw(
-                    &StatusCode::FORBIDDEN,
-                    "User doesn't have permission to read these files",
-                )
+                return Err(Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    "User doesn't have permission to read this file",
+                ))
             }
         }
     } else {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "User doesn't have permission to read these files",
-        );
+        return Err(Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "User doesn't have permission to read this file",
+        ));
     };
 
-    let (files_data, total) = match created_by {
-        Some(created_by) => {
-            match FileDao::db_select_many_by_created_by_and_bucket_id(
-                ctx.dao().db(),
-                &created_by,
-                &bucket_data,
-                query.before_id(),
-                query.limit(),
-            )
-            .await
-            {
-                Ok(data) => data,
-                Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
-            }
+    if let Some(created_by) = &created_by {
+        if created_by != file_data.created_by() {
+            return Err(Response::error_raw(
+                &StatusCode::FORBIDDEN,
+                "User doesn't have permission to read this file",
+            ));
         }
-        None => {
-            match FileDao::db_select_many_by_bucket_id(
-                ctx.dao().db(),
-                &bucket_data,
-                query.before_id(),
-                query.limit(),
-            )
-            .await
-            {
-                Ok(data) => data,
-                Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
-            }
+    }
+
+    if file_data.bucket_id() != bucket_data.id() {
+        return Err(Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Bucket id does not match",
+        ));
+    }
+
+    Ok(())
+}
+
+// Subscribers of the bucket get the same payload as the response, broadcast before responding so
+// the messages of a file stay in the same order as the requests that changed it
+fn broadcast_file(ctx: &ApiRestCtx, kind: WebSocketMessageKind, file_data: &FileDao) {
+    if let Err(err) = websocket_broadcast(
+        ctx.websocket().handler(),
+        WebSocketTarget::Bucket(*file_data.bucket_id()),
+        Some(*file_data.created_by()),
+        kind,
+        &FileResJson::new(
+            file_data.id(),
+            file_data.created_by(),
+            file_data.created_at(),
+            file_data.updated_at(),
+            file_data.bucket_id(),
+            file_data.file_name(),
+            &file_data.content_type().to_string(),
+            file_data.size(),
+            file_data.public(),
+            file_data.pending(),
+            file_data.checksum(),
+        ),
+    ) {
+        hb_log::error(
+            None,
+            &format!(
+                "[ApiRestServer] Error when broadcasting file {} to websocket: {}",
+                file_data.id(),
+                err
+            ),
+        );
+    }
+}
+
+fn check_bucket_limits(
+    bucket_data: &BucketDao,
+    size: &i64,
+    content_type: &Mime,
+) -> Result<(), HttpResponse> {
+    if let Some(max_file_size) = bucket_data.max_file_size() {
+        if size > max_file_size {
+            return Err(Response::error_raw(
+                &StatusCode::PAYLOAD_TOO_LARGE,
+                &format!(
+                    "File size of {size} bytes exceeds the maximum file size of this bucket, which is {max_file_size} bytes"
+                ),
+            ));
         }
-    };
+    }
 
-    let total = match usize::try_from(total) {
-        Ok(data) => data,
-        Err(err) => {
-            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+    if !bucket_data.is_allowed_content_type(content_type) {
+        return Err(Response::error_raw(
+            &StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            &format!(
+                "Content type '{}' is not allowed in this bucket, allowed content types are: {}",
+                content_type.essence_str(),
+                bucket_data
+                    .allowed_content_types()
+                    .as_ref()
+                    .map(|allowed_content_types| allowed_content_types.join(", "))
+                    .unwrap_or_default()
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+// Turns away a file that clearly doesn't fit before its content is written. The usage read here
+// can be stale, so the quota is enforced again when the usage is reserved along with the file row,
+// which is answered with the same status. An upload session counts towards the usage of its
+// bucket from the moment it's started, so it's only checked against the quota once.
+fn check_bucket_quota(bucket_data: &BucketDao, size: &i64) -> Result<(), HttpResponse> {
+    if let Some(quota_bytes) = bucket_data.quota_bytes() {
+        if bucket_data.used_bytes() + size > *quota_bytes {
+            return Err(Response::error_raw(
+                &StatusCode::INSUFFICIENT_STORAGE,
+                &format!(
+                    "File size of {size} bytes exceeds the remaining quota of this bucket, {} of {quota_bytes} bytes are already used",
+                    bucket_data.used_bytes()
+                ),
+            ));
         }
+    }
+
+    Ok(())
+}
+
+// Clients can send the SHA-256 of the content they upload, hex encoded, so the upload fails when
+// the content arrives altered
+fn content_sha256(req: &HttpRequest) -> Result<Option<String>, HttpResponse> {
+    let value = match req.headers().get("Content-SHA256") {
+        Some(value) => value,
+        None => return Ok(None),
     };
+    match value.to_str() {
+        Ok(checksum) if checksum.len() == 64 && checksum.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Ok(Some(checksum.to_ascii_lowercase()))
+        }
+        _ => Err(Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Content-SHA256 header must be a hex encoded SHA-256 digest",
+        )),
+    }
+}
 
-    Response::data(
-        &StatusCode::OK,
-        &Some(PaginationRes::new(&files_data.len(), &total)),
-        &files_data
-            .iter()
-            .map(|data| {
-                FileResJson::new(
-                    data.id(),
-                    data.created_by(),
-                    data.created_at(),
-                    data.updated_at(),
-                    data.bucket_id(),
-                    data.file_name(),
-                    &data.content_type().to_string(),
-                    data.size(),
-                    data.public(),
-                )
-            })
-            .collect::<Vec<_>>(),
-    )
+// The checksum of the content makes a stable ETag, unlike the one actix derives from the file
+// metadata, so it's used instead whenever the file has one
+fn checksum_etag(file_data: &FileDao) -> Option<EntityTag> {
+    file_data
+        .checksum()
+        .as_ref()
+        .map(|checksum| EntityTag::new_strong(checksum.to_owned()))
+}
+
+fn is_etag_matched(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(items)) => items.iter().any(|item| item.weak_eq(etag)),
+        None => false,
+    }
+}
+
+// A presigned url is only valid for the file it is signed for and until it expires
+fn verify_presigned(
+    ctx: &ApiRestCtx,
+    bucket_data: &BucketDao,
+    file_data: &FileDao,
+    signature: &str,
+    expires: &i64,
+) -> Result<(), HttpResponse> {
+    if *expires < Utc::now().timestamp() {
+        return Err(Response::error_raw(
+            &StatusCode::FORBIDDEN,
+            "Presigned url has expired",
+        ));
+    }
+
+    if !ctx
+        .token()
+        .jwt()
+        .verify_signature(&presign_message(file_data.id(), expires), signature)
+    {
+        return Err(Response::error_raw(
+            &StatusCode::FORBIDDEN,
+            "Presigned url signature is invalid",
+        ));
+    }
+
+    if file_data.bucket_id() != bucket_data.id() {
+        return Err(Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "Bucket id does not match",
+        ));
+    }
+
+    Ok(())
+}
+
+fn presign_message(file_id: &Uuid, expires: &i64) -> String {
+    format!("file:{file_id}:{expires}")
 }
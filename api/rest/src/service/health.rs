@@ -0,0 +1,181 @@
+use std::{
+    future::Future,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use actix_web::{http::StatusCode, web, HttpResponse};
+use anyhow::{Error, Result};
+use hb_dao::file::FileDao;
+
+use crate::{
+    context::ApiRestCtx,
+    model::{
+        health::{HealthComponentResJson, HealthResJson, PoolStatusResJson, WarmupResJson},
+        Response,
+    },
+};
+
+// A check taking longer than this counts as failed, so a hanging database doesn't hold up the
+// load balancer probing it
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Components that stop the server from taking traffic when they fail. The bucket is one of them:
+// it's a local path of this instance, and one that can't be written fails every upload, so the
+// load balancer should send traffic to an instance whose bucket works instead. The mailer and MQTT
+// only affect side channels and leave the server degraded but ready.
+const READINESS_COMPONENTS: [&str; 3] = ["database", "bucket", "warmup"];
+
+// /health/ready is kept for probes set up against it and answers the same as /readyz
+pub fn health_api(cfg: &mut web::ServiceConfig) {
+    cfg.route("/health/ready", web::get().to(readyz))
+        .route("/health/warmup", web::get().to(warmup));
+}
+
+pub fn health_probe_api(cfg: &mut web::ServiceConfig) {
+    cfg.route("/healthz", web::get().to(healthz))
+        .route("/readyz", web::get().to(readyz));
+}
+
+async fn warmup(ctx: web::Data<ApiRestCtx>) -> HttpResponse {
+    match ctx.warmup() {
+        Some(warmup) => Response::data(
+            &StatusCode::OK,
+            &None,
+            &WarmupResJson::new(
+                &warmup.is_finished(),
+                &warmup.collections_loaded(),
+                &warmup.tables_total(),
+                &warmup.tables_prepared(),
+                &warmup.tables_failed(),
+            ),
+        ),
+        None => Response::error_raw(&StatusCode::NOT_FOUND, "Warm-up is not enabled"),
+    }
+}
+
+// Liveness stays OK whatever the components report, restarting the server doesn't fix a database
+// that is briefly unreachable
+async fn healthz(ctx: web::Data<ApiRestCtx>) -> HttpResponse {
+    let components = check_components(&ctx).await;
+    let status = if components.iter().any(|component| component.is_error()) {
+        "degraded"
+    } else {
+        "ok"
+    };
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &health_res(&ctx, status, components),
+    )
+}
+
+async fn readyz(ctx: web::Data<ApiRestCtx>) -> HttpResponse {
+    let components = check_components(&ctx).await;
+    if components
+        .iter()
+        .any(|component| component.is_error() && READINESS_COMPONENTS.contains(&component.name()))
+    {
+        return Response::data(
+            &StatusCode::SERVICE_UNAVAILABLE,
+            &None,
+            &health_res(&ctx, "unavailable", components),
+        );
+    }
+    let status = if components.iter().any(|component| component.is_error()) {
+        "degraded"
+    } else {
+        "ok"
+    };
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &health_res(&ctx, status, components),
+    )
+}
+
+fn health_res(
+    ctx: &ApiRestCtx,
+    status: &'static str,
+    components: Vec<HealthComponentResJson>,
+) -> HealthResJson {
+    HealthResJson::new(
+        status,
+        components,
+        &ctx.dao().db().slow_queries(),
+        PoolStatusResJson::new(&ctx.dao().db().pool_status()),
+    )
+}
+
+async fn check_components(ctx: &ApiRestCtx) -> Vec<HealthComponentResJson> {
+    let (database, bucket) = tokio::join!(
+        check("database", ctx.dao().db().ping()),
+        check("bucket", FileDao::check_storage_writable(ctx.bucket_path())),
+    );
+
+    let mailer = match ctx.mailer() {
+        Some(mailer) => {
+            check("mailer", async {
+                if mailer.sender().is_closed() {
+                    Err(Error::msg("Mailer channel is closed"))
+                } else {
+                    Ok(())
+                }
+            })
+            .await
+        }
+        None => HealthComponentResJson::disabled("mailer"),
+    };
+
+    let mqtt = match ctx.mqtt_connected() {
+        Some(mqtt_connected) => {
+            check("mqtt", async {
+                if mqtt_connected.load(Ordering::Relaxed) {
+                    Ok(())
+                } else {
+                    Err(Error::msg("Not connected to the MQTT broker"))
+                }
+            })
+            .await
+        }
+        None => HealthComponentResJson::disabled("mqtt"),
+    };
+
+    let warmup = match ctx.warmup() {
+        Some(warmup) => {
+            check("warmup", async {
+                if warmup.is_ready() {
+                    Ok(())
+                } else {
+                    Err(Error::msg(format!(
+                        "Warming up ({} of {} record tables prepared)",
+                        warmup.tables_prepared() + warmup.tables_failed(),
+                        warmup.tables_total()
+                    )))
+                }
+            })
+            .await
+        }
+        None => HealthComponentResJson::disabled("warmup"),
+    };
+
+    vec![database, bucket, mailer, mqtt, warmup]
+}
+
+async fn check(
+    name: &'static str,
+    check: impl Future<Output = Result<()>>,
+) -> HealthComponentResJson {
+    let start = Instant::now();
+    let result = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(result) => result,
+        Err(_) => Err(Error::msg(format!("Timed out after {CHECK_TIMEOUT:?}"))),
+    };
+    HealthComponentResJson::new(
+        name,
+        &(start.elapsed().as_secs_f64() * 1000.0),
+        &result.err().map(|err| err.to_string()),
+    )
+}
@@ -9,7 +9,8 @@ pub fn info_api(cfg: &mut web::ServiceConfig) {
         .route(
             "/info/admin_registration",
             web::get().to(admin_registration),
-        );
+        )
+        .route("/info/read_only", web::get().to(read_only));
 }
 
 async fn schema_fields() -> HttpResponse {
@@ -25,3 +26,7 @@ async fn schema_fields() -> HttpResponse {
 async fn admin_registration(ctx: web::Data<ApiRestCtx>) -> HttpResponse {
     Response::data(&StatusCode::OK, &None, ctx.admin_registration())
 }
+
+async fn read_only(ctx: web::Data<ApiRestCtx>) -> HttpResponse {
+    Response::data(&StatusCode::OK, &None, ctx.read_only())
+}
@@ -1,7 +1,13 @@
 use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
-use hb_api_websocket::{message::Target, session::UserSession};
-use hb_dao::{admin::AdminDao, log::LogDao, project::ProjectDao};
+use hb_api_websocket::{
+    message::{MessageFormat, Target},
+    session::{UserSession, UserSessionId},
+};
+use hb_dao::{
+    admin::AdminDao,
+    log::{LogDao, LogKind},
+    project::ProjectDao,
+};
 use hb_token_jwt::claim::ClaimId;
 
 use crate::{
@@ -13,6 +19,11 @@ use crate::{
         },
         PaginationRes, Response,
     },
+    util::{
+        api_auth::ApiAuth,
+        project_access::{authorize_project, ProjectAccess},
+        ws_origin::validate_websocket_origin,
+    },
 };
 
 pub fn log_api(cfg: &mut web::ServiceConfig) {
@@ -25,7 +36,7 @@ pub fn log_api(cfg: &mut web::ServiceConfig) {
 
 async fn find_many(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     path: web::Path<FindManyLogReqPath>,
     query: web::Query<FindManyLogReqQuery>,
 ) -> HttpResponse {
@@ -59,17 +70,34 @@ async fn find_many(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
     }
 
+    let kind = match query.kind() {
+        Some(kind) => match LogKind::from_str(kind) {
+            Ok(kind) => Some(kind),
+            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        },
+        None => None,
+    };
+    let message = query.q().as_ref().filter(|q| !q.is_empty()).cloned();
+
     let (logs_data, total) = match LogDao::db_select_many_by_admin_id_and_project_id(
         ctx.dao().db(),
         &admin_id,
         path.project_id(),
+        &kind,
+        query.from(),
+        query.to(),
+        &message,
         query.before_id(),
         query.limit(),
     )
@@ -110,6 +138,19 @@ async fn subscribe(
     path: web::Path<SubscribeLogReqPath>,
     query: web::Query<SubscribeLogReqQuery>,
 ) -> HttpResponse {
+    if let Err(err) = validate_websocket_origin(ctx.websocket(), &req) {
+        hb_log::warn(
+            None,
+            &format!("[ApiRestServer] Rejected websocket connection to subscribe log: {err}"),
+        );
+        return Response::error_raw(&StatusCode::FORBIDDEN, &err.to_string());
+    }
+
+    let format = match MessageFormat::from_str(query.format().as_deref().unwrap_or("json")) {
+        Ok(format) => format,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
     let token = query.token();
 
     let token_claim = match ctx.token().jwt().decode(token) {
@@ -140,11 +181,15 @@ async fn subscribe(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
     }
 
     let (res, session, msg_stream) = match actix_ws_ng::handle(&req, stream) {
@@ -160,8 +205,11 @@ async fn subscribe(
             .handler()
             .clone()
             .connection(
-                UserSession::Admin(admin_id),
+                UserSession::new(UserSessionId::Admin(admin_id), token_claim.exp()),
                 Target::Log,
+                *query.last_seq(),
+                false,
+                format,
                 session,
                 msg_stream,
             )
@@ -0,0 +1,380 @@
+use actix_web::{http::StatusCode, web, HttpResponse};
+use hb_dao::{
+    admin::AdminDao,
+    admin_project_member::{AdminProjectMemberDao, AdminProjectRole},
+    error::DaoError,
+    project::ProjectDao,
+};
+use hb_token_jwt::claim::ClaimId;
+use validator::Validate;
+
+use crate::{
+    context::ApiRestCtx,
+    model::{
+        member::{
+            DeleteOneMemberReqPath, FindManyMemberReqPath, InsertOneMemberReqJson,
+            InsertOneMemberReqPath, MemberIDResJson, MemberResJson, UpdateOneMemberReqJson,
+            UpdateOneMemberReqPath,
+        },
+        PaginationRes, Response,
+    },
+    util::{
+        api_auth::ApiAuth,
+        project_access::{authorize_project, ProjectAccess},
+    },
+};
+
+pub fn member_api(cfg: &mut web::ServiceConfig) {
+    cfg.route("/project/{project_id}/member", web::post().to(insert_one))
+        .route(
+            "/project/{project_id}/member/{member_id}",
+            web::patch().to(update_one),
+        )
+        .route(
+            "/project/{project_id}/member/{member_id}",
+            web::delete().to(delete_one),
+        )
+        .route("/project/{project_id}/members", web::get().to(find_many));
+}
+
+async fn insert_one(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<InsertOneMemberReqPath>,
+    data: web::Json<InsertOneMemberReqJson>,
+) -> HttpResponse {
+    if let Err(err) = data.validate() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string());
+    }
+
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
+    }
+
+    let role = match AdminProjectRole::from_str(data.role()) {
+        Ok(role) => role,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_email = data.admin_email().to_lowercase();
+
+    let member_admin_data = match AdminDao::db_select_by_email(ctx.dao().db(), &admin_email).await {
+        Ok(data) => data,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    if member_admin_data.id() == project_data.admin_id() {
+        return Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "This admin already owns the project",
+        );
+    }
+
+    match AdminProjectMemberDao::db_select_by_project_id_and_admin_id(
+        ctx.dao().db(),
+        project_data.id(),
+        member_admin_data.id(),
+    )
+    .await
+    {
+        Ok(_) => {
+            return Response::error_raw(
+                &StatusCode::CONFLICT,
+                "This admin is already a member of the project",
+            )
+        }
+        Err(err) if DaoError::is_not_found(&err) => (),
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    }
+
+    let member_data = AdminProjectMemberDao::new(project_data.id(), member_admin_data.id(), &role);
+    if let Err(err) = member_data.db_insert(ctx.dao().db()).await {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+
+    Response::data(
+        &StatusCode::CREATED,
+        &None,
+        &MemberResJson::new(
+            member_data.id(),
+            member_data.created_at(),
+            member_data.updated_at(),
+            member_data.project_id(),
+            member_data.admin_id(),
+            member_admin_data.email(),
+            member_data.role().to_str(),
+        ),
+    )
+}
+
+async fn update_one(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<UpdateOneMemberReqPath>,
+    data: web::Json<UpdateOneMemberReqJson>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let (project_data, mut member_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        AdminProjectMemberDao::db_select(ctx.dao().db(), path.member_id())
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if project_data.id() != member_data.project_id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    let role = match AdminProjectRole::from_str(data.role()) {
+        Ok(role) => role,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let member_admin_data = match AdminDao::db_select(ctx.dao().db(), member_data.admin_id()).await
+    {
+        Ok(data) => data,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    member_data.set_role(&role);
+    if let Err(err) = member_data.db_update(ctx.dao().db()).await {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &MemberResJson::new(
+            member_data.id(),
+            member_data.created_at(),
+            member_data.updated_at(),
+            member_data.project_id(),
+            member_data.admin_id(),
+            member_admin_data.email(),
+            member_data.role().to_str(),
+        ),
+    )
+}
+
+async fn delete_one(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<DeleteOneMemberReqPath>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let (project_data, member_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        AdminProjectMemberDao::db_select(ctx.dao().db(), path.member_id())
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if project_data.id() != member_data.project_id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    // Any member can leave the project on their own
+    if member_data.admin_id() != &admin_id {
+        if let Err(err) = authorize_project(
+            ctx.dao().db(),
+            &admin_id,
+            &project_data,
+            &ProjectAccess::Manage,
+        )
+        .await
+        {
+            return err;
+        }
+    }
+
+    if let Err(err) = AdminProjectMemberDao::db_delete(ctx.dao().db(), member_data.id()).await {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &MemberIDResJson::new(member_data.id()),
+    )
+}
+
+async fn find_many(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<FindManyMemberReqPath>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
+    }
+
+    let members_data = match AdminProjectMemberDao::db_select_many_by_project_id(
+        ctx.dao().db(),
+        project_data.id(),
+    )
+    .await
+    {
+        Ok(data) => data,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let mut members_res = Vec::with_capacity(members_data.len());
+    for member_data in &members_data {
+        let member_admin_data =
+            match AdminDao::db_select(ctx.dao().db(), member_data.admin_id()).await {
+                Ok(data) => data,
+                Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+            };
+        members_res.push(MemberResJson::new(
+            member_data.id(),
+            member_data.created_at(),
+            member_data.updated_at(),
+            member_data.project_id(),
+            member_data.admin_id(),
+            member_admin_data.email(),
+            member_data.role().to_str(),
+        ));
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &Some(PaginationRes::new(&members_res.len(), &members_res.len())),
+        &members_res,
+    )
+}
@@ -1,18 +1,37 @@
-use actix_web::{http::StatusCode, web, HttpResponse};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
+use std::env;
+
+use actix_multipart::form::MultipartForm;
+use actix_web::{
+    error,
+    http::{header, StatusCode},
+    web, HttpRequest, HttpResponse,
+};
 use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
+use futures::stream;
+use hb_api_websocket::{
+    message::{MessageFormat, Target},
+    session::{UserSession, UserSessionId},
+};
 use hb_dao::{
     admin::AdminDao,
+    admin_project_member::AdminProjectMemberDao,
+    audit::{AuditAction, AuditEntity},
     bucket::BucketDao,
-    bucket_rule::BucketRuleDao,
-    collection::CollectionDao,
-    collection_rule::CollectionRuleDao,
-    file::FileDao,
+    bucket_rule::{BucketPermission, BucketRuleDao, ALL_BUCKETS_ID},
+    collection::{CollectionDao, CollectionOrder},
+    collection_rule::{CollectionPermission, CollectionRuleDao, ALL_COLLECTIONS_ID},
+    error::DaoError,
+    file::{FileDao, FileFilter, FileOrder},
     project::ProjectDao,
     record::{RecordDao, RecordFilters, RecordPagination},
     token::TokenDao,
+    value::{ColumnKind, ColumnValue},
 };
 use hb_token_jwt::claim::ClaimId;
+use mime::Mime;
+use serde_json::json;
+use tokio::{fs, sync::mpsc};
+use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
@@ -20,17 +39,35 @@ use crate::{
     model::{
         project::{
             DeleteOneProjectReqPath, DuplicateOneProjectReqJson, DuplicateOneProjectReqPath,
-            FindOneProjectReqPath, InsertOneProjectReqJson, ProjectIDResJson, ProjectResJson,
-            TransferOneProjectReqJson, TransferOneProjectReqPath, UpdateOneProjectReqJson,
-            UpdateOneProjectReqPath,
+            ExportOneProjectReqPath, FindOneProjectReqPath, ImportProjectReqForm,
+            ImportProjectReqQuery, ImportProjectResJson, InsertOneProjectReqJson,
+            ProjectArchiveJson, ProjectIDResJson, ProjectResJson, SubscribeProjectReqPath,
+            SubscribeProjectReqQuery, TransferOneProjectReqJson, TransferOneProjectReqPath,
+            UpdateOneProjectReqJson, UpdateOneProjectReqPath,
         },
         PaginationRes, Response,
     },
+    util::{
+        api_auth::ApiAuth,
+        archive::{self, ArchiveEntry},
+        audit::{audit, audit_state},
+        project_access::{authorize_project, ProjectAccess},
+        project_export::{
+            ProjectExport, ARCHIVE_CHANNEL_CAPACITY, ARCHIVE_MANIFEST_PATH, ARCHIVE_VERSION,
+        },
+        schema::schema_fields_from_json,
+        ws_origin::validate_websocket_origin,
+    },
 };
 
+const IMPORT_PROJECT_MAX_ERRORS: usize = 100;
+
 pub fn project_api(cfg: &mut web::ServiceConfig) {
     cfg.route("/project", web::post().to(insert_one))
+        // Registered ahead of /project/{project_id} so "import" isn't taken as an id
+        .route("/project/import", web::post().to(import_one))
         .route("/project/{project_id}", web::get().to(find_one))
+        .route("/project/{project_id}/subscribe", web::get().to(subscribe))
         .route("/project/{project_id}", web::patch().to(update_one))
         .route("/project/{project_id}", web::delete().to(delete_one))
         .route(
@@ -41,12 +78,14 @@ pub fn project_api(cfg: &mut web::ServiceConfig) {
             "/project/{project_id}/duplicate",
             web::post().to(duplicate_one),
         )
+        .route("/project/{project_id}/export", web::get().to(export_one))
         .route("/projects", web::get().to(find_many));
 }
 
 async fn insert_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     data: web::Json<InsertOneProjectReqJson>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -80,21 +119,31 @@ async fn insert_one(
         return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
     }
 
-    Response::data(
-        &StatusCode::CREATED,
+    let project_res = ProjectResJson::new(
+        project_data.id(),
+        project_data.created_at(),
+        project_data.updated_at(),
+        project_data.name(),
+    );
+
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        project_data.id(),
+        &AuditAction::Create,
+        &AuditEntity::Project,
+        project_data.id(),
         &None,
-        &ProjectResJson::new(
-            project_data.id(),
-            project_data.created_at(),
-            project_data.updated_at(),
-            project_data.name(),
-        ),
-    )
+        &audit_state(&project_res),
+    );
+
+    Response::data(&StatusCode::CREATED, &None, &project_res)
 }
 
 async fn find_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     path: web::Path<FindOneProjectReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -124,14 +173,18 @@ async fn find_one(
 
     let project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
     }
 
     Response::data(
@@ -146,9 +199,100 @@ async fn find_one(
     )
 }
 
+// Changes to the collections, buckets, tokens and rules of the project, so an admin dashboard can
+// refresh without polling
+async fn subscribe(
+    ctx: web::Data<ApiRestCtx>,
+    req: HttpRequest,
+    stream: web::Payload,
+    path: web::Path<SubscribeProjectReqPath>,
+    query: web::Query<SubscribeProjectReqQuery>,
+) -> HttpResponse {
+    if let Err(err) = validate_websocket_origin(ctx.websocket(), &req) {
+        hb_log::warn(
+            None,
+            &format!("[ApiRestServer] Rejected websocket connection to subscribe project: {err}"),
+        );
+        return Response::error_raw(&StatusCode::FORBIDDEN, &err.to_string());
+    }
+
+    let format = match MessageFormat::from_str(query.format().as_deref().unwrap_or("json")) {
+        Ok(format) => format,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let token = query.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
+        Ok(data) => data,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
+    }
+
+    let (res, session, msg_stream) = match actix_ws_ng::handle(&req, stream) {
+        Ok(res) => res,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    tokio::task::spawn_local((|| async move {
+        let _ = ctx
+            .websocket()
+            .handler()
+            .clone()
+            .connection(
+                UserSession::new(UserSessionId::Admin(admin_id), token_claim.exp()),
+                Target::Project(*project_data.id()),
+                *query.last_seq(),
+                false,
+                format,
+                session,
+                msg_stream,
+            )
+            .await;
+    })());
+
+    res
+}
+
 async fn update_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<UpdateOneProjectReqPath>,
     data: web::Json<UpdateOneProjectReqJson>,
 ) -> HttpResponse {
@@ -179,16 +323,27 @@ async fn update_one(
 
     let mut project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
     }
 
+    let before = audit_state(&ProjectResJson::new(
+        project_data.id(),
+        project_data.created_at(),
+        project_data.updated_at(),
+        project_data.name(),
+    ));
+
     if let Some(name) = data.name() {
         project_data.set_name(name);
     }
@@ -199,21 +354,34 @@ async fn update_one(
         }
     }
 
-    Response::data(
-        &StatusCode::OK,
-        &None,
-        &ProjectResJson::new(
+    let project_res = ProjectResJson::new(
+        project_data.id(),
+        project_data.created_at(),
+        project_data.updated_at(),
+        project_data.name(),
+    );
+
+    if !data.is_all_none() {
+        audit(
+            &ctx,
+            &req,
+            &admin_id,
             project_data.id(),
-            project_data.created_at(),
-            project_data.updated_at(),
-            project_data.name(),
-        ),
-    )
+            &AuditAction::Update,
+            &AuditEntity::Project,
+            project_data.id(),
+            &before,
+            &audit_state(&project_res),
+        );
+    }
+
+    Response::data(&StatusCode::OK, &None, &project_res)
 }
 
 async fn delete_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<DeleteOneProjectReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -243,20 +411,41 @@ async fn delete_one(
 
     let project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
     }
 
     if let Err(err) = ProjectDao::db_delete(ctx.dao().db(), path.project_id()).await {
         return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
     }
 
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        project_data.id(),
+        &AuditAction::Delete,
+        &AuditEntity::Project,
+        project_data.id(),
+        &audit_state(&ProjectResJson::new(
+            project_data.id(),
+            project_data.created_at(),
+            project_data.updated_at(),
+            project_data.name(),
+        )),
+        &None,
+    );
+
     Response::data(
         &StatusCode::OK,
         &None,
@@ -266,7 +455,8 @@ async fn delete_one(
 
 async fn transfer_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<TransferOneProjectReqPath>,
     data: web::Json<TransferOneProjectReqJson>,
 ) -> HttpResponse {
@@ -301,14 +491,18 @@ async fn transfer_one(
 
     let mut project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
     }
 
     let admin_email = data.admin_email().to_lowercase();
@@ -318,14 +512,39 @@ async fn transfer_one(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
+    // A member with the owner role can transfer too, the tokens still move from the admin the
+    // project belonged to
+    let prev_admin_id = *project_data.admin_id();
+
     project_data.set_admin_id(admin_data.id());
     if let Err(err) = project_data.db_update(ctx.dao().db()).await {
         return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string());
     }
 
+    // The new owner doesn't need a member row anymore
+    match AdminProjectMemberDao::db_select_by_project_id_and_admin_id(
+        ctx.dao().db(),
+        project_data.id(),
+        admin_data.id(),
+    )
+    .await
+    {
+        Ok(member_data) => {
+            if let Err(err) =
+                AdminProjectMemberDao::db_delete(ctx.dao().db(), member_data.id()).await
+            {
+                return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+            }
+        }
+        Err(err) if DaoError::is_not_found(&err) => (),
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    }
+
     let tokens_data = match TokenDao::db_select_many_by_admin_id_and_project_id(
         ctx.dao().db(),
-        &admin_id,
+        &prev_admin_id,
         path.project_id(),
     )
     .await
@@ -341,6 +560,19 @@ async fn transfer_one(
         }
     }
 
+    // The owner isn't part of the project as the API returns it
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        project_data.id(),
+        &AuditAction::Update,
+        &AuditEntity::Project,
+        project_data.id(),
+        &Some(json!({ "admin_id": prev_admin_id })),
+        &Some(json!({ "admin_id": admin_data.id() })),
+    );
+
     Response::data(
         &StatusCode::OK,
         &None,
@@ -350,7 +582,8 @@ async fn transfer_one(
 
 async fn duplicate_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<DuplicateOneProjectReqPath>,
     data: web::Json<DuplicateOneProjectReqJson>,
 ) -> HttpResponse {
@@ -381,14 +614,18 @@ async fn duplicate_one(
 
     let project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
     }
 
     let new_project_data = ProjectDao::new(
@@ -400,7 +637,14 @@ async fn duplicate_one(
     }
 
     let (collections_data, buckets_data, tokens_data) = match tokio::try_join!(
-        CollectionDao::db_select_many_by_project_id(ctx.dao().db(), project_data.id(),),
+        CollectionDao::db_select_many_by_project_id(
+            ctx.dao().db(),
+            project_data.id(),
+            &None,
+            &None,
+            &CollectionOrder::CreatedAtDesc,
+            &None
+        ),
         BucketDao::db_select_many_by_project_id(ctx.dao().db(), project_data.id()),
         TokenDao::db_select_many_by_project_id(ctx.dao().db(), project_data.id())
     ) {
@@ -414,8 +658,12 @@ async fn duplicate_one(
             new_project_data.id(),
             collection_data.name(),
             collection_data.schema_fields(),
+            collection_data.unique_indexes(),
             collection_data.opt_auth_column_id(),
             collection_data.opt_ttl(),
+            collection_data.opt_soft_delete(),
+            collection_data.opt_per_record_ttl(),
+            collection_data.opt_mqtt_publish(),
         );
         if let Err(err) = new_collection_data.db_insert(ctx.dao().db()).await {
             return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
@@ -482,6 +730,9 @@ async fn duplicate_one(
             bucket_data.name(),
             ctx.bucket_path(),
             bucket_data.opt_ttl(),
+            bucket_data.max_file_size(),
+            bucket_data.allowed_content_types(),
+            bucket_data.quota_bytes(),
         )
         .await
         {
@@ -500,6 +751,8 @@ async fn duplicate_one(
             let (files_data, _) = match FileDao::db_select_many_by_bucket_id(
                 ctx.dao().db(),
                 bucket_data,
+                &FileFilter::default(),
+                &FileOrder::CreatedAtDesc,
                 &None,
                 &None,
             )
@@ -510,7 +763,11 @@ async fn duplicate_one(
             };
 
             for file_data in &files_data {
-                let new_file_data = FileDao::new(
+                // Unfinished uploads are left behind
+                if *file_data.pending() {
+                    continue;
+                }
+                let mut new_file_data = FileDao::new(
                     file_data.created_by(),
                     new_bucket_data.id(),
                     file_data.file_name(),
@@ -528,13 +785,15 @@ async fn duplicate_one(
                     }
                 };
                 if let Err(err) = new_file_data
-                    .save(ctx.dao().db(), new_bucket_data.path(), &file_data_path)
+                    .save(
+                        ctx.dao().db(),
+                        new_bucket_data.path(),
+                        &file_data_path,
+                        file_data.checksum(),
+                    )
                     .await
                 {
-                    return Response::error_raw(
-                        &StatusCode::INTERNAL_SERVER_ERROR,
-                        &err.to_string(),
-                    );
+                    return Response::error_dao(&StatusCode::INTERNAL_SERVER_ERROR, &err);
                 }
             }
         }
@@ -549,9 +808,6 @@ async fn duplicate_one(
             token_data.allow_anonymous(),
             token_data.expired_at(),
         );
-        if let Err(err) = new_token_data.db_insert(ctx.dao().db()).await {
-            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
-        }
 
         let (collection_rules_data, bucket_rules_data) = match tokio::try_join!(
             CollectionRuleDao::db_select_many_by_token_id(ctx.dao().db(), token_data.id()),
@@ -561,8 +817,12 @@ async fn duplicate_one(
             Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
         };
 
+        let mut new_collection_rules_data = Vec::with_capacity(collection_rules_data.len());
         for collection_rule_data in &collection_rules_data {
-            let new_collection_id =
+            // Wildcard rules aren't tied to any collection, so they're copied as they are
+            let new_collection_id = if collection_rule_data.is_wildcard() {
+                collection_rule_data.collection_id()
+            } else {
                 match old_new_collection_id_map.get(collection_rule_data.collection_id()) {
                     Some(id) => id,
                     None => {
@@ -574,9 +834,10 @@ async fn duplicate_one(
                             ),
                         )
                     }
-                };
+                }
+            };
 
-            let new_collection_rule_data = CollectionRuleDao::new(
+            new_collection_rules_data.push(CollectionRuleDao::new(
                 new_project_data.id(),
                 new_token_data.id(),
                 new_collection_id,
@@ -585,27 +846,31 @@ async fn duplicate_one(
                 collection_rule_data.insert_one(),
                 collection_rule_data.update_one(),
                 collection_rule_data.delete_one(),
-            );
-            if let Err(err) = new_collection_rule_data.db_insert(ctx.dao().db()).await {
-                return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
-            }
+                collection_rule_data.readable_fields(),
+                collection_rule_data.writable_fields(),
+            ));
         }
 
+        let mut new_bucket_rules_data = Vec::with_capacity(bucket_rules_data.len());
         for bucket_rule_data in &bucket_rules_data {
-            let new_bucket_id = match old_new_bucket_id_map.get(bucket_rule_data.bucket_id()) {
-                Some(id) => id,
-                None => {
-                    return Response::error_raw(
-                        &StatusCode::INTERNAL_SERVER_ERROR,
-                        &format!(
-                            "Bucket id '{}' doesn't found in this project",
-                            bucket_rule_data.bucket_id()
-                        ),
-                    )
+            let new_bucket_id = if bucket_rule_data.is_wildcard() {
+                bucket_rule_data.bucket_id()
+            } else {
+                match old_new_bucket_id_map.get(bucket_rule_data.bucket_id()) {
+                    Some(id) => id,
+                    None => {
+                        return Response::error_raw(
+                            &StatusCode::INTERNAL_SERVER_ERROR,
+                            &format!(
+                                "Bucket id '{}' doesn't found in this project",
+                                bucket_rule_data.bucket_id()
+                            ),
+                        )
+                    }
                 }
             };
 
-            let new_bucket_rule_data = BucketRuleDao::new(
+            new_bucket_rules_data.push(BucketRuleDao::new(
                 new_project_data.id(),
                 new_token_data.id(),
                 new_bucket_id,
@@ -614,26 +879,45 @@ async fn duplicate_one(
                 bucket_rule_data.insert_one(),
                 bucket_rule_data.update_one(),
                 bucket_rule_data.delete_one(),
-            );
-            if let Err(err) = new_bucket_rule_data.db_insert(ctx.dao().db()).await {
-                return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
-            }
+            ));
+        }
+
+        if let Err(err) = new_token_data
+            .db_insert_with_rules(
+                ctx.dao().db(),
+                &new_collection_rules_data,
+                &new_bucket_rules_data,
+            )
+            .await
+        {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
         }
     }
 
-    Response::data(
-        &StatusCode::CREATED,
+    let project_res = ProjectResJson::new(
+        new_project_data.id(),
+        new_project_data.created_at(),
+        new_project_data.updated_at(),
+        new_project_data.name(),
+    );
+
+    // The copied collections, buckets and tokens are part of the project being created
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        new_project_data.id(),
+        &AuditAction::Create,
+        &AuditEntity::Project,
+        new_project_data.id(),
         &None,
-        &ProjectResJson::new(
-            new_project_data.id(),
-            new_project_data.created_at(),
-            new_project_data.updated_at(),
-            new_project_data.name(),
-        ),
-    )
+        &audit_state(&project_res),
+    );
+
+    Response::data(&StatusCode::CREATED, &None, &project_res)
 }
 
-async fn find_many(ctx: web::Data<ApiRestCtx>, auth: BearerAuth) -> HttpResponse {
+async fn find_many(ctx: web::Data<ApiRestCtx>, auth: ApiAuth) -> HttpResponse {
     let token = auth.token();
 
     let token_claim = match ctx.token().jwt().decode(token) {
@@ -659,11 +943,24 @@ async fn find_many(ctx: web::Data<ApiRestCtx>, auth: BearerAuth) -> HttpResponse
         }
     };
 
-    let projects_data =
-        match ProjectDao::db_select_many_by_admin_id(ctx.dao().db(), &admin_id).await {
-            Ok(data) => data,
-            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
-        };
+    let (mut projects_data, members_data) = match tokio::try_join!(
+        ProjectDao::db_select_many_by_admin_id(ctx.dao().db(), &admin_id),
+        AdminProjectMemberDao::db_select_many_by_admin_id(ctx.dao().db(), &admin_id)
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    // Projects shared with the admin are listed after their own
+    for member_data in &members_data {
+        match ProjectDao::db_select(ctx.dao().db(), member_data.project_id()).await {
+            Ok(data) => projects_data.push(data),
+            Err(err) if DaoError::is_not_found(&err) => continue,
+            Err(err) => {
+                return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+            }
+        }
+    }
 
     Response::data(
         &StatusCode::OK,
@@ -679,3 +976,824 @@ async fn find_many(ctx: web::Data<ApiRestCtx>, auth: BearerAuth) -> HttpResponse
             .collect::<Vec<_>>(),
     )
 }
+
+async fn export_one(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<ExportOneProjectReqPath>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
+    }
+
+    let project_export = match ProjectExport::new(ctx.dao().db(), &project_data).await {
+        Ok(project_export) => project_export,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let content_disposition = format!("attachment; filename=\"project-{}.tar\"", project_data.id());
+    let (tx, rx) = mpsc::channel(ARCHIVE_CHANNEL_CAPACITY);
+    tokio::spawn((|| async move {
+        project_export.write(ctx.dao().db(), tx).await;
+    })());
+
+    HttpResponse::Ok()
+        .content_type("application/x-tar")
+        .insert_header((header::CONTENT_DISPOSITION, content_disposition))
+        .streaming(stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|chunk| {
+                (
+                    chunk
+                        .map(web::Bytes::from)
+                        .map_err(error::ErrorInternalServerError),
+                    rx,
+                )
+            })
+        }))
+}
+
+async fn import_one(
+    ctx: web::Data<ApiRestCtx>,
+    req: HttpRequest,
+    auth: ApiAuth,
+    query: web::Query<ImportProjectReqQuery>,
+    form: MultipartForm<ImportProjectReqForm>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let mut archive_file = match fs::File::open(form.file_path()).await {
+        Ok(file) => file,
+        Err(err) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &format!("Failed to read the uploaded file: {err}"),
+            )
+        }
+    };
+    let entries = match archive::read_entries(&mut archive_file).await {
+        Ok(entries) => entries,
+        Err(err) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &format!("The uploaded file isn't a valid archive: {err}"),
+            )
+        }
+    };
+    let manifest = match entries
+        .iter()
+        .find(|entry| entry.path() == ARCHIVE_MANIFEST_PATH)
+    {
+        Some(entry) => match archive::read_entry(&mut archive_file, entry)
+            .await
+            .and_then(|manifest| Ok(serde_json::from_slice::<ProjectArchiveJson>(&manifest)?))
+        {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    &format!("Failed to read the archive manifest: {err}"),
+                )
+            }
+        },
+        None => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &format!("The archive doesn't have a '{ARCHIVE_MANIFEST_PATH}'"),
+            )
+        }
+    };
+    if *manifest.version() != ARCHIVE_VERSION {
+        return Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            &format!(
+                "Archive version {} isn't supported, only version {ARCHIVE_VERSION} is",
+                manifest.version()
+            ),
+        );
+    }
+
+    let mut plan = match plan_import(
+        &ctx,
+        &admin_id,
+        &manifest,
+        &entries,
+        &mut archive_file,
+        &query.preserve_ids().unwrap_or(false),
+    )
+    .await
+    {
+        Ok(plan) => plan,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+    if !plan.errors.is_empty() {
+        return Response::data(
+            &StatusCode::BAD_REQUEST,
+            &None,
+            &ImportProjectResJson::new(None, &plan.errors),
+        );
+    }
+
+    if let Err(err) = plan.project_data.db_insert(ctx.dao().db()).await {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+    // Whatever was written before a failure goes away with the project
+    if let Err(err) = write_import(&ctx, &mut plan, &entries, &mut archive_file).await {
+        if let Err(err) = ProjectDao::db_delete(ctx.dao().db(), plan.project_data.id()).await {
+            hb_log::error(
+                None,
+                &format!("[ApiRestServer] Error when removing a failed project import: {err}"),
+            );
+        }
+        return Response::error_raw(
+            &StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("Failed to import the project: {err}"),
+        );
+    }
+
+    let project_res = ProjectResJson::new(
+        plan.project_data.id(),
+        plan.project_data.created_at(),
+        plan.project_data.updated_at(),
+        plan.project_data.name(),
+    );
+
+    // The imported collections, buckets and tokens are part of the project being created
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        plan.project_data.id(),
+        &AuditAction::Create,
+        &AuditEntity::Project,
+        plan.project_data.id(),
+        &None,
+        &audit_state(&project_res),
+    );
+
+    Response::data(
+        &StatusCode::CREATED,
+        &None,
+        &ImportProjectResJson::new(Some(project_res), &Vec::new()),
+    )
+}
+
+// Everything an import is going to write, built before any of it is written
+struct ImportPlan {
+    project_data: ProjectDao,
+    // Ids in the archive mapped to the ids they get in this instance, anything not in here is
+    // kept as it is
+    ids: HashMap<Uuid, Uuid>,
+    collections_data: HashMap<Uuid, CollectionDao>,
+    buckets_data: Vec<BucketDao>,
+    files_data: Vec<(String, FileDao, Option<String>)>,
+    tokens_data: Vec<(TokenDao, Vec<CollectionRuleDao>, Vec<BucketRuleDao>)>,
+    errors: Vec<String>,
+}
+
+// Checks the whole archive against itself and against this instance, so an import is either
+// refused with every problem found or written completely
+async fn plan_import(
+    ctx: &ApiRestCtx,
+    admin_id: &Uuid,
+    manifest: &ProjectArchiveJson,
+    entries: &Vec<ArchiveEntry>,
+    archive_file: &mut fs::File,
+    preserve_ids: &bool,
+) -> anyhow::Result<ImportPlan> {
+    let mut errors = Vec::new();
+    let mut ids = HashMap::new();
+    let new_id = |id: &Uuid| if *preserve_ids { *id } else { Uuid::now_v7() };
+
+    ids.insert(*manifest.project().admin_id(), *admin_id);
+    let mut project_data = ProjectDao::new(admin_id, manifest.project().name());
+    project_data.set_id(&new_id(manifest.project().id()));
+    ids.insert(*manifest.project().id(), *project_data.id());
+    if ProjectDao::db_select_many_by_admin_id(ctx.dao().db(), admin_id)
+        .await?
+        .iter()
+        .any(|other_project_data| other_project_data.name() == project_data.name())
+    {
+        errors.push(format!("Project '{}' already exists", project_data.name()));
+    }
+
+    for collection in manifest.collections() {
+        ids.insert(*collection.id(), new_id(collection.id()));
+    }
+    for bucket in manifest.buckets() {
+        ids.insert(*bucket.id(), new_id(bucket.id()));
+    }
+    for file in manifest.files() {
+        ids.insert(*file.id(), new_id(file.id()));
+    }
+    for token in manifest.tokens() {
+        ids.insert(*token.id(), new_id(token.id()));
+    }
+
+    // Every lookup that doesn't come back as not found counts against the import, so an id that
+    // couldn't be checked is never written over
+    if *preserve_ids {
+        check_id_free(
+            &mut errors,
+            "Project",
+            project_data.id(),
+            ProjectDao::db_select(ctx.dao().db(), project_data.id())
+                .await
+                .map(|_| true),
+        );
+        for collection in manifest.collections() {
+            check_id_free(
+                &mut errors,
+                "Collection",
+                collection.id(),
+                CollectionDao::db_select(ctx.dao().db(), collection.id())
+                    .await
+                    .map(|_| true),
+            );
+            // Records only clash with the ones in the table of their collection, which can be
+            // left behind without the collection
+            check_id_free(
+                &mut errors,
+                "Records table of collection",
+                collection.id(),
+                RecordDao::db_check_table_existence(ctx.dao().db(), collection.id()).await,
+            );
+        }
+        for bucket in manifest.buckets() {
+            check_id_free(
+                &mut errors,
+                "Bucket",
+                bucket.id(),
+                BucketDao::db_select(ctx.dao().db(), bucket.id())
+                    .await
+                    .map(|_| true),
+            );
+        }
+        for file in manifest.files() {
+            check_id_free(
+                &mut errors,
+                "File",
+                file.id(),
+                FileDao::db_exists(ctx.dao().db(), file.id()).await,
+            );
+        }
+        for token in manifest.tokens() {
+            check_id_free(
+                &mut errors,
+                "Token",
+                token.id(),
+                TokenDao::db_select(ctx.dao().db(), token.id())
+                    .await
+                    .map(|_| true),
+            );
+        }
+    }
+
+    let mut collection_names = HashSet::with_capacity(manifest.collections().len());
+    let mut collections_data = HashMap::with_capacity(manifest.collections().len());
+    for collection in manifest.collections() {
+        let schema = collection.schema();
+        if !collection_names.insert(schema.name()) {
+            errors.push(format!(
+                "Collection '{}' appears more than once",
+                schema.name()
+            ));
+        }
+        for (field, props) in schema.schema_fields() {
+            if let Some(reference_collection_id) = props.reference_collection_id() {
+                if !manifest
+                    .collections()
+                    .iter()
+                    .any(|collection| collection.id() == reference_collection_id)
+                {
+                    errors.push(format!(
+                        "Field '{field}' of collection '{}' references a collection that isn't in the archive",
+                        schema.name()
+                    ));
+                }
+            }
+        }
+        let schema_fields = match schema_fields_from_json(schema.schema_fields(), &ids) {
+            Ok(schema_fields) => schema_fields,
+            Err(err) => {
+                errors.push(format!("Collection '{}': {err}", schema.name()));
+                continue;
+            }
+        };
+        let mut collection_data = CollectionDao::new(
+            project_data.id(),
+            schema.name(),
+            &schema_fields,
+            schema.unique_indexes(),
+            schema.opt_auth_column_id(),
+            schema.opt_ttl(),
+            schema.opt_soft_delete(),
+            schema.opt_per_record_ttl(),
+            schema.opt_mqtt_publish(),
+        );
+        collection_data.set_id(&ids[collection.id()]);
+        collections_data.insert(*collection.id(), collection_data);
+    }
+
+    let mut bucket_names = HashSet::with_capacity(manifest.buckets().len());
+    let mut buckets_data = Vec::with_capacity(manifest.buckets().len());
+    let mut bucket_paths = HashMap::with_capacity(manifest.buckets().len());
+    for bucket in manifest.buckets() {
+        if !bucket_names.insert(bucket.name()) {
+            errors.push(format!("Bucket '{}' appears more than once", bucket.name()));
+        }
+        if let Some(allowed_content_types) = bucket.allowed_content_types() {
+            if let Err(err) = BucketDao::check_content_type_patterns(allowed_content_types) {
+                errors.push(format!("Bucket '{}': {err}", bucket.name()));
+                continue;
+            }
+        }
+        let mut bucket_data = BucketDao::new(
+            project_data.id(),
+            bucket.name(),
+            ctx.bucket_path(),
+            bucket.opt_ttl(),
+            bucket.max_file_size(),
+            bucket.allowed_content_types(),
+            bucket.quota_bytes(),
+        )
+        .await?;
+        bucket_data.set_id(&ids[bucket.id()]);
+        bucket_paths.insert(*bucket.id(), bucket_data.path().to_owned());
+        buckets_data.push(bucket_data);
+    }
+
+    let mut files_data = Vec::with_capacity(manifest.files().len());
+    for file in manifest.files() {
+        if !bucket_paths.contains_key(file.bucket_id()) {
+            errors.push(format!(
+                "File '{}' belongs to a bucket that isn't in the archive",
+                file.id()
+            ));
+            continue;
+        }
+        let content_type = match file.content_type().parse::<Mime>() {
+            Ok(content_type) => content_type,
+            Err(err) => {
+                errors.push(format!("File '{}': {err}", file.id()));
+                continue;
+            }
+        };
+        let file_path = format!("files/{}", file.id());
+        match entries.iter().find(|entry| entry.path() == file_path) {
+            Some(entry) if i64::try_from(*entry.size()).is_ok_and(|size| size == *file.size()) => {}
+            Some(_) => {
+                errors.push(format!(
+                    "Content of file '{}' doesn't match its size",
+                    file.id()
+                ));
+                continue;
+            }
+            None => {
+                errors.push(format!(
+                    "Content of file '{}' isn't in the archive",
+                    file.id()
+                ));
+                continue;
+            }
+        }
+        let mut file_data = FileDao::new(
+            ids.get(file.created_by()).unwrap_or(file.created_by()),
+            &ids[file.bucket_id()],
+            file.file_name(),
+            &content_type,
+            file.size(),
+            file.public(),
+        );
+        file_data.set_id(&ids[file.id()]);
+        files_data.push((file_path, file_data, file.checksum().clone()));
+    }
+
+    let mut token_names = HashSet::with_capacity(manifest.tokens().len());
+    let mut tokens_data = Vec::with_capacity(manifest.tokens().len());
+    for token in manifest.tokens() {
+        if !token_names.insert(token.name()) {
+            errors.push(format!("Token '{}' appears more than once", token.name()));
+        }
+        let mut token_data = TokenDao::new(
+            project_data.id(),
+            admin_id,
+            token.name(),
+            ctx.access_token_length(),
+            token.allow_anonymous(),
+            token.expired_at(),
+        );
+        token_data.set_id(&ids[token.id()]);
+
+        let mut collection_rules_data = Vec::with_capacity(token.collection_rules().len());
+        for collection_rule in token.collection_rules() {
+            // Wildcard rules aren't tied to any collection, so they're kept as they are
+            let collection_id = if *collection_rule.collection_id() == ALL_COLLECTIONS_ID {
+                ALL_COLLECTIONS_ID
+            } else if let Some(collection_data) =
+                collections_data.get(collection_rule.collection_id())
+            {
+                for field in [
+                    collection_rule.readable_fields(),
+                    collection_rule.writable_fields(),
+                ]
+                .into_iter()
+                .flatten()
+                .flatten()
+                {
+                    if !collection_data.schema_fields().contains_key(field) {
+                        errors.push(format!(
+                            "A rule of token '{}' has field '{field}' that isn't in collection '{}'",
+                            token.name(),
+                            collection_data.name()
+                        ));
+                    }
+                }
+                *collection_data.id()
+            } else {
+                errors.push(format!(
+                    "A rule of token '{}' is for a collection that isn't in the archive",
+                    token.name()
+                ));
+                continue;
+            };
+            let permissions = (
+                CollectionPermission::from_str(collection_rule.find_one()),
+                CollectionPermission::from_str(collection_rule.find_many()),
+                CollectionPermission::from_str(collection_rule.update_one()),
+                CollectionPermission::from_str(collection_rule.delete_one()),
+            );
+            let (find_one, find_many, update_one, delete_one) = match permissions {
+                (Ok(find_one), Ok(find_many), Ok(update_one), Ok(delete_one)) => {
+                    (find_one, find_many, update_one, delete_one)
+                }
+                (Err(err), _, _, _)
+                | (_, Err(err), _, _)
+                | (_, _, Err(err), _)
+                | (_, _, _, Err(err)) => {
+                    errors.push(format!("A rule of token '{}': {err}", token.name()));
+                    continue;
+                }
+            };
+            collection_rules_data.push(CollectionRuleDao::new(
+                project_data.id(),
+                token_data.id(),
+                &collection_id,
+                &find_one,
+                &find_many,
+                collection_rule.insert_one(),
+                &update_one,
+                &delete_one,
+                collection_rule.readable_fields(),
+                collection_rule.writable_fields(),
+            ));
+        }
+
+        let mut bucket_rules_data = Vec::with_capacity(token.bucket_rules().len());
+        for bucket_rule in token.bucket_rules() {
+            let bucket_id = if *bucket_rule.bucket_id() == ALL_BUCKETS_ID {
+                ALL_BUCKETS_ID
+            } else if bucket_paths.contains_key(bucket_rule.bucket_id()) {
+                ids[bucket_rule.bucket_id()]
+            } else {
+                errors.push(format!(
+                    "A rule of token '{}' is for a bucket that isn't in the archive",
+                    token.name()
+                ));
+                continue;
+            };
+            let permissions = (
+                BucketPermission::from_str(bucket_rule.find_one()),
+                BucketPermission::from_str(bucket_rule.find_many()),
+                BucketPermission::from_str(bucket_rule.update_one()),
+                BucketPermission::from_str(bucket_rule.delete_one()),
+            );
+            let (find_one, find_many, update_one, delete_one) = match permissions {
+                (Ok(find_one), Ok(find_many), Ok(update_one), Ok(delete_one)) => {
+                    (find_one, find_many, update_one, delete_one)
+                }
+                (Err(err), _, _, _)
+                | (_, Err(err), _, _)
+                | (_, _, Err(err), _)
+                | (_, _, _, Err(err)) => {
+                    errors.push(format!("A rule of token '{}': {err}", token.name()));
+                    continue;
+                }
+            };
+            bucket_rules_data.push(BucketRuleDao::new(
+                project_data.id(),
+                token_data.id(),
+                &bucket_id,
+                &find_one,
+                &find_many,
+                bucket_rule.insert_one(),
+                &update_one,
+                &delete_one,
+            ));
+        }
+
+        tokens_data.push((token_data, collection_rules_data, bucket_rules_data));
+    }
+
+    // Records are read once here to check them and collect their ids, and again when written
+    for entry in entries {
+        let collection_id = match records_entry_collection_id(entry) {
+            Some(collection_id) => collection_id,
+            None => continue,
+        };
+        let collection_data = match collections_data.get(&collection_id) {
+            Some(collection_data) => collection_data,
+            None => {
+                if !manifest
+                    .collections()
+                    .iter()
+                    .any(|collection| *collection.id() == collection_id)
+                {
+                    errors.push(format!(
+                        "Records in '{}' belong to a collection that isn't in the archive",
+                        entry.path()
+                    ));
+                }
+                continue;
+            }
+        };
+        let content = archive::read_entry(archive_file, entry).await?;
+        for (line, row) in records_entry_rows(&content) {
+            if errors.len() >= IMPORT_PROJECT_MAX_ERRORS {
+                break;
+            }
+            match row.and_then(|row| archive_record(collection_data, &row, &HashMap::new())) {
+                Ok(record_data) => {
+                    if let Some(record_id) = record_data.id() {
+                        if ids.contains_key(record_id) {
+                            errors.push(format!(
+                                "Line {line} of '{}': Record id '{record_id}' appears more than once",
+                                entry.path()
+                            ));
+                            continue;
+                        }
+                        ids.insert(*record_id, new_id(record_id));
+                    }
+                }
+                Err(err) => errors.push(format!("Line {line} of '{}': {err}", entry.path())),
+            }
+        }
+    }
+    errors.truncate(IMPORT_PROJECT_MAX_ERRORS);
+
+    Ok(ImportPlan {
+        project_data,
+        ids,
+        collections_data,
+        buckets_data,
+        files_data,
+        tokens_data,
+        errors,
+    })
+}
+
+fn check_id_free(errors: &mut Vec<String>, kind: &str, id: &Uuid, taken: anyhow::Result<bool>) {
+    match taken {
+        Ok(false) => (),
+        Err(err) if DaoError::is_not_found(&err) => (),
+        Ok(true) => errors.push(format!("{kind} id '{id}' already exists")),
+        Err(err) => errors.push(format!("{kind} id '{id}' couldn't be checked: {err}")),
+    }
+}
+
+async fn write_import(
+    ctx: &ApiRestCtx,
+    plan: &mut ImportPlan,
+    entries: &Vec<ArchiveEntry>,
+    archive_file: &mut fs::File,
+) -> anyhow::Result<()> {
+    for collection_data in plan.collections_data.values() {
+        collection_data.db_insert(ctx.dao().db()).await?;
+    }
+
+    for entry in entries {
+        let collection_data = match records_entry_collection_id(entry)
+            .and_then(|collection_id| plan.collections_data.get(&collection_id))
+        {
+            Some(collection_data) => collection_data,
+            None => continue,
+        };
+        let content = archive::read_entry(archive_file, entry).await?;
+        let mut records_data = Vec::new();
+        for (line, row) in records_entry_rows(&content) {
+            records_data.push(
+                row.and_then(|row| archive_record(collection_data, &row, &plan.ids))
+                    .map_err(|err| {
+                        anyhow::Error::msg(format!("Line {line} of '{}': {err}", entry.path()))
+                    })?,
+            );
+        }
+        // Every record of a collection gets the same columns, so the first one tells how many
+        // rows fit in a single insert
+        let batch_size = match records_data.first() {
+            Some(record_data) => (*ctx.import_batch_size())
+                .min(RecordDao::insert_many_limit(&record_data.len()))
+                .max(1),
+            None => continue,
+        };
+        let collection_data = Some(collection_data.clone());
+        for batch in records_data.chunks(batch_size) {
+            RecordDao::db_insert_many(ctx.dao().db(), batch, &collection_data).await?;
+        }
+    }
+
+    for bucket_data in &plan.buckets_data {
+        bucket_data.db_insert(ctx.dao().db()).await?;
+    }
+
+    for (file_path, file_data, checksum) in &mut plan.files_data {
+        let bucket_data = match plan
+            .buckets_data
+            .iter()
+            .find(|bucket_data| bucket_data.id() == file_data.bucket_id())
+        {
+            Some(bucket_data) => bucket_data,
+            None => continue,
+        };
+        let entry = match entries.iter().find(|entry| entry.path() == *file_path) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        // The content goes through a file of its own so it's checked and saved like an upload
+        let temp_path = env::temp_dir().join(format!("hb-import-{}", Uuid::now_v7()));
+        let res = match archive::extract_entry(archive_file, entry, &temp_path).await {
+            Ok(_) => {
+                file_data
+                    .save(ctx.dao().db(), bucket_data.path(), &temp_path, checksum)
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+        let _ = fs::remove_file(&temp_path).await;
+        res?;
+    }
+
+    for (token_data, collection_rules_data, bucket_rules_data) in &plan.tokens_data {
+        token_data
+            .db_insert_with_rules(ctx.dao().db(), collection_rules_data, bucket_rules_data)
+            .await?;
+    }
+
+    Ok(())
+}
+
+// Records are kept under records/{collection_id}/, one JSON Lines chunk per entry
+fn records_entry_collection_id(entry: &ArchiveEntry) -> Option<Uuid> {
+    entry
+        .path()
+        .strip_prefix("records/")
+        .and_then(|path| path.split_once('/'))
+        .and_then(|(collection_id, _)| Uuid::parse_str(collection_id).ok())
+}
+
+fn records_entry_rows(
+    content: &[u8],
+) -> Vec<(
+    usize,
+    Result<serde_json::Map<String, serde_json::Value>, String>,
+)> {
+    let content = match std::str::from_utf8(content) {
+        Ok(content) => content,
+        Err(err) => return vec![(1, Err(err.to_string()))],
+    };
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            let row = match serde_json::from_str::<serde_json::Value>(line) {
+                Ok(serde_json::Value::Object(row)) => Ok(row),
+                Ok(_) => Err("Line is not a JSON object".to_owned()),
+                Err(err) => Err(err.to_string()),
+            };
+            (idx + 1, row)
+        })
+        .collect()
+}
+
+// Turns a line of a records chunk back into a record of the imported collection. Values are
+// stored as they were exported, hashed fields and counters included, and ids found in `ids` are
+// replaced so owners and references keep pointing at the imported data.
+fn archive_record(
+    collection_data: &CollectionDao,
+    row: &serde_json::Map<String, serde_json::Value>,
+    ids: &HashMap<Uuid, Uuid>,
+) -> Result<RecordDao, String> {
+    let map_id = |id: Uuid| *ids.get(&id).unwrap_or(&id);
+    let id_field = |field: &str| match row
+        .get(field)
+        .and_then(|value| value.as_str())
+        .map(Uuid::parse_str)
+    {
+        Some(Ok(id)) => Ok(map_id(id)),
+        _ => Err(format!("Field '{field}' is missing or isn't a valid id")),
+    };
+    let timestamp_field = |field: &str| {
+        ColumnValue::from_serde_json(
+            &ColumnKind::Timestamp,
+            row.get(field).unwrap_or(&serde_json::Value::Null),
+        )
+        .map_err(|err| format!("Error in field '{field}': {err}"))
+    };
+
+    for field in row.keys() {
+        match field.as_str() {
+            "_id" | "_created_by" | "_updated_at" => (),
+            "_expired_at" if *collection_data.opt_per_record_ttl() => (),
+            _ if collection_data.schema_fields().contains_key(field) => (),
+            _ => return Err(format!("Field '{field}' is not exist in the collection")),
+        }
+    }
+
+    let mut record_data =
+        RecordDao::new(&id_field("_created_by")?, collection_data.id(), &row.len());
+    record_data.upsert("_id", &ColumnValue::Uuid(Some(id_field("_id")?)));
+    if row.contains_key("_updated_at") {
+        record_data.upsert("_updated_at", &timestamp_field("_updated_at")?);
+    }
+    // Every record gets the column, so records of a collection can be inserted together
+    if *collection_data.opt_per_record_ttl() {
+        record_data.upsert("_expired_at", &timestamp_field("_expired_at")?);
+    }
+    for (field, props) in collection_data.schema_fields() {
+        let value = match row.get(field) {
+            Some(value) => ColumnValue::from_serde_json(props.kind(), value)
+                .map_err(|err| format!("Error in field '{field}': {err}"))?,
+            None => ColumnValue::none(props.kind()),
+        };
+        let value = match (props.kind(), value) {
+            (ColumnKind::Reference { .. }, ColumnValue::Uuid(Some(id))) => {
+                ColumnValue::Uuid(Some(map_id(id)))
+            }
+            (_, value) => value,
+        };
+        record_data.upsert(field, &value);
+    }
+    Ok(record_data)
+}
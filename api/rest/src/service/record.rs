@@ -1,20 +1,22 @@
-use actix_web::{http::StatusCode, web, HttpResponse};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
+use actix_multipart::form::MultipartForm;
+use actix_web::{error, http::StatusCode, web, HttpRequest, HttpResponse};
 use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
-use hb_api_websocket::message::{
-    Message as WebSocketMessage, MessageKind as WebSocketMessageKind, Target as WebSocketTarget,
-};
+use futures::{stream, Stream, StreamExt};
+use hb_api_websocket::message::{MessageKind as WebSocketMessageKind, Target as WebSocketTarget};
 use hb_dao::{
     admin::AdminDao,
     collection::CollectionDao,
-    collection_rule::CollectionPermission,
+    collection_rule::{CollectionPermission, CollectionRuleDao},
+    error::DaoError,
+    idempotency::IdempotencyDao,
     log::{LogDao, LogKind},
     project::ProjectDao,
-    record::{RecordDao, RecordFilters, RecordOrder, RecordPagination},
+    record::{RecordDao, RecordFilter, RecordFilters, RecordOrder, RecordPagination},
     token::TokenDao,
     value::{ColumnKind, ColumnValue},
 };
-use hb_token_jwt::claim::ClaimId;
+use hb_token_jwt::claim::{ClaimId, UserClaim};
+use tokio::sync::mpsc;
 use uuid::Uuid;
 
 use crate::{
@@ -22,16 +24,27 @@ use crate::{
     model::{
         log::LogResJson,
         record::{
-            DeleteOneRecordReqPath, DeleteRecordResJson, FindManyRecordReqJson,
-            FindManyRecordReqPath, FindOneRecordReqPath, FindOneRecordReqQuery,
-            InsertOneRecordReqJson, InsertOneRecordReqPath, RecordResJson, UpdateOneRecordReqJson,
+            DeleteOneRecordReqPath, DeleteRecordResJson, ExportRecordReqJson, ExportRecordReqPath,
+            ExportRecordReqQuery, FindManyRecordReqJson, FindManyRecordReqPath,
+            FindOneRecordReqPath, FindOneRecordReqQuery, ImportRecordErrorResJson,
+            ImportRecordReqForm, ImportRecordReqPath, ImportRecordResJson,
+            IncrementCounterRecordReqJson, IncrementCounterRecordReqPath, InsertOneRecordReqJson,
+            InsertOneRecordReqPath, RecordResJson, RestoreOneRecordReqPath, UpdateOneRecordReqJson,
             UpdateOneRecordReqPath,
         },
         PaginationRes, Response,
     },
-    util::ws_broadcast::websocket_broadcast,
+    util::{
+        api_auth::ApiAuth,
+        export::ExportFormat,
+        import::ImportFormat,
+        project_access::{authorize_project, ProjectAccess},
+        ws_broadcast::websocket_broadcast,
+    },
 };
 
+const STREAM_CHANNEL_CAPACITY: usize = 1000;
+
 pub fn record_api(cfg: &mut web::ServiceConfig) {
     cfg.route(
         "/project/{project_id}/collection/{collection_id}/record",
@@ -49,18 +62,48 @@ pub fn record_api(cfg: &mut web::ServiceConfig) {
         "/project/{project_id}/collection/{collection_id}/record/{record_id}",
         web::delete().to(delete_one),
     )
+    .route(
+        "/project/{project_id}/collection/{collection_id}/record/{record_id}/restore",
+        web::post().to(restore_one),
+    )
+    .route(
+        "/project/{project_id}/collection/{collection_id}/record/{record_id}/counters/{field}",
+        web::post().to(increment_counter),
+    )
     .route(
         "/project/{project_id}/collection/{collection_id}/records",
         web::post().to(find_many),
+    )
+    .route(
+        "/project/{project_id}/collection/{collection_id}/records/export",
+        web::post().to(export_many),
+    )
+    .route(
+        "/project/{project_id}/collection/{collection_id}/records/import",
+        web::post().to(import_many),
     );
 }
 
 async fn insert_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<InsertOneRecordReqPath>,
     data: web::Json<InsertOneRecordReqJson>,
 ) -> HttpResponse {
+    let idempotency_key = match req.headers().get("Idempotency-Key") {
+        Some(value) => match value.to_str() {
+            Ok(value) if !value.is_empty() && value.len() <= 255 => Some(value.to_owned()),
+            _ => {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    "Idempotency-Key header must be 1 to 255 visible ASCII characters",
+                )
+            }
+        },
+        None => None,
+    };
+
     let token = auth.token();
 
     let token_claim = match ctx.token().jwt().decode(token) {
@@ -79,8 +122,11 @@ async fn insert_one(
             }
         },
         ClaimId::Token(token_id, user_claim) => {
-            match TokenDao::db_select(ctx.dao().db(), token_id).await {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
                 Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                }
                 Err(err) => {
                     return Response::error_raw(
                         &StatusCode::BAD_REQUEST,
@@ -145,20 +191,84 @@ async fn insert_one(
         CollectionDao::db_select(ctx.dao().db(), path.collection_id())
     ) {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if &admin_id != project_data.admin_id() {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
     }
 
     if project_data.id() != collection_data.project_id() {
         return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
     }
 
+    let collection_rule_data = token_collection_rule(&ctx, &token_data, collection_data.id()).await;
+
+    // Idempotency keys are scoped to whoever sent the request, so clients can't replay each
+    // other's inserts
+    let idempotency_owner_id = match token_claim.id() {
+        ClaimId::Admin(_) => admin_id,
+        ClaimId::Token(token_id, user_claim) => match user_claim {
+            Some(user_claim) => *user_claim.id(),
+            None => *token_id,
+        },
+    };
+
+    if let Some(idempotency_key) = &idempotency_key {
+        match IdempotencyDao::db_select(
+            ctx.dao().db(),
+            collection_data.id(),
+            &idempotency_owner_id,
+            idempotency_key,
+        )
+        .await
+        {
+            Ok(idempotency_data) => {
+                let record_data = match RecordDao::db_select(
+                    ctx.dao().db(),
+                    idempotency_data.record_id(),
+                    &None,
+                    &HashSet::new(),
+                    &collection_data,
+                    &token_data.is_none(),
+                )
+                .await
+                {
+                    Ok(data) => data,
+                    Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+                };
+
+                let mut record = match record_data.to_serde_json() {
+                    Ok(data) => data,
+                    Err(err) => {
+                        return Response::error_raw(
+                            &StatusCode::INTERNAL_SERVER_ERROR,
+                            &err.to_string(),
+                        )
+                    }
+                };
+                strip_unreadable_fields(&collection_rule_data, std::slice::from_mut(&mut record));
+
+                return Response::data(&StatusCode::OK, &None, &RecordResJson::new(&record));
+            }
+            Err(err) => {
+                if !DaoError::is_not_found(&err) {
+                    return Response::error_raw(
+                        &StatusCode::INTERNAL_SERVER_ERROR,
+                        &err.to_string(),
+                    );
+                }
+            }
+        }
+    }
+
     for field_name in data.keys() {
         if field_name == "_created_by" {
             if matches!(token_claim.id(), ClaimId::Admin(_)) {
@@ -170,6 +280,9 @@ async fn insert_one(
                 );
             }
         }
+        if field_name == "_expired_at" && *collection_data.opt_per_record_ttl() {
+            continue;
+        }
         if !collection_data.schema_fields().contains_key(field_name) {
             return Response::error_raw(
                 &StatusCode::BAD_REQUEST,
@@ -178,6 +291,11 @@ async fn insert_one(
         }
     }
 
+    if let Err(err) = check_writable_fields(&collection_rule_data, data.keys().map(String::as_str))
+    {
+        return err;
+    }
+
     let created_by = if let Some(created_by) = data.get("_created_by") {
         match created_by.as_str() {
             Some(created_by) => match Uuid::parse_str(created_by) {
@@ -229,6 +347,18 @@ async fn insert_one(
 
     let mut record_data = RecordDao::new(&created_by, collection_data.id(), &data.len());
     for (field_name, field_props) in collection_data.schema_fields() {
+        if *field_props.kind() == ColumnKind::Counter {
+            if let Some(value) = data.get(field_name) {
+                if !value.is_null() {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Field '{field_name}' is a counter and can only be changed using the counters endpoint"),
+                    );
+                }
+            }
+            record_data.upsert(field_name, &ColumnValue::none(field_props.kind()));
+            continue;
+        }
         if let Some(value) = data.get(field_name) {
             if !value.is_null() {
                 let mut value = value.clone();
@@ -266,7 +396,7 @@ async fn insert_one(
                 }
                 record_data.upsert(
                     field_name,
-                    &match ColumnValue::from_serde_json(field_props.kind(), &value) {
+                    &match field_props.value_from_serde_json(&value) {
                         Ok(value) => value,
                         Err(err) => {
                             return Response::error_raw(
@@ -279,7 +409,9 @@ async fn insert_one(
                 continue;
             }
         }
-        if *field_props.required() {
+        if let Some(default) = field_props.default() {
+            record_data.upsert(field_name, default);
+        } else if *field_props.required() {
             return Response::error_raw(
                 &StatusCode::BAD_REQUEST,
                 &format!("Value for '{field_name}' is required"),
@@ -289,66 +421,110 @@ async fn insert_one(
         }
     }
 
+    if *collection_data.opt_per_record_ttl() {
+        record_data.upsert(
+            "_expired_at",
+            &match ColumnValue::from_serde_json(
+                &ColumnKind::Timestamp,
+                data.get("_expired_at").unwrap_or(&serde_json::Value::Null),
+            ) {
+                Ok(value) => value,
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Error in field '_expired_at': {err}"),
+                    )
+                }
+            },
+        );
+    }
+
     if let Err(err) = record_data
-        .db_insert(ctx.dao().db(), &Some(collection_data))
+        .db_check_references(ctx.dao().db(), &collection_data)
         .await
     {
-        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+        return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string());
     }
 
-    let mut record = HashMap::with_capacity(record_data.len());
-    for (key, value) in record_data.data() {
-        let value = match value.to_serde_json() {
-            Ok(value) => value,
-            Err(err) => {
-                return Response::error_raw(
-                    &StatusCode::INTERNAL_SERVER_ERROR,
-                    &format!("Error in field '{key}': {err}"),
-                )
-            }
-        };
-        record.insert(key.to_owned(), value);
+    // The key is stored before the record, so a retry racing the first request is refused instead
+    // of inserting a duplicate
+    let idempotency_data = match (&idempotency_key, record_data.id()) {
+        (Some(idempotency_key), Some(record_id)) => Some(IdempotencyDao::new(
+            collection_data.id(),
+            &idempotency_owner_id,
+            idempotency_key,
+            record_id,
+        )),
+        _ => None,
+    };
+    if let Some(idempotency_data) = &idempotency_data {
+        if let Err(err) = idempotency_data.db_insert(ctx.dao().db()).await {
+            return match IdempotencyDao::db_select(
+                ctx.dao().db(),
+                idempotency_data.collection_id(),
+                idempotency_data.owner_id(),
+                idempotency_data.key(),
+            )
+            .await
+            {
+                Ok(_) => Response::error_raw(
+                    &StatusCode::CONFLICT,
+                    "A request with the same Idempotency-Key is already being processed",
+                ),
+                Err(_) => Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
+            };
+        }
     }
 
-    let record_pub = record.clone();
-    tokio::spawn((|| async move {
-        let record_id = Uuid::parse_str(record_pub["_id"].as_str().unwrap()).unwrap();
-        let created_by = Uuid::parse_str(record_pub["_created_by"].as_str().unwrap()).unwrap();
-
-        let record = match serde_json::to_value(&record_pub) {
-            Ok(value) => value,
-            Err(err) => {
+    if let Err(err) = record_data
+        .db_insert(ctx.dao().db(), &Some(collection_data))
+        .await
+    {
+        if let Some(idempotency_data) = &idempotency_data {
+            if let Err(err) = idempotency_data.db_delete(ctx.dao().db()).await {
                 hb_log::error(
                     None,
-                    &format!("[ApiRestServer] Error when serializing record {record_id}: {err}"),
+                    &format!("[ApiRestServer] Error when deleting idempotency key: {err}"),
                 );
-                return;
             }
-        };
+        }
+        return Response::error_dao(&StatusCode::INTERNAL_SERVER_ERROR, &err);
+    }
 
-        if let Err(err) = ctx.websocket().handler().broadcast(WebSocketMessage::new(
-            WebSocketTarget::Collection(*path.collection_id()),
-            Some(created_by),
-            WebSocketMessageKind::InsertOne,
-            record,
-        )) {
-            hb_log::error(
-                None,
-                &format!(
-                    "[ApiRestServer] Error when broadcasting insert_one record {} to websocket: {}",
-                    record_pub["_id"], err
-                ),
-            );
-            return;
+    let mut record = match record_data.to_serde_json() {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
         }
-    })());
+    };
+
+    // Broadcasting before responding keeps the websocket messages of a record in the same order as
+    // the requests that changed it
+    let created_by = Uuid::parse_str(record["_created_by"].as_str().unwrap()).unwrap();
+    if let Err(err) = websocket_broadcast(
+        ctx.websocket().handler(),
+        WebSocketTarget::Collection(*path.collection_id()),
+        Some(created_by),
+        WebSocketMessageKind::InsertOne,
+        &record,
+    ) {
+        hb_log::error(
+            None,
+            &format!(
+                "[ApiRestServer] Error when broadcasting insert_one record {} to websocket: {}",
+                record["_id"], err
+            ),
+        );
+    }
+
+    strip_unreadable_fields(&collection_rule_data, std::slice::from_mut(&mut record));
 
     Response::data(&StatusCode::CREATED, &None, &RecordResJson::new(&record))
 }
 
 async fn find_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     path: web::Path<FindOneRecordReqPath>,
     query: web::Query<FindOneRecordReqQuery>,
 ) -> HttpResponse {
@@ -370,8 +546,11 @@ async fn find_one(
             }
         },
         ClaimId::Token(token_id, user_claim) => {
-            match TokenDao::db_select(ctx.dao().db(), token_id).await {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
                 Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                }
                 Err(err) => {
                     return Response::error_raw(
                         &StatusCode::BAD_REQUEST,
@@ -441,14 +620,18 @@ async fn find_one(
         CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
     ) {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if &admin_id != project_data.admin_id() {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
     }
 
     if project_data.id() != collection_data.project_id() {
@@ -521,7 +704,9 @@ async fn find_one(
         );
     };
 
-    let fields = match query.fields() {
+    let collection_rule_data = token_collection_rule(&ctx, &token_data, collection_data.id()).await;
+
+    let mut fields = match query.fields() {
         Some(origin_fields) => {
             let mut fields = HashSet::with_capacity(origin_fields.len());
             for field in origin_fields {
@@ -531,6 +716,8 @@ async fn find_one(
                     || field == "_updated_at"
                 {
                     fields.insert(field.as_str());
+                } else if field == "_created_at" {
+                    fields.insert("_id");
                 } else {
                     return Response::error_raw(
                         &StatusCode::BAD_REQUEST,
@@ -542,6 +729,7 @@ async fn find_one(
         }
         None => HashSet::new(),
     };
+    retain_readable_fields(&collection_rule_data, &mut fields);
 
     let record_data = match RecordDao::db_select(
         ctx.dao().db(),
@@ -554,18 +742,30 @@ async fn find_one(
     .await
     {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    let mut record = HashMap::with_capacity(record_data.len());
-    for (key, value) in record_data.data() {
-        let value = match value.to_serde_json() {
-            Ok(value) => value,
-            Err(err) => {
-                return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
-            }
-        };
-        record.insert(key.to_owned(), value);
+    let mut record = match record_data.to_serde_json() {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+    strip_unreadable_fields(&collection_rule_data, std::slice::from_mut(&mut record));
+
+    if let Some(includes) = query.includes() {
+        if let Err(res) = include_references(
+            &ctx,
+            &token_data,
+            &user_claim,
+            &collection_data,
+            includes,
+            std::slice::from_mut(&mut record),
+        )
+        .await
+        {
+            return res;
+        }
     }
 
     Response::data(&StatusCode::OK, &None, &RecordResJson::new(&record))
@@ -573,7 +773,7 @@ async fn find_one(
 
 async fn update_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     path: web::Path<UpdateOneRecordReqPath>,
     data: web::Json<UpdateOneRecordReqJson>,
 ) -> HttpResponse {
@@ -595,8 +795,11 @@ async fn update_one(
             }
         },
         ClaimId::Token(token_id, user_claim) => {
-            match TokenDao::db_select(ctx.dao().db(), token_id).await {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
                 Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                }
                 Err(err) => {
                     return Response::error_raw(
                         &StatusCode::BAD_REQUEST,
@@ -666,20 +869,26 @@ async fn update_one(
         CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
     ) {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if &admin_id != project_data.admin_id() {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
     }
 
     if project_data.id() != collection_data.project_id() {
         return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
     }
 
+    let collection_rule_data = token_collection_rule(&ctx, &token_data, collection_data.id()).await;
+
     let created_by = if matches!(token_claim.id(), ClaimId::Admin(_)) {
         None
     } else if let Some(rule) = rule_update_one {
@@ -757,6 +966,9 @@ async fn update_one(
                 );
             }
         }
+        if field_name == "_expired_at" && *collection_data.opt_per_record_ttl() {
+            continue;
+        }
         if !collection_data.schema_fields().contains_key(field_name) {
             return Response::error_raw(
                 &StatusCode::BAD_REQUEST,
@@ -765,6 +977,11 @@ async fn update_one(
         }
     }
 
+    if let Err(err) = check_writable_fields(&collection_rule_data, data.keys().map(String::as_str))
+    {
+        return err;
+    }
+
     let mut record_data = match RecordDao::db_select(
         ctx.dao().db(),
         path.record_id(),
@@ -776,7 +993,7 @@ async fn update_one(
     .await
     {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
     if let Some(created_by) = data.get("_created_by") {
@@ -796,8 +1013,30 @@ async fn update_one(
         }
     }
 
+    // A null '_expired_at' makes the record permanent again
+    if let Some(expired_at) = data.get("_expired_at") {
+        record_data.upsert(
+            "_expired_at",
+            &match ColumnValue::from_serde_json(&ColumnKind::Timestamp, expired_at) {
+                Ok(value) => value,
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Error in field '_expired_at': {err}"),
+                    )
+                }
+            },
+        )
+    }
+
     for (field_name, field_props) in collection_data.schema_fields() {
         if let Some(value) = data.get(field_name) {
+            if *field_props.kind() == ColumnKind::Counter {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    &format!("Field '{field_name}' is a counter and can only be changed using the counters endpoint"),
+                );
+            }
             if value.is_null() {
                 if *field_props.required() {
                     return Response::error_raw(
@@ -860,7 +1099,7 @@ async fn update_one(
             if !is_skip {
                 record_data.upsert(
                     field_name,
-                    &match ColumnValue::from_serde_json(field_props.kind(), &value) {
+                    &match field_props.value_from_serde_json(&value) {
                         Ok(value) => value,
                         Err(err) => {
                             return Response::error_raw(
@@ -874,61 +1113,51 @@ async fn update_one(
         }
     }
 
-    if let Err(err) = record_data.db_update(ctx.dao().db()).await {
-        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    if let Err(err) = record_data
+        .db_check_references(ctx.dao().db(), &collection_data)
+        .await
+    {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string());
     }
 
-    let mut record = HashMap::with_capacity(record_data.len());
-    for (key, value) in record_data.data() {
-        let value = match value.to_serde_json() {
-            Ok(value) => value,
-            Err(err) => {
-                return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
-            }
-        };
-        record.insert(key.to_owned(), value);
+    if let Err(err) = record_data.db_update(ctx.dao().db()).await {
+        return Response::error_dao(&StatusCode::INTERNAL_SERVER_ERROR, &err);
     }
 
-    let record_id = path.record_id().clone();
-    let record_pub = record.clone();
-    tokio::spawn((|| async move {
-        let created_by = Uuid::parse_str(record_pub["_created_by"].as_str().unwrap()).unwrap();
+    let mut record = match record_data.to_serde_json() {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
 
-        let record = match serde_json::to_value(&record_pub) {
-            Ok(value) => value,
-            Err(err) => {
-                hb_log::error(
-                    None,
-                    &format!("[ApiRestServer] Error when serializing record {record_id}: {err}"),
-                );
-                return;
-            }
-        };
+    let created_by = Uuid::parse_str(record["_created_by"].as_str().unwrap()).unwrap();
+    if let Err(err) = websocket_broadcast(
+        ctx.websocket().handler(),
+        WebSocketTarget::Collection(*collection_data.id()),
+        Some(created_by),
+        WebSocketMessageKind::UpdateOne,
+        &record,
+    ) {
+        hb_log::error(
+            None,
+            &format!(
+                "[ApiRestServer] Error when broadcasting update_one record {} to websocket: {}",
+                record["_id"], err
+            ),
+        );
+    }
 
-        if let Err(err) = ctx.websocket().handler().broadcast(WebSocketMessage::new(
-            WebSocketTarget::Collection(*collection_data.id()),
-            Some(created_by),
-            WebSocketMessageKind::UpdateOne,
-            record,
-        )) {
-            hb_log::error(
-                None,
-                &format!(
-                    "[ApiRestServer] Error when broadcasting update_one record {} to websocket: {}",
-                    record_pub["_id"], err
-                ),
-            );
-            return;
-        }
-    })());
+    strip_unreadable_fields(&collection_rule_data, std::slice::from_mut(&mut record));
 
     Response::data(&StatusCode::OK, &None, &RecordResJson::new(&record))
 }
 
-async fn delete_one(
+async fn increment_counter(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
-    path: web::Path<DeleteOneRecordReqPath>,
+    auth: ApiAuth,
+    path: web::Path<IncrementCounterRecordReqPath>,
+    data: web::Json<IncrementCounterRecordReqJson>,
 ) -> HttpResponse {
     let token = auth.token();
 
@@ -948,8 +1177,11 @@ async fn delete_one(
             }
         },
         ClaimId::Token(token_id, user_claim) => {
-            match TokenDao::db_select(ctx.dao().db(), token_id).await {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
                 Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                }
                 Err(err) => {
                     return Response::error_raw(
                         &StatusCode::BAD_REQUEST,
@@ -960,20 +1192,20 @@ async fn delete_one(
         }
     };
 
-    let rule_delete_one = if let Some(token_data) = &token_data {
+    let rule_update_one = if let Some(token_data) = &token_data {
         if let Some(rule) = token_data
-            .is_allow_delete_record(ctx.dao().db(), path.collection_id())
+            .is_allow_update_record(ctx.dao().db(), path.collection_id())
             .await
         {
             Some(rule)
         } else {
-            let err_msg = "This token doesn't have permission to delete this record";
+            let err_msg = "This token doesn't have permission to update this record";
             let log_data = LogDao::new(
                 token_data.admin_id(),
                 token_data.project_id(),
                 &LogKind::Error,
                 &format!(
-                    "REST: Failed to delete a record in collection id '{}' using token id '{}': {}",
+                    "REST: Failed to increment a counter of a record in collection id '{}' using token id '{}': {}",
                     path.collection_id(),
                     token_data.id(),
                     err_msg
@@ -1019,23 +1251,29 @@ async fn delete_one(
         CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
     ) {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if &admin_id != project_data.admin_id() {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
     }
 
     if project_data.id() != collection_data.project_id() {
         return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
     }
 
+    let collection_rule_data = token_collection_rule(&ctx, &token_data, collection_data.id()).await;
+
     let created_by = if matches!(token_claim.id(), ClaimId::Admin(_)) {
         None
-    } else if let Some(rule) = rule_delete_one {
+    } else if let Some(rule) = rule_update_one {
         match rule {
             CollectionPermission::All => None,
             CollectionPermission::SelfMade => match user_claim {
@@ -1095,88 +1333,91 @@ async fn delete_one(
     } else {
         return Response::error_raw(
             &StatusCode::BAD_REQUEST,
-            "User doesn't have permission to delete this record",
+            "User doesn't have permission to update this record",
         );
     };
 
-    let mut fields = HashSet::with_capacity(1);
-    fields.insert("_created_by");
+    match collection_data.schema_fields().get(path.field()) {
+        Some(field_props) => {
+            if *field_props.kind() != ColumnKind::Counter {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    &format!("Field '{}' is not a counter", path.field()),
+                );
+            }
+        }
+        None => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &format!("Field '{}' is not exist in the collection", path.field()),
+            )
+        }
+    }
+
+    if let Err(err) = check_writable_fields(&collection_rule_data, [path.field()]) {
+        return err;
+    }
+
+    if let Err(err) = RecordDao::db_increment_counter(
+        ctx.dao().db(),
+        collection_data.id(),
+        path.record_id(),
+        &created_by,
+        path.field(),
+        data.delta(),
+    )
+    .await
+    {
+        return Response::error_dao(&StatusCode::BAD_REQUEST, &err);
+    }
 
     let record_data = match RecordDao::db_select(
         ctx.dao().db(),
         path.record_id(),
         &created_by,
-        &fields,
+        &HashSet::new(),
         &collection_data,
         &token_data.is_none(),
     )
     .await
     {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if let Err(err) = RecordDao::db_delete(
-        ctx.dao().db(),
-        collection_data.id(),
-        path.record_id(),
-        &created_by,
-    )
-    .await
-    {
-        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
-    }
-
-    let record_id = path.record_id().clone();
-    tokio::spawn((|| async move {
-        let created_by = Uuid::parse_str(
-            record_data.data()["_created_by"]
-                .to_serde_json()
-                .unwrap()
-                .as_str()
-                .unwrap(),
-        )
-        .unwrap();
+    let mut record = match record_data.to_serde_json() {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
 
-        let record_id_val = match serde_json::to_value(&record_id) {
-            Ok(value) => value,
-            Err(err) => {
-                hb_log::error(
-                    None,
-                    &format!("[ApiRestServer] Error when serializing record {record_id}: {err}"),
-                );
-                return;
-            }
-        };
+    let created_by = Uuid::parse_str(record["_created_by"].as_str().unwrap()).unwrap();
+    if let Err(err) = websocket_broadcast(
+        ctx.websocket().handler(),
+        WebSocketTarget::Collection(*collection_data.id()),
+        Some(created_by),
+        WebSocketMessageKind::UpdateOne,
+        &record,
+    ) {
+        hb_log::error(
+            None,
+            &format!(
+                "[ApiRestServer] Error when broadcasting increment_counter record {} to websocket: {}",
+                record["_id"], err
+            ),
+        );
+    }
 
-        if let Err(err) = ctx.websocket().handler().broadcast(WebSocketMessage::new(
-            WebSocketTarget::Collection(*collection_data.id()),
-            Some(created_by),
-            WebSocketMessageKind::DeleteOne,
-            record_id_val,
-        )) {
-            hb_log::error(
-                None,
-                &format!(
-                    "[ApiRestServer] Error when broadcasting delete_one record {record_id} to websocket: {err}"
-                ),
-            );
-            return;
-        }
-    })());
+    strip_unreadable_fields(&collection_rule_data, std::slice::from_mut(&mut record));
 
-    Response::data(
-        &StatusCode::OK,
-        &None,
-        &DeleteRecordResJson::new(path.record_id()),
-    )
+    Response::data(&StatusCode::OK, &None, &RecordResJson::new(&record))
 }
 
-async fn find_many(
+async fn delete_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
-    path: web::Path<FindManyRecordReqPath>,
-    query_data: web::Json<FindManyRecordReqJson>,
+    auth: ApiAuth,
+    path: web::Path<DeleteOneRecordReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
 
@@ -1196,8 +1437,11 @@ async fn find_many(
             }
         },
         ClaimId::Token(token_id, user_claim) => {
-            match TokenDao::db_select(ctx.dao().db(), token_id).await {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
                 Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                }
                 Err(err) => {
                     return Response::error_raw(
                         &StatusCode::BAD_REQUEST,
@@ -1208,20 +1452,20 @@ async fn find_many(
         }
     };
 
-    let rule_find_many = if let Some(token_data) = &token_data {
+    let rule_delete_one = if let Some(token_data) = &token_data {
         if let Some(rule) = token_data
-            .is_allow_find_many_records(ctx.dao().db(), path.collection_id())
+            .is_allow_delete_record(ctx.dao().db(), path.collection_id())
             .await
         {
             Some(rule)
         } else {
-            let err_msg = "This token doesn't have permission to read these records";
+            let err_msg = "This token doesn't have permission to delete this record";
             let log_data = LogDao::new(
                 token_data.admin_id(),
                 token_data.project_id(),
                 &LogKind::Error,
                 &format!(
-                    "REST: Failed to read many records in collection id '{}' using token id '{}': {}",
+                    "REST: Failed to delete a record in collection id '{}' using token id '{}': {}",
                     path.collection_id(),
                     token_data.id(),
                     err_msg
@@ -1267,14 +1511,18 @@ async fn find_many(
         CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
     ) {
         Ok(data) => data,
-        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
     };
 
-    if &admin_id != project_data.admin_id() {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
     }
 
     if project_data.id() != collection_data.project_id() {
@@ -1283,7 +1531,7 @@ async fn find_many(
 
     let created_by = if matches!(token_claim.id(), ClaimId::Admin(_)) {
         None
-    } else if let Some(rule) = rule_find_many {
+    } else if let Some(rule) = rule_delete_one {
         match rule {
             CollectionPermission::All => None,
             CollectionPermission::SelfMade => match user_claim {
@@ -1342,125 +1590,1773 @@ async fn find_many(
         }
     } else {
         return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "User doesn't have permission to read these records",
+            &StatusCode::BAD_REQUEST,
+            "User doesn't have permission to delete this record",
         );
     };
 
-    let fields = match query_data.fields() {
-        Some(origin_fields) => {
-            let mut fields = HashSet::with_capacity(origin_fields.len());
-            for field in origin_fields {
-                if collection_data.schema_fields().contains_key(field)
-                    || field == "_id"
-                    || field == "_created_by"
-                    || field == "_updated_at"
-                {
-                    fields.insert(field.as_str());
-                } else if field == "$COUNT" {
-                    fields.insert(field);
-                } else {
-                    return Response::error_raw(
-                        &StatusCode::BAD_REQUEST,
-                        &format!("Field '{field}' is not exist in the collection"),
-                    );
-                }
-            }
-            fields
-        }
-        None => HashSet::new(),
-    };
-    let filters = match query_data.filters() {
-        Some(filter) => match filter.to_dao(&collection_data) {
-            Ok(filter) => filter,
-            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
-        },
-        None => RecordFilters::new(&Vec::new()),
-    };
-    let groups = match query_data.groups() {
-        Some(origin_groups) => {
-            let mut groups = Vec::with_capacity(origin_groups.len());
-            for field in origin_groups {
-                if collection_data.schema_fields().contains_key(field)
-                    || field == "_id"
-                    || field == "_created_by"
-                    || field == "_updated_at"
-                {
-                    groups.push(field.as_str());
-                } else {
-                    return Response::error_raw(
-                        &StatusCode::BAD_REQUEST,
-                        &format!("Field '{field}' is not exist in the collection"),
-                    );
-                }
-            }
-            groups
-        }
-        None => Vec::new(),
-    };
-    let orders = match query_data.orders() {
-        Some(order) => {
-            let mut orders = Vec::with_capacity(order.len());
-            for o in order {
-                if collection_data.schema_fields().contains_key(o.field()) || o.field() == "_id" {
-                    orders.push(RecordOrder::new(o.field(), o.kind()));
-                } else {
-                    return Response::error_raw(
-                        &StatusCode::BAD_REQUEST,
-                        &format!("Field '{}' is not exist in the collection", o.field()),
-                    );
-                }
-            }
-            orders
-        }
-        None => Vec::new(),
-    };
-    let pagination = RecordPagination::new(query_data.limit());
-    let (records_data, total) = match RecordDao::db_select_many(
+    let mut fields = HashSet::with_capacity(1);
+    fields.insert("_created_by");
+
+    let record_data = match RecordDao::db_select(
         ctx.dao().db(),
+        path.record_id(),
+        &created_by,
         &fields,
         &collection_data,
-        &created_by,
-        &filters,
-        &groups,
-        &orders,
-        &pagination,
         &token_data.is_none(),
     )
     .await
     {
         Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) =
+        RecordDao::db_delete_references(ctx.dao().db(), &collection_data, path.record_id()).await
+    {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string());
+    }
+
+    if let Err(err) = RecordDao::db_delete(
+        ctx.dao().db(),
+        &collection_data,
+        path.record_id(),
+        &created_by,
+    )
+    .await
+    {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+
+    let created_by = Uuid::parse_str(
+        record_data.data()["_created_by"]
+            .to_serde_json()
+            .unwrap()
+            .as_str()
+            .unwrap(),
+    )
+    .unwrap();
+    if let Err(err) = websocket_broadcast(
+        ctx.websocket().handler(),
+        WebSocketTarget::Collection(*collection_data.id()),
+        Some(created_by),
+        WebSocketMessageKind::DeleteOne,
+        path.record_id(),
+    ) {
+        hb_log::error(
+            None,
+            &format!(
+                "[ApiRestServer] Error when broadcasting delete_one record {} to websocket: {}",
+                path.record_id(),
+                err
+            ),
+        );
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &DeleteRecordResJson::new(path.record_id()),
+    )
+}
+
+async fn restore_one(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<RestoreOneRecordReqPath>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    let mut records = Vec::with_capacity(records_data.len());
-    for record_data in &records_data {
-        let mut record = HashMap::with_capacity(record_data.len());
-        for (key, value) in record_data.data() {
-            let value = match value.to_serde_json() {
-                Ok(value) => value,
+    let (admin_id, token_data, user_claim) = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => (*data.id(), None, None),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(token_id, user_claim) => {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
+                Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                }
                 Err(err) => {
                     return Response::error_raw(
-                        &StatusCode::INTERNAL_SERVER_ERROR,
-                        &err.to_string(),
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Failed to get token data: {err}"),
                     )
                 }
-            };
-            record.insert(key.to_owned(), value);
-        }
-        records.push(record);
-    }
-
-    let total = match usize::try_from(total) {
-        Ok(data) => data,
-        Err(err) => {
-            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+            }
         }
     };
 
-    Response::data(
-        &StatusCode::OK,
-        &Some(PaginationRes::new(&records_data.len(), &total)),
-        &records,
-    )
+    let rule_restore_one = if let Some(token_data) = &token_data {
+        if let Some(rule) = token_data
+            .is_allow_delete_record(ctx.dao().db(), path.collection_id())
+            .await
+        {
+            Some(rule)
+        } else {
+            let err_msg = "This token doesn't have permission to restore this record";
+            let log_data = LogDao::new(
+                token_data.admin_id(),
+                token_data.project_id(),
+                &LogKind::Error,
+                &format!(
+                    "REST: Failed to restore a record in collection id '{}' using token id '{}': {}",
+                    path.collection_id(),
+                    token_data.id(),
+                    err_msg
+                ),
+            );
+            tokio::spawn((|| async move {
+                match log_data.db_insert(ctx.dao().db()).await {
+                    Ok(_) => {
+                        if let Err(err) = websocket_broadcast(
+                            ctx.websocket().handler(),
+                            WebSocketTarget::Log,
+                            None,
+                            WebSocketMessageKind::InsertOne,
+                            LogResJson::new(
+                                log_data.id(),
+                                log_data.created_at(),
+                                log_data.kind().to_str(),
+                                log_data.message(),
+                            ),
+                        ) {
+                            hb_log::error(
+                                None,
+                                &format!(
+                                    "[ApiRestServer] Error when broadcasting websocket data: {err}"
+                                ),
+                            );
+                        }
+                    }
+                    Err(err) => hb_log::error(
+                        None,
+                        &format!("[ApiRestServer] Error when inserting log data: {err}"),
+                    ),
+                }
+            })());
+            return Response::error_raw(&StatusCode::FORBIDDEN, err_msg);
+        }
+    } else {
+        None
+    };
+
+    let (project_data, collection_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if project_data.id() != collection_data.project_id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    let collection_rule_data = token_collection_rule(&ctx, &token_data, collection_data.id()).await;
+
+    let created_by = if matches!(token_claim.id(), ClaimId::Admin(_)) {
+        None
+    } else if let Some(rule) = rule_restore_one {
+        match rule {
+            CollectionPermission::All => None,
+            CollectionPermission::SelfMade => match user_claim {
+                Some(user_claim) => {
+                    let collection_data =
+                        match CollectionDao::db_select(ctx.dao().db(), user_claim.collection_id())
+                            .await
+                        {
+                            Ok(data) => data,
+                            Err(err) => {
+                                return Response::error_raw(
+                                    &StatusCode::BAD_REQUEST,
+                                    &err.to_string(),
+                                )
+                            }
+                        };
+                    let user_data = match RecordDao::db_select(
+                        ctx.dao().db(),
+                        user_claim.id(),
+                        &None,
+                        &HashSet::from_iter(["_id"]),
+                        &collection_data,
+                        &token_data.is_none(),
+                    )
+                    .await
+                    {
+                        Ok(data) => data,
+                        Err(err) => {
+                            return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string())
+                        }
+                    };
+
+                    if let Some(id) = user_data.id() {
+                        Some(*id)
+                    } else {
+                        return Response::error_raw(&StatusCode::BAD_REQUEST, "User not found");
+                    }
+                }
+                None => {
+                    if let Some(token_data) = &token_data {
+                        Some(*token_data.id())
+                    } else {
+                        return Response::error_raw(
+                            &StatusCode::INTERNAL_SERVER_ERROR,
+                            "Cannot determine created_by",
+                        );
+                    }
+                }
+            },
+            CollectionPermission::None => {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    "User doesn't have permission to read this record",
+                )
+            }
+        }
+    } else {
+        return Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            "User doesn't have permission to restore this record",
+        );
+    };
+
+    if let Err(err) = RecordDao::db_restore(
+        ctx.dao().db(),
+        &collection_data,
+        path.record_id(),
+        &created_by,
+    )
+    .await
+    {
+        return Response::error_dao(&StatusCode::BAD_REQUEST, &err);
+    }
+
+    let record_data = match RecordDao::db_select(
+        ctx.dao().db(),
+        path.record_id(),
+        &None,
+        &HashSet::new(),
+        &collection_data,
+        &token_data.is_none(),
+    )
+    .await
+    {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    let mut record = match record_data.to_serde_json() {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    // Subscribers never saw the record after it was deleted, so it comes back as a new one
+    let created_by = Uuid::parse_str(record["_created_by"].as_str().unwrap()).unwrap();
+    if let Err(err) = websocket_broadcast(
+        ctx.websocket().handler(),
+        WebSocketTarget::Collection(*collection_data.id()),
+        Some(created_by),
+        WebSocketMessageKind::InsertOne,
+        &record,
+    ) {
+        hb_log::error(
+            None,
+            &format!(
+                "[ApiRestServer] Error when broadcasting restore_one record {} to websocket: {}",
+                record["_id"], err
+            ),
+        );
+    }
+
+    strip_unreadable_fields(&collection_rule_data, std::slice::from_mut(&mut record));
+
+    Response::data(&StatusCode::OK, &None, &RecordResJson::new(&record))
+}
+
+async fn find_many(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<FindManyRecordReqPath>,
+    query_data: web::Json<FindManyRecordReqJson>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let (admin_id, token_data, user_claim) = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => (*data.id(), None, None),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(token_id, user_claim) => {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
+                Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                }
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Failed to get token data: {err}"),
+                    )
+                }
+            }
+        }
+    };
+
+    let rule_find_many = if let Some(token_data) = &token_data {
+        if let Some(rule) = token_data
+            .is_allow_find_many_records(ctx.dao().db(), path.collection_id())
+            .await
+        {
+            Some(rule)
+        } else {
+            let err_msg = "This token doesn't have permission to read these records";
+            let log_data = LogDao::new(
+                token_data.admin_id(),
+                token_data.project_id(),
+                &LogKind::Error,
+                &format!(
+                    "REST: Failed to read many records in collection id '{}' using token id '{}': {}",
+                    path.collection_id(),
+                    token_data.id(),
+                    err_msg
+                ),
+            );
+            tokio::spawn((|| async move {
+                match log_data.db_insert(ctx.dao().db()).await {
+                    Ok(_) => {
+                        if let Err(err) = websocket_broadcast(
+                            ctx.websocket().handler(),
+                            WebSocketTarget::Log,
+                            None,
+                            WebSocketMessageKind::InsertOne,
+                            LogResJson::new(
+                                log_data.id(),
+                                log_data.created_at(),
+                                log_data.kind().to_str(),
+                                log_data.message(),
+                            ),
+                        ) {
+                            hb_log::error(
+                                None,
+                                &format!(
+                                    "[ApiRestServer] Error when broadcasting websocket data: {err}"
+                                ),
+                            );
+                        }
+                    }
+                    Err(err) => hb_log::error(
+                        None,
+                        &format!("[ApiRestServer] Error when inserting log data: {err}"),
+                    ),
+                }
+            })());
+            return Response::error_raw(&StatusCode::FORBIDDEN, err_msg);
+        }
+    } else {
+        None
+    };
+
+    let (project_data, collection_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if project_data.id() != collection_data.project_id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    let collection_rule_data = token_collection_rule(&ctx, &token_data, collection_data.id()).await;
+
+    let created_by = if matches!(token_claim.id(), ClaimId::Admin(_)) {
+        None
+    } else if let Some(rule) = rule_find_many {
+        match rule {
+            CollectionPermission::All => None,
+            CollectionPermission::SelfMade => match user_claim {
+                Some(user_claim) => {
+                    let collection_data =
+                        match CollectionDao::db_select(ctx.dao().db(), user_claim.collection_id())
+                            .await
+                        {
+                            Ok(data) => data,
+                            Err(err) => {
+                                return Response::error_raw(
+                                    &StatusCode::BAD_REQUEST,
+                                    &err.to_string(),
+                                )
+                            }
+                        };
+                    let user_data = match RecordDao::db_select(
+                        ctx.dao().db(),
+                        user_claim.id(),
+                        &None,
+                        &HashSet::from_iter(["_id"]),
+                        &collection_data,
+                        &token_data.is_none(),
+                    )
+                    .await
+                    {
+                        Ok(data) => data,
+                        Err(err) => {
+                            return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string())
+                        }
+                    };
+
+                    if let Some(id) = user_data.id() {
+                        Some(*id)
+                    } else {
+                        return Response::error_raw(&StatusCode::BAD_REQUEST, "User not found");
+                    }
+                }
+                None => {
+                    if let Some(token_data) = &token_data {
+                        Some(*token_data.id())
+                    } else {
+                        return Response::error_raw(
+                            &StatusCode::INTERNAL_SERVER_ERROR,
+                            "Cannot determine created_by",
+                        );
+                    }
+                }
+            },
+            CollectionPermission::None => {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    "User doesn't have permission to read this record",
+                )
+            }
+        }
+    } else {
+        return Response::error_raw(
+            &StatusCode::FORBIDDEN,
+            "User doesn't have permission to read these records",
+        );
+    };
+
+    let mut fields = match query_data.fields() {
+        Some(origin_fields) => {
+            let mut fields = HashSet::with_capacity(origin_fields.len());
+            for field in origin_fields {
+                if collection_data.schema_fields().contains_key(field)
+                    || field == "_id"
+                    || field == "_created_by"
+                    || field == "_updated_at"
+                {
+                    fields.insert(field.as_str());
+                } else if field == "_created_at" {
+                    fields.insert("_id");
+                } else if field == "$COUNT" {
+                    fields.insert(field);
+                } else {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Field '{field}' is not exist in the collection"),
+                    );
+                }
+            }
+            fields
+        }
+        None => HashSet::new(),
+    };
+    retain_readable_fields(&collection_rule_data, &mut fields);
+    let filters = match query_data.filters() {
+        Some(filter) => match filter.to_dao(&collection_data) {
+            Ok(filter) => filter,
+            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        },
+        None => RecordFilters::new(&Vec::new()),
+    };
+    let groups = match query_data.groups() {
+        Some(origin_groups) => {
+            let mut groups = Vec::with_capacity(origin_groups.len());
+            for field in origin_groups {
+                if collection_data.schema_fields().contains_key(field)
+                    || field == "_id"
+                    || field == "_created_by"
+                    || field == "_updated_at"
+                {
+                    groups.push(field.as_str());
+                } else {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Field '{field}' is not exist in the collection"),
+                    );
+                }
+            }
+            groups
+        }
+        None => Vec::new(),
+    };
+    let orders = match query_data.orders() {
+        Some(order) => {
+            let mut orders = Vec::with_capacity(order.len());
+            for o in order {
+                if let Some(near) = o.near() {
+                    match collection_data.schema_fields().get(o.field()) {
+                        Some(field_props) if *field_props.kind() == ColumnKind::Point => {
+                            if let Err(err) = ColumnValue::validate_point(*near.lat(), *near.lng())
+                            {
+                                return Response::error_raw(
+                                    &StatusCode::BAD_REQUEST,
+                                    &format!("Error in order field '{}': {}", o.field(), err),
+                                );
+                            }
+                            orders.push(RecordOrder::near(
+                                o.field(),
+                                o.kind(),
+                                near.lat(),
+                                near.lng(),
+                            ));
+                        }
+                        _ => {
+                            return Response::error_raw(
+                                &StatusCode::BAD_REQUEST,
+                                &format!(
+                                "Ordering by distance requires field '{}' to be of type 'point'",
+                                o.field()
+                            ),
+                            )
+                        }
+                    }
+                } else if collection_data.schema_fields().contains_key(o.field())
+                    || o.field() == "_id"
+                {
+                    orders.push(RecordOrder::new(o.field(), o.kind()));
+                } else if o.field() == "_created_at" {
+                    orders.push(RecordOrder::new("_id", o.kind()));
+                } else {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Field '{}' is not exist in the collection", o.field()),
+                    );
+                }
+            }
+            orders
+        }
+        None => Vec::new(),
+    };
+    // Filtering, grouping or ordering by a field would reveal what it holds just as well
+    if let Err(err) = check_readable_fields(
+        &collection_rule_data,
+        filters
+            .fields()
+            .into_iter()
+            .chain(groups.iter().copied())
+            .chain(orders.iter().map(RecordOrder::field)),
+    ) {
+        return err;
+    }
+    let pagination = RecordPagination::new(query_data.limit());
+
+    // Without a limit the result can be of any size, so it's written out as it's read unless it
+    // has to be grouped, counted or joined with its references first
+    if pagination.limit().is_none()
+        && groups.is_empty()
+        && !fields.contains("$COUNT")
+        && query_data.includes().is_none()
+    {
+        let columns = if fields.is_empty() {
+            let mut columns = Vec::with_capacity(collection_data.schema_fields().len() + 3);
+            for column in ["_id", "_created_by", "_updated_at"] {
+                columns.push(column.to_owned());
+            }
+            for column in collection_data.schema_fields().keys() {
+                columns.push(column.to_owned());
+            }
+            columns
+        } else {
+            fields.iter().map(|field| (*field).to_owned()).collect()
+        };
+        let records = stream_records(
+            ctx,
+            columns,
+            collection_data,
+            created_by,
+            filters,
+            orders,
+            pagination,
+            token_data.is_none(),
+            move |idx, record_data| {
+                let mut records = [record_data.to_serde_json()?];
+                strip_unreadable_fields(&collection_rule_data, &mut records);
+                let record = serde_json::to_string(&records[0])?;
+                Ok(if *idx == 0 {
+                    record
+                } else {
+                    format!(",{record}")
+                })
+            },
+            |count| {
+                serde_json::to_string(&PaginationRes::new(count, count))
+                    .ok()
+                    .map(|pagination| format!("],\"pagination\":{pagination}}}"))
+            },
+        );
+
+        return HttpResponse::Ok()
+            .content_type("application/json")
+            .streaming(
+                stream::once(async { Ok(web::Bytes::from_static(b"{\"data\":[")) }).chain(records),
+            );
+    }
+
+    let (records_data, total) = match RecordDao::db_select_many(
+        ctx.dao().db(),
+        &fields,
+        &collection_data,
+        &created_by,
+        &filters,
+        &groups,
+        &orders,
+        &pagination,
+        &token_data.is_none(),
+    )
+    .await
+    {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    let mut records = Vec::with_capacity(records_data.len());
+    for record_data in &records_data {
+        let record = match record_data.to_serde_json() {
+            Ok(data) => data,
+            Err(err) => {
+                return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+            }
+        };
+        records.push(record);
+    }
+    strip_unreadable_fields(&collection_rule_data, &mut records);
+
+    if let Some(includes) = query_data.includes() {
+        if let Err(res) = include_references(
+            &ctx,
+            &token_data,
+            &user_claim,
+            &collection_data,
+            includes,
+            &mut records,
+        )
+        .await
+        {
+            return res;
+        }
+    }
+
+    let total = match usize::try_from(total) {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        }
+    };
+
+    Response::data(
+        &StatusCode::OK,
+        &Some(PaginationRes::new(&records_data.len(), &total)),
+        &records,
+    )
+}
+
+async fn export_many(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<ExportRecordReqPath>,
+    query: web::Query<ExportRecordReqQuery>,
+    query_data: web::Json<ExportRecordReqJson>,
+) -> HttpResponse {
+    let format = match ExportFormat::from_str(query.format().as_deref().unwrap_or("csv")) {
+        Ok(format) => format,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let (admin_id, token_data, user_claim) = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => (*data.id(), None, None),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(token_id, user_claim) => {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
+                Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                }
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Failed to get token data: {err}"),
+                    )
+                }
+            }
+        }
+    };
+
+    let rule_find_many = if let Some(token_data) = &token_data {
+        if let Some(rule) = token_data
+            .is_allow_find_many_records(ctx.dao().db(), path.collection_id())
+            .await
+        {
+            Some(rule)
+        } else {
+            let err_msg = "This token doesn't have permission to read these records";
+            let log_data = LogDao::new(
+                token_data.admin_id(),
+                token_data.project_id(),
+                &LogKind::Error,
+                &format!(
+                    "REST: Failed to export records in collection id '{}' using token id '{}': {}",
+                    path.collection_id(),
+                    token_data.id(),
+                    err_msg
+                ),
+            );
+            tokio::spawn((|| async move {
+                match log_data.db_insert(ctx.dao().db()).await {
+                    Ok(_) => {
+                        if let Err(err) = websocket_broadcast(
+                            ctx.websocket().handler(),
+                            WebSocketTarget::Log,
+                            None,
+                            WebSocketMessageKind::InsertOne,
+                            LogResJson::new(
+                                log_data.id(),
+                                log_data.created_at(),
+                                log_data.kind().to_str(),
+                                log_data.message(),
+                            ),
+                        ) {
+                            hb_log::error(
+                                None,
+                                &format!(
+                                    "[ApiRestServer] Error when broadcasting websocket data: {err}"
+                                ),
+                            );
+                        }
+                    }
+                    Err(err) => hb_log::error(
+                        None,
+                        &format!("[ApiRestServer] Error when inserting log data: {err}"),
+                    ),
+                }
+            })());
+            return Response::error_raw(&StatusCode::FORBIDDEN, err_msg);
+        }
+    } else {
+        None
+    };
+
+    let (project_data, collection_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        CollectionDao::db_select(ctx.dao().db(), path.collection_id()),
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if project_data.id() != collection_data.project_id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    let collection_rule_data = token_collection_rule(&ctx, &token_data, collection_data.id()).await;
+
+    let created_by = if matches!(token_claim.id(), ClaimId::Admin(_)) {
+        None
+    } else if let Some(rule) = rule_find_many {
+        match rule {
+            CollectionPermission::All => None,
+            CollectionPermission::SelfMade => match user_claim {
+                Some(user_claim) => {
+                    let collection_data =
+                        match CollectionDao::db_select(ctx.dao().db(), user_claim.collection_id())
+                            .await
+                        {
+                            Ok(data) => data,
+                            Err(err) => {
+                                return Response::error_raw(
+                                    &StatusCode::BAD_REQUEST,
+                                    &err.to_string(),
+                                )
+                            }
+                        };
+                    let user_data = match RecordDao::db_select(
+                        ctx.dao().db(),
+                        user_claim.id(),
+                        &None,
+                        &HashSet::from_iter(["_id"]),
+                        &collection_data,
+                        &token_data.is_none(),
+                    )
+                    .await
+                    {
+                        Ok(data) => data,
+                        Err(err) => {
+                            return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string())
+                        }
+                    };
+
+                    if let Some(id) = user_data.id() {
+                        Some(*id)
+                    } else {
+                        return Response::error_raw(&StatusCode::BAD_REQUEST, "User not found");
+                    }
+                }
+                None => {
+                    if let Some(token_data) = &token_data {
+                        Some(*token_data.id())
+                    } else {
+                        return Response::error_raw(
+                            &StatusCode::INTERNAL_SERVER_ERROR,
+                            "Cannot determine created_by",
+                        );
+                    }
+                }
+            },
+            CollectionPermission::None => {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    "User doesn't have permission to read this record",
+                )
+            }
+        }
+    } else {
+        return Response::error_raw(
+            &StatusCode::FORBIDDEN,
+            "User doesn't have permission to read these records",
+        );
+    };
+
+    let is_admin = token_data.is_none();
+    let is_readable = |field: &str| {
+        collection_rule_data
+            .as_ref()
+            .map_or(true, |collection_rule_data| {
+                collection_rule_data.is_readable_field(field)
+            })
+    };
+    let columns = match query_data.fields() {
+        Some(fields) => {
+            let mut columns = Vec::with_capacity(fields.len());
+            for field in fields {
+                if field == "_id" || field == "_created_by" || field == "_updated_at" {
+                    columns.push(field.to_owned());
+                } else if let Some(field_props) = collection_data.schema_fields().get(field) {
+                    if (is_admin || !*field_props.hidden()) && is_readable(field) {
+                        columns.push(field.to_owned());
+                    }
+                } else {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Field '{field}' is not exist in the collection"),
+                    );
+                }
+            }
+            columns
+        }
+        None => {
+            let mut fields = collection_data
+                .schema_fields()
+                .iter()
+                .filter(|(field, field_props)| {
+                    (is_admin || !*field_props.hidden()) && is_readable(field)
+                })
+                .map(|(field, _)| field.to_owned())
+                .collect::<Vec<_>>();
+            fields.sort_unstable();
+            let mut columns = Vec::with_capacity(fields.len() + 3);
+            for column in ["_id", "_created_by", "_updated_at"] {
+                columns.push(column.to_owned());
+            }
+            columns.append(&mut fields);
+            columns
+        }
+    };
+    if columns.is_empty() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "There are no fields to export");
+    }
+    let filters = match query_data.filters() {
+        Some(filter) => match filter.to_dao(&collection_data) {
+            Ok(filter) => filter,
+            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        },
+        None => RecordFilters::new(&Vec::new()),
+    };
+    let orders = match query_data.orders() {
+        Some(order) => {
+            let mut orders = Vec::with_capacity(order.len());
+            for o in order {
+                if let Some(near) = o.near() {
+                    match collection_data.schema_fields().get(o.field()) {
+                        Some(field_props) if *field_props.kind() == ColumnKind::Point => {
+                            if let Err(err) = ColumnValue::validate_point(*near.lat(), *near.lng())
+                            {
+                                return Response::error_raw(
+                                    &StatusCode::BAD_REQUEST,
+                                    &format!("Error in order field '{}': {}", o.field(), err),
+                                );
+                            }
+                            orders.push(RecordOrder::near(
+                                o.field(),
+                                o.kind(),
+                                near.lat(),
+                                near.lng(),
+                            ));
+                        }
+                        _ => {
+                            return Response::error_raw(
+                                &StatusCode::BAD_REQUEST,
+                                &format!(
+                                "Ordering by distance requires field '{}' to be of type 'point'",
+                                o.field()
+                            ),
+                            )
+                        }
+                    }
+                } else if collection_data.schema_fields().contains_key(o.field())
+                    || o.field() == "_id"
+                {
+                    orders.push(RecordOrder::new(o.field(), o.kind()));
+                } else {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Field '{}' is not exist in the collection", o.field()),
+                    );
+                }
+            }
+            orders
+        }
+        None => Vec::new(),
+    };
+    // Filtering or ordering by a field would reveal what it holds just as well
+    if let Err(err) = check_readable_fields(
+        &collection_rule_data,
+        filters
+            .fields()
+            .into_iter()
+            .chain(orders.iter().map(RecordOrder::field)),
+    ) {
+        return err;
+    }
+    let pagination = RecordPagination::new(query_data.limit());
+
+    let header = format.header(&columns);
+    let lines = stream_records(
+        ctx,
+        columns.clone(),
+        collection_data,
+        created_by,
+        filters,
+        orders,
+        pagination,
+        is_admin,
+        move |_, record_data| format.line(&columns, record_data),
+        |_| None,
+    );
+
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .streaming(stream::iter(header.map(|header| Ok(web::Bytes::from(header)))).chain(lines))
+}
+
+// Records are read on a separate task and written out as they arrive, so the response never holds
+// more than the channel's worth of records in memory. An error past the first line can only abort
+// the response. actix errors aren't Send, so the task sends its own and they're converted here.
+fn stream_records(
+    ctx: web::Data<ApiRestCtx>,
+    columns: Vec<String>,
+    collection_data: CollectionDao,
+    created_by: Option<Uuid>,
+    filters: RecordFilters,
+    orders: Vec<RecordOrder>,
+    pagination: RecordPagination,
+    is_admin: bool,
+    mut line: impl FnMut(&usize, &RecordDao) -> anyhow::Result<String> + Send + 'static,
+    end: impl FnOnce(&usize) -> Option<String> + Send + 'static,
+) -> impl Stream<Item = Result<web::Bytes, error::Error>> {
+    let (tx, rx) = mpsc::channel::<anyhow::Result<web::Bytes>>(STREAM_CHANNEL_CAPACITY);
+    tokio::spawn((|| async move {
+        let columns = columns.iter().map(|column| column.as_str()).collect();
+        let records = RecordDao::db_select_many_stream(
+            ctx.dao().db(),
+            &columns,
+            &collection_data,
+            &created_by,
+            &filters,
+            &orders,
+            &pagination,
+            &is_admin,
+        );
+        tokio::pin!(records);
+
+        let mut count = 0;
+        while let Some(record_data) = records.next().await {
+            let line = match record_data.and_then(|record_data| line(&count, &record_data)) {
+                Ok(line) => line,
+                Err(err) => {
+                    let _ = tx.send(Err(err)).await;
+                    return;
+                }
+            };
+            if tx.send(Ok(web::Bytes::from(line))).await.is_err() {
+                return;
+            }
+            count += 1;
+        }
+        if let Some(end) = end(&count) {
+            let _ = tx.send(Ok(web::Bytes::from(end))).await;
+        }
+    })());
+
+    stream::unfold(rx, |mut rx| async move {
+        rx.recv()
+            .await
+            .map(|line| (line.map_err(error::ErrorInternalServerError), rx))
+    })
+}
+
+async fn import_many(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<ImportRecordReqPath>,
+    form: MultipartForm<ImportRecordReqForm>,
+) -> HttpResponse {
+    let format = match form.format() {
+        Some(format) => match ImportFormat::from_str(&format) {
+            Ok(format) => format,
+            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        },
+        None => match form
+            .file_name()
+            .as_ref()
+            .and_then(|file_name| ImportFormat::from_file_name(file_name))
+        {
+            Some(format) => format,
+            None => {
+                return Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    "Unknown import format, set the 'format' field to 'csv' or 'jsonl'",
+                )
+            }
+        },
+    };
+
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let (admin_id, token_data, user_claim) = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => (*data.id(), None, None),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(token_id, user_claim) => {
+            match TokenDao::db_select_unexpired(ctx.dao().db(), token_id).await {
+                Ok(data) => (*data.admin_id(), Some(data), *user_claim),
+                Err(err) if DaoError::is_expired(&err) => {
+                    return Response::error_raw(&StatusCode::UNAUTHORIZED, &err.to_string())
+                }
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Failed to get token data: {err}"),
+                    )
+                }
+            }
+        }
+    };
+
+    if let Some(token_data) = &token_data {
+        if !token_data
+            .is_allow_insert_record(ctx.dao().db(), path.collection_id())
+            .await
+        {
+            let err_msg = "This token doesn't have permission to write data to this collection";
+            let log_data = LogDao::new(
+                token_data.admin_id(),
+                token_data.project_id(),
+                &LogKind::Error,
+                &format!(
+                    "REST: Failed to import records using token id '{}': {}",
+                    token_data.id(),
+                    err_msg
+                ),
+            );
+            tokio::spawn((|| async move {
+                match log_data.db_insert(ctx.dao().db()).await {
+                    Ok(_) => {
+                        if let Err(err) = websocket_broadcast(
+                            ctx.websocket().handler(),
+                            WebSocketTarget::Log,
+                            None,
+                            WebSocketMessageKind::InsertOne,
+                            LogResJson::new(
+                                log_data.id(),
+                                log_data.created_at(),
+                                log_data.kind().to_str(),
+                                log_data.message(),
+                            ),
+                        ) {
+                            hb_log::error(
+                                None,
+                                &format!(
+                                    "[ApiRestServer] Error when broadcasting websocket data: {err}"
+                                ),
+                            );
+                        }
+                    }
+                    Err(err) => hb_log::error(
+                        None,
+                        &format!("[ApiRestServer] Error when inserting log data: {err}"),
+                    ),
+                }
+            })());
+            return Response::error_raw(&StatusCode::FORBIDDEN, &err_msg);
+        }
+    }
+
+    let (project_data, collection_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        CollectionDao::db_select(ctx.dao().db(), path.collection_id())
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if project_data.id() != collection_data.project_id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    let collection_rule_data = token_collection_rule(&ctx, &token_data, collection_data.id()).await;
+
+    let content = match tokio::fs::read_to_string(form.file_path()).await {
+        Ok(content) => content,
+        Err(err) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &format!("Failed to read the uploaded file: {err}"),
+            )
+        }
+    };
+
+    let rows = match format.rows(&content, collection_data.schema_fields()) {
+        Ok(rows) => rows,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let is_admin = matches!(token_claim.id(), ClaimId::Admin(_));
+    let created_by = if is_admin {
+        admin_id
+    } else if let Some(user_claim) = user_claim {
+        let collection_data =
+            match CollectionDao::db_select(ctx.dao().db(), user_claim.collection_id()).await {
+                Ok(data) => data,
+                Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+            };
+        let user_data = match RecordDao::db_select(
+            ctx.dao().db(),
+            user_claim.id(),
+            &None,
+            &HashSet::from_iter(["_id"]),
+            &collection_data,
+            &token_data.is_none(),
+        )
+        .await
+        {
+            Ok(data) => data,
+            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+        };
+
+        if let Some(user_id) = user_data.id() {
+            *user_id
+        } else {
+            return Response::error_raw(&StatusCode::BAD_REQUEST, "User doesn't found");
+        }
+    } else if let Some(token_data) = token_data {
+        *token_data.id()
+    } else {
+        return Response::error_raw(
+            &StatusCode::INTERNAL_SERVER_ERROR,
+            "Cannot determine created_by",
+        );
+    };
+
+    let mut errors = Vec::new();
+    let mut lines = Vec::with_capacity(rows.len());
+    let mut records = Vec::with_capacity(rows.len());
+    for (line, row) in rows {
+        let record_data = match row.map_err(|err| err.to_string()).and_then(|row| {
+            import_record(
+                &ctx,
+                &collection_data,
+                &collection_rule_data,
+                &created_by,
+                &is_admin,
+                &row,
+            )
+        }) {
+            Ok(record_data) => record_data,
+            Err(err) => {
+                errors.push(ImportRecordErrorResJson::new(&line, &err));
+                continue;
+            }
+        };
+        if let Err(err) = record_data
+            .db_check_references(ctx.dao().db(), &collection_data)
+            .await
+        {
+            errors.push(ImportRecordErrorResJson::new(&line, &err.to_string()));
+            continue;
+        }
+        lines.push(line);
+        records.push(record_data);
+    }
+
+    // Every imported record has the same columns, so the first one tells how many rows fit in a
+    // single insert
+    let batch_size = match records.first() {
+        Some(record_data) => (*ctx.import_batch_size())
+            .min(RecordDao::insert_many_limit(&record_data.len()))
+            .max(1),
+        None => 1,
+    };
+    let collection_data = Some(collection_data);
+    let mut inserted = vec![false; records.len()];
+    for (batch_idx, batch) in records.chunks(batch_size).enumerate() {
+        let offset = batch_idx * batch_size;
+        if RecordDao::db_insert_many(ctx.dao().db(), batch, &collection_data)
+            .await
+            .is_ok()
+        {
+            inserted[offset..offset + batch.len()].fill(true);
+            continue;
+        }
+        // Retry the failed batch one record at a time to find out which rows are at fault
+        for (idx, record_data) in batch.iter().enumerate() {
+            match record_data
+                .db_insert(ctx.dao().db(), &collection_data)
+                .await
+            {
+                Ok(_) => inserted[offset + idx] = true,
+                Err(err) => errors.push(ImportRecordErrorResJson::new(
+                    &lines[offset + idx],
+                    &err.to_string(),
+                )),
+            }
+        }
+    }
+    errors.sort_by_key(|error| *error.line());
+
+    let imported_records = records
+        .iter()
+        .zip(inserted)
+        .filter_map(|(record_data, inserted)| if inserted { Some(record_data) } else { None })
+        .collect::<Vec<_>>();
+
+    for record_data in imported_records.iter() {
+        let record = match record_data.to_serde_json() {
+            Ok(record) => record,
+            Err(err) => {
+                hb_log::error(
+                    None,
+                    &format!("[ApiRestServer] Error in imported record: {err}"),
+                );
+                continue;
+            }
+        };
+        let created_by = match record_data.get("_created_by") {
+            Some(ColumnValue::Uuid(Some(created_by))) => Some(*created_by),
+            _ => None,
+        };
+
+        if let Err(err) = websocket_broadcast(
+            ctx.websocket().handler(),
+            WebSocketTarget::Collection(*path.collection_id()),
+            created_by,
+            WebSocketMessageKind::InsertOne,
+            &record,
+        ) {
+            hb_log::error(
+                None,
+                &format!(
+                    "[ApiRestServer] Error when broadcasting imported record to websocket: {err}"
+                ),
+            );
+            break;
+        }
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &ImportRecordResJson::new(&imported_records.len(), &errors),
+    )
+}
+
+// Builds a record out of an imported row with the same rules as insert_one, returning the reason
+// the row is rejected instead of a response
+fn import_record(
+    ctx: &ApiRestCtx,
+    collection_data: &CollectionDao,
+    collection_rule_data: &Option<CollectionRuleDao>,
+    created_by: &Uuid,
+    is_admin: &bool,
+    row: &serde_json::Map<String, serde_json::Value>,
+) -> Result<RecordDao, String> {
+    let mut created_by = *created_by;
+    for (field_name, value) in row {
+        match field_name.as_str() {
+            // Ids and timestamps are always generated, so exported files can be imported as is
+            "_id" | "_created_at" | "_updated_at" => continue,
+            "_created_by" => {
+                if value.is_null() {
+                    continue;
+                }
+                if !*is_admin {
+                    return Err(
+                        "Must be logged in using password-based login to insert '_created_by' field"
+                            .to_owned(),
+                    );
+                }
+                created_by = match value.as_str().map(Uuid::parse_str) {
+                    Some(Ok(created_by)) => created_by,
+                    _ => return Err("Invalid '_created_by' field".to_owned()),
+                };
+            }
+            "_expired_at" if *collection_data.opt_per_record_ttl() => continue,
+            _ => {
+                if !collection_data.schema_fields().contains_key(field_name) {
+                    return Err(format!(
+                        "Field '{field_name}' is not exist in the collection"
+                    ));
+                }
+                // Empty cells of an exported file are fine even if the field isn't writable
+                if !value.is_null()
+                    && collection_rule_data
+                        .as_ref()
+                        .is_some_and(|collection_rule_data| {
+                            !collection_rule_data.is_writable_field(field_name)
+                        })
+                {
+                    return Err(format!(
+                        "This token doesn't have permission to write field '{field_name}'"
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut record_data = RecordDao::new(&created_by, collection_data.id(), &row.len());
+    for (field_name, field_props) in collection_data.schema_fields() {
+        if *field_props.kind() == ColumnKind::Counter {
+            if let Some(value) = row.get(field_name) {
+                if !value.is_null() {
+                    return Err(format!("Field '{field_name}' is a counter and can only be changed using the counters endpoint"));
+                }
+            }
+            record_data.upsert(field_name, &ColumnValue::none(field_props.kind()));
+            continue;
+        }
+        if let Some(value) = row.get(field_name) {
+            if !value.is_null() {
+                let mut value = value.clone();
+                if *field_props.hashed() {
+                    let value_str = match value.as_str() {
+                        Some(val) => val,
+                        None => {
+                            return Err(format!(
+                                "Field {field_name} must be of type string because it is hashed"
+                            ))
+                        }
+                    };
+                    value = match ctx.hash().argon2().hash_password(value_str.as_bytes()) {
+                        Ok(val) => serde_json::Value::String(val.to_string()),
+                        Err(err) => {
+                            return Err(format!(
+                                "Failed to hash value of field '{field_name}': {err}"
+                            ))
+                        }
+                    };
+                }
+                record_data.upsert(
+                    field_name,
+                    &match field_props.value_from_serde_json(&value) {
+                        Ok(value) => value,
+                        Err(err) => return Err(format!("Error in field '{field_name}': {err}")),
+                    },
+                );
+                continue;
+            }
+        }
+        if let Some(default) = field_props.default() {
+            record_data.upsert(field_name, default);
+        } else if *field_props.required() {
+            return Err(format!("Value for '{field_name}' is required"));
+        } else {
+            record_data.upsert(field_name, &ColumnValue::none(field_props.kind()));
+        }
+    }
+
+    if *collection_data.opt_per_record_ttl() {
+        record_data.upsert(
+            "_expired_at",
+            &ColumnValue::from_serde_json(
+                &ColumnKind::Timestamp,
+                row.get("_expired_at").unwrap_or(&serde_json::Value::Null),
+            )
+            .map_err(|err| format!("Error in field '_expired_at': {err}"))?,
+        );
+    }
+
+    Ok(record_data)
+}
+
+// Replaces the value of every included reference field with the record it references. The
+// referenced records are loaded with a single query per referenced collection, and references to
+// records that can't be found are replaced with null.
+async fn include_references(
+    ctx: &ApiRestCtx,
+    token_data: &Option<TokenDao>,
+    user_claim: &Option<UserClaim>,
+    collection_data: &CollectionDao,
+    includes: &HashSet<String>,
+    records: &mut [HashMap<String, serde_json::Value>],
+) -> Result<(), HttpResponse> {
+    let mut reference_collections =
+        HashMap::<Uuid, (Option<Uuid>, Option<CollectionRuleDao>, HashSet<Uuid>)>::new();
+    for field in includes {
+        let reference_collection_id = match collection_data.schema_fields().get(field) {
+            Some(field_props) => match field_props.kind().reference_collection_id() {
+                Some(collection_id) => *collection_id,
+                None => {
+                    return Err(Response::error_raw(
+                        &StatusCode::BAD_REQUEST,
+                        &format!("Field '{field}' can't be included because it is not a reference"),
+                    ))
+                }
+            },
+            None => {
+                return Err(Response::error_raw(
+                    &StatusCode::BAD_REQUEST,
+                    &format!("Field '{field}' is not exist in the collection"),
+                ))
+            }
+        };
+
+        if !reference_collections.contains_key(&reference_collection_id) {
+            let collection_rule_data =
+                token_collection_rule(ctx, token_data, &reference_collection_id).await;
+            let created_by = if let Some(token_data) = token_data {
+                match collection_rule_data
+                    .as_ref()
+                    .map(|collection_rule_data| *collection_rule_data.find_many())
+                {
+                    Some(CollectionPermission::All) => None,
+                    Some(CollectionPermission::SelfMade) => match user_claim {
+                        Some(user_claim) => Some(*user_claim.id()),
+                        None => Some(*token_data.id()),
+                    },
+                    Some(CollectionPermission::None) | None => {
+                        return Err(Response::error_raw(
+                            &StatusCode::FORBIDDEN,
+                            &format!("This token doesn't have permission to read the records referenced by field '{field}'"),
+                        ))
+                    }
+                }
+            } else {
+                None
+            };
+            reference_collections.insert(
+                reference_collection_id,
+                (created_by, collection_rule_data, HashSet::new()),
+            );
+        }
+
+        let (_, _, ids) = reference_collections
+            .get_mut(&reference_collection_id)
+            .unwrap();
+        for record in records.iter() {
+            if let Some(id) = record
+                .get(field)
+                .and_then(|value| value.as_str())
+                .and_then(|value| Uuid::parse_str(value).ok())
+            {
+                ids.insert(id);
+            }
+        }
+    }
+
+    let mut references = HashMap::new();
+    for (reference_collection_id, (created_by, collection_rule_data, ids)) in &reference_collections
+    {
+        if ids.is_empty() {
+            continue;
+        }
+        let reference_collection_data =
+            match CollectionDao::db_select(ctx.dao().db(), reference_collection_id).await {
+                Ok(data) => data,
+                Err(err) => {
+                    return Err(Response::error_raw(
+                        &StatusCode::INTERNAL_SERVER_ERROR,
+                        &err.to_string(),
+                    ))
+                }
+            };
+        let filter = match RecordFilter::any_of(
+            "_id",
+            &ids.iter().map(|id| ColumnValue::Uuid(Some(*id))).collect(),
+        ) {
+            Ok(filter) => filter,
+            Err(err) => {
+                return Err(Response::error_raw(
+                    &StatusCode::INTERNAL_SERVER_ERROR,
+                    &err.to_string(),
+                ))
+            }
+        };
+        let (reference_records_data, _) = match RecordDao::db_select_many(
+            ctx.dao().db(),
+            &HashSet::new(),
+            &reference_collection_data,
+            created_by,
+            &RecordFilters::new(&vec![filter]),
+            &Vec::new(),
+            &Vec::new(),
+            &RecordPagination::new(&None),
+            &token_data.is_none(),
+        )
+        .await
+        {
+            Ok(data) => data,
+            Err(err) => {
+                return Err(Response::error_raw(
+                    &StatusCode::INTERNAL_SERVER_ERROR,
+                    &err.to_string(),
+                ))
+            }
+        };
+        for reference_record_data in &reference_records_data {
+            let id = match reference_record_data.id() {
+                Some(id) => *id,
+                None => continue,
+            };
+            let mut reference_record = match reference_record_data.to_serde_json() {
+                Ok(reference_record) => reference_record,
+                Err(err) => {
+                    return Err(Response::error_raw(
+                        &StatusCode::INTERNAL_SERVER_ERROR,
+                        &err.to_string(),
+                    ))
+                }
+            };
+            strip_unreadable_fields(
+                collection_rule_data,
+                std::slice::from_mut(&mut reference_record),
+            );
+            references.insert(
+                (*reference_collection_id, id),
+                serde_json::Value::Object(reference_record.into_iter().collect()),
+            );
+        }
+    }
+
+    for field in includes {
+        let reference_collection_id = match collection_data
+            .schema_fields()
+            .get(field)
+            .and_then(|field_props| field_props.kind().reference_collection_id())
+        {
+            Some(collection_id) => *collection_id,
+            None => continue,
+        };
+        for record in records.iter_mut() {
+            if let Some(value) = record.get_mut(field) {
+                let id = value.as_str().and_then(|value| Uuid::parse_str(value).ok());
+                *value = match id {
+                    Some(id) => match references.get(&(reference_collection_id, id)) {
+                        Some(reference) => reference.clone(),
+                        None => serde_json::Value::Null,
+                    },
+                    None => serde_json::Value::Null,
+                };
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// The collection rule of the token for the collection, none for admins and tokens without one
+async fn token_collection_rule(
+    ctx: &ApiRestCtx,
+    token_data: &Option<TokenDao>,
+    collection_id: &Uuid,
+) -> Option<CollectionRuleDao> {
+    match token_data {
+        Some(token_data) => {
+            token_data
+                .collection_rule(ctx.dao().db(), collection_id)
+                .await
+        }
+        None => None,
+    }
+}
+
+// Requested fields the token can't read are left out. If none of them is left, only '_id' is
+// selected since an empty selection means every field.
+fn retain_readable_fields(
+    collection_rule_data: &Option<CollectionRuleDao>,
+    fields: &mut HashSet<&str>,
+) {
+    if let Some(collection_rule_data) = collection_rule_data {
+        if fields.is_empty() {
+            return;
+        }
+        fields.retain(|field| {
+            field.starts_with('$') || collection_rule_data.is_readable_field(field)
+        });
+        if fields.is_empty() {
+            fields.insert("_id");
+        }
+    }
+}
+
+// Aggregates like '$COUNT' aren't fields, so they're kept
+fn strip_unreadable_fields(
+    collection_rule_data: &Option<CollectionRuleDao>,
+    records: &mut [HashMap<String, serde_json::Value>],
+) {
+    if let Some(collection_rule_data) = collection_rule_data {
+        for record in records {
+            record.retain(|field, _| {
+                field.starts_with('$') || collection_rule_data.is_readable_field(field)
+            });
+        }
+    }
+}
+
+fn check_readable_fields<'a>(
+    collection_rule_data: &Option<CollectionRuleDao>,
+    fields: impl IntoIterator<Item = &'a str>,
+) -> Result<(), HttpResponse> {
+    if let Some(collection_rule_data) = collection_rule_data {
+        for field in fields {
+            if !collection_rule_data.is_readable_field(field) {
+                return Err(Response::error_raw(
+                    &StatusCode::FORBIDDEN,
+                    &format!("This token doesn't have permission to read field '{field}'"),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_writable_fields<'a>(
+    collection_rule_data: &Option<CollectionRuleDao>,
+    fields: impl IntoIterator<Item = &'a str>,
+) -> Result<(), HttpResponse> {
+    if let Some(collection_rule_data) = collection_rule_data {
+        for field in fields {
+            if !collection_rule_data.is_writable_field(field) {
+                return Err(Response::error_raw(
+                    &StatusCode::FORBIDDEN,
+                    &format!("This token doesn't have permission to write field '{field}'"),
+                ));
+            }
+        }
+    }
+    Ok(())
 }
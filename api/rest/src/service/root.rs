@@ -1,11 +1,12 @@
 use actix_web::{http::StatusCode, web, HttpResponse};
 
-use crate::model::Response;
+use crate::{model::Response, service::health::health_probe_api};
 
 pub fn root_api(cfg: &mut web::ServiceConfig) {
     cfg.route("/", web::get().to(root))
         .route("/api", web::get().to(root))
-        .route("/api/rest", web::get().to(root));
+        .route("/api/rest", web::get().to(root))
+        .configure(health_probe_api);
 }
 
 async fn root() -> HttpResponse {
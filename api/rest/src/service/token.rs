@@ -1,19 +1,31 @@
-use actix_web::{http::StatusCode, web, HttpResponse};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
 use chrono::{Duration, Utc};
-use hb_dao::{admin::AdminDao, project::ProjectDao, token::TokenDao};
+use hb_api_websocket::message::MessageKind;
+use hb_dao::{
+    admin::AdminDao,
+    audit::{AuditAction, AuditEntity},
+    project::ProjectDao,
+    token::TokenDao,
+};
 use hb_token_jwt::claim::ClaimId;
+use serde_json::{json, Value};
 
 use crate::{
     context::ApiRestCtx,
     model::{
         token::{
-            DeleteOneTokenReqPath, DeleteTokenResJson, FindManyTokenReqPath, FindOneTokenReqPath,
-            InsertOneTokenReqJson, InsertOneTokenReqPath, TokenResJson, UpdateOneTokenReqJson,
-            UpdateOneTokenReqPath,
+            DeleteOneTokenReqPath, DeleteTokenResJson, FindManyTokenReqPath, FindManyTokenReqQuery,
+            FindOneTokenReqPath, InsertOneTokenReqJson, InsertOneTokenReqPath,
+            RegenerateOneTokenReqPath, TokenResJson, UpdateOneTokenReqJson, UpdateOneTokenReqPath,
         },
         PaginationRes, Response,
     },
+    util::{
+        api_auth::ApiAuth,
+        audit::{audit, audit_state},
+        project_access::{authorize_project, ProjectAccess},
+        ws_broadcast::{websocket_broadcast_project, websocket_invalidate_token},
+    },
 };
 
 pub fn token_api(cfg: &mut web::ServiceConfig) {
@@ -26,6 +38,10 @@ pub fn token_api(cfg: &mut web::ServiceConfig) {
             "/project/{project_id}/token/{token_id}",
             web::patch().to(update_one),
         )
+        .route(
+            "/project/{project_id}/token/{token_id}/regenerate",
+            web::post().to(regenerate_one),
+        )
         .route(
             "/project/{project_id}/token/{token_id}",
             web::delete().to(delete_one),
@@ -35,7 +51,8 @@ pub fn token_api(cfg: &mut web::ServiceConfig) {
 
 async fn insert_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<InsertOneTokenReqPath>,
     data: web::Json<InsertOneTokenReqJson>,
 ) -> HttpResponse {
@@ -69,11 +86,15 @@ async fn insert_one(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
     }
 
     if let Some(expired_at) = data.expired_at() {
@@ -96,6 +117,24 @@ async fn insert_one(
     if let Err(err) = token_data.db_insert(ctx.dao().db()).await {
         return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
     }
+    websocket_broadcast_project(
+        ctx.websocket().handler(),
+        token_data.project_id(),
+        MessageKind::InsertOne,
+        "token",
+        token_data.id(),
+    );
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        project_data.id(),
+        &AuditAction::Create,
+        &AuditEntity::Token,
+        token_data.id(),
+        &None,
+        &token_state(&token_data),
+    );
 
     Response::data(
         &StatusCode::CREATED,
@@ -106,16 +145,18 @@ async fn insert_one(
             token_data.updated_at(),
             token_data.project_id(),
             token_data.name(),
-            token_data.token(),
+            token_data.plain_token(),
             token_data.allow_anonymous(),
             token_data.expired_at(),
+            token_data.last_used_at(),
+            token_data.request_count(),
         ),
     )
 }
 
 async fn find_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     path: web::Path<FindOneTokenReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -148,11 +189,15 @@ async fn find_one(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
     }
 
     let token_data = match TokenDao::db_select(ctx.dao().db(), path.token_id()).await {
@@ -173,16 +218,19 @@ async fn find_one(
             token_data.updated_at(),
             token_data.project_id(),
             token_data.name(),
-            token_data.token(),
+            token_data.plain_token(),
             token_data.allow_anonymous(),
             token_data.expired_at(),
+            token_data.last_used_at(),
+            token_data.request_count(),
         ),
     )
 }
 
 async fn update_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<UpdateOneTokenReqPath>,
     data: web::Json<UpdateOneTokenReqJson>,
 ) -> HttpResponse {
@@ -216,11 +264,15 @@ async fn update_one(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
     }
 
     let mut token_data = match TokenDao::db_select(ctx.dao().db(), path.token_id()).await {
@@ -236,6 +288,7 @@ async fn update_one(
     if token_data.admin_id() != &admin_id {
         return Response::error_raw(&StatusCode::FORBIDDEN, "This token does not belong to you");
     }
+    let before = token_state(&token_data);
 
     if let Some(name) = data.name() {
         token_data.set_name(name);
@@ -262,8 +315,130 @@ async fn update_one(
         if let Err(err) = token_data.db_update(ctx.dao().db()).await {
             return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
         }
+        websocket_invalidate_token(ctx.websocket().handler(), token_data.id());
+        websocket_broadcast_project(
+            ctx.websocket().handler(),
+            token_data.project_id(),
+            MessageKind::UpdateOne,
+            "token",
+            token_data.id(),
+        );
+        audit(
+            &ctx,
+            &req,
+            &admin_id,
+            project_data.id(),
+            &AuditAction::Update,
+            &AuditEntity::Token,
+            token_data.id(),
+            &before,
+            &token_state(&token_data),
+        );
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &TokenResJson::new(
+            token_data.id(),
+            token_data.created_at(),
+            token_data.updated_at(),
+            token_data.project_id(),
+            token_data.name(),
+            token_data.plain_token(),
+            token_data.allow_anonymous(),
+            token_data.expired_at(),
+            token_data.last_used_at(),
+            token_data.request_count(),
+        ),
+    )
+}
+
+async fn regenerate_one(
+    ctx: web::Data<ApiRestCtx>,
+    req: HttpRequest,
+    auth: ApiAuth,
+    path: web::Path<RegenerateOneTokenReqPath>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let project_data = match ProjectDao::db_select(ctx.dao().db(), path.project_id()).await {
+        Ok(data) => data,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
     }
 
+    let mut token_data = match TokenDao::db_select(ctx.dao().db(), path.token_id()).await {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                &format!("Failed to get token data: {err}"),
+            )
+        }
+    };
+
+    if token_data.admin_id() != &admin_id {
+        return Response::error_raw(&StatusCode::FORBIDDEN, "This token does not belong to you");
+    }
+
+    token_data.regenerate_token(ctx.access_token_length());
+    if let Err(err) = token_data.db_update(ctx.dao().db()).await {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+    websocket_broadcast_project(
+        ctx.websocket().handler(),
+        token_data.project_id(),
+        MessageKind::UpdateOne,
+        "token",
+        token_data.id(),
+    );
+    // The token itself is never stored in the audit log, only that it was regenerated
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        project_data.id(),
+        &AuditAction::Update,
+        &AuditEntity::Token,
+        token_data.id(),
+        &None,
+        &Some(json!({ "token_regenerated": true })),
+    );
+
     Response::data(
         &StatusCode::OK,
         &None,
@@ -273,16 +448,19 @@ async fn update_one(
             token_data.updated_at(),
             token_data.project_id(),
             token_data.name(),
-            token_data.token(),
+            token_data.plain_token(),
             token_data.allow_anonymous(),
             token_data.expired_at(),
+            token_data.last_used_at(),
+            token_data.request_count(),
         ),
     )
 }
 
 async fn delete_one(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    req: HttpRequest,
+    auth: ApiAuth,
     path: web::Path<DeleteOneTokenReqPath>,
 ) -> HttpResponse {
     let token = auth.token();
@@ -315,11 +493,15 @@ async fn delete_one(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
     }
 
     let token_data = match TokenDao::db_select(ctx.dao().db(), path.token_id()).await {
@@ -334,6 +516,25 @@ async fn delete_one(
     if let Err(err) = TokenDao::db_delete(ctx.dao().db(), path.token_id()).await {
         return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
     }
+    websocket_invalidate_token(ctx.websocket().handler(), path.token_id());
+    websocket_broadcast_project(
+        ctx.websocket().handler(),
+        token_data.project_id(),
+        MessageKind::DeleteOne,
+        "token",
+        token_data.id(),
+    );
+    audit(
+        &ctx,
+        &req,
+        &admin_id,
+        project_data.id(),
+        &AuditAction::Delete,
+        &AuditEntity::Token,
+        token_data.id(),
+        &token_state(&token_data),
+        &None,
+    );
 
     Response::data(
         &StatusCode::OK,
@@ -344,8 +545,9 @@ async fn delete_one(
 
 async fn find_many(
     ctx: web::Data<ApiRestCtx>,
-    auth: BearerAuth,
+    auth: ApiAuth,
     path: web::Path<FindManyTokenReqPath>,
+    query: web::Query<FindManyTokenReqQuery>,
 ) -> HttpResponse {
     let token = auth.token();
 
@@ -377,11 +579,15 @@ async fn find_many(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
-    if project_data.admin_id() != &admin_id {
-        return Response::error_raw(
-            &StatusCode::FORBIDDEN,
-            "This project does not belong to you",
-        );
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Manage,
+    )
+    .await
+    {
+        return err;
     }
 
     let tokens_data = match TokenDao::db_select_many_by_admin_id_and_project_id(
@@ -395,6 +601,20 @@ async fn find_many(
         Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
     };
 
+    let tokens_data: Vec<_> = match query.unused_for_days() {
+        Some(unused_for_days) => {
+            let used_since = Utc::now() - Duration::days((*unused_for_days).into());
+            tokens_data
+                .into_iter()
+                .filter(|token_data| match token_data.last_used_at() {
+                    Some(last_used_at) => last_used_at < &used_since,
+                    None => true,
+                })
+                .collect()
+        }
+        None => tokens_data,
+    };
+
     let mut tokens_res = Vec::with_capacity(tokens_data.len());
     for token_data in &tokens_data {
         tokens_res.push(TokenResJson::new(
@@ -403,9 +623,11 @@ async fn find_many(
             token_data.updated_at(),
             token_data.project_id(),
             token_data.name(),
-            token_data.token(),
+            token_data.plain_token(),
             token_data.allow_anonymous(),
             token_data.expired_at(),
+            token_data.last_used_at(),
+            token_data.request_count(),
         ));
     }
 
@@ -415,3 +637,19 @@ async fn find_many(
         &tokens_res,
     )
 }
+
+// The token as the API returns it, which is what the audit log compares
+fn token_state(token_data: &TokenDao) -> Option<Value> {
+    audit_state(&TokenResJson::new(
+        token_data.id(),
+        token_data.created_at(),
+        token_data.updated_at(),
+        token_data.project_id(),
+        token_data.name(),
+        token_data.plain_token(),
+        token_data.allow_anonymous(),
+        token_data.expired_at(),
+        token_data.last_used_at(),
+        token_data.request_count(),
+    ))
+}
@@ -1,16 +1,15 @@
 use actix_web::{http::StatusCode, web, HttpResponse};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
-use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
+use ahash::{HashSet, HashSetExt};
 use hb_dao::{collection::CollectionDao, record::RecordDao};
 use hb_token_jwt::claim::ClaimId;
 
-use crate::{context::ApiRestCtx, model::Response};
+use crate::{context::ApiRestCtx, model::Response, util::api_auth::ApiAuth};
 
 pub fn user_api(cfg: &mut web::ServiceConfig) {
     cfg.route("/user", web::get().to(find_one));
 }
 
-async fn find_one(ctx: web::Data<ApiRestCtx>, auth: BearerAuth) -> HttpResponse {
+async fn find_one(ctx: web::Data<ApiRestCtx>, auth: ApiAuth) -> HttpResponse {
     let token = auth.token();
 
     let token_claim = match ctx.token().jwt().decode(token) {
@@ -52,19 +51,15 @@ async fn find_one(ctx: web::Data<ApiRestCtx>, auth: BearerAuth) -> HttpResponse
                 Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
             };
 
-            let mut user = HashMap::with_capacity(user_data.len());
-            for (key, value) in user_data.data() {
-                let value = match value.to_serde_json() {
-                    Ok(value) => value,
-                    Err(err) => {
-                        return Response::error_raw(
-                            &StatusCode::INTERNAL_SERVER_ERROR,
-                            &err.to_string(),
-                        )
-                    }
-                };
-                user.insert(key.to_owned(), value);
-            }
+            let user = match user_data.to_serde_json() {
+                Ok(data) => data,
+                Err(err) => {
+                    return Response::error_raw(
+                        &StatusCode::INTERNAL_SERVER_ERROR,
+                        &err.to_string(),
+                    )
+                }
+            };
 
             Response::data(&StatusCode::OK, &None, &user)
         }
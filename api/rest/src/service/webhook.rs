@@ -0,0 +1,472 @@
+use actix_web::{http::StatusCode, web, HttpResponse};
+use hb_dao::{
+    admin::AdminDao,
+    collection::CollectionDao,
+    project::ProjectDao,
+    webhook::{WebhookDao, WebhookEvent},
+};
+use hb_token_jwt::claim::ClaimId;
+
+use crate::{
+    context::ApiRestCtx,
+    model::{
+        webhook::{
+            DeleteOneWebhookReqPath, DeleteWebhookResJson, FindManyWebhookReqPath,
+            FindOneWebhookReqPath, InsertOneWebhookReqJson, InsertOneWebhookReqPath,
+            UpdateOneWebhookReqJson, UpdateOneWebhookReqPath, WebhookResJson,
+        },
+        PaginationRes, Response,
+    },
+    util::{
+        api_auth::ApiAuth,
+        project_access::{authorize_project, ProjectAccess},
+    },
+};
+
+pub fn webhook_api(cfg: &mut web::ServiceConfig) {
+    cfg.route("/project/{project_id}/webhook", web::post().to(insert_one))
+        .route(
+            "/project/{project_id}/webhook/{webhook_id}",
+            web::get().to(find_one),
+        )
+        .route(
+            "/project/{project_id}/webhook/{webhook_id}",
+            web::patch().to(update_one),
+        )
+        .route(
+            "/project/{project_id}/webhook/{webhook_id}",
+            web::delete().to(delete_one),
+        )
+        .route("/project/{project_id}/webhooks", web::get().to(find_many));
+}
+
+async fn insert_one(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<InsertOneWebhookReqPath>,
+    data: web::Json<InsertOneWebhookReqJson>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let (project_data, collection_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        CollectionDao::db_select(ctx.dao().db(), data.collection_id())
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if collection_data.project_id() != project_data.id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    if let Err(err) = check_webhook_url(data.url()) {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, &err);
+    }
+
+    let events = match webhook_events_from_str(data.events()) {
+        Ok(events) => events,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    let webhook_data = WebhookDao::new(
+        &admin_id,
+        project_data.id(),
+        collection_data.id(),
+        data.url(),
+        &events,
+        data.secret(),
+        &data.enabled().unwrap_or(true),
+    );
+
+    if let Err(err) = webhook_data.db_insert(ctx.dao().db()).await {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+
+    Response::data(
+        &StatusCode::CREATED,
+        &None,
+        &WebhookResJson::new(
+            webhook_data.id(),
+            webhook_data.created_at(),
+            webhook_data.updated_at(),
+            webhook_data.project_id(),
+            webhook_data.collection_id(),
+            webhook_data.url(),
+            &webhook_events_to_str(webhook_data.events()),
+            webhook_data.secret(),
+            webhook_data.enabled(),
+        ),
+    )
+}
+
+async fn find_one(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<FindOneWebhookReqPath>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let (project_data, webhook_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        WebhookDao::db_select(ctx.dao().db(), path.webhook_id()),
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if webhook_data.project_id() != project_data.id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &WebhookResJson::new(
+            webhook_data.id(),
+            webhook_data.created_at(),
+            webhook_data.updated_at(),
+            webhook_data.project_id(),
+            webhook_data.collection_id(),
+            webhook_data.url(),
+            &webhook_events_to_str(webhook_data.events()),
+            webhook_data.secret(),
+            webhook_data.enabled(),
+        ),
+    )
+}
+
+async fn update_one(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<UpdateOneWebhookReqPath>,
+    data: web::Json<UpdateOneWebhookReqJson>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let (project_data, mut webhook_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        WebhookDao::db_select(ctx.dao().db(), path.webhook_id()),
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if webhook_data.project_id() != project_data.id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    if let Some(url) = data.url() {
+        if let Err(err) = check_webhook_url(url) {
+            return Response::error_raw(&StatusCode::BAD_REQUEST, &err);
+        }
+        webhook_data.set_url(url);
+    }
+
+    if let Some(events) = data.events() {
+        let events = match webhook_events_from_str(events) {
+            Ok(events) => events,
+            Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err),
+        };
+        webhook_data.set_events(&events);
+    }
+
+    if let Some(secret) = data.secret() {
+        webhook_data.set_secret(secret);
+    }
+
+    if let Some(enabled) = data.enabled() {
+        webhook_data.set_enabled(enabled);
+    }
+
+    if !data.is_all_none() {
+        if let Err(err) = webhook_data.db_update(ctx.dao().db()).await {
+            return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+        }
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &WebhookResJson::new(
+            webhook_data.id(),
+            webhook_data.created_at(),
+            webhook_data.updated_at(),
+            webhook_data.project_id(),
+            webhook_data.collection_id(),
+            webhook_data.url(),
+            &webhook_events_to_str(webhook_data.events()),
+            webhook_data.secret(),
+            webhook_data.enabled(),
+        ),
+    )
+}
+
+async fn delete_one(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<DeleteOneWebhookReqPath>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let (project_data, webhook_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        WebhookDao::db_select(ctx.dao().db(), path.webhook_id()),
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Write,
+    )
+    .await
+    {
+        return err;
+    }
+
+    if webhook_data.project_id() != project_data.id() {
+        return Response::error_raw(&StatusCode::BAD_REQUEST, "Project id does not match");
+    }
+
+    if let Err(err) = WebhookDao::db_delete(ctx.dao().db(), webhook_data.id()).await {
+        return Response::error_raw(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &None,
+        &DeleteWebhookResJson::new(webhook_data.id()),
+    )
+}
+
+async fn find_many(
+    ctx: web::Data<ApiRestCtx>,
+    auth: ApiAuth,
+    path: web::Path<FindManyWebhookReqPath>,
+) -> HttpResponse {
+    let token = auth.token();
+
+    let token_claim = match ctx.token().jwt().decode(token) {
+        Ok(token) => token,
+        Err(err) => return Response::error_raw(&StatusCode::BAD_REQUEST, &err.to_string()),
+    };
+
+    let admin_id = match token_claim.id() {
+        ClaimId::Admin(id) => match AdminDao::db_select(ctx.dao().db(), id).await {
+            Ok(data) => *data.id(),
+            Err(err) => {
+                return Response::error_raw(
+                    &StatusCode::UNAUTHORIZED,
+                    &format!("Failed to get admin data: {err}"),
+                )
+            }
+        },
+        ClaimId::Token(_, _) => {
+            return Response::error_raw(
+                &StatusCode::BAD_REQUEST,
+                "Must be logged in using password-based login",
+            )
+        }
+    };
+
+    let (project_data, webhooks_data) = match tokio::try_join!(
+        ProjectDao::db_select(ctx.dao().db(), path.project_id()),
+        WebhookDao::db_select_many_by_project_id(ctx.dao().db(), path.project_id()),
+    ) {
+        Ok(data) => data,
+        Err(err) => return Response::error_dao(&StatusCode::BAD_REQUEST, &err),
+    };
+
+    if let Err(err) = authorize_project(
+        ctx.dao().db(),
+        &admin_id,
+        &project_data,
+        &ProjectAccess::Read,
+    )
+    .await
+    {
+        return err;
+    }
+
+    Response::data(
+        &StatusCode::OK,
+        &Some(PaginationRes::new(
+            &webhooks_data.len(),
+            &webhooks_data.len(),
+        )),
+        &webhooks_data
+            .iter()
+            .map(|data| {
+                WebhookResJson::new(
+                    data.id(),
+                    data.created_at(),
+                    data.updated_at(),
+                    data.project_id(),
+                    data.collection_id(),
+                    data.url(),
+                    &webhook_events_to_str(data.events()),
+                    data.secret(),
+                    data.enabled(),
+                )
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn check_webhook_url(url: &str) -> Result<(), String> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err("Webhook url must start with 'http://' or 'https://'".to_owned())
+    }
+}
+
+fn webhook_events_from_str(events: &Vec<String>) -> Result<Vec<WebhookEvent>, String> {
+    if events.is_empty() {
+        return Err("Webhook must listen to at least one event".to_owned());
+    }
+    let mut webhook_events = Vec::with_capacity(events.len());
+    for event in events {
+        let event = WebhookEvent::from_str(event).map_err(|err| err.to_string())?;
+        if !webhook_events.contains(&event) {
+            webhook_events.push(event);
+        }
+    }
+    Ok(webhook_events)
+}
+
+fn webhook_events_to_str(events: &Vec<WebhookEvent>) -> Vec<String> {
+    events
+        .iter()
+        .map(|event| event.to_str().to_owned())
+        .collect()
+}
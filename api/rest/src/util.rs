@@ -1 +1,14 @@
+pub mod api_auth;
+pub mod archive;
+pub mod audit;
+pub mod client_ip;
+pub mod export;
+pub mod import;
+pub mod password_policy;
+pub mod project_access;
+pub mod project_export;
+pub mod read_only;
+pub mod schema;
+pub mod tls;
 pub mod ws_broadcast;
+pub mod ws_origin;
@@ -0,0 +1,117 @@
+use actix_web::{
+    dev::Payload, error::InternalError, http::StatusCode, web, FromRequest, HttpRequest,
+};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use futures::future::LocalBoxFuture;
+use hb_dao::{error::DaoError, token::TokenDao};
+use hb_token_jwt::claim::ClaimId;
+
+use crate::{context::ApiRestCtx, model::Response};
+
+const API_KEY_HEADER: &str = "X-API-Key";
+const API_KEY_BEARER_PREFIX: &str = "hb_";
+
+// Bearer authentication that also accepts the token of a project as a long-lived API key, either
+// in the X-API-Key header or as a bearer token prefixed with "hb_". The key is exchanged for the
+// same JWT an anonymous token-based login gives, so handlers don't need to tell them apart.
+pub struct ApiAuth {
+    token: String,
+}
+
+impl ApiAuth {
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+impl FromRequest for ApiAuth {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            let api_key = match req.headers().get(API_KEY_HEADER) {
+                Some(api_key) => match api_key.to_str() {
+                    Ok(api_key) => api_key.to_owned(),
+                    Err(_) => {
+                        return Err(error(
+                            &StatusCode::UNAUTHORIZED,
+                            "API key header is not a valid string",
+                        ))
+                    }
+                },
+                None => {
+                    let bearer = BearerAuth::extract(&req).await?;
+                    match bearer.token().strip_prefix(API_KEY_BEARER_PREFIX) {
+                        Some(api_key) => api_key.to_owned(),
+                        None => {
+                            // Requests made with a JWT of a token count towards its usage too. An
+                            // invalid JWT is left for the handler to reject.
+                            if let Some(ctx) = req.app_data::<web::Data<ApiRestCtx>>() {
+                                if let Ok(claim) = ctx.token().jwt().decode(bearer.token()) {
+                                    if let ClaimId::Token(token_id, _) = claim.id() {
+                                        ctx.dao().token_usage().record(token_id);
+                                    }
+                                }
+                            }
+                            return Ok(Self {
+                                token: bearer.token().to_owned(),
+                            });
+                        }
+                    }
+                }
+            };
+
+            let ctx = match req.app_data::<web::Data<ApiRestCtx>>() {
+                Some(ctx) => ctx,
+                None => {
+                    return Err(error(
+                        &StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to get server context",
+                    ))
+                }
+            };
+
+            // Looked up on every request so a deleted or regenerated token is revoked right away
+            let mut token_data = match TokenDao::db_select_by_token(ctx.dao().db(), &api_key).await
+            {
+                Ok(data) => data,
+                Err(_) => return Err(error(&StatusCode::UNAUTHORIZED, "Invalid API key")),
+            };
+
+            if let Err(err) = token_data.check_expired(ctx.dao().db()).await {
+                if DaoError::is_expired(&err) {
+                    return Err(error(&StatusCode::UNAUTHORIZED, "API key has expired"));
+                }
+                return Err(error(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()));
+            }
+
+            if !token_data.allow_anonymous() {
+                return Err(error(
+                    &StatusCode::FORBIDDEN,
+                    "Token is set to prevent anonymous login",
+                ));
+            }
+
+            ctx.dao().token_usage().record(token_data.id());
+
+            match ctx
+                .token()
+                .jwt()
+                .encode(&ClaimId::Token(*token_data.id(), None))
+            {
+                Ok(token) => Ok(Self { token }),
+                Err(err) => Err(error(&StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())),
+            }
+        })
+    }
+}
+
+fn error(status_code: &StatusCode, message: &str) -> actix_web::Error {
+    InternalError::from_response(
+        message.to_owned(),
+        Response::error_raw(status_code, message),
+    )
+    .into()
+}
@@ -0,0 +1,159 @@
+use std::{io::SeekFrom, path::Path};
+
+use anyhow::{Error, Result};
+use tokio::{
+    fs,
+    io::{self, AsyncReadExt, AsyncSeekExt},
+};
+
+// Archives are plain ustar: every entry is a 512-byte header followed by its content padded to a
+// whole block, and the archive ends with two zeroed blocks
+const BLOCK_SIZE: u64 = 512;
+const NAME_LENGTH: usize = 100;
+
+pub struct ArchiveEntry {
+    path: String,
+    offset: u64,
+    size: u64,
+}
+
+impl ArchiveEntry {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn size(&self) -> &u64 {
+        &self.size
+    }
+}
+
+// A whole entry as it is written to the archive, header and padding included
+pub fn entry(path: &str, content: &[u8]) -> Result<Vec<u8>> {
+    let size = u64::try_from(content.len())?;
+    let entry_size = usize::try_from(BLOCK_SIZE + padded_size(&size))?;
+    let mut entry = Vec::with_capacity(entry_size);
+    entry.extend_from_slice(&header(path, &size)?);
+    entry.extend_from_slice(content);
+    entry.resize(entry_size, 0);
+    Ok(entry)
+}
+
+pub fn end() -> Vec<u8> {
+    vec![0; 2 * 512]
+}
+
+fn header(path: &str, size: &u64) -> Result<[u8; 512]> {
+    if path.len() > NAME_LENGTH {
+        return Err(Error::msg(format!(
+            "Archive entry path '{path}' is longer than {NAME_LENGTH} bytes"
+        )));
+    }
+
+    let mut header = [0; 512];
+    header[..path.len()].copy_from_slice(path.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[108..116].copy_from_slice(b"0000000\0");
+    header[116..124].copy_from_slice(b"0000000\0");
+    header[124..136].copy_from_slice(format!("{size:011o}\0").as_bytes());
+    header[136..148].copy_from_slice(b"00000000000\0");
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    let sum = checksum(&header);
+    header[148..156].copy_from_slice(format!("{sum:06o}\0 ").as_bytes());
+    Ok(header)
+}
+
+// The checksum is taken with its own field filled with spaces
+fn checksum(header: &[u8; 512]) -> u32 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(idx, byte)| {
+            if (148..156).contains(&idx) {
+                u32::from(b' ')
+            } else {
+                u32::from(*byte)
+            }
+        })
+        .sum()
+}
+
+fn padded_size(size: &u64) -> u64 {
+    size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE
+}
+
+// Lists the regular files of an archive without reading their content. Directories and links
+// are skipped, and a header with a wrong checksum fails the whole archive.
+pub async fn read_entries(file: &mut fs::File) -> Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    file.seek(SeekFrom::Start(0)).await?;
+    loop {
+        let mut header = [0; 512];
+        match file.read_exact(&mut header).await {
+            Ok(_) => (),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof && header == [0; 512] => break,
+            Err(err) => return Err(err.into()),
+        }
+        if header == [0; 512] {
+            break;
+        }
+        offset += BLOCK_SIZE;
+
+        if u64::from(checksum(&header)) != octal(&header[148..156])? {
+            return Err(Error::msg(format!(
+                "Archive header at byte {} has a wrong checksum",
+                offset - BLOCK_SIZE
+            )));
+        }
+        let path = String::from_utf8(
+            header[..NAME_LENGTH]
+                .iter()
+                .take_while(|byte| **byte != 0)
+                .copied()
+                .collect(),
+        )?;
+        let size = octal(&header[124..136])?;
+        if header[156] == b'0' || header[156] == 0 {
+            entries.push(ArchiveEntry { path, offset, size });
+        }
+
+        offset += padded_size(&size);
+        file.seek(SeekFrom::Start(offset)).await?;
+    }
+    Ok(entries)
+}
+
+pub async fn read_entry(file: &mut fs::File, entry: &ArchiveEntry) -> Result<Vec<u8>> {
+    let mut content = vec![0; usize::try_from(entry.size)?];
+    file.seek(SeekFrom::Start(entry.offset)).await?;
+    file.read_exact(&mut content).await?;
+    Ok(content)
+}
+
+// Copies the content of an entry into its own file, so it can be saved like an upload
+pub async fn extract_entry(
+    file: &mut fs::File,
+    entry: &ArchiveEntry,
+    target_path: impl AsRef<Path>,
+) -> Result<()> {
+    file.seek(SeekFrom::Start(entry.offset)).await?;
+    let mut target_file = fs::File::create(target_path).await?;
+    let copied = io::copy(&mut (&mut *file).take(entry.size), &mut target_file).await?;
+    if copied != entry.size {
+        return Err(Error::msg(format!(
+            "Archive entry '{}' is truncated",
+            entry.path
+        )));
+    }
+    Ok(())
+}
+
+fn octal(field: &[u8]) -> Result<u64> {
+    let field = std::str::from_utf8(field)?.trim_matches(|c: char| c == '\0' || c == ' ');
+    if field.is_empty() {
+        return Ok(0);
+    }
+    Ok(u64::from_str_radix(field, 8)?)
+}
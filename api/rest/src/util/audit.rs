@@ -0,0 +1,83 @@
+use actix_web::{web, HttpRequest};
+use hb_dao::audit::{AuditAction, AuditDao, AuditEntity};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::{context::ApiRestCtx, util::client_ip::client_ip};
+
+// Fields that change on their own rather than by an admin, and the token secret which must never
+// be stored
+const IGNORED_FIELDS: [&str; 6] = [
+    "updated_at",
+    "token",
+    "days_remaining",
+    "last_used_at",
+    "request_count",
+    "used_bytes",
+];
+
+// Records the change on a separate task so the request doesn't wait for the write. The states
+// are the entity as the API returns it before and after the change, None when it didn't exist.
+pub fn audit(
+    ctx: &web::Data<ApiRestCtx>,
+    req: &HttpRequest,
+    admin_id: &Uuid,
+    project_id: &Uuid,
+    action: &AuditAction,
+    entity_type: &AuditEntity,
+    entity_id: &Uuid,
+    before: &Option<Value>,
+    after: &Option<Value>,
+) {
+    let audit_data = AuditDao::new(
+        admin_id,
+        project_id,
+        action,
+        entity_type,
+        entity_id,
+        &diff(before, after),
+        &client_ip(req).map(|ip| ip.to_string()),
+    );
+    let ctx = ctx.clone();
+    tokio::spawn((|| async move {
+        if let Err(err) = audit_data.db_insert(ctx.dao().db()).await {
+            hb_log::error(
+                None,
+                &format!("[ApiRestServer] Error when inserting audit data: {err}"),
+            );
+        }
+    })());
+}
+
+pub fn audit_state(value: &impl Serialize) -> Option<Value> {
+    serde_json::to_value(value).ok()
+}
+
+// Keeps only the fields whose value changed, as {"field": {"before": ..., "after": ...}}
+fn diff(before: &Option<Value>, after: &Option<Value>) -> Value {
+    let empty = Map::new();
+    let before = match before {
+        Some(Value::Object(before)) => before,
+        _ => &empty,
+    };
+    let after = match after {
+        Some(Value::Object(after)) => after,
+        _ => &empty,
+    };
+
+    let mut diff = Map::new();
+    for field in before.keys().chain(after.keys()) {
+        if IGNORED_FIELDS.contains(&field.as_str()) || diff.contains_key(field) {
+            continue;
+        }
+        let (before_value, after_value) = (before.get(field), after.get(field));
+        if before_value != after_value {
+            diff.insert(
+                field.to_owned(),
+                json!({ "before": before_value, "after": after_value }),
+            );
+        }
+    }
+    Value::Object(diff)
+}
@@ -0,0 +1,270 @@
+use std::net::{IpAddr, SocketAddr};
+
+use actix_web::{http::header, web, HttpRequest};
+use anyhow::{Error, Result};
+
+pub struct TrustedProxies(Vec<IpCidr>);
+
+impl TrustedProxies {
+    pub fn new(cidrs: &Vec<String>) -> Result<Self> {
+        let mut trusted_proxies = Vec::with_capacity(cidrs.len());
+        for cidr in cidrs {
+            trusted_proxies.push(IpCidr::parse(cidr)?);
+        }
+        Ok(Self(trusted_proxies))
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.iter().any(|cidr| cidr.contains(ip))
+    }
+}
+
+struct IpCidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpCidr {
+    fn parse(cidr: &str) -> Result<Self> {
+        let (addr, prefix) = match cidr.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (cidr, None),
+        };
+        let addr = addr
+            .trim()
+            .parse::<IpAddr>()
+            .map_err(|_| Error::msg(format!("Trusted proxy '{cidr}' is not a valid address")))?;
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => match prefix.trim().parse::<u8>() {
+                Ok(prefix) if prefix <= max_prefix => prefix,
+                _ => {
+                    return Err(Error::msg(format!(
+                        "Trusted proxy '{cidr}' has an invalid prefix length"
+                    )))
+                }
+            },
+            None => max_prefix,
+        };
+        Ok(Self { addr, prefix })
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        // IPv4-mapped IPv6 peers (dual-stack sockets) are compared as IPv4
+        let ip = match ip {
+            IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+                Some(ip) => IpAddr::V4(ip),
+                None => IpAddr::V6(*ip),
+            },
+            IpAddr::V4(ip) => IpAddr::V4(*ip),
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+// Resolves the address of the client that made the request. Forwarding headers are only taken
+// into account when the direct peer is a trusted proxy, and the chain is walked from the nearest
+// hop backwards so entries prepended by the client itself can't be used to spoof the address.
+pub fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    let peer_ip = req.peer_addr()?.ip();
+
+    let trusted_proxies = match req.app_data::<web::Data<TrustedProxies>>() {
+        Some(trusted_proxies) => trusted_proxies,
+        None => return Some(peer_ip),
+    };
+
+    let mut ip = peer_ip;
+    if !trusted_proxies.contains(&ip) {
+        return Some(ip);
+    }
+
+    for hop in forwarded_chain(req).iter().rev() {
+        match hop {
+            Some(hop) => {
+                ip = *hop;
+                if !trusted_proxies.contains(&ip) {
+                    break;
+                }
+            }
+            // Anything before an obfuscated or malformed hop can't be verified
+            None => break,
+        }
+    }
+
+    Some(ip)
+}
+
+// Addresses listed in the Forwarded header (RFC 7239), falling back to X-Forwarded-For, ordered
+// from the original client to the nearest proxy
+fn forwarded_chain(req: &HttpRequest) -> Vec<Option<IpAddr>> {
+    let forwarded = req
+        .headers()
+        .get_all(header::FORWARDED)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|element| {
+            element.split(';').find_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                if key.trim().eq_ignore_ascii_case("for") {
+                    Some(parse_node(value.trim().trim_matches('"')))
+                } else {
+                    None
+                }
+            })
+        })
+        .map(|node| node.flatten())
+        .collect::<Vec<_>>();
+    if !forwarded.is_empty() {
+        return forwarded;
+    }
+
+    req.headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|node| parse_node(node.trim()))
+        .collect()
+}
+
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Ok(ip) = node.parse::<IpAddr>() {
+        return Some(ip);
+    }
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    node.strip_prefix('[')
+        .and_then(|node| node.strip_suffix(']'))
+        .and_then(|node| node.parse::<IpAddr>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn trusted_proxies(cidrs: &[&str]) -> TrustedProxies {
+        TrustedProxies::new(&cidrs.iter().map(|cidr| (*cidr).to_owned()).collect()).unwrap()
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    fn resolve(peer: &str, headers: &[(&str, &str)]) -> Option<IpAddr> {
+        let mut req = TestRequest::default()
+            .peer_addr(SocketAddr::new(ip(peer), 443))
+            .app_data(web::Data::new(trusted_proxies(&["10.0.0.0/8", "fd00::/8"])));
+        for header in headers {
+            req = req.append_header(*header);
+        }
+        client_ip(&req.to_http_request())
+    }
+
+    #[test]
+    fn cidr_contains() {
+        let proxies = trusted_proxies(&["10.0.0.0/8", "192.168.1.1", "fd00::/8"]);
+        assert!(proxies.contains(&ip("10.1.2.3")));
+        assert!(!proxies.contains(&ip("11.0.0.1")));
+        assert!(proxies.contains(&ip("192.168.1.1")));
+        assert!(!proxies.contains(&ip("192.168.1.2")));
+        assert!(proxies.contains(&ip("fd12::1")));
+        assert!(!proxies.contains(&ip("fe80::1")));
+        assert!(proxies.contains(&ip("::ffff:10.0.0.1")));
+        assert!(trusted_proxies(&["0.0.0.0/0"]).contains(&ip("203.0.113.9")));
+    }
+
+    #[test]
+    fn invalid_cidrs_are_rejected() {
+        for cidr in ["10.0.0.0/33", "fd00::/129", "10.0.0", "10.0.0.0/x", "proxy"] {
+            assert!(
+                TrustedProxies::new(&vec![cidr.to_owned()]).is_err(),
+                "{cidr}"
+            );
+        }
+    }
+
+    #[test]
+    fn untrusted_peer_cannot_spoof() {
+        let headers = [("x-forwarded-for", "1.2.3.4"), ("forwarded", "for=1.2.3.4")];
+        for header in headers {
+            assert_eq!(resolve("203.0.113.9", &[header]), Some(ip("203.0.113.9")));
+        }
+    }
+
+    #[test]
+    fn trusted_proxy_is_followed() {
+        assert_eq!(
+            resolve("10.0.0.1", &[("x-forwarded-for", "203.0.113.9")]),
+            Some(ip("203.0.113.9"))
+        );
+        assert_eq!(
+            resolve("10.0.0.1", &[("forwarded", "for=\"[2001:db8::1]:4711\"")]),
+            Some(ip("2001:db8::1"))
+        );
+        assert_eq!(resolve("10.0.0.1", &[]), Some(ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn prepended_hops_cannot_spoof() {
+        // The client sent "1.2.3.4" itself, the trusted proxies appended the real address after it
+        assert_eq!(
+            resolve(
+                "10.0.0.1",
+                &[("x-forwarded-for", "1.2.3.4, 203.0.113.9, 10.0.0.2")]
+            ),
+            Some(ip("203.0.113.9"))
+        );
+        assert_eq!(
+            resolve(
+                "10.0.0.1",
+                &[("forwarded", "for=1.2.3.4, for=203.0.113.9;proto=https")]
+            ),
+            Some(ip("203.0.113.9"))
+        );
+    }
+
+    #[test]
+    fn unverifiable_hop_stops_the_walk() {
+        assert_eq!(
+            resolve("10.0.0.1", &[("forwarded", "for=1.2.3.4, for=_hidden")]),
+            Some(ip("10.0.0.1"))
+        );
+        assert_eq!(
+            resolve(
+                "10.0.0.1",
+                &[("x-forwarded-for", "1.2.3.4, garbage, 10.0.0.2")]
+            ),
+            Some(ip("10.0.0.2"))
+        );
+    }
+
+    #[test]
+    fn forwarded_takes_precedence() {
+        assert_eq!(
+            resolve(
+                "10.0.0.1",
+                &[
+                    ("forwarded", "for=203.0.113.9"),
+                    ("x-forwarded-for", "1.2.3.4")
+                ]
+            ),
+            Some(ip("203.0.113.9"))
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::{Error, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hb_dao::{record::RecordDao, value::ColumnValue};
+
+#[derive(Clone, Copy)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+}
+
+impl ExportFormat {
+    pub fn from_str(str: &str) -> Result<Self> {
+        match str {
+            "csv" => Ok(Self::Csv),
+            "jsonl" => Ok(Self::Jsonl),
+            _ => Err(Error::msg(format!("Unknown export format '{str}'"))),
+        }
+    }
+
+    pub fn content_type(&self) -> &str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Jsonl => "application/x-ndjson",
+        }
+    }
+
+    pub fn header(&self, columns: &Vec<String>) -> Option<String> {
+        match self {
+            Self::Csv => Some(csv_line(columns.iter().map(|column| column.to_owned()))),
+            Self::Jsonl => None,
+        }
+    }
+
+    pub fn line(&self, columns: &Vec<String>, record: &RecordDao) -> Result<String> {
+        match self {
+            Self::Csv => {
+                let mut cells = Vec::with_capacity(columns.len());
+                for column in columns {
+                    cells.push(match record.get(column) {
+                        Some(value) => csv_cell(value)?,
+                        None => String::new(),
+                    });
+                }
+                Ok(csv_line(cells.into_iter()))
+            }
+            Self::Jsonl => {
+                let mut object = serde_json::Map::with_capacity(columns.len());
+                for column in columns {
+                    if let Some(value) = record.get(column) {
+                        object.insert(column.to_owned(), value.to_serde_json()?);
+                    }
+                }
+                Ok(serde_json::Value::Object(object).to_string() + "\n")
+            }
+        }
+    }
+}
+
+// Binary values are written as base64 and json values as their serialized text, anything else
+// that isn't a scalar ends up as JSON
+fn csv_cell(value: &ColumnValue) -> Result<String> {
+    Ok(match value {
+        ColumnValue::Binary(data) => match data {
+            Some(data) => STANDARD.encode(data),
+            None => String::new(),
+        },
+        ColumnValue::Json(data) => data.to_owned().unwrap_or_default(),
+        _ => match value.to_serde_json()? {
+            serde_json::Value::Null => String::new(),
+            serde_json::Value::String(data) => data,
+            data => data.to_string(),
+        },
+    })
+}
+
+fn csv_line(cells: impl Iterator<Item = String>) -> String {
+    let mut line = cells
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
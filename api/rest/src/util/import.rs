@@ -0,0 +1,186 @@
+use std::mem;
+
+use ahash::{HashMap, HashSet, HashSetExt};
+use anyhow::{Error, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hb_dao::{collection::SchemaFieldProps, value::ColumnKind};
+
+pub type ImportRow = (usize, Result<serde_json::Map<String, serde_json::Value>>);
+
+#[derive(Clone, Copy)]
+pub enum ImportFormat {
+    Csv,
+    Jsonl,
+}
+
+impl ImportFormat {
+    pub fn from_str(str: &str) -> Result<Self> {
+        match str {
+            "csv" => Ok(Self::Csv),
+            "jsonl" => Ok(Self::Jsonl),
+            _ => Err(Error::msg(format!("Unknown import format '{str}'"))),
+        }
+    }
+
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        match file_name.rsplit_once('.') {
+            Some((_, extension)) => match extension.to_lowercase().as_str() {
+                "csv" => Some(Self::Csv),
+                "jsonl" | "ndjson" => Some(Self::Jsonl),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
+    // Splits the content into rows along with the line each row starts at. A malformed CSV header
+    // fails the whole import, anything else only fails the row it is in.
+    pub fn rows(
+        &self,
+        content: &str,
+        schema_fields: &HashMap<String, SchemaFieldProps>,
+    ) -> Result<Vec<ImportRow>> {
+        match self {
+            Self::Csv => {
+                let mut records = csv_records(content).into_iter();
+                let header = match records.next() {
+                    Some((_, header)) => header?,
+                    None => return Err(Error::msg("The file doesn't have a header row")),
+                };
+                let mut header_set = HashSet::with_capacity(header.len());
+                for column in &header {
+                    if !header_set.insert(column) {
+                        return Err(Error::msg(format!(
+                            "Column '{column}' appears more than once in the header row"
+                        )));
+                    }
+                }
+
+                let mut rows = Vec::with_capacity(records.len());
+                for (line, cells) in records {
+                    rows.push((line, csv_row(&header, cells, schema_fields)));
+                }
+                Ok(rows)
+            }
+            Self::Jsonl => {
+                let mut rows = Vec::new();
+                for (idx, line) in content.lines().enumerate() {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let row = match serde_json::from_str::<serde_json::Value>(line) {
+                        Ok(serde_json::Value::Object(row)) => Ok(row),
+                        Ok(_) => Err(Error::msg("Line is not a JSON object")),
+                        Err(err) => Err(err.into()),
+                    };
+                    rows.push((idx + 1, row));
+                }
+                Ok(rows)
+            }
+        }
+    }
+}
+
+fn csv_row(
+    header: &Vec<String>,
+    cells: Result<Vec<String>>,
+    schema_fields: &HashMap<String, SchemaFieldProps>,
+) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let cells = cells?;
+    if cells.len() != header.len() {
+        return Err(Error::msg(format!(
+            "Expected {} cells but found {}",
+            header.len(),
+            cells.len()
+        )));
+    }
+    let mut row = serde_json::Map::with_capacity(cells.len());
+    for (column, cell) in header.iter().zip(cells) {
+        let kind = schema_fields.get(column).map(|props| props.kind());
+        match csv_value(kind, cell) {
+            Ok(value) => row.insert(column.to_owned(), value),
+            Err(err) => {
+                return Err(Error::msg(format!("Error in field '{column}': {err}")));
+            }
+        };
+    }
+    Ok(row)
+}
+
+// Reverses the cell encoding used by the CSV export: empty cells are null, binary values are
+// base64, and scalars that aren't strings are JSON
+fn csv_value(kind: Option<&ColumnKind>, cell: String) -> Result<serde_json::Value> {
+    if cell.is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+    match kind {
+        Some(ColumnKind::Binary) => Ok(serde_json::json!(STANDARD.decode(&cell)?)),
+        Some(
+            ColumnKind::Boolean
+            | ColumnKind::TinyInt
+            | ColumnKind::SmallInt
+            | ColumnKind::Int
+            | ColumnKind::BigInt
+            | ColumnKind::Float
+            | ColumnKind::Double
+            | ColumnKind::StringArray
+            | ColumnKind::IntArray
+            | ColumnKind::Counter
+            | ColumnKind::Point,
+        ) => Ok(serde_json::from_str(&cell)?),
+        _ => Ok(serde_json::Value::String(cell)),
+    }
+}
+
+// Parses RFC 4180 records, each paired with the line it starts at since quoted cells may span
+// several lines. Blank lines are skipped.
+fn csv_records(content: &str) -> Vec<(usize, Result<Vec<String>>)> {
+    let mut records = Vec::new();
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+    while let Some(char) = chars.next() {
+        if in_quotes {
+            match char {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    cell.push(char);
+                }
+                _ => cell.push(char),
+            }
+            continue;
+        }
+        match char {
+            '"' if cell.is_empty() => in_quotes = true,
+            ',' => cells.push(mem::take(&mut cell)),
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' => {
+                if cells.is_empty() && cell.is_empty() {
+                    line += 1;
+                    record_line = line;
+                    continue;
+                }
+                cells.push(mem::take(&mut cell));
+                records.push((record_line, Ok(mem::take(&mut cells))));
+                line += 1;
+                record_line = line;
+            }
+            _ => cell.push(char),
+        }
+    }
+    if in_quotes {
+        records.push((record_line, Err(Error::msg("Quoted cell is never closed"))));
+    } else if !cells.is_empty() || !cell.is_empty() {
+        cells.push(cell);
+        records.push((record_line, Ok(cells)));
+    }
+    records
+}
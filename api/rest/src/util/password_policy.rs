@@ -0,0 +1,42 @@
+use actix_web::{http::StatusCode, HttpResponse};
+
+use crate::{context::PasswordPolicy, model::Response};
+
+// Rejects a new password that doesn't meet the configured policy, listing everything it lacks
+pub fn check_password_strength(
+    policy: &PasswordPolicy,
+    password: &str,
+) -> Result<(), HttpResponse> {
+    let mut requirements = Vec::new();
+    if password.chars().count() < *policy.min_length() {
+        requirements.push(format!(
+            "be at least {} characters long",
+            policy.min_length()
+        ));
+    }
+    if *policy.require_lowercase() && !password.chars().any(|c| c.is_lowercase()) {
+        requirements.push("contain a lowercase letter".to_owned());
+    }
+    if *policy.require_uppercase() && !password.chars().any(|c| c.is_uppercase()) {
+        requirements.push("contain an uppercase letter".to_owned());
+    }
+    if *policy.require_digit() && !password.chars().any(|c| c.is_ascii_digit()) {
+        requirements.push("contain a digit".to_owned());
+    }
+    if *policy.require_symbol()
+        && !password
+            .chars()
+            .any(|c| !c.is_alphanumeric() && !c.is_whitespace())
+    {
+        requirements.push("contain a symbol".to_owned());
+    }
+
+    if requirements.is_empty() {
+        Ok(())
+    } else {
+        Err(Response::error_raw(
+            &StatusCode::BAD_REQUEST,
+            &format!("Password must {}", requirements.join(", ")),
+        ))
+    }
+}
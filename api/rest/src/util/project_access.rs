@@ -0,0 +1,80 @@
+use actix_web::{http::StatusCode, HttpResponse};
+use hb_dao::{
+    admin_project_member::{AdminProjectMemberDao, AdminProjectRole},
+    error::DaoError,
+    project::ProjectDao,
+    Db,
+};
+use uuid::Uuid;
+
+use crate::model::Response;
+
+// What a handler is about to do with a project, from the least to the most privileged
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum ProjectAccess {
+    // Find endpoints
+    Read,
+    // Changing collections, records, buckets, files, webhooks and the project itself
+    Write,
+    // Deleting, transferring or duplicating the project, tokens and their rules, and members
+    Manage,
+}
+
+impl ProjectAccess {
+    fn is_allowed_for(&self, role: &AdminProjectRole) -> bool {
+        match role {
+            AdminProjectRole::Owner => true,
+            AdminProjectRole::Editor => *self <= Self::Write,
+            AdminProjectRole::Viewer => *self <= Self::Read,
+        }
+    }
+}
+
+// Returns the role the admin has in the project, the admin the project belongs to is always an
+// owner even without a member row
+pub async fn project_role(
+    db: &Db,
+    admin_id: &Uuid,
+    project_data: &ProjectDao,
+) -> Result<AdminProjectRole, HttpResponse> {
+    if project_data.admin_id() == admin_id {
+        return Ok(AdminProjectRole::Owner);
+    }
+
+    match AdminProjectMemberDao::db_select_by_project_id_and_admin_id(
+        db,
+        project_data.id(),
+        admin_id,
+    )
+    .await
+    {
+        Ok(member_data) => Ok(*member_data.role()),
+        Err(err) if DaoError::is_not_found(&err) => Err(Response::error_raw(
+            &StatusCode::FORBIDDEN,
+            "This project does not belong to you",
+        )),
+        Err(err) => Err(Response::error_raw(
+            &StatusCode::INTERNAL_SERVER_ERROR,
+            &err.to_string(),
+        )),
+    }
+}
+
+pub async fn authorize_project(
+    db: &Db,
+    admin_id: &Uuid,
+    project_data: &ProjectDao,
+    access: &ProjectAccess,
+) -> Result<(), HttpResponse> {
+    let role = project_role(db, admin_id, project_data).await?;
+    if !access.is_allowed_for(&role) {
+        return Err(Response::error_raw(
+            &StatusCode::FORBIDDEN,
+            &format!(
+                "The '{}' role in this project isn't allowed to do this",
+                role.to_str()
+            ),
+        ));
+    }
+    Ok(())
+}
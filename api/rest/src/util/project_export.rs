@@ -0,0 +1,251 @@
+use std::path::PathBuf;
+
+use anyhow::{Error, Result};
+use futures::StreamExt;
+use hb_dao::{
+    bucket::BucketDao,
+    bucket_rule::BucketRuleDao,
+    collection::{CollectionDao, CollectionOrder},
+    collection_rule::CollectionRuleDao,
+    file::{FileDao, FileFilter, FileOrder},
+    project::ProjectDao,
+    record::{RecordDao, RecordFilters, RecordPagination},
+    token::TokenDao,
+    Db,
+};
+use tokio::{fs, sync::mpsc};
+use uuid::Uuid;
+
+use crate::{
+    model::project::{
+        ProjectArchiveBucketJson, ProjectArchiveBucketRuleJson, ProjectArchiveCollectionJson,
+        ProjectArchiveCollectionRuleJson, ProjectArchiveFileJson, ProjectArchiveJson,
+        ProjectArchiveProjectJson, ProjectArchiveTokenJson,
+    },
+    util::{archive, export::ExportFormat, schema::collection_schema_json},
+};
+
+pub const ARCHIVE_VERSION: u32 = 1;
+pub const ARCHIVE_MANIFEST_PATH: &str = "manifest.json";
+pub const ARCHIVE_CHANNEL_CAPACITY: usize = 16;
+const ARCHIVE_RECORDS_PER_ENTRY: usize = 1000;
+
+// A project read out for its archive. The manifest is built up front, the records and file
+// contents are only read once the archive is written.
+pub struct ProjectExport {
+    manifest: Vec<u8>,
+    collections_data: Vec<CollectionDao>,
+    file_paths: Vec<(Uuid, PathBuf)>,
+}
+
+impl ProjectExport {
+    pub async fn new(db: &Db, project_data: &ProjectDao) -> Result<Self> {
+        let (collections_data, buckets_data, tokens_data) = tokio::try_join!(
+            CollectionDao::db_select_many_by_project_id(
+                db,
+                project_data.id(),
+                &None,
+                &None,
+                &CollectionOrder::CreatedAtDesc,
+                &None
+            ),
+            BucketDao::db_select_many_by_project_id(db, project_data.id()),
+            TokenDao::db_select_many_by_project_id(db, project_data.id())
+        )?;
+
+        let mut files = Vec::new();
+        let mut file_paths = Vec::new();
+        for bucket_data in &buckets_data {
+            let (files_data, _) = FileDao::db_select_many_by_bucket_id(
+                db,
+                bucket_data,
+                &FileFilter::default(),
+                &FileOrder::CreatedAtDesc,
+                &None,
+                &None,
+            )
+            .await?;
+
+            for file_data in &files_data {
+                // Unfinished uploads are left behind
+                if *file_data.pending() {
+                    continue;
+                }
+                files.push(ProjectArchiveFileJson::new(
+                    file_data.id(),
+                    file_data.bucket_id(),
+                    file_data.created_by(),
+                    file_data.file_name(),
+                    &file_data.content_type().to_string(),
+                    file_data.size(),
+                    file_data.public(),
+                    file_data.checksum(),
+                ));
+                file_paths.push((
+                    *file_data.id(),
+                    FileDao::full_path(bucket_data.path(), file_data.id())?,
+                ));
+            }
+        }
+
+        let mut tokens = Vec::with_capacity(tokens_data.len());
+        for token_data in &tokens_data {
+            let (collection_rules_data, bucket_rules_data) = tokio::try_join!(
+                CollectionRuleDao::db_select_many_by_token_id(db, token_data.id()),
+                BucketRuleDao::db_select_many_by_token_id(db, token_data.id())
+            )?;
+
+            tokens.push(ProjectArchiveTokenJson::new(
+                token_data.id(),
+                token_data.name(),
+                token_data.allow_anonymous(),
+                token_data.expired_at(),
+                collection_rules_data
+                    .iter()
+                    .map(|collection_rule_data| {
+                        ProjectArchiveCollectionRuleJson::new(
+                            collection_rule_data.collection_id(),
+                            collection_rule_data.find_one().to_str(),
+                            collection_rule_data.find_many().to_str(),
+                            collection_rule_data.insert_one(),
+                            collection_rule_data.update_one().to_str(),
+                            collection_rule_data.delete_one().to_str(),
+                            collection_rule_data.readable_fields(),
+                            collection_rule_data.writable_fields(),
+                        )
+                    })
+                    .collect(),
+                bucket_rules_data
+                    .iter()
+                    .map(|bucket_rule_data| {
+                        ProjectArchiveBucketRuleJson::new(
+                            bucket_rule_data.bucket_id(),
+                            bucket_rule_data.find_one().to_str(),
+                            bucket_rule_data.find_many().to_str(),
+                            bucket_rule_data.insert_one(),
+                            bucket_rule_data.update_one().to_str(),
+                            bucket_rule_data.delete_one().to_str(),
+                        )
+                    })
+                    .collect(),
+            ));
+        }
+
+        let manifest = ProjectArchiveJson::new(
+            &ARCHIVE_VERSION,
+            ProjectArchiveProjectJson::new(
+                project_data.id(),
+                project_data.admin_id(),
+                project_data.name(),
+            ),
+            collections_data
+                .iter()
+                .map(|collection_data| {
+                    ProjectArchiveCollectionJson::new(
+                        collection_data.id(),
+                        collection_schema_json(collection_data),
+                    )
+                })
+                .collect(),
+            buckets_data
+                .iter()
+                .map(|bucket_data| {
+                    ProjectArchiveBucketJson::new(
+                        bucket_data.id(),
+                        bucket_data.name(),
+                        bucket_data.opt_ttl(),
+                        bucket_data.max_file_size(),
+                        bucket_data.allowed_content_types(),
+                        bucket_data.quota_bytes(),
+                    )
+                })
+                .collect(),
+            files,
+            tokens,
+        );
+
+        Ok(Self {
+            manifest: archive::entry(ARCHIVE_MANIFEST_PATH, &serde_json::to_vec(&manifest)?)?,
+            collections_data,
+            file_paths,
+        })
+    }
+
+    // Sends the archive one entry at a time, so neither the records nor the files of the project
+    // are ever held in memory at once. A failure is sent as the last item, the archive is cut
+    // short by it.
+    pub async fn write(&self, db: &Db, tx: mpsc::Sender<Result<Vec<u8>>>) {
+        if let Err(err) = self.write_entries(db, &tx).await {
+            let _ = tx.send(Err(err)).await;
+        }
+    }
+
+    async fn write_entries(&self, db: &Db, tx: &mpsc::Sender<Result<Vec<u8>>>) -> Result<()> {
+        let send = |chunk: Vec<u8>| async move {
+            tx.send(Ok(chunk))
+                .await
+                .map_err(|_| Error::msg("The archive is no longer being read"))
+        };
+
+        send(self.manifest.clone()).await?;
+
+        let filters = RecordFilters::new(&Vec::new());
+        let orders = Vec::new();
+        let pagination = RecordPagination::new(&None);
+        for collection_data in &self.collections_data {
+            let mut columns = Vec::with_capacity(collection_data.schema_fields().len() + 4);
+            columns.extend(["_id", "_created_by", "_updated_at"]);
+            columns.extend(
+                collection_data
+                    .schema_fields()
+                    .keys()
+                    .map(|field| field.as_str()),
+            );
+            if *collection_data.opt_per_record_ttl() {
+                columns.push("_expired_at");
+            }
+            let line_columns = columns
+                .iter()
+                .map(|column| column.to_string())
+                .collect::<Vec<_>>();
+
+            let records = RecordDao::db_select_many_stream(
+                db,
+                &columns,
+                collection_data,
+                &None,
+                &filters,
+                &orders,
+                &pagination,
+                &true,
+            );
+            tokio::pin!(records);
+
+            let entry_path =
+                |chunk_idx: usize| format!("records/{}/{chunk_idx}.jsonl", collection_data.id());
+            let mut chunk = String::new();
+            let mut chunk_len = 0;
+            let mut chunk_idx = 0;
+            while let Some(record_data) = records.next().await {
+                chunk.push_str(&ExportFormat::Jsonl.line(&line_columns, &record_data?)?);
+                chunk_len += 1;
+                if chunk_len == ARCHIVE_RECORDS_PER_ENTRY {
+                    send(archive::entry(&entry_path(chunk_idx), chunk.as_bytes())?).await?;
+                    chunk.clear();
+                    chunk_len = 0;
+                    chunk_idx += 1;
+                }
+            }
+            if chunk_len > 0 {
+                send(archive::entry(&entry_path(chunk_idx), chunk.as_bytes())?).await?;
+            }
+        }
+
+        for (file_id, file_path) in &self.file_paths {
+            let content = fs::read(file_path).await?;
+            send(archive::entry(&format!("files/{file_id}"), &content)?).await?;
+        }
+
+        send(archive::end()).await
+    }
+}
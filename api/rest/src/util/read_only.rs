@@ -0,0 +1,26 @@
+use actix_web::{dev::ServiceRequest, http::Method};
+
+// Requests that go through POST without changing any data, either because their filters don't fit
+// in a query string, because they only sign in or because they only read the data out
+const READ_POST_PATHS: [&str; 10] = [
+    "/records",
+    "/records/export",
+    "/presign",
+    "/auth/password-based",
+    "/auth/token-based",
+    "/auth/2fa/verify",
+    "/auth/mqtt_authentication",
+    "/auth/mqtt_authorization",
+    "/admin/backup/run",
+    "/admin/config/reload",
+];
+
+pub fn is_write_request(req: &ServiceRequest) -> bool {
+    match *req.method() {
+        Method::GET | Method::HEAD | Method::OPTIONS => false,
+        Method::POST => !READ_POST_PATHS
+            .iter()
+            .any(|read_path| req.path().ends_with(read_path)),
+        _ => true,
+    }
+}
@@ -0,0 +1,112 @@
+use ahash::{HashMap, HashMapExt};
+use hb_dao::{
+    collection::{CollectionDao, ReferenceAction, SchemaFieldProps},
+    value::ColumnKind,
+};
+use uuid::Uuid;
+
+use crate::model::collection::{CollectionSchemaJson, SchemaFieldPropsJson};
+
+// The document export_schema returns, which apply_schema and the project import read back
+pub fn collection_schema_json(collection_data: &CollectionDao) -> CollectionSchemaJson {
+    CollectionSchemaJson::new(
+        collection_data.name(),
+        &collection_data
+            .schema_fields()
+            .iter()
+            .map(|(field, props)| {
+                (
+                    field.clone(),
+                    SchemaFieldPropsJson::new(
+                        props.kind().to_str(),
+                        &Some(*props.required()),
+                        &Some(*props.unique()),
+                        &Some(*props.indexed()),
+                        &Some(*props.auth_column()),
+                        &Some(*props.hashed()),
+                        &Some(*props.hidden()),
+                        &props
+                            .default()
+                            .as_ref()
+                            .and_then(|default| default.to_serde_json().ok()),
+                        props.min(),
+                        props.max(),
+                        props.max_length(),
+                        props.regex(),
+                        &props.enum_values().as_ref().map(|enum_values| {
+                            enum_values
+                                .iter()
+                                .filter_map(|value| value.to_serde_json().ok())
+                                .collect()
+                        }),
+                        &props.kind().reference_collection_id().copied(),
+                        &props
+                            .on_delete()
+                            .map(|on_delete| on_delete.to_str().to_owned()),
+                    ),
+                )
+            })
+            .collect(),
+        collection_data.unique_indexes(),
+        collection_data.opt_auth_column_id(),
+        collection_data.opt_ttl(),
+        collection_data.opt_soft_delete(),
+        collection_data.opt_per_record_ttl(),
+        collection_data.opt_mqtt_publish(),
+    )
+}
+
+// Builds the schema of a collection out of its JSON form with the same rules as insert_one,
+// returning the reason it is rejected instead of a response. References to a collection found in
+// collection_ids are pointed at the id it maps to.
+pub fn schema_fields_from_json(
+    schema_fields_json: &HashMap<String, SchemaFieldPropsJson>,
+    collection_ids: &HashMap<Uuid, Uuid>,
+) -> Result<HashMap<String, SchemaFieldProps>, String> {
+    let mut schema_fields = HashMap::with_capacity(schema_fields_json.len());
+    for (field, props) in schema_fields_json {
+        if field.is_empty() {
+            return Err("Field name in schema_fields can't be empty string".to_owned());
+        }
+        if field.starts_with("_") || !field.chars().all(|c| c == '_' || ('a'..='z').contains(&c)) {
+            return Err(format!("Field '{field}' should only have lowercase English letters and an optional underscore (_) after the first character"));
+        }
+        if props.indexed().is_some_and(|indexed| indexed)
+            && !props.required().is_some_and(|required| required)
+        {
+            return Err(format!(
+                "Field '{field}' must be required because it is in the indexes"
+            ));
+        }
+        let reference_collection_id = props
+            .reference_collection_id()
+            .map(|id| *collection_ids.get(&id).unwrap_or(&id));
+        let kind = ColumnKind::from_str_with_reference(props.kind(), &reference_collection_id)
+            .map_err(|err| err.to_string())?;
+        let on_delete = match props.on_delete() {
+            Some(on_delete) => {
+                Some(ReferenceAction::from_str(on_delete).map_err(|err| err.to_string())?)
+            }
+            None => None,
+        };
+        let schema_field_props = SchemaFieldProps::new(
+            &kind,
+            &props.required().unwrap_or(false),
+            &props.unique().unwrap_or(false),
+            &props.indexed().unwrap_or(false),
+            &props.auth_column().unwrap_or(false),
+            &props.hashed().unwrap_or(false),
+            &props.hidden().unwrap_or(false),
+            props.default(),
+            props.min(),
+            props.max(),
+            props.max_length(),
+            props.regex(),
+            props.enum_values(),
+            &on_delete,
+        )
+        .map_err(|err| format!("Error in field '{field}': {err}"))?;
+        schema_fields.insert(field.to_owned(), schema_field_props);
+    }
+    Ok(schema_fields)
+}
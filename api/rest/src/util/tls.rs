@@ -0,0 +1,234 @@
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Error, Result};
+use hb_config::api::ApiRestTlsConfig;
+use rustls::{
+    pki_types::CertificateDer,
+    server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier},
+    sign::CertifiedKey,
+    RootCertStore, ServerConfig,
+};
+use tokio::{
+    task::JoinHandle,
+    time::{self, Instant, MissedTickBehavior},
+};
+use tokio_util::sync::CancellationToken;
+
+const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(60);
+
+pub struct Tls {
+    server_config: ServerConfig,
+    cert_resolver: Arc<CertResolver>,
+    watch_interval: Duration,
+}
+
+impl Tls {
+    // Everything is loaded and checked right away, so a bad certificate or key stops the startup
+    // instead of failing every handshake
+    pub fn new(config: &ApiRestTlsConfig) -> Result<Self> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let cert_resolver = Arc::new(CertResolver::new(config.cert_path(), config.key_path())?);
+
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?;
+        let builder = match config.client_ca_path() {
+            Some(client_ca_path) => {
+                let mut roots = RootCertStore::empty();
+                for cert in read_certs(client_ca_path)? {
+                    if let Err(err) = roots.add(cert) {
+                        return Err(Error::msg(format!(
+                            "TLS client CA file '{client_ca_path}' has an invalid certificate: {err}"
+                        )));
+                    }
+                }
+                let verifier =
+                    match WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                        .build()
+                    {
+                        Ok(verifier) => verifier,
+                        Err(err) => {
+                            return Err(Error::msg(format!(
+                                "TLS client CA file '{client_ca_path}' can't be used: {err}"
+                            )))
+                        }
+                    };
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        Ok(Self {
+            server_config: builder.with_cert_resolver(cert_resolver.clone()),
+            cert_resolver,
+            watch_interval: config.watch_interval().unwrap_or(DEFAULT_WATCH_INTERVAL),
+        })
+    }
+
+    pub fn server_config(&self) -> &ServerConfig {
+        &self.server_config
+    }
+
+    // Picks up a renewed certificate without a restart. Connections already open keep the one
+    // they were made with.
+    pub fn watch(&self, cancel_token: CancellationToken) -> JoinHandle<()> {
+        let cert_resolver = self.cert_resolver.clone();
+        let watch_interval = self.watch_interval;
+
+        tokio::spawn((|| async move {
+            let mut interval = time::interval_at(Instant::now() + watch_interval, watch_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        match cert_resolver.reload_if_changed() {
+                            Ok(true) => hb_log::info(
+                                Some("🔐"),
+                                "[ApiRestServer] Reloaded the TLS certificate",
+                            ),
+                            Ok(false) => (),
+                            Err(err) => hb_log::error(
+                                None,
+                                &format!(
+                                    "[ApiRestServer] Failed to reload the TLS certificate, keeping the current one: {err}"
+                                ),
+                            ),
+                        }
+                    }
+                }
+            }
+        })())
+    }
+}
+
+// Hands out the certificate last loaded from the files, which is replaced whenever they change
+#[derive(Debug)]
+struct CertResolver {
+    cert_path: String,
+    key_path: String,
+    certified_key: RwLock<Arc<CertifiedKey>>,
+    modified: Mutex<Option<(SystemTime, SystemTime)>>,
+}
+
+impl CertResolver {
+    fn new(cert_path: &str, key_path: &str) -> Result<Self> {
+        let modified = modified(cert_path, key_path);
+        let certified_key = load_certified_key(cert_path, key_path)?;
+
+        Ok(Self {
+            cert_path: cert_path.to_owned(),
+            key_path: key_path.to_owned(),
+            certified_key: RwLock::new(Arc::new(certified_key)),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    // A renewal can be caught between writing the certificate and the key, so files that don't
+    // load are tried again on the next check rather than taken as they are
+    fn reload_if_changed(&self) -> Result<bool> {
+        let modified = modified(&self.cert_path, &self.key_path);
+        if modified == *self.modified.lock().unwrap() {
+            return Ok(false);
+        }
+
+        let certified_key = load_certified_key(&self.cert_path, &self.key_path)?;
+        *self.certified_key.write().unwrap() = Arc::new(certified_key);
+        *self.modified.lock().unwrap() = modified;
+
+        Ok(true)
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.certified_key.read().unwrap().clone())
+    }
+}
+
+// Followed through symlinks, so a renewal that only points them at new files is seen as well
+fn modified(cert_path: &str, key_path: &str) -> Option<(SystemTime, SystemTime)> {
+    let cert_modified = fs::metadata(cert_path).and_then(|metadata| metadata.modified());
+    let key_modified = fs::metadata(key_path).and_then(|metadata| metadata.modified());
+    match (cert_modified, key_modified) {
+        (Ok(cert_modified), Ok(key_modified)) => Some((cert_modified, key_modified)),
+        _ => None,
+    }
+}
+
+fn load_certified_key(cert_path: &str, key_path: &str) -> Result<CertifiedKey> {
+    let certs = read_certs(cert_path)?;
+
+    let key_file = match File::open(key_path) {
+        Ok(key_file) => key_file,
+        Err(err) => {
+            return Err(Error::msg(format!(
+                "TLS key file '{key_path}' can't be read: {err}"
+            )))
+        }
+    };
+    let key = match rustls_pemfile::private_key(&mut BufReader::new(key_file)) {
+        Ok(Some(key)) => key,
+        Ok(None) => {
+            return Err(Error::msg(format!(
+                "TLS key file '{key_path}' has no private key"
+            )))
+        }
+        Err(err) => {
+            return Err(Error::msg(format!(
+                "TLS key file '{key_path}' can't be read: {err}"
+            )))
+        }
+    };
+    let signing_key = match rustls::crypto::ring::sign::any_supported_type(&key) {
+        Ok(signing_key) => signing_key,
+        Err(err) => {
+            return Err(Error::msg(format!(
+                "TLS key file '{key_path}' has an unsupported private key: {err}"
+            )))
+        }
+    };
+
+    let certified_key = CertifiedKey::new(certs, signing_key);
+    if let Err(err) = certified_key.keys_match() {
+        return Err(Error::msg(format!(
+            "TLS certificate '{cert_path}' doesn't match the key '{key_path}': {err}"
+        )));
+    }
+
+    Ok(certified_key)
+}
+
+fn read_certs(path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            return Err(Error::msg(format!(
+                "TLS certificate file '{path}' can't be read: {err}"
+            )))
+        }
+    };
+    let certs =
+        match rustls_pemfile::certs(&mut BufReader::new(file)).collect::<Result<Vec<_>, _>>() {
+            Ok(certs) => certs,
+            Err(err) => {
+                return Err(Error::msg(format!(
+                    "TLS certificate file '{path}' can't be read: {err}"
+                )))
+            }
+        };
+    if certs.is_empty() {
+        return Err(Error::msg(format!(
+            "TLS certificate file '{path}' has no certificates"
+        )));
+    }
+
+    Ok(certs)
+}
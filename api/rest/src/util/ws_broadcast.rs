@@ -19,3 +19,39 @@ where
     let data = serde_json::to_value(data)?;
     handler.broadcast(Message::new(target, created_by, kind, data))
 }
+
+// Makes the websocket server look the permissions of the token up again after the token or one of
+// its rules changed
+pub fn websocket_invalidate_token(handler: &WebSocketHandler, token_id: &Uuid) {
+    if let Err(err) = handler.invalidate_token(token_id) {
+        hb_log::error(
+            None,
+            &format!(
+                "[ApiRestServer] Error when invalidating websocket permissions of token {token_id}: {err}"
+            ),
+        );
+    }
+}
+
+// Lets the admins subscribed to the project know that one of its collections, buckets, tokens or
+// rules changed, so they can refetch it
+pub fn websocket_broadcast_project(
+    handler: &WebSocketHandler,
+    project_id: &Uuid,
+    kind: MessageKind,
+    entity: &str,
+    id: &Uuid,
+) {
+    if let Err(err) = websocket_broadcast(
+        handler,
+        Target::Project(*project_id),
+        None,
+        kind,
+        serde_json::json!({ "entity": entity, "id": id }),
+    ) {
+        hb_log::error(
+            None,
+            &format!("[ApiRestServer] Error when broadcasting {entity} {id} to websocket: {err}"),
+        );
+    }
+}
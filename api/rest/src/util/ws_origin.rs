@@ -0,0 +1,83 @@
+use actix_web::{
+    http::header::{self, HeaderValue},
+    HttpRequest,
+};
+use anyhow::{Error, Result};
+
+use crate::context::ApiRestWsCtx;
+
+// CORS doesn't apply to websocket upgrades, so the Origin header is checked here instead.
+// Requests without an Origin header don't come from a browser and are let through.
+pub fn validate_websocket_origin(ctx: &ApiRestWsCtx, req: &HttpRequest) -> Result<()> {
+    if !*ctx.check_origin() {
+        return Ok(());
+    }
+
+    check_origin(req.headers().get(header::ORIGIN), &ctx.allowed_origin())
+}
+
+fn check_origin(origin: Option<&HeaderValue>, allowed_origin: &Option<String>) -> Result<()> {
+    let origin = match origin {
+        Some(origin) => match origin.to_str() {
+            Ok(origin) => origin,
+            Err(_) => return Err(Error::msg("Origin header is not a valid string")),
+        },
+        None => return Ok(()),
+    };
+
+    match allowed_origin {
+        Some(allowed_origin) if allowed_origin == origin => Ok(()),
+        _ => Err(Error::msg(format!(
+            "Origin '{origin}' is not allowed to open a websocket connection"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    const ALLOWED_ORIGIN: &str = "https://app.example.com";
+
+    fn check(req: TestRequest, allowed_origin: Option<&str>) -> Result<()> {
+        let req = req.to_http_request();
+        check_origin(
+            req.headers().get(header::ORIGIN),
+            &allowed_origin.map(str::to_owned),
+        )
+    }
+
+    #[test]
+    fn missing_origin_is_let_through() {
+        assert!(check(TestRequest::default(), Some(ALLOWED_ORIGIN)).is_ok());
+        assert!(check(TestRequest::default(), None).is_ok());
+    }
+
+    #[test]
+    fn matching_origin_is_let_through() {
+        let req = TestRequest::default().insert_header((header::ORIGIN, ALLOWED_ORIGIN));
+        assert!(check(req, Some(ALLOWED_ORIGIN)).is_ok());
+    }
+
+    #[test]
+    fn mismatching_origin_is_rejected() {
+        for origin in [
+            "https://evil.example.com",
+            "http://app.example.com",
+            "https://app.example.com:8443",
+            "https://app.example.com.evil.com",
+            "null",
+        ] {
+            let req = TestRequest::default().insert_header((header::ORIGIN, origin));
+            assert!(check(req, Some(ALLOWED_ORIGIN)).is_err(), "{origin}");
+        }
+    }
+
+    #[test]
+    fn any_origin_is_rejected_without_an_allowed_origin() {
+        let req = TestRequest::default().insert_header((header::ORIGIN, ALLOWED_ORIGIN));
+        assert!(check(req, None).is_err());
+    }
+}
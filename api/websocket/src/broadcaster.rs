@@ -3,6 +3,11 @@ use tokio::sync::mpsc;
 
 use crate::message::Message;
 
+// Messages reach the subscribers of a target in the order they're handed to broadcast, numbered
+// by the seq of that order. The record handlers broadcast inline once their write is done, so the
+// changes of a record made by requests that finish one after another arrive in that order.
+// Messages aren't routed by record: changes racing each other on the same record arrive in the
+// order their requests finished, which isn't always the order the database applied them in.
 #[derive(Clone)]
 pub struct WebSocketBroadcaster {
     broadcast_tx: mpsc::UnboundedSender<Message>,
@@ -1,5 +1,6 @@
 use anyhow::{Error, Result};
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
 use crate::{
     message::{Message, Target},
@@ -12,7 +13,14 @@ pub enum Connection {
         user_session: UserSession,
         target: Target,
         connection_id: ConnectionId,
-        connection_tx: mpsc::UnboundedSender<Message>,
+        connection_tx: mpsc::Sender<Message>,
+        evict_token: CancellationToken,
+        last_seq: Option<u64>,
+        with_snapshot: bool,
+    },
+    Authenticate {
+        connection_id: ConnectionId,
+        token: String,
     },
     Disconnect(ConnectionId),
 }
@@ -32,7 +40,10 @@ impl WebSocketConnection {
         user_session: UserSession,
         target: Target,
         connection_id: ConnectionId,
-        connection_tx: mpsc::UnboundedSender<Message>,
+        connection_tx: mpsc::Sender<Message>,
+        evict_token: CancellationToken,
+        last_seq: Option<u64>,
+        with_snapshot: bool,
     ) -> Result<()> {
         self.tx
             .send(Connection::Connect {
@@ -40,6 +51,19 @@ impl WebSocketConnection {
                 target,
                 connection_id,
                 connection_tx,
+                evict_token,
+                last_seq,
+                with_snapshot,
+            })
+            .map_err(|err| Error::from(err))?;
+        Ok(())
+    }
+
+    pub fn authenticate(&self, connection_id: ConnectionId, token: String) -> Result<()> {
+        self.tx
+            .send(Connection::Authenticate {
+                connection_id,
+                token,
             })
             .map_err(|err| Error::from(err))?;
         Ok(())
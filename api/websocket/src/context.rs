@@ -1,17 +1,47 @@
 use std::sync::Arc;
 
 use hb_dao::Db;
+use hb_token_jwt::token::JwtToken;
+use hb_webhook::WebhookPayload;
+use tokio::sync::mpsc;
+
+use crate::message::RecordEvent;
 
 pub struct ApiWebSocketCtx {
     db: Arc<Db>,
+    jwt: Arc<JwtToken>,
+    webhook: Option<mpsc::Sender<WebhookPayload>>,
+    mqtt: Option<mpsc::Sender<RecordEvent>>,
 }
 
 impl ApiWebSocketCtx {
-    pub fn new(db: Arc<Db>) -> Self {
-        Self { db }
+    pub fn new(
+        db: Arc<Db>,
+        jwt: Arc<JwtToken>,
+        webhook: Option<mpsc::Sender<WebhookPayload>>,
+        mqtt: Option<mpsc::Sender<RecordEvent>>,
+    ) -> Self {
+        Self {
+            db,
+            jwt,
+            webhook,
+            mqtt,
+        }
     }
 
     pub fn db(&self) -> &Db {
         &self.db
     }
+
+    pub fn jwt(&self) -> &JwtToken {
+        &self.jwt
+    }
+
+    pub fn webhook(&self) -> &Option<mpsc::Sender<WebhookPayload>> {
+        &self.webhook
+    }
+
+    pub fn mqtt(&self) -> &Option<mpsc::Sender<RecordEvent>> {
+        &self.mqtt
+    }
 }
@@ -3,13 +3,15 @@ use std::time::{Duration, Instant};
 use anyhow::Result;
 use futures::StreamExt;
 use tokio::{select, sync::mpsc, time::interval};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use crate::{
     broadcaster::WebSocketBroadcaster,
     connection::WebSocketConnection,
-    message::{Message, Target},
+    message::{ClientMessage, Message, MessageFormat, Target},
     session::UserSession,
+    ConnectionId, TokenId,
 };
 
 #[derive(Clone)]
@@ -19,6 +21,7 @@ pub struct WebSocketHandler {
 
     heartbeat_interval: Duration,
     client_timeout: Duration,
+    send_buffer_size: usize,
 }
 
 impl WebSocketHandler {
@@ -27,12 +30,14 @@ impl WebSocketHandler {
         broadcaster: WebSocketBroadcaster,
         heartbeat_interval: &Duration,
         client_timeout: &Duration,
+        send_buffer_size: &usize,
     ) -> Self {
         Self {
             connection,
             broadcaster,
             heartbeat_interval: *heartbeat_interval,
             client_timeout: *client_timeout,
+            send_buffer_size: *send_buffer_size,
         }
     }
 
@@ -40,21 +45,38 @@ impl WebSocketHandler {
         self,
         user_session: UserSession,
         target: Target,
+        last_seq: Option<u64>,
+        with_snapshot: bool,
+        format: MessageFormat,
         mut session: actix_ws_ng::Session,
         mut msg_stream: actix_ws_ng::MessageStream,
     ) -> Result<()> {
         let connection_id = Uuid::now_v7();
 
-        let (connection_tx, mut connection_rx) = mpsc::unbounded_channel();
+        let (connection_tx, mut connection_rx) = mpsc::channel(self.send_buffer_size);
+        let evict_token = CancellationToken::new();
 
-        self.connection
-            .connect(user_session, target, connection_id, connection_tx)?;
+        self.connection.connect(
+            user_session,
+            target,
+            connection_id,
+            connection_tx,
+            evict_token.clone(),
+            last_seq,
+            with_snapshot,
+        )?;
 
         let mut last_heartbeat = Instant::now();
         let mut interval = interval(self.heartbeat_interval);
 
         let close_reason = loop {
             select! {
+                _ = evict_token.cancelled() => {
+                    break Some(actix_ws_ng::CloseReason {
+                        code: actix_ws_ng::CloseCode::Again,
+                        description: Some("slow consumer".to_owned()),
+                    });
+                }
                 _ = interval.tick() => {
                     if Instant::now().duration_since(last_heartbeat) > self.client_timeout {
                         hb_log::info(
@@ -85,7 +107,20 @@ impl WebSocketHandler {
                             }
                             actix_ws_ng::Message::Pong(_) => last_heartbeat = Instant::now(),
                             actix_ws_ng::Message::Close(reason) => break reason,
-                            actix_ws_ng::Message::Text(_) | actix_ws_ng::Message::Binary(_) => (),
+                            actix_ws_ng::Message::Text(text) => {
+                                if let Ok(client_msg) = serde_json::from_str(&text) {
+                                    if self.handle_client_message(connection_id, client_msg).is_err() {
+                                        break None;
+                                    }
+                                }
+                            }
+                            actix_ws_ng::Message::Binary(bytes) => {
+                                if let Ok(client_msg) = rmp_serde::from_slice(&bytes) {
+                                    if self.handle_client_message(connection_id, client_msg).is_err() {
+                                        break None;
+                                    }
+                                }
+                            }
                             _ => break None,
                         }
                     } else if let Some(Err(err)) = msg {
@@ -101,19 +136,34 @@ impl WebSocketHandler {
                     }
                 }
                 Some(msg) = connection_rx.recv() => {
-                    let msg = match serde_json::to_string(&msg) {
-                        Ok(msg) => msg,
-                        Err(err) => {
-                            hb_log::error(
-                                None,
-                                &format!(
-                                    "[ApiWebSocketServer] Error when serializing message: {err}",
-                                ),
-                            );
-                            continue;
-                        }
+                    let sent = match format {
+                        MessageFormat::Json => match serde_json::to_string(&msg) {
+                            Ok(msg) => session.text(msg).await,
+                            Err(err) => {
+                                hb_log::error(
+                                    None,
+                                    &format!(
+                                        "[ApiWebSocketServer] Error when serializing message: {err}",
+                                    ),
+                                );
+                                continue;
+                            }
+                        },
+                        // Named so the fields are keyed the same way as in JSON
+                        MessageFormat::MessagePack => match rmp_serde::to_vec_named(&msg) {
+                            Ok(msg) => session.binary(msg).await,
+                            Err(err) => {
+                                hb_log::error(
+                                    None,
+                                    &format!(
+                                        "[ApiWebSocketServer] Error when serializing message: {err}",
+                                    ),
+                                );
+                                continue;
+                            }
+                        },
                     };
-                    if session.text(msg).await.is_err() {
+                    if sent.is_err() {
                         hb_log::info(
                             None,
                             &format!(
@@ -133,7 +183,23 @@ impl WebSocketHandler {
         Ok(())
     }
 
+    // Anything the client sends that isn't a known control message is ignored
+    fn handle_client_message(
+        &self,
+        connection_id: ConnectionId,
+        client_msg: ClientMessage,
+    ) -> Result<()> {
+        match client_msg {
+            ClientMessage::Auth { token } => self.connection.authenticate(connection_id, token),
+        }
+    }
+
     pub fn broadcast(&self, message: Message) -> Result<()> {
         self.broadcaster.broadcast(message)
     }
+
+    pub fn invalidate_token(&self, token_id: &TokenId) -> Result<()> {
+        self.broadcaster
+            .broadcast(Message::invalidate_token(token_id))
+    }
 }
@@ -1,15 +1,26 @@
-use std::time::Duration;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
 
-use ahash::{HashMap, HashMapExt, HashSet};
+use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use anyhow::Result;
 use broadcaster::WebSocketBroadcaster;
 use connection::{Connection, WebSocketConnection};
 use context::ApiWebSocketCtx;
 use handler::WebSocketHandler;
-use hb_dao::{collection_rule::CollectionPermission, token::TokenDao};
-use message::{Message, Target};
-use session::UserSession;
-use tokio::{sync::mpsc, task::JoinHandle};
+use hb_dao::{
+    bucket_rule::BucketPermission,
+    collection::CollectionDao,
+    collection_rule::{CollectionPermission, CollectionRuleDao},
+    error::DaoError,
+    record::{RecordDao, RecordFilters, RecordPagination},
+    token::TokenDao,
+};
+use hb_webhook::WebhookPayload;
+use message::{Message, MessageKind, RecordEvent, Target};
+use session::{UserSession, UserSessionId};
+use tokio::{sync::mpsc, task::JoinHandle, time::interval};
 use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
@@ -26,12 +37,35 @@ pub type UserId = Uuid;
 pub type TokenId = Uuid;
 pub type AdminId = Uuid;
 
+// How long the permission of a token to receive the messages of a target is reused for when
+// broadcasting. Changes to the token or its rules drop it right away.
+const PERMISSION_CACHE_TTL: Duration = Duration::from_secs(30);
+
+// How often the sessions are checked for a JWT about to expire, and how long before it does the
+// client is warned to send a newer one
+const TOKEN_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const TOKEN_EXPIRING_WARNING_SECS: i64 = 60;
+
 pub struct ApiWebSocketServer {
     ctx: ApiWebSocketCtx,
 
-    sessions: HashMap<ConnectionId, mpsc::UnboundedSender<Message>>,
+    // Sender of every connection along with the token that closes it when it can't keep up
+    sessions: HashMap<ConnectionId, (mpsc::Sender<Message>, CancellationToken)>,
     user_sessions: HashMap<ConnectionId, UserSession>,
+    // Sessions already told their JWT is about to expire
+    expiring_sessions: HashSet<ConnectionId>,
     subscribers: HashMap<Target, HashSet<ConnectionId>>,
+    // Reverse index of subscribers so a connection is removed without going through every target
+    connection_targets: HashMap<ConnectionId, Target>,
+    permission_cache: HashMap<(TokenId, Target), (Instant, CachedPermission)>,
+    sequences: HashMap<Target, u64>,
+    // The most recent messages of every target, replayed to clients reconnecting with the
+    // sequence of the last message they got
+    history: HashMap<Target, VecDeque<Message>>,
+    replay_buffer_size: usize,
+    snapshot_max_records: usize,
+    send_buffer_size: usize,
+    slow_consumers: u64,
 
     connection_rx: mpsc::UnboundedReceiver<Connection>,
     broadcast_rx: mpsc::UnboundedReceiver<Message>,
@@ -43,12 +77,16 @@ impl ApiWebSocketServer {
 
         heartbeat_interval: &Duration,
         client_timeout: &Duration,
+        replay_buffer_size: &usize,
+        snapshot_max_records: &usize,
+        send_buffer_size: &usize,
     ) -> (Self, WebSocketHandler, WebSocketBroadcaster) {
         hb_log::info(Some("⚡"), "[ApiWebSocketServer] Initializing component");
 
         let (connection_tx, connection_rx) = mpsc::unbounded_channel();
         let (broadcast_tx, broadcast_rx) = mpsc::unbounded_channel();
 
+        let send_buffer_size = (*send_buffer_size).max(1);
         let connection = WebSocketConnection::new(connection_tx);
         let publisher = WebSocketBroadcaster::new(broadcast_tx);
         let handler = WebSocketHandler::new(
@@ -56,6 +94,7 @@ impl ApiWebSocketServer {
             publisher.clone(),
             heartbeat_interval,
             client_timeout,
+            &send_buffer_size,
         );
 
         (
@@ -64,7 +103,16 @@ impl ApiWebSocketServer {
 
                 sessions: HashMap::new(),
                 user_sessions: HashMap::new(),
+                expiring_sessions: HashSet::default(),
                 subscribers: HashMap::new(),
+                connection_targets: HashMap::new(),
+                permission_cache: HashMap::new(),
+                sequences: HashMap::new(),
+                history: HashMap::new(),
+                replay_buffer_size: *replay_buffer_size,
+                snapshot_max_records: *snapshot_max_records,
+                send_buffer_size,
+                slow_consumers: 0,
 
                 connection_rx,
                 broadcast_rx,
@@ -74,10 +122,12 @@ impl ApiWebSocketServer {
         )
     }
 
-    pub fn run(mut self, cancel_token: CancellationToken) -> JoinHandle<()> {
+    pub fn run(mut self, cancel_token: CancellationToken) -> JoinHandle<Result<()>> {
         hb_log::info(Some("💫"), "[ApiWebSocketServer] Running component");
 
         tokio::spawn((|| async move {
+            let mut expiry_interval = interval(TOKEN_EXPIRY_CHECK_INTERVAL);
+
             loop {
                 tokio::select! {
                     _ = cancel_token.cancelled() => {
@@ -86,6 +136,7 @@ impl ApiWebSocketServer {
                     _ = tokio::signal::ctrl_c() => {
                         break;
                     }
+                    _ = expiry_interval.tick() => self.warn_expiring_sessions(),
                     connection = self.connection_rx.recv() => {
                         if let Some(connection) = connection {
                             match connection {
@@ -94,18 +145,34 @@ impl ApiWebSocketServer {
                                     target,
                                     connection_id,
                                     connection_tx,
-                                } => self.insert_connection(
-                                    user_session,
-                                    target,
+                                    evict_token,
+                                    last_seq,
+                                    with_snapshot,
+                                } => {
+                                    let _ = self
+                                        .insert_connection(
+                                            user_session,
+                                            target,
+                                            connection_id,
+                                            connection_tx,
+                                            evict_token,
+                                            last_seq,
+                                            with_snapshot,
+                                        )
+                                        .await;
+                                }
+                                Connection::Authenticate {
                                     connection_id,
-                                    connection_tx,
-                                ),
+                                    token,
+                                } => self.authenticate(&connection_id, &token),
                                 Connection::Disconnect(connection_id) => self.disconnect(connection_id),
                             }
                         } else {
                             break;
                         }
                     }
+                    // Messages are fanned out one at a time in the order they were sent, see
+                    // WebSocketBroadcaster for what that means for the changes of a record
                     broadcast = self.broadcast_rx.recv() => {
                         if let Some(message) = broadcast {
                             let _ = self.broadcast(message).await;
@@ -117,70 +184,692 @@ impl ApiWebSocketServer {
             }
 
             hb_log::info(None, "[ApiWebSocketServer] Shutting down component");
+
+            Ok(())
         })())
     }
 
-    fn insert_connection(
+    async fn insert_connection(
         &mut self,
         user_session: UserSession,
         target: Target,
         connection_id: ConnectionId,
-        connection_tx: mpsc::UnboundedSender<Message>,
-    ) {
-        self.sessions.insert(connection_id, connection_tx);
+        connection_tx: mpsc::Sender<Message>,
+        evict_token: CancellationToken,
+        last_seq: Option<u64>,
+        with_snapshot: bool,
+    ) -> Result<()> {
+        self.sessions
+            .insert(connection_id, (connection_tx, evict_token));
         self.user_sessions.insert(connection_id, user_session);
         self.subscribers
-            .entry(target)
+            .entry(target.clone())
             .or_default()
             .insert(connection_id);
+        self.connection_targets
+            .insert(connection_id, target.clone());
+
+        // Messages are handled one at a time, so nothing live reaches the connection before the
+        // snapshot or the replay is done. A snapshot already covers anything a replay would send.
+        if with_snapshot {
+            if let Err(err) = self.snapshot(&connection_id, &target).await {
+                hb_log::error(
+                    None,
+                    &format!(
+                        "[ApiWebSocketServer] Error when sending snapshot to connection '{connection_id}': {err}"
+                    ),
+                );
+                return Err(err);
+            }
+        } else if let Some(last_seq) = last_seq {
+            self.replay(&connection_id, &target, &last_seq).await?;
+        }
+
+        Ok(())
+    }
+
+    // Sends the records of the collection as they currently are, one message each, followed by a
+    // marker. Live messages still queued are handled after it, so none of them is overtaken by a
+    // snapshot row older than it.
+    async fn snapshot(&mut self, connection_id: &ConnectionId, target: &Target) -> Result<()> {
+        let collection_id = match target {
+            Target::Collection(collection_id) => collection_id,
+            _ => return Ok(()),
+        };
+
+        let (token_id, user_id) = match self.user_sessions.get(connection_id).map(|s| s.id()) {
+            Some(UserSessionId::Admin(_)) => (None, None),
+            Some(UserSessionId::Token(token_id, user_id)) => (Some(*token_id), *user_id),
+            None => return Ok(()),
+        };
+
+        let (collection_rule_data, created_by) = match token_id {
+            Some(token_id) => {
+                let key = (token_id, target.clone());
+                self.cache_permission(&key).await?;
+                let collection_rule_data = match self.permission_cache.get(&key) {
+                    Some((_, CachedPermission::Collection(collection_rule_data))) => {
+                        collection_rule_data
+                    }
+                    _ => return Ok(()),
+                };
+                let created_by = match collection_rule_data.find_many() {
+                    CollectionPermission::All => None,
+                    CollectionPermission::SelfMade => Some(user_id.unwrap_or(token_id)),
+                    CollectionPermission::None => return Ok(()),
+                };
+                (Some(collection_rule_data), created_by)
+            }
+            None => (None, None),
+        };
+
+        let collection_data = CollectionDao::db_select(self.ctx.db(), collection_id).await?;
+        let (records_data, _) = RecordDao::db_select_many(
+            self.ctx.db(),
+            &HashSet::new(),
+            &collection_data,
+            &created_by,
+            &RecordFilters::new(&Vec::new()),
+            &Vec::new(),
+            &Vec::new(),
+            // The whole snapshot has to fit in the send buffer of the connection, with room for
+            // the marker
+            &RecordPagination::new(&Some(
+                i32::try_from(
+                    self.snapshot_max_records
+                        .min(self.send_buffer_size.saturating_sub(1)),
+                )
+                .unwrap_or(i32::MAX),
+            )),
+            &token_id.is_none(),
+        )
+        .await?;
+
+        let seq = self.sequences.get(target).copied().unwrap_or(0);
+        let mut messages = Vec::with_capacity(records_data.len() + 1);
+        for record_data in &records_data {
+            let mut message = Message::new(
+                target.clone(),
+                None,
+                MessageKind::Snapshot,
+                serde_json::to_value(record_data.to_serde_json()?)?,
+            );
+            message.set_seq(seq);
+            if let Some(collection_rule_data) = collection_rule_data {
+                message.retain_data_fields(|field| collection_rule_data.is_readable_field(field));
+            }
+            messages.push(message);
+        }
+        let mut message = Message::new(
+            target.clone(),
+            None,
+            MessageKind::SnapshotComplete,
+            serde_json::json!({ "count": records_data.len() }),
+        );
+        message.set_seq(seq);
+        messages.push(message);
+
+        for message in messages {
+            self.try_send(connection_id, message);
+        }
+
+        Ok(())
+    }
+
+    async fn replay(
+        &mut self,
+        connection_id: &ConnectionId,
+        target: &Target,
+        last_seq: &u64,
+    ) -> Result<()> {
+        let seq = self.sequences.get(target).copied().unwrap_or(0);
+        if *last_seq == seq {
+            return Ok(());
+        }
+
+        // A sequence ahead of the server means it restarted and the sequences started over. A
+        // replay that wouldn't fit in the send buffer is refetched through the REST API instead.
+        let messages: Vec<_> = match self.history.get(target) {
+            Some(history)
+                if *last_seq < seq
+                    && seq - *last_seq < self.send_buffer_size as u64
+                    && history
+                        .front()
+                        .is_some_and(|message| *message.seq() <= *last_seq + 1) =>
+            {
+                history
+                    .iter()
+                    .filter(|message| message.seq() > last_seq)
+                    .cloned()
+                    .collect()
+            }
+            _ => {
+                self.try_send(connection_id, Message::resync_required(target, &seq));
+                return Ok(());
+            }
+        };
+
+        for message in &messages {
+            self.send(connection_id, message).await?;
+        }
+
+        Ok(())
     }
 
     fn disconnect(&mut self, connection_id: ConnectionId) {
-        if self.sessions.remove(&connection_id).is_some() {
-            for (_, connection_ids) in &mut self.subscribers {
+        self.sessions.remove(&connection_id);
+        self.user_sessions.remove(&connection_id);
+        self.expiring_sessions.remove(&connection_id);
+        if let Some(target) = self.connection_targets.remove(&connection_id) {
+            if let Some(connection_ids) = self.subscribers.get_mut(&target) {
                 connection_ids.remove(&connection_id);
-                break;
+                if connection_ids.is_empty() {
+                    self.subscribers.remove(&target);
+                }
             }
         }
     }
 
-    async fn broadcast(&self, message: Message) -> Result<()> {
-        if let Some(connection_ids) = self.subscribers.get(&message.target) {
-            for connection_id in connection_ids {
-                if let Some(user_session) = self.user_sessions.get(connection_id) {
-                    if let UserSession::Token(token_id, user_id) = user_session {
-                        if message.created_by.is_none() {
-                            continue;
-                        }
-                        if let Target::Collection(collection_id) = &message.target {
-                            let token_data = TokenDao::db_select(self.ctx.db(), token_id).await?;
-                            if let Some(permission) = token_data
-                                .is_allow_find_many_records(self.ctx.db(), collection_id)
-                                .await
-                            {
-                                if permission == CollectionPermission::None {
-                                    continue;
-                                }
-                                if permission == CollectionPermission::SelfMade
-                                    && &message.created_by != user_id
-                                {
-                                    continue;
-                                }
-                            } else {
-                                continue;
+    // Swaps the JWT of the session for a newer one and tells the client whether it was accepted
+    fn authenticate(&mut self, connection_id: &ConnectionId, token: &str) {
+        let target = match self.connection_targets.get(connection_id) {
+            Some(target) => target.clone(),
+            None => return,
+        };
+        let user_session = match self.user_sessions.get_mut(connection_id) {
+            Some(user_session) => user_session,
+            None => return,
+        };
+
+        let result = match self.ctx.jwt().decode(token) {
+            Ok(claim) => {
+                if user_session.renew(&claim) {
+                    Ok(*user_session.expires_at())
+                } else {
+                    Err("Token belongs to a different session".to_owned())
+                }
+            }
+            Err(err) => Err(err.to_string()),
+        };
+
+        let seq = self.sequences.get(&target).copied().unwrap_or(0);
+        let message = match result {
+            Ok(expires_at) => {
+                self.expiring_sessions.remove(connection_id);
+                Message::notice(
+                    &target,
+                    &seq,
+                    MessageKind::AuthSuccess,
+                    serde_json::json!({ "expires_at": expires_at }),
+                )
+            }
+            Err(err) => Message::notice(
+                &target,
+                &seq,
+                MessageKind::AuthFailure,
+                serde_json::json!({ "error": err }),
+            ),
+        };
+        self.try_send(connection_id, message);
+    }
+
+    // Warns every session once when its JWT is about to expire, so the client has time to send a
+    // newer one before it stops getting messages
+    fn warn_expiring_sessions(&mut self) {
+        let connection_ids: Vec<_> = self
+            .user_sessions
+            .iter()
+            .filter(|(connection_id, user_session)| {
+                user_session.seconds_left() <= TOKEN_EXPIRING_WARNING_SECS
+                    && !self.expiring_sessions.contains(*connection_id)
+            })
+            .map(|(connection_id, user_session)| (*connection_id, *user_session.expires_at()))
+            .collect();
+
+        for (connection_id, expires_at) in connection_ids {
+            let target = match self.connection_targets.get(&connection_id) {
+                Some(target) => target.clone(),
+                None => continue,
+            };
+            let seq = self.sequences.get(&target).copied().unwrap_or(0);
+            self.expiring_sessions.insert(connection_id);
+            self.try_send(
+                &connection_id,
+                Message::notice(
+                    &target,
+                    &seq,
+                    MessageKind::TokenExpiring,
+                    serde_json::json!({ "expires_at": expires_at }),
+                ),
+            );
+        }
+    }
+
+    async fn broadcast(&mut self, mut message: Message) -> Result<()> {
+        if let MessageKind::InvalidateToken(token_id) = message.kind() {
+            let token_id = *token_id;
+            self.permission_cache.retain(|(id, _), _| id != &token_id);
+            return Ok(());
+        }
+
+        let seq = self.sequences.entry(message.target.clone()).or_default();
+        *seq += 1;
+        message.set_seq(*seq);
+
+        // Every record change passes through here whichever API made it, so this is also where
+        // the webhooks of its collection are fired
+        if let (Target::Collection(collection_id), Some(webhook), Some(event)) = (
+            &message.target,
+            self.ctx.webhook(),
+            message.kind().to_webhook_event(),
+        ) {
+            if let Err(err) =
+                webhook.try_send(WebhookPayload::new(collection_id, &event, message.data()))
+            {
+                hb_log::error(
+                    None,
+                    &format!("[ApiWebSocketServer] Error when sending data to webhook: {err}"),
+                );
+            }
+        }
+
+        if let (Target::Collection(collection_id), Some(mqtt)) = (&message.target, self.ctx.mqtt())
+        {
+            if matches!(
+                message.kind(),
+                MessageKind::InsertOne | MessageKind::UpdateOne | MessageKind::DeleteOne
+            ) {
+                match CollectionDao::db_select(self.ctx.db(), collection_id).await {
+                    Ok(collection_data) => {
+                        if *collection_data.opt_mqtt_publish() {
+                            if let Err(err) = mqtt.try_send(RecordEvent::new(
+                                collection_data.project_id(),
+                                collection_id,
+                                message.kind(),
+                                message.data(),
+                            )) {
+                                hb_log::error(
+                                    None,
+                                    &format!(
+                                        "[ApiWebSocketServer] Error when sending data to MQTT publisher: {err}"
+                                    ),
+                                );
                             }
-                        } else {
-                            continue;
                         }
                     }
+                    Err(err) => hb_log::error(
+                        None,
+                        &format!(
+                            "[ApiWebSocketServer] Error when getting collection '{collection_id}' for MQTT publisher: {err}"
+                        ),
+                    ),
+                }
+            }
+        }
+
+        if self.replay_buffer_size > 0 {
+            let history = self.history.entry(message.target.clone()).or_default();
+            if history.len() >= self.replay_buffer_size {
+                history.pop_front();
+            }
+            history.push_back(message.clone());
+        }
+
+        let connection_ids: Vec<_> = match self.subscribers.get(&message.target) {
+            Some(connection_ids) => connection_ids.iter().copied().collect(),
+            None => return Ok(()),
+        };
+        for connection_id in &connection_ids {
+            self.send(connection_id, &message).await?;
+        }
 
-                    if let Some(connection_tx) = self.sessions.get(connection_id) {
-                        let _ = connection_tx.send(message.clone());
+        Ok(())
+    }
+
+    // Sends the message to the connection as long as its session is allowed to receive it
+    async fn send(&mut self, connection_id: &ConnectionId, message: &Message) -> Result<()> {
+        // A session whose JWT expired gets nothing until it authenticates again
+        let (token_id, user_id) = match self.user_sessions.get(connection_id) {
+            Some(user_session) if user_session.is_expired() => return Ok(()),
+            Some(user_session) => match user_session.id() {
+                UserSessionId::Admin(_) => (None, None),
+                UserSessionId::Token(token_id, user_id) => (Some(*token_id), *user_id),
+            },
+            None => return Ok(()),
+        };
+
+        let message = if let Some(token_id) = token_id {
+            if message.created_by.is_none()
+                || matches!(message.target, Target::Log | Target::Project(_))
+            {
+                return Ok(());
+            }
+
+            let key = (token_id, message.target.clone());
+            self.cache_permission(&key).await?;
+
+            match self.permission_cache.get(&key) {
+                Some((_, CachedPermission::Collection(collection_rule_data))) => {
+                    let permission = *collection_rule_data.find_many();
+                    if permission == CollectionPermission::None {
+                        return Ok(());
+                    }
+                    if permission == CollectionPermission::SelfMade && message.created_by != user_id
+                    {
+                        return Ok(());
                     }
+                    // Fields the token can't read are left out of its own copy
+                    let mut message = message.clone();
+                    if collection_rule_data.readable_fields().is_some() {
+                        message.retain_data_fields(|field| {
+                            collection_rule_data.is_readable_field(field)
+                        });
+                    }
+                    message
                 }
+                Some((_, CachedPermission::Bucket(permission))) => {
+                    if *permission == BucketPermission::None {
+                        return Ok(());
+                    }
+                    if *permission == BucketPermission::SelfMade && message.created_by != user_id {
+                        return Ok(());
+                    }
+                    message.clone()
+                }
+                Some((_, CachedPermission::None)) | None => return Ok(()),
             }
+        } else {
+            message.clone()
+        };
+
+        self.try_send(connection_id, message);
+
+        Ok(())
+    }
+
+    // Never waits for a connection to make room, a client that can't keep up with its messages is
+    // disconnected instead of holding up every other subscriber
+    fn try_send(&mut self, connection_id: &ConnectionId, message: Message) {
+        let (connection_tx, evict_token) = match self.sessions.get(connection_id) {
+            Some(session) => session,
+            None => return,
+        };
+        if let Err(mpsc::error::TrySendError::Full(_)) = connection_tx.try_send(message) {
+            evict_token.cancel();
+            self.disconnect(*connection_id);
+            self.slow_consumers += 1;
+            hb_log::warn(
+                None,
+                &format!(
+                    "[ApiWebSocketServer] Disconnected connection '{connection_id}' because it is a slow consumer ({} so far)",
+                    self.slow_consumers
+                ),
+            );
+        }
+    }
+
+    // What the token is allowed to receive from the target is looked up once per TTL instead of on
+    // every message
+    async fn cache_permission(&mut self, key: &(TokenId, Target)) -> Result<()> {
+        let is_cached = self
+            .permission_cache
+            .get(key)
+            .is_some_and(|(cached_at, _)| cached_at.elapsed() < PERMISSION_CACHE_TTL);
+        if is_cached {
+            return Ok(());
         }
 
+        let permission = Self::select_permission(&self.ctx, &key.0, &key.1).await?;
+        let now = Instant::now();
+        self.permission_cache
+            .retain(|_, (cached_at, _)| now.duration_since(*cached_at) < PERMISSION_CACHE_TTL);
+        self.permission_cache.insert(key.clone(), (now, permission));
+
         Ok(())
     }
+
+    async fn select_permission(
+        ctx: &ApiWebSocketCtx,
+        token_id: &TokenId,
+        target: &Target,
+    ) -> Result<CachedPermission> {
+        let token_data = match TokenDao::db_select_unexpired(ctx.db(), token_id).await {
+            Ok(data) => data,
+            Err(err) if DaoError::is_expired(&err) => return Ok(CachedPermission::None),
+            Err(err) => return Err(err),
+        };
+        Ok(match target {
+            Target::Collection(collection_id) => {
+                match token_data.collection_rule(ctx.db(), collection_id).await {
+                    Some(collection_rule_data) => {
+                        CachedPermission::Collection(collection_rule_data)
+                    }
+                    None => CachedPermission::None,
+                }
+            }
+            Target::Bucket(bucket_id) => {
+                match token_data
+                    .is_allow_find_many_files(ctx.db(), bucket_id)
+                    .await
+                {
+                    Some(permission) => CachedPermission::Bucket(permission),
+                    None => CachedPermission::None,
+                }
+            }
+            Target::Log | Target::Project(_) => CachedPermission::None,
+        })
+    }
+}
+
+enum CachedPermission {
+    Collection(CollectionRuleDao),
+    Bucket(BucketPermission),
+    None,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
+
+    use hb_dao::Db;
+    use hb_db_sqlite::db::SqliteDb;
+    use hb_token_jwt::token::JwtToken;
+    use session::UserSessionId;
+
+    use super::*;
+
+    const RECORDS: usize = 20;
+    const MESSAGES: usize = RECORDS * 3;
+
+    // Admin sessions on a collection without MQTT publishing never reach the database, it's only
+    // there because the context needs one
+    async fn server() -> (
+        ApiWebSocketServer,
+        WebSocketConnection,
+        WebSocketBroadcaster,
+        PathBuf,
+    ) {
+        let path = env::temp_dir().join(format!("hb-websocket-test-{}.db", Uuid::now_v7()));
+        let db = SqliteDb::new(
+            &path.to_string_lossy(),
+            &1,
+            &None,
+            &Some(Duration::from_secs(5)),
+            &None,
+            &None,
+            &None,
+            &None,
+            &None,
+            &None,
+            &None,
+            &None,
+            &0,
+            &0,
+            &0,
+            &0,
+        )
+        .await;
+        let ctx = ApiWebSocketCtx::new(
+            Arc::new(Db::SqliteDb(db)),
+            Arc::new(JwtToken::new("secret", &3600)),
+            None,
+            None,
+        );
+        let (mut server, _, broadcaster) = ApiWebSocketServer::new(
+            ctx,
+            &Duration::from_secs(5),
+            &Duration::from_secs(10),
+            &MESSAGES,
+            &0,
+            &(MESSAGES + 1),
+        );
+
+        // Connections are handed straight to the server instead of going through a websocket
+        let (connection_tx, connection_rx) = mpsc::unbounded_channel();
+        server.connection_rx = connection_rx;
+
+        (
+            server,
+            WebSocketConnection::new(connection_tx),
+            broadcaster,
+            path,
+        )
+    }
+
+    fn remove_sqlite_db(path: &Path) {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = path.as_os_str().to_owned();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    fn admin_session() -> UserSession {
+        UserSession::new(UserSessionId::Admin(Uuid::now_v7()), &usize::MAX)
+    }
+
+    fn record_message(target: &Target, kind: MessageKind, record_id: &Uuid) -> Message {
+        Message::new(
+            target.clone(),
+            Some(Uuid::now_v7()),
+            kind,
+            serde_json::json!({ "_id": record_id }),
+        )
+    }
+
+    #[tokio::test]
+    async fn changes_of_a_record_arrive_in_order() {
+        let (server, connection, broadcaster, path) = server().await;
+        let cancel_token = CancellationToken::new();
+        let server = server.run(cancel_token.clone());
+
+        // Messages broadcast before the server handles the connection are replayed to it from the
+        // start, so the connection sees every one of them whichever comes first
+        let target = Target::Collection(Uuid::now_v7());
+        let (connection_tx, mut connection_rx) = mpsc::channel(MESSAGES + 1);
+        connection
+            .connect(
+                admin_session(),
+                target.clone(),
+                Uuid::now_v7(),
+                connection_tx,
+                CancellationToken::new(),
+                Some(0),
+                false,
+            )
+            .unwrap();
+
+        // Every record is inserted, updated and deleted in a row, while the records race each
+        // other like concurrent requests do
+        let mut tasks = Vec::with_capacity(RECORDS);
+        for _ in 0..RECORDS {
+            let broadcaster = broadcaster.clone();
+            let target = target.clone();
+            tasks.push(tokio::spawn(async move {
+                let record_id = Uuid::now_v7();
+                for kind in [
+                    MessageKind::InsertOne,
+                    MessageKind::UpdateOne,
+                    MessageKind::DeleteOne,
+                ] {
+                    broadcaster
+                        .broadcast(record_message(&target, kind, &record_id))
+                        .unwrap();
+                    tokio::task::yield_now().await;
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let mut record_kinds: HashMap<String, Vec<serde_json::Value>> = HashMap::new();
+        for seq in 1..=MESSAGES as u64 {
+            let message = tokio::time::timeout(Duration::from_secs(5), connection_rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(*message.seq(), seq);
+            record_kinds
+                .entry(message.data()["_id"].as_str().unwrap().to_owned())
+                .or_default()
+                .push(serde_json::to_value(message.kind()).unwrap());
+        }
+        assert_eq!(record_kinds.len(), RECORDS);
+        for kinds in record_kinds.values() {
+            assert_eq!(kinds, &["insert_one", "update_one", "delete_one"]);
+        }
+
+        cancel_token.cancel();
+        server.await.unwrap().unwrap();
+        remove_sqlite_db(&path);
+    }
+
+    #[tokio::test]
+    async fn disconnected_connection_is_no_longer_sent_to() {
+        let (mut server, _, _, path) = server().await;
+        let target = Target::Collection(Uuid::now_v7());
+        let record_id = Uuid::now_v7();
+        let connection_id = Uuid::now_v7();
+        let (connection_tx, mut connection_rx) = mpsc::channel(8);
+        server
+            .insert_connection(
+                admin_session(),
+                target.clone(),
+                connection_id,
+                connection_tx,
+                CancellationToken::new(),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+
+        server
+            .broadcast(record_message(&target, MessageKind::InsertOne, &record_id))
+            .await
+            .unwrap();
+        assert!(connection_rx.try_recv().is_ok());
+
+        server.disconnect(connection_id);
+        assert!(!server.subscribers.contains_key(&target));
+        assert!(!server.connection_targets.contains_key(&connection_id));
+        assert!(!server.sessions.contains_key(&connection_id));
+        assert!(!server.user_sessions.contains_key(&connection_id));
+
+        // The sender went away with the connection, so nothing is left to deliver through
+        server
+            .broadcast(record_message(&target, MessageKind::UpdateOne, &record_id))
+            .await
+            .unwrap();
+        assert!(matches!(
+            connection_rx.try_recv(),
+            Err(mpsc::error::TryRecvError::Disconnected)
+        ));
+        remove_sqlite_db(&path);
+    }
 }
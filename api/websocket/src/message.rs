@@ -1,7 +1,9 @@
-use serde::Serialize;
+use anyhow::{Error, Result};
+use hb_dao::webhook::WebhookEvent;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::UserId;
+use crate::{TokenId, UserId};
 
 #[derive(Serialize, Clone)]
 pub struct Message {
@@ -10,6 +12,9 @@ pub struct Message {
     #[serde(skip_serializing)]
     pub created_by: Option<UserId>,
 
+    // Assigned by the server in the order messages of a target are broadcast, subscribers can see
+    // gaps when they aren't allowed to read some of the records
+    seq: u64,
     kind: MessageKind,
     data: serde_json::Value,
 }
@@ -24,16 +29,83 @@ impl Message {
         Self {
             target,
             created_by,
+            seq: 0,
             kind,
             data,
         }
     }
+
+    // Tells the server to drop the permissions it cached for the token, sent whenever the token
+    // or one of its rules changes. It never reaches the subscribers, the target is left unused.
+    pub fn invalidate_token(token_id: &TokenId) -> Self {
+        Self::new(
+            Target::Log,
+            None,
+            MessageKind::InvalidateToken(*token_id),
+            serde_json::Value::Null,
+        )
+    }
+
+    // Sent in place of a replay when the messages after the sequence the client asked for are no
+    // longer kept, so it knows to refetch through the REST API. Carries the latest sequence of the
+    // target to resume from.
+    pub(crate) fn resync_required(target: &Target, seq: &u64) -> Self {
+        Self {
+            target: target.clone(),
+            created_by: None,
+            seq: *seq,
+            kind: MessageKind::ResyncRequired,
+            data: serde_json::Value::Null,
+        }
+    }
+
+    // Sent to a single connection about its own session rather than a change of the target,
+    // carries the latest sequence of the target so it doesn't disturb the client's position
+    pub(crate) fn notice(
+        target: &Target,
+        seq: &u64,
+        kind: MessageKind,
+        data: serde_json::Value,
+    ) -> Self {
+        Self {
+            target: target.clone(),
+            created_by: None,
+            seq: *seq,
+            kind,
+            data,
+        }
+    }
+
+    pub(crate) fn seq(&self) -> &u64 {
+        &self.seq
+    }
+
+    pub(crate) fn set_seq(&mut self, seq: u64) {
+        self.seq = seq;
+    }
+
+    pub(crate) fn kind(&self) -> &MessageKind {
+        &self.kind
+    }
+
+    pub(crate) fn data(&self) -> &serde_json::Value {
+        &self.data
+    }
+
+    pub(crate) fn retain_data_fields(&mut self, f: impl Fn(&str) -> bool) {
+        if let serde_json::Value::Object(data) = &mut self.data {
+            data.retain(|field, _| f(field));
+        }
+    }
 }
 
 #[derive(Eq, Hash, PartialEq, Clone)]
 pub enum Target {
     Collection(Uuid),
+    Bucket(Uuid),
     Log,
+    // Changes to the collections, buckets, tokens and rules of a project, only for its admins
+    Project(Uuid),
 }
 
 #[derive(Serialize, Clone)]
@@ -42,4 +114,174 @@ pub enum MessageKind {
     InsertOne,
     UpdateOne,
     DeleteOne,
+    ResyncRequired,
+    Snapshot,
+    SnapshotComplete,
+    AuthSuccess,
+    AuthFailure,
+    TokenExpiring,
+    #[serde(skip_serializing)]
+    InvalidateToken(TokenId),
+}
+
+impl MessageKind {
+    pub(crate) fn to_webhook_event(&self) -> Option<WebhookEvent> {
+        match self {
+            Self::InsertOne => Some(WebhookEvent::InsertOne),
+            Self::UpdateOne => Some(WebhookEvent::UpdateOne),
+            Self::DeleteOne => Some(WebhookEvent::DeleteOne),
+            Self::ResyncRequired
+            | Self::Snapshot
+            | Self::SnapshotComplete
+            | Self::AuthSuccess
+            | Self::AuthFailure
+            | Self::TokenExpiring
+            | Self::InvalidateToken(_) => None,
+        }
+    }
+}
+
+// A record change of a collection that opted in to MQTT publishing, handed to the MQTT client
+#[derive(Serialize)]
+pub struct RecordEvent {
+    project_id: Uuid,
+    collection_id: Uuid,
+    kind: MessageKind,
+    data: serde_json::Value,
+}
+
+impl RecordEvent {
+    pub(crate) fn new(
+        project_id: &Uuid,
+        collection_id: &Uuid,
+        kind: &MessageKind,
+        data: &serde_json::Value,
+    ) -> Self {
+        Self {
+            project_id: *project_id,
+            collection_id: *collection_id,
+            kind: kind.clone(),
+            data: data.clone(),
+        }
+    }
+
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+}
+
+// Encoding of the messages sent to a connection, picked when it subscribes
+#[derive(Clone, Copy)]
+pub enum MessageFormat {
+    Json,
+    MessagePack,
+}
+
+impl MessageFormat {
+    pub fn from_str(str: &str) -> Result<Self> {
+        match str {
+            "json" => Ok(Self::Json),
+            "msgpack" => Ok(Self::MessagePack),
+            _ => Err(Error::msg(format!("Unknown message format '{str}'"))),
+        }
+    }
+}
+
+// Control messages a client sends over its connection, in the format it subscribed with
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub(crate) enum ClientMessage {
+    // Replaces the JWT the connection was opened with before it expires
+    Auth { token: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use hb_dao::value::{ColumnKind, ColumnValue};
+    use serde_json::json;
+
+    use super::*;
+
+    // A value in every JSON form a record field can take, each sent as the data of a message
+    fn record_values() -> Vec<(&'static str, serde_json::Value)> {
+        vec![
+            ("boolean", json!(true)),
+            ("tinyint", json!(i8::MIN)),
+            ("smallint", json!(i16::MAX)),
+            ("int", json!(-42)),
+            ("bigint", json!(i64::MIN)),
+            ("bigint", json!(i64::MAX)),
+            ("varint", json!("123456789012345678901234567890")),
+            ("float", json!(1.5)),
+            ("double", json!(-1.0e308)),
+            ("double", json!(0.1)),
+            ("decimal", json!("12345.6789")),
+            ("string", json!("héllo 👋")),
+            ("string", json!("")),
+            ("binary", json!([0, 1, 254, 255])),
+            ("uuid", json!("0190c1f4-8b8e-7b3a-9c2d-3e4f5a6b7c8d")),
+            ("date", json!("2024-02-29")),
+            ("time", json!("23:59:59.123456")),
+            ("timestamp", json!("2024-02-29T23:59:59.123456Z")),
+            (
+                "json",
+                json!({ "a": [1, "b", null, { "c": 2.5 }], "d": true }),
+            ),
+            ("json", json!([])),
+            ("string_array", json!(["a", "b"])),
+            ("int_array", json!([i32::MIN, 0, i32::MAX])),
+            ("counter", json!(7)),
+            ("point", json!({ "lat": -6.9147, "lng": 107.6098 })),
+            ("string", serde_json::Value::Null),
+            ("counter", serde_json::Value::Null),
+        ]
+    }
+
+    #[test]
+    fn msgpack_carries_the_same_data_as_json() {
+        for (kind, value) in record_values() {
+            let column_value =
+                ColumnValue::from_serde_json(&ColumnKind::from_str(kind).unwrap(), &value).unwrap();
+            let msg = Message::new(
+                Target::Collection(Uuid::now_v7()),
+                None,
+                MessageKind::InsertOne,
+                json!({ "field": column_value.to_serde_json().unwrap() }),
+            );
+
+            let encoded = rmp_serde::to_vec_named(&msg).unwrap();
+            let decoded: serde_json::Value = rmp_serde::from_slice(&encoded).unwrap();
+            assert_eq!(
+                decoded,
+                serde_json::to_value(&msg).unwrap(),
+                "{kind}: {value}"
+            );
+        }
+    }
+
+    #[test]
+    fn msgpack_is_keyed_like_json() {
+        let mut msg = Message::new(
+            Target::Log,
+            None,
+            MessageKind::UpdateOne,
+            json!({ "_id": "0190c1f4-8b8e-7b3a-9c2d-3e4f5a6b7c8d" }),
+        );
+        msg.set_seq(3);
+
+        let decoded: serde_json::Value =
+            rmp_serde::from_slice(&rmp_serde::to_vec_named(&msg).unwrap()).unwrap();
+        assert_eq!(
+            decoded,
+            json!({
+                "seq": 3,
+                "kind": "update_one",
+                "data": { "_id": "0190c1f4-8b8e-7b3a-9c2d-3e4f5a6b7c8d" },
+            })
+        );
+    }
 }
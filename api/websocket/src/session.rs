@@ -1,6 +1,61 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hb_token_jwt::claim::{Claim, ClaimId};
+
 use crate::{AdminId, TokenId, UserId};
 
-pub enum UserSession {
+pub struct UserSession {
+    id: UserSessionId,
+    // Unix timestamp of when the JWT the session was authenticated with expires
+    expires_at: usize,
+}
+
+impl UserSession {
+    pub fn new(id: UserSessionId, expires_at: &usize) -> Self {
+        Self {
+            id,
+            expires_at: *expires_at,
+        }
+    }
+
+    pub fn id(&self) -> &UserSessionId {
+        &self.id
+    }
+
+    pub fn expires_at(&self) -> &usize {
+        &self.expires_at
+    }
+
+    pub(crate) fn seconds_left(&self) -> i64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .unwrap_or(0);
+        i64::try_from(self.expires_at).unwrap_or(i64::MAX) - i64::try_from(now).unwrap_or(0)
+    }
+
+    pub(crate) fn is_expired(&self) -> bool {
+        self.seconds_left() <= 0
+    }
+
+    // Takes the expiry of a newer JWT, which has to belong to the same admin or token (and user)
+    // the session was opened with
+    pub(crate) fn renew(&mut self, claim: &Claim) -> bool {
+        let is_same = match (&self.id, claim.id()) {
+            (UserSessionId::Admin(admin_id), ClaimId::Admin(id)) => admin_id == id,
+            (UserSessionId::Token(token_id, user_id), ClaimId::Token(id, user_claim)) => {
+                token_id == id && *user_id == user_claim.map(|user_claim| *user_claim.id())
+            }
+            _ => false,
+        };
+        if is_same {
+            self.expires_at = *claim.exp();
+        }
+        is_same
+    }
+}
+
+pub enum UserSessionId {
     Admin(AdminId),
     Token(TokenId, Option<UserId>),
 }
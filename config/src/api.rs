@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use duration_str::deserialize_duration;
+use duration_str::{deserialize_duration, deserialize_option_duration};
 use serde::Deserialize;
 
 #[derive(Deserialize)]
@@ -29,6 +29,11 @@ pub struct ApiRestConfig {
     host: String,
     port: u16,
     allowed_origin: Option<String>,
+    trusted_proxies: Option<Vec<String>>,
+    import_batch_size: Option<usize>,
+    idempotency_ttl: Option<u32>,
+    // Serves HTTPS instead of plain HTTP when set
+    tls: Option<ApiRestTlsConfig>,
 }
 
 impl ApiRestConfig {
@@ -43,6 +48,52 @@ impl ApiRestConfig {
     pub fn allowed_origin(&self) -> &Option<String> {
         &self.allowed_origin
     }
+
+    pub fn trusted_proxies(&self) -> &Option<Vec<String>> {
+        &self.trusted_proxies
+    }
+
+    pub fn import_batch_size(&self) -> &Option<usize> {
+        &self.import_batch_size
+    }
+
+    pub fn idempotency_ttl(&self) -> &Option<u32> {
+        &self.idempotency_ttl
+    }
+
+    pub fn tls(&self) -> &Option<ApiRestTlsConfig> {
+        &self.tls
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ApiRestTlsConfig {
+    // PEM files, the certificate one holding the whole chain
+    cert_path: String,
+    key_path: String,
+    // Requires clients to present a certificate signed by one of these CAs
+    client_ca_path: Option<String>,
+    // How often the certificate and key files are checked for changes
+    #[serde(default, deserialize_with = "deserialize_option_duration")]
+    watch_interval: Option<Duration>,
+}
+
+impl ApiRestTlsConfig {
+    pub fn cert_path(&self) -> &str {
+        &self.cert_path
+    }
+
+    pub fn key_path(&self) -> &str {
+        &self.key_path
+    }
+
+    pub fn client_ca_path(&self) -> &Option<String> {
+        &self.client_ca_path
+    }
+
+    pub fn watch_interval(&self) -> &Option<Duration> {
+        &self.watch_interval
+    }
 }
 
 #[derive(Deserialize)]
@@ -51,6 +102,10 @@ pub struct ApiWebSocketConfig {
     heartbeat_interval: Duration,
     #[serde(deserialize_with = "deserialize_duration")]
     client_timeout: Duration,
+    check_origin: Option<bool>,
+    replay_buffer_size: Option<usize>,
+    snapshot_max_records: Option<usize>,
+    send_buffer_size: Option<usize>,
 }
 
 impl ApiWebSocketConfig {
@@ -61,18 +116,44 @@ impl ApiWebSocketConfig {
     pub fn client_timeout(&self) -> &Duration {
         &self.client_timeout
     }
+
+    pub fn check_origin(&self) -> &Option<bool> {
+        &self.check_origin
+    }
+
+    pub fn replay_buffer_size(&self) -> &Option<usize> {
+        &self.replay_buffer_size
+    }
+
+    pub fn snapshot_max_records(&self) -> &Option<usize> {
+        &self.snapshot_max_records
+    }
+
+    pub fn send_buffer_size(&self) -> &Option<usize> {
+        &self.send_buffer_size
+    }
 }
 
 #[derive(Deserialize)]
 pub struct ApiMqttConfig {
     host: String,
     port: u16,
+    // Can hold {project_id} and {collection_id} levels, which are then read from the topic
     topic: String,
+    // Subscribes through an MQTT 5 shared subscription so the nodes in the group split the load
+    shared_subscription_group: Option<String>,
     username: String,
     password: String,
     channel_capacity: usize,
     #[serde(deserialize_with = "deserialize_duration")]
     timeout: Duration,
+    // Failed connection attempts in a row before giving up, retries forever when left out
+    reconnect_max_retries: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_option_duration")]
+    reconnect_max_interval: Option<Duration>,
+    // Most records a single payload can carry in its data array
+    max_batch_items: Option<usize>,
+    publish: Option<ApiMqttPublishConfig>,
 }
 
 impl ApiMqttConfig {
@@ -88,6 +169,10 @@ impl ApiMqttConfig {
         &self.topic
     }
 
+    pub fn shared_subscription_group(&self) -> &Option<String> {
+        &self.shared_subscription_group
+    }
+
     pub fn username(&self) -> &str {
         &self.username
     }
@@ -103,4 +188,36 @@ impl ApiMqttConfig {
     pub fn timeout(&self) -> &Duration {
         &self.timeout
     }
+
+    pub fn reconnect_max_retries(&self) -> &Option<u32> {
+        &self.reconnect_max_retries
+    }
+
+    pub fn reconnect_max_interval(&self) -> &Option<Duration> {
+        &self.reconnect_max_interval
+    }
+
+    pub fn max_batch_items(&self) -> &Option<usize> {
+        &self.max_batch_items
+    }
+
+    pub fn publish(&self) -> &Option<ApiMqttPublishConfig> {
+        &self.publish
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ApiMqttPublishConfig {
+    topic_prefix: Option<String>,
+    qos: Option<u8>,
+}
+
+impl ApiMqttPublishConfig {
+    pub fn topic_prefix(&self) -> &Option<String> {
+        &self.topic_prefix
+    }
+
+    pub fn qos(&self) -> &Option<u8> {
+        &self.qos
+    }
 }
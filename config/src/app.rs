@@ -3,12 +3,19 @@ use serde::Deserialize;
 #[derive(Deserialize)]
 pub struct AppConfig {
     mode: AppConfigMode,
+    // Rejects every write made through the REST API and MQTT, reads and websocket subscriptions
+    // keep working
+    read_only: Option<bool>,
 }
 
 impl AppConfig {
     pub fn mode(&self) -> &AppConfigMode {
         &self.mode
     }
+
+    pub fn read_only(&self) -> &Option<bool> {
+        &self.read_only
+    }
 }
 
 #[derive(Deserialize, Clone, Copy)]
@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use uuid::Uuid;
 
 #[derive(Deserialize)]
 pub struct AuthConfig {
@@ -6,6 +7,12 @@ pub struct AuthConfig {
     access_token_length: usize,
     registration_ttl: u32,
     reset_password_ttl: u32,
+    sign_in_max_attempts: Option<u32>,
+    sign_in_lockout_duration: Option<u32>,
+    password_policy: Option<PasswordPolicyConfig>,
+    // Admins allowed to run server-wide operations like backups and config reloads. Anyone can
+    // register as an admin, so being one isn't enough for those.
+    operator_admin_ids: Option<Vec<Uuid>>,
 }
 
 impl AuthConfig {
@@ -24,4 +31,51 @@ impl AuthConfig {
     pub fn reset_password_ttl(&self) -> &u32 {
         &self.reset_password_ttl
     }
+
+    pub fn sign_in_max_attempts(&self) -> &Option<u32> {
+        &self.sign_in_max_attempts
+    }
+
+    pub fn sign_in_lockout_duration(&self) -> &Option<u32> {
+        &self.sign_in_lockout_duration
+    }
+
+    pub fn password_policy(&self) -> &Option<PasswordPolicyConfig> {
+        &self.password_policy
+    }
+
+    pub fn operator_admin_ids(&self) -> &Option<Vec<Uuid>> {
+        &self.operator_admin_ids
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PasswordPolicyConfig {
+    min_length: Option<usize>,
+    require_lowercase: Option<bool>,
+    require_uppercase: Option<bool>,
+    require_digit: Option<bool>,
+    require_symbol: Option<bool>,
+}
+
+impl PasswordPolicyConfig {
+    pub fn min_length(&self) -> &Option<usize> {
+        &self.min_length
+    }
+
+    pub fn require_lowercase(&self) -> &Option<bool> {
+        &self.require_lowercase
+    }
+
+    pub fn require_uppercase(&self) -> &Option<bool> {
+        &self.require_uppercase
+    }
+
+    pub fn require_digit(&self) -> &Option<bool> {
+        &self.require_digit
+    }
+
+    pub fn require_symbol(&self) -> &Option<bool> {
+        &self.require_symbol
+    }
 }
@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+use duration_str::deserialize_duration;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct BackupConfig {
+    #[serde(deserialize_with = "deserialize_duration")]
+    interval: Duration,
+    path: String,
+    retention: usize,
+    notify_email: Option<String>,
+}
+
+impl BackupConfig {
+    pub fn interval(&self) -> &Duration {
+        &self.interval
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn retention(&self) -> &usize {
+        &self.retention
+    }
+
+    pub fn notify_email(&self) -> &Option<String> {
+        &self.notify_email
+    }
+}
@@ -3,10 +3,25 @@ use serde::Deserialize;
 #[derive(Deserialize)]
 pub struct BucketConfig {
     path: String,
+    upload_session_ttl: Option<u32>,
+    presign_max_expiry: Option<u32>,
+    image_resize_max_dimension: Option<u32>,
 }
 
 impl BucketConfig {
     pub fn path(&self) -> &str {
         &self.path
     }
+
+    pub fn upload_session_ttl(&self) -> &Option<u32> {
+        &self.upload_session_ttl
+    }
+
+    pub fn presign_max_expiry(&self) -> &Option<u32> {
+        &self.presign_max_expiry
+    }
+
+    pub fn image_resize_max_dimension(&self) -> &Option<u32> {
+        &self.image_resize_max_dimension
+    }
 }
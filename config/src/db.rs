@@ -1,14 +1,17 @@
 use serde::Deserialize;
 
 use self::{
-    mysql::DbMysqlConfig, postgres::DbPostgresConfig, scylla::DbScyllaConfig,
-    sqlite::DbSqliteConfig,
+    cache::DbCacheConfig, mysql::DbMysqlConfig, option::DbOptionConfig, postgres::DbPostgresConfig,
+    scylla::DbScyllaConfig, sqlite::DbSqliteConfig, warmup::DbWarmupConfig,
 };
 
+pub mod cache;
 pub mod mysql;
+pub mod option;
 pub mod postgres;
 pub mod scylla;
 pub mod sqlite;
+pub mod warmup;
 
 #[derive(Deserialize)]
 pub struct DbConfig {
@@ -16,6 +19,11 @@ pub struct DbConfig {
     postgres: Option<DbPostgresConfig>,
     mysql: Option<DbMysqlConfig>,
     sqlite: Option<DbSqliteConfig>,
+    warmup: Option<DbWarmupConfig>,
+    option: Option<DbOptionConfig>,
+    cache: Option<DbCacheConfig>,
+    statement_timeout_ms: Option<u64>,
+    slow_query_threshold_ms: Option<u64>,
 }
 
 impl DbConfig {
@@ -34,4 +42,24 @@ impl DbConfig {
     pub fn sqlite(&self) -> &Option<DbSqliteConfig> {
         &self.sqlite
     }
+
+    pub fn warmup(&self) -> &Option<DbWarmupConfig> {
+        &self.warmup
+    }
+
+    pub fn option(&self) -> &Option<DbOptionConfig> {
+        &self.option
+    }
+
+    pub fn cache(&self) -> &Option<DbCacheConfig> {
+        &self.cache
+    }
+
+    pub fn statement_timeout_ms(&self) -> &Option<u64> {
+        &self.statement_timeout_ms
+    }
+
+    pub fn slow_query_threshold_ms(&self) -> &Option<u64> {
+        &self.slow_query_threshold_ms
+    }
 }
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use duration_str::deserialize_option_duration;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct DbCacheConfig {
+    enabled: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_option_duration")]
+    ttl: Option<Duration>,
+    capacity: Option<usize>,
+}
+
+impl DbCacheConfig {
+    pub fn enabled(&self) -> &Option<bool> {
+        &self.enabled
+    }
+
+    pub fn ttl(&self) -> &Option<Duration> {
+        &self.ttl
+    }
+
+    pub fn capacity(&self) -> &Option<usize> {
+        &self.capacity
+    }
+}
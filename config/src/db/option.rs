@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+use duration_str::deserialize_duration;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct DbOptionConfig {
+    #[serde(deserialize_with = "deserialize_duration")]
+    ttl_sweep_interval: Duration,
+    ttl_sweep_batch_size: i64,
+}
+
+impl DbOptionConfig {
+    pub fn ttl_sweep_interval(&self) -> &Duration {
+        &self.ttl_sweep_interval
+    }
+
+    pub fn ttl_sweep_batch_size(&self) -> &i64 {
+        &self.ttl_sweep_batch_size
+    }
+}
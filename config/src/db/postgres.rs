@@ -1,3 +1,6 @@
+use std::time::Duration;
+
+use duration_str::deserialize_option_duration;
 use serde::Deserialize;
 
 #[derive(Deserialize)]
@@ -8,6 +11,18 @@ pub struct DbPostgresConfig {
     port: String,
     db_name: String,
     max_connections: u32,
+    min_connections: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_option_duration")]
+    acquire_timeout: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_option_duration")]
+    idle_timeout: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_option_duration")]
+    max_lifetime: Option<Duration>,
+    // One of disable, prefer, require, verify-ca or verify-full
+    ssl_mode: Option<String>,
+    ssl_root_cert: Option<String>,
+    ssl_client_cert: Option<String>,
+    ssl_client_key: Option<String>,
 }
 
 impl DbPostgresConfig {
@@ -34,4 +49,36 @@ impl DbPostgresConfig {
     pub fn max_connections(&self) -> &u32 {
         &self.max_connections
     }
+
+    pub fn min_connections(&self) -> &Option<u32> {
+        &self.min_connections
+    }
+
+    pub fn acquire_timeout(&self) -> &Option<Duration> {
+        &self.acquire_timeout
+    }
+
+    pub fn idle_timeout(&self) -> &Option<Duration> {
+        &self.idle_timeout
+    }
+
+    pub fn max_lifetime(&self) -> &Option<Duration> {
+        &self.max_lifetime
+    }
+
+    pub fn ssl_mode(&self) -> &Option<String> {
+        &self.ssl_mode
+    }
+
+    pub fn ssl_root_cert(&self) -> &Option<String> {
+        &self.ssl_root_cert
+    }
+
+    pub fn ssl_client_cert(&self) -> &Option<String> {
+        &self.ssl_client_cert
+    }
+
+    pub fn ssl_client_key(&self) -> &Option<String> {
+        &self.ssl_client_key
+    }
 }
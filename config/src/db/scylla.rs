@@ -1,13 +1,24 @@
+use std::collections::BTreeMap;
+
 use serde::Deserialize;
 
 #[derive(Deserialize)]
 pub struct DbScyllaConfig {
     user: String,
     password: String,
-    host: String,
+    // Either a single host or a list of hosts to use as contact points, sharing the same port
+    host: Option<String>,
+    hosts: Option<Vec<String>>,
     port: String,
+    // Defaults to hyperbase
+    keyspace: Option<String>,
+    // Datacenters left out of datacenter_replication_factors get replication_factor
     replication_factor: i64,
+    datacenter_replication_factors: Option<BTreeMap<String, i64>>,
     prepared_statement_cache_size: usize,
+    // Nodes of this datacenter are queried first when set
+    local_datacenter: Option<String>,
+    consistency: Option<String>,
 }
 
 impl DbScyllaConfig {
@@ -19,19 +30,39 @@ impl DbScyllaConfig {
         &self.password
     }
 
-    pub fn host(&self) -> &str {
+    pub fn host(&self) -> &Option<String> {
         &self.host
     }
 
+    pub fn hosts(&self) -> &Option<Vec<String>> {
+        &self.hosts
+    }
+
     pub fn port(&self) -> &str {
         &self.port
     }
 
+    pub fn keyspace(&self) -> &Option<String> {
+        &self.keyspace
+    }
+
     pub fn replication_factor(&self) -> &i64 {
         &self.replication_factor
     }
 
+    pub fn datacenter_replication_factors(&self) -> &Option<BTreeMap<String, i64>> {
+        &self.datacenter_replication_factors
+    }
+
     pub fn prepared_statement_cache_size(&self) -> &usize {
         &self.prepared_statement_cache_size
     }
+
+    pub fn local_datacenter(&self) -> &Option<String> {
+        &self.local_datacenter
+    }
+
+    pub fn consistency(&self) -> &Option<String> {
+        &self.consistency
+    }
 }
@@ -1,9 +1,25 @@
+use std::time::Duration;
+
+use duration_str::deserialize_option_duration;
 use serde::Deserialize;
 
 #[derive(Deserialize)]
 pub struct DbSqliteConfig {
     path: String,
     max_connections: u32,
+    min_connections: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_option_duration")]
+    acquire_timeout: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_option_duration")]
+    idle_timeout: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_option_duration")]
+    max_lifetime: Option<Duration>,
+    // One of wal, delete, truncate, persist, memory or off
+    journal_mode: Option<String>,
+    busy_timeout_ms: Option<u64>,
+    // One of off, normal, full or extra
+    synchronous: Option<String>,
+    foreign_keys: Option<bool>,
 }
 
 impl DbSqliteConfig {
@@ -14,4 +30,36 @@ impl DbSqliteConfig {
     pub fn max_connections(&self) -> &u32 {
         &self.max_connections
     }
+
+    pub fn min_connections(&self) -> &Option<u32> {
+        &self.min_connections
+    }
+
+    pub fn acquire_timeout(&self) -> &Option<Duration> {
+        &self.acquire_timeout
+    }
+
+    pub fn idle_timeout(&self) -> &Option<Duration> {
+        &self.idle_timeout
+    }
+
+    pub fn max_lifetime(&self) -> &Option<Duration> {
+        &self.max_lifetime
+    }
+
+    pub fn journal_mode(&self) -> &Option<String> {
+        &self.journal_mode
+    }
+
+    pub fn busy_timeout_ms(&self) -> &Option<u64> {
+        &self.busy_timeout_ms
+    }
+
+    pub fn synchronous(&self) -> &Option<String> {
+        &self.synchronous
+    }
+
+    pub fn foreign_keys(&self) -> &Option<bool> {
+        &self.foreign_keys
+    }
 }
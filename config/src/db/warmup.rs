@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+use duration_str::deserialize_duration;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct DbWarmupConfig {
+    prepare_limit: usize,
+    #[serde(deserialize_with = "deserialize_duration")]
+    readiness_timeout: Duration,
+}
+
+impl DbWarmupConfig {
+    pub fn prepare_limit(&self) -> &usize {
+        &self.prepare_limit
+    }
+
+    pub fn readiness_timeout(&self) -> &Duration {
+        &self.readiness_timeout
+    }
+}
@@ -1,3 +1,6 @@
+use std::time::Duration;
+
+use duration_str::deserialize_option_duration;
 use serde::Deserialize;
 
 #[derive(Deserialize)]
@@ -16,6 +19,11 @@ pub struct Argon2HashConfig {
     algorithm: String,
     version: String,
     salt: String,
+    memory_kib: Option<u32>,
+    iterations: Option<u32>,
+    parallelism: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_option_duration")]
+    time_budget: Option<Duration>,
 }
 
 impl Argon2HashConfig {
@@ -30,4 +38,20 @@ impl Argon2HashConfig {
     pub fn salt(&self) -> &str {
         &self.salt
     }
+
+    pub fn memory_kib(&self) -> &Option<u32> {
+        &self.memory_kib
+    }
+
+    pub fn iterations(&self) -> &Option<u32> {
+        &self.iterations
+    }
+
+    pub fn parallelism(&self) -> &Option<u32> {
+        &self.parallelism
+    }
+
+    pub fn time_budget(&self) -> &Option<Duration> {
+        &self.time_budget
+    }
 }
@@ -3,23 +3,31 @@ use std::{fs::File, path::Path};
 use api::ApiConfig;
 use app::AppConfig;
 use auth::AuthConfig;
+use backup::BackupConfig;
 use bucket::BucketConfig;
 use db::DbConfig;
 use hash::HashConfig;
 use log::LogConfig;
 use mailer::MailerConfig;
 use serde::Deserialize;
+use serde_yaml_ng::{Mapping, Value};
+use source::ConfigSource;
 use token::TokenConfig;
+use webhook::WebhookConfig;
 
 pub mod api;
 pub mod app;
 pub mod auth;
+pub mod backup;
 pub mod bucket;
 pub mod db;
 pub mod hash;
 pub mod log;
 pub mod mailer;
+pub mod source;
 pub mod token;
+mod validate;
+pub mod webhook;
 
 #[derive(Deserialize)]
 pub struct Config {
@@ -28,10 +36,16 @@ pub struct Config {
     hash: HashConfig,
     token: TokenConfig,
     mailer: Option<MailerConfig>,
+    webhook: Option<WebhookConfig>,
     db: DbConfig,
     bucket: BucketConfig,
+    backup: Option<BackupConfig>,
     api: ApiConfig,
     auth: AuthConfig,
+    #[serde(skip)]
+    sources: Vec<ConfigSource>,
+    #[serde(skip)]
+    values: Value,
 }
 
 impl Config {
@@ -55,6 +69,10 @@ impl Config {
         &self.mailer
     }
 
+    pub fn webhook(&self) -> &Option<WebhookConfig> {
+        &self.webhook
+    }
+
     pub fn db(&self) -> &DbConfig {
         &self.db
     }
@@ -63,6 +81,10 @@ impl Config {
         &self.bucket
     }
 
+    pub fn backup(&self) -> &Option<BackupConfig> {
+        &self.backup
+    }
+
     pub fn api(&self) -> &ApiConfig {
         &self.api
     }
@@ -70,9 +92,82 @@ impl Config {
     pub fn auth(&self) -> &AuthConfig {
         &self.auth
     }
+
+    pub fn sources(&self) -> &Vec<ConfigSource> {
+        &self.sources
+    }
+
+    // Keys whose values differ from the ones in the other config, including keys only one of
+    // them has
+    pub fn changed_keys(&self, other: &Config) -> Vec<String> {
+        source::changed_keys(&self.values, &other.values)
+    }
 }
 
-pub fn from_path(path: &Path) -> Config {
-    let file = File::open(path).expect("");
-    serde_yaml_ng::from_reader::<_, Config>(file).unwrap()
+// The file is optional when the environment variables give every required key. The merged keys
+// are written back out and read again as YAML text, which lets a value like 5432 fill a string
+// key as well as a number one. Every problem found is returned together rather than only the
+// first one.
+pub fn load(path: &Option<&Path>) -> Result<Config, Vec<String>> {
+    let mut config = match path {
+        Some(path) => {
+            let file = match File::open(path) {
+                Ok(file) => file,
+                Err(err) => {
+                    return Err(Vec::from([format!(
+                        "Failed to open the config file '{}': {err}",
+                        path.display()
+                    )]))
+                }
+            };
+            match serde_yaml_ng::from_reader::<_, Value>(file) {
+                Ok(Value::Null) => Value::Mapping(Mapping::new()),
+                Ok(config) => config,
+                Err(err) => {
+                    return Err(Vec::from([format!(
+                        "Failed to read the config file '{}': {err}",
+                        path.display()
+                    )]))
+                }
+            }
+        }
+        None => Value::Mapping(Mapping::new()),
+    };
+
+    let env_paths = source::apply_env(&mut config, std::env::vars());
+    let mut problems = Vec::new();
+    let file_paths = source::apply_files(&mut config, &env_paths, &mut problems);
+    let sources = source::sources(&config, &env_paths, &file_paths);
+
+    problems.extend(validate::check_sections(&config));
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+
+    let values = config;
+    let mut config = match serde_yaml_ng::to_string(&values)
+        .and_then(|config| serde_yaml_ng::from_str::<Config>(&config))
+    {
+        Ok(config) => config,
+        Err(err) => {
+            // The location is in the merged keys rather than in the file, so only the path of
+            // the key is kept
+            let err = err.to_string();
+            let err = match err.rsplit_once(" at line ") {
+                Some((err, _)) => err.to_owned(),
+                None => err,
+            };
+            problems.push(err);
+            return Err(problems);
+        }
+    };
+
+    problems.extend(validate::check(&config));
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+
+    config.sources = sources;
+    config.values = values;
+    Ok(config)
 }
@@ -5,6 +5,8 @@ pub struct LogConfig {
     display_level: bool,
     level_filter: String,
     db_ttl: u32,
+    // Exports traces when set, nothing is traced otherwise
+    otlp: Option<LogOtlpConfig>,
 }
 
 impl LogConfig {
@@ -19,4 +21,25 @@ impl LogConfig {
     pub fn db_ttl(&self) -> &u32 {
         &self.db_ttl
     }
+
+    pub fn otlp(&self) -> &Option<LogOtlpConfig> {
+        &self.otlp
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LogOtlpConfig {
+    // gRPC endpoint of an OpenTelemetry collector
+    endpoint: String,
+    service_name: Option<String>,
+}
+
+impl LogOtlpConfig {
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn service_name(&self) -> &Option<String> {
+        &self.service_name
+    }
 }
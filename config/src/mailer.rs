@@ -1,24 +1,54 @@
+use std::time::Duration;
+
+use duration_str::deserialize_option_duration;
 use serde::Deserialize;
 
+use self::http::MailerHttpConfig;
+
+pub mod http;
+
+// Emails are sent through the HTTP provider when it's configured, and through SMTP otherwise
 #[derive(Deserialize)]
 pub struct MailerConfig {
-    smtp_host: String,
-    smtp_username: String,
-    smtp_password: String,
+    http: Option<MailerHttpConfig>,
+    smtp_host: Option<String>,
+    smtp_port: Option<u16>,
+    // One of tls, starttls or none
+    smtp_security: Option<String>,
+    // Left out for relays that don't authenticate
+    smtp_username: Option<String>,
+    smtp_password: Option<String>,
     sender_name: String,
     sender_email: String,
+    channel_capacity: Option<usize>,
+    max_retries: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_option_duration")]
+    retry_interval: Option<Duration>,
+    dead_letter_path: Option<String>,
 }
 
 impl MailerConfig {
-    pub fn smtp_host(&self) -> &str {
+    pub fn http(&self) -> &Option<MailerHttpConfig> {
+        &self.http
+    }
+
+    pub fn smtp_host(&self) -> &Option<String> {
         &self.smtp_host
     }
 
-    pub fn smtp_username(&self) -> &str {
+    pub fn smtp_port(&self) -> &Option<u16> {
+        &self.smtp_port
+    }
+
+    pub fn smtp_security(&self) -> &Option<String> {
+        &self.smtp_security
+    }
+
+    pub fn smtp_username(&self) -> &Option<String> {
         &self.smtp_username
     }
 
-    pub fn smtp_password(&self) -> &str {
+    pub fn smtp_password(&self) -> &Option<String> {
         &self.smtp_password
     }
 
@@ -29,4 +59,20 @@ impl MailerConfig {
     pub fn sender_email(&self) -> &str {
         &self.sender_email
     }
+
+    pub fn channel_capacity(&self) -> &Option<usize> {
+        &self.channel_capacity
+    }
+
+    pub fn max_retries(&self) -> &Option<u32> {
+        &self.max_retries
+    }
+
+    pub fn retry_interval(&self) -> &Option<Duration> {
+        &self.retry_interval
+    }
+
+    pub fn dead_letter_path(&self) -> &Option<String> {
+        &self.dead_letter_path
+    }
 }
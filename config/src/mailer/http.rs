@@ -0,0 +1,37 @@
+use std::{collections::HashMap, time::Duration};
+
+use duration_str::deserialize_option_duration;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct MailerHttpConfig {
+    // One of generic, sendgrid or mailgun
+    preset: Option<String>,
+    url: Option<String>,
+    api_key: Option<String>,
+    headers: Option<HashMap<String, String>>,
+    #[serde(default, deserialize_with = "deserialize_option_duration")]
+    timeout: Option<Duration>,
+}
+
+impl MailerHttpConfig {
+    pub fn preset(&self) -> &Option<String> {
+        &self.preset
+    }
+
+    pub fn url(&self) -> &Option<String> {
+        &self.url
+    }
+
+    pub fn api_key(&self) -> &Option<String> {
+        &self.api_key
+    }
+
+    pub fn headers(&self) -> &Option<HashMap<String, String>> {
+        &self.headers
+    }
+
+    pub fn timeout(&self) -> &Option<Duration> {
+        &self.timeout
+    }
+}
@@ -0,0 +1,555 @@
+use std::fs;
+
+use serde_yaml_ng::{Mapping, Value};
+
+// Every config key can be set through an environment variable named after its path, e.g.
+// HB_DB__POSTGRES__PASSWORD for db.postgres.password
+const ENV_PREFIX: &str = "HB_";
+const ENV_SEPARATOR: &str = "__";
+// Already used for the path of the config file itself
+const ENV_IGNORED: [&str; 1] = ["HB_CONFIG_PATH"];
+// Keys whose values are masked when they're reported. The ones ending with any of them, like
+// smtp_password, count as well.
+const SECRET_KEYS: [&str; 4] = ["password", "secret", "salt", "api_key"];
+// Maps whose every value is masked
+const SECRET_MAPS: [&str; 1] = ["headers"];
+const SECRET_MASK: &str = "********";
+// A secret key can be read from a file instead, through the same key with this suffix
+const FILE_SUFFIX: &str = "_file";
+
+pub enum ConfigOrigin {
+    File,
+    Env,
+    SecretFile,
+}
+
+impl ConfigOrigin {
+    pub fn to_str(&self) -> &str {
+        match self {
+            Self::File => "file",
+            Self::Env => "env",
+            Self::SecretFile => "secret file",
+        }
+    }
+}
+
+pub struct ConfigSource {
+    key: String,
+    value: String,
+    origin: ConfigOrigin,
+}
+
+impl ConfigSource {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn origin(&self) -> &ConfigOrigin {
+        &self.origin
+    }
+}
+
+// Sets the environment variables over the keys read from the file and returns the paths they
+// were set on. A variable takes the place of the whole value at its path.
+pub(crate) fn apply_env(
+    config: &mut Value,
+    vars: impl Iterator<Item = (String, String)>,
+) -> Vec<Vec<String>> {
+    let mut env_paths = Vec::new();
+    for (name, var) in vars {
+        if ENV_IGNORED.contains(&name.as_str()) {
+            continue;
+        }
+        let path = match name.strip_prefix(ENV_PREFIX) {
+            Some(path) => path
+                .split(ENV_SEPARATOR)
+                .map(|key| key.to_lowercase())
+                .collect::<Vec<_>>(),
+            None => continue,
+        };
+        if path.iter().any(|key| key.is_empty()) {
+            continue;
+        }
+
+        let mut target = &mut *config;
+        for key in &path {
+            if !target.is_mapping() {
+                *target = Value::Mapping(Mapping::new());
+            }
+            target = target
+                .as_mapping_mut()
+                .unwrap()
+                .entry(Value::String(key.to_owned()))
+                .or_insert(Value::Null);
+        }
+        *target = env_value(var);
+        env_paths.push(path);
+    }
+    env_paths
+}
+
+// Variables are read as YAML, so numbers, booleans and lists keep their types. A scalar that
+// wouldn't be written back the same way, like 007, is kept as the string it is.
+fn env_value(var: String) -> Value {
+    match serde_yaml_ng::from_str::<Value>(&var) {
+        Ok(value @ (Value::Sequence(_) | Value::Mapping(_))) => value,
+        Ok(value @ (Value::Number(_) | Value::Bool(_)))
+            if serde_yaml_ng::to_string(&value).is_ok_and(|written| written.trim_end() == var) =>
+        {
+            value
+        }
+        _ => Value::String(var),
+    }
+}
+
+// Replaces every *_file key of a secret with the content of the file it points to, without its
+// trailing newline. When both variants of a key are set, the one from an environment variable
+// takes precedence over the one from the config file, and having both in the same place is a
+// problem. Returns the paths of the secrets read from files.
+pub(crate) fn apply_files(
+    config: &mut Value,
+    env_paths: &[Vec<String>],
+    problems: &mut Vec<String>,
+) -> Vec<Vec<String>> {
+    let mut file_paths = Vec::new();
+    apply_files_at(
+        config,
+        &mut Vec::new(),
+        env_paths,
+        &mut file_paths,
+        problems,
+    );
+    file_paths
+}
+
+fn apply_files_at(
+    value: &mut Value,
+    path: &mut Vec<String>,
+    env_paths: &[Vec<String>],
+    file_paths: &mut Vec<Vec<String>>,
+    problems: &mut Vec<String>,
+) {
+    let mapping = match value.as_mapping_mut() {
+        Some(mapping) => mapping,
+        None => return,
+    };
+
+    let keys = mapping
+        .keys()
+        .filter_map(|key| key.as_str().map(str::to_owned))
+        .collect::<Vec<_>>();
+    for key in keys {
+        let secret_key = match key.strip_suffix(FILE_SUFFIX) {
+            Some(secret_key) if is_secret(secret_key) => secret_key.to_owned(),
+            _ => {
+                if let Some(value) = mapping.get_mut(key.as_str()) {
+                    path.push(key);
+                    apply_files_at(value, path, env_paths, file_paths, problems);
+                    path.pop();
+                }
+                continue;
+            }
+        };
+
+        let file_value = mapping.remove(key.as_str()).unwrap_or(Value::Null);
+        let mut file_key_path = path.clone();
+        file_key_path.push(key);
+        let mut secret_path = path.clone();
+        secret_path.push(secret_key.to_owned());
+        let secret_key_name = secret_path.join(".");
+
+        if mapping
+            .get(secret_key.as_str())
+            .is_some_and(|value| !value.is_null())
+        {
+            match (
+                is_from_env(env_paths, &secret_path),
+                is_from_env(env_paths, &file_key_path),
+            ) {
+                (true, false) => continue,
+                (false, true) => (),
+                _ => {
+                    problems.push(format!(
+                        "{secret_key_name} and {secret_key_name}{FILE_SUFFIX} are both set, only \
+                         one of them can be"
+                    ));
+                    continue;
+                }
+            }
+        }
+
+        match read_secret(&file_value) {
+            Ok(secret) => {
+                mapping.insert(Value::String(secret_key), Value::String(secret));
+                file_paths.push(secret_path);
+            }
+            Err(err) => problems.push(format!("{secret_key_name}{FILE_SUFFIX} {err}")),
+        }
+    }
+}
+
+fn read_secret(file_value: &Value) -> Result<String, String> {
+    let file_path = match file_value {
+        Value::String(file_path) => file_path,
+        _ => return Err("must be the path of a file".to_owned()),
+    };
+    match fs::read_to_string(file_path) {
+        Ok(secret) => {
+            let secret = secret.trim_end_matches(['\r', '\n']);
+            if secret.is_empty() {
+                Err(format!("'{file_path}' is empty"))
+            } else {
+                Ok(secret.to_owned())
+            }
+        }
+        Err(err) => Err(format!("'{file_path}' can't be read: {err}")),
+    }
+}
+
+fn is_secret(key: &str) -> bool {
+    SECRET_KEYS
+        .iter()
+        .any(|secret| key == *secret || key.ends_with(&format!("_{secret}")))
+}
+
+fn is_from_env(env_paths: &[Vec<String>], path: &[String]) -> bool {
+    env_paths.iter().any(|env_path| path.starts_with(env_path))
+}
+
+pub(crate) fn sources(
+    config: &Value,
+    env_paths: &[Vec<String>],
+    file_paths: &[Vec<String>],
+) -> Vec<ConfigSource> {
+    let mut sources = Vec::new();
+    collect_sources(config, &mut Vec::new(), env_paths, file_paths, &mut sources);
+    sources
+}
+
+fn collect_sources(
+    value: &Value,
+    path: &mut Vec<String>,
+    env_paths: &[Vec<String>],
+    file_paths: &[Vec<String>],
+    sources: &mut Vec<ConfigSource>,
+) {
+    if let Value::Mapping(mapping) = value {
+        for (key, value) in mapping {
+            path.push(match key {
+                Value::String(key) => key.to_owned(),
+                key => scalar(key),
+            });
+            collect_sources(value, path, env_paths, file_paths, sources);
+            path.pop();
+        }
+        return;
+    }
+
+    let origin = if file_paths.contains(path) {
+        ConfigOrigin::SecretFile
+    } else if is_from_env(env_paths, path) {
+        ConfigOrigin::Env
+    } else {
+        ConfigOrigin::File
+    };
+    let value = if path
+        .iter()
+        .any(|key| is_secret(key) || SECRET_MAPS.contains(&key.as_str()))
+    {
+        SECRET_MASK.to_owned()
+    } else {
+        match value {
+            Value::Sequence(values) => format!(
+                "[{}]",
+                values.iter().map(scalar).collect::<Vec<_>>().join(", ")
+            ),
+            value => scalar(value),
+        }
+    };
+    sources.push(ConfigSource {
+        key: path.join("."),
+        value,
+        origin,
+    });
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.to_owned(),
+        value => serde_yaml_ng::to_string(value)
+            .map(|written| written.trim_end().to_owned())
+            .unwrap_or_default(),
+    }
+}
+
+pub(crate) fn changed_keys(previous: &Value, current: &Value) -> Vec<String> {
+    let mut previous_leaves = Vec::new();
+    collect_leaves(previous, &mut Vec::new(), &mut previous_leaves);
+    let mut current_leaves = Vec::new();
+    collect_leaves(current, &mut Vec::new(), &mut current_leaves);
+
+    let mut changed_keys = Vec::new();
+    for (key, value) in &current_leaves {
+        let unchanged = previous_leaves
+            .iter()
+            .any(|(previous_key, previous_value)| previous_key == key && previous_value == value);
+        if !unchanged {
+            changed_keys.push(key.to_owned());
+        }
+    }
+    for (key, _) in &previous_leaves {
+        if !current_leaves
+            .iter()
+            .any(|(current_key, _)| current_key == key)
+        {
+            changed_keys.push(key.to_owned());
+        }
+    }
+    changed_keys
+}
+
+fn collect_leaves<'a>(
+    value: &'a Value,
+    path: &mut Vec<String>,
+    leaves: &mut Vec<(String, &'a Value)>,
+) {
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                path.push(match key {
+                    Value::String(key) => key.to_owned(),
+                    key => scalar(key),
+                });
+                collect_leaves(value, path, leaves);
+                path.pop();
+            }
+        }
+        value => leaves.push((path.join("."), value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::PathBuf};
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    struct Loaded {
+        config: Value,
+        sources: Vec<ConfigSource>,
+        problems: Vec<String>,
+    }
+
+    impl Loaded {
+        fn get(&self, path: &str) -> Value {
+            let mut value = &self.config;
+            for key in path.split('.') {
+                value = &value[key];
+            }
+            value.clone()
+        }
+
+        fn source(&self, key: &str) -> &ConfigSource {
+            self.sources
+                .iter()
+                .find(|source| source.key() == key)
+                .unwrap()
+        }
+    }
+
+    // Same order as when the config is loaded: the file, then the environment, then *_file keys
+    fn load(yaml: &str, vars: &[(&str, &str)]) -> Loaded {
+        let mut config = serde_yaml_ng::from_str(yaml).unwrap();
+        let env_paths = apply_env(
+            &mut config,
+            vars.iter()
+                .map(|(name, var)| ((*name).to_owned(), (*var).to_owned())),
+        );
+        let mut problems = Vec::new();
+        let file_paths = apply_files(&mut config, &env_paths, &mut problems);
+        let sources = sources(&config, &env_paths, &file_paths);
+        Loaded {
+            config,
+            sources,
+            problems,
+        }
+    }
+
+    fn secret_file(content: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("hb_config_secret_{}", Uuid::now_v7()));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn env_takes_precedence_over_file() {
+        let loaded = load(
+            "db:\n  postgres:\n    host: file-host\n    port: 5432\n",
+            &[("HB_DB__POSTGRES__HOST", "env-host")],
+        );
+        assert_eq!(loaded.get("db.postgres.host"), "env-host");
+        assert_eq!(loaded.source("db.postgres.host").origin().to_str(), "env");
+        assert_eq!(loaded.get("db.postgres.port"), 5432);
+        assert_eq!(loaded.source("db.postgres.port").origin().to_str(), "file");
+    }
+
+    #[test]
+    fn env_replaces_the_whole_value_at_its_path() {
+        let loaded = load(
+            "api:\n  rest:\n    host: 0.0.0.0\n    port: 8080\n",
+            &[("HB_API__REST", "{ port: 9090 }")],
+        );
+        assert_eq!(loaded.get("api.rest.port"), 9090);
+        assert!(loaded.get("api.rest.host").is_null());
+    }
+
+    #[test]
+    fn env_values_keep_their_types() {
+        let loaded = load(
+            "{}",
+            &[
+                ("HB_A__INT", "42"),
+                ("HB_A__BOOL", "true"),
+                ("HB_A__LIST", "[a, b]"),
+                ("HB_A__PADDED", "007"),
+                ("HB_A__TEXT", "hello"),
+                ("HB_CONFIG_PATH", "/etc/hyperbase.yml"),
+                ("OTHER", "ignored"),
+                ("HB___EMPTY", "ignored"),
+            ],
+        );
+        assert_eq!(loaded.get("a.int"), 42);
+        assert_eq!(loaded.get("a.bool"), true);
+        assert!(loaded.get("a.list").is_sequence());
+        assert_eq!(loaded.get("a.padded"), "007");
+        assert_eq!(loaded.get("a.text"), "hello");
+        assert!(loaded.get("config_path").is_null());
+        assert_eq!(loaded.sources.len(), 5);
+    }
+
+    #[test]
+    fn secret_file_replaces_its_key() {
+        let path = secret_file("s3cr3t\n");
+        let loaded = load(
+            &format!("db:\n  postgres:\n    password_file: {}\n", path.display()),
+            &[],
+        );
+        assert!(loaded.problems.is_empty(), "{:?}", loaded.problems);
+        assert_eq!(loaded.get("db.postgres.password"), "s3cr3t");
+        assert!(loaded.get("db.postgres.password_file").is_null());
+        let source = loaded.source("db.postgres.password");
+        assert_eq!(source.origin().to_str(), "secret file");
+        assert_eq!(source.value(), SECRET_MASK);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn env_secret_takes_precedence_over_file_secret_file() {
+        let path = secret_file("from-file");
+        let loaded = load(
+            &format!("db:\n  postgres:\n    password_file: {}\n", path.display()),
+            &[("HB_DB__POSTGRES__PASSWORD", "from-env")],
+        );
+        assert!(loaded.problems.is_empty(), "{:?}", loaded.problems);
+        assert_eq!(loaded.get("db.postgres.password"), "from-env");
+        assert_eq!(
+            loaded.source("db.postgres.password").origin().to_str(),
+            "env"
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn env_secret_file_takes_precedence_over_file_secret() {
+        let path = secret_file("from-secret-file");
+        let path_str = path.display().to_string();
+        let loaded = load(
+            "db:\n  postgres:\n    password: from-file\n",
+            &[("HB_DB__POSTGRES__PASSWORD_FILE", &path_str)],
+        );
+        assert!(loaded.problems.is_empty(), "{:?}", loaded.problems);
+        assert_eq!(loaded.get("db.postgres.password"), "from-secret-file");
+        assert_eq!(
+            loaded.source("db.postgres.password").origin().to_str(),
+            "secret file"
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn secret_and_secret_file_in_the_same_place_conflict() {
+        let path = secret_file("from-secret-file");
+        let path_str = path.display().to_string();
+
+        let loaded = load(
+            &format!("db:\n  postgres:\n    password: from-file\n    password_file: {path_str}\n"),
+            &[],
+        );
+        assert_eq!(loaded.problems.len(), 1);
+        assert_eq!(loaded.get("db.postgres.password"), "from-file");
+
+        let loaded = load(
+            "{}",
+            &[
+                ("HB_DB__POSTGRES__PASSWORD", "from-env"),
+                ("HB_DB__POSTGRES__PASSWORD_FILE", &path_str),
+            ],
+        );
+        assert_eq!(loaded.problems.len(), 1);
+        assert_eq!(loaded.get("db.postgres.password"), "from-env");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn unreadable_secret_files_are_problems() {
+        let empty = secret_file("\n");
+        let loaded = load(
+            &format!(
+                "a:\n  password_file: {}\n  api_key_file: /nonexistent/{}\n  salt_file: [1]\n",
+                empty.display(),
+                Uuid::now_v7()
+            ),
+            &[],
+        );
+        assert_eq!(loaded.problems.len(), 3, "{:?}", loaded.problems);
+        assert!(loaded.get("a.password").is_null());
+        fs::remove_file(empty).unwrap();
+    }
+
+    #[test]
+    fn only_secrets_are_read_from_files() {
+        let loaded = load("tls:\n  cert_file: /etc/cert.pem\n", &[]);
+        assert!(loaded.problems.is_empty());
+        assert_eq!(loaded.get("tls.cert_file"), "/etc/cert.pem");
+        assert_eq!(loaded.source("tls.cert_file").value(), "/etc/cert.pem");
+    }
+
+    #[test]
+    fn secrets_are_masked() {
+        let loaded = load(
+            "mailer:\n  smtp_password: p\n  smtp_host: h\nwebhook:\n  headers:\n    x-token: t\n",
+            &[],
+        );
+        assert_eq!(loaded.source("mailer.smtp_password").value(), SECRET_MASK);
+        assert_eq!(loaded.source("mailer.smtp_host").value(), "h");
+        assert_eq!(
+            loaded.source("webhook.headers.x-token").value(),
+            SECRET_MASK
+        );
+    }
+
+    #[test]
+    fn changed_keys_include_added_changed_and_removed() {
+        let previous = serde_yaml_ng::from_str("a: 1\nb: 2\nc:\n  d: 3\n").unwrap();
+        let current = serde_yaml_ng::from_str("a: 1\nb: 4\nc:\n  e: 5\n").unwrap();
+        let mut changed_keys = changed_keys(&previous, &current);
+        changed_keys.sort();
+        assert_eq!(changed_keys, ["b", "c.d", "c.e"]);
+    }
+}
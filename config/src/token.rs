@@ -3,12 +3,17 @@ use serde::Deserialize;
 #[derive(Deserialize)]
 pub struct TokenConfig {
     jwt: JwtTokenConfig,
+    totp: Option<TotpTokenConfig>,
 }
 
 impl TokenConfig {
     pub fn jwt(&self) -> &JwtTokenConfig {
         &self.jwt
     }
+
+    pub fn totp(&self) -> &Option<TotpTokenConfig> {
+        &self.totp
+    }
 }
 
 #[derive(Deserialize)]
@@ -26,3 +31,19 @@ impl JwtTokenConfig {
         &self.expiry_duration
     }
 }
+
+#[derive(Deserialize)]
+pub struct TotpTokenConfig {
+    issuer: String,
+    secret: String,
+}
+
+impl TotpTokenConfig {
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+}
@@ -0,0 +1,236 @@
+use std::{fs, path::Path, time::Duration};
+
+use serde_yaml_ng::Value;
+
+use crate::Config;
+
+const REQUIRED_SECTIONS: [&str; 8] = ["app", "log", "hash", "token", "db", "bucket", "api", "auth"];
+const DB_SECTIONS: [&str; 4] = ["scylla", "postgres", "mysql", "sqlite"];
+const LEVEL_FILTERS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+
+// Checked before the keys are read into the config, since a missing section would otherwise only
+// be reported as the first missing field
+pub(crate) fn check_sections(config: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for section in REQUIRED_SECTIONS {
+        if !config.get(section).is_some_and(Value::is_mapping) {
+            problems.push(format!("Missing the '{section}' section"));
+        }
+    }
+
+    if let Some(db) = config.get("db") {
+        let db_sections = DB_SECTIONS
+            .into_iter()
+            .filter(|section| db.get(section).is_some_and(|value| !value.is_null()))
+            .collect::<Vec<_>>();
+        match db_sections.len() {
+            1 => (),
+            0 => problems.push(format!(
+                "db needs one of {}",
+                DB_SECTIONS.map(|section| format!("'{section}'")).join(", ")
+            )),
+            _ => problems.push(format!(
+                "db can only have one of {}, but has {}",
+                DB_SECTIONS.map(|section| format!("'{section}'")).join(", "),
+                db_sections
+                    .iter()
+                    .map(|section| format!("'{section}'"))
+                    .collect::<Vec<_>>()
+                    .join(" and ")
+            )),
+        }
+    }
+
+    problems
+}
+
+pub(crate) fn check(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let level_filter = config.log().level_filter();
+    if !LEVEL_FILTERS.contains(&level_filter.to_lowercase().as_str()) {
+        problems.push(format!(
+            "log.level_filter must be one of {}, got '{level_filter}'",
+            LEVEL_FILTERS.join(", ")
+        ));
+    }
+
+    if let Some(otlp) = config.log().otlp() {
+        let endpoint = otlp.endpoint();
+        if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+            problems.push(format!(
+                "log.otlp.endpoint must be an http:// or https:// URL, got '{endpoint}'"
+            ));
+        }
+    }
+
+    if *config.api().rest().port() == 0 {
+        problems.push("api.rest.port can't be 0".to_owned());
+    }
+    if let Some(tls) = config.api().rest().tls() {
+        check_file(&mut problems, "api.rest.tls.cert_path", tls.cert_path());
+        check_file(&mut problems, "api.rest.tls.key_path", tls.key_path());
+        if let Some(client_ca_path) = tls.client_ca_path() {
+            check_file(&mut problems, "api.rest.tls.client_ca_path", client_ca_path);
+        }
+        if let Some(watch_interval) = tls.watch_interval() {
+            check_duration(&mut problems, "api.rest.tls.watch_interval", watch_interval);
+        }
+    }
+
+    let websocket = config.api().websocket();
+    check_duration(
+        &mut problems,
+        "api.websocket.heartbeat_interval",
+        websocket.heartbeat_interval(),
+    );
+    check_duration(
+        &mut problems,
+        "api.websocket.client_timeout",
+        websocket.client_timeout(),
+    );
+    if websocket.client_timeout() <= websocket.heartbeat_interval() {
+        problems.push(
+            "api.websocket.client_timeout must be longer than api.websocket.heartbeat_interval, \
+             or clients time out between heartbeats"
+                .to_owned(),
+        );
+    }
+
+    if let Some(mqtt) = config.api().mqtt() {
+        if *mqtt.port() == 0 {
+            problems.push("api.mqtt.port can't be 0".to_owned());
+        }
+        if mqtt.topic().trim().is_empty() {
+            problems.push("api.mqtt is configured but api.mqtt.topic is empty".to_owned());
+        }
+        if *mqtt.channel_capacity() == 0 {
+            problems.push("api.mqtt.channel_capacity can't be 0".to_owned());
+        }
+        check_duration(&mut problems, "api.mqtt.timeout", mqtt.timeout());
+        if let Some(qos) = mqtt.publish().as_ref().and_then(|publish| *publish.qos()) {
+            if qos > 2 {
+                problems.push(format!("api.mqtt.publish.qos must be 0, 1 or 2, got {qos}"));
+            }
+        }
+    }
+
+    if let Some(scylla) = config.db().scylla() {
+        check_port(&mut problems, "db.scylla.port", scylla.port());
+        match (scylla.host(), scylla.hosts()) {
+            (Some(_), _) => (),
+            (None, Some(hosts)) if !hosts.is_empty() => (),
+            _ => problems.push("db.scylla needs either host or hosts".to_owned()),
+        }
+    }
+    if let Some(postgres) = config.db().postgres() {
+        check_port(&mut problems, "db.postgres.port", postgres.port());
+    }
+    if let Some(mysql) = config.db().mysql() {
+        check_port(&mut problems, "db.mysql.port", mysql.port());
+    }
+    if let Some(option) = config.db().option() {
+        check_duration(
+            &mut problems,
+            "db.option.ttl_sweep_interval",
+            option.ttl_sweep_interval(),
+        );
+    }
+
+    check_dir(&mut problems, "bucket.path", config.bucket().path());
+
+    if let Some(mailer) = config.mailer() {
+        match mailer.http() {
+            Some(http) => match http.preset().as_deref() {
+                None | Some("generic") | Some("mailgun") if http.url().is_none() => problems.push(
+                    "mailer.http.url is required for the generic and mailgun presets".to_owned(),
+                ),
+                None | Some("generic") | Some("sendgrid") | Some("mailgun") => (),
+                Some(preset) => problems.push(format!(
+                    "mailer.http.preset must be generic, sendgrid or mailgun, got '{preset}'"
+                )),
+            },
+            None => {
+                if mailer.smtp_host().is_none() {
+                    problems.push("mailer needs either smtp_host or http".to_owned());
+                }
+            }
+        }
+        if let Some(smtp_security) = mailer.smtp_security() {
+            if !["tls", "starttls", "none"].contains(&smtp_security.as_str()) {
+                problems.push(format!(
+                    "mailer.smtp_security must be tls, starttls or none, got '{smtp_security}'"
+                ));
+            }
+        }
+    }
+
+    if let Some(webhook) = config.webhook() {
+        if *webhook.channel_capacity() == 0 {
+            problems.push("webhook.channel_capacity can't be 0".to_owned());
+        }
+        check_duration(&mut problems, "webhook.timeout", webhook.timeout());
+    }
+
+    if let Some(backup) = config.backup() {
+        check_duration(&mut problems, "backup.interval", backup.interval());
+        if backup.path().trim().is_empty() {
+            problems.push("backup.path can't be empty".to_owned());
+        }
+    }
+
+    problems
+}
+
+fn check_port(problems: &mut Vec<String>, key: &str, port: &str) {
+    match port.parse::<u16>() {
+        Ok(0) => problems.push(format!("{key} can't be 0")),
+        Ok(_) => (),
+        Err(_) => problems.push(format!(
+            "{key} must be a number from 1 to 65535, got '{port}'"
+        )),
+    }
+}
+
+fn check_duration(problems: &mut Vec<String>, key: &str, duration: &Duration) {
+    if duration.is_zero() {
+        problems.push(format!("{key} must be longer than 0"));
+    }
+}
+
+// Only whether the file can be read, what's in it is checked when it's loaded
+fn check_file(problems: &mut Vec<String>, key: &str, path: &str) {
+    match fs::File::open(path) {
+        Ok(file) => match file.metadata() {
+            Ok(metadata) if metadata.is_file() => (),
+            Ok(_) => problems.push(format!("{key} '{path}' isn't a file")),
+            Err(err) => problems.push(format!("{key} '{path}' can't be read: {err}")),
+        },
+        Err(err) => problems.push(format!("{key} '{path}' can't be read: {err}")),
+    }
+}
+
+// The directory isn't created on startup, so it has to be there already. Writing to it is checked
+// by creating and removing a file in it.
+fn check_dir(problems: &mut Vec<String>, key: &str, path: &str) {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => (),
+        Ok(_) => {
+            problems.push(format!("{key} '{path}' isn't a directory"));
+            return;
+        }
+        Err(err) => {
+            problems.push(format!("{key} '{path}' can't be read: {err}"));
+            return;
+        }
+    }
+
+    let probe_path = Path::new(path).join(".hb_write_check");
+    match fs::write(&probe_path, []) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe_path);
+        }
+        Err(err) => problems.push(format!("{key} '{path}' isn't writable: {err}")),
+    }
+}
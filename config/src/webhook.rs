@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+use duration_str::deserialize_duration;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct WebhookConfig {
+    channel_capacity: usize,
+    #[serde(deserialize_with = "deserialize_duration")]
+    timeout: Duration,
+    max_retries: u32,
+    #[serde(deserialize_with = "deserialize_duration")]
+    retry_interval: Duration,
+}
+
+impl WebhookConfig {
+    pub fn channel_capacity(&self) -> &usize {
+        &self.channel_capacity
+    }
+
+    pub fn timeout(&self) -> &Duration {
+        &self.timeout
+    }
+
+    pub fn max_retries(&self) -> &u32 {
+        &self.max_retries
+    }
+
+    pub fn retry_interval(&self) -> &Duration {
+        &self.retry_interval
+    }
+}
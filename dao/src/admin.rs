@@ -4,10 +4,18 @@ use hb_db_mysql::model::admin::AdminModel as AdminMysqlModel;
 use hb_db_postgresql::model::admin::AdminModel as AdminPostgresModel;
 use hb_db_scylladb::model::admin::AdminModel as AdminScyllaModel;
 use hb_db_sqlite::model::admin::AdminModel as AdminSqliteModel;
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::instrument;
 use uuid::Uuid;
 
-use crate::{project::ProjectDao, util::conversion, Db};
+use crate::{
+    admin_project_member::AdminProjectMemberDao, project::ProjectDao, util::conversion, Db,
+};
+
+const TOTP_BACKUP_CODE_COUNT: usize = 10;
+const TOTP_BACKUP_CODE_LENGTH: usize = 10;
 
 #[derive(Deserialize, Serialize)]
 pub struct AdminDao {
@@ -16,6 +24,9 @@ pub struct AdminDao {
     updated_at: DateTime<Utc>,
     email: String,
     password_hash: String,
+    totp_secret: Option<String>,
+    totp_enabled_at: Option<DateTime<Utc>>,
+    totp_backup_codes: Vec<String>,
 }
 
 impl AdminDao {
@@ -27,6 +38,9 @@ impl AdminDao {
             updated_at: now,
             email: email.to_owned(),
             password_hash: password_hash.to_owned(),
+            totp_secret: None,
+            totp_enabled_at: None,
+            totp_backup_codes: Vec::new(),
         }
     }
 
@@ -61,6 +75,23 @@ impl AdminDao {
         &self.password_hash
     }
 
+    // Encrypted, only the TOTP component can read it
+    pub fn totp_secret(&self) -> &Option<String> {
+        &self.totp_secret
+    }
+
+    pub fn totp_enabled_at(&self) -> &Option<DateTime<Utc>> {
+        &self.totp_enabled_at
+    }
+
+    pub fn is_totp_enabled(&self) -> bool {
+        self.totp_enabled_at.is_some()
+    }
+
+    pub fn totp_backup_codes_remaining(&self) -> usize {
+        self.totp_backup_codes.len()
+    }
+
     pub fn set_email(&mut self, email: &str) {
         self.email = email.to_owned()
     }
@@ -69,6 +100,45 @@ impl AdminDao {
         self.password_hash = password_hash.to_owned();
     }
 
+    // Stores a secret that isn't in use until it's confirmed with enable_totp
+    pub fn set_totp_secret(&mut self, totp_secret: &str) {
+        self.totp_secret = Some(totp_secret.to_owned());
+        self.totp_enabled_at = None;
+        self.totp_backup_codes.clear();
+    }
+
+    // Returns the backup codes in plain text, only their hashes are kept
+    pub fn enable_totp(&mut self) -> Vec<String> {
+        let backup_codes = (0..TOTP_BACKUP_CODE_COUNT)
+            .map(|_| Self::generate_backup_code())
+            .collect::<Vec<_>>();
+        self.totp_enabled_at = Some(Utc::now());
+        self.totp_backup_codes = backup_codes
+            .iter()
+            .map(|code| Self::hash_backup_code(code))
+            .collect();
+        backup_codes
+    }
+
+    pub fn disable_totp(&mut self) {
+        self.totp_secret = None;
+        self.totp_enabled_at = None;
+        self.totp_backup_codes.clear();
+    }
+
+    // Removes the backup code when it matches, so each one can only be used once
+    pub fn use_totp_backup_code(&mut self, backup_code: &str) -> bool {
+        let hash = Self::hash_backup_code(backup_code);
+        match self.totp_backup_codes.iter().position(|code| code == &hash) {
+            Some(idx) => {
+                self.totp_backup_codes.remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[instrument(name = "AdminDao::db_insert", skip_all)]
     pub async fn db_insert(&self, db: &Db) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.insert_admin(&self.to_scylladb_model()).await,
@@ -78,6 +148,7 @@ impl AdminDao {
         }
     }
 
+    #[instrument(name = "AdminDao::db_select", skip_all)]
     pub async fn db_select(db: &Db, id: &Uuid) -> Result<Self> {
         match db {
             Db::ScyllaDb(db) => Self::from_scylladb_model(&db.select_admin(id).await?),
@@ -87,6 +158,7 @@ impl AdminDao {
         }
     }
 
+    #[instrument(name = "AdminDao::db_select_by_email", skip_all)]
     pub async fn db_select_by_email(db: &Db, email: &str) -> Result<Self> {
         match db {
             Db::ScyllaDb(db) => Self::from_scylladb_model(&db.select_admin_by_email(email).await?),
@@ -102,6 +174,7 @@ impl AdminDao {
         }
     }
 
+    #[instrument(name = "AdminDao::db_update", skip_all)]
     pub async fn db_update(&mut self, db: &Db) -> Result<()> {
         self.updated_at = Utc::now();
         match db {
@@ -112,11 +185,13 @@ impl AdminDao {
         }
     }
 
+    #[instrument(name = "AdminDao::db_delete", skip_all)]
     pub async fn db_delete(db: &Db, id: &Uuid) -> Result<()> {
         let projects_data = ProjectDao::db_select_many_by_admin_id(db, id).await?;
         for project_data in &projects_data {
             ProjectDao::db_delete(db, project_data.id()).await?;
         }
+        AdminProjectMemberDao::db_delete_many_by_admin_id(db, id).await?;
 
         match db {
             Db::ScyllaDb(db) => db.delete_admin(id).await,
@@ -126,6 +201,20 @@ impl AdminDao {
         }
     }
 
+    fn generate_backup_code() -> String {
+        thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TOTP_BACKUP_CODE_LENGTH)
+            .map(|c| char::from(c).to_ascii_lowercase())
+            .collect()
+    }
+
+    fn hash_backup_code(backup_code: &str) -> String {
+        hex::encode(Sha256::digest(
+            backup_code.trim().to_ascii_lowercase().as_bytes(),
+        ))
+    }
+
     fn from_scylladb_model(model: &AdminScyllaModel) -> Result<Self> {
         Ok(Self {
             id: *model.id(),
@@ -133,6 +222,14 @@ impl AdminDao {
             updated_at: conversion::scylla_cql_timestamp_to_datetime_utc(model.updated_at())?,
             email: model.email().to_owned(),
             password_hash: model.password_hash().to_owned(),
+            totp_secret: model.totp_secret().clone(),
+            totp_enabled_at: match model.totp_enabled_at() {
+                Some(totp_enabled_at) => Some(conversion::scylla_cql_timestamp_to_datetime_utc(
+                    totp_enabled_at,
+                )?),
+                None => None,
+            },
+            totp_backup_codes: model.totp_backup_codes().clone().unwrap_or_default(),
         })
     }
 
@@ -143,6 +240,16 @@ impl AdminDao {
             &conversion::datetime_utc_to_scylla_cql_timestamp(&self.updated_at),
             &self.email,
             &self.password_hash,
+            &self.totp_secret,
+            &self
+                .totp_enabled_at
+                .as_ref()
+                .map(conversion::datetime_utc_to_scylla_cql_timestamp),
+            &if self.totp_backup_codes.is_empty() {
+                None
+            } else {
+                Some(self.totp_backup_codes.clone())
+            },
         )
     }
 
@@ -153,6 +260,12 @@ impl AdminDao {
             updated_at: *model.updated_at(),
             email: model.email().to_owned(),
             password_hash: model.password_hash().to_owned(),
+            totp_secret: model.totp_secret().clone(),
+            totp_enabled_at: *model.totp_enabled_at(),
+            totp_backup_codes: match model.totp_backup_codes() {
+                Some(totp_backup_codes) => totp_backup_codes.0.clone(),
+                None => Vec::new(),
+            },
         }
     }
 
@@ -163,6 +276,13 @@ impl AdminDao {
             &self.updated_at,
             &self.email,
             &self.password_hash,
+            &self.totp_secret,
+            &self.totp_enabled_at,
+            &if self.totp_backup_codes.is_empty() {
+                None
+            } else {
+                Some(sqlx::types::Json(self.totp_backup_codes.clone()))
+            },
         )
     }
 
@@ -173,6 +293,12 @@ impl AdminDao {
             updated_at: *model.updated_at(),
             email: model.email().to_owned(),
             password_hash: model.password_hash().to_owned(),
+            totp_secret: model.totp_secret().clone(),
+            totp_enabled_at: *model.totp_enabled_at(),
+            totp_backup_codes: match model.totp_backup_codes() {
+                Some(totp_backup_codes) => totp_backup_codes.0.clone(),
+                None => Vec::new(),
+            },
         }
     }
 
@@ -183,6 +309,13 @@ impl AdminDao {
             &self.updated_at,
             &self.email,
             &self.password_hash,
+            &self.totp_secret,
+            &self.totp_enabled_at,
+            &if self.totp_backup_codes.is_empty() {
+                None
+            } else {
+                Some(sqlx::types::Json(self.totp_backup_codes.clone()))
+            },
         )
     }
 
@@ -193,6 +326,12 @@ impl AdminDao {
             updated_at: *model.updated_at(),
             email: model.email().to_owned(),
             password_hash: model.password_hash().to_owned(),
+            totp_secret: model.totp_secret().clone(),
+            totp_enabled_at: *model.totp_enabled_at(),
+            totp_backup_codes: match model.totp_backup_codes() {
+                Some(totp_backup_codes) => totp_backup_codes.0.clone(),
+                None => Vec::new(),
+            },
         }
     }
 
@@ -203,6 +342,13 @@ impl AdminDao {
             &self.updated_at,
             &self.email,
             &self.password_hash,
+            &self.totp_secret,
+            &self.totp_enabled_at,
+            &if self.totp_backup_codes.is_empty() {
+                None
+            } else {
+                Some(sqlx::types::Json(self.totp_backup_codes.clone()))
+            },
         )
     }
 }
@@ -5,6 +5,7 @@ use hb_db_postgresql::model::admin_password_reset::AdminPasswordResetModel as Ad
 use hb_db_scylladb::model::admin_password_reset::AdminPasswordResetModel as AdminPasswordResetScyllaModel;
 use hb_db_sqlite::model::admin_password_reset::AdminPasswordResetModel as AdminPasswordResetSqliteModel;
 use rand::{thread_rng, Rng};
+use tracing::instrument;
 use uuid::Uuid;
 
 use crate::{util::conversion, Db};
@@ -49,6 +50,7 @@ impl AdminPasswordResetDao {
         &self.code
     }
 
+    #[instrument(name = "AdminPasswordResetDao::db_insert", skip_all)]
     pub async fn db_insert(&self, db: &Db) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => {
@@ -70,6 +72,7 @@ impl AdminPasswordResetDao {
         }
     }
 
+    #[instrument(name = "AdminPasswordResetDao::db_select", skip_all)]
     pub async fn db_select(db: &Db, id: &Uuid) -> Result<Self> {
         match db {
             Db::ScyllaDb(db) => {
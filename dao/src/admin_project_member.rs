@@ -0,0 +1,438 @@
+use anyhow::{Error, Result};
+use chrono::{DateTime, Utc};
+use hb_db_mysql::model::admin_project_member::AdminProjectMemberModel as AdminProjectMemberMysqlModel;
+use hb_db_postgresql::model::admin_project_member::AdminProjectMemberModel as AdminProjectMemberPostgresModel;
+use hb_db_scylladb::model::admin_project_member::AdminProjectMemberModel as AdminProjectMemberScyllaModel;
+use hb_db_sqlite::model::admin_project_member::AdminProjectMemberModel as AdminProjectMemberSqliteModel;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{error::DaoError, util::conversion, Db};
+
+// Grants an admin access to a project they don't own. The admin_id stored on the project itself
+// is always treated as an owner and doesn't need a member row.
+#[derive(Deserialize, Serialize)]
+pub struct AdminProjectMemberDao {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    project_id: Uuid,
+    admin_id: Uuid,
+    role: AdminProjectRole,
+}
+
+impl AdminProjectMemberDao {
+    pub fn new(project_id: &Uuid, admin_id: &Uuid, role: &AdminProjectRole) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: Uuid::now_v7(),
+            created_at: now,
+            updated_at: now,
+            project_id: *project_id,
+            admin_id: *admin_id,
+            role: *role,
+        }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        &self.created_at
+    }
+
+    pub fn updated_at(&self) -> &DateTime<Utc> {
+        &self.updated_at
+    }
+
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn admin_id(&self) -> &Uuid {
+        &self.admin_id
+    }
+
+    pub fn role(&self) -> &AdminProjectRole {
+        &self.role
+    }
+
+    pub fn set_role(&mut self, role: &AdminProjectRole) {
+        self.role = *role;
+    }
+
+    #[instrument(name = "AdminProjectMemberDao::db_insert", skip_all)]
+    pub async fn db_insert(&self, db: &Db) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => {
+                db.insert_admin_project_member(&self.to_scylladb_model())
+                    .await
+            }
+            Db::PostgresqlDb(db) => {
+                db.insert_admin_project_member(&self.to_postgresdb_model())
+                    .await
+            }
+            Db::MysqlDb(db) => {
+                db.insert_admin_project_member(&self.to_mysqldb_model())
+                    .await
+            }
+            Db::SqliteDb(db) => {
+                db.insert_admin_project_member(&self.to_sqlitedb_model())
+                    .await
+            }
+        }
+    }
+
+    #[instrument(name = "AdminProjectMemberDao::db_select", skip_all)]
+    pub async fn db_select(db: &Db, id: &Uuid) -> Result<Self> {
+        let not_found =
+            |err| DaoError::from_no_rows(err, format!("Project member '{id}' is not found"));
+        match db {
+            Db::ScyllaDb(db) => Self::from_scylladb_model(
+                &db.select_admin_project_member(id)
+                    .await
+                    .map_err(not_found)?,
+            ),
+            Db::PostgresqlDb(db) => Self::from_postgresdb_model(
+                &db.select_admin_project_member(id)
+                    .await
+                    .map_err(not_found)?,
+            ),
+            Db::MysqlDb(db) => Self::from_mysqldb_model(
+                &db.select_admin_project_member(id)
+                    .await
+                    .map_err(not_found)?,
+            ),
+            Db::SqliteDb(db) => Self::from_sqlitedb_model(
+                &db.select_admin_project_member(id)
+                    .await
+                    .map_err(not_found)?,
+            ),
+        }
+    }
+
+    #[instrument(
+        name = "AdminProjectMemberDao::db_select_by_project_id_and_admin_id",
+        skip_all
+    )]
+    pub async fn db_select_by_project_id_and_admin_id(
+        db: &Db,
+        project_id: &Uuid,
+        admin_id: &Uuid,
+    ) -> Result<Self> {
+        let not_found = |err| {
+            DaoError::from_no_rows(
+                err,
+                format!("Admin '{admin_id}' is not a member of project '{project_id}'"),
+            )
+        };
+        match db {
+            Db::ScyllaDb(db) => Self::from_scylladb_model(
+                &db.select_admin_project_member_by_project_id_and_admin_id(project_id, admin_id)
+                    .await
+                    .map_err(not_found)?,
+            ),
+            Db::PostgresqlDb(db) => Self::from_postgresdb_model(
+                &db.select_admin_project_member_by_project_id_and_admin_id(project_id, admin_id)
+                    .await
+                    .map_err(not_found)?,
+            ),
+            Db::MysqlDb(db) => Self::from_mysqldb_model(
+                &db.select_admin_project_member_by_project_id_and_admin_id(project_id, admin_id)
+                    .await
+                    .map_err(not_found)?,
+            ),
+            Db::SqliteDb(db) => Self::from_sqlitedb_model(
+                &db.select_admin_project_member_by_project_id_and_admin_id(project_id, admin_id)
+                    .await
+                    .map_err(not_found)?,
+            ),
+        }
+    }
+
+    #[instrument(name = "AdminProjectMemberDao::db_select_many_by_project_id", skip_all)]
+    pub async fn db_select_many_by_project_id(db: &Db, project_id: &Uuid) -> Result<Vec<Self>> {
+        match db {
+            Db::ScyllaDb(db) => {
+                let mut members_data = Vec::new();
+                let members = db
+                    .select_many_admin_project_members_by_project_id(project_id)
+                    .await?;
+                for member in members {
+                    members_data.push(Self::from_scylladb_model(&member?)?);
+                }
+                Ok(members_data)
+            }
+            Db::PostgresqlDb(db) => {
+                let members = db
+                    .select_many_admin_project_members_by_project_id(project_id)
+                    .await?;
+                let mut members_data = Vec::with_capacity(members.len());
+                for member in &members {
+                    members_data.push(Self::from_postgresdb_model(member)?);
+                }
+                Ok(members_data)
+            }
+            Db::MysqlDb(db) => {
+                let members = db
+                    .select_many_admin_project_members_by_project_id(project_id)
+                    .await?;
+                let mut members_data = Vec::with_capacity(members.len());
+                for member in &members {
+                    members_data.push(Self::from_mysqldb_model(member)?);
+                }
+                Ok(members_data)
+            }
+            Db::SqliteDb(db) => {
+                let members = db
+                    .select_many_admin_project_members_by_project_id(project_id)
+                    .await?;
+                let mut members_data = Vec::with_capacity(members.len());
+                for member in &members {
+                    members_data.push(Self::from_sqlitedb_model(member)?);
+                }
+                Ok(members_data)
+            }
+        }
+    }
+
+    #[instrument(name = "AdminProjectMemberDao::db_select_many_by_admin_id", skip_all)]
+    pub async fn db_select_many_by_admin_id(db: &Db, admin_id: &Uuid) -> Result<Vec<Self>> {
+        match db {
+            Db::ScyllaDb(db) => {
+                let mut members_data = Vec::new();
+                let members = db
+                    .select_many_admin_project_members_by_admin_id(admin_id)
+                    .await?;
+                for member in members {
+                    members_data.push(Self::from_scylladb_model(&member?)?);
+                }
+                Ok(members_data)
+            }
+            Db::PostgresqlDb(db) => {
+                let members = db
+                    .select_many_admin_project_members_by_admin_id(admin_id)
+                    .await?;
+                let mut members_data = Vec::with_capacity(members.len());
+                for member in &members {
+                    members_data.push(Self::from_postgresdb_model(member)?);
+                }
+                Ok(members_data)
+            }
+            Db::MysqlDb(db) => {
+                let members = db
+                    .select_many_admin_project_members_by_admin_id(admin_id)
+                    .await?;
+                let mut members_data = Vec::with_capacity(members.len());
+                for member in &members {
+                    members_data.push(Self::from_mysqldb_model(member)?);
+                }
+                Ok(members_data)
+            }
+            Db::SqliteDb(db) => {
+                let members = db
+                    .select_many_admin_project_members_by_admin_id(admin_id)
+                    .await?;
+                let mut members_data = Vec::with_capacity(members.len());
+                for member in &members {
+                    members_data.push(Self::from_sqlitedb_model(member)?);
+                }
+                Ok(members_data)
+            }
+        }
+    }
+
+    #[instrument(name = "AdminProjectMemberDao::db_update", skip_all)]
+    pub async fn db_update(&mut self, db: &Db) -> Result<()> {
+        self.updated_at = Utc::now();
+        match db {
+            Db::ScyllaDb(db) => {
+                db.update_admin_project_member(&self.to_scylladb_model())
+                    .await
+            }
+            Db::PostgresqlDb(db) => {
+                db.update_admin_project_member(&self.to_postgresdb_model())
+                    .await
+            }
+            Db::MysqlDb(db) => {
+                db.update_admin_project_member(&self.to_mysqldb_model())
+                    .await
+            }
+            Db::SqliteDb(db) => {
+                db.update_admin_project_member(&self.to_sqlitedb_model())
+                    .await
+            }
+        }
+    }
+
+    #[instrument(name = "AdminProjectMemberDao::db_delete", skip_all)]
+    pub async fn db_delete(db: &Db, id: &Uuid) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => db.delete_admin_project_member(id).await,
+            Db::PostgresqlDb(db) => db.delete_admin_project_member(id).await,
+            Db::MysqlDb(db) => db.delete_admin_project_member(id).await,
+            Db::SqliteDb(db) => db.delete_admin_project_member(id).await,
+        }
+    }
+
+    #[instrument(name = "AdminProjectMemberDao::db_delete_many_by_project_id", skip_all)]
+    pub async fn db_delete_many_by_project_id(db: &Db, project_id: &Uuid) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => {
+                db.delete_many_admin_project_members_by_project_id(project_id)
+                    .await
+            }
+            Db::PostgresqlDb(db) => {
+                db.delete_many_admin_project_members_by_project_id(project_id)
+                    .await
+            }
+            Db::MysqlDb(db) => {
+                db.delete_many_admin_project_members_by_project_id(project_id)
+                    .await
+            }
+            Db::SqliteDb(db) => {
+                db.delete_many_admin_project_members_by_project_id(project_id)
+                    .await
+            }
+        }
+    }
+
+    #[instrument(name = "AdminProjectMemberDao::db_delete_many_by_admin_id", skip_all)]
+    pub async fn db_delete_many_by_admin_id(db: &Db, admin_id: &Uuid) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => {
+                db.delete_many_admin_project_members_by_admin_id(admin_id)
+                    .await
+            }
+            Db::PostgresqlDb(db) => {
+                db.delete_many_admin_project_members_by_admin_id(admin_id)
+                    .await
+            }
+            Db::MysqlDb(db) => {
+                db.delete_many_admin_project_members_by_admin_id(admin_id)
+                    .await
+            }
+            Db::SqliteDb(db) => {
+                db.delete_many_admin_project_members_by_admin_id(admin_id)
+                    .await
+            }
+        }
+    }
+
+    fn from_scylladb_model(model: &AdminProjectMemberScyllaModel) -> Result<Self> {
+        Ok(Self {
+            id: *model.id(),
+            created_at: conversion::scylla_cql_timestamp_to_datetime_utc(model.created_at())?,
+            updated_at: conversion::scylla_cql_timestamp_to_datetime_utc(model.updated_at())?,
+            project_id: *model.project_id(),
+            admin_id: *model.admin_id(),
+            role: AdminProjectRole::from_str(model.role())?,
+        })
+    }
+
+    fn to_scylladb_model(&self) -> AdminProjectMemberScyllaModel {
+        AdminProjectMemberScyllaModel::new(
+            &self.id,
+            &conversion::datetime_utc_to_scylla_cql_timestamp(&self.created_at),
+            &conversion::datetime_utc_to_scylla_cql_timestamp(&self.updated_at),
+            &self.project_id,
+            &self.admin_id,
+            self.role.to_str(),
+        )
+    }
+
+    fn from_postgresdb_model(model: &AdminProjectMemberPostgresModel) -> Result<Self> {
+        Ok(Self {
+            id: *model.id(),
+            created_at: *model.created_at(),
+            updated_at: *model.updated_at(),
+            project_id: *model.project_id(),
+            admin_id: *model.admin_id(),
+            role: AdminProjectRole::from_str(model.role())?,
+        })
+    }
+
+    fn to_postgresdb_model(&self) -> AdminProjectMemberPostgresModel {
+        AdminProjectMemberPostgresModel::new(
+            &self.id,
+            &self.created_at,
+            &self.updated_at,
+            &self.project_id,
+            &self.admin_id,
+            self.role.to_str(),
+        )
+    }
+
+    fn from_mysqldb_model(model: &AdminProjectMemberMysqlModel) -> Result<Self> {
+        Ok(Self {
+            id: *model.id(),
+            created_at: *model.created_at(),
+            updated_at: *model.updated_at(),
+            project_id: *model.project_id(),
+            admin_id: *model.admin_id(),
+            role: AdminProjectRole::from_str(model.role())?,
+        })
+    }
+
+    fn to_mysqldb_model(&self) -> AdminProjectMemberMysqlModel {
+        AdminProjectMemberMysqlModel::new(
+            &self.id,
+            &self.created_at,
+            &self.updated_at,
+            &self.project_id,
+            &self.admin_id,
+            self.role.to_str(),
+        )
+    }
+
+    fn from_sqlitedb_model(model: &AdminProjectMemberSqliteModel) -> Result<Self> {
+        Ok(Self {
+            id: *model.id(),
+            created_at: *model.created_at(),
+            updated_at: *model.updated_at(),
+            project_id: *model.project_id(),
+            admin_id: *model.admin_id(),
+            role: AdminProjectRole::from_str(model.role())?,
+        })
+    }
+
+    fn to_sqlitedb_model(&self) -> AdminProjectMemberSqliteModel {
+        AdminProjectMemberSqliteModel::new(
+            &self.id,
+            &self.created_at,
+            &self.updated_at,
+            &self.project_id,
+            &self.admin_id,
+            self.role.to_str(),
+        )
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum AdminProjectRole {
+    Owner,
+    Editor,
+    Viewer,
+}
+
+impl AdminProjectRole {
+    pub fn to_str(&self) -> &str {
+        match self {
+            Self::Owner => "owner",
+            Self::Editor => "editor",
+            Self::Viewer => "viewer",
+        }
+    }
+
+    pub fn from_str(str: &str) -> Result<Self> {
+        match str {
+            "owner" => Ok(Self::Owner),
+            "editor" => Ok(Self::Editor),
+            "viewer" => Ok(Self::Viewer),
+            _ => Err(Error::msg(format!("Unknown admin project role '{str}'"))),
+        }
+    }
+}
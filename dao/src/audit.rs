@@ -0,0 +1,390 @@
+use anyhow::{Error, Result};
+use chrono::{DateTime, Utc};
+use hb_db_mysql::model::audit::AuditModel as AuditMysqlModel;
+use hb_db_postgresql::model::audit::AuditModel as AuditPostgresModel;
+use hb_db_scylladb::model::audit::AuditModel as AuditScyllaModel;
+use hb_db_sqlite::model::audit::AuditModel as AuditSqliteModel;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{util::conversion, Db};
+
+// A change an admin made to the configuration of a project, records and files aren't audited
+pub struct AuditDao {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    admin_id: Uuid,
+    project_id: Uuid,
+    action: AuditAction,
+    entity_type: AuditEntity,
+    entity_id: Uuid,
+    diff: serde_json::Value,
+    source_ip: Option<String>,
+}
+
+impl AuditDao {
+    pub fn new(
+        admin_id: &Uuid,
+        project_id: &Uuid,
+        action: &AuditAction,
+        entity_type: &AuditEntity,
+        entity_id: &Uuid,
+        diff: &serde_json::Value,
+        source_ip: &Option<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            created_at: Utc::now(),
+            admin_id: *admin_id,
+            project_id: *project_id,
+            action: *action,
+            entity_type: *entity_type,
+            entity_id: *entity_id,
+            diff: diff.clone(),
+            source_ip: source_ip.clone(),
+        }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        &self.created_at
+    }
+
+    pub fn admin_id(&self) -> &Uuid {
+        &self.admin_id
+    }
+
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn action(&self) -> &AuditAction {
+        &self.action
+    }
+
+    pub fn entity_type(&self) -> &AuditEntity {
+        &self.entity_type
+    }
+
+    pub fn entity_id(&self) -> &Uuid {
+        &self.entity_id
+    }
+
+    pub fn diff(&self) -> &serde_json::Value {
+        &self.diff
+    }
+
+    pub fn source_ip(&self) -> &Option<String> {
+        &self.source_ip
+    }
+
+    #[instrument(name = "AuditDao::db_insert", skip_all)]
+    pub async fn db_insert(&self, db: &Db) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => db.insert_audit(&self.to_scylladb_model()?).await,
+            Db::PostgresqlDb(db) => db.insert_audit(&self.to_postgresdb_model()?).await,
+            Db::MysqlDb(db) => db.insert_audit(&self.to_mysqldb_model()?).await,
+            Db::SqliteDb(db) => db.insert_audit(&self.to_sqlitedb_model()?).await,
+        }
+    }
+
+    // Both ends of the time range are inclusive, the total counts every entry that matches
+    // regardless of before_id and limit
+    #[instrument(name = "AuditDao::db_select_many_by_project_id", skip_all)]
+    pub async fn db_select_many_by_project_id(
+        db: &Db,
+        project_id: &Uuid,
+        entity_type: &Option<AuditEntity>,
+        entity_id: &Option<Uuid>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        before_id: &Option<Uuid>,
+        limit: &Option<i32>,
+    ) -> Result<(Vec<Self>, i64)> {
+        let entity_type = &entity_type.as_ref().map(|entity_type| entity_type.to_str());
+        match db {
+            Db::ScyllaDb(db) => {
+                let from = &from
+                    .as_ref()
+                    .map(conversion::datetime_utc_to_scylla_cql_timestamp);
+                let to = &to
+                    .as_ref()
+                    .map(conversion::datetime_utc_to_scylla_cql_timestamp);
+                let mut audits_data = Vec::new();
+                let (audits, total) = tokio::try_join!(
+                    db.select_many_audits_by_project_id(
+                        project_id,
+                        entity_type,
+                        entity_id,
+                        from,
+                        to,
+                        before_id,
+                        limit
+                    ),
+                    db.count_many_audits_by_project_id(
+                        project_id,
+                        entity_type,
+                        entity_id,
+                        from,
+                        to
+                    )
+                )?;
+                for audit in audits {
+                    audits_data.push(Self::from_scylladb_model(&audit?)?);
+                }
+                Ok((audits_data, total))
+            }
+            Db::PostgresqlDb(db) => {
+                let (audits, total) = tokio::try_join!(
+                    db.select_many_audits_by_project_id(
+                        project_id,
+                        entity_type,
+                        entity_id,
+                        from,
+                        to,
+                        before_id,
+                        limit
+                    ),
+                    db.count_many_audits_by_project_id(
+                        project_id,
+                        entity_type,
+                        entity_id,
+                        from,
+                        to
+                    )
+                )?;
+                let mut audits_data = Vec::with_capacity(audits.len());
+                for audit in &audits {
+                    audits_data.push(Self::from_postgresdb_model(audit)?);
+                }
+                Ok((audits_data, total))
+            }
+            Db::MysqlDb(db) => {
+                let (audits, total) = tokio::try_join!(
+                    db.select_many_audits_by_project_id(
+                        project_id,
+                        entity_type,
+                        entity_id,
+                        from,
+                        to,
+                        before_id,
+                        limit
+                    ),
+                    db.count_many_audits_by_project_id(
+                        project_id,
+                        entity_type,
+                        entity_id,
+                        from,
+                        to
+                    )
+                )?;
+                let mut audits_data = Vec::with_capacity(audits.len());
+                for audit in &audits {
+                    audits_data.push(Self::from_mysqldb_model(audit)?);
+                }
+                Ok((audits_data, total))
+            }
+            Db::SqliteDb(db) => {
+                let (audits, total) = tokio::try_join!(
+                    db.select_many_audits_by_project_id(
+                        project_id,
+                        entity_type,
+                        entity_id,
+                        from,
+                        to,
+                        before_id,
+                        limit
+                    ),
+                    db.count_many_audits_by_project_id(
+                        project_id,
+                        entity_type,
+                        entity_id,
+                        from,
+                        to
+                    )
+                )?;
+                let mut audits_data = Vec::with_capacity(audits.len());
+                for audit in &audits {
+                    audits_data.push(Self::from_sqlitedb_model(audit)?);
+                }
+                Ok((audits_data, total))
+            }
+        }
+    }
+
+    fn from_scylladb_model(model: &AuditScyllaModel) -> Result<Self> {
+        Ok(Self {
+            id: *model.id(),
+            created_at: conversion::scylla_cql_timestamp_to_datetime_utc(model.created_at())?,
+            admin_id: *model.admin_id(),
+            project_id: *model.project_id(),
+            action: AuditAction::from_str(model.action())?,
+            entity_type: AuditEntity::from_str(model.entity_type())?,
+            entity_id: *model.entity_id(),
+            diff: serde_json::from_str(model.diff())?,
+            source_ip: model.source_ip().clone(),
+        })
+    }
+
+    fn to_scylladb_model(&self) -> Result<AuditScyllaModel> {
+        Ok(AuditScyllaModel::new(
+            &self.id,
+            &conversion::datetime_utc_to_scylla_cql_timestamp(&self.created_at),
+            &self.admin_id,
+            &self.project_id,
+            self.action.to_str(),
+            self.entity_type.to_str(),
+            &self.entity_id,
+            &serde_json::to_string(&self.diff)?,
+            &self.source_ip,
+        ))
+    }
+
+    fn from_postgresdb_model(model: &AuditPostgresModel) -> Result<Self> {
+        Ok(Self {
+            id: *model.id(),
+            created_at: *model.created_at(),
+            admin_id: *model.admin_id(),
+            project_id: *model.project_id(),
+            action: AuditAction::from_str(model.action())?,
+            entity_type: AuditEntity::from_str(model.entity_type())?,
+            entity_id: *model.entity_id(),
+            diff: serde_json::from_str(model.diff())?,
+            source_ip: model.source_ip().clone(),
+        })
+    }
+
+    fn to_postgresdb_model(&self) -> Result<AuditPostgresModel> {
+        Ok(AuditPostgresModel::new(
+            &self.id,
+            &self.created_at,
+            &self.admin_id,
+            &self.project_id,
+            self.action.to_str(),
+            self.entity_type.to_str(),
+            &self.entity_id,
+            &serde_json::to_string(&self.diff)?,
+            &self.source_ip,
+        ))
+    }
+
+    fn from_mysqldb_model(model: &AuditMysqlModel) -> Result<Self> {
+        Ok(Self {
+            id: *model.id(),
+            created_at: *model.created_at(),
+            admin_id: *model.admin_id(),
+            project_id: *model.project_id(),
+            action: AuditAction::from_str(model.action())?,
+            entity_type: AuditEntity::from_str(model.entity_type())?,
+            entity_id: *model.entity_id(),
+            diff: serde_json::from_str(model.diff())?,
+            source_ip: model.source_ip().clone(),
+        })
+    }
+
+    fn to_mysqldb_model(&self) -> Result<AuditMysqlModel> {
+        Ok(AuditMysqlModel::new(
+            &self.id,
+            &self.created_at,
+            &self.admin_id,
+            &self.project_id,
+            self.action.to_str(),
+            self.entity_type.to_str(),
+            &self.entity_id,
+            &serde_json::to_string(&self.diff)?,
+            &self.source_ip,
+        ))
+    }
+
+    fn from_sqlitedb_model(model: &AuditSqliteModel) -> Result<Self> {
+        Ok(Self {
+            id: *model.id(),
+            created_at: *model.created_at(),
+            admin_id: *model.admin_id(),
+            project_id: *model.project_id(),
+            action: AuditAction::from_str(model.action())?,
+            entity_type: AuditEntity::from_str(model.entity_type())?,
+            entity_id: *model.entity_id(),
+            diff: serde_json::from_str(model.diff())?,
+            source_ip: model.source_ip().clone(),
+        })
+    }
+
+    fn to_sqlitedb_model(&self) -> Result<AuditSqliteModel> {
+        Ok(AuditSqliteModel::new(
+            &self.id,
+            &self.created_at,
+            &self.admin_id,
+            &self.project_id,
+            self.action.to_str(),
+            self.entity_type.to_str(),
+            &self.entity_id,
+            &serde_json::to_string(&self.diff)?,
+            &self.source_ip,
+        ))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+}
+
+impl AuditAction {
+    pub fn from_str(str: &str) -> Result<Self> {
+        match str {
+            "create" => Ok(Self::Create),
+            "update" => Ok(Self::Update),
+            "delete" => Ok(Self::Delete),
+            _ => Err(Error::msg(format!("Unknown audit action '{str}'"))),
+        }
+    }
+
+    pub fn to_str(&self) -> &str {
+        match self {
+            Self::Create => "create",
+            Self::Update => "update",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum AuditEntity {
+    Project,
+    Collection,
+    Token,
+    CollectionRule,
+    BucketRule,
+    Bucket,
+}
+
+impl AuditEntity {
+    pub fn from_str(str: &str) -> Result<Self> {
+        match str {
+            "project" => Ok(Self::Project),
+            "collection" => Ok(Self::Collection),
+            "token" => Ok(Self::Token),
+            "collection_rule" => Ok(Self::CollectionRule),
+            "bucket_rule" => Ok(Self::BucketRule),
+            "bucket" => Ok(Self::Bucket),
+            _ => Err(Error::msg(format!("Unknown audit entity type '{str}'"))),
+        }
+    }
+
+    pub fn to_str(&self) -> &str {
+        match self {
+            Self::Project => "project",
+            Self::Collection => "collection",
+            Self::Token => "token",
+            Self::CollectionRule => "collection_rule",
+            Self::BucketRule => "bucket_rule",
+            Self::Bucket => "bucket",
+        }
+    }
+}
@@ -1,15 +1,25 @@
-use anyhow::Result;
+use std::str::FromStr;
+
+use anyhow::{Error, Result};
 use chrono::{DateTime, Utc};
 use futures::future;
 use hb_db_mysql::model::bucket::BucketModel as BucketMysqlModel;
 use hb_db_postgresql::model::bucket::BucketModel as BucketPostgresModel;
 use hb_db_scylladb::model::bucket::BucketModel as BucketScyllaModel;
 use hb_db_sqlite::model::bucket::BucketModel as BucketSqliteModel;
+use mime::Mime;
 use serde::{Deserialize, Serialize};
 use tokio::fs;
+use tracing::instrument;
 use uuid::Uuid;
 
-use crate::{bucket_rule::BucketRuleDao, file::FileDao, util::conversion, Db};
+use crate::{
+    bucket_rule::BucketRuleDao,
+    error::DaoError,
+    file::{FileDao, FileFilter, FileOrder},
+    util::conversion,
+    Db,
+};
 
 #[derive(Deserialize, Serialize)]
 pub struct BucketDao {
@@ -20,15 +30,27 @@ pub struct BucketDao {
     name: String,
     path: String,
     opt_ttl: Option<i64>,
+    max_file_size: Option<i64>,
+    allowed_content_types: Option<Vec<String>>,
+    quota_bytes: Option<i64>,
+    used_bytes: i64,
 }
 
 impl BucketDao {
+    #[instrument(name = "BucketDao::new", skip_all)]
     pub async fn new(
         project_id: &Uuid,
         name: &str,
         path: &str,
         opt_ttl: &Option<i64>,
+        max_file_size: &Option<i64>,
+        allowed_content_types: &Option<Vec<String>>,
+        quota_bytes: &Option<i64>,
     ) -> Result<Self> {
+        if let Some(allowed_content_types) = allowed_content_types {
+            Self::check_content_type_patterns(allowed_content_types)?;
+        }
+
         fs::create_dir_all(path).await?;
 
         let now = Utc::now();
@@ -41,6 +63,21 @@ impl BucketDao {
             name: name.to_owned(),
             path: path.to_owned(),
             opt_ttl: *opt_ttl,
+            max_file_size: match max_file_size {
+                Some(max_file_size) if *max_file_size > 0 => Some(*max_file_size),
+                _ => None,
+            },
+            allowed_content_types: match allowed_content_types {
+                Some(allowed_content_types) if !allowed_content_types.is_empty() => {
+                    Some(allowed_content_types.clone())
+                }
+                _ => None,
+            },
+            quota_bytes: match quota_bytes {
+                Some(quota_bytes) if *quota_bytes > 0 => Some(*quota_bytes),
+                _ => None,
+            },
+            used_bytes: 0,
         })
     }
 
@@ -83,6 +120,45 @@ impl BucketDao {
         &self.opt_ttl
     }
 
+    pub fn max_file_size(&self) -> &Option<i64> {
+        &self.max_file_size
+    }
+
+    pub fn allowed_content_types(&self) -> &Option<Vec<String>> {
+        &self.allowed_content_types
+    }
+
+    pub fn quota_bytes(&self) -> &Option<i64> {
+        &self.quota_bytes
+    }
+
+    pub fn used_bytes(&self) -> &i64 {
+        &self.used_bytes
+    }
+
+    // Patterns are either an exact mime type like 'image/png', a whole top-level type like
+    // 'image/*', or '*/*' to allow anything
+    pub fn is_allowed_content_type(&self, content_type: &Mime) -> bool {
+        match &self.allowed_content_types {
+            Some(allowed_content_types) => {
+                allowed_content_types
+                    .iter()
+                    .any(|pattern| match pattern.split_once('/') {
+                        Some(("*", "*")) => true,
+                        Some((type_, "*")) => {
+                            type_.eq_ignore_ascii_case(content_type.type_().as_str())
+                        }
+                        _ => pattern.eq_ignore_ascii_case(content_type.essence_str()),
+                    })
+            }
+            None => true,
+        }
+    }
+
+    pub fn set_id(&mut self, id: &Uuid) {
+        self.id = *id;
+    }
+
     pub fn set_name(&mut self, name: &str) {
         self.name = name.to_owned();
     }
@@ -97,6 +173,59 @@ impl BucketDao {
         self.opt_ttl = *opt_ttl;
     }
 
+    pub fn set_max_file_size(&mut self, max_file_size: &Option<i64>) {
+        if let Some(max_file_size) = max_file_size {
+            if *max_file_size <= 0 {
+                self.max_file_size = None;
+                return;
+            }
+        }
+        self.max_file_size = *max_file_size;
+    }
+
+    pub fn set_quota_bytes(&mut self, quota_bytes: &Option<i64>) {
+        if let Some(quota_bytes) = quota_bytes {
+            if *quota_bytes <= 0 {
+                self.quota_bytes = None;
+                return;
+            }
+        }
+        self.quota_bytes = *quota_bytes;
+    }
+
+    pub fn set_allowed_content_types(
+        &mut self,
+        allowed_content_types: &Option<Vec<String>>,
+    ) -> Result<()> {
+        if let Some(allowed_content_types) = allowed_content_types {
+            Self::check_content_type_patterns(allowed_content_types)?;
+            if allowed_content_types.is_empty() {
+                self.allowed_content_types = None;
+                return Ok(());
+            }
+        }
+        self.allowed_content_types = allowed_content_types.clone();
+        Ok(())
+    }
+
+    pub fn check_content_type_patterns(patterns: &[String]) -> Result<()> {
+        for pattern in patterns {
+            let is_valid = match pattern.split_once('/') {
+                Some(("*", "*")) => true,
+                Some((type_, "*")) => Mime::from_str(&format!("{type_}/octet-stream")).is_ok(),
+                Some((type_, _)) if type_ != "*" => Mime::from_str(pattern).is_ok(),
+                _ => false,
+            };
+            if !is_valid {
+                return Err(Error::msg(format!(
+                    "Content type pattern '{pattern}' is invalid"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "BucketDao::db_insert", skip_all)]
     pub async fn db_insert(&self, db: &Db) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.insert_bucket(&self.to_scylladb_model()).await,
@@ -106,22 +235,38 @@ impl BucketDao {
         }
     }
 
+    #[instrument(name = "BucketDao::db_select", skip_all)]
     pub async fn db_select(db: &Db, id: &Uuid) -> Result<Self> {
+        let not_found = |err| DaoError::from_no_rows(err, format!("Bucket '{id}' is not found"));
         match db {
-            Db::ScyllaDb(db) => Self::from_scylladb_model(&db.select_bucket(id).await?),
-            Db::PostgresqlDb(db) => Ok(Self::from_postgresdb_model(&db.select_bucket(id).await?)),
-            Db::MysqlDb(db) => Ok(Self::from_mysqldb_model(&db.select_bucket(id).await?)),
-            Db::SqliteDb(db) => Ok(Self::from_sqlitedb_model(&db.select_bucket(id).await?)),
+            Db::ScyllaDb(db) => {
+                let (bucket, used_bytes) =
+                    tokio::try_join!(db.select_bucket(id), db.select_bucket_used_bytes(id))
+                        .map_err(not_found)?;
+                Self::from_scylladb_model(&bucket, &used_bytes)
+            }
+            Db::PostgresqlDb(db) => Ok(Self::from_postgresdb_model(
+                &db.select_bucket(id).await.map_err(not_found)?,
+            )),
+            Db::MysqlDb(db) => Ok(Self::from_mysqldb_model(
+                &db.select_bucket(id).await.map_err(not_found)?,
+            )),
+            Db::SqliteDb(db) => Ok(Self::from_sqlitedb_model(
+                &db.select_bucket(id).await.map_err(not_found)?,
+            )),
         }
     }
 
+    #[instrument(name = "BucketDao::db_select_many_by_project_id", skip_all)]
     pub async fn db_select_many_by_project_id(db: &Db, project_id: &Uuid) -> Result<Vec<Self>> {
         match db {
             Db::ScyllaDb(db) => {
                 let mut buckets_data = Vec::new();
                 let buckets = db.select_many_buckets_by_project_id(project_id).await?;
                 for bucket in buckets {
-                    buckets_data.push(Self::from_scylladb_model(&bucket?)?);
+                    let bucket = bucket?;
+                    let used_bytes = db.select_bucket_used_bytes(bucket.id()).await?;
+                    buckets_data.push(Self::from_scylladb_model(&bucket, &used_bytes)?);
                 }
                 Ok(buckets_data)
             }
@@ -152,6 +297,7 @@ impl BucketDao {
         }
     }
 
+    #[instrument(name = "BucketDao::db_update", skip_all)]
     pub async fn db_update(&mut self, db: &Db) -> Result<()> {
         self.updated_at = Utc::now();
         match db {
@@ -162,14 +308,34 @@ impl BucketDao {
         }
     }
 
+    // Rebuilds the usage of the bucket from the sizes of its files, in case the tracked usage has
+    // drifted away from them
+    #[instrument(name = "BucketDao::db_recalculate_used_bytes", skip_all)]
+    pub async fn db_recalculate_used_bytes(db: &Db, id: &Uuid) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => db.recalculate_bucket_used_bytes(id).await,
+            Db::PostgresqlDb(db) => db.recalculate_bucket_used_bytes(id).await,
+            Db::MysqlDb(db) => db.recalculate_bucket_used_bytes(id).await,
+            Db::SqliteDb(db) => db.recalculate_bucket_used_bytes(id).await,
+        }
+    }
+
+    #[instrument(name = "BucketDao::db_delete", skip_all)]
     pub async fn db_delete(db: &Db, id: &Uuid) -> Result<()> {
         let bucket_data = Self::db_select(db, id).await?;
 
-        let (files_data, _) =
-            FileDao::db_select_many_by_bucket_id(db, &bucket_data, &None, &None).await?;
+        let (files_data, _) = FileDao::db_select_many_by_bucket_id(
+            db,
+            &bucket_data,
+            &FileFilter::default(),
+            &FileOrder::CreatedAtDesc,
+            &None,
+            &None,
+        )
+        .await?;
         let mut delete_file_mut = Vec::with_capacity(files_data.len());
         for file_data in &files_data {
-            delete_file_mut.push(FileDao::delete(db, &bucket_data, file_data.id()));
+            delete_file_mut.push(file_data.delete(db, &bucket_data));
         }
         future::try_join_all(delete_file_mut).await?;
 
@@ -183,7 +349,7 @@ impl BucketDao {
         }
     }
 
-    fn from_scylladb_model(model: &BucketScyllaModel) -> Result<Self> {
+    fn from_scylladb_model(model: &BucketScyllaModel, used_bytes: &i64) -> Result<Self> {
         Ok(Self {
             id: *model.id(),
             created_at: conversion::scylla_cql_timestamp_to_datetime_utc(model.created_at())?,
@@ -192,6 +358,10 @@ impl BucketDao {
             name: model.name().to_owned(),
             path: model.path().to_owned(),
             opt_ttl: *model.opt_ttl(),
+            max_file_size: *model.max_file_size(),
+            allowed_content_types: model.allowed_content_types().clone(),
+            quota_bytes: *model.quota_bytes(),
+            used_bytes: *used_bytes,
         })
     }
 
@@ -204,6 +374,9 @@ impl BucketDao {
             &self.name,
             &self.path,
             &self.opt_ttl,
+            &self.max_file_size,
+            &self.allowed_content_types,
+            &self.quota_bytes,
         )
     }
 
@@ -216,6 +389,13 @@ impl BucketDao {
             name: model.name().to_owned(),
             path: model.path().to_owned(),
             opt_ttl: *model.opt_ttl(),
+            max_file_size: *model.max_file_size(),
+            allowed_content_types: model
+                .allowed_content_types()
+                .as_ref()
+                .map(|allowed_content_types| allowed_content_types.0.clone()),
+            quota_bytes: *model.quota_bytes(),
+            used_bytes: *model.used_bytes(),
         }
     }
 
@@ -228,6 +408,10 @@ impl BucketDao {
             &self.name,
             &self.path,
             &self.opt_ttl,
+            &self.max_file_size,
+            &self.allowed_content_types.clone().map(sqlx::types::Json),
+            &self.quota_bytes,
+            &self.used_bytes,
         )
     }
 
@@ -240,6 +424,13 @@ impl BucketDao {
             name: model.name().to_owned(),
             path: model.path().to_owned(),
             opt_ttl: *model.opt_ttl(),
+            max_file_size: *model.max_file_size(),
+            allowed_content_types: model
+                .allowed_content_types()
+                .as_ref()
+                .map(|allowed_content_types| allowed_content_types.0.clone()),
+            quota_bytes: *model.quota_bytes(),
+            used_bytes: *model.used_bytes(),
         }
     }
 
@@ -252,6 +443,10 @@ impl BucketDao {
             &self.name,
             &self.path,
             &self.opt_ttl,
+            &self.max_file_size,
+            &self.allowed_content_types.clone().map(sqlx::types::Json),
+            &self.quota_bytes,
+            &self.used_bytes,
         )
     }
 
@@ -264,6 +459,13 @@ impl BucketDao {
             name: model.name().to_owned(),
             path: model.path().to_owned(),
             opt_ttl: *model.opt_ttl(),
+            max_file_size: *model.max_file_size(),
+            allowed_content_types: model
+                .allowed_content_types()
+                .as_ref()
+                .map(|allowed_content_types| allowed_content_types.0.clone()),
+            quota_bytes: *model.quota_bytes(),
+            used_bytes: *model.used_bytes(),
         }
     }
 
@@ -276,6 +478,10 @@ impl BucketDao {
             &self.name,
             &self.path,
             &self.opt_ttl,
+            &self.max_file_size,
+            &self.allowed_content_types.clone().map(sqlx::types::Json),
+            &self.quota_bytes,
+            &self.used_bytes,
         )
     }
 }
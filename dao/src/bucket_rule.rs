@@ -5,10 +5,15 @@ use hb_db_postgresql::model::bucket_rule::BucketRuleModel as BucketRulePostgresM
 use hb_db_scylladb::model::bucket_rule::BucketRuleModel as BucketRuleScyllaModel;
 use hb_db_sqlite::model::bucket_rule::BucketRuleModel as BucketRuleSqliteModel;
 use serde::{Deserialize, Serialize};
+use tracing::instrument;
 use uuid::Uuid;
 
 use crate::{util::conversion, Db};
 
+// Stored as the bucket id of a rule that applies to every bucket in the project of the token.
+// Rules for a specific bucket take precedence over it.
+pub const ALL_BUCKETS_ID: Uuid = Uuid::nil();
+
 #[derive(Deserialize, Serialize)]
 pub struct BucketRuleDao {
     id: Uuid,
@@ -87,6 +92,10 @@ impl BucketRuleDao {
         &self.bucket_id
     }
 
+    pub fn is_wildcard(&self) -> bool {
+        self.bucket_id == ALL_BUCKETS_ID
+    }
+
     pub fn find_one(&self) -> &BucketPermission {
         &self.find_one
     }
@@ -127,6 +136,7 @@ impl BucketRuleDao {
         self.delete_one = *rule;
     }
 
+    #[instrument(name = "BucketRuleDao::db_insert", skip_all)]
     pub async fn db_insert(&self, db: &Db) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.insert_bucket_rule(&self.to_scylladb_model()).await,
@@ -136,6 +146,7 @@ impl BucketRuleDao {
         }
     }
 
+    #[instrument(name = "BucketRuleDao::db_select", skip_all)]
     pub async fn db_select(db: &Db, id: &Uuid) -> Result<Self> {
         match db {
             Db::ScyllaDb(db) => Self::from_scylladb_model(&db.select_bucket_rule(id).await?),
@@ -145,6 +156,7 @@ impl BucketRuleDao {
         }
     }
 
+    #[instrument(name = "BucketRuleDao::db_select_by_token_id_and_bucket_id", skip_all)]
     pub async fn db_select_by_token_id_and_bucket_id(
         db: &Db,
         token_id: &Uuid,
@@ -170,6 +182,7 @@ impl BucketRuleDao {
         }
     }
 
+    #[instrument(name = "BucketRuleDao::db_select_many_by_token_id", skip_all)]
     pub async fn db_select_many_by_token_id(db: &Db, token_id: &Uuid) -> Result<Vec<Self>> {
         match db {
             Db::ScyllaDb(db) => {
@@ -207,6 +220,7 @@ impl BucketRuleDao {
         }
     }
 
+    #[instrument(name = "BucketRuleDao::db_update", skip_all)]
     pub async fn db_update(&mut self, db: &Db) -> Result<()> {
         self.updated_at = Utc::now();
         match db {
@@ -217,6 +231,7 @@ impl BucketRuleDao {
         }
     }
 
+    #[instrument(name = "BucketRuleDao::db_delete", skip_all)]
     pub async fn db_delete(db: &Db, id: &Uuid) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.delete_bucket_rule(id).await,
@@ -226,6 +241,7 @@ impl BucketRuleDao {
         }
     }
 
+    #[instrument(name = "BucketRuleDao::db_delete_many_by_token_id", skip_all)]
     pub async fn db_delete_many_by_token_id(db: &Db, token_id: &Uuid) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.delete_many_bucket_rules_by_token_id(token_id).await,
@@ -235,6 +251,7 @@ impl BucketRuleDao {
         }
     }
 
+    #[instrument(name = "BucketRuleDao::db_delete_many_by_bucket_id", skip_all)]
     pub async fn db_delete_many_by_bucket_id(db: &Db, bucket_id: &Uuid) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.delete_many_bucket_rules_by_bucket_id(bucket_id).await,
@@ -373,7 +390,7 @@ impl BucketRuleDao {
     }
 }
 
-#[derive(Deserialize, Serialize, Clone, Copy)]
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
 pub enum BucketPermission {
     All,
     SelfMade,
@@ -0,0 +1,114 @@
+use std::{
+    sync::{OnceLock, RwLock},
+    time::{Duration, Instant},
+};
+
+use ahash::{HashMap, HashMapExt};
+use uuid::Uuid;
+
+// Set once at startup, the caches stay off until then
+static CONFIG: OnceLock<CacheConfig> = OnceLock::new();
+
+struct CacheConfig {
+    ttl: Duration,
+    capacity: usize,
+}
+
+// Turns on the lookup caches of the tokens, projects and collections. Another server changing one of them is
+// seen here after at most the TTL, changes made through this server are seen right away.
+pub fn init(ttl: &Duration, capacity: &usize) {
+    let _ = CONFIG.set(CacheConfig {
+        ttl: *ttl,
+        capacity: *capacity,
+    });
+}
+
+// Lookups by id kept for a short while, so the checks every request repeats don't each cost a
+// round trip
+pub(crate) struct TtlCache<T> {
+    entries: OnceLock<RwLock<HashMap<Uuid, (Instant, T)>>>,
+}
+
+impl<T: Clone> TtlCache<T> {
+    pub const fn new() -> Self {
+        Self {
+            entries: OnceLock::new(),
+        }
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<T> {
+        let config = CONFIG.get()?;
+        let entries = self.entries().read().unwrap();
+        match entries.get(id) {
+            Some((cached_at, value)) if cached_at.elapsed() < config.ttl => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    // Once full the expired entries make room, values that still don't fit aren't cached
+    pub fn insert(&self, id: &Uuid, value: &T) {
+        let config = match CONFIG.get() {
+            Some(config) => config,
+            None => return,
+        };
+        let mut entries = self.entries().write().unwrap();
+        if entries.len() >= config.capacity && !entries.contains_key(id) {
+            entries.retain(|_, (cached_at, _)| cached_at.elapsed() < config.ttl);
+            if entries.len() >= config.capacity {
+                return;
+            }
+        }
+        entries.insert(*id, (Instant::now(), value.clone()));
+    }
+
+    pub fn remove(&self, id: &Uuid) {
+        if CONFIG.get().is_some() {
+            self.entries().write().unwrap().remove(id);
+        }
+    }
+
+    fn entries(&self) -> &RwLock<HashMap<Uuid, (Instant, T)>> {
+        self.entries.get_or_init(|| RwLock::new(HashMap::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    const TTL: Duration = Duration::from_millis(50);
+
+    #[test]
+    fn stale_reads_never_outlive_the_ttl() {
+        init(&TTL, &2);
+        let cache = TtlCache::new();
+        let id = Uuid::now_v7();
+
+        cache.insert(&id, &1);
+        let inserted_at = Instant::now();
+        while let Some(value) = cache.get(&id) {
+            assert_eq!(value, 1);
+            assert!(inserted_at.elapsed() < TTL);
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(inserted_at.elapsed() < TTL * 4);
+
+        // Changes made through this server replace the cached value right away
+        cache.insert(&id, &2);
+        assert_eq!(cache.get(&id), Some(2));
+        cache.remove(&id);
+        assert_eq!(cache.get(&id), None);
+
+        // A full cache only takes new entries once the expired ones are gone
+        let ids = [Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7()];
+        for id in &ids {
+            cache.insert(id, &3);
+        }
+        assert_eq!(cache.get(&ids[2]), None);
+        thread::sleep(TTL);
+        cache.insert(&ids[2], &3);
+        assert_eq!(cache.get(&ids[2]), Some(3));
+    }
+}
@@ -1,7 +1,9 @@
+use std::sync::OnceLock;
+
 use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use anyhow::{Error, Result};
 use chrono::{DateTime, Utc};
-use futures::future;
+use futures::{future, TryStreamExt};
 use hb_db_mysql::model::collection::{
     CollectionModel as CollectionMysqlModel, SchemaFieldPropsModel as SchemaFieldPropsMysqlModel,
 };
@@ -15,14 +17,26 @@ use hb_db_scylladb::model::collection::{
 use hb_db_sqlite::model::collection::{
     CollectionModel as CollectionSqliteModel, SchemaFieldPropsModel as SchemaFieldPropsSqliteModel,
 };
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use tracing::instrument;
 use uuid::Uuid;
 
 use crate::{
-    collection_rule::CollectionRuleDao, record::RecordDao, util::conversion, value::ColumnKind, Db,
+    cache::TtlCache,
+    collection_rule::CollectionRuleDao,
+    error::DaoError,
+    record::RecordDao,
+    util::conversion,
+    value::{ColumnKind, ColumnValue},
+    webhook::WebhookDao,
+    Db,
 };
 
-#[derive(Deserialize, Serialize)]
+// Collections are read on almost every request to check the schema, see cache::init
+static CACHE: TtlCache<CollectionDao> = TtlCache::new();
+
+#[derive(Deserialize, Serialize, Clone)]
 pub struct CollectionDao {
     id: Uuid,
     created_at: DateTime<Utc>,
@@ -30,8 +44,12 @@ pub struct CollectionDao {
     project_id: Uuid,
     name: String,
     schema_fields: HashMap<String, SchemaFieldProps>,
+    unique_indexes: Vec<Vec<String>>,
     opt_auth_column_id: bool,
     opt_ttl: Option<i64>,
+    opt_soft_delete: bool,
+    opt_per_record_ttl: bool,
+    opt_mqtt_publish: bool,
     #[serde(skip)]
     _preserve: Option<Preserve>,
 }
@@ -41,8 +59,12 @@ impl CollectionDao {
         project_id: &Uuid,
         name: &str,
         schema_fields: &HashMap<String, SchemaFieldProps>,
+        unique_indexes: &Vec<Vec<String>>,
         opt_auth_column_id: &bool,
         opt_ttl: &Option<i64>,
+        opt_soft_delete: &bool,
+        opt_per_record_ttl: &bool,
+        opt_mqtt_publish: &bool,
     ) -> Self {
         let now = Utc::now();
 
@@ -53,8 +75,12 @@ impl CollectionDao {
             project_id: *project_id,
             name: name.to_owned(),
             schema_fields: schema_fields.clone(),
+            unique_indexes: unique_indexes.clone(),
             opt_auth_column_id: *opt_auth_column_id,
             opt_ttl: *opt_ttl,
+            opt_soft_delete: *opt_soft_delete,
+            opt_per_record_ttl: *opt_per_record_ttl,
+            opt_mqtt_publish: *opt_mqtt_publish,
             _preserve: None,
         }
     }
@@ -94,6 +120,11 @@ impl CollectionDao {
         &self.schema_fields
     }
 
+    // Unique indexes spanning several fields, a single field is made unique in its props instead
+    pub fn unique_indexes(&self) -> &Vec<Vec<String>> {
+        &self.unique_indexes
+    }
+
     pub fn opt_auth_column_id(&self) -> &bool {
         &self.opt_auth_column_id
     }
@@ -102,25 +133,179 @@ impl CollectionDao {
         &self.opt_ttl
     }
 
+    pub fn opt_soft_delete(&self) -> &bool {
+        &self.opt_soft_delete
+    }
+
+    pub fn opt_per_record_ttl(&self) -> &bool {
+        &self.opt_per_record_ttl
+    }
+
+    pub fn opt_mqtt_publish(&self) -> &bool {
+        &self.opt_mqtt_publish
+    }
+
+    // Fields of this collection that reference records of the given collection
+    pub fn reference_fields(&self, collection_id: &Uuid) -> Vec<(&String, &SchemaFieldProps)> {
+        self.schema_fields
+            .iter()
+            .filter(|(_, field_props)| {
+                field_props.kind().reference_collection_id() == Some(collection_id)
+            })
+            .collect()
+    }
+
+    pub fn set_id(&mut self, id: &Uuid) {
+        self.id = *id;
+    }
+
     pub fn set_name(&mut self, name: &str) {
         self.name = name.to_owned();
     }
 
-    pub fn update_schema_fields(&mut self, schema_fields: &HashMap<String, SchemaFieldProps>) {
+    // Records that exist before a field is added get its value from backfill_values, or from its
+    // default when backfill_defaults is set
+    pub fn update_schema_fields(
+        &mut self,
+        schema_fields: &HashMap<String, SchemaFieldProps>,
+        backfill_defaults: &bool,
+        backfill_values: &HashMap<String, ColumnValue>,
+    ) {
         if self._preserve.is_none() {
             self._preserve = Some(Preserve {
                 schema_fields: Some(self.schema_fields.clone()),
+                backfill_defaults: *backfill_defaults,
+                backfill_values: backfill_values.clone(),
+                opt_soft_delete: None,
+                opt_per_record_ttl: None,
+                rename_fields: None,
+                unique_indexes: None,
             });
         } else {
-            self._preserve.as_mut().unwrap().schema_fields = Some(self.schema_fields.clone());
+            let preserve = self._preserve.as_mut().unwrap();
+            preserve.schema_fields = Some(self.schema_fields.clone());
+            preserve.backfill_defaults = *backfill_defaults;
+            preserve.backfill_values = backfill_values.clone();
         }
         self.schema_fields = schema_fields.clone();
     }
 
+    // Renamed fields keep their data. A schema given afterwards with update_schema_fields must
+    // already use the new names.
+    pub fn rename_schema_fields(&mut self, rename_fields: &HashMap<String, String>) -> Result<()> {
+        let mut new_field_names = HashSet::with_capacity(rename_fields.len());
+        for (field_name, new_field_name) in rename_fields {
+            if !self.schema_fields.contains_key(field_name) {
+                return Err(Error::msg(format!(
+                    "Field '{field_name}' is not exist in the collection"
+                )));
+            }
+            if new_field_name.starts_with('_') {
+                return Err(Error::msg(format!(
+                    "Field '{field_name}' can't be renamed to '{new_field_name}' because it collides with a system column"
+                )));
+            }
+            if self.schema_fields.contains_key(new_field_name)
+                || !new_field_names.insert(new_field_name)
+            {
+                return Err(Error::msg(format!(
+                    "Field '{field_name}' can't be renamed to '{new_field_name}' because it collides with another field"
+                )));
+            }
+        }
+
+        if self._preserve.is_none() {
+            self._preserve = Some(Preserve {
+                schema_fields: None,
+                backfill_defaults: false,
+                backfill_values: HashMap::new(),
+                opt_soft_delete: None,
+                opt_per_record_ttl: None,
+                rename_fields: Some(rename_fields.clone()),
+                unique_indexes: Some(self.unique_indexes.clone()),
+            });
+        } else {
+            let preserve = self._preserve.as_mut().unwrap();
+            preserve.rename_fields = Some(rename_fields.clone());
+            if preserve.unique_indexes.is_none() {
+                preserve.unique_indexes = Some(self.unique_indexes.clone());
+            }
+        }
+        for (field_name, new_field_name) in rename_fields {
+            if let Some(field_props) = self.schema_fields.remove(field_name) {
+                self.schema_fields
+                    .insert(new_field_name.to_owned(), field_props);
+            }
+        }
+        for unique_index in &mut self.unique_indexes {
+            for field_name in unique_index {
+                if let Some(new_field_name) = rename_fields.get(field_name) {
+                    *field_name = new_field_name.to_owned();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Lists what update_schema_fields with the given schema would change, without changing anything
+    pub fn schema_fields_changes(
+        &self,
+        schema_fields: &HashMap<String, SchemaFieldProps>,
+    ) -> SchemaFieldsChanges {
+        let mut changes = SchemaFieldsChanges {
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        };
+        for (field_name, field_props) in schema_fields {
+            match self.schema_fields.get(field_name) {
+                Some(props) => {
+                    if props != field_props {
+                        changes.changed.push(field_name.to_owned());
+                    }
+                }
+                None => changes.added.push(field_name.to_owned()),
+            }
+        }
+        for field_name in self.schema_fields.keys() {
+            if !schema_fields.contains_key(field_name) {
+                changes.removed.push(field_name.to_owned());
+            }
+        }
+        changes.added.sort_unstable();
+        changes.removed.sort_unstable();
+        changes.changed.sort_unstable();
+        changes
+    }
+
+    pub fn set_unique_indexes(&mut self, unique_indexes: &Vec<Vec<String>>) {
+        if self._preserve.is_none() {
+            self._preserve = Some(Preserve {
+                schema_fields: None,
+                backfill_defaults: false,
+                backfill_values: HashMap::new(),
+                opt_soft_delete: None,
+                opt_per_record_ttl: None,
+                rename_fields: None,
+                unique_indexes: Some(self.unique_indexes.clone()),
+            });
+        } else {
+            let preserve = self._preserve.as_mut().unwrap();
+            if preserve.unique_indexes.is_none() {
+                preserve.unique_indexes = Some(self.unique_indexes.clone());
+            }
+        }
+        self.unique_indexes = unique_indexes.clone();
+    }
+
     pub fn set_opt_auth_column_id(&mut self, opt_auth_column_id: &bool) {
         self.opt_auth_column_id = *opt_auth_column_id;
     }
 
+    pub fn set_opt_mqtt_publish(&mut self, opt_mqtt_publish: &bool) {
+        self.opt_mqtt_publish = *opt_mqtt_publish;
+    }
+
     pub fn set_opt_ttl(&mut self, opt_ttl: &Option<i64>) {
         if let Some(opt_ttl) = opt_ttl {
             if *opt_ttl <= 0 {
@@ -131,68 +316,244 @@ impl CollectionDao {
         self.opt_ttl = *opt_ttl;
     }
 
+    pub fn set_opt_soft_delete(&mut self, opt_soft_delete: &bool) {
+        if self._preserve.is_none() {
+            self._preserve = Some(Preserve {
+                schema_fields: None,
+                backfill_defaults: false,
+                backfill_values: HashMap::new(),
+                opt_soft_delete: Some(self.opt_soft_delete),
+                opt_per_record_ttl: None,
+                rename_fields: None,
+                unique_indexes: None,
+            });
+        } else {
+            let preserve = self._preserve.as_mut().unwrap();
+            preserve.opt_soft_delete = Some(self.opt_soft_delete);
+        }
+        self.opt_soft_delete = *opt_soft_delete;
+    }
+
+    pub fn set_opt_per_record_ttl(&mut self, opt_per_record_ttl: &bool) {
+        if self._preserve.is_none() {
+            self._preserve = Some(Preserve {
+                schema_fields: None,
+                backfill_defaults: false,
+                backfill_values: HashMap::new(),
+                opt_soft_delete: None,
+                opt_per_record_ttl: Some(self.opt_per_record_ttl),
+                rename_fields: None,
+                unique_indexes: None,
+            });
+        } else {
+            let preserve = self._preserve.as_mut().unwrap();
+            preserve.opt_per_record_ttl = Some(self.opt_per_record_ttl);
+        }
+        self.opt_per_record_ttl = *opt_per_record_ttl;
+    }
+
+    // PostgreSQL and SQLite make the table, its indexes and the collection in one transaction.
+    // MySQL commits on every schema change and ScyllaDB has no transactions, so there the table is
+    // dropped again when a step after it fails.
+    #[instrument(name = "CollectionDao::db_insert", skip_all)]
     pub async fn db_insert(&self, db: &Db) -> Result<()> {
+        self.check_opt_soft_delete(db)?;
+        self.check_opt_per_record_ttl(db)?;
+        self.check_unique_indexes(db)?;
+
+        let tx = db.begin().await?;
+
         let mut create_indexes_fut = Vec::with_capacity(self.schema_fields.len());
-        let mut create_unique_indexes_fut = Vec::with_capacity(self.schema_fields.len());
+        let mut create_unique_indexes_fut =
+            Vec::with_capacity(self.schema_fields.len() + self.unique_indexes.len());
 
-        match db {
-            Db::MysqlDb(_) => {
-                for (field, props) in &self.schema_fields {
-                    if props.unique || props.indexed {
-                        match &props.kind {
-                        ColumnKind::Binary
-                        | ColumnKind::Varint
-                        | ColumnKind::Decimal
-                        | ColumnKind::Json => {
-                            return Err(Error::msg(format!(
-                                "Field '{}' has type '{}' that doesn't support indexing in the data type implementation of Hyperbase for MySQL",
-                                field,
-                                props.kind.to_str()
-                            )))
-                        }
-                        _ => (),
-                    };
-                        if props.indexed {
-                            create_indexes_fut
-                                .push(RecordDao::db_create_index(db, &self.id, field));
-                        }
-                        if props.unique {
-                            create_unique_indexes_fut
-                                .push(RecordDao::db_create_unique_index(db, &self.id, field));
-                        }
-                    }
+        for (field, props) in &self.schema_fields {
+            if props.indexed {
+                Self::check_indexable(db, field, props)?;
+                create_indexes_fut.push(RecordDao::db_create_index(&tx, &self.id, field));
+            }
+            if props.unique {
+                Self::check_indexable(db, field, props)?;
+                create_unique_indexes_fut.push(RecordDao::db_create_unique_index(
+                    &tx,
+                    &self.id,
+                    std::slice::from_ref(field),
+                ));
+            }
+        }
+        for unique_index in &self.unique_indexes {
+            create_unique_indexes_fut.push(RecordDao::db_create_unique_index(
+                &tx,
+                &self.id,
+                unique_index,
+            ));
+        }
+
+        RecordDao::db_create_table(&tx, self).await?;
+
+        let result = async {
+            tokio::try_join!(
+                future::try_join_all(create_indexes_fut),
+                future::try_join_all(create_unique_indexes_fut)
+            )?;
+
+            match &*tx {
+                Db::ScyllaDb(db) => db.insert_collection(&self.to_scylladb_model()).await,
+                Db::PostgresqlDb(db) => db.insert_collection(&self.to_postgresdb_model()).await,
+                Db::MysqlDb(db) => db.insert_collection(&self.to_mysqldb_model()).await,
+                Db::SqliteDb(db) => db.insert_collection(&self.to_sqlitedb_model()).await,
+            }
+        }
+        .await;
+        match result {
+            Ok(_) => tx.commit().await?,
+            Err(err) => {
+                drop(tx);
+                if db.is_transactional_ddl() {
+                    return Err(err);
                 }
+                return Err(match RecordDao::db_drop_table(db, &self.id).await {
+                    Ok(_) => err,
+                    Err(drop_err) => Error::msg(format!(
+                        "{err}, and the table left behind couldn't be dropped: {drop_err}"
+                    )),
+                });
             }
-            _ => (),
         }
 
-        RecordDao::db_create_table(db, self).await?;
+        self.cache_insert();
 
-        tokio::try_join!(
-            future::try_join_all(create_indexes_fut),
-            future::try_join_all(create_unique_indexes_fut)
-        )?;
+        Ok(())
+    }
 
-        match db {
-            Db::ScyllaDb(db) => db.insert_collection(&self.to_scylladb_model()).await,
-            Db::PostgresqlDb(db) => db.insert_collection(&self.to_postgresdb_model()).await,
-            Db::MysqlDb(db) => db.insert_collection(&self.to_mysqldb_model()).await,
-            Db::SqliteDb(db) => db.insert_collection(&self.to_sqlitedb_model()).await,
+    // Creates a collection with the same schema and options under another name. Collection rules
+    // belong to tokens, so they are not carried over.
+    #[instrument(name = "CollectionDao::db_duplicate", skip_all)]
+    pub async fn db_duplicate(
+        &self,
+        db: &Db,
+        name: &str,
+        include_records: &bool,
+        preserve_ids: &bool,
+    ) -> Result<Self> {
+        if *include_records {
+            if let Db::ScyllaDb(_) = db {
+                return Err(Error::msg(
+                    "Copying records is not supported in ScyllaDB because it has no INSERT INTO ... SELECT",
+                ));
+            }
+        }
+
+        let collection_data = Self::new(
+            &self.project_id,
+            name,
+            &self.schema_fields,
+            &self.unique_indexes,
+            &self.opt_auth_column_id,
+            &self.opt_ttl,
+            &self.opt_soft_delete,
+            &self.opt_per_record_ttl,
+            &self.opt_mqtt_publish,
+        );
+        collection_data.db_insert(db).await?;
+
+        if *include_records {
+            if let Err(err) =
+                RecordDao::db_copy_records(db, self, &collection_data, preserve_ids).await
+            {
+                Self::db_delete(db, collection_data.id()).await?;
+                return Err(err);
+            }
         }
+
+        Ok(collection_data)
     }
 
+    #[instrument(name = "CollectionDao::db_select", skip_all)]
     pub async fn db_select(db: &Db, id: &Uuid) -> Result<Self> {
-        match db {
-            Db::ScyllaDb(db) => Ok(Self::from_scylladb_model(&db.select_collection(id).await?)?),
-            Db::PostgresqlDb(db) => Ok(Self::from_postgresdb_model(
-                &db.select_collection(id).await?,
-            )?),
-            Db::MysqlDb(db) => Ok(Self::from_mysqldb_model(&db.select_collection(id).await?)?),
-            Db::SqliteDb(db) => Ok(Self::from_sqlitedb_model(&db.select_collection(id).await?)?),
+        if let Some(collection_data) = CACHE.get(id) {
+            return Ok(collection_data);
         }
+
+        let not_found =
+            |err| DaoError::from_no_rows(err, format!("Collection '{id}' is not found"));
+        let collection_data = match db {
+            Db::ScyllaDb(db) => {
+                Self::from_scylladb_model(&db.select_collection(id).await.map_err(not_found)?)?
+            }
+            Db::PostgresqlDb(db) => {
+                Self::from_postgresdb_model(&db.select_collection(id).await.map_err(not_found)?)?
+            }
+            Db::MysqlDb(db) => {
+                Self::from_mysqldb_model(&db.select_collection(id).await.map_err(not_found)?)?
+            }
+            Db::SqliteDb(db) => {
+                Self::from_sqlitedb_model(&db.select_collection(id).await.map_err(not_found)?)?
+            }
+        };
+        collection_data.cache_insert();
+
+        Ok(collection_data)
     }
 
-    pub async fn db_select_many_by_project_id(db: &Db, project_id: &Uuid) -> Result<Vec<Self>> {
+    #[instrument(name = "CollectionDao::db_select_many", skip_all)]
+    pub async fn db_select_many(db: &Db) -> Result<Vec<Self>> {
+        let collections_data = match db {
+            Db::ScyllaDb(db) => {
+                let mut collections_data = Vec::new();
+                let mut collections = db.select_many_collections().await?;
+                while let Some(collection) = collections.try_next().await? {
+                    collections_data.push(Self::from_scylladb_model(&collection)?);
+                }
+                collections_data
+            }
+            Db::PostgresqlDb(db) => {
+                let collections = db.select_many_collections().await?;
+                let mut collections_data = Vec::with_capacity(collections.len());
+                for collection in &collections {
+                    collections_data.push(Self::from_postgresdb_model(collection)?);
+                }
+                collections_data
+            }
+            Db::MysqlDb(db) => {
+                let collections = db.select_many_collections().await?;
+                let mut collections_data = Vec::with_capacity(collections.len());
+                for collection in &collections {
+                    collections_data.push(Self::from_mysqldb_model(collection)?);
+                }
+                collections_data
+            }
+            Db::SqliteDb(db) => {
+                let collections = db.select_many_collections().await?;
+                let mut collections_data = Vec::with_capacity(collections.len());
+                for collection in &collections {
+                    collections_data.push(Self::from_sqlitedb_model(collection)?);
+                }
+                collections_data
+            }
+        };
+
+        for collection_data in &collections_data {
+            collection_data.cache_insert();
+        }
+
+        Ok(collections_data)
+    }
+
+    #[instrument(name = "CollectionDao::db_select_many_by_project_id", skip_all)]
+    pub async fn db_select_many_by_project_id(
+        db: &Db,
+        project_id: &Uuid,
+        after_id: &Option<Uuid>,
+        search: &Option<String>,
+        order: &CollectionOrder,
+        limit: &Option<i32>,
+    ) -> Result<Vec<Self>> {
+        let order_by_name = matches!(order, CollectionOrder::NameAsc | CollectionOrder::NameDesc);
+        let order_desc = matches!(
+            order,
+            CollectionOrder::CreatedAtDesc | CollectionOrder::NameDesc
+        );
         match db {
             Db::ScyllaDb(db) => {
                 let mut collections_data = Vec::new();
@@ -200,10 +561,43 @@ impl CollectionDao {
                 for collection in collections {
                     collections_data.push(Self::from_scylladb_model(&collection?)?);
                 }
+                // ScyllaDB can't search or sort the collections of a project, so the page is
+                // made here with the same rules as the SQL backends
+                collections_data.sort_by(|a, b| order.compare(a, b));
+                if let Some(after_id) = after_id {
+                    match collections_data
+                        .iter()
+                        .position(|data| &data.id == after_id)
+                    {
+                        Some(idx) => {
+                            collections_data.drain(..=idx);
+                        }
+                        None => collections_data.retain(|data| match order {
+                            CollectionOrder::CreatedAtAsc => &data.id > after_id,
+                            CollectionOrder::CreatedAtDesc => &data.id < after_id,
+                            _ => false,
+                        }),
+                    }
+                }
+                if let Some(search) = search {
+                    collections_data.retain(|data| data.matches_search(search));
+                }
+                if let Some(limit) = limit {
+                    collections_data.truncate(usize::try_from(*limit).unwrap_or(0));
+                }
                 Ok(collections_data)
             }
             Db::PostgresqlDb(db) => {
-                let collections = db.select_many_collections_by_project_id(project_id).await?;
+                let collections = db
+                    .select_many_collections_by_project_id(
+                        project_id,
+                        after_id,
+                        search,
+                        &order_by_name,
+                        &order_desc,
+                        limit,
+                    )
+                    .await?;
                 let mut collections_data = Vec::with_capacity(collections.len());
                 for collection in &collections {
                     collections_data.push(Self::from_postgresdb_model(collection)?);
@@ -211,7 +605,16 @@ impl CollectionDao {
                 Ok(collections_data)
             }
             Db::MysqlDb(db) => {
-                let collections = db.select_many_collections_by_project_id(project_id).await?;
+                let collections = db
+                    .select_many_collections_by_project_id(
+                        project_id,
+                        after_id,
+                        search,
+                        &order_by_name,
+                        &order_desc,
+                        limit,
+                    )
+                    .await?;
                 let mut collections_data = Vec::with_capacity(collections.len());
                 for collection in &collections {
                     collections_data.push(Self::from_mysqldb_model(collection)?);
@@ -219,7 +622,16 @@ impl CollectionDao {
                 Ok(collections_data)
             }
             Db::SqliteDb(db) => {
-                let collections = db.select_many_collections_by_project_id(project_id).await?;
+                let collections = db
+                    .select_many_collections_by_project_id(
+                        project_id,
+                        after_id,
+                        search,
+                        &order_by_name,
+                        &order_desc,
+                        limit,
+                    )
+                    .await?;
                 let mut collections_data = Vec::with_capacity(collections.len());
                 for collection in &collections {
                     collections_data.push(Self::from_sqlitedb_model(collection)?);
@@ -229,28 +641,140 @@ impl CollectionDao {
         }
     }
 
-    async fn db_update_prepare(&mut self, db: &Db) -> Result<()> {
+    #[instrument(name = "CollectionDao::db_count_many_by_project_id", skip_all)]
+    pub async fn db_count_many_by_project_id(
+        db: &Db,
+        project_id: &Uuid,
+        search: &Option<String>,
+    ) -> Result<i64> {
         match db {
-            Db::MysqlDb(_) => {
-                for (field, props) in &self.schema_fields {
-                    if props.indexed {
-                        match &props.kind {
-                        ColumnKind::Binary
-                        | ColumnKind::Varint
-                        | ColumnKind::Decimal
-                        | ColumnKind::Json => {
-                            return Err(Error::msg(format!(
-                                "Field '{}' has type '{}' that doesn't support indexing in the data type implementation of Hyperbase for MySQL",
-                                field,
-                                props.kind.to_str()
-                            )))
+            Db::ScyllaDb(db) => {
+                let mut total = 0;
+                let collections = db.select_many_collections_by_project_id(project_id).await?;
+                for collection in collections {
+                    let collection_data = Self::from_scylladb_model(&collection?)?;
+                    if let Some(search) = search {
+                        if !collection_data.matches_search(search) {
+                            continue;
                         }
-                        _ => (),
-                    };
                     }
+                    total += 1;
+                }
+                Ok(total)
+            }
+            Db::PostgresqlDb(db) => {
+                db.count_many_collections_by_project_id(project_id, search)
+                    .await
+            }
+            Db::MysqlDb(db) => {
+                db.count_many_collections_by_project_id(project_id, search)
+                    .await
+            }
+            Db::SqliteDb(db) => {
+                db.count_many_collections_by_project_id(project_id, search)
+                    .await
+            }
+        }
+    }
+
+    async fn db_update_prepare(&mut self, db: &Db) -> Result<()> {
+        self.check_opt_soft_delete(db)?;
+        self.check_opt_per_record_ttl(db)?;
+        self.check_unique_indexes(db)?;
+
+        for (field, props) in &self.schema_fields {
+            if props.indexed || props.unique {
+                Self::check_indexable(db, field, props)?;
+            }
+        }
+
+        // Unique indexes are named after their fields, so the ones that are gone or have a renamed
+        // field are dropped before any column is renamed or dropped and the new ones are made last
+        let mut create_unique_indexes = Vec::new();
+        if let Some(Some(preserved_unique_indexes)) = self
+            ._preserve
+            .as_ref()
+            .map(|preserve| &preserve.unique_indexes)
+        {
+            let mut drop_unique_indexes_fut = Vec::with_capacity(preserved_unique_indexes.len());
+            for unique_index in preserved_unique_indexes {
+                if !self.unique_indexes.contains(unique_index) {
+                    drop_unique_indexes_fut.push(RecordDao::db_drop_unique_index(
+                        db,
+                        &self.id,
+                        unique_index,
+                    ));
+                }
+            }
+            if !drop_unique_indexes_fut.is_empty() {
+                future::try_join_all(drop_unique_indexes_fut).await?;
+            }
+            for unique_index in &self.unique_indexes {
+                if !preserved_unique_indexes.contains(unique_index) {
+                    create_unique_indexes.push(unique_index);
                 }
             }
-            _ => (),
+        }
+
+        if let Some(preserve) = &self._preserve {
+            if let Some(rename_fields) = &preserve.rename_fields {
+                // Indexes are named after their field, so the ones of a renamed field are made
+                // again under the new name
+                let renamed_schema_fields = match &preserve.schema_fields {
+                    Some(schema_fields) => schema_fields,
+                    None => &self.schema_fields,
+                };
+                let mut drop_indexes_fut = Vec::with_capacity(rename_fields.len());
+                let mut drop_unique_indexes_fut = Vec::with_capacity(rename_fields.len());
+                for (field_name, new_field_name) in rename_fields {
+                    if let Some(props) = renamed_schema_fields.get(new_field_name) {
+                        if props.indexed {
+                            drop_indexes_fut
+                                .push(RecordDao::db_drop_index(db, &self.id, field_name));
+                        }
+                        if props.unique {
+                            drop_unique_indexes_fut.push(RecordDao::db_drop_unique_index(
+                                db,
+                                &self.id,
+                                std::slice::from_ref(field_name),
+                            ));
+                        }
+                    }
+                }
+                if !drop_indexes_fut.is_empty() {
+                    future::try_join_all(drop_indexes_fut).await?;
+                }
+                if !drop_unique_indexes_fut.is_empty() {
+                    future::try_join_all(drop_unique_indexes_fut).await?;
+                }
+
+                RecordDao::db_rename_columns(db, &self.id, rename_fields).await?;
+
+                let mut create_indexes_fut = Vec::with_capacity(rename_fields.len());
+                let mut create_unique_indexes_fut = Vec::with_capacity(rename_fields.len());
+                for new_field_name in rename_fields.values() {
+                    if let Some(props) = renamed_schema_fields.get(new_field_name) {
+                        if props.indexed {
+                            create_indexes_fut.push(RecordDao::db_create_index(
+                                db,
+                                &self.id,
+                                new_field_name,
+                            ));
+                        }
+                        if props.unique {
+                            create_unique_indexes_fut.push(RecordDao::db_create_unique_index(
+                                db,
+                                &self.id,
+                                std::slice::from_ref(new_field_name),
+                            ));
+                        }
+                    }
+                }
+                tokio::try_join!(
+                    future::try_join_all(create_indexes_fut),
+                    future::try_join_all(create_unique_indexes_fut)
+                )?;
+            }
         }
 
         let mut already_indexed = HashSet::new();
@@ -280,12 +804,14 @@ impl CollectionDao {
                                     already_unique_indexed.insert(field_name.as_str());
                                 } else {
                                     drop_unique_indexes_fut.push(RecordDao::db_drop_unique_index(
-                                        db, &self.id, field_name,
+                                        db,
+                                        &self.id,
+                                        std::slice::from_ref(field_name),
                                     ));
                                 }
                             }
                             if field_props.kind() != props.kind() {
-                                columns_change_type.insert(field_name.to_owned(), *props);
+                                columns_change_type.insert(field_name.to_owned(), props.clone());
                             }
                         }
                         None => {
@@ -294,8 +820,11 @@ impl CollectionDao {
                                     .push(RecordDao::db_drop_index(db, &self.id, field_name));
                             }
                             if field_props.unique {
-                                drop_unique_indexes_fut
-                                    .push(RecordDao::db_drop_unique_index(db, &self.id, field_name))
+                                drop_unique_indexes_fut.push(RecordDao::db_drop_unique_index(
+                                    db,
+                                    &self.id,
+                                    std::slice::from_ref(field_name),
+                                ))
                             }
                             columns_drop.insert(field_name.clone());
                         }
@@ -325,11 +854,63 @@ impl CollectionDao {
                         .unwrap()
                         .contains_key(field_name)
                     {
-                        columns_add.insert(field_name.to_owned(), *field_props);
+                        columns_add.insert(field_name.to_owned(), field_props.clone());
                     }
                 }
                 if !columns_add.is_empty() {
                     RecordDao::db_add_columns(db, &self.id, &columns_add).await?;
+                    for (field_name, field_props) in &columns_add {
+                        let value = match preserve.backfill_values.get(field_name) {
+                            Some(value) => value,
+                            None => match &field_props.default {
+                                Some(default) if preserve.backfill_defaults => default,
+                                _ => continue,
+                            },
+                        };
+                        RecordDao::db_backfill_column(db, &self.id, field_name, value).await?;
+                    }
+                }
+            }
+        }
+
+        if let Some(Some(preserved_opt_soft_delete)) = self
+            ._preserve
+            .as_ref()
+            .map(|preserve| preserve.opt_soft_delete)
+        {
+            if preserved_opt_soft_delete != self.opt_soft_delete {
+                if self.opt_soft_delete {
+                    let mut columns_add = HashMap::with_capacity(1);
+                    columns_add.insert("_deleted_at".to_owned(), SchemaFieldProps::deleted_at());
+                    RecordDao::db_add_columns(db, &self.id, &columns_add).await?;
+                } else {
+                    // Records that are still soft-deleted would show up again once the column is
+                    // gone, so they're removed for good first
+                    RecordDao::db_purge_deleted(db, &self.id).await?;
+                    let mut columns_drop = HashSet::with_capacity(1);
+                    columns_drop.insert("_deleted_at".to_owned());
+                    RecordDao::db_drop_columns(db, &self.id, &columns_drop).await?;
+                }
+            }
+        }
+
+        if let Some(Some(preserved_opt_per_record_ttl)) = self
+            ._preserve
+            .as_ref()
+            .map(|preserve| preserve.opt_per_record_ttl)
+        {
+            if preserved_opt_per_record_ttl != self.opt_per_record_ttl {
+                if self.opt_per_record_ttl {
+                    let mut columns_add = HashMap::with_capacity(1);
+                    columns_add.insert("_expired_at".to_owned(), SchemaFieldProps::expired_at());
+                    RecordDao::db_add_columns(db, &self.id, &columns_add).await?;
+                } else {
+                    // Records that are already expired would never be swept once the column is
+                    // gone, so they're removed first
+                    RecordDao::db_delete_expired_by_record(db, &self.id).await?;
+                    let mut columns_drop = HashSet::with_capacity(1);
+                    columns_drop.insert("_expired_at".to_owned());
+                    RecordDao::db_drop_columns(db, &self.id, &columns_drop).await?;
                 }
             }
         }
@@ -341,10 +922,20 @@ impl CollectionDao {
                 create_indexes_fut.push(RecordDao::db_create_index(db, &self.id, field));
             }
             if props.unique && !already_unique_indexed.contains(field.as_str()) {
-                create_unique_indexes_fut
-                    .push(RecordDao::db_create_unique_index(db, &self.id, field))
+                create_unique_indexes_fut.push(RecordDao::db_create_unique_index(
+                    db,
+                    &self.id,
+                    std::slice::from_ref(field),
+                ))
             }
         }
+        for unique_index in create_unique_indexes {
+            create_unique_indexes_fut.push(RecordDao::db_create_unique_index(
+                db,
+                &self.id,
+                unique_index,
+            ));
+        }
 
         tokio::try_join!(
             future::try_join_all(create_indexes_fut),
@@ -354,41 +945,166 @@ impl CollectionDao {
         Ok(())
     }
 
+    // The schema changes and the collection are written in one transaction, which only rolls the
+    // schema back on PostgreSQL and SQLite. MySQL commits on every schema change and ScyllaDB has
+    // no transactions, so there a failure halfway leaves the changes before it made.
+    #[instrument(name = "CollectionDao::db_update", skip_all)]
     pub async fn db_update(&mut self, db: &Db) -> Result<()> {
-        self.db_update_prepare(db).await?;
-
         self.updated_at = Utc::now();
+        self.db_update_in_transaction(db).await
+    }
+
+    #[instrument(name = "CollectionDao::db_update_raw", skip_all)]
+    pub async fn db_update_raw(&mut self, db: &Db) -> Result<()> {
+        self.db_update_in_transaction(db).await
+    }
+
+    async fn db_update_in_transaction(&mut self, db: &Db) -> Result<()> {
+        let tx = db.begin().await?;
 
+        self.db_update_prepare(&tx).await?;
+
+        match &*tx {
+            Db::ScyllaDb(db) => db.update_collection(&self.to_scylladb_model()).await?,
+            Db::PostgresqlDb(db) => db.update_collection(&self.to_postgresdb_model()).await?,
+            Db::MysqlDb(db) => db.update_collection(&self.to_mysqldb_model()).await?,
+            Db::SqliteDb(db) => db.update_collection(&self.to_sqlitedb_model()).await?,
+        }
+
+        tx.commit().await?;
+
+        self.cache_insert();
+
+        Ok(())
+    }
+
+    // ScyllaDB has no transactions, so the steps are done one by one and a failure halfway leaves
+    // the ones before it done. Running the delete again finishes it.
+    #[instrument(name = "CollectionDao::db_delete", skip_all)]
+    pub async fn db_delete(db: &Db, id: &Uuid) -> Result<()> {
         match db {
-            Db::ScyllaDb(db) => db.update_collection(&self.to_scylladb_model()).await,
-            Db::PostgresqlDb(db) => db.update_collection(&self.to_postgresdb_model()).await,
-            Db::MysqlDb(db) => db.update_collection(&self.to_mysqldb_model()).await,
-            Db::SqliteDb(db) => db.update_collection(&self.to_sqlitedb_model()).await,
+            Db::ScyllaDb(scylla_db) => {
+                RecordDao::db_drop_table(db, id).await?;
+                CollectionRuleDao::db_delete_many_by_collection_id(db, id).await?;
+                WebhookDao::db_delete_many_by_collection_id(db, id).await?;
+                scylla_db.delete_collection(id).await?;
+            }
+            Db::PostgresqlDb(db) => {
+                db.delete_collection(id, &RecordDao::new_table_name(id))
+                    .await?
+            }
+            Db::MysqlDb(mysql_db) => {
+                RecordDao::db_drop_table(db, id).await?;
+                mysql_db.delete_collection(id).await?;
+            }
+            Db::SqliteDb(db) => {
+                db.delete_collection(id, &RecordDao::new_table_name(id))
+                    .await?
+            }
         }
+
+        CACHE.remove(id);
+
+        Ok(())
     }
 
-    pub async fn db_update_raw(&mut self, db: &Db) -> Result<()> {
-        self.db_update_prepare(db).await?;
+    // Searching by name is case-insensitive, like the INSTR(LOWER(...)) of the SQL backends
+    fn matches_search(&self, search: &str) -> bool {
+        self.name.to_lowercase().contains(&search.to_lowercase())
+    }
 
-        match db {
-            Db::ScyllaDb(db) => db.update_collection(&self.to_scylladb_model()).await,
-            Db::PostgresqlDb(db) => db.update_collection(&self.to_postgresdb_model()).await,
-            Db::MysqlDb(db) => db.update_collection(&self.to_mysqldb_model()).await,
-            Db::SqliteDb(db) => db.update_collection(&self.to_sqlitedb_model()).await,
+    fn check_unique_indexes(&self, db: &Db) -> Result<()> {
+        if self.unique_indexes.is_empty() {
+            return Ok(());
+        }
+        if let Db::ScyllaDb(_) = db {
+            return Err(Error::msg(
+                "Composite unique indexes are not supported in ScyllaDB because it has no unique indexes",
+            ));
         }
+        for (idx, unique_index) in self.unique_indexes.iter().enumerate() {
+            if unique_index.len() < 2 {
+                return Err(Error::msg(
+                    "Unique index must span at least 2 fields, a single field is made unique with its 'unique' option",
+                ));
+            }
+            let mut field_names = HashSet::with_capacity(unique_index.len());
+            for field_name in unique_index {
+                let props = match self.schema_fields.get(field_name) {
+                    Some(props) => props,
+                    None => {
+                        return Err(Error::msg(format!(
+                        "Field '{field_name}' of unique index '{}' is not exist in the collection",
+                        unique_index.join("_")
+                    )))
+                    }
+                };
+                if !field_names.insert(field_name) {
+                    return Err(Error::msg(format!(
+                        "Field '{field_name}' appears more than once in unique index '{}'",
+                        unique_index.join("_")
+                    )));
+                }
+                Self::check_indexable(db, field_name, props)?;
+            }
+            if self.unique_indexes[..idx].contains(unique_index) {
+                return Err(Error::msg(format!(
+                    "Unique index '{}' appears more than once",
+                    unique_index.join("_")
+                )));
+            }
+        }
+        Ok(())
     }
 
-    pub async fn db_delete(db: &Db, id: &Uuid) -> Result<()> {
-        RecordDao::db_drop_table(db, id).await?;
+    fn check_indexable(db: &Db, field: &str, props: &SchemaFieldProps) -> Result<()> {
+        if let Db::MysqlDb(_) = db {
+            match &props.kind {
+                ColumnKind::Binary
+                | ColumnKind::Varint
+                | ColumnKind::Decimal
+                | ColumnKind::Json
+                | ColumnKind::StringArray
+                | ColumnKind::IntArray
+                | ColumnKind::Point => {
+                    return Err(Error::msg(format!(
+                        "Field '{}' has type '{}' that doesn't support indexing in the data type implementation of Hyperbase for MySQL",
+                        field,
+                        props.kind.to_str()
+                    )))
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
 
-        CollectionRuleDao::db_delete_many_by_collection_id(db, id).await?;
+    fn check_opt_soft_delete(&self, db: &Db) -> Result<()> {
+        if self.opt_soft_delete {
+            if let Db::ScyllaDb(_) = db {
+                return Err(Error::msg(
+                    "Soft delete is not supported in ScyllaDB because it can't filter records that have no '_deleted_at' value",
+                ));
+            }
+        }
+        Ok(())
+    }
 
-        match db {
-            Db::ScyllaDb(db) => db.delete_collection(id).await,
-            Db::PostgresqlDb(db) => db.delete_collection(id).await,
-            Db::MysqlDb(db) => db.delete_collection(id).await,
-            Db::SqliteDb(db) => db.delete_collection(id).await,
+    fn check_opt_per_record_ttl(&self, db: &Db) -> Result<()> {
+        if self.opt_per_record_ttl {
+            if let Db::ScyllaDb(_) = db {
+                return Err(Error::msg(
+                    "Per-record TTL is not supported in ScyllaDB because it can't delete records by their '_expired_at' value",
+                ));
+            }
         }
+        Ok(())
+    }
+
+    fn cache_insert(&self) {
+        let mut collection_data = self.clone();
+        collection_data._preserve = None;
+        CACHE.insert(&collection_data.id, &collection_data);
     }
 
     fn from_scylladb_model(model: &CollectionScyllaModel) -> Result<Self> {
@@ -410,8 +1126,15 @@ impl CollectionDao {
             project_id: *model.project_id(),
             name: model.name().to_owned(),
             schema_fields,
+            // Unique indexes are never made on ScyllaDB, so they aren't stored there
+            unique_indexes: Vec::new(),
             opt_auth_column_id: *model.opt_auth_column_id(),
             opt_ttl: *model.opt_ttl(),
+            // Soft delete and per-record TTL are never enabled on ScyllaDB, so they aren't stored
+            // there
+            opt_soft_delete: false,
+            opt_per_record_ttl: false,
+            opt_mqtt_publish: *model.opt_mqtt_publish(),
             _preserve: None,
         })
     }
@@ -431,6 +1154,7 @@ impl CollectionDao {
             ),
             &self.opt_auth_column_id,
             &self.opt_ttl,
+            &self.opt_mqtt_publish,
         )
     }
 
@@ -450,8 +1174,12 @@ impl CollectionDao {
             project_id: *model.project_id(),
             name: model.name().to_owned(),
             schema_fields,
+            unique_indexes: model.unique_indexes().0.clone(),
             opt_auth_column_id: *model.opt_auth_column_id(),
             opt_ttl: *model.opt_ttl(),
+            opt_soft_delete: *model.opt_soft_delete(),
+            opt_per_record_ttl: *model.opt_per_record_ttl(),
+            opt_mqtt_publish: *model.opt_mqtt_publish(),
             _preserve: None,
         })
     }
@@ -469,8 +1197,12 @@ impl CollectionDao {
                     .map(|(key, value)| (key.to_owned(), value.to_postgresdb_model()))
                     .collect(),
             ),
+            &sqlx::types::Json(self.unique_indexes.clone()),
             &self.opt_auth_column_id,
             &self.opt_ttl,
+            &self.opt_soft_delete,
+            &self.opt_per_record_ttl,
+            &self.opt_mqtt_publish,
         )
     }
 
@@ -490,8 +1222,12 @@ impl CollectionDao {
             project_id: *model.project_id(),
             name: model.name().to_owned(),
             schema_fields,
+            unique_indexes: model.unique_indexes().0.clone(),
             opt_auth_column_id: *model.opt_auth_column_id(),
             opt_ttl: *model.opt_ttl(),
+            opt_soft_delete: *model.opt_soft_delete(),
+            opt_per_record_ttl: *model.opt_per_record_ttl(),
+            opt_mqtt_publish: *model.opt_mqtt_publish(),
             _preserve: None,
         })
     }
@@ -509,8 +1245,12 @@ impl CollectionDao {
                     .map(|(key, value)| (key.to_owned(), value.to_mysqldb_model()))
                     .collect(),
             ),
+            &sqlx::types::Json(self.unique_indexes.clone()),
             &self.opt_auth_column_id,
             &self.opt_ttl,
+            &self.opt_soft_delete,
+            &self.opt_per_record_ttl,
+            &self.opt_mqtt_publish,
         )
     }
 
@@ -530,8 +1270,12 @@ impl CollectionDao {
             project_id: *model.project_id(),
             name: model.name().to_owned(),
             schema_fields,
+            unique_indexes: model.unique_indexes().0.clone(),
             opt_auth_column_id: *model.opt_auth_column_id(),
             opt_ttl: *model.opt_ttl(),
+            opt_soft_delete: *model.opt_soft_delete(),
+            opt_per_record_ttl: *model.opt_per_record_ttl(),
+            opt_mqtt_publish: *model.opt_mqtt_publish(),
             _preserve: None,
         })
     }
@@ -549,13 +1293,17 @@ impl CollectionDao {
                     .map(|(key, value)| (key.to_owned(), value.to_sqlitedb_model()))
                     .collect(),
             ),
+            &sqlx::types::Json(self.unique_indexes.clone()),
             &self.opt_auth_column_id,
             &self.opt_ttl,
+            &self.opt_soft_delete,
+            &self.opt_per_record_ttl,
+            &self.opt_mqtt_publish,
         )
     }
 }
 
-#[derive(Deserialize, Serialize, Clone, Copy)]
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
 pub struct SchemaFieldProps {
     kind: ColumnKind,
     required: bool,
@@ -564,6 +1312,15 @@ pub struct SchemaFieldProps {
     auth_column: bool,
     hashed: bool,
     hidden: bool,
+    default: Option<ColumnValue>,
+    min: Option<f64>,
+    max: Option<f64>,
+    max_length: Option<i64>,
+    regex: Option<String>,
+    enum_values: Option<Vec<ColumnValue>>,
+    on_delete: Option<ReferenceAction>,
+    #[serde(skip)]
+    compiled_regex: CompiledRegex,
 }
 
 impl SchemaFieldProps {
@@ -575,11 +1332,141 @@ impl SchemaFieldProps {
         auth_column: &bool,
         hashed: &bool,
         hidden: &bool,
+        default: &Option<serde_json::Value>,
+        min: &Option<f64>,
+        max: &Option<f64>,
+        max_length: &Option<i64>,
+        regex: &Option<String>,
+        enum_values: &Option<Vec<serde_json::Value>>,
+        on_delete: &Option<ReferenceAction>,
     ) -> Result<Self> {
         if *hashed && *kind != ColumnKind::String {
             return Err(Error::msg("Hashed field must be of type string"));
         }
-        Ok(Self {
+        if let Some(on_delete) = on_delete {
+            if kind.reference_collection_id().is_none() {
+                return Err(Error::msg(
+                    "Option 'on_delete' can only be used on a field of type 'reference'",
+                ));
+            }
+            if *required && *on_delete == ReferenceAction::SetNull {
+                return Err(Error::msg(
+                    "Option 'on_delete' of a required field can't be 'set_null'",
+                ));
+            }
+        }
+        if *hashed
+            && (min.is_some()
+                || max.is_some()
+                || max_length.is_some()
+                || regex.is_some()
+                || enum_values.is_some())
+        {
+            return Err(Error::msg(
+                "Hashed field can't have validation rules because only its hash is stored",
+            ));
+        }
+        if min.is_some() || max.is_some() {
+            if !matches!(
+                kind,
+                ColumnKind::TinyInt
+                    | ColumnKind::SmallInt
+                    | ColumnKind::Int
+                    | ColumnKind::BigInt
+                    | ColumnKind::Varint
+                    | ColumnKind::Float
+                    | ColumnKind::Double
+                    | ColumnKind::Decimal
+            ) {
+                return Err(Error::msg(
+                    "Rules 'min' and 'max' can only be used on a field of a numeric type",
+                ));
+            }
+            if min.is_some_and(|min| !min.is_finite()) || max.is_some_and(|max| !max.is_finite()) {
+                return Err(Error::msg("Rules 'min' and 'max' must be finite numbers"));
+            }
+            if let (Some(min), Some(max)) = (min, max) {
+                if min > max {
+                    return Err(Error::msg("Rule 'min' must not be greater than rule 'max'"));
+                }
+            }
+        }
+        if let Some(max_length) = max_length {
+            if *kind != ColumnKind::String {
+                return Err(Error::msg(
+                    "Rule 'max_length' can only be used on a field of type 'string'",
+                ));
+            }
+            if *max_length < 0 {
+                return Err(Error::msg("Rule 'max_length' must not be negative"));
+            }
+        }
+        let compiled_regex = CompiledRegex::default();
+        if let Some(regex) = regex {
+            if *kind != ColumnKind::String {
+                return Err(Error::msg(
+                    "Rule 'regex' can only be used on a field of type 'string'",
+                ));
+            }
+            match Regex::new(regex) {
+                Ok(compiled) => {
+                    let _ = compiled_regex.0.set(compiled);
+                }
+                Err(err) => return Err(Error::msg(format!("Rule 'regex' is invalid: {err}"))),
+            }
+        }
+        let enum_values = match enum_values {
+            Some(enum_values) => {
+                if matches!(
+                    kind,
+                    ColumnKind::Binary
+                        | ColumnKind::Json
+                        | ColumnKind::StringArray
+                        | ColumnKind::IntArray
+                        | ColumnKind::Counter
+                        | ColumnKind::Point
+                        | ColumnKind::Reference { .. }
+                ) {
+                    return Err(Error::msg(format!(
+                        "Rule 'enum' can't be used on a field of type '{}'",
+                        kind.to_str()
+                    )));
+                }
+                if enum_values.is_empty() {
+                    return Err(Error::msg("Rule 'enum' must have at least one value"));
+                }
+                let mut values = Vec::with_capacity(enum_values.len());
+                for value in enum_values {
+                    if value.is_null() {
+                        return Err(Error::msg("Rule 'enum' must not contain null"));
+                    }
+                    match ColumnValue::from_serde_json(kind, value) {
+                        Ok(value) => values.push(value),
+                        Err(err) => {
+                            return Err(Error::msg(format!("Rule 'enum' has invalid value: {err}")))
+                        }
+                    }
+                }
+                Some(values)
+            }
+            None => None,
+        };
+        let default = match default {
+            Some(serde_json::Value::Null) | None => None,
+            Some(default) => {
+                if *hashed {
+                    return Err(Error::msg("Hashed field can't have a default value"));
+                }
+                if *kind == ColumnKind::Counter {
+                    return Err(Error::msg("Counter field can't have a default value"));
+                }
+                match ColumnValue::from_serde_json(kind, default) {
+                    Ok(default) => Some(default),
+                    Err(err) => return Err(Error::msg(format!("Invalid default value: {err}"))),
+                }
+            }
+        };
+        let mut props = Self {
             kind: *kind,
             required: *required,
             unique: *unique,
@@ -587,7 +1474,65 @@ impl SchemaFieldProps {
             auth_column: *auth_column,
             hashed: *hashed,
             hidden: *hidden,
-        })
+            default: None,
+            min: *min,
+            max: *max,
+            max_length: *max_length,
+            regex: regex.clone(),
+            enum_values,
+            on_delete: *on_delete,
+            compiled_regex,
+        };
+        if let Some(default) = default {
+            if let Err(err) = props.validate(&default) {
+                return Err(Error::msg(format!("Invalid default value: {err}")));
+            }
+            props.default = Some(default);
+        }
+        Ok(props)
+    }
+
+    // System column that marks a record as deleted in a collection with soft delete enabled
+    pub(crate) fn deleted_at() -> Self {
+        Self {
+            kind: ColumnKind::Timestamp,
+            required: false,
+            unique: false,
+            indexed: false,
+            auth_column: false,
+            hashed: false,
+            hidden: false,
+            default: None,
+            min: None,
+            max: None,
+            max_length: None,
+            regex: None,
+            enum_values: None,
+            on_delete: None,
+            compiled_regex: CompiledRegex::default(),
+        }
+    }
+
+    // System column that holds the time a record expires in a collection with per-record TTL
+    // enabled, records without a value never expire
+    pub(crate) fn expired_at() -> Self {
+        Self {
+            kind: ColumnKind::Timestamp,
+            required: false,
+            unique: false,
+            indexed: false,
+            auth_column: false,
+            hashed: false,
+            hidden: false,
+            default: None,
+            min: None,
+            max: None,
+            max_length: None,
+            regex: None,
+            enum_values: None,
+            on_delete: None,
+            compiled_regex: CompiledRegex::default(),
+        }
     }
 
     pub fn kind(&self) -> &ColumnKind {
@@ -618,8 +1563,152 @@ impl SchemaFieldProps {
         &self.hidden
     }
 
+    pub fn default(&self) -> &Option<ColumnValue> {
+        &self.default
+    }
+
+    pub fn min(&self) -> &Option<f64> {
+        &self.min
+    }
+
+    pub fn max(&self) -> &Option<f64> {
+        &self.max
+    }
+
+    pub fn max_length(&self) -> &Option<i64> {
+        &self.max_length
+    }
+
+    pub fn regex(&self) -> &Option<String> {
+        &self.regex
+    }
+
+    pub fn enum_values(&self) -> &Option<Vec<ColumnValue>> {
+        &self.enum_values
+    }
+
+    pub fn on_delete(&self) -> &Option<ReferenceAction> {
+        &self.on_delete
+    }
+
+    // Converts a value sent by a client and checks it against the validation rules of the field
+    pub fn value_from_serde_json(&self, value: &serde_json::Value) -> Result<ColumnValue> {
+        let value = ColumnValue::from_serde_json(&self.kind, value)?;
+        self.validate(&value)?;
+        Ok(value)
+    }
+
+    pub fn validate(&self, value: &ColumnValue) -> Result<()> {
+        if self.min.is_some() || self.max.is_some() {
+            let number = match value {
+                ColumnValue::TinyInteger(Some(data)) => Some(f64::from(*data)),
+                ColumnValue::SmallInteger(Some(data)) => Some(f64::from(*data)),
+                ColumnValue::Integer(Some(data)) => Some(f64::from(*data)),
+                ColumnValue::BigInteger(Some(data)) => Some(*data as f64),
+                ColumnValue::VarInteger(Some(data)) => data.to_string().parse().ok(),
+                ColumnValue::Float(Some(data)) => Some(f64::from(*data)),
+                ColumnValue::Double(Some(data)) => Some(*data),
+                ColumnValue::Decimal(Some(data)) => data.to_string().parse().ok(),
+                _ => None,
+            };
+            if let Some(number) = number {
+                if let Some(min) = self.min {
+                    if number < min {
+                        return Err(Error::msg(format!(
+                            "Value violates rule 'min': it must be greater than or equal to {min}"
+                        )));
+                    }
+                }
+                if let Some(max) = self.max {
+                    if number > max {
+                        return Err(Error::msg(format!(
+                            "Value violates rule 'max': it must be less than or equal to {max}"
+                        )));
+                    }
+                }
+            }
+        }
+        if let ColumnValue::String(Some(data)) = value {
+            if let Some(max_length) = self.max_length {
+                if data.chars().count() as i64 > max_length {
+                    return Err(Error::msg(format!(
+                        "Value violates rule 'max_length': it must be at most {max_length} characters long"
+                    )));
+                }
+            }
+            if let Some(regex) = &self.regex {
+                if !self.compiled_regex.get(regex)?.is_match(data) {
+                    return Err(Error::msg(format!(
+                        "Value violates rule 'regex': it must match the pattern '{regex}'"
+                    )));
+                }
+            }
+        }
+        if let Some(enum_values) = &self.enum_values {
+            if *value != ColumnValue::none(&self.kind) && !enum_values.contains(value) {
+                return Err(Error::msg(
+                    "Value violates rule 'enum': it must be one of the allowed values",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Defaults are persisted as json text so every backend can store them in the same way
+    fn default_from_model(
+        kind: &ColumnKind,
+        default: &Option<String>,
+    ) -> Result<Option<ColumnValue>> {
+        match default {
+            Some(default) => Ok(Some(ColumnValue::from_serde_json(
+                kind,
+                &serde_json::from_str(default)?,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    fn default_to_model(&self) -> Option<String> {
+        self.default
+            .as_ref()
+            .and_then(|default| default.to_serde_json().ok())
+            .map(|default| default.to_string())
+    }
+
+    fn enum_values_from_model(
+        kind: &ColumnKind,
+        enum_values: &Option<String>,
+    ) -> Result<Option<Vec<ColumnValue>>> {
+        match enum_values {
+            Some(enum_values) => {
+                let enum_values = serde_json::from_str::<Vec<serde_json::Value>>(enum_values)?;
+                let mut values = Vec::with_capacity(enum_values.len());
+                for value in &enum_values {
+                    values.push(ColumnValue::from_serde_json(kind, value)?);
+                }
+                Ok(Some(values))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn enum_values_to_model(&self) -> Option<String> {
+        self.enum_values.as_ref().map(|enum_values| {
+            serde_json::Value::Array(
+                enum_values
+                    .iter()
+                    .filter_map(|value| value.to_serde_json().ok())
+                    .collect(),
+            )
+            .to_string()
+        })
+    }
+
     fn from_scylladb_model(model: &SchemaFieldPropsScyllaModel) -> Result<Self> {
-        let kind = match ColumnKind::from_str(model.kind()) {
+        let kind = match ColumnKind::from_str_with_reference(
+            model.kind(),
+            model.reference_collection_id(),
+        ) {
             Ok(kind) => kind,
             Err(err) => return Err(err),
         };
@@ -631,6 +1720,17 @@ impl SchemaFieldProps {
             auth_column: *model.auth_column(),
             hashed: *model.hashed(),
             hidden: *model.hidden(),
+            default: Self::default_from_model(&kind, model.default())?,
+            min: *model.min(),
+            max: *model.max(),
+            max_length: *model.max_length(),
+            regex: model.regex().clone(),
+            enum_values: Self::enum_values_from_model(&kind, model.enum_values())?,
+            on_delete: match model.on_delete() {
+                Some(on_delete) => Some(ReferenceAction::from_str(on_delete)?),
+                None => None,
+            },
+            compiled_regex: CompiledRegex::default(),
         })
     }
 
@@ -644,11 +1744,24 @@ impl SchemaFieldProps {
             &self.auth_column,
             &self.hashed,
             &self.hidden,
+            &self.default_to_model(),
+            &self.min,
+            &self.max,
+            &self.max_length,
+            &self.regex,
+            &self.enum_values_to_model(),
+            &self.kind.reference_collection_id().copied(),
+            &self
+                .on_delete
+                .map(|on_delete| on_delete.to_str().to_owned()),
         )
     }
 
     fn from_postgresdb_model(model: &SchemaFieldPropsPostgresModel) -> Result<Self> {
-        let kind = match ColumnKind::from_str(model.kind()) {
+        let kind = match ColumnKind::from_str_with_reference(
+            model.kind(),
+            model.reference_collection_id(),
+        ) {
             Ok(kind) => kind,
             Err(err) => return Err(err),
         };
@@ -660,6 +1773,17 @@ impl SchemaFieldProps {
             auth_column: *model.auth_column(),
             hashed: *model.hashed(),
             hidden: *model.hidden(),
+            default: Self::default_from_model(&kind, model.default())?,
+            min: *model.min(),
+            max: *model.max(),
+            max_length: *model.max_length(),
+            regex: model.regex().clone(),
+            enum_values: Self::enum_values_from_model(&kind, model.enum_values())?,
+            on_delete: match model.on_delete() {
+                Some(on_delete) => Some(ReferenceAction::from_str(on_delete)?),
+                None => None,
+            },
+            compiled_regex: CompiledRegex::default(),
         })
     }
 
@@ -673,11 +1797,24 @@ impl SchemaFieldProps {
             &self.auth_column,
             &self.hashed,
             &self.hidden,
+            &self.default_to_model(),
+            &self.min,
+            &self.max,
+            &self.max_length,
+            &self.regex,
+            &self.enum_values_to_model(),
+            &self.kind.reference_collection_id().copied(),
+            &self
+                .on_delete
+                .map(|on_delete| on_delete.to_str().to_owned()),
         )
     }
 
     fn from_mysqldb_model(model: &SchemaFieldPropsMysqlModel) -> Result<Self> {
-        let kind = match ColumnKind::from_str(model.kind()) {
+        let kind = match ColumnKind::from_str_with_reference(
+            model.kind(),
+            model.reference_collection_id(),
+        ) {
             Ok(kind) => kind,
             Err(err) => return Err(err),
         };
@@ -689,6 +1826,17 @@ impl SchemaFieldProps {
             auth_column: *model.auth_column(),
             hashed: *model.hashed(),
             hidden: *model.hidden(),
+            default: Self::default_from_model(&kind, model.default())?,
+            min: *model.min(),
+            max: *model.max(),
+            max_length: *model.max_length(),
+            regex: model.regex().clone(),
+            enum_values: Self::enum_values_from_model(&kind, model.enum_values())?,
+            on_delete: match model.on_delete() {
+                Some(on_delete) => Some(ReferenceAction::from_str(on_delete)?),
+                None => None,
+            },
+            compiled_regex: CompiledRegex::default(),
         })
     }
 
@@ -702,11 +1850,24 @@ impl SchemaFieldProps {
             &self.auth_column,
             &self.hashed,
             &self.hidden,
+            &self.default_to_model(),
+            &self.min,
+            &self.max,
+            &self.max_length,
+            &self.regex,
+            &self.enum_values_to_model(),
+            &self.kind.reference_collection_id().copied(),
+            &self
+                .on_delete
+                .map(|on_delete| on_delete.to_str().to_owned()),
         )
     }
 
     fn from_sqlitedb_model(model: &SchemaFieldPropsSqliteModel) -> Result<Self> {
-        let kind = match ColumnKind::from_str(model.kind()) {
+        let kind = match ColumnKind::from_str_with_reference(
+            model.kind(),
+            model.reference_collection_id(),
+        ) {
             Ok(kind) => kind,
             Err(err) => return Err(err),
         };
@@ -718,6 +1879,17 @@ impl SchemaFieldProps {
             auth_column: *model.auth_column(),
             hashed: *model.hashed(),
             hidden: *model.hidden(),
+            default: Self::default_from_model(&kind, model.default())?,
+            min: *model.min(),
+            max: *model.max(),
+            max_length: *model.max_length(),
+            regex: model.regex().clone(),
+            enum_values: Self::enum_values_from_model(&kind, model.enum_values())?,
+            on_delete: match model.on_delete() {
+                Some(on_delete) => Some(ReferenceAction::from_str(on_delete)?),
+                None => None,
+            },
+            compiled_regex: CompiledRegex::default(),
         })
     }
 
@@ -731,10 +1903,132 @@ impl SchemaFieldProps {
             &self.auth_column,
             &self.hashed,
             &self.hidden,
+            &self.default_to_model(),
+            &self.min,
+            &self.max,
+            &self.max_length,
+            &self.regex,
+            &self.enum_values_to_model(),
+            &self.kind.reference_collection_id().copied(),
+            &self
+                .on_delete
+                .map(|on_delete| on_delete.to_str().to_owned()),
         )
     }
 }
 
+// What happens to the records referencing a record when it is deleted
+// The pattern of the 'regex' rule, compiled when the field is made or else on its first use, so
+// validating a value doesn't compile it again
+#[derive(Clone, Default)]
+struct CompiledRegex(OnceLock<Regex>);
+
+impl CompiledRegex {
+    fn get(&self, pattern: &str) -> Result<&Regex> {
+        if let Some(compiled) = self.0.get() {
+            return Ok(compiled);
+        }
+        let compiled = Regex::new(pattern)?;
+        Ok(self.0.get_or_init(|| compiled))
+    }
+}
+
+// Follows from the pattern, which is compared on its own
+impl PartialEq for CompiledRegex {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone, Copy)]
+pub enum ReferenceAction {
+    Restrict, // Refuse to delete the referenced record
+    Cascade,  // Delete the referencing records too
+    SetNull,  // Clear the reference in the referencing records
+}
+
+impl ReferenceAction {
+    pub fn from_str(str: &str) -> Result<Self> {
+        match str {
+            "restrict" => Ok(Self::Restrict),
+            "cascade" => Ok(Self::Cascade),
+            "set_null" => Ok(Self::SetNull),
+            _ => Err(Error::msg(format!("Unknown reference action '{str}'"))),
+        }
+    }
+
+    pub fn to_str(&self) -> &str {
+        match self {
+            Self::Restrict => "restrict",
+            Self::Cascade => "cascade",
+            Self::SetNull => "set_null",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum CollectionOrder {
+    CreatedAtAsc,
+    CreatedAtDesc,
+    NameAsc,
+    NameDesc,
+}
+
+impl CollectionOrder {
+    // A leading '-' sorts in descending order, like '-name'
+    pub fn from_str(str: &str) -> Result<Self> {
+        match str {
+            "created_at" => Ok(Self::CreatedAtAsc),
+            "-created_at" => Ok(Self::CreatedAtDesc),
+            "name" => Ok(Self::NameAsc),
+            "-name" => Ok(Self::NameDesc),
+            _ => Err(Error::msg(format!("Unknown collection order '{str}'"))),
+        }
+    }
+
+    // Collection ids are UUIDv7, so ordering by id is ordering by creation time. Collections
+    // with the same name keep their creation order.
+    fn compare(&self, a: &CollectionDao, b: &CollectionDao) -> std::cmp::Ordering {
+        match self {
+            Self::CreatedAtAsc => a.id.cmp(&b.id),
+            Self::CreatedAtDesc => b.id.cmp(&a.id),
+            Self::NameAsc => (&a.name, &a.id).cmp(&(&b.name, &b.id)),
+            Self::NameDesc => (&b.name, &b.id).cmp(&(&a.name, &a.id)),
+        }
+    }
+}
+
+pub struct SchemaFieldsChanges {
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+}
+
+impl SchemaFieldsChanges {
+    pub fn added(&self) -> &Vec<String> {
+        &self.added
+    }
+
+    pub fn removed(&self) -> &Vec<String> {
+        &self.removed
+    }
+
+    pub fn changed(&self) -> &Vec<String> {
+        &self.changed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Clone)]
 struct Preserve {
     schema_fields: Option<HashMap<String, SchemaFieldProps>>,
+    backfill_defaults: bool,
+    backfill_values: HashMap<String, ColumnValue>,
+    opt_soft_delete: Option<bool>,
+    opt_per_record_ttl: Option<bool>,
+    rename_fields: Option<HashMap<String, String>>,
+    unique_indexes: Option<Vec<Vec<String>>>,
 }
@@ -5,10 +5,15 @@ use hb_db_postgresql::model::collection_rule::CollectionRuleModel as CollectionR
 use hb_db_scylladb::model::collection_rule::CollectionRuleModel as CollectionRuleScyllaModel;
 use hb_db_sqlite::model::collection_rule::CollectionRuleModel as CollectionRuleSqliteModel;
 use serde::{Deserialize, Serialize};
+use tracing::instrument;
 use uuid::Uuid;
 
 use crate::{util::conversion, Db};
 
+// Stored as the collection id of a rule that applies to every collection in the project of the token.
+// Rules for a specific collection take precedence over it.
+pub const ALL_COLLECTIONS_ID: Uuid = Uuid::nil();
+
 #[derive(Deserialize, Serialize)]
 pub struct CollectionRuleDao {
     id: Uuid,
@@ -22,6 +27,8 @@ pub struct CollectionRuleDao {
     insert_one: bool,
     update_one: CollectionPermission,
     delete_one: CollectionPermission,
+    readable_fields: Option<Vec<String>>,
+    writable_fields: Option<Vec<String>>,
 }
 
 impl CollectionRuleDao {
@@ -34,6 +41,8 @@ impl CollectionRuleDao {
         insert_one: &bool,
         update_one: &CollectionPermission,
         delete_one: &CollectionPermission,
+        readable_fields: &Option<Vec<String>>,
+        writable_fields: &Option<Vec<String>>,
     ) -> Self {
         let now = Utc::now();
 
@@ -49,6 +58,8 @@ impl CollectionRuleDao {
             insert_one: *insert_one,
             update_one: *update_one,
             delete_one: *delete_one,
+            readable_fields: readable_fields.clone(),
+            writable_fields: writable_fields.clone(),
         }
     }
 
@@ -87,6 +98,10 @@ impl CollectionRuleDao {
         &self.collection_id
     }
 
+    pub fn is_wildcard(&self) -> bool {
+        self.collection_id == ALL_COLLECTIONS_ID
+    }
+
     pub fn find_one(&self) -> &CollectionPermission {
         &self.find_one
     }
@@ -107,6 +122,34 @@ impl CollectionRuleDao {
         &self.delete_one
     }
 
+    pub fn readable_fields(&self) -> &Option<Vec<String>> {
+        &self.readable_fields
+    }
+
+    pub fn writable_fields(&self) -> &Option<Vec<String>> {
+        &self.writable_fields
+    }
+
+    // No list means every field is allowed. System fields, which are the ones starting with '_',
+    // are never restricted since they can't be schema fields.
+    pub fn is_readable_field(&self, field: &str) -> bool {
+        match &self.readable_fields {
+            Some(readable_fields) => {
+                field.starts_with('_') || readable_fields.iter().any(|f| f == field)
+            }
+            None => true,
+        }
+    }
+
+    pub fn is_writable_field(&self, field: &str) -> bool {
+        match &self.writable_fields {
+            Some(writable_fields) => {
+                field.starts_with('_') || writable_fields.iter().any(|f| f == field)
+            }
+            None => true,
+        }
+    }
+
     pub fn set_find_one(&mut self, rule: &CollectionPermission) {
         self.find_one = *rule;
     }
@@ -127,6 +170,15 @@ impl CollectionRuleDao {
         self.delete_one = *rule;
     }
 
+    pub fn set_readable_fields(&mut self, fields: &Option<Vec<String>>) {
+        self.readable_fields = fields.clone();
+    }
+
+    pub fn set_writable_fields(&mut self, fields: &Option<Vec<String>>) {
+        self.writable_fields = fields.clone();
+    }
+
+    #[instrument(name = "CollectionRuleDao::db_insert", skip_all)]
     pub async fn db_insert(&self, db: &Db) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.insert_collection_rule(&self.to_scylladb_model()).await,
@@ -136,6 +188,7 @@ impl CollectionRuleDao {
         }
     }
 
+    #[instrument(name = "CollectionRuleDao::db_select", skip_all)]
     pub async fn db_select(db: &Db, id: &Uuid) -> Result<Self> {
         match db {
             Db::ScyllaDb(db) => Self::from_scylladb_model(&db.select_collection_rule(id).await?),
@@ -147,6 +200,10 @@ impl CollectionRuleDao {
         }
     }
 
+    #[instrument(
+        name = "CollectionRuleDao::db_select_by_token_id_and_collection_id",
+        skip_all
+    )]
     pub async fn db_select_by_token_id_and_collection_id(
         db: &Db,
         token_id: &Uuid,
@@ -172,6 +229,7 @@ impl CollectionRuleDao {
         }
     }
 
+    #[instrument(name = "CollectionRuleDao::db_select_many_by_token_id", skip_all)]
     pub async fn db_select_many_by_token_id(db: &Db, token_id: &Uuid) -> Result<Vec<Self>> {
         match db {
             Db::ScyllaDb(db) => {
@@ -217,6 +275,7 @@ impl CollectionRuleDao {
         }
     }
 
+    #[instrument(name = "CollectionRuleDao::db_update", skip_all)]
     pub async fn db_update(&mut self, db: &Db) -> Result<()> {
         self.updated_at = Utc::now();
         match db {
@@ -227,6 +286,7 @@ impl CollectionRuleDao {
         }
     }
 
+    #[instrument(name = "CollectionRuleDao::db_delete", skip_all)]
     pub async fn db_delete(db: &Db, id: &Uuid) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.delete_collection_rule(id).await,
@@ -236,6 +296,7 @@ impl CollectionRuleDao {
         }
     }
 
+    #[instrument(name = "CollectionRuleDao::db_delete_many_by_token_id", skip_all)]
     pub async fn db_delete_many_by_token_id(db: &Db, token_id: &Uuid) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.delete_many_collection_rules_by_token_id(token_id).await,
@@ -245,6 +306,7 @@ impl CollectionRuleDao {
         }
     }
 
+    #[instrument(name = "CollectionRuleDao::db_delete_many_by_collection_id", skip_all)]
     pub async fn db_delete_many_by_collection_id(db: &Db, collection_id: &Uuid) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => {
@@ -279,6 +341,8 @@ impl CollectionRuleDao {
             insert_one: *model.insert_one(),
             update_one: CollectionPermission::from_str(model.update_one())?,
             delete_one: CollectionPermission::from_str(model.delete_one())?,
+            readable_fields: model.readable_fields().clone(),
+            writable_fields: model.writable_fields().clone(),
         })
     }
 
@@ -295,6 +359,8 @@ impl CollectionRuleDao {
             &self.insert_one,
             self.update_one.to_str(),
             self.delete_one.to_str(),
+            &self.readable_fields,
+            &self.writable_fields,
         )
     }
 
@@ -311,6 +377,14 @@ impl CollectionRuleDao {
             insert_one: *model.insert_one(),
             update_one: CollectionPermission::from_str(model.update_one())?,
             delete_one: CollectionPermission::from_str(model.delete_one())?,
+            readable_fields: model
+                .readable_fields()
+                .as_ref()
+                .map(|readable_fields| readable_fields.0.clone()),
+            writable_fields: model
+                .writable_fields()
+                .as_ref()
+                .map(|writable_fields| writable_fields.0.clone()),
         })
     }
 
@@ -327,6 +401,8 @@ impl CollectionRuleDao {
             &self.insert_one,
             self.update_one.to_str(),
             self.delete_one.to_str(),
+            &self.readable_fields.clone().map(sqlx::types::Json),
+            &self.writable_fields.clone().map(sqlx::types::Json),
         )
     }
 
@@ -343,6 +419,14 @@ impl CollectionRuleDao {
             insert_one: *model.insert_one(),
             update_one: CollectionPermission::from_str(model.update_one())?,
             delete_one: CollectionPermission::from_str(model.delete_one())?,
+            readable_fields: model
+                .readable_fields()
+                .as_ref()
+                .map(|readable_fields| readable_fields.0.clone()),
+            writable_fields: model
+                .writable_fields()
+                .as_ref()
+                .map(|writable_fields| writable_fields.0.clone()),
         })
     }
 
@@ -359,6 +443,8 @@ impl CollectionRuleDao {
             &self.insert_one,
             self.update_one.to_str(),
             self.delete_one.to_str(),
+            &self.readable_fields.clone().map(sqlx::types::Json),
+            &self.writable_fields.clone().map(sqlx::types::Json),
         )
     }
 
@@ -375,6 +461,14 @@ impl CollectionRuleDao {
             insert_one: *model.insert_one(),
             update_one: CollectionPermission::from_str(model.update_one())?,
             delete_one: CollectionPermission::from_str(model.delete_one())?,
+            readable_fields: model
+                .readable_fields()
+                .as_ref()
+                .map(|readable_fields| readable_fields.0.clone()),
+            writable_fields: model
+                .writable_fields()
+                .as_ref()
+                .map(|writable_fields| writable_fields.0.clone()),
         })
     }
 
@@ -391,6 +485,8 @@ impl CollectionRuleDao {
             &self.insert_one,
             self.update_one.to_str(),
             self.delete_one.to_str(),
+            &self.readable_fields.clone().map(sqlx::types::Json),
+            &self.writable_fields.clone().map(sqlx::types::Json),
         )
     }
 }
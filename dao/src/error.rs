@@ -0,0 +1,157 @@
+use std::{fmt, io::ErrorKind};
+
+use anyhow::Error;
+use scylla::transport::{
+    errors::{DbError, QueryError},
+    query_result::{FirstRowError, FirstRowTypedError},
+};
+use sqlx::mysql::MySqlDatabaseError;
+
+// Errors the API layers need to tell apart from a generic failure
+#[derive(Debug)]
+pub enum DaoError {
+    NotFound(String),
+    Conflict(String),
+    ChecksumMismatch(String),
+    Expired(String),
+    Timeout(String),
+    QuotaExceeded(String),
+}
+
+impl DaoError {
+    pub fn is_not_found(err: &Error) -> bool {
+        matches!(err.downcast_ref::<Self>(), Some(Self::NotFound(_)))
+    }
+
+    pub fn is_conflict(err: &Error) -> bool {
+        matches!(err.downcast_ref::<Self>(), Some(Self::Conflict(_)))
+    }
+
+    pub fn is_checksum_mismatch(err: &Error) -> bool {
+        matches!(err.downcast_ref::<Self>(), Some(Self::ChecksumMismatch(_)))
+    }
+
+    pub fn is_expired(err: &Error) -> bool {
+        matches!(err.downcast_ref::<Self>(), Some(Self::Expired(_)))
+    }
+
+    pub fn is_timeout(err: &Error) -> bool {
+        matches!(err.downcast_ref::<Self>(), Some(Self::Timeout(_)))
+    }
+
+    pub fn is_quota_exceeded(err: &Error) -> bool {
+        matches!(err.downcast_ref::<Self>(), Some(Self::QuotaExceeded(_)))
+    }
+
+    // Turns the "no rows" error of every backend into NotFound and keeps any other error as is
+    pub(crate) fn from_no_rows(err: Error, message: String) -> Error {
+        if matches!(
+            err.downcast_ref::<sqlx::Error>(),
+            Some(sqlx::Error::RowNotFound)
+        ) || matches!(
+            err.downcast_ref::<FirstRowError>(),
+            Some(FirstRowError::RowsEmpty)
+        ) || matches!(
+            err.downcast_ref::<FirstRowTypedError>(),
+            Some(FirstRowTypedError::RowsEmpty)
+        ) {
+            Self::NotFound(message).into()
+        } else {
+            err
+        }
+    }
+
+    // Turns the statement timeout of every backend into Timeout and keeps any other error as is
+    pub(crate) fn from_timeout(err: Error) -> Error {
+        let is_timeout = match err.downcast_ref::<sqlx::Error>() {
+            Some(sqlx::Error::PoolTimedOut) => true,
+            Some(sqlx::Error::Io(io_err)) => io_err.kind() == ErrorKind::TimedOut,
+            Some(sqlx::Error::Database(db_err)) => {
+                // PostgreSQL: query_canceled, MySQL: ER_QUERY_TIMEOUT
+                db_err.code().as_deref() == Some("57014")
+                    || db_err
+                        .try_downcast_ref::<MySqlDatabaseError>()
+                        .is_some_and(|db_err| db_err.number() == 3024)
+            }
+            _ => matches!(
+                err.downcast_ref::<QueryError>(),
+                Some(
+                    QueryError::RequestTimeout(_)
+                        | QueryError::TimeoutError
+                        | QueryError::DbError(
+                            DbError::ReadTimeout { .. } | DbError::WriteTimeout { .. },
+                            _
+                        )
+                )
+            ),
+        };
+        if is_timeout {
+            Self::Timeout("Query timed out".to_owned()).into()
+        } else {
+            err
+        }
+    }
+
+    // Turns a unique index violation of the SQL backends into Conflict naming the index the way
+    // the collection does, any other error is kept as is
+    pub(crate) fn from_unique_violation(err: Error, record_table: &str) -> Error {
+        let db_err = match err.downcast_ref::<sqlx::Error>() {
+            Some(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => db_err,
+            _ => return err,
+        };
+        let index = match db_err.constraint() {
+            Some(constraint) => constraint.to_owned(),
+            None => {
+                let message = db_err.message();
+                if let Some((_, key)) = message.rsplit_once(" for key '") {
+                    // MySQL: Duplicate entry '...' for key '<table>.<index>'
+                    let key = key.trim_end_matches('\'');
+                    match key.rsplit_once('.') {
+                        Some((_, key)) => key.to_owned(),
+                        None => key.to_owned(),
+                    }
+                } else if let Some((_, columns)) = message.split_once("constraint failed: ") {
+                    // SQLite names the columns instead: <table>.<column>, <table>.<column>
+                    format!(
+                        "{record_table}_{}_unique",
+                        columns
+                            .split(", ")
+                            .map(|col| match col.rsplit_once('.') {
+                                Some((_, col)) => col,
+                                None => col,
+                            })
+                            .collect::<Vec<_>>()
+                            .join("_")
+                    )
+                } else {
+                    return err;
+                }
+            }
+        };
+        let index = match index
+            .strip_prefix(&format!("{record_table}_"))
+            .and_then(|index| index.strip_suffix("_unique"))
+        {
+            Some(index) => index.to_owned(),
+            None => index,
+        };
+        Self::Conflict(format!("Record violates unique index '{index}'")).into()
+    }
+}
+
+impl fmt::Display for DaoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(message)
+            | Self::Conflict(message)
+            | Self::ChecksumMismatch(message)
+            | Self::Expired(message)
+            | Self::Timeout(message)
+            | Self::QuotaExceeded(message) => {
+                write!(f, "{message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DaoError {}
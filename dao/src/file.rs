@@ -1,24 +1,47 @@
 use std::{
+    io::ErrorKind,
     path::{Path, PathBuf},
     str::FromStr,
+    time::Duration,
 };
 
 use anyhow::{Error, Result};
 use chrono::{DateTime, Utc};
 use futures::future;
-use hb_db_mysql::model::file::FileModel as FileMysqlModel;
-use hb_db_postgresql::model::file::FileModel as FilePostgresModel;
+use hb_db_mysql::model::file::{
+    FileFilterModel as FileFilterMysqlModel, FileModel as FileMysqlModel,
+    FileOrderColumn as FileOrderColumnMysql, FileOrderModel as FileOrderMysqlModel,
+};
+use hb_db_postgresql::model::file::{
+    FileFilterModel as FileFilterPostgresModel, FileModel as FilePostgresModel,
+    FileOrderColumn as FileOrderColumnPostgres, FileOrderModel as FileOrderPostgresModel,
+};
 use hb_db_scylladb::model::file::FileModel as FileScyllaModel;
-use hb_db_sqlite::model::file::FileModel as FileSqliteModel;
+use hb_db_sqlite::model::file::{
+    FileFilterModel as FileFilterSqliteModel, FileModel as FileSqliteModel,
+    FileOrderColumn as FileOrderColumnSqlite, FileOrderModel as FileOrderSqliteModel,
+};
+use image::{imageops::FilterType, DynamicImage, ImageFormat as EncodeFormat};
 use mime::Mime;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tokio::{
     fs,
     io::{AsyncReadExt, AsyncWriteExt},
 };
+use tracing::instrument;
 use uuid::Uuid;
 
-use crate::{bucket::BucketDao, util::conversion, Db};
+use crate::{bucket::BucketDao, error::DaoError, project::ProjectDao, util::conversion, Db};
+
+const PARTIAL_FILE_EXTENSION: &str = ".part";
+const UPLOAD_FILE_EXTENSION: &str = ".upload";
+const REPLACEMENT_FILE_EXTENSION: &str = ".replacement";
+const VARIANTS_DIR_EXTENSION: &str = ".variants";
+const WRITE_BLOB_MAX_ATTEMPTS: u32 = 3;
+const WRITE_BLOB_RETRY_DELAY: Duration = Duration::from_millis(200);
+const MANY_FILES_BATCH_SIZE: usize = 100;
+const CHECKSUM_CHUNK_SIZE: usize = 64 * 1024;
 
 #[derive(Deserialize, Serialize)]
 pub struct FileDao {
@@ -31,6 +54,8 @@ pub struct FileDao {
     content_type: String,
     size: i64,
     public: bool,
+    pending: bool,
+    checksum: Option<String>,
     _bytes: Option<Vec<u8>>,
 }
 
@@ -55,6 +80,8 @@ impl FileDao {
             content_type: content_type.to_string(),
             size: *size,
             public: *public,
+            pending: false,
+            checksum: None,
             _bytes: None,
         }
     }
@@ -106,6 +133,19 @@ impl FileDao {
         &self.public
     }
 
+    pub fn pending(&self) -> &bool {
+        &self.pending
+    }
+
+    // Hex encoded SHA-256 of the content, pending files don't have one yet
+    pub fn checksum(&self) -> &Option<String> {
+        &self.checksum
+    }
+
+    pub fn set_id(&mut self, id: &Uuid) {
+        self.id = *id;
+    }
+
     pub fn set_created_by(&mut self, created_by: &Uuid) {
         self.created_by = *created_by;
     }
@@ -118,6 +158,7 @@ impl FileDao {
         self.public = *public;
     }
 
+    #[instrument(name = "FileDao::populate_file_bytes", skip_all)]
     pub async fn populate_file_bytes(&mut self, bucket_path: &str) -> Result<()> {
         let mut file = fs::File::open(&Self::full_path(bucket_path, &self.id)?).await?;
         let mut bytes = Vec::with_capacity(file.metadata().await?.len().try_into()?);
@@ -131,6 +172,26 @@ impl FileDao {
     }
 
     pub fn full_path(bucket_path: &str, id: &Uuid) -> Result<PathBuf> {
+        Ok(Self::dir_path(bucket_path)?.join(id.to_string()))
+    }
+
+    fn partial_path(bucket_path: &str, id: &Uuid) -> Result<PathBuf> {
+        Ok(Self::dir_path(bucket_path)?.join(format!("{id}{PARTIAL_FILE_EXTENSION}")))
+    }
+
+    fn replacement_path(bucket_path: &str, id: &Uuid) -> Result<PathBuf> {
+        Ok(Self::dir_path(bucket_path)?.join(format!("{id}{REPLACEMENT_FILE_EXTENSION}")))
+    }
+
+    fn upload_path(bucket_path: &str, id: &Uuid) -> Result<PathBuf> {
+        Ok(Self::dir_path(bucket_path)?.join(format!("{id}{UPLOAD_FILE_EXTENSION}")))
+    }
+
+    fn variants_dir_path(bucket_path: &str, id: &Uuid) -> Result<PathBuf> {
+        Ok(Self::dir_path(bucket_path)?.join(format!("{id}{VARIANTS_DIR_EXTENSION}")))
+    }
+
+    fn dir_path(bucket_path: &str) -> Result<PathBuf> {
         let exe_path = std::env::current_exe()?;
         let dir_path =
             match exe_path.parent() {
@@ -146,32 +207,545 @@ impl FileDao {
                     ))
                 }
             };
-        Ok(PathBuf::from(format!(
-            "{}/{}/{}",
-            dir_path, bucket_path, id
-        )))
+        Ok(PathBuf::from(format!("{}/{}", dir_path, bucket_path)))
+    }
+
+    #[instrument(name = "FileDao::check_storage", skip_all)]
+    pub async fn check_storage(bucket_path: &str) -> Result<()> {
+        let dir_path = Self::dir_path(bucket_path)?;
+        match fs::metadata(&dir_path).await {
+            Ok(metadata) => {
+                if metadata.is_dir() {
+                    Ok(())
+                } else {
+                    Err(Error::msg(format!(
+                        "Bucket storage path '{}' is not a directory",
+                        dir_path.display()
+                    )))
+                }
+            }
+            Err(err) => Err(Error::msg(format!(
+                "Bucket storage path '{}' is unavailable: {}",
+                dir_path.display(),
+                err
+            ))),
+        }
+    }
+
+    // Writes and removes a small probe file, a read-only mount passes check_storage but can't
+    // take uploads
+    #[instrument(name = "FileDao::check_storage_writable", skip_all)]
+    pub async fn check_storage_writable(bucket_path: &str) -> Result<()> {
+        Self::check_storage(bucket_path).await?;
+        let probe_path = Self::dir_path(bucket_path)?.join(format!(".probe-{}", Uuid::now_v7()));
+        if let Err(err) = fs::write(&probe_path, b"").await {
+            return Err(Error::msg(format!(
+                "Bucket storage path '{}' is not writable: {}",
+                probe_path.parent().unwrap_or(&probe_path).display(),
+                err
+            )));
+        }
+        fs::remove_file(&probe_path).await?;
+        Ok(())
+    }
+
+    #[instrument(name = "FileDao::check_blob", skip_all)]
+    pub async fn check_blob(bucket_path: &str, id: &Uuid) -> Result<BlobStatus> {
+        if Self::check_storage(bucket_path).await.is_err() {
+            return Ok(BlobStatus::StorageUnavailable);
+        }
+        match fs::metadata(&Self::full_path(bucket_path, id)?).await {
+            Ok(_) => Ok(BlobStatus::Available),
+            Err(err) => {
+                if err.kind() == ErrorKind::NotFound {
+                    Ok(BlobStatus::NotFound)
+                } else {
+                    Ok(BlobStatus::StorageUnavailable)
+                }
+            }
+        }
+    }
+
+    // Removes leftover partial writes from uploads and replacements that were interrupted before
+    // being renamed. Only the ones left untouched for longer than older_than are removed, so a
+    // write still in progress keeps its file.
+    #[instrument(name = "FileDao::clean_partial_writes", skip_all)]
+    pub async fn clean_partial_writes(bucket_path: &str, older_than: &Duration) -> Result<usize> {
+        let mut count = 0;
+        let mut entries = fs::read_dir(&Self::dir_path(bucket_path)?).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(file_name) = entry.file_name().to_str() {
+                if !file_name.ends_with(PARTIAL_FILE_EXTENSION)
+                    && !file_name.ends_with(REPLACEMENT_FILE_EXTENSION)
+                {
+                    continue;
+                }
+                if !older_than.is_zero() {
+                    let modified = entry.metadata().await?.modified()?;
+                    if modified.elapsed().unwrap_or_default() < *older_than {
+                        continue;
+                    }
+                }
+                match fs::remove_file(entry.path()).await {
+                    Ok(_) => count += 1,
+                    // Renamed into place or removed by its writer in the meantime
+                    Err(err) if err.kind() == ErrorKind::NotFound => (),
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+        Ok(count)
     }
 
-    pub async fn save(&self, db: &Db, bucket_path: &str, path: impl AsRef<Path>) -> Result<()> {
-        fs::copy(path, &Self::full_path(bucket_path, &self.id)?).await?;
-        self.db_insert(db).await
+    // Finds the files whose row is there but whose blob isn't. Pending uploads don't have a blob
+    // yet and are skipped. An unavailable storage is an error rather than every blob missing.
+    #[instrument(name = "FileDao::find_missing_blobs", skip_all)]
+    pub async fn find_missing_blobs(db: &Db, bucket_path: &str) -> Result<Vec<Self>> {
+        Self::check_storage(bucket_path).await?;
+
+        let mut missing = Vec::new();
+        let limit = Some(i32::try_from(MANY_FILES_BATCH_SIZE)?);
+        for project_data in ProjectDao::db_select_many(db).await? {
+            for bucket_data in
+                BucketDao::db_select_many_by_project_id(db, project_data.id()).await?
+            {
+                let mut before_id = None;
+                loop {
+                    let (files_data, _) = Self::db_select_many_by_bucket_id(
+                        db,
+                        &bucket_data,
+                        &FileFilter::default(),
+                        &FileOrder::CreatedAtDesc,
+                        &before_id,
+                        &limit,
+                    )
+                    .await?;
+                    let next_before_id = match files_data.last() {
+                        Some(file_data) if files_data.len() == MANY_FILES_BATCH_SIZE => {
+                            Some(file_data.id)
+                        }
+                        _ => None,
+                    };
+                    for file_data in files_data {
+                        if file_data.pending {
+                            continue;
+                        }
+                        match Self::check_blob(bucket_path, &file_data.id).await? {
+                            BlobStatus::Available => (),
+                            BlobStatus::NotFound => missing.push(file_data),
+                            BlobStatus::StorageUnavailable => {
+                                return Err(Error::msg(format!(
+                                    "Bucket storage became unavailable while checking file '{}'",
+                                    file_data.id
+                                )))
+                            }
+                        }
+                    }
+                    match next_before_id {
+                        Some(next_before_id) => before_id = Some(next_before_id),
+                        None => break,
+                    }
+                }
+            }
+        }
+        Ok(missing)
     }
 
-    pub async fn save_from_bytes(&self, db: &Db, bucket_path: &str) -> Result<()> {
-        if let Some(bytes) = &self._bytes {
-            let mut file = fs::File::create(&Self::full_path(bucket_path, &self.id)?).await?;
-            file.write_all(bytes).await?;
-            file.flush().await?;
+    #[instrument(name = "FileDao::save", skip_all)]
+    pub async fn save(
+        &mut self,
+        db: &Db,
+        bucket_path: &str,
+        path: impl AsRef<Path>,
+        expected_checksum: &Option<String>,
+    ) -> Result<()> {
+        let full_path = Self::full_path(bucket_path, &self.id)?;
+        let checksum = self
+            .write_blob(bucket_path, BlobSource::Path(path.as_ref()), &full_path)
+            .await?;
+        if let Err(err) = Self::check_checksum(&checksum, expected_checksum) {
+            let _ = fs::remove_file(&full_path).await;
+            return Err(err);
+        }
+        self.checksum = Some(checksum);
+        self.db_insert_or_remove_blob(db, bucket_path).await
+    }
 
-            self.db_insert(db).await
+    #[instrument(name = "FileDao::save_from_bytes", skip_all)]
+    pub async fn save_from_bytes(&mut self, db: &Db, bucket_path: &str) -> Result<()> {
+        let checksum = if let Some(bytes) = &self._bytes {
+            self.write_blob(
+                bucket_path,
+                BlobSource::Bytes(bytes),
+                &Self::full_path(bucket_path, &self.id)?,
+            )
+            .await?
         } else {
-            Err(Error::msg("File bytes is empty"))
+            return Err(Error::msg("File bytes is empty"));
+        };
+        self.checksum = Some(checksum);
+        self.db_insert_or_remove_blob(db, bucket_path).await
+    }
+
+    // Writes to a temporary file first and only renames it into place after the content is
+    // synced, so a crash or a vanished mount never leaves a truncated blob under the target path.
+    // Returns the checksum of the written content
+    async fn write_blob(
+        &self,
+        bucket_path: &str,
+        source: BlobSource<'_>,
+        target_path: &Path,
+    ) -> Result<String> {
+        let partial_path = Self::partial_path(bucket_path, &self.id)?;
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match Self::write_blob_once(&partial_path, target_path, &source).await {
+                Ok(checksum) => return Ok(checksum),
+                Err(err) => {
+                    let _ = fs::remove_file(&partial_path).await;
+                    if attempt >= WRITE_BLOB_MAX_ATTEMPTS {
+                        return Err(Error::msg(format!(
+                            "Failed to write file to the bucket storage after {attempt} attempts: {err}"
+                        )));
+                    }
+                    tokio::time::sleep(WRITE_BLOB_RETRY_DELAY * attempt).await;
+                }
+            }
+        }
+    }
+
+    async fn write_blob_once(
+        partial_path: &Path,
+        target_path: &Path,
+        source: &BlobSource<'_>,
+    ) -> Result<String> {
+        let mut file = fs::File::create(partial_path).await?;
+        let mut hasher = Sha256::new();
+        match source {
+            BlobSource::Path(path) => {
+                // Copied in chunks so the checksum is computed along the way without holding the
+                // whole file in memory
+                let mut source = fs::File::open(path).await?;
+                let mut buf = vec![0; CHECKSUM_CHUNK_SIZE];
+                loop {
+                    let len = source.read(&mut buf).await?;
+                    if len == 0 {
+                        break;
+                    }
+                    hasher.update(&buf[..len]);
+                    file.write_all(&buf[..len]).await?;
+                }
+            }
+            BlobSource::Bytes(bytes) => {
+                hasher.update(bytes);
+                file.write_all(bytes).await?;
+            }
+        }
+        file.flush().await?;
+        file.sync_all().await?;
+        fs::rename(partial_path, target_path).await?;
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    async fn file_checksum(path: &Path) -> Result<String> {
+        let mut file = fs::File::open(path).await?;
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; CHECKSUM_CHUNK_SIZE];
+        loop {
+            let len = file.read(&mut buf).await?;
+            if len == 0 {
+                break;
+            }
+            hasher.update(&buf[..len]);
+        }
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    fn check_checksum(checksum: &str, expected_checksum: &Option<String>) -> Result<()> {
+        if let Some(expected_checksum) = expected_checksum {
+            if !checksum.eq_ignore_ascii_case(expected_checksum) {
+                return Err(DaoError::ChecksumMismatch(format!(
+                    "Content SHA-256 of the file is '{checksum}', which doesn't match the expected '{expected_checksum}'"
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    async fn db_insert_or_remove_blob(&self, db: &Db, bucket_path: &str) -> Result<()> {
+        if let Err(err) = self.db_insert(db).await {
+            let _ = fs::remove_file(&Self::full_path(bucket_path, &self.id)?).await;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    // Swaps the content of the file for a new one under the same id. The new content is written
+    // next to the old one and only renamed over it after the database is updated, so readers never
+    // see a truncated file and the ones that already opened the old content keep reading it.
+    #[instrument(name = "FileDao::replace", skip_all)]
+    pub async fn replace(
+        &mut self,
+        db: &Db,
+        bucket_path: &str,
+        path: impl AsRef<Path>,
+        content_type: &Mime,
+        size: &i64,
+        expected_checksum: &Option<String>,
+    ) -> Result<()> {
+        if self.pending {
+            return Err(Error::msg("File upload is not completed yet"));
+        }
+
+        let replacement_path = Self::replacement_path(bucket_path, &self.id)?;
+        let checksum = self
+            .write_blob(
+                bucket_path,
+                BlobSource::Path(path.as_ref()),
+                &replacement_path,
+            )
+            .await?;
+        if let Err(err) = Self::check_checksum(&checksum, expected_checksum) {
+            let _ = fs::remove_file(&replacement_path).await;
+            return Err(err);
+        }
+
+        let previous = (
+            self.updated_at,
+            self.content_type.clone(),
+            self.size,
+            self.checksum.clone(),
+        );
+        self.updated_at = Utc::now();
+        self.content_type = content_type.to_string();
+        self.size = *size;
+        self.checksum = Some(checksum);
+        if let Err(err) = self.db_update_content(db, &previous.2).await {
+            (self.updated_at, self.content_type, self.size, self.checksum) = previous;
+            let _ = fs::remove_file(&replacement_path).await;
+            return Err(err);
+        }
+        if let Err(err) =
+            fs::rename(&replacement_path, &Self::full_path(bucket_path, &self.id)?).await
+        {
+            let replaced_size = self.size;
+            (self.updated_at, self.content_type, self.size, self.checksum) = previous;
+            let _ = self.db_update_content(db, &replaced_size).await;
+            let _ = fs::remove_file(&replacement_path).await;
+            return Err(err.into());
+        }
+
+        // Resized copies were made out of the old content
+        if let Err(err) = fs::remove_dir_all(&Self::variants_dir_path(bucket_path, &self.id)?).await
+        {
+            if err.kind() != ErrorKind::NotFound {
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    // Starts an upload session, the file stays pending with its content in a temporary file until
+    // the upload is completed
+    #[instrument(name = "FileDao::start_upload", skip_all)]
+    pub async fn start_upload(&mut self, db: &Db, bucket_path: &str) -> Result<()> {
+        self.pending = true;
+        let upload_path = Self::upload_path(bucket_path, &self.id)?;
+        fs::File::create(&upload_path).await?;
+        if let Err(err) = self.db_insert(db).await {
+            let _ = fs::remove_file(&upload_path).await;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "FileDao::uploaded_size", skip_all)]
+    pub async fn uploaded_size(&self, bucket_path: &str) -> Result<i64> {
+        Ok(fs::metadata(&Self::upload_path(bucket_path, &self.id)?)
+            .await?
+            .len()
+            .try_into()?)
+    }
+
+    #[instrument(name = "FileDao::open_upload", skip_all)]
+    pub async fn open_upload(&self, bucket_path: &str) -> Result<fs::File> {
+        if !self.pending {
+            return Err(Error::msg("File upload is already completed"));
+        }
+        Ok(fs::OpenOptions::new()
+            .append(true)
+            .open(&Self::upload_path(bucket_path, &self.id)?)
+            .await?)
+    }
+
+    // Moves the uploaded content into place once all of it is received, and moves it back if the
+    // file can't be marked as available so the upload can be completed again
+    #[instrument(name = "FileDao::complete_upload", skip_all)]
+    pub async fn complete_upload(
+        &mut self,
+        db: &Db,
+        bucket_path: &str,
+        expected_checksum: &Option<String>,
+    ) -> Result<()> {
+        if !self.pending {
+            return Err(Error::msg("File upload is already completed"));
+        }
+        let uploaded_size = self.uploaded_size(bucket_path).await?;
+        if uploaded_size != self.size {
+            return Err(Error::msg(format!(
+                "File upload is incomplete, received {uploaded_size} of {} bytes",
+                self.size
+            )));
+        }
+
+        let upload_path = Self::upload_path(bucket_path, &self.id)?;
+        let full_path = Self::full_path(bucket_path, &self.id)?;
+        let checksum = Self::file_checksum(&upload_path).await?;
+        Self::check_checksum(&checksum, expected_checksum)?;
+        fs::File::open(&upload_path).await?.sync_all().await?;
+        fs::rename(&upload_path, &full_path).await?;
+
+        self.pending = false;
+        self.checksum = Some(checksum);
+        if let Err(err) = self.db_update(db).await {
+            self.pending = true;
+            self.checksum = None;
+            let _ = fs::rename(&full_path, &upload_path).await;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    // Returns the path of a resized copy of the image, creating it on the first request. Copies
+    // are kept next to the original, keyed by the resize parameters.
+    #[instrument(name = "FileDao::resized_path", skip_all)]
+    pub async fn resized_path(
+        &self,
+        bucket_path: &str,
+        width: &Option<u32>,
+        height: &Option<u32>,
+        fit: &ImageFit,
+        format: &ImageFormat,
+    ) -> Result<PathBuf> {
+        if self.content_type().type_() != mime::IMAGE {
+            return Err(Error::msg("Only image files can be resized"));
+        }
+        if width.is_none() && height.is_none() {
+            return Err(Error::msg("Width or height is required to resize an image"));
+        }
+
+        let variants_dir_path = Self::variants_dir_path(bucket_path, &self.id)?;
+        let variant_path = variants_dir_path.join(format!(
+            "{}x{}.{}.{}",
+            width.map(|width| width.to_string()).unwrap_or_default(),
+            height.map(|height| height.to_string()).unwrap_or_default(),
+            fit.to_str(),
+            format.to_str()
+        ));
+        if fs::metadata(&variant_path).await.is_ok() {
+            return Ok(variant_path);
+        }
+
+        let bytes = fs::read(&Self::full_path(bucket_path, &self.id)?).await?;
+        let (width, height, fit, format) = (*width, *height, *fit, *format);
+        let resized = tokio::task::spawn_blocking(move || -> Result<Vec<u8>> {
+            let image = image::load_from_memory(&bytes)?;
+            let image = match (width, height, fit) {
+                (Some(width), Some(height), ImageFit::Cover) => {
+                    image.resize_to_fill(width, height, FilterType::Lanczos3)
+                }
+                (width, height, _) => image.resize(
+                    width.unwrap_or(u32::MAX),
+                    height.unwrap_or(u32::MAX),
+                    FilterType::Lanczos3,
+                ),
+            };
+            let mut buffer = std::io::Cursor::new(Vec::new());
+            match format {
+                // JPEG has no alpha channel
+                ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+                    .write_to(&mut buffer, EncodeFormat::Jpeg)?,
+                ImageFormat::Webp => DynamicImage::ImageRgba8(image.to_rgba8())
+                    .write_to(&mut buffer, EncodeFormat::WebP)?,
+            }
+            Ok(buffer.into_inner())
+        })
+        .await??;
+
+        // Concurrent requests for the same copy each write their own temporary file, the last
+        // rename wins and both results are the same
+        fs::create_dir_all(&variants_dir_path).await?;
+        let partial_path =
+            variants_dir_path.join(format!("{}{PARTIAL_FILE_EXTENSION}", Uuid::now_v7()));
+        if let Err(err) = Self::write_variant(&partial_path, &variant_path, &resized).await {
+            let _ = fs::remove_file(&partial_path).await;
+            return Err(err);
+        }
+        Ok(variant_path)
+    }
+
+    async fn write_variant(partial_path: &Path, variant_path: &Path, bytes: &[u8]) -> Result<()> {
+        let mut file = fs::File::create(partial_path).await?;
+        file.write_all(bytes).await?;
+        file.flush().await?;
+        fs::rename(partial_path, variant_path).await?;
+        Ok(())
+    }
+
+    #[instrument(name = "FileDao::delete", skip_all)]
+    pub async fn delete(&self, db: &Db, bucket_data: &BucketDao) -> Result<()> {
+        // A pending file only has the temporary file of its upload session
+        if let Err(err) = fs::remove_file(&Self::full_path(bucket_data.path(), &self.id)?).await {
+            if err.kind() != ErrorKind::NotFound {
+                return Err(err.into());
+            }
+            fs::remove_file(&Self::upload_path(bucket_data.path(), &self.id)?).await?;
+        }
+        if let Err(err) =
+            fs::remove_dir_all(&Self::variants_dir_path(bucket_data.path(), &self.id)?).await
+        {
+            if err.kind() != ErrorKind::NotFound {
+                return Err(err.into());
+            }
         }
+        self.db_delete(db).await
     }
 
-    pub async fn delete(db: &Db, bucket_data: &BucketDao, id: &Uuid) -> Result<()> {
-        fs::remove_file(&Self::full_path(bucket_data.path(), id)?).await?;
-        Self::db_delete(db, bucket_data.id(), id).await
+    // Deletes the files a batch at a time, so a large cleanup doesn't open thousands of files and
+    // connections at once. The results are in the same order as the files
+    #[instrument(name = "FileDao::delete_many", skip_all)]
+    pub async fn delete_many(
+        db: &Db,
+        bucket_data: &BucketDao,
+        files_data: &[Self],
+    ) -> Vec<Result<()>> {
+        let mut results = Vec::with_capacity(files_data.len());
+        for batch in files_data.chunks(MANY_FILES_BATCH_SIZE) {
+            results.extend(
+                future::join_all(
+                    batch
+                        .iter()
+                        .map(|file_data| file_data.delete(db, bucket_data)),
+                )
+                .await,
+            );
+        }
+        results
+    }
+
+    // Removes the upload sessions that haven't received anything within the ttl, along with their
+    // temporary files
+    #[instrument(name = "FileDao::delete_stale_uploads", skip_all)]
+    pub async fn delete_stale_uploads(db: &Db, ttl_seconds: &i64) -> Result<usize> {
+        let files_data = Self::db_select_many_stale_upload(db, ttl_seconds).await?;
+        for file_data in &files_data {
+            let bucket_data = BucketDao::db_select(db, &file_data.bucket_id).await?;
+            file_data.delete(db, &bucket_data).await?;
+        }
+        Ok(files_data.len())
     }
 
     async fn delete_expired(db: &Db, bucket_data: &BucketDao) -> Result<()> {
@@ -180,22 +754,33 @@ impl FileDao {
                 Self::db_select_many_expired(db, bucket_data.id(), ttl_seconds).await?;
             let mut delete_expired_mut = Vec::with_capacity(files_data.len());
             for file_data in &files_data {
-                delete_expired_mut.push(Self::delete(db, bucket_data, &file_data.id));
+                delete_expired_mut.push(file_data.delete(db, bucket_data));
             }
             future::try_join_all(delete_expired_mut).await?;
         }
         Ok(())
     }
 
+    // The usage of the bucket is reserved along with the insert, so concurrent uploads can't go
+    // over its quota together
     async fn db_insert(&self, db: &Db) -> Result<()> {
-        match db {
-            Db::ScyllaDb(db) => db.insert_file(&self.to_scylladb_model()).await,
-            Db::PostgresqlDb(db) => db.insert_file(&self.to_postgresdb_model()).await,
-            Db::MysqlDb(db) => db.insert_file(&self.to_mysqldb_model()).await,
-            Db::SqliteDb(db) => db.insert_file(&self.to_sqlitedb_model()).await,
+        let reserved = match db {
+            Db::ScyllaDb(db) => db.insert_file(&self.to_scylladb_model()).await?,
+            Db::PostgresqlDb(db) => db.insert_file(&self.to_postgresdb_model()).await?,
+            Db::MysqlDb(db) => db.insert_file(&self.to_mysqldb_model()).await?,
+            Db::SqliteDb(db) => db.insert_file(&self.to_sqlitedb_model()).await?,
+        };
+        if !reserved {
+            return Err(DaoError::QuotaExceeded(format!(
+                "File of {} bytes exceeds the remaining quota of its bucket",
+                self.size
+            ))
+            .into());
         }
+        Ok(())
     }
 
+    #[instrument(name = "FileDao::db_select", skip_all)]
     pub async fn db_select(db: &Db, bucket_data: &BucketDao, id: &Uuid) -> Result<Self> {
         Self::delete_expired(db, bucket_data).await?;
 
@@ -207,9 +792,78 @@ impl FileDao {
         }
     }
 
+    // Looks in every bucket, without sweeping the expired files first
+    #[instrument(name = "FileDao::db_exists", skip_all)]
+    pub async fn db_exists(db: &Db, id: &Uuid) -> Result<bool> {
+        let result = match db {
+            Db::ScyllaDb(db) => db.select_file(id).await.map(|_| ()),
+            Db::PostgresqlDb(db) => db.select_file(id).await.map(|_| ()),
+            Db::MysqlDb(db) => db.select_file(id).await.map(|_| ()),
+            Db::SqliteDb(db) => db.select_file(id).await.map(|_| ()),
+        };
+        match result.map_err(|err| DaoError::from_no_rows(err, format!("File '{id}' is not found")))
+        {
+            Ok(_) => Ok(true),
+            Err(err) if DaoError::is_not_found(&err) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Ids that don't belong to the bucket are left out of the result
+    #[instrument(name = "FileDao::db_select_many_by_ids", skip_all)]
+    pub async fn db_select_many_by_ids(
+        db: &Db,
+        bucket_data: &BucketDao,
+        ids: &[Uuid],
+    ) -> Result<Vec<Self>> {
+        Self::delete_expired(db, bucket_data).await?;
+
+        let mut files_data = Vec::with_capacity(ids.len());
+        for ids in ids.chunks(MANY_FILES_BATCH_SIZE) {
+            match db {
+                Db::ScyllaDb(db) => {
+                    for file in db
+                        .select_many_files_by_bucket_id_and_ids(bucket_data.id(), ids)
+                        .await?
+                    {
+                        files_data.push(Self::from_scylladb_model(&file?)?);
+                    }
+                }
+                Db::PostgresqlDb(db) => {
+                    for file in &db
+                        .select_many_files_by_bucket_id_and_ids(bucket_data.id(), ids)
+                        .await?
+                    {
+                        files_data.push(Self::from_postgresdb_model(file)?);
+                    }
+                }
+                Db::MysqlDb(db) => {
+                    for file in &db
+                        .select_many_files_by_bucket_id_and_ids(bucket_data.id(), ids)
+                        .await?
+                    {
+                        files_data.push(Self::from_mysqldb_model(file)?);
+                    }
+                }
+                Db::SqliteDb(db) => {
+                    for file in &db
+                        .select_many_files_by_bucket_id_and_ids(bucket_data.id(), ids)
+                        .await?
+                    {
+                        files_data.push(Self::from_sqlitedb_model(file)?);
+                    }
+                }
+            }
+        }
+        Ok(files_data)
+    }
+
+    #[instrument(name = "FileDao::db_select_many_by_bucket_id", skip_all)]
     pub async fn db_select_many_by_bucket_id(
         db: &Db,
         bucket_data: &BucketDao,
+        filter: &FileFilter,
+        order: &FileOrder,
         before_id: &Option<Uuid>,
         limit: &Option<i32>,
     ) -> Result<(Vec<Self>, i64)> {
@@ -217,20 +871,44 @@ impl FileDao {
 
         match db {
             Db::ScyllaDb(db) => {
+                if filter.is_empty() && matches!(order, FileOrder::CreatedAtDesc) {
+                    let mut files_data = Vec::new();
+                    let (files, total) = tokio::try_join!(
+                        db.select_many_files_by_bucket_id(bucket_data.id(), before_id, limit),
+                        db.count_many_files_by_bucket_id(bucket_data.id())
+                    )?;
+                    for file in files {
+                        files_data.push(Self::from_scylladb_model(&file?)?);
+                    }
+                    return Ok((files_data, total));
+                }
+
                 let mut files_data = Vec::new();
-                let (files, total) = tokio::try_join!(
-                    db.select_many_files_by_bucket_id(bucket_data.id(), before_id, limit),
-                    db.count_many_files_by_bucket_id(bucket_data.id())
-                )?;
+                let files = db
+                    .select_many_files_by_bucket_id(bucket_data.id(), &None, &None)
+                    .await?;
                 for file in files {
-                    files_data.push(Self::from_scylladb_model(&file?)?);
+                    let file_data = Self::from_scylladb_model(&file?)?;
+                    if filter.matches(&file_data) {
+                        files_data.push(file_data);
+                    }
                 }
+                let total = i64::try_from(files_data.len())?;
+                Self::paginate(&mut files_data, order, before_id, limit);
                 Ok((files_data, total))
             }
             Db::PostgresqlDb(db) => {
+                let filter = filter.to_postgresdb_model();
+                let order = order.to_postgresdb_model();
                 let (files, total) = tokio::try_join!(
-                    db.select_many_files_by_bucket_id(bucket_data.id(), before_id, limit),
-                    db.count_many_files_by_bucket_id(bucket_data.id())
+                    db.select_many_files_by_bucket_id(
+                        bucket_data.id(),
+                        &filter,
+                        &order,
+                        before_id,
+                        limit,
+                    ),
+                    db.count_many_files_by_bucket_id(bucket_data.id(), &filter)
                 )?;
                 let mut files_data = Vec::with_capacity(files.len());
                 for file in &files {
@@ -239,9 +917,17 @@ impl FileDao {
                 Ok((files_data, total))
             }
             Db::MysqlDb(db) => {
+                let filter = filter.to_mysqldb_model();
+                let order = order.to_mysqldb_model();
                 let (files, total) = tokio::try_join!(
-                    db.select_many_files_by_bucket_id(bucket_data.id(), before_id, limit),
-                    db.count_many_files_by_bucket_id(bucket_data.id())
+                    db.select_many_files_by_bucket_id(
+                        bucket_data.id(),
+                        &filter,
+                        &order,
+                        before_id,
+                        limit,
+                    ),
+                    db.count_many_files_by_bucket_id(bucket_data.id(), &filter)
                 )?;
                 let mut files_data = Vec::with_capacity(files.len());
                 for file in &files {
@@ -250,9 +936,17 @@ impl FileDao {
                 Ok((files_data, total))
             }
             Db::SqliteDb(db) => {
+                let filter = filter.to_sqlitedb_model();
+                let order = order.to_sqlitedb_model();
                 let (files, total) = tokio::try_join!(
-                    db.select_many_files_by_bucket_id(bucket_data.id(), before_id, limit),
-                    db.count_many_files_by_bucket_id(bucket_data.id())
+                    db.select_many_files_by_bucket_id(
+                        bucket_data.id(),
+                        &filter,
+                        &order,
+                        before_id,
+                        limit,
+                    ),
+                    db.count_many_files_by_bucket_id(bucket_data.id(), &filter)
                 )?;
                 let mut files_data = Vec::with_capacity(files.len());
                 for file in &files {
@@ -263,10 +957,13 @@ impl FileDao {
         }
     }
 
+    #[instrument(name = "FileDao::db_select_many_by_created_by_and_bucket_id", skip_all)]
     pub async fn db_select_many_by_created_by_and_bucket_id(
         db: &Db,
         created_by: &Uuid,
         bucket_data: &BucketDao,
+        filter: &FileFilter,
+        order: &FileOrder,
         before_id: &Option<Uuid>,
         limit: &Option<i32>,
     ) -> Result<(Vec<Self>, i64)> {
@@ -274,30 +971,62 @@ impl FileDao {
 
         match db {
             Db::ScyllaDb(db) => {
+                if filter.is_empty() && matches!(order, FileOrder::CreatedAtDesc) {
+                    let mut files_data = Vec::new();
+                    let (files, total) = tokio::try_join!(
+                        db.select_many_files_by_created_by_and_bucket_id(
+                            created_by,
+                            bucket_data.id(),
+                            before_id,
+                            limit,
+                        ),
+                        db.count_many_files_by_created_by_and_bucket_id(
+                            created_by,
+                            bucket_data.id()
+                        )
+                    )?;
+                    for file in files {
+                        files_data.push(Self::from_scylladb_model(&file?)?);
+                    }
+                    return Ok((files_data, total));
+                }
+
                 let mut files_data = Vec::new();
-                let (files, total) = tokio::try_join!(
-                    db.select_many_files_by_created_by_and_bucket_id(
+                let files = db
+                    .select_many_files_by_created_by_and_bucket_id(
                         created_by,
                         bucket_data.id(),
-                        before_id,
-                        limit,
-                    ),
-                    db.count_many_files_by_created_by_and_bucket_id(created_by, bucket_data.id())
-                )?;
+                        &None,
+                        &None,
+                    )
+                    .await?;
                 for file in files {
-                    files_data.push(Self::from_scylladb_model(&file?)?);
+                    let file_data = Self::from_scylladb_model(&file?)?;
+                    if filter.matches(&file_data) {
+                        files_data.push(file_data);
+                    }
                 }
+                let total = i64::try_from(files_data.len())?;
+                Self::paginate(&mut files_data, order, before_id, limit);
                 Ok((files_data, total))
             }
             Db::PostgresqlDb(db) => {
+                let filter = filter.to_postgresdb_model();
+                let order = order.to_postgresdb_model();
                 let (files, total) = tokio::try_join!(
                     db.select_many_files_by_created_by_and_bucket_id(
                         created_by,
                         bucket_data.id(),
+                        &filter,
+                        &order,
                         before_id,
                         limit,
                     ),
-                    db.count_many_files_by_created_by_and_bucket_id(created_by, bucket_data.id())
+                    db.count_many_files_by_created_by_and_bucket_id(
+                        created_by,
+                        bucket_data.id(),
+                        &filter,
+                    )
                 )?;
                 let mut files_data = Vec::with_capacity(files.len());
                 for file in &files {
@@ -306,14 +1035,22 @@ impl FileDao {
                 Ok((files_data, total))
             }
             Db::MysqlDb(db) => {
+                let filter = filter.to_mysqldb_model();
+                let order = order.to_mysqldb_model();
                 let (files, total) = tokio::try_join!(
                     db.select_many_files_by_created_by_and_bucket_id(
                         created_by,
                         bucket_data.id(),
+                        &filter,
+                        &order,
                         before_id,
                         limit,
                     ),
-                    db.count_many_files_by_created_by_and_bucket_id(created_by, bucket_data.id())
+                    db.count_many_files_by_created_by_and_bucket_id(
+                        created_by,
+                        bucket_data.id(),
+                        &filter,
+                    )
                 )?;
                 let mut files_data = Vec::with_capacity(files.len());
                 for file in &files {
@@ -322,14 +1059,22 @@ impl FileDao {
                 Ok((files_data, total))
             }
             Db::SqliteDb(db) => {
+                let filter = filter.to_sqlitedb_model();
+                let order = order.to_sqlitedb_model();
                 let (files, total) = tokio::try_join!(
                     db.select_many_files_by_created_by_and_bucket_id(
                         created_by,
                         bucket_data.id(),
+                        &filter,
+                        &order,
                         before_id,
                         limit,
                     ),
-                    db.count_many_files_by_created_by_and_bucket_id(created_by, bucket_data.id())
+                    db.count_many_files_by_created_by_and_bucket_id(
+                        created_by,
+                        bucket_data.id(),
+                        &filter,
+                    )
                 )?;
                 let mut files_data = Vec::with_capacity(files.len());
                 for file in &files {
@@ -340,6 +1085,32 @@ impl FileDao {
         }
     }
 
+    // ScyllaDB can't search or sort the files of a bucket, so the page is made here with the same
+    // rules as the SQL backends
+    fn paginate(
+        files_data: &mut Vec<Self>,
+        order: &FileOrder,
+        before_id: &Option<Uuid>,
+        limit: &Option<i32>,
+    ) {
+        files_data.sort_by(|a, b| order.compare(a, b));
+        if let Some(before_id) = before_id {
+            match files_data.iter().position(|data| &data.id == before_id) {
+                Some(idx) => {
+                    files_data.drain(..=idx);
+                }
+                None => files_data.retain(|data| match order {
+                    FileOrder::CreatedAtAsc => &data.id > before_id,
+                    FileOrder::CreatedAtDesc => &data.id < before_id,
+                    _ => false,
+                }),
+            }
+        }
+        if let Some(limit) = limit {
+            files_data.truncate(usize::try_from(*limit).unwrap_or(0));
+        }
+    }
+
     async fn db_select_many_expired(
         db: &Db,
         bucket_id: &Uuid,
@@ -381,6 +1152,44 @@ impl FileDao {
         }
     }
 
+    async fn db_select_many_stale_upload(db: &Db, ttl_seconds: &i64) -> Result<Vec<Self>> {
+        match db {
+            Db::ScyllaDb(db) => {
+                let mut files_data = Vec::new();
+                let files = db.select_many_stale_upload_files(ttl_seconds).await?;
+                for file in files {
+                    files_data.push(Self::from_scylladb_model(&file?)?);
+                }
+                Ok(files_data)
+            }
+            Db::PostgresqlDb(db) => {
+                let files = db.select_many_stale_upload_files(ttl_seconds).await?;
+                let mut files_data = Vec::with_capacity(files.len());
+                for file in &files {
+                    files_data.push(Self::from_postgresdb_model(file)?);
+                }
+                Ok(files_data)
+            }
+            Db::MysqlDb(db) => {
+                let files = db.select_many_stale_upload_files(ttl_seconds).await?;
+                let mut files_data = Vec::with_capacity(files.len());
+                for file in &files {
+                    files_data.push(Self::from_mysqldb_model(file)?);
+                }
+                Ok(files_data)
+            }
+            Db::SqliteDb(db) => {
+                let files = db.select_many_stale_upload_files(ttl_seconds).await?;
+                let mut files_data = Vec::with_capacity(files.len());
+                for file in &files {
+                    files_data.push(Self::from_sqlitedb_model(file)?);
+                }
+                Ok(files_data)
+            }
+        }
+    }
+
+    #[instrument(name = "FileDao::db_update", skip_all)]
     pub async fn db_update(&mut self, db: &Db) -> Result<()> {
         self.updated_at = Utc::now();
         match db {
@@ -391,12 +1200,41 @@ impl FileDao {
         }
     }
 
-    async fn db_delete(db: &Db, bucket_id: &Uuid, id: &Uuid) -> Result<()> {
+    async fn db_update_content(&self, db: &Db, previous_size: &i64) -> Result<()> {
+        let reserved = match db {
+            Db::ScyllaDb(db) => {
+                db.update_file_content(&self.to_scylladb_model(), previous_size)
+                    .await?
+            }
+            Db::PostgresqlDb(db) => {
+                db.update_file_content(&self.to_postgresdb_model(), previous_size)
+                    .await?
+            }
+            Db::MysqlDb(db) => {
+                db.update_file_content(&self.to_mysqldb_model(), previous_size)
+                    .await?
+            }
+            Db::SqliteDb(db) => {
+                db.update_file_content(&self.to_sqlitedb_model(), previous_size)
+                    .await?
+            }
+        };
+        if !reserved {
+            return Err(DaoError::QuotaExceeded(format!(
+                "File of {} bytes exceeds the remaining quota of its bucket",
+                self.size
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    async fn db_delete(&self, db: &Db) -> Result<()> {
         match db {
-            Db::ScyllaDb(db) => db.delete_file(bucket_id, id).await,
-            Db::PostgresqlDb(db) => db.delete_file(id).await,
-            Db::MysqlDb(db) => db.delete_file(id).await,
-            Db::SqliteDb(db) => db.delete_file(id).await,
+            Db::ScyllaDb(db) => db.delete_file(&self.bucket_id, &self.id, &self.size).await,
+            Db::PostgresqlDb(db) => db.delete_file(&self.bucket_id, &self.id, &self.size).await,
+            Db::MysqlDb(db) => db.delete_file(&self.bucket_id, &self.id, &self.size).await,
+            Db::SqliteDb(db) => db.delete_file(&self.bucket_id, &self.id, &self.size).await,
         }
     }
 
@@ -411,6 +1249,8 @@ impl FileDao {
             content_type: Mime::from_str(model.content_type())?.to_string(),
             size: *model.size(),
             public: *model.public(),
+            pending: *model.pending(),
+            checksum: model.checksum().clone(),
             _bytes: None,
         })
     }
@@ -426,6 +1266,8 @@ impl FileDao {
             &self.content_type.to_string(),
             &self.size,
             &self.public,
+            &self.pending,
+            &self.checksum,
         )
     }
 
@@ -440,6 +1282,8 @@ impl FileDao {
             content_type: Mime::from_str(model.content_type())?.to_string(),
             size: *model.size(),
             public: *model.public(),
+            pending: *model.pending(),
+            checksum: model.checksum().clone(),
             _bytes: None,
         })
     }
@@ -455,6 +1299,8 @@ impl FileDao {
             &self.content_type.to_string(),
             &self.size,
             &self.public,
+            &self.pending,
+            &self.checksum,
         )
     }
 
@@ -469,6 +1315,8 @@ impl FileDao {
             content_type: Mime::from_str(model.content_type())?.to_string(),
             size: *model.size(),
             public: *model.public(),
+            pending: *model.pending(),
+            checksum: model.checksum().clone(),
             _bytes: None,
         })
     }
@@ -484,6 +1332,8 @@ impl FileDao {
             &self.content_type.to_string(),
             &self.size,
             &self.public,
+            &self.pending,
+            &self.checksum,
         )
     }
 
@@ -498,6 +1348,8 @@ impl FileDao {
             content_type: Mime::from_str(model.content_type())?.to_string(),
             size: *model.size(),
             public: *model.public(),
+            pending: *model.pending(),
+            checksum: model.checksum().clone(),
             _bytes: None,
         })
     }
@@ -513,6 +1365,263 @@ impl FileDao {
             &self.content_type.to_string(),
             &self.size,
             &self.public,
+            &self.pending,
+            &self.checksum,
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct FileFilter {
+    file_name: Option<String>,
+    content_type: Option<String>,
+    public: Option<bool>,
+    created_by: Option<Uuid>,
+    min_size: Option<i64>,
+    max_size: Option<i64>,
+}
+
+impl FileFilter {
+    pub fn new(
+        file_name: &Option<String>,
+        content_type: &Option<String>,
+        public: &Option<bool>,
+        created_by: &Option<Uuid>,
+        min_size: &Option<i64>,
+        max_size: &Option<i64>,
+    ) -> Self {
+        Self {
+            file_name: file_name.clone(),
+            content_type: content_type.clone(),
+            public: *public,
+            created_by: *created_by,
+            min_size: *min_size,
+            max_size: *max_size,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.file_name.is_none()
+            && self.content_type.is_none()
+            && self.public.is_none()
+            && self.created_by.is_none()
+            && self.min_size.is_none()
+            && self.max_size.is_none()
+    }
+
+    // Same rules as the SQL backends, the name is searched and the content type is matched by
+    // prefix, both case insensitive
+    fn matches(&self, file_data: &FileDao) -> bool {
+        if let Some(file_name) = &self.file_name {
+            if !file_data
+                .file_name
+                .to_lowercase()
+                .contains(&file_name.to_lowercase())
+            {
+                return false;
+            }
+        }
+        if let Some(content_type) = &self.content_type {
+            if !file_data
+                .content_type
+                .to_lowercase()
+                .starts_with(&content_type.to_lowercase())
+            {
+                return false;
+            }
+        }
+        if let Some(public) = &self.public {
+            if &file_data.public != public {
+                return false;
+            }
+        }
+        if let Some(created_by) = &self.created_by {
+            if &file_data.created_by != created_by {
+                return false;
+            }
+        }
+        if let Some(min_size) = &self.min_size {
+            if &file_data.size < min_size {
+                return false;
+            }
+        }
+        if let Some(max_size) = &self.max_size {
+            if &file_data.size > max_size {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn to_postgresdb_model(&self) -> FileFilterPostgresModel {
+        FileFilterPostgresModel::new(
+            &self.file_name,
+            &self.content_type,
+            &self.public,
+            &self.created_by,
+            &self.min_size,
+            &self.max_size,
+        )
+    }
+
+    fn to_mysqldb_model(&self) -> FileFilterMysqlModel {
+        FileFilterMysqlModel::new(
+            &self.file_name,
+            &self.content_type,
+            &self.public,
+            &self.created_by,
+            &self.min_size,
+            &self.max_size,
+        )
+    }
+
+    fn to_sqlitedb_model(&self) -> FileFilterSqliteModel {
+        FileFilterSqliteModel::new(
+            &self.file_name,
+            &self.content_type,
+            &self.public,
+            &self.created_by,
+            &self.min_size,
+            &self.max_size,
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum FileOrder {
+    CreatedAtAsc,
+    CreatedAtDesc,
+    SizeAsc,
+    SizeDesc,
+    NameAsc,
+    NameDesc,
+}
+
+impl FileOrder {
+    // A leading '-' sorts in descending order, like '-size'
+    pub fn from_str(str: &str) -> Result<Self> {
+        match str {
+            "created_at" => Ok(Self::CreatedAtAsc),
+            "-created_at" => Ok(Self::CreatedAtDesc),
+            "size" => Ok(Self::SizeAsc),
+            "-size" => Ok(Self::SizeDesc),
+            "name" => Ok(Self::NameAsc),
+            "-name" => Ok(Self::NameDesc),
+            _ => Err(Error::msg(format!("Unknown file order '{str}'"))),
+        }
+    }
+
+    // File ids are UUIDv7, so ordering by id is ordering by creation time. Files with the same
+    // size or name keep their creation order.
+    fn compare(&self, a: &FileDao, b: &FileDao) -> std::cmp::Ordering {
+        match self {
+            Self::CreatedAtAsc => a.id.cmp(&b.id),
+            Self::CreatedAtDesc => b.id.cmp(&a.id),
+            Self::SizeAsc => (a.size, a.id).cmp(&(b.size, b.id)),
+            Self::SizeDesc => (b.size, b.id).cmp(&(a.size, a.id)),
+            Self::NameAsc => (&a.file_name, &a.id).cmp(&(&b.file_name, &b.id)),
+            Self::NameDesc => (&b.file_name, &b.id).cmp(&(&a.file_name, &a.id)),
+        }
+    }
+
+    fn is_descending(&self) -> bool {
+        matches!(self, Self::CreatedAtDesc | Self::SizeDesc | Self::NameDesc)
+    }
+
+    fn to_postgresdb_model(&self) -> FileOrderPostgresModel {
+        FileOrderPostgresModel::new(
+            &match self {
+                Self::CreatedAtAsc | Self::CreatedAtDesc => FileOrderColumnPostgres::CreatedAt,
+                Self::SizeAsc | Self::SizeDesc => FileOrderColumnPostgres::Size,
+                Self::NameAsc | Self::NameDesc => FileOrderColumnPostgres::FileName,
+            },
+            &self.is_descending(),
         )
     }
+
+    fn to_mysqldb_model(&self) -> FileOrderMysqlModel {
+        FileOrderMysqlModel::new(
+            &match self {
+                Self::CreatedAtAsc | Self::CreatedAtDesc => FileOrderColumnMysql::CreatedAt,
+                Self::SizeAsc | Self::SizeDesc => FileOrderColumnMysql::Size,
+                Self::NameAsc | Self::NameDesc => FileOrderColumnMysql::FileName,
+            },
+            &self.is_descending(),
+        )
+    }
+
+    fn to_sqlitedb_model(&self) -> FileOrderSqliteModel {
+        FileOrderSqliteModel::new(
+            &match self {
+                Self::CreatedAtAsc | Self::CreatedAtDesc => FileOrderColumnSqlite::CreatedAt,
+                Self::SizeAsc | Self::SizeDesc => FileOrderColumnSqlite::Size,
+                Self::NameAsc | Self::NameDesc => FileOrderColumnSqlite::FileName,
+            },
+            &self.is_descending(),
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum ImageFit {
+    Cover,
+    Contain,
+}
+
+impl ImageFit {
+    pub fn from_str(str: &str) -> Result<Self> {
+        match str {
+            "cover" => Ok(Self::Cover),
+            "contain" => Ok(Self::Contain),
+            _ => Err(Error::msg(format!("Unknown image fit '{str}'"))),
+        }
+    }
+
+    pub fn to_str(&self) -> &str {
+        match self {
+            Self::Cover => "cover",
+            Self::Contain => "contain",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum ImageFormat {
+    Webp,
+    Jpeg,
+}
+
+impl ImageFormat {
+    pub fn from_str(str: &str) -> Result<Self> {
+        match str {
+            "webp" => Ok(Self::Webp),
+            "jpeg" => Ok(Self::Jpeg),
+            _ => Err(Error::msg(format!("Unknown image format '{str}'"))),
+        }
+    }
+
+    pub fn to_str(&self) -> &str {
+        match self {
+            Self::Webp => "webp",
+            Self::Jpeg => "jpeg",
+        }
+    }
+
+    pub fn content_type(&self) -> Mime {
+        match self {
+            Self::Webp => Mime::from_str("image/webp").unwrap(),
+            Self::Jpeg => mime::IMAGE_JPEG,
+        }
+    }
+}
+
+pub enum BlobStatus {
+    Available,
+    NotFound,
+    StorageUnavailable,
+}
+
+enum BlobSource<'a> {
+    Path(&'a Path),
+    Bytes(&'a [u8]),
 }
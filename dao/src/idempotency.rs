@@ -0,0 +1,195 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use hb_db_mysql::model::idempotency::IdempotencyModel as IdempotencyMysqlModel;
+use hb_db_postgresql::model::idempotency::IdempotencyModel as IdempotencyPostgresModel;
+use hb_db_scylladb::model::idempotency::IdempotencyModel as IdempotencyScyllaModel;
+use hb_db_sqlite::model::idempotency::IdempotencyModel as IdempotencySqliteModel;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{error::DaoError, util::conversion, Db};
+
+pub struct IdempotencyDao {
+    collection_id: Uuid,
+    owner_id: Uuid,
+    key: String,
+    created_at: DateTime<Utc>,
+    record_id: Uuid,
+}
+
+impl IdempotencyDao {
+    pub fn new(collection_id: &Uuid, owner_id: &Uuid, key: &str, record_id: &Uuid) -> Self {
+        Self {
+            collection_id: *collection_id,
+            owner_id: *owner_id,
+            key: key.to_owned(),
+            created_at: Utc::now(),
+            record_id: *record_id,
+        }
+    }
+
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+
+    pub fn owner_id(&self) -> &Uuid {
+        &self.owner_id
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        &self.created_at
+    }
+
+    pub fn record_id(&self) -> &Uuid {
+        &self.record_id
+    }
+
+    #[instrument(name = "IdempotencyDao::db_insert", skip_all)]
+    pub async fn db_insert(&self, db: &Db) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => db.insert_idempotency(&self.to_scylladb_model()).await,
+            Db::PostgresqlDb(db) => db.insert_idempotency(&self.to_postgresdb_model()).await,
+            Db::MysqlDb(db) => db.insert_idempotency(&self.to_mysqldb_model()).await,
+            Db::SqliteDb(db) => db.insert_idempotency(&self.to_sqlitedb_model()).await,
+        }
+    }
+
+    #[instrument(name = "IdempotencyDao::db_select", skip_all)]
+    pub async fn db_select(
+        db: &Db,
+        collection_id: &Uuid,
+        owner_id: &Uuid,
+        key: &str,
+    ) -> Result<Self> {
+        let not_found =
+            |err| DaoError::from_no_rows(err, format!("Idempotency key '{key}' is not found"));
+        match db {
+            Db::ScyllaDb(db) => Self::from_scylladb_model(
+                &db.select_idempotency(collection_id, owner_id, key)
+                    .await
+                    .map_err(not_found)?,
+            ),
+            Db::PostgresqlDb(db) => Ok(Self::from_postgresdb_model(
+                &db.select_idempotency(collection_id, owner_id, key)
+                    .await
+                    .map_err(not_found)?,
+            )),
+            Db::MysqlDb(db) => Ok(Self::from_mysqldb_model(
+                &db.select_idempotency(collection_id, owner_id, key)
+                    .await
+                    .map_err(not_found)?,
+            )),
+            Db::SqliteDb(db) => Ok(Self::from_sqlitedb_model(
+                &db.select_idempotency(collection_id, owner_id, key)
+                    .await
+                    .map_err(not_found)?,
+            )),
+        }
+    }
+
+    #[instrument(name = "IdempotencyDao::db_delete", skip_all)]
+    pub async fn db_delete(&self, db: &Db) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => {
+                db.delete_idempotency(&self.collection_id, &self.owner_id, &self.key)
+                    .await
+            }
+            Db::PostgresqlDb(db) => {
+                db.delete_idempotency(&self.collection_id, &self.owner_id, &self.key)
+                    .await
+            }
+            Db::MysqlDb(db) => {
+                db.delete_idempotency(&self.collection_id, &self.owner_id, &self.key)
+                    .await
+            }
+            Db::SqliteDb(db) => {
+                db.delete_idempotency(&self.collection_id, &self.owner_id, &self.key)
+                    .await
+            }
+        }
+    }
+
+    fn from_scylladb_model(model: &IdempotencyScyllaModel) -> Result<Self> {
+        Ok(Self {
+            collection_id: *model.collection_id(),
+            owner_id: *model.owner_id(),
+            key: model.key().to_owned(),
+            created_at: conversion::scylla_cql_timestamp_to_datetime_utc(model.created_at())?,
+            record_id: *model.record_id(),
+        })
+    }
+
+    fn to_scylladb_model(&self) -> IdempotencyScyllaModel {
+        IdempotencyScyllaModel::new(
+            &self.collection_id,
+            &self.owner_id,
+            &self.key,
+            &conversion::datetime_utc_to_scylla_cql_timestamp(&self.created_at),
+            &self.record_id,
+        )
+    }
+
+    fn from_postgresdb_model(model: &IdempotencyPostgresModel) -> Self {
+        Self {
+            collection_id: *model.collection_id(),
+            owner_id: *model.owner_id(),
+            key: model.key().to_owned(),
+            created_at: *model.created_at(),
+            record_id: *model.record_id(),
+        }
+    }
+
+    fn to_postgresdb_model(&self) -> IdempotencyPostgresModel {
+        IdempotencyPostgresModel::new(
+            &self.collection_id,
+            &self.owner_id,
+            &self.key,
+            &self.created_at,
+            &self.record_id,
+        )
+    }
+
+    fn from_mysqldb_model(model: &IdempotencyMysqlModel) -> Self {
+        Self {
+            collection_id: *model.collection_id(),
+            owner_id: *model.owner_id(),
+            key: model.key().to_owned(),
+            created_at: *model.created_at(),
+            record_id: *model.record_id(),
+        }
+    }
+
+    fn to_mysqldb_model(&self) -> IdempotencyMysqlModel {
+        IdempotencyMysqlModel::new(
+            &self.collection_id,
+            &self.owner_id,
+            &self.key,
+            &self.created_at,
+            &self.record_id,
+        )
+    }
+
+    fn from_sqlitedb_model(model: &IdempotencySqliteModel) -> Self {
+        Self {
+            collection_id: *model.collection_id(),
+            owner_id: *model.owner_id(),
+            key: model.key().to_owned(),
+            created_at: *model.created_at(),
+            record_id: *model.record_id(),
+        }
+    }
+
+    fn to_sqlitedb_model(&self) -> IdempotencySqliteModel {
+        IdempotencySqliteModel::new(
+            &self.collection_id,
+            &self.owner_id,
+            &self.key,
+            &self.created_at,
+            &self.record_id,
+        )
+    }
+}
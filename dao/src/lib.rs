@@ -1,3 +1,6 @@
+use std::{fmt, ops::Deref};
+
+use anyhow::Result;
 use hb_db_mysql::db::MysqlDb;
 use hb_db_postgresql::db::PostgresDb;
 use hb_db_scylladb::db::ScyllaDb;
@@ -5,18 +8,27 @@ use hb_db_sqlite::db::SqliteDb;
 
 pub mod admin;
 pub mod admin_password_reset;
+pub mod admin_project_member;
+pub mod audit;
 pub mod bucket;
 pub mod bucket_rule;
+pub mod cache;
 pub mod collection;
 pub mod collection_rule;
+pub mod error;
 pub mod file;
+pub mod idempotency;
 pub mod log;
 pub mod project;
 pub mod record;
 pub mod registration;
+pub mod sign_in_attempt;
 pub mod token;
+pub mod token_usage;
 mod util;
 pub mod value;
+pub mod warmup;
+pub mod webhook;
 
 pub enum Db {
     ScyllaDb(ScyllaDb),
@@ -24,3 +36,642 @@ pub enum Db {
     MysqlDb(MysqlDb),
     SqliteDb(SqliteDb),
 }
+
+impl Db {
+    // Starts a transaction that DAO methods run in when they're given the returned handle in place
+    // of this Db. ScyllaDB has no multi-table transactions, so its handle writes right away.
+    pub async fn begin(&self) -> Result<DbTx<'_>> {
+        Ok(match self {
+            Self::ScyllaDb(_) => DbTx::Direct(self),
+            Self::PostgresqlDb(db) => {
+                DbTx::Transaction(Self::PostgresqlDb(db.transaction().await?))
+            }
+            Self::MysqlDb(db) => DbTx::Transaction(Self::MysqlDb(db.transaction().await?)),
+            Self::SqliteDb(db) => DbTx::Transaction(Self::SqliteDb(db.transaction().await?)),
+        })
+    }
+
+    // PostgreSQL and SQLite roll back schema changes along with the rest of a transaction, MySQL
+    // commits before every one of them
+    pub fn is_transactional_ddl(&self) -> bool {
+        matches!(self, Self::PostgresqlDb(_) | Self::SqliteDb(_))
+    }
+
+    pub async fn ping(&self) -> Result<()> {
+        match self {
+            Self::ScyllaDb(db) => Ok(db.ping().await?),
+            Self::PostgresqlDb(db) => Ok(db.ping().await?),
+            Self::MysqlDb(db) => Ok(db.ping().await?),
+            Self::SqliteDb(db) => Ok(db.ping().await?),
+        }
+    }
+
+    pub fn slow_queries(&self) -> u64 {
+        match self {
+            Self::ScyllaDb(db) => db.slow_queries(),
+            Self::PostgresqlDb(db) => db.slow_queries(),
+            Self::MysqlDb(db) => db.slow_queries(),
+            Self::SqliteDb(db) => db.slow_queries(),
+        }
+    }
+
+    pub fn pool_status(&self) -> DbPoolStatus {
+        match self {
+            Self::ScyllaDb(db) => DbPoolStatus::Scylla {
+                nodes: db.pool_nodes(),
+                connected_nodes: db.pool_connected_nodes(),
+                connections: db.pool_connections(),
+            },
+            Self::PostgresqlDb(db) => DbPoolStatus::Sql {
+                size: db.pool_size(),
+                idle: db.pool_idle(),
+                waiting: db.pool_waiting(),
+            },
+            Self::MysqlDb(db) => DbPoolStatus::Sql {
+                size: db.pool_size(),
+                idle: db.pool_idle(),
+                waiting: db.pool_waiting(),
+            },
+            Self::SqliteDb(db) => DbPoolStatus::Sql {
+                size: db.pool_size(),
+                idle: db.pool_idle(),
+                waiting: db.pool_waiting(),
+            },
+        }
+    }
+}
+
+// Everything written through the handle is rolled back when it's dropped without being committed
+pub enum DbTx<'a> {
+    Transaction(Db),
+    Direct(&'a Db),
+}
+
+impl DbTx<'_> {
+    pub async fn commit(self) -> Result<()> {
+        match self {
+            Self::Transaction(Db::PostgresqlDb(db)) => db.commit().await,
+            Self::Transaction(Db::MysqlDb(db)) => db.commit().await,
+            Self::Transaction(Db::SqliteDb(db)) => db.commit().await,
+            Self::Transaction(Db::ScyllaDb(_)) | Self::Direct(_) => Ok(()),
+        }
+    }
+}
+
+impl Deref for DbTx<'_> {
+    type Target = Db;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Transaction(db) => db,
+            Self::Direct(db) => db,
+        }
+    }
+}
+
+// ScyllaDB multiplexes requests over a connection per shard instead of checking connections out
+// of a pool, so it reports its nodes instead
+pub enum DbPoolStatus {
+    Sql {
+        size: u32,
+        idle: u32,
+        waiting: u32,
+    },
+    Scylla {
+        nodes: u32,
+        connected_nodes: u32,
+        connections: u32,
+    },
+}
+
+impl fmt::Display for DbPoolStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sql {
+                size,
+                idle,
+                waiting,
+            } => write!(f, "{size} connections, {idle} idle, {waiting} waiting"),
+            Self::Scylla {
+                nodes,
+                connected_nodes,
+                connections,
+            } => write!(
+                f,
+                "{connected_nodes} of {nodes} nodes up, {connections} connections"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        path::{Path, PathBuf},
+        time::{Duration, Instant},
+    };
+
+    use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
+    use chrono::Utc;
+    use hb_db_sqlite::{db::SqliteDb, query::record as sqlite_record};
+    use uuid::Uuid;
+
+    use crate::{
+        bucket_rule::{BucketPermission, BucketRuleDao},
+        collection::{CollectionDao, SchemaFieldProps},
+        error::DaoError,
+        record::{RecordDao, RecordFilters, RecordPagination},
+        token::TokenDao,
+        value::{ColumnKind, ColumnValue},
+        Db,
+    };
+
+    // A single connection, so a statement that misses the transaction would wait on it and fail
+    // instead of passing unnoticed
+    async fn sqlite_db() -> (Db, PathBuf) {
+        sqlite_pool(&1).await
+    }
+
+    async fn sqlite_pool(max_connections: &u32) -> (Db, PathBuf) {
+        let path = env::temp_dir().join(format!("hb-dao-test-{}.db", Uuid::now_v7()));
+        let db = SqliteDb::new(
+            &path.to_string_lossy(),
+            max_connections,
+            &None,
+            &Some(Duration::from_secs(5)),
+            &None,
+            &None,
+            &None,
+            &None,
+            &None,
+            &None,
+            &None,
+            &None,
+            &0,
+            &0,
+            &0,
+            &0,
+        )
+        .await;
+        (Db::SqliteDb(db), path)
+    }
+
+    fn remove_sqlite_db(path: &Path) {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = path.as_os_str().to_owned();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    // Fails every statement of the given kind on the table, which stands in for the database
+    // failing halfway through an operation
+    async fn inject_failure(db: &Db, event: &str, table: &str) {
+        let db = match db {
+            Db::SqliteDb(db) => db,
+            _ => unreachable!(),
+        };
+        db.execute_unprepared(sqlx::query(&format!(
+            "CREATE TRIGGER \"fail_{event}_{table}\" BEFORE {event} ON \"{table}\" BEGIN SELECT RAISE(ABORT, 'injected failure'); END"
+        )))
+        .await
+        .unwrap();
+    }
+
+    async fn has_column(db: &Db, collection_id: &Uuid, column: &str) -> bool {
+        let db = match db {
+            Db::SqliteDb(db) => db,
+            _ => unreachable!(),
+        };
+        // Selecting the column isn't enough, SQLite reads an unknown quoted column as a string
+        let (count,) = db
+            .fetch_one_unprepared(
+                sqlx::query_as::<_, (i64,)>(
+                    "SELECT COUNT(1) FROM pragma_table_info(?) WHERE \"name\" = ?",
+                )
+                .bind(RecordDao::new_table_name(collection_id))
+                .bind(column),
+            )
+            .await
+            .unwrap();
+        count > 0
+    }
+
+    fn string_field() -> SchemaFieldProps {
+        field(&ColumnKind::String)
+    }
+
+    fn field(kind: &ColumnKind) -> SchemaFieldProps {
+        SchemaFieldProps::new(
+            kind, &false, &false, &false, &false, &false, &false, &None, &None, &None, &None,
+            &None, &None, &None,
+        )
+        .unwrap()
+    }
+
+    fn collection(schema_fields: &[&str]) -> CollectionDao {
+        collection_with_ttl(schema_fields, &None)
+    }
+
+    fn collection_with_ttl(schema_fields: &[&str], opt_ttl: &Option<i64>) -> CollectionDao {
+        CollectionDao::new(
+            &Uuid::now_v7(),
+            "collection",
+            &schema_fields
+                .iter()
+                .map(|field| (field.to_string(), string_field()))
+                .collect(),
+            &Vec::new(),
+            &false,
+            opt_ttl,
+            &false,
+            &false,
+            &false,
+        )
+    }
+
+    async fn insert_records(db: &Db, collection_data: &CollectionDao, count: usize) -> Vec<Uuid> {
+        let mut ids = Vec::with_capacity(count);
+        for idx in 0..count {
+            let mut record_data = RecordDao::new(&Uuid::now_v7(), collection_data.id(), &1);
+            record_data.upsert("title", &ColumnValue::String(Some(format!("record {idx}"))));
+            record_data.db_insert(db, &None).await.unwrap();
+            ids.push(record_data.id().unwrap());
+        }
+        ids
+    }
+
+    fn token() -> TokenDao {
+        TokenDao::new(
+            &Uuid::now_v7(),
+            &Uuid::now_v7(),
+            "token",
+            &32,
+            &false,
+            &None,
+        )
+    }
+
+    fn bucket_rule(token_data: &TokenDao) -> BucketRuleDao {
+        BucketRuleDao::new(
+            token_data.project_id(),
+            token_data.id(),
+            &Uuid::now_v7(),
+            &BucketPermission::All,
+            &BucketPermission::All,
+            &true,
+            &BucketPermission::All,
+            &BucketPermission::All,
+        )
+    }
+
+    #[tokio::test]
+    async fn dropped_transaction_keeps_nothing() {
+        let (db, path) = sqlite_db().await;
+        let token_data = token();
+
+        let tx = db.begin().await.unwrap();
+        token_data.db_insert(&tx).await.unwrap();
+        drop(tx);
+
+        assert!(TokenDao::db_select(&db, token_data.id()).await.is_err());
+        remove_sqlite_db(&path);
+    }
+
+    #[tokio::test]
+    async fn token_with_rules_is_rolled_back_when_a_rule_fails() {
+        let (db, path) = sqlite_db().await;
+        let token_data = token();
+        let bucket_rules_data = [bucket_rule(&token_data), bucket_rule(&token_data)];
+
+        inject_failure(&db, "INSERT", "bucket_rules").await;
+        assert!(token_data
+            .db_insert_with_rules(&db, &[], &bucket_rules_data)
+            .await
+            .is_err());
+
+        assert!(TokenDao::db_select(&db, token_data.id()).await.is_err());
+        assert!(
+            BucketRuleDao::db_select_many_by_token_id(&db, token_data.id())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        remove_sqlite_db(&path);
+    }
+
+    #[tokio::test]
+    async fn token_with_rules_is_committed() {
+        let (db, path) = sqlite_db().await;
+        let token_data = token();
+        let bucket_rules_data = [bucket_rule(&token_data), bucket_rule(&token_data)];
+
+        token_data
+            .db_insert_with_rules(&db, &[], &bucket_rules_data)
+            .await
+            .unwrap();
+
+        assert!(TokenDao::db_select(&db, token_data.id()).await.is_ok());
+        assert_eq!(
+            BucketRuleDao::db_select_many_by_token_id(&db, token_data.id())
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        remove_sqlite_db(&path);
+    }
+
+    #[tokio::test]
+    async fn collection_insert_is_rolled_back_when_the_row_fails() {
+        let (db, path) = sqlite_db().await;
+        let collection_data = collection(&["title"]);
+
+        inject_failure(&db, "INSERT", "collections").await;
+        assert!(collection_data.db_insert(&db).await.is_err());
+
+        assert!(
+            !RecordDao::db_check_table_existence(&db, collection_data.id())
+                .await
+                .unwrap()
+        );
+        remove_sqlite_db(&path);
+    }
+
+    #[tokio::test]
+    async fn collection_update_is_rolled_back_when_the_row_fails() {
+        let (db, path) = sqlite_db().await;
+        let mut collection_data = collection(&["title"]);
+        collection_data.db_insert(&db).await.unwrap();
+
+        let mut schema_fields = collection_data.schema_fields().clone();
+        schema_fields.insert("body".to_owned(), string_field());
+        collection_data.update_schema_fields(&schema_fields, &false, &HashMap::new());
+
+        inject_failure(&db, "UPDATE", "collections").await;
+        assert!(collection_data.db_update(&db).await.is_err());
+
+        assert!(has_column(&db, collection_data.id(), "title").await);
+        assert!(!has_column(&db, collection_data.id(), "body").await);
+        remove_sqlite_db(&path);
+    }
+
+    #[tokio::test]
+    async fn expired_records_are_never_read_and_eventually_deleted() {
+        let (db, path) = sqlite_db().await;
+        let collection_data = collection_with_ttl(&["title"], &Some(1));
+        collection_data.db_insert(&db).await.unwrap();
+        let ids = insert_records(&db, &collection_data, 5).await;
+
+        let ttl = chrono::Duration::seconds(1);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut first_read = true;
+        loop {
+            // A record read back must not have been expired when the read started
+            let read_at = Utc::now();
+            let (records_data, _) = RecordDao::db_select_many(
+                &db,
+                &HashSet::new(),
+                &collection_data,
+                &None,
+                &RecordFilters::new(&Vec::new()),
+                &Vec::new(),
+                &Vec::new(),
+                &RecordPagination::new(&None),
+                &true,
+            )
+            .await
+            .unwrap();
+            if first_read {
+                assert_eq!(records_data.len(), ids.len());
+                first_read = false;
+            }
+            for record_data in &records_data {
+                assert!(record_data.updated_at().unwrap() + ttl > read_at);
+            }
+            for id in &ids {
+                let read_at = Utc::now();
+                match RecordDao::db_select(&db, id, &None, &HashSet::new(), &collection_data, &true)
+                    .await
+                {
+                    Ok(record_data) => assert!(record_data.updated_at().unwrap() + ttl > read_at),
+                    Err(err) => assert!(DaoError::is_not_found(&err), "{err}"),
+                }
+            }
+
+            RecordDao::db_delete_expired(&db, &collection_data, &2)
+                .await
+                .unwrap();
+            if RecordDao::db_count(&db, collection_data.id())
+                .await
+                .unwrap()
+                == 0
+            {
+                break;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "Expired records were never deleted"
+            );
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        remove_sqlite_db(&path);
+    }
+
+    // The record endpoints find, update and delete a record through db_select, restore it through
+    // db_restore and increment its counters through db_increment_counter
+    #[tokio::test]
+    async fn missing_record_is_not_found() {
+        let (db, path) = sqlite_db().await;
+        let mut schema_fields = HashMap::new();
+        schema_fields.insert("title".to_owned(), string_field());
+        schema_fields.insert("views".to_owned(), field(&ColumnKind::Counter));
+        let collection_data = CollectionDao::new(
+            &Uuid::now_v7(),
+            "collection",
+            &schema_fields,
+            &Vec::new(),
+            &false,
+            &None,
+            &true,
+            &false,
+            &false,
+        );
+        collection_data.db_insert(&db).await.unwrap();
+        let id = insert_records(&db, &collection_data, 1).await[0];
+
+        let assert_not_found = |result: anyhow::Result<()>| match result {
+            Ok(_) => panic!("Missing record was found"),
+            Err(err) => assert!(DaoError::is_not_found(&err), "{err}"),
+        };
+        let missing_id = Uuid::now_v7();
+        for id in [&missing_id, &id] {
+            let select =
+                RecordDao::db_select(&db, id, &None, &HashSet::new(), &collection_data, &true)
+                    .await;
+            let increment =
+                RecordDao::db_increment_counter(&db, collection_data.id(), id, &None, "views", &1)
+                    .await;
+            let restore = RecordDao::db_restore(&db, &collection_data, id, &None).await;
+            if id == &missing_id {
+                assert_not_found(select.map(|_| ()));
+                assert_not_found(increment);
+            } else {
+                assert!(select.is_ok());
+                assert!(increment.is_ok());
+            }
+            // Restoring a record that isn't deleted is just as much of a miss
+            assert_not_found(restore);
+        }
+
+        // A soft-deleted record can't be read until it is restored
+        RecordDao::db_delete(&db, &collection_data, &id, &None)
+            .await
+            .unwrap();
+        assert_not_found(
+            RecordDao::db_select(&db, &id, &None, &HashSet::new(), &collection_data, &true)
+                .await
+                .map(|_| ()),
+        );
+        assert!(RecordDao::db_restore(&db, &collection_data, &id, &None)
+            .await
+            .is_ok());
+        remove_sqlite_db(&path);
+    }
+
+    // Every write takes the database lock, so without WAL and a busy timeout the writers would
+    // fail with "database is locked" and the readers would be blocked by them
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrent_writes_and_reads_do_not_fail() {
+        const WRITERS: usize = 8;
+        const RECORDS_PER_WRITER: usize = 25;
+
+        let (db, path) = sqlite_pool(&16).await;
+        let collection_data = collection(&["title"]);
+        collection_data.db_insert(&db).await.unwrap();
+
+        let writers = (0..WRITERS).map(|_| async {
+            for idx in 0..RECORDS_PER_WRITER {
+                let mut record_data = RecordDao::new(&Uuid::now_v7(), collection_data.id(), &1);
+                record_data.upsert("title", &ColumnValue::String(Some(format!("record {idx}"))));
+                record_data.db_insert(&db, &None).await?;
+            }
+            anyhow::Ok(())
+        });
+        let readers = (0..WRITERS).map(|_| async {
+            for _ in 0..RECORDS_PER_WRITER {
+                RecordDao::db_select_many(
+                    &db,
+                    &HashSet::new(),
+                    &collection_data,
+                    &None,
+                    &RecordFilters::new(&Vec::new()),
+                    &Vec::new(),
+                    &Vec::new(),
+                    &RecordPagination::new(&Some(10)),
+                    &true,
+                )
+                .await?;
+            }
+            anyhow::Ok(())
+        });
+        let (writes, reads) = tokio::join!(
+            futures::future::join_all(writers),
+            futures::future::join_all(readers)
+        );
+        for result in writes.into_iter().chain(reads) {
+            if let Err(err) = result {
+                panic!("Concurrent query failed: {err}");
+            }
+        }
+
+        assert_eq!(
+            RecordDao::db_count(&db, collection_data.id())
+                .await
+                .unwrap(),
+            (WRITERS * RECORDS_PER_WRITER) as i64
+        );
+        remove_sqlite_db(&path);
+    }
+
+    // The steps of the plan SQLite picks for the query, a step that goes through every row of a
+    // table starts with SCAN
+    async fn query_plan<'a>(
+        db: &Db,
+        query: sqlx::query::QueryAs<
+            'a,
+            sqlx::Sqlite,
+            (i64, i64, i64, String),
+            sqlx::sqlite::SqliteArguments<'a>,
+        >,
+    ) -> Vec<String> {
+        let db = match db {
+            Db::SqliteDb(db) => db,
+            _ => unreachable!(),
+        };
+        db.fetch_all(query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, _, _, detail)| detail)
+            .collect()
+    }
+
+    // Permission checks filter records on _created_by and the TTL sweep on _updated_at, so neither
+    // may scan the whole record table once the collection holds many records
+    #[tokio::test]
+    async fn record_queries_search_by_index() {
+        let (db, path) = sqlite_db().await;
+        let collection_data = collection_with_ttl(&["title"], &Some(3600));
+        collection_data.db_insert(&db).await.unwrap();
+        insert_records(&db, &collection_data, 100).await;
+        let record_table = RecordDao::new_table_name(collection_data.id());
+
+        let select_by_created_by = format!(
+            "EXPLAIN QUERY PLAN {}",
+            sqlite_record::select_many(
+                &record_table,
+                &vec!["_id", "title"],
+                "\"_created_by\" = ?",
+                &Vec::new(),
+                &Vec::new(),
+                &true,
+            )
+        );
+        let plan = query_plan(
+            &db,
+            sqlx::query_as(&select_by_created_by)
+                .bind(Uuid::now_v7())
+                .bind(10),
+        )
+        .await;
+        assert!(
+            !plan.iter().any(|step| step.starts_with("SCAN")),
+            "{plan:?}"
+        );
+        assert!(
+            plan.iter().any(|step| step.contains("(_created_by=?)")),
+            "{plan:?}"
+        );
+
+        let delete_expired = format!(
+            "EXPLAIN QUERY PLAN {}",
+            sqlite_record::delete_expired(&record_table)
+        );
+        let plan = query_plan(
+            &db,
+            sqlx::query_as(&delete_expired).bind(Utc::now()).bind(10),
+        )
+        .await;
+        assert!(
+            !plan.iter().any(|step| step.starts_with("SCAN")),
+            "{plan:?}"
+        );
+        assert!(
+            plan.iter().any(|step| step.contains("(_updated_at<?)")),
+            "{plan:?}"
+        );
+        remove_sqlite_db(&path);
+    }
+}
@@ -4,6 +4,7 @@ use hb_db_mysql::model::log::LogModel as LogMysqlModel;
 use hb_db_postgresql::model::log::LogModel as LogPostgresModel;
 use hb_db_scylladb::model::log::LogModel as LogScyllaModel;
 use hb_db_sqlite::model::log::LogModel as LogSqliteModel;
+use tracing::instrument;
 use uuid::Uuid;
 
 use crate::{util::conversion, Db};
@@ -53,6 +54,7 @@ impl LogDao {
         &self.message
     }
 
+    #[instrument(name = "LogDao::db_insert", skip_all)]
     pub async fn db_insert(&self, db: &Db) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.insert_log(&self.to_scylladb_model()).await,
@@ -62,21 +64,37 @@ impl LogDao {
         }
     }
 
+    // Logs can be narrowed down by kind, by creation time (both ends inclusive) and by a part of the
+    // message, the total counts every log that matches regardless of before_id and limit
+    #[instrument(name = "LogDao::db_select_many_by_admin_id_and_project_id", skip_all)]
     pub async fn db_select_many_by_admin_id_and_project_id(
         db: &Db,
         admin_id: &Uuid,
         project_id: &Uuid,
+        kind: &Option<LogKind>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        message: &Option<String>,
         before_id: &Option<Uuid>,
         limit: &Option<i32>,
     ) -> Result<(Vec<Self>, i64)> {
+        let kind = &kind.as_ref().map(|kind| kind.to_str());
         match db {
             Db::ScyllaDb(db) => {
+                let from = &from
+                    .as_ref()
+                    .map(conversion::datetime_utc_to_scylla_cql_timestamp);
+                let to = &to
+                    .as_ref()
+                    .map(conversion::datetime_utc_to_scylla_cql_timestamp);
                 let mut logs_data = Vec::new();
                 let (logs, total) = tokio::try_join!(
                     db.select_many_logs_by_admin_id_and_project_id(
-                        admin_id, project_id, before_id, limit
+                        admin_id, project_id, kind, from, to, message, before_id, limit
                     ),
-                    db.count_many_logs_by_admin_id_and_project_id(admin_id, project_id)
+                    db.count_many_logs_by_admin_id_and_project_id(
+                        admin_id, project_id, kind, from, to, message
+                    )
                 )?;
                 for log in logs {
                     logs_data.push(Self::from_scylladb_model(&log?)?);
@@ -86,9 +104,11 @@ impl LogDao {
             Db::PostgresqlDb(db) => {
                 let (logs, total) = tokio::try_join!(
                     db.select_many_logs_by_admin_id_and_project_id(
-                        admin_id, project_id, before_id, limit
+                        admin_id, project_id, kind, from, to, message, before_id, limit
                     ),
-                    db.count_many_logs_by_admin_id_and_project_id(admin_id, project_id)
+                    db.count_many_logs_by_admin_id_and_project_id(
+                        admin_id, project_id, kind, from, to, message
+                    )
                 )?;
                 let mut logs_data = Vec::with_capacity(logs.len());
                 for log in &logs {
@@ -99,9 +119,11 @@ impl LogDao {
             Db::MysqlDb(db) => {
                 let (logs, total) = tokio::try_join!(
                     db.select_many_logs_by_admin_id_and_project_id(
-                        admin_id, project_id, before_id, limit
+                        admin_id, project_id, kind, from, to, message, before_id, limit
                     ),
-                    db.count_many_logs_by_admin_id_and_project_id(admin_id, project_id)
+                    db.count_many_logs_by_admin_id_and_project_id(
+                        admin_id, project_id, kind, from, to, message
+                    )
                 )?;
                 let mut logs_data = Vec::with_capacity(logs.len());
                 for log in &logs {
@@ -112,9 +134,11 @@ impl LogDao {
             Db::SqliteDb(db) => {
                 let (logs, total) = tokio::try_join!(
                     db.select_many_logs_by_admin_id_and_project_id(
-                        admin_id, project_id, before_id, limit
+                        admin_id, project_id, kind, from, to, message, before_id, limit
                     ),
-                    db.count_many_logs_by_admin_id_and_project_id(admin_id, project_id)
+                    db.count_many_logs_by_admin_id_and_project_id(
+                        admin_id, project_id, kind, from, to, message
+                    )
                 )?;
                 let mut logs_data = Vec::with_capacity(logs.len());
                 for log in &logs {
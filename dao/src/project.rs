@@ -1,16 +1,29 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use futures::future;
+use futures::{future, TryStreamExt};
 use hb_db_mysql::model::project::ProjectModel as ProjectMysqlModel;
 use hb_db_postgresql::model::project::ProjectModel as ProjectPostgresModel;
 use hb_db_scylladb::model::project::ProjectModel as ProjectScyllaModel;
 use hb_db_sqlite::model::project::ProjectModel as ProjectSqliteModel;
 use serde::{Deserialize, Serialize};
+use tracing::instrument;
 use uuid::Uuid;
 
-use crate::{bucket::BucketDao, collection::CollectionDao, token::TokenDao, util::conversion, Db};
+use crate::{
+    admin_project_member::AdminProjectMemberDao,
+    bucket::BucketDao,
+    cache::TtlCache,
+    collection::{CollectionDao, CollectionOrder},
+    error::DaoError,
+    token::TokenDao,
+    util::conversion,
+    Db,
+};
 
-#[derive(Deserialize, Serialize)]
+// Looked up by most requests to check the project they target, see cache::init
+static CACHE: TtlCache<ProjectDao> = TtlCache::new();
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct ProjectDao {
     id: Uuid,
     created_at: DateTime<Utc>,
@@ -62,6 +75,10 @@ impl ProjectDao {
         &self.name
     }
 
+    pub fn set_id(&mut self, id: &Uuid) {
+        self.id = *id;
+    }
+
     pub fn set_admin_id(&mut self, admin_id: &Uuid) {
         self.admin_id = *admin_id;
     }
@@ -70,6 +87,7 @@ impl ProjectDao {
         self.name = name.to_owned();
     }
 
+    #[instrument(name = "ProjectDao::db_insert", skip_all)]
     pub async fn db_insert(&self, db: &Db) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.insert_project(&self.to_scylladb_model()).await,
@@ -79,15 +97,73 @@ impl ProjectDao {
         }
     }
 
+    #[instrument(name = "ProjectDao::db_select", skip_all)]
     pub async fn db_select(db: &Db, id: &Uuid) -> Result<Self> {
+        if let Some(project_data) = CACHE.get(id) {
+            return Ok(project_data);
+        }
+        let project_data = Self::select(db, id).await?;
+        CACHE.insert(id, &project_data);
+        Ok(project_data)
+    }
+
+    async fn select(db: &Db, id: &Uuid) -> Result<Self> {
+        let not_found = |err| DaoError::from_no_rows(err, format!("Project '{id}' is not found"));
         match db {
-            Db::ScyllaDb(db) => Self::from_scylladb_model(&db.select_project(id).await?),
-            Db::PostgresqlDb(db) => Ok(Self::from_postgresdb_model(&db.select_project(id).await?)),
-            Db::MysqlDb(db) => Ok(Self::from_mysqldb_model(&db.select_project(id).await?)),
-            Db::SqliteDb(db) => Ok(Self::from_sqlitedb_model(&db.select_project(id).await?)),
+            Db::ScyllaDb(db) => {
+                Self::from_scylladb_model(&db.select_project(id).await.map_err(not_found)?)
+            }
+            Db::PostgresqlDb(db) => Ok(Self::from_postgresdb_model(
+                &db.select_project(id).await.map_err(not_found)?,
+            )),
+            Db::MysqlDb(db) => Ok(Self::from_mysqldb_model(
+                &db.select_project(id).await.map_err(not_found)?,
+            )),
+            Db::SqliteDb(db) => Ok(Self::from_sqlitedb_model(
+                &db.select_project(id).await.map_err(not_found)?,
+            )),
         }
     }
 
+    #[instrument(name = "ProjectDao::db_select_many", skip_all)]
+    pub async fn db_select_many(db: &Db) -> Result<Vec<Self>> {
+        match db {
+            Db::ScyllaDb(db) => {
+                let mut projects_data = Vec::new();
+                let mut projects = db.select_many_projects().await?;
+                while let Some(project) = projects.try_next().await? {
+                    projects_data.push(Self::from_scylladb_model(&project)?);
+                }
+                Ok(projects_data)
+            }
+            Db::PostgresqlDb(db) => {
+                let projects = db.select_many_projects().await?;
+                let mut projects_data = Vec::with_capacity(projects.len());
+                for project in &projects {
+                    projects_data.push(Self::from_postgresdb_model(project));
+                }
+                Ok(projects_data)
+            }
+            Db::MysqlDb(db) => {
+                let projects = db.select_many_projects().await?;
+                let mut projects_data = Vec::with_capacity(projects.len());
+                for project in &projects {
+                    projects_data.push(Self::from_mysqldb_model(project));
+                }
+                Ok(projects_data)
+            }
+            Db::SqliteDb(db) => {
+                let projects = db.select_many_projects().await?;
+                let mut projects_data = Vec::with_capacity(projects.len());
+                for project in &projects {
+                    projects_data.push(Self::from_sqlitedb_model(project));
+                }
+                Ok(projects_data)
+            }
+        }
+    }
+
+    #[instrument(name = "ProjectDao::db_select_many_by_admin_id", skip_all)]
     pub async fn db_select_many_by_admin_id(db: &Db, admin_id: &Uuid) -> Result<Vec<Self>> {
         match db {
             Db::ScyllaDb(db) => {
@@ -125,8 +201,10 @@ impl ProjectDao {
         }
     }
 
+    #[instrument(name = "ProjectDao::db_update", skip_all)]
     pub async fn db_update(&mut self, db: &Db) -> Result<()> {
         self.updated_at = Utc::now();
+        CACHE.remove(&self.id);
         match db {
             Db::ScyllaDb(db) => db.update_project(&self.to_scylladb_model()).await,
             Db::PostgresqlDb(db) => db.update_project(&self.to_postgresdb_model()).await,
@@ -135,9 +213,17 @@ impl ProjectDao {
         }
     }
 
+    #[instrument(name = "ProjectDao::db_delete", skip_all)]
     pub async fn db_delete(db: &Db, id: &Uuid) -> Result<()> {
         let (collections_data, buckets_data, tokens_data) = tokio::try_join!(
-            CollectionDao::db_select_many_by_project_id(db, id),
+            CollectionDao::db_select_many_by_project_id(
+                db,
+                id,
+                &None,
+                &None,
+                &CollectionOrder::CreatedAtDesc,
+                &None
+            ),
             BucketDao::db_select_many_by_project_id(db, id),
             TokenDao::db_select_many_by_project_id(db, id)
         )?;
@@ -160,6 +246,9 @@ impl ProjectDao {
         }
         future::join_all(remove_tokens).await;
 
+        AdminProjectMemberDao::db_delete_many_by_project_id(db, id).await?;
+
+        CACHE.remove(id);
         match db {
             Db::ScyllaDb(db) => db.delete_project(id).await,
             Db::PostgresqlDb(db) => db.delete_project(id).await,
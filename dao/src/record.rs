@@ -3,6 +3,7 @@ use std::collections::hash_map::Keys;
 use ahash::{HashMap, HashMapExt, HashSet};
 use anyhow::{Error, Result};
 use chrono::{DateTime, Duration, Utc};
+use futures::{future, stream, Stream, StreamExt, TryStreamExt};
 use hb_db_mysql::{
     db::MysqlDb,
     model::{
@@ -50,16 +51,55 @@ use hb_db_sqlite::{
     },
     query::{record as sqlite_record, system::COUNT_TABLE as SQLITE_COUNT_TABLE},
 };
-use scylla::{frame::response::result::CqlValue as ScyllaCqlValue, serialize::value::SerializeCql};
+use scylla::{
+    frame::{response::result::CqlValue as ScyllaCqlValue, value::Counter as ScyllaCounter},
+    serialize::value::SerializeCql,
+};
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::instrument;
 use uuid::Uuid;
 
 use crate::{
-    collection::{CollectionDao, SchemaFieldProps},
+    collection::{CollectionDao, CollectionOrder, ReferenceAction, SchemaFieldProps},
+    error::DaoError,
     value::{ColumnKind, ColumnValue},
     Db,
 };
 
+const NEAR_FILTER_REQUIRED_ERROR: &str =
+    "Operator '$near' requires a 'value' field with 'lat', 'lng', and 'radius' (in meters)";
+const METERS_PER_DEGREE: f64 = 6371008.8 * std::f64::consts::PI / 180.0;
+const SCYLLADB_STREAM_CHUNK_SIZE: usize = 500;
+const COPY_RECORDS_CHANNEL_CAPACITY: usize = 500;
+const SELECT_STREAM_CHANNEL_CAPACITY: usize = 500;
+// Lowest bind parameter limit among the SQL backends (SQLite's SQLITE_MAX_VARIABLE_NUMBER)
+const INSERT_MANY_MAX_PARAMS: usize = 32766;
+// Expired records deleted per statement when a whole collection is purged at once
+const DELETE_EXPIRED_BATCH_SIZE: i64 = 1000;
+// Comparison operators that test the field alone and therefore must not carry a value
+const VALUELESS_OPERATOR: [&str; 10] = [
+    "IS NULL",
+    "IS NOT NULL",
+    "ISNULL",
+    "NOTNULL",
+    "IS TRUE",
+    "IS NOT TRUE",
+    "IS FALSE",
+    "IS NOT FALSE",
+    "IS UNKNOWN",
+    "IS NOT UNKNOWN",
+];
+// Comparison operators that take a list of values instead of a single one
+const LIST_OPERATOR: [&str; 2] = ["IN", "NOT IN"];
+// Comparison operators that take the lower and upper bound of a range
+const RANGE_OPERATOR: [&str; 4] = [
+    "BETWEEN",
+    "NOT BETWEEN",
+    "BETWEEN SYMMETRIC",
+    "NOT BETWEEN SYMMETRIC",
+];
+
 #[derive(Deserialize, Serialize)]
 pub struct RecordDao {
     table_name: String,
@@ -134,10 +174,39 @@ impl RecordDao {
         }
     }
 
+    // _created_at isn't stored, it's read from the timestamp a UUIDv7 _id starts with. Ids that
+    // carry no timestamp, like the UUIDv4 ones, have no creation time.
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        Self::id_timestamp(self.id().as_ref()?)
+    }
+
+    fn id_timestamp(id: &Uuid) -> Option<DateTime<Utc>> {
+        let (secs, nanos) = id.get_timestamp()?.to_unix();
+        DateTime::from_timestamp(i64::try_from(secs).ok()?, nanos)
+    }
+
     pub fn data(&self) -> &HashMap<String, ColumnValue> {
         &self.data
     }
 
+    // The record as sent to clients, with the virtual _created_at field next to _id
+    pub fn to_serde_json(&self) -> Result<HashMap<String, serde_json::Value>> {
+        let mut record = HashMap::with_capacity(self.data.len() + 1);
+        for (key, value) in &self.data {
+            match value.to_serde_json() {
+                Ok(value) => record.insert(key.to_owned(), value),
+                Err(err) => return Err(Error::msg(format!("Error in field '{key}': {err}"))),
+            };
+        }
+        if self.data.contains_key("_id") {
+            record.insert(
+                "_created_at".to_owned(),
+                ColumnValue::Timestamp(self.created_at()).to_serde_json()?,
+            );
+        }
+        Ok(record)
+    }
+
     pub fn get(&self, key: &str) -> Option<&ColumnValue> {
         self.data.get(key)
     }
@@ -154,28 +223,41 @@ impl RecordDao {
         self.data.insert(key.to_owned(), value.to_owned());
     }
 
+    #[instrument(name = "RecordDao::db_create_table", skip_all)]
     pub async fn db_create_table(db: &Db, collection: &CollectionDao) -> Result<()> {
+        let mut schema_fields = collection.schema_fields().clone();
+        if *collection.opt_soft_delete() {
+            schema_fields.insert("_deleted_at".to_owned(), SchemaFieldProps::deleted_at());
+        }
+        if *collection.opt_per_record_ttl() {
+            schema_fields.insert("_expired_at".to_owned(), SchemaFieldProps::expired_at());
+        }
         match db {
             Db::ScyllaDb(db) => {
                 Self::scylladb_create_table(
                     db,
                     collection.id(),
-                    &collection
-                        .schema_fields()
+                    &schema_fields
                         .iter()
                         .map(|(field_name, field_props)| {
                             (field_name.clone(), field_props.to_scylladb_model())
                         })
                         .collect::<HashMap<_, _>>(),
                 )
-                .await
+                .await?;
+                if schema_fields
+                    .values()
+                    .any(|field_props| *field_props.kind() == ColumnKind::Counter)
+                {
+                    Self::scylladb_create_counter_table(db, collection.id()).await?;
+                }
+                Ok(())
             }
             Db::PostgresqlDb(db) => {
                 Self::postgresdb_create_table(
                     db,
                     collection.id(),
-                    &collection
-                        .schema_fields()
+                    &schema_fields
                         .iter()
                         .map(|(field_name, field_props)| {
                             (field_name.clone(), field_props.to_postgresdb_model())
@@ -188,8 +270,7 @@ impl RecordDao {
                 Self::mysqldb_create_table(
                     db,
                     collection.id(),
-                    &collection
-                        .schema_fields()
+                    &schema_fields
                         .iter()
                         .map(|(field_name, field_props)| {
                             (field_name.clone(), field_props.to_mysqldb_model())
@@ -202,8 +283,7 @@ impl RecordDao {
                 Self::sqlitedb_create_table(
                     db,
                     collection.id(),
-                    &collection
-                        .schema_fields()
+                    &schema_fields
                         .iter()
                         .map(|(field_name, field_props)| {
                             (field_name.clone(), field_props.to_sqlitedb_model())
@@ -212,9 +292,18 @@ impl RecordDao {
                 )
                 .await
             }
+        }?;
+
+        // Permission checks filter records on _created_by and the TTL sweep of the SQL backends on
+        // _updated_at, ScyllaDB expires records through the table's own TTL instead
+        Self::db_create_index(db, collection.id(), "_created_by").await?;
+        if !matches!(db, Db::ScyllaDb(_)) {
+            Self::db_create_index(db, collection.id(), "_updated_at").await?;
         }
+        Ok(())
     }
 
+    #[instrument(name = "RecordDao::db_drop_table", skip_all)]
     pub async fn db_drop_table(db: &Db, collection_id: &Uuid) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => Self::scylladb_drop_table(db, collection_id).await,
@@ -224,6 +313,7 @@ impl RecordDao {
         }
     }
 
+    #[instrument(name = "RecordDao::db_check_table_existence", skip_all)]
     pub async fn db_check_table_existence(db: &Db, collection_id: &Uuid) -> Result<bool> {
         match db {
             Db::ScyllaDb(db) => Self::scylladb_check_table_existence(db, collection_id).await,
@@ -233,6 +323,7 @@ impl RecordDao {
         }
     }
 
+    #[instrument(name = "RecordDao::db_check_table_must_exist", skip_all)]
     pub async fn db_check_table_must_exist(db: &Db, collection_id: &Uuid) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => {
@@ -270,6 +361,7 @@ impl RecordDao {
         }
     }
 
+    #[instrument(name = "RecordDao::db_add_columns", skip_all)]
     pub async fn db_add_columns(
         db: &Db,
         collection_id: &Uuid,
@@ -285,7 +377,14 @@ impl RecordDao {
                         .map(|(col, col_props)| (col.to_owned(), col_props.to_scylladb_model()))
                         .collect(),
                 )
-                .await
+                .await?;
+                if columns
+                    .values()
+                    .any(|col_props| *col_props.kind() == ColumnKind::Counter)
+                {
+                    Self::scylladb_create_counter_table(db, collection_id).await?;
+                }
+                Ok(())
             }
             Db::PostgresqlDb(db) => {
                 Self::postgresdb_add_columns(
@@ -323,6 +422,7 @@ impl RecordDao {
         }
     }
 
+    #[instrument(name = "RecordDao::db_drop_columns", skip_all)]
     pub async fn db_drop_columns(
         db: &Db,
         collection_id: &Uuid,
@@ -338,6 +438,23 @@ impl RecordDao {
         }
     }
 
+    #[instrument(name = "RecordDao::db_rename_columns", skip_all)]
+    pub async fn db_rename_columns(
+        db: &Db,
+        collection_id: &Uuid,
+        columns: &HashMap<String, String>,
+    ) -> Result<()> {
+        match db {
+            Db::ScyllaDb(_) => Err(Error::msg(
+                "Renaming fields is not supported in ScyllaDB because it can only rename primary key columns",
+            )),
+            Db::PostgresqlDb(db) => Self::postgresdb_rename_columns(db, collection_id, columns).await,
+            Db::MysqlDb(db) => Self::mysqldb_rename_columns(db, collection_id, columns).await,
+            Db::SqliteDb(db) => Self::sqlitedb_rename_columns(db, collection_id, columns).await,
+        }
+    }
+
+    #[instrument(name = "RecordDao::db_change_columns_type", skip_all)]
     pub async fn db_change_columns_type(
         db: &Db,
         collection_id: &Uuid,
@@ -353,7 +470,14 @@ impl RecordDao {
                         .map(|(col, col_props)| (col.to_owned(), col_props.to_scylladb_model()))
                         .collect(),
                 )
-                .await
+                .await?;
+                if columns
+                    .values()
+                    .any(|col_props| *col_props.kind() == ColumnKind::Counter)
+                {
+                    Self::scylladb_create_counter_table(db, collection_id).await?;
+                }
+                Ok(())
             }
             Db::PostgresqlDb(db) => {
                 Self::postgresdb_change_columns_type(
@@ -391,6 +515,7 @@ impl RecordDao {
         }
     }
 
+    #[instrument(name = "RecordDao::db_create_index", skip_all)]
     pub async fn db_create_index(db: &Db, collection_id: &Uuid, index: &str) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => Self::scylladb_create_index(db, collection_id, index).await,
@@ -400,17 +525,31 @@ impl RecordDao {
         }
     }
 
-    pub async fn db_create_unique_index(db: &Db, collection_id: &Uuid, index: &str) -> Result<()> {
+    #[instrument(name = "RecordDao::db_create_unique_index", skip_all)]
+    pub async fn db_create_unique_index(
+        db: &Db,
+        collection_id: &Uuid,
+        columns: &[String],
+    ) -> Result<()> {
+        let columns = columns.iter().map(String::as_str).collect::<Vec<_>>();
         match db {
-            Db::ScyllaDb(db) => Self::scylladb_create_index(db, collection_id, index).await,
+            Db::ScyllaDb(db) => match columns.as_slice() {
+                [column] => Self::scylladb_create_index(db, collection_id, column).await,
+                _ => Err(Error::msg(
+                    "Composite unique indexes are not supported in ScyllaDB",
+                )),
+            },
             Db::PostgresqlDb(db) => {
-                Self::postgresdb_create_unique_index(db, collection_id, index).await
+                Self::postgresdb_create_unique_index(db, collection_id, &columns).await
+            }
+            Db::MysqlDb(db) => Self::mysqldb_create_unique_index(db, collection_id, &columns).await,
+            Db::SqliteDb(db) => {
+                Self::sqlitedb_create_unique_index(db, collection_id, &columns).await
             }
-            Db::MysqlDb(db) => Self::mysqldb_create_unique_index(db, collection_id, index).await,
-            Db::SqliteDb(db) => Self::sqlitedb_create_unique_index(db, collection_id, index).await,
         }
     }
 
+    #[instrument(name = "RecordDao::db_drop_index", skip_all)]
     pub async fn db_drop_index(db: &Db, collection_id: &Uuid, index: &str) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => Self::scylladb_drop_index(db, collection_id, index).await,
@@ -420,17 +559,29 @@ impl RecordDao {
         }
     }
 
-    pub async fn db_drop_unique_index(db: &Db, collection_id: &Uuid, index: &str) -> Result<()> {
+    #[instrument(name = "RecordDao::db_drop_unique_index", skip_all)]
+    pub async fn db_drop_unique_index(
+        db: &Db,
+        collection_id: &Uuid,
+        columns: &[String],
+    ) -> Result<()> {
+        let columns = columns.iter().map(String::as_str).collect::<Vec<_>>();
         match db {
-            Db::ScyllaDb(db) => Self::scylladb_drop_index(db, collection_id, index).await,
+            Db::ScyllaDb(db) => match columns.as_slice() {
+                [column] => Self::scylladb_drop_index(db, collection_id, column).await,
+                _ => Err(Error::msg(
+                    "Composite unique indexes are not supported in ScyllaDB",
+                )),
+            },
             Db::PostgresqlDb(db) => {
-                Self::postgresdb_drop_unique_index(db, collection_id, index).await
+                Self::postgresdb_drop_unique_index(db, collection_id, &columns).await
             }
-            Db::MysqlDb(db) => Self::mysqldb_drop_unique_index(db, collection_id, index).await,
-            Db::SqliteDb(db) => Self::sqlitedb_drop_unique_index(db, collection_id, index).await,
+            Db::MysqlDb(db) => Self::mysqldb_drop_unique_index(db, collection_id, &columns).await,
+            Db::SqliteDb(db) => Self::sqlitedb_drop_unique_index(db, collection_id, &columns).await,
         }
     }
 
+    #[instrument(name = "RecordDao::db_insert", skip_all)]
     pub async fn db_insert(&self, db: &Db, collection_data: &Option<CollectionDao>) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => Self::scylladb_insert(self, db, collection_data).await,
@@ -438,8 +589,111 @@ impl RecordDao {
             Db::MysqlDb(db) => Self::mysqldb_insert(self, db).await,
             Db::SqliteDb(db) => Self::sqlitedb_insert(self, db).await,
         }
+        .map_err(|err| DaoError::from_unique_violation(err, &self.table_name))
+    }
+
+    // Records inserted together must belong to the same collection and have the same set of
+    // columns. SQL backends write them in a single multi-row statement, so a batch either lands
+    // completely or not at all.
+    #[instrument(name = "RecordDao::db_insert_many", skip_all)]
+    pub async fn db_insert_many(
+        db: &Db,
+        records: &[Self],
+        collection_data: &Option<CollectionDao>,
+    ) -> Result<()> {
+        let first_record = match records.first() {
+            Some(record) => record,
+            None => return Ok(()),
+        };
+        let mut columns = first_record
+            .data
+            .keys()
+            .map(|col| col.as_str())
+            .collect::<Vec<_>>();
+        columns.sort_unstable();
+        for record in records {
+            if record.table_name != first_record.table_name
+                || record.data.len() != columns.len()
+                || !columns.iter().all(|col| record.data.contains_key(*col))
+            {
+                return Err(Error::msg(
+                    "Records inserted together must belong to the same collection and have the same fields",
+                ));
+            }
+        }
+        if records.len() > Self::insert_many_limit(&columns.len()) {
+            return Err(Error::msg(format!(
+                "Can't insert more than {} records of this collection at once",
+                Self::insert_many_limit(&columns.len())
+            )));
+        }
+
+        match db {
+            Db::ScyllaDb(db) => Self::scylladb_insert_many(db, records, collection_data).await,
+            Db::PostgresqlDb(db) => Self::postgresdb_insert_many(db, &columns, records).await,
+            Db::MysqlDb(db) => Self::mysqldb_insert_many(db, &columns, records).await,
+            Db::SqliteDb(db) => Self::sqlitedb_insert_many(db, &columns, records).await,
+        }
+        .map_err(|err| DaoError::from_unique_violation(err, &first_record.table_name))
+    }
+
+    pub fn insert_many_limit(columns: &usize) -> usize {
+        (INSERT_MANY_MAX_PARAMS / columns.max(&1)).max(1)
+    }
+
+    // Copies the records of a collection into another collection with the same schema, leaving out
+    // the ones that are expired or soft-deleted. Records keeping their ids are copied by the
+    // database in a single INSERT INTO ... SELECT, new ids are generated here because none of the
+    // backends can generate a UUIDv7. Per-record expiry times are kept when both collections have
+    // per-record TTL enabled.
+    #[instrument(name = "RecordDao::db_copy_records", skip_all)]
+    pub async fn db_copy_records(
+        db: &Db,
+        source_collection: &CollectionDao,
+        target_collection: &CollectionDao,
+        preserve_ids: &bool,
+    ) -> Result<()> {
+        let mut columns = Vec::with_capacity(source_collection.schema_fields().len() + 3);
+        columns.extend(["_id", "_created_by", "_updated_at"]);
+        columns.extend(
+            source_collection
+                .schema_fields()
+                .keys()
+                .map(|field| field.as_str()),
+        );
+        if *source_collection.opt_per_record_ttl() && *target_collection.opt_per_record_ttl() {
+            columns.push("_expired_at");
+        }
+
+        match db {
+            Db::ScyllaDb(_) => Err(Error::msg(
+                "Copying records is not supported in ScyllaDB because it has no INSERT INTO ... SELECT",
+            )),
+            _ if !*preserve_ids => {
+                Self::db_copy_records_with_new_ids(
+                    db,
+                    &columns,
+                    source_collection,
+                    target_collection,
+                )
+                .await
+            }
+            Db::PostgresqlDb(db) => {
+                Self::postgresdb_copy_records(db, &columns, source_collection, target_collection)
+                    .await
+            }
+            Db::MysqlDb(db) => {
+                Self::mysqldb_copy_records(db, &columns, source_collection, target_collection)
+                    .await
+            }
+            Db::SqliteDb(db) => {
+                Self::sqlitedb_copy_records(db, &columns, source_collection, target_collection)
+                    .await
+            }
+        }
     }
 
+    #[instrument(name = "RecordDao::db_select", skip_all)]
     pub async fn db_select(
         db: &Db,
         id: &Uuid,
@@ -448,9 +702,15 @@ impl RecordDao {
         collection_data: &CollectionDao,
         is_admin: &bool,
     ) -> Result<Self> {
-        if let Some(ttl_seconds) = collection_data.opt_ttl() {
-            Self::db_delete_expired(db, collection_data.id(), ttl_seconds).await?;
-        }
+        let not_found = |err| {
+            DaoError::from_timeout(DaoError::from_no_rows(
+                err,
+                format!(
+                    "Record '{id}' is not found in collection '{}'",
+                    collection_data.name()
+                ),
+            ))
+        };
         match db {
             Db::ScyllaDb(db) => {
                 let table_name = Self::new_table_name(collection_data.id());
@@ -467,13 +727,7 @@ impl RecordDao {
                         }
                     }
                 } else {
-                    columns = Vec::with_capacity(collection_data.schema_fields().len() + 3);
-                    for column in ["_id", "_created_by", "_updated_at"] {
-                        columns.push(column);
-                    }
-                    for column in collection_data.schema_fields().keys() {
-                        columns.push(column);
-                    }
+                    columns = Self::scylladb_select_columns(collection_data);
                 }
 
                 let scylladb_data = Self::scylladb_select(
@@ -484,7 +738,8 @@ impl RecordDao {
                     id,
                     created_by,
                 )
-                .await?;
+                .await
+                .map_err(not_found)?;
 
                 let mut data = HashMap::with_capacity(scylladb_data.len());
                 for (idx, value) in scylladb_data.iter().enumerate() {
@@ -517,11 +772,19 @@ impl RecordDao {
                     };
                 }
 
-                Ok(Self {
+                let mut record = Self {
                     table_name,
                     collection_id: *collection_data.id(),
                     data,
-                })
+                };
+                Self::scylladb_merge_counters(
+                    db,
+                    &record.table_name.to_owned(),
+                    std::slice::from_mut(&mut record),
+                )
+                .await?;
+
+                Ok(record)
             }
             Db::PostgresqlDb(db) => {
                 let table_name = Self::new_table_name(collection_data.id());
@@ -545,8 +808,16 @@ impl RecordDao {
                     }
                 }
 
-                let postgresdb_data =
-                    Self::postgresdb_select(db, &table_name, &columns, id, created_by).await?;
+                let postgresdb_data = Self::postgresdb_select(
+                    db,
+                    &table_name,
+                    &columns,
+                    id,
+                    created_by,
+                    collection_data,
+                )
+                .await
+                .map_err(not_found)?;
 
                 let mut data = HashMap::with_capacity(columns.len());
                 for column in columns {
@@ -599,8 +870,16 @@ impl RecordDao {
                     }
                 }
 
-                let mysqldb_data =
-                    Self::mysqldb_select(db, &table_name, &columns, id, created_by).await?;
+                let mysqldb_data = Self::mysqldb_select(
+                    db,
+                    &table_name,
+                    &columns,
+                    id,
+                    created_by,
+                    collection_data,
+                )
+                .await
+                .map_err(not_found)?;
 
                 let mut data = HashMap::with_capacity(columns.len());
                 for column in columns {
@@ -653,8 +932,16 @@ impl RecordDao {
                     }
                 }
 
-                let sqlitedb_data =
-                    Self::sqlitedb_select(db, &table_name, &columns, id, created_by).await?;
+                let sqlitedb_data = Self::sqlitedb_select(
+                    db,
+                    &table_name,
+                    &columns,
+                    id,
+                    created_by,
+                    collection_data,
+                )
+                .await
+                .map_err(not_found)?;
 
                 let mut data = HashMap::with_capacity(columns.len());
                 for column in columns {
@@ -688,6 +975,9 @@ impl RecordDao {
         }
     }
 
+    // Filters over large collections are what runs into the statement timeout, so it's told apart
+    // from the other errors here
+    #[instrument(name = "RecordDao::db_select_many", skip_all)]
     pub async fn db_select_many(
         db: &Db,
         fields: &HashSet<&str>,
@@ -699,12 +989,42 @@ impl RecordDao {
         pagination: &RecordPagination,
         is_admin: &bool,
     ) -> Result<(Vec<Self>, i64)> {
-        if let Some(ttl_seconds) = collection_data.opt_ttl() {
-            Self::db_delete_expired(db, collection_data.id(), ttl_seconds).await?;
-        }
+        Self::select_many(
+            db,
+            fields,
+            collection_data,
+            created_by,
+            filters,
+            groups,
+            orders,
+            pagination,
+            is_admin,
+        )
+        .await
+        .map_err(DaoError::from_timeout)
+    }
+
+    async fn select_many(
+        db: &Db,
+        fields: &HashSet<&str>,
+        collection_data: &CollectionDao,
+        created_by: &Option<Uuid>,
+        filters: &RecordFilters,
+        groups: &Vec<&str>,
+        orders: &Vec<RecordOrder>,
+        pagination: &RecordPagination,
+        is_admin: &bool,
+    ) -> Result<(Vec<Self>, i64)> {
+        let filters = &Self::visible_filters(db, collection_data, filters)?;
 
         match db {
             Db::ScyllaDb(db) => {
+                if orders.iter().any(|order| order.near.is_some()) {
+                    return Err(Error::msg(
+                        "Ordering by distance is not supported in ScyllaDB because it has no geospatial functions",
+                    ));
+                }
+
                 let table_name = Self::new_table_name(collection_data.id());
 
                 let mut count = false;
@@ -803,6 +1123,8 @@ impl RecordDao {
                     });
                 }
 
+                Self::scylladb_merge_counters(db, &table_name, &mut data_many).await?;
+
                 Ok((data_many, total))
             }
             Db::PostgresqlDb(db) => {
@@ -1063,6 +1385,155 @@ impl RecordDao {
         }
     }
 
+    // Yields the matching records as they're read instead of collecting them. Hidden fields are
+    // left out of every record unless an admin reads them, and an error ends the stream.
+    pub fn db_select_many_stream<'a>(
+        db: &'a Db,
+        columns: &'a Vec<&'a str>,
+        collection_data: &'a CollectionDao,
+        created_by: &'a Option<Uuid>,
+        filters: &'a RecordFilters,
+        orders: &'a Vec<RecordOrder>,
+        pagination: &'a RecordPagination,
+        is_admin: &bool,
+    ) -> impl Stream<Item = Result<Self>> + Send + 'a {
+        let is_admin = *is_admin;
+        let (tx, rx) = mpsc::channel(SELECT_STREAM_CHANNEL_CAPACITY);
+
+        let records = stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|record| (Ok(record), rx))
+        });
+        // Reading is driven by polling the stream, the sender is dropped once it's done
+        let select = stream::once(async move {
+            Self::select_many_send(
+                db,
+                columns,
+                collection_data,
+                created_by,
+                filters,
+                orders,
+                pagination,
+                &tx,
+            )
+            .await
+        })
+        .filter_map(|res| future::ready(res.err().map(|err| Err(DaoError::from_timeout(err)))));
+
+        stream::select(records, select).map(move |record| {
+            record.map(|mut record| {
+                if !is_admin {
+                    record.data.retain(|column, _| {
+                        collection_data
+                            .schema_fields()
+                            .get(column)
+                            .map_or(true, |field_props| !*field_props.hidden())
+                    });
+                }
+                record
+            })
+        })
+    }
+
+    // Sends the matching records to the channel as they're read instead of collecting them, and
+    // stops early when the receiver is dropped
+    async fn select_many_send(
+        db: &Db,
+        columns: &Vec<&str>,
+        collection_data: &CollectionDao,
+        created_by: &Option<Uuid>,
+        filters: &RecordFilters,
+        orders: &Vec<RecordOrder>,
+        pagination: &RecordPagination,
+        tx: &mpsc::Sender<Self>,
+    ) -> Result<()> {
+        let filters = &Self::visible_filters(db, collection_data, filters)?;
+
+        let mut kinds = Vec::with_capacity(columns.len());
+        for column in columns {
+            kinds.push(match *column {
+                "_id" | "_created_by" => &ColumnKind::Uuid,
+                "_updated_at" | "_expired_at" => &ColumnKind::Timestamp,
+                _ => collection_data
+                    .schema_fields()
+                    .get(*column)
+                    .ok_or_else(|| {
+                        Error::msg(format!("Field {column} is not found in the collection"))
+                    })?
+                    .kind(),
+            });
+        }
+
+        let table_name = Self::new_table_name(collection_data.id());
+
+        match db {
+            Db::ScyllaDb(db) => {
+                if orders.iter().any(|order| order.near.is_some()) {
+                    return Err(Error::msg(
+                        "Ordering by distance is not supported in ScyllaDB because it has no geospatial functions",
+                    ));
+                }
+                Self::scylladb_select_many_stream(
+                    db,
+                    &table_name,
+                    columns,
+                    &kinds,
+                    collection_data.id(),
+                    created_by,
+                    filters,
+                    pagination,
+                    tx,
+                )
+                .await
+            }
+            Db::PostgresqlDb(db) => {
+                Self::postgresdb_select_many_stream(
+                    db,
+                    &table_name,
+                    columns,
+                    &kinds,
+                    collection_data.id(),
+                    created_by,
+                    filters,
+                    orders,
+                    pagination,
+                    tx,
+                )
+                .await
+            }
+            Db::MysqlDb(db) => {
+                Self::mysqldb_select_many_stream(
+                    db,
+                    &table_name,
+                    columns,
+                    &kinds,
+                    collection_data.id(),
+                    created_by,
+                    filters,
+                    orders,
+                    pagination,
+                    tx,
+                )
+                .await
+            }
+            Db::SqliteDb(db) => {
+                Self::sqlitedb_select_many_stream(
+                    db,
+                    &table_name,
+                    columns,
+                    &kinds,
+                    collection_data.id(),
+                    created_by,
+                    filters,
+                    orders,
+                    pagination,
+                    tx,
+                )
+                .await
+            }
+        }
+    }
+
+    #[instrument(name = "RecordDao::db_update", skip_all)]
     pub async fn db_update(&mut self, db: &Db) -> Result<()> {
         self.data.insert(
             "_updated_at".to_owned(),
@@ -1074,62 +1545,624 @@ impl RecordDao {
             Db::MysqlDb(db) => Self::mysqldb_update(self, db).await,
             Db::SqliteDb(db) => Self::sqlitedb_update(self, db).await,
         }
+        .map_err(|err| DaoError::from_unique_violation(err, &self.table_name))
     }
 
-    pub async fn db_delete(
+    // Consistency of counter increments per backend:
+    // - ScyllaDB: native counter columns in a companion table. Increments are commutative and never
+    //   lost, but they are not idempotent (a retried timeout may apply twice) and reads are eventual
+    // - PostgreSQL and MySQL: a single UPDATE holds the row lock, so concurrent increments serialize
+    // - SQLite: the database-wide write lock serializes increments
+    #[instrument(name = "RecordDao::db_increment_counter", skip_all)]
+    pub async fn db_increment_counter(
         db: &Db,
         collection_id: &Uuid,
         id: &Uuid,
         created_by: &Option<Uuid>,
+        field: &str,
+        delta: &i64,
     ) -> Result<()> {
         match db {
-            Db::ScyllaDb(db) => Self::scylladb_delete(db, collection_id, id, created_by).await,
+            Db::ScyllaDb(db) => {
+                Self::scylladb_increment_counter(db, collection_id, id, created_by, field, delta)
+                    .await
+            }
             Db::PostgresqlDb(db) => {
-                Self::postgresdb_delete(db, collection_id, id, created_by).await
+                Self::postgresdb_increment_counter(db, collection_id, id, created_by, field, delta)
+                    .await
+            }
+            Db::MysqlDb(db) => {
+                Self::mysqldb_increment_counter(db, collection_id, id, created_by, field, delta)
+                    .await
+            }
+            Db::SqliteDb(db) => {
+                Self::sqlitedb_increment_counter(db, collection_id, id, created_by, field, delta)
+                    .await
             }
-            Db::MysqlDb(db) => Self::mysqldb_delete(db, collection_id, id, created_by).await,
-            Db::SqliteDb(db) => Self::sqlitedb_delete(db, collection_id, id, created_by).await,
         }
     }
 
-    async fn db_delete_expired(db: &Db, collection_id: &Uuid, ttl_seconds: &i64) -> Result<()> {
+    #[instrument(name = "RecordDao::db_backfill_column", skip_all)]
+    pub async fn db_backfill_column(
+        db: &Db,
+        collection_id: &Uuid,
+        column: &str,
+        value: &ColumnValue,
+    ) -> Result<()> {
         match db {
-            Db::ScyllaDb(_) => Ok(()),
+            Db::ScyllaDb(db) => {
+                Self::scylladb_backfill_column(db, collection_id, column, value).await
+            }
             Db::PostgresqlDb(db) => {
-                Self::postgresdb_delete_expired(db, collection_id, ttl_seconds).await
+                Self::postgresdb_backfill_column(db, collection_id, column, value).await
+            }
+            Db::MysqlDb(db) => {
+                Self::mysqldb_backfill_column(db, collection_id, column, value).await
+            }
+            Db::SqliteDb(db) => {
+                Self::sqlitedb_backfill_column(db, collection_id, column, value).await
             }
-            Db::MysqlDb(db) => Self::mysqldb_delete_expired(db, collection_id, ttl_seconds).await,
-            Db::SqliteDb(db) => Self::sqlitedb_delete_expired(db, collection_id, ttl_seconds).await,
         }
     }
 
-    async fn scylladb_create_table(
-        db: &ScyllaDb,
-        collection_id: &Uuid,
-        schema_fields: &HashMap<String, SchemaFieldPropsScyllaModel>,
+    #[instrument(name = "RecordDao::db_delete", skip_all)]
+    pub async fn db_delete(
+        db: &Db,
+        collection_data: &CollectionDao,
+        id: &Uuid,
+        created_by: &Option<Uuid>,
     ) -> Result<()> {
-        db.session_query(
-            &scylla_record::create_table(&Self::new_table_name(collection_id), schema_fields),
-            &[],
-        )
-        .await?;
-        Ok(())
-    }
-
-    async fn scylladb_drop_table(db: &ScyllaDb, collection_id: &Uuid) -> Result<()> {
-        db.session_query(
-            &scylla_record::drop_table(&RecordDao::new_table_name(collection_id)),
-            &[],
-        )
-        .await?;
-        Ok(())
+        let collection_id = collection_data.id();
+        if *collection_data.opt_soft_delete() {
+            return match db {
+                Db::ScyllaDb(_) => Err(Error::msg("Soft delete is not supported in ScyllaDB")),
+                Db::PostgresqlDb(db) => {
+                    Self::postgresdb_soft_delete(db, collection_id, id, created_by).await
+                }
+                Db::MysqlDb(db) => {
+                    Self::mysqldb_soft_delete(db, collection_id, id, created_by).await
+                }
+                Db::SqliteDb(db) => {
+                    Self::sqlitedb_soft_delete(db, collection_id, id, created_by).await
+                }
+            };
+        }
+        match db {
+            Db::ScyllaDb(db) => {
+                let has_counters = collection_data
+                    .schema_fields()
+                    .values()
+                    .any(|field_props| *field_props.kind() == ColumnKind::Counter);
+                Self::scylladb_delete(db, collection_id, id, created_by, &has_counters).await
+            }
+            Db::PostgresqlDb(db) => {
+                Self::postgresdb_delete(db, collection_id, id, created_by).await
+            }
+            Db::MysqlDb(db) => Self::mysqldb_delete(db, collection_id, id, created_by).await,
+            Db::SqliteDb(db) => Self::sqlitedb_delete(db, collection_id, id, created_by).await,
+        }
+    }
+
+    // Brings back a soft-deleted record
+    #[instrument(name = "RecordDao::db_restore", skip_all)]
+    pub async fn db_restore(
+        db: &Db,
+        collection_data: &CollectionDao,
+        id: &Uuid,
+        created_by: &Option<Uuid>,
+    ) -> Result<()> {
+        if !*collection_data.opt_soft_delete() {
+            return Err(Error::msg(format!(
+                "Collection '{}' doesn't have soft delete enabled",
+                collection_data.name()
+            )));
+        }
+        let collection_id = collection_data.id();
+        match db {
+            Db::ScyllaDb(_) => Err(Error::msg("Soft delete is not supported in ScyllaDB")),
+            Db::PostgresqlDb(db) => {
+                Self::postgresdb_restore(db, collection_id, id, created_by).await
+            }
+            Db::MysqlDb(db) => Self::mysqldb_restore(db, collection_id, id, created_by).await,
+            Db::SqliteDb(db) => Self::sqlitedb_restore(db, collection_id, id, created_by).await,
+        }
+    }
+
+    // Removes every soft-deleted record of the collection for good
+    #[instrument(name = "RecordDao::db_purge_deleted", skip_all)]
+    pub async fn db_purge_deleted(db: &Db, collection_id: &Uuid) -> Result<()> {
+        match db {
+            Db::ScyllaDb(_) => Ok(()),
+            Db::PostgresqlDb(db) => Self::postgresdb_purge_deleted(db, collection_id).await,
+            Db::MysqlDb(db) => Self::mysqldb_purge_deleted(db, collection_id).await,
+            Db::SqliteDb(db) => Self::sqlitedb_purge_deleted(db, collection_id).await,
+        }
+    }
+
+    // Counts every record of the collection, soft-deleted ones included
+    #[instrument(name = "RecordDao::db_count", skip_all)]
+    pub async fn db_count(db: &Db, collection_id: &Uuid) -> Result<i64> {
+        match db {
+            Db::ScyllaDb(db) => Self::scylladb_count(db, collection_id).await,
+            Db::PostgresqlDb(db) => Self::postgresdb_count(db, collection_id).await,
+            Db::MysqlDb(db) => Self::mysqldb_count(db, collection_id).await,
+            Db::SqliteDb(db) => Self::sqlitedb_count(db, collection_id).await,
+        }
+    }
+
+    #[instrument(name = "RecordDao::db_stats", skip_all)]
+    pub async fn db_stats(db: &Db, collection_id: &Uuid) -> Result<RecordStats> {
+        let (count, size, oldest_id, newest_id, mut indexes) = match db {
+            Db::ScyllaDb(db) => Self::scylladb_stats(db, collection_id).await?,
+            Db::PostgresqlDb(db) => Self::postgresdb_stats(db, collection_id).await?,
+            Db::MysqlDb(db) => Self::mysqldb_stats(db, collection_id).await?,
+            Db::SqliteDb(db) => Self::sqlitedb_stats(db, collection_id).await?,
+        };
+        indexes.sort_unstable();
+        Ok(RecordStats {
+            count,
+            size,
+            oldest_created_at: oldest_id.as_ref().and_then(Self::id_timestamp),
+            newest_created_at: newest_id.as_ref().and_then(Self::id_timestamp),
+            indexes,
+        })
+    }
+
+    // Makes sure every reference field of the record points to a record that exists
+    #[instrument(name = "RecordDao::db_check_references", skip_all)]
+    pub async fn db_check_references(
+        &self,
+        db: &Db,
+        collection_data: &CollectionDao,
+    ) -> Result<()> {
+        let mut fields = HashSet::default();
+        fields.insert("_id");
+        for (field_name, field_props) in collection_data.schema_fields() {
+            let reference_collection_id = match field_props.kind().reference_collection_id() {
+                Some(collection_id) => collection_id,
+                None => continue,
+            };
+            let id = match self.data.get(field_name) {
+                Some(ColumnValue::Uuid(Some(id))) => id,
+                _ => continue,
+            };
+            let reference_collection_data =
+                CollectionDao::db_select(db, reference_collection_id).await?;
+            if Self::db_select(db, id, &None, &fields, &reference_collection_data, &true)
+                .await
+                .is_err()
+            {
+                return Err(Error::msg(format!(
+                    "Field '{field_name}' references record {id} which doesn't exist in collection '{}'",
+                    reference_collection_data.name()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // Applies the 'on_delete' action of every field referencing a record that is about to be
+    // deleted. All affected records are looked up first so a restricting reference anywhere in
+    // the cascade stops the deletion before anything is changed. The record itself is not deleted.
+    #[instrument(name = "RecordDao::db_delete_references", skip_all)]
+    pub async fn db_delete_references(
+        db: &Db,
+        collection_data: &CollectionDao,
+        id: &Uuid,
+    ) -> Result<()> {
+        let collections_data = CollectionDao::db_select_many_by_project_id(
+            db,
+            collection_data.project_id(),
+            &None,
+            &None,
+            &CollectionOrder::CreatedAtDesc,
+            &None,
+        )
+        .await?;
+
+        let mut visited = HashSet::default();
+        visited.insert((*collection_data.id(), *id));
+        let mut queue = vec![(*collection_data.id(), *id)];
+        let mut deletes = Vec::new();
+        let mut set_nulls = Vec::new();
+        while let Some((collection_id, id)) = queue.pop() {
+            for referencing_collection_data in &collections_data {
+                for (field_name, field_props) in
+                    referencing_collection_data.reference_fields(&collection_id)
+                {
+                    let mut fields = HashSet::default();
+                    fields.insert("_id");
+                    fields.insert(field_name.as_str());
+                    let (records_data, _) = Self::db_select_many(
+                        db,
+                        &fields,
+                        referencing_collection_data,
+                        &None,
+                        &RecordFilters::new(&vec![RecordFilter::new(
+                            &Some(field_name.to_owned()),
+                            "=",
+                            &Some(ColumnValue::Uuid(Some(id))),
+                            &None,
+                        )?]),
+                        &Vec::new(),
+                        &Vec::new(),
+                        &RecordPagination::new(&None),
+                        &true,
+                    )
+                    .await?;
+                    for record_data in records_data {
+                        let record_id = match record_data.id() {
+                            Some(record_id) => *record_id,
+                            None => return Err(Error::msg("_id is undefined")),
+                        };
+                        match field_props.on_delete().unwrap_or(ReferenceAction::Restrict) {
+                            ReferenceAction::Restrict => {
+                                return Err(Error::msg(format!(
+                                    "Record {id} is referenced by record {record_id} in field '{field_name}' of collection '{}'",
+                                    referencing_collection_data.name()
+                                )))
+                            }
+                            ReferenceAction::Cascade => {
+                                if visited.insert((*referencing_collection_data.id(), record_id)) {
+                                    queue.push((*referencing_collection_data.id(), record_id));
+                                    deletes.push((*referencing_collection_data.id(), record_id));
+                                }
+                            }
+                            ReferenceAction::SetNull => set_nulls.push((
+                                record_data,
+                                field_name.to_owned(),
+                                *field_props.kind(),
+                            )),
+                        }
+                    }
+                }
+            }
+        }
+
+        for (mut record_data, field_name, kind) in set_nulls {
+            if (*record_data.id())
+                .is_some_and(|id| visited.contains(&(*record_data.collection_id(), id)))
+            {
+                continue;
+            }
+            record_data.upsert(&field_name, &ColumnValue::none(&kind));
+            record_data.db_update(db).await?;
+        }
+        for (collection_id, id) in deletes {
+            let collection_data = collections_data
+                .iter()
+                .find(|collection_data| *collection_data.id() == collection_id)
+                .ok_or_else(|| Error::msg(format!("Collection '{collection_id}' is not found")))?;
+            Self::db_delete(db, collection_data, &id, &None).await?;
+        }
+
+        Ok(())
+    }
+
+    #[instrument(name = "RecordDao::db_prepare_statements", skip_all)]
+    pub async fn db_prepare_statements(db: &Db, collection_data: &CollectionDao) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => Self::scylladb_prepare_statements(db, collection_data).await,
+            // Statements are prepared per connection by the pool on first use
+            Db::PostgresqlDb(_) | Db::MysqlDb(_) | Db::SqliteDb(_) => Ok(()),
+        }
+    }
+
+    // Deletes a batch of at most `limit` records that outlived the collection TTL and, with
+    // per-record TTL enabled, of the ones past their own '_expired_at'. Reads already leave these
+    // records out, so this only reclaims their space and runs from the background sweeper.
+    #[instrument(name = "RecordDao::db_delete_expired", skip_all)]
+    pub async fn db_delete_expired(
+        db: &Db,
+        collection_data: &CollectionDao,
+        limit: &i64,
+    ) -> Result<u64> {
+        let mut deleted = 0;
+        if let Some(ttl_seconds) = collection_data.opt_ttl() {
+            deleted += match db {
+                // ScyllaDB expires the records by itself through the default TTL of the table
+                Db::ScyllaDb(_) => 0,
+                Db::PostgresqlDb(db) => {
+                    Self::postgresdb_delete_expired(db, collection_data.id(), ttl_seconds, limit)
+                        .await?
+                }
+                Db::MysqlDb(db) => {
+                    Self::mysqldb_delete_expired(db, collection_data.id(), ttl_seconds, limit)
+                        .await?
+                }
+                Db::SqliteDb(db) => {
+                    Self::sqlitedb_delete_expired(db, collection_data.id(), ttl_seconds, limit)
+                        .await?
+                }
+            };
+        }
+        if *collection_data.opt_per_record_ttl() {
+            deleted += match db {
+                Db::ScyllaDb(_) => 0,
+                Db::PostgresqlDb(db) => {
+                    Self::postgresdb_delete_expired_by_record(db, collection_data.id(), limit)
+                        .await?
+                }
+                Db::MysqlDb(db) => {
+                    Self::mysqldb_delete_expired_by_record(db, collection_data.id(), limit).await?
+                }
+                Db::SqliteDb(db) => {
+                    Self::sqlitedb_delete_expired_by_record(db, collection_data.id(), limit).await?
+                }
+            };
+        }
+        Ok(deleted)
+    }
+
+    // Removes every record whose own '_expired_at' has passed
+    #[instrument(name = "RecordDao::db_delete_expired_by_record", skip_all)]
+    pub async fn db_delete_expired_by_record(db: &Db, collection_id: &Uuid) -> Result<()> {
+        loop {
+            let deleted = match db {
+                Db::ScyllaDb(_) => 0,
+                Db::PostgresqlDb(db) => {
+                    Self::postgresdb_delete_expired_by_record(
+                        db,
+                        collection_id,
+                        &DELETE_EXPIRED_BATCH_SIZE,
+                    )
+                    .await?
+                }
+                Db::MysqlDb(db) => {
+                    Self::mysqldb_delete_expired_by_record(
+                        db,
+                        collection_id,
+                        &DELETE_EXPIRED_BATCH_SIZE,
+                    )
+                    .await?
+                }
+                Db::SqliteDb(db) => {
+                    Self::sqlitedb_delete_expired_by_record(
+                        db,
+                        collection_id,
+                        &DELETE_EXPIRED_BATCH_SIZE,
+                    )
+                    .await?
+                }
+            };
+            if deleted < DELETE_EXPIRED_BATCH_SIZE as u64 {
+                return Ok(());
+            }
+        }
+    }
+
+    // Streams the records out of the source collection and inserts them in batches, each one under
+    // a new id and update time
+    async fn db_copy_records_with_new_ids(
+        db: &Db,
+        columns: &Vec<&str>,
+        source_collection: &CollectionDao,
+        target_collection: &CollectionDao,
+    ) -> Result<()> {
+        let (tx, mut rx) = mpsc::channel(COPY_RECORDS_CHANNEL_CAPACITY);
+        let batch_size = Self::insert_many_limit(&columns.len());
+        let target_collection_data = Some(target_collection.clone());
+
+        let select_fut = async move {
+            Self::select_many_send(
+                db,
+                columns,
+                source_collection,
+                &None,
+                &RecordFilters::new(&Vec::new()),
+                &Vec::new(),
+                &RecordPagination::new(&None),
+                &tx,
+            )
+            .await
+        };
+        let insert_fut = async {
+            let mut records = Vec::with_capacity(batch_size);
+            while let Some(record) = rx.recv().await {
+                let created_by = record
+                    .created_by()
+                    .ok_or_else(|| Error::msg("Record doesn't have a '_created_by' value"))?;
+                let mut record_data =
+                    Self::new(&created_by, target_collection.id(), &record.data.len());
+                for (column, value) in record.data {
+                    if !column.starts_with('_') || column == "_expired_at" {
+                        record_data.data.insert(column, value);
+                    }
+                }
+                records.push(record_data);
+                if records.len() == batch_size {
+                    Self::db_insert_many(db, &records, &target_collection_data).await?;
+                    records.clear();
+                }
+            }
+            Self::db_insert_many(db, &records, &target_collection_data).await
+        };
+
+        tokio::try_join!(select_fut, insert_fut)?;
+        Ok(())
+    }
+
+    // Leaves soft-deleted records out when the collection has soft delete enabled, and expired
+    // records out until the background sweeper deletes them
+    fn visible_filters(
+        db: &Db,
+        collection_data: &CollectionDao,
+        filters: &RecordFilters,
+    ) -> Result<RecordFilters> {
+        let mut children = filters.0.clone();
+        if *collection_data.opt_soft_delete() {
+            children.push(RecordFilter::new(
+                &Some("_deleted_at".to_owned()),
+                "IS NULL",
+                &None,
+                &None,
+            )?);
+        }
+        // ScyllaDB expires the records by itself through the default TTL of the table
+        if !matches!(db, Db::ScyllaDb(_)) {
+            let now = Utc::now();
+            if let Some(ttl_seconds) = collection_data.opt_ttl() {
+                children.push(RecordFilter::new(
+                    &Some("_updated_at".to_owned()),
+                    ">=",
+                    &Some(ColumnValue::Timestamp(Some(Self::ttl_cutoff(
+                        &now,
+                        ttl_seconds,
+                    )?))),
+                    &None,
+                )?);
+            }
+            if *collection_data.opt_per_record_ttl() {
+                children.push(RecordFilter::new(
+                    &None,
+                    "OR",
+                    &None,
+                    &Some(RecordFilters(vec![
+                        RecordFilter::new(
+                            &Some("_expired_at".to_owned()),
+                            "IS NULL",
+                            &None,
+                            &None,
+                        )?,
+                        RecordFilter::new(
+                            &Some("_expired_at".to_owned()),
+                            ">=",
+                            &Some(ColumnValue::Timestamp(Some(now))),
+                            &None,
+                        )?,
+                    ])),
+                )?);
+            }
+        }
+        if children.len() == filters.0.len() {
+            return Ok(filters.clone());
+        }
+        Ok(RecordFilters(vec![RecordFilter::new(
+            &None,
+            "AND",
+            &None,
+            &Some(RecordFilters(children)),
+        )?]))
+    }
+
+    // Cut-off times of the conditions leaving out expired records, in the order they're bound: the
+    // oldest update time kept by the collection TTL, then the current time for '_expired_at'
+    fn expiry_cutoffs(collection_data: &CollectionDao) -> Result<Vec<DateTime<Utc>>> {
+        let now = Utc::now();
+        let mut cutoffs = Vec::with_capacity(2);
+        if let Some(ttl_seconds) = collection_data.opt_ttl() {
+            cutoffs.push(Self::ttl_cutoff(&now, ttl_seconds)?);
+        }
+        if *collection_data.opt_per_record_ttl() {
+            cutoffs.push(now);
+        }
+        Ok(cutoffs)
+    }
+
+    fn ttl_cutoff(now: &DateTime<Utc>, ttl_seconds: &i64) -> Result<DateTime<Utc>> {
+        now.checked_sub_signed(
+            Duration::try_seconds(*ttl_seconds)
+                .ok_or_else(|| Error::msg("collection ttl is out of range."))?,
+        )
+        .ok_or_else(|| Error::msg("collection ttl is out of range."))
+    }
+
+    fn scylladb_select_columns(collection_data: &CollectionDao) -> Vec<&str> {
+        let mut columns = Vec::with_capacity(collection_data.schema_fields().len() + 3);
+        for column in ["_id", "_created_by", "_updated_at"] {
+            columns.push(column);
+        }
+        let mut fields = collection_data
+            .schema_fields()
+            .keys()
+            .map(|field| field.as_str())
+            .collect::<Vec<_>>();
+        fields.sort_unstable();
+        columns.append(&mut fields);
+        columns
+    }
+
+    // Prepares the statements of the shapes used by selecting, inserting and updating a whole
+    // record, which are the ones most requests end up executing
+    async fn scylladb_prepare_statements(
+        db: &ScyllaDb,
+        collection_data: &CollectionDao,
+    ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_data.id());
+
+        let mut insert_columns = collection_data
+            .schema_fields()
+            .keys()
+            .map(|field| field.as_str())
+            .collect::<Vec<_>>();
+        insert_columns.extend(["_collection_id", "_created_by", "_id", "_updated_at"]);
+        insert_columns.sort_unstable();
+
+        // Counters are left out of updates
+        let mut update_columns = collection_data
+            .schema_fields()
+            .iter()
+            .filter(|(_, field_props)| *field_props.kind() != ColumnKind::Counter)
+            .map(|(field, _)| field.as_str())
+            .collect::<Vec<_>>();
+        update_columns.push("_created_by");
+        update_columns.sort_unstable();
+        update_columns.push("_updated_at");
+
+        let query_select =
+            scylla_record::select(&table_name, &Self::scylladb_select_columns(collection_data));
+        let query_insert = scylla_record::insert(&table_name, &insert_columns);
+        let query_update = scylla_record::update(&table_name, &update_columns);
+        tokio::try_join!(
+            db.prepare(&query_select),
+            db.prepare(&query_insert),
+            db.prepare(&query_update),
+        )?;
+
+        Ok(())
+    }
+
+    async fn scylladb_create_table(
+        db: &ScyllaDb,
+        collection_id: &Uuid,
+        schema_fields: &HashMap<String, SchemaFieldPropsScyllaModel>,
+    ) -> Result<()> {
+        db.session_query(
+            &scylla_record::create_table(&Self::new_table_name(collection_id), schema_fields),
+            &[],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn scylladb_drop_table(db: &ScyllaDb, collection_id: &Uuid) -> Result<()> {
+        db.session_query(
+            &scylla_record::drop_table(&RecordDao::new_table_name(collection_id)),
+            &[],
+        )
+        .await?;
+        db.session_query(
+            &scylla_record::drop_counter_table(&RecordDao::new_table_name(collection_id)),
+            &[],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn scylladb_create_counter_table(db: &ScyllaDb, collection_id: &Uuid) -> Result<()> {
+        db.session_query(
+            &scylla_record::create_counter_table(&Self::new_table_name(collection_id)),
+            &[],
+        )
+        .await?;
+        Ok(())
     }
 
     async fn scylladb_check_table_existence(db: &ScyllaDb, collection_id: &Uuid) -> Result<bool> {
         Ok(db
             .session_query(
                 SCYLLA_COUNT_TABLE,
-                [&RecordDao::new_table_name(collection_id)].as_ref(),
+                (db.keyspace(), &RecordDao::new_table_name(collection_id)),
             )
             .await?
             .first_row_typed::<(i64,)>()?
@@ -1220,9 +2253,10 @@ impl RecordDao {
                             .first_row_typed::<(i64,)>()?
                             .0;
                         if count > 0 {
-                            return Err(Error::msg(format!(
-                                "Duplicate value in field '{field}' violates unique constraint"
-                            )));
+                            return Err(DaoError::Conflict(format!(
+                                "Record violates unique index '{field}'"
+                            ))
+                            .into());
                         }
                     }
                 }
@@ -1234,9 +2268,12 @@ impl RecordDao {
             "_collection_id".to_owned(),
             ColumnValue::Uuid(Some(self.collection_id)),
         );
+        // Columns are sorted so the same set of columns always maps to the same prepared statement
+        let mut data = data.iter().collect::<Vec<_>>();
+        data.sort_unstable_by_key(|(col, _)| *col);
         let mut columns = Vec::with_capacity(data.len());
         let mut values = Vec::with_capacity(data.len());
-        for (col, val) in &data {
+        for (col, val) in data {
             columns.push(col.as_str());
             values.push(val.to_scylladb_model()?);
         }
@@ -1245,6 +2282,21 @@ impl RecordDao {
         Ok(())
     }
 
+    async fn scylladb_insert_many(
+        db: &ScyllaDb,
+        records: &[Self],
+        collection_data: &Option<CollectionDao>,
+    ) -> Result<()> {
+        // ScyllaDB has no multi-row insert, so the records are written concurrently instead
+        future::try_join_all(
+            records
+                .iter()
+                .map(|record| record.scylladb_insert(db, collection_data)),
+        )
+        .await?;
+        Ok(())
+    }
+
     async fn scylladb_select(
         db: &ScyllaDb,
         table_name: &str,
@@ -1327,11 +2379,97 @@ impl RecordDao {
         ))
     }
 
+    async fn scylladb_select_many_stream(
+        db: &ScyllaDb,
+        table_name: &str,
+        columns: &Vec<&str>,
+        kinds: &Vec<&ColumnKind>,
+        collection_id: &Uuid,
+        created_by: &Option<Uuid>,
+        filters: &RecordFilters,
+        pagination: &RecordPagination,
+        tx: &mpsc::Sender<Self>,
+    ) -> Result<()> {
+        let mut filter = filters.scylladb_filter_query(&None, 0)?;
+        if created_by.is_some() {
+            if filter.len() > 0 {
+                filter = format!("\"_created_by\" = ? AND ({filter})");
+            } else {
+                filter = format!("\"_created_by\" = ?");
+            }
+        }
+
+        let mut values =
+            Vec::<Box<dyn SerializeCql + Send + Sync>>::with_capacity(filters.0.len() + 2);
+        if let Some(created_by) = created_by {
+            values.push(Box::new(*created_by));
+        }
+        values.append(&mut filters.scylladb_values()?);
+        if let Some(limit) = pagination.limit() {
+            values.push(Box::new(*limit));
+        }
+
+        let query_select_many = scylla_record::select_many(
+            table_name,
+            columns,
+            &filter,
+            &Vec::new(),
+            &pagination.limit().is_some(),
+        );
+
+        // Rows are fetched page by page, and counters are merged for a chunk of records at a time
+        let mut rows = db.execute_iter(&query_select_many, &values).await?;
+        let mut records = Vec::with_capacity(SCYLLADB_STREAM_CHUNK_SIZE);
+        while let Some(row) = rows.try_next().await? {
+            let mut data = HashMap::with_capacity(columns.len());
+            for (idx, value) in row.columns.iter().enumerate() {
+                data.insert(
+                    columns[idx].to_owned(),
+                    match value {
+                        Some(value) => ColumnValue::from_scylladb_model(kinds[idx], value)?,
+                        None => ColumnValue::none(kinds[idx]),
+                    },
+                );
+            }
+            records.push(Self {
+                table_name: table_name.to_owned(),
+                collection_id: *collection_id,
+                data,
+            });
+            if records.len() == SCYLLADB_STREAM_CHUNK_SIZE
+                && !Self::scylladb_send_many(db, table_name, &mut records, tx).await?
+            {
+                return Ok(());
+            }
+        }
+        Self::scylladb_send_many(db, table_name, &mut records, tx).await?;
+
+        Ok(())
+    }
+
+    // Returns false once the receiver is gone
+    async fn scylladb_send_many(
+        db: &ScyllaDb,
+        table_name: &str,
+        records: &mut Vec<Self>,
+        tx: &mpsc::Sender<Self>,
+    ) -> Result<bool> {
+        Self::scylladb_merge_counters(db, table_name, records).await?;
+        for record in records.drain(..) {
+            if tx.send(record).await.is_err() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     async fn scylladb_update(&self, db: &ScyllaDb) -> Result<()> {
+        let mut data = self.data.iter().collect::<Vec<_>>();
+        data.sort_unstable_by_key(|(col, _)| *col);
         let mut columns = Vec::with_capacity(self.data.len());
         let mut values = Vec::with_capacity(self.data.len());
-        for (col, val) in &self.data {
-            if col != "_id" && col != "_updated_at" {
+        for (col, val) in data {
+            if col != "_id" && col != "_updated_at" && !matches!(val, ColumnValue::Counter(_)) {
                 columns.push(col.as_str());
                 values.push(val.to_scylladb_model()?);
             }
@@ -1353,29 +2491,190 @@ impl RecordDao {
         Ok(())
     }
 
+    async fn scylladb_increment_counter(
+        db: &ScyllaDb,
+        collection_id: &Uuid,
+        id: &Uuid,
+        created_by: &Option<Uuid>,
+        field: &str,
+        delta: &i64,
+    ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_id);
+        // Counter updates are upserts in Scylla, so make sure the record exists first
+        Self::scylladb_select(db, &table_name, &vec!["_id"], collection_id, id, created_by)
+            .await
+            .map_err(|err| DaoError::from_no_rows(err, format!("Record '{id}' is not found")))?;
+        db.execute(
+            &scylla_record::increment_counter(&table_name),
+            (ScyllaCounter(*delta), id, field),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn scylladb_merge_counters(
+        db: &ScyllaDb,
+        table_name: &str,
+        records: &mut [Self],
+    ) -> Result<()> {
+        let ids = records
+            .iter()
+            .filter(|record| {
+                record
+                    .data
+                    .values()
+                    .any(|value| matches!(value, ColumnValue::Counter(_)))
+            })
+            .filter_map(|record| *record.id())
+            .collect::<Vec<_>>();
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut counters = HashMap::new();
+        for row in db
+            .execute(&scylla_record::select_counters(table_name), (ids,))
+            .await?
+            .rows_typed::<(Uuid, String, ScyllaCounter)>()?
+        {
+            let (id, field, value) = row?;
+            counters.insert((id, field), value.0);
+        }
+
+        for record in records.iter_mut() {
+            if let Some(id) = *record.id() {
+                for (field, value) in record.data.iter_mut() {
+                    if let ColumnValue::Counter(value) = value {
+                        *value = counters.get(&(id, field.to_owned())).copied();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn scylladb_backfill_column(
+        db: &ScyllaDb,
+        collection_id: &Uuid,
+        column: &str,
+        value: &ColumnValue,
+    ) -> Result<()> {
+        // Scylla can only update rows by their full primary key, so every record is updated in turn
+        let table_name = Self::new_table_name(collection_id);
+        let query_update = scylla_record::update(&table_name, &vec![column]);
+        for row in db
+            .execute(
+                &scylla_record::select_ids(&table_name),
+                [collection_id].as_ref(),
+            )
+            .await?
+            .rows_typed::<(Uuid,)>()?
+        {
+            let (id,) = row?;
+            let values: Vec<Box<dyn SerializeCql + Send + Sync>> = vec![
+                value.to_scylladb_model()?,
+                Box::new(*collection_id),
+                Box::new(id),
+            ];
+            db.execute(&query_update, &values).await?;
+        }
+        Ok(())
+    }
+
+    async fn scylladb_count(db: &ScyllaDb, collection_id: &Uuid) -> Result<i64> {
+        Ok(db
+            .execute(
+                &scylla_record::count(&Self::new_table_name(collection_id), "", &Vec::new()),
+                &[],
+            )
+            .await?
+            .first_row_typed::<(i64,)>()?
+            .0)
+    }
+
+    async fn scylladb_stats(
+        db: &ScyllaDb,
+        collection_id: &Uuid,
+    ) -> Result<(i64, Option<i64>, Option<Uuid>, Option<Uuid>, Vec<String>)> {
+        let table_name = Self::new_table_name(collection_id);
+        let query_count = scylla_record::count(&table_name, "", &Vec::new());
+        let query_oldest_id = scylla_record::select_oldest_id(&table_name);
+        let query_newest_id = scylla_record::select_newest_id(&table_name);
+        let query_size_estimates = scylla_record::select_size_estimates(db.keyspace(), &table_name);
+        let query_indexes = scylla_record::select_indexes(db.keyspace(), &table_name);
+        let oldest_id_values = (collection_id,);
+
+        let (count, oldest_id, newest_id, size_estimates, indexes) = tokio::try_join!(
+            db.execute(&query_count, &[]),
+            db.execute(&query_oldest_id, &oldest_id_values),
+            db.execute(&query_newest_id, &[]),
+            db.session_query(&query_size_estimates, &[]),
+            db.session_query(&query_indexes, &[])
+        )?;
+
+        let mut size = None;
+        for row in size_estimates.rows_typed::<(i64, i64)>()? {
+            let (mean_partition_size, partitions_count) = row?;
+            size = Some(size.unwrap_or(0) + mean_partition_size * partitions_count);
+        }
+
+        Ok((
+            count.first_row_typed::<(i64,)>()?.0,
+            size,
+            oldest_id
+                .maybe_first_row_typed::<(Uuid,)>()?
+                .map(|(id,)| id),
+            newest_id
+                .maybe_first_row_typed::<(Uuid,)>()?
+                .map(|(id,)| id),
+            indexes
+                .rows_typed::<(String,)>()?
+                .map(|row| row.map(|(index,)| index))
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+
     async fn scylladb_delete(
         db: &ScyllaDb,
         collection_id: &Uuid,
         id: &Uuid,
         created_by: &Option<Uuid>,
+        has_counters: &bool,
     ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_id);
         let mut column = Vec::with_capacity(3);
         column.push("_collection_id");
         column.push("_id");
         if let Some(created_by) = created_by {
             column.push("_created_by");
             db.execute(
-                &scylla_record::delete(&Self::new_table_name(collection_id), &column),
+                &scylla_record::delete(&table_name, &column),
                 [collection_id, id, created_by].as_ref(),
             )
             .await?;
         } else {
             db.execute(
-                &scylla_record::delete(&Self::new_table_name(collection_id), &column),
+                &scylla_record::delete(&table_name, &column),
                 [collection_id, id].as_ref(),
             )
             .await?;
         }
+
+        // Counter fields live in a table of their own, which has to be cleared separately. That's
+        // only done once the record is gone, so a delete that didn't match its owner leaves them.
+        if *has_counters
+            && db
+                .execute(
+                    &scylla_record::select(&table_name, &vec!["_id"]),
+                    [collection_id, id].as_ref(),
+                )
+                .await?
+                .maybe_first_row_typed::<(Uuid,)>()?
+                .is_none()
+        {
+            db.execute(&scylla_record::delete_counters(&table_name), [id].as_ref())
+                .await?;
+        }
         Ok(())
     }
 
@@ -1440,6 +2739,23 @@ impl RecordDao {
         Ok(())
     }
 
+    async fn postgresdb_rename_columns(
+        db: &PostgresDb,
+        collection_id: &Uuid,
+        columns: &HashMap<String, String>,
+    ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_id);
+        for (column, new_column) in columns {
+            db.execute_unprepared(sqlx::query(&postgres_record::rename_column(
+                &table_name,
+                column,
+                new_column,
+            )))
+            .await?;
+        }
+        Ok(())
+    }
+
     async fn postgresdb_change_columns_type(
         db: &PostgresDb,
         collection_id: &Uuid,
@@ -1469,11 +2785,11 @@ impl RecordDao {
     async fn postgresdb_create_unique_index(
         db: &PostgresDb,
         collection_id: &Uuid,
-        index: &str,
+        columns: &Vec<&str>,
     ) -> Result<()> {
         db.execute_unprepared(sqlx::query(&postgres_record::create_unique_index(
             &Self::new_table_name(collection_id),
-            index,
+            columns,
         )))
         .await?;
         Ok(())
@@ -1495,11 +2811,11 @@ impl RecordDao {
     async fn postgresdb_drop_unique_index(
         db: &PostgresDb,
         collection_id: &Uuid,
-        index: &str,
+        columns: &Vec<&str>,
     ) -> Result<()> {
         db.execute_unprepared(sqlx::query(&postgres_record::drop_unique_index(
             &Self::new_table_name(collection_id),
-            index,
+            columns,
         )))
         .await?;
         Ok(())
@@ -1521,25 +2837,82 @@ impl RecordDao {
         Ok(())
     }
 
+    async fn postgresdb_insert_many(
+        db: &PostgresDb,
+        columns: &Vec<&str>,
+        records: &[Self],
+    ) -> Result<()> {
+        let query = postgres_record::insert_many(&records[0].table_name, columns, &records.len());
+        let mut query = sqlx::query(&query);
+        for record in records {
+            for col in columns {
+                query = record.data[*col].to_postgresdb_model(query)?;
+            }
+        }
+        db.execute(query).await?;
+        Ok(())
+    }
+
+    async fn postgresdb_copy_records(
+        db: &PostgresDb,
+        columns: &Vec<&str>,
+        source_collection: &CollectionDao,
+        target_collection: &CollectionDao,
+    ) -> Result<()> {
+        let target_table = Self::new_table_name(target_collection.id());
+        let source_table = Self::new_table_name(source_collection.id());
+        let query = postgres_record::copy_visible(
+            &target_table,
+            &source_table,
+            columns,
+            source_collection.opt_soft_delete(),
+            &source_collection.opt_ttl().is_some(),
+            source_collection.opt_per_record_ttl(),
+        );
+        let mut query = sqlx::query(&query);
+        for cutoff in Self::expiry_cutoffs(source_collection)? {
+            query = query.bind(cutoff);
+        }
+        db.execute(query).await?;
+        Ok(())
+    }
+
     async fn postgresdb_select(
         db: &PostgresDb,
         table_name: &str,
         columns: &Vec<&str>,
         id: &Uuid,
         created_by: &Option<Uuid>,
+        collection_data: &CollectionDao,
     ) -> Result<sqlx::postgres::PgRow> {
+        let ttl = collection_data.opt_ttl().is_some();
+        let cutoffs = Self::expiry_cutoffs(collection_data)?;
         Ok(if let Some(created_by) = created_by {
-            db.fetch_one_row(
-                sqlx::query(&postgres_record::select_by_id_and_created_by(
-                    table_name, columns,
-                ))
-                .bind(id)
-                .bind(created_by),
-            )
-            .await?
+            let mut query = if *collection_data.opt_soft_delete() {
+                postgres_record::select_not_deleted_by_id_and_created_by(table_name, columns)
+            } else {
+                postgres_record::select_by_id_and_created_by(table_name, columns)
+            };
+            query +=
+                &postgres_record::and_not_expired(&ttl, collection_data.opt_per_record_ttl(), &3);
+            let mut query = sqlx::query(&query).bind(id).bind(created_by);
+            for cutoff in cutoffs {
+                query = query.bind(cutoff);
+            }
+            db.fetch_one_row(query).await?
         } else {
-            db.fetch_one_row(sqlx::query(&postgres_record::select(table_name, columns)).bind(id))
-                .await?
+            let mut query = if *collection_data.opt_soft_delete() {
+                postgres_record::select_not_deleted(table_name, columns)
+            } else {
+                postgres_record::select(table_name, columns)
+            };
+            query +=
+                &postgres_record::and_not_expired(&ttl, collection_data.opt_per_record_ttl(), &2);
+            let mut query = sqlx::query(&query).bind(id);
+            for cutoff in cutoffs {
+                query = query.bind(cutoff);
+            }
+            db.fetch_one_row(query).await?
         })
     }
 
@@ -1553,6 +2926,94 @@ impl RecordDao {
         orders: &Vec<RecordOrder>,
         pagination: &RecordPagination,
     ) -> Result<(Vec<sqlx::postgres::PgRow>, i64)> {
+        let (query_select_many, query_total) = Self::postgresdb_select_many_query(
+            table_name, columns, created_by, filters, groups, orders, pagination,
+        )?;
+
+        let mut query_select_many = sqlx::query(&query_select_many);
+        let mut query_total = sqlx::query_as(&query_total);
+
+        query_select_many = filters.postgresdb_values(query_select_many)?;
+        query_total = filters.postgresdb_values_as(query_total)?;
+        if let Some(created_by) = created_by {
+            query_select_many = query_select_many.bind(created_by);
+            query_total = query_total.bind(created_by);
+        }
+        if let Some(limit) = pagination.limit() {
+            query_select_many = query_select_many.bind(limit);
+        }
+
+        let (rows, total) = tokio::try_join!(
+            db.fetch_all_rows(query_select_many),
+            db.fetch_one::<(i64,)>(query_total)
+        )?;
+
+        Ok((rows, total.0))
+    }
+
+    async fn postgresdb_select_many_stream(
+        db: &PostgresDb,
+        table_name: &str,
+        columns: &Vec<&str>,
+        kinds: &Vec<&ColumnKind>,
+        collection_id: &Uuid,
+        created_by: &Option<Uuid>,
+        filters: &RecordFilters,
+        orders: &Vec<RecordOrder>,
+        pagination: &RecordPagination,
+        tx: &mpsc::Sender<Self>,
+    ) -> Result<()> {
+        let (query_select_many, _) = Self::postgresdb_select_many_query(
+            table_name,
+            columns,
+            created_by,
+            filters,
+            &Vec::new(),
+            orders,
+            pagination,
+        )?;
+
+        let mut query_select_many = sqlx::query(&query_select_many);
+
+        query_select_many = filters.postgresdb_values(query_select_many)?;
+        if let Some(created_by) = created_by {
+            query_select_many = query_select_many.bind(created_by);
+        }
+        if let Some(limit) = pagination.limit() {
+            query_select_many = query_select_many.bind(limit);
+        }
+
+        let mut rows = db.fetch_rows(query_select_many);
+        while let Some(row) = rows.try_next().await? {
+            let mut data = HashMap::with_capacity(columns.len());
+            for (column, kind) in columns.iter().zip(kinds) {
+                data.insert(
+                    (*column).to_owned(),
+                    ColumnValue::from_postgresdb_model(kind, column, &row)?,
+                );
+            }
+            let record = Self {
+                table_name: table_name.to_owned(),
+                collection_id: *collection_id,
+                data,
+            };
+            if tx.send(record).await.is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn postgresdb_select_many_query(
+        table_name: &str,
+        columns: &Vec<&str>,
+        created_by: &Option<Uuid>,
+        filters: &RecordFilters,
+        groups: &Vec<&str>,
+        orders: &Vec<RecordOrder>,
+        pagination: &RecordPagination,
+    ) -> Result<(String, String)> {
         let mut argument_idx = 1;
         let mut filter = filters.postgresdb_filter_query(&None, 0, &mut argument_idx)?;
         if created_by.is_some() {
@@ -1567,7 +3028,14 @@ impl RecordDao {
         let mut order = Vec::with_capacity(orders.len());
         for o in orders {
             if POSTGRES_ORDER_TYPE.contains(&o.kind.to_uppercase().as_str()) {
-                order.push((o.field.as_str(), o.kind.as_str()));
+                if let Some((lat, lng)) = o.near {
+                    order.push((
+                        postgres_record::distance(&o.field, &lat.to_string(), &lng.to_string()),
+                        o.kind.as_str(),
+                    ));
+                } else {
+                    order.push((format!("\"{}\"", o.field), o.kind.as_str()));
+                }
             } else {
                 return Err(Error::msg(format!(
                     "Order type '{}' is not supported",
@@ -1587,32 +3055,14 @@ impl RecordDao {
         );
         let query_total = postgres_record::count(table_name, &filter, groups);
 
-        let mut query_select_many = sqlx::query(&query_select_many);
-        let mut query_total = sqlx::query_as(&query_total);
-
-        query_select_many = filters.postgresdb_values(query_select_many)?;
-        query_total = filters.postgresdb_values_as(query_total)?;
-        if let Some(created_by) = created_by {
-            query_select_many = query_select_many.bind(created_by);
-            query_total = query_total.bind(created_by);
-        }
-        if let Some(limit) = pagination.limit() {
-            query_select_many = query_select_many.bind(limit);
-        }
-
-        let (rows, total) = tokio::try_join!(
-            db.fetch_all_rows(query_select_many),
-            db.fetch_one::<(i64,)>(query_total)
-        )?;
-
-        Ok((rows, total.0))
+        Ok((query_select_many, query_total))
     }
 
     async fn postgresdb_update(&self, db: &PostgresDb) -> Result<()> {
         let mut columns = Vec::with_capacity(self.data.len());
         let mut values = Vec::with_capacity(self.data.len());
         for (col, val) in &self.data {
-            if col != "_id" && col != "_updated_at" {
+            if col != "_id" && col != "_updated_at" && !matches!(val, ColumnValue::Counter(_)) {
                 columns.push(col.as_str());
                 values.push(val);
             }
@@ -1633,62 +3083,228 @@ impl RecordDao {
         for val in values {
             query = val.to_postgresdb_model(query)?;
         }
-        db.execute(query).await?;
+        db.execute(query).await?;
+        Ok(())
+    }
+
+    async fn postgresdb_increment_counter(
+        db: &PostgresDb,
+        collection_id: &Uuid,
+        id: &Uuid,
+        created_by: &Option<Uuid>,
+        field: &str,
+        delta: &i64,
+    ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_id);
+        let result = if let Some(created_by) = created_by {
+            db.execute(
+                sqlx::query(&postgres_record::increment_counter_by_created_by(
+                    &table_name,
+                    field,
+                ))
+                .bind(delta)
+                .bind(Utc::now())
+                .bind(id)
+                .bind(created_by),
+            )
+            .await?
+        } else {
+            db.execute(
+                sqlx::query(&postgres_record::increment_counter(&table_name, field))
+                    .bind(delta)
+                    .bind(Utc::now())
+                    .bind(id),
+            )
+            .await?
+        };
+        if result.rows_affected() == 0 {
+            return Err(DaoError::NotFound(format!("Record '{id}' is not found")).into());
+        }
+        Ok(())
+    }
+
+    async fn postgresdb_backfill_column(
+        db: &PostgresDb,
+        collection_id: &Uuid,
+        column: &str,
+        value: &ColumnValue,
+    ) -> Result<()> {
+        db.execute(
+            value.to_postgresdb_model(sqlx::query(&postgres_record::backfill_column(
+                &Self::new_table_name(collection_id),
+                column,
+            )))?,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn postgresdb_delete(
+        db: &PostgresDb,
+        collection_id: &Uuid,
+        id: &Uuid,
+        created_by: &Option<Uuid>,
+    ) -> Result<()> {
+        let mut column = Vec::with_capacity(2);
+        column.push("_id");
+        if let Some(created_by) = created_by {
+            column.push("_created_by");
+            db.execute(
+                sqlx::query(&postgres_record::delete(
+                    &Self::new_table_name(collection_id),
+                    &column,
+                ))
+                .bind(id)
+                .bind(created_by),
+            )
+            .await?;
+        } else {
+            db.execute(
+                sqlx::query(&postgres_record::delete(
+                    &Self::new_table_name(collection_id),
+                    &column,
+                ))
+                .bind(id),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn postgresdb_soft_delete(
+        db: &PostgresDb,
+        collection_id: &Uuid,
+        id: &Uuid,
+        created_by: &Option<Uuid>,
+    ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_id);
+        let now = Utc::now();
+        if let Some(created_by) = created_by {
+            db.execute(
+                sqlx::query(&postgres_record::soft_delete_by_created_by(&table_name))
+                    .bind(now)
+                    .bind(now)
+                    .bind(id)
+                    .bind(created_by),
+            )
+            .await?;
+        } else {
+            db.execute(
+                sqlx::query(&postgres_record::soft_delete(&table_name))
+                    .bind(now)
+                    .bind(now)
+                    .bind(id),
+            )
+            .await?;
+        }
         Ok(())
     }
 
-    async fn postgresdb_delete(
+    async fn postgresdb_restore(
         db: &PostgresDb,
         collection_id: &Uuid,
         id: &Uuid,
         created_by: &Option<Uuid>,
     ) -> Result<()> {
-        let mut column = Vec::with_capacity(2);
-        column.push("_id");
-        if let Some(created_by) = created_by {
-            column.push("_created_by");
+        let table_name = Self::new_table_name(collection_id);
+        let result = if let Some(created_by) = created_by {
             db.execute(
-                sqlx::query(&postgres_record::delete(
-                    &Self::new_table_name(collection_id),
-                    &column,
-                ))
-                .bind(id)
-                .bind(created_by),
+                sqlx::query(&postgres_record::restore_by_created_by(&table_name))
+                    .bind(Utc::now())
+                    .bind(id)
+                    .bind(created_by),
             )
-            .await?;
+            .await?
         } else {
             db.execute(
-                sqlx::query(&postgres_record::delete(
-                    &Self::new_table_name(collection_id),
-                    &column,
-                ))
-                .bind(id),
+                sqlx::query(&postgres_record::restore(&table_name))
+                    .bind(Utc::now())
+                    .bind(id),
             )
-            .await?;
+            .await?
+        };
+        if result.rows_affected() == 0 {
+            return Err(DaoError::NotFound(format!("Deleted record '{id}' is not found")).into());
         }
         Ok(())
     }
 
+    async fn postgresdb_purge_deleted(db: &PostgresDb, collection_id: &Uuid) -> Result<()> {
+        db.execute(sqlx::query(&postgres_record::purge_deleted(
+            &Self::new_table_name(collection_id),
+        )))
+        .await?;
+        Ok(())
+    }
+
+    async fn postgresdb_count(db: &PostgresDb, collection_id: &Uuid) -> Result<i64> {
+        Ok(db
+            .fetch_one::<(i64,)>(sqlx::query_as(&postgres_record::count(
+                &Self::new_table_name(collection_id),
+                "",
+                &Vec::new(),
+            )))
+            .await?
+            .0)
+    }
+
+    async fn postgresdb_stats(
+        db: &PostgresDb,
+        collection_id: &Uuid,
+    ) -> Result<(i64, Option<i64>, Option<Uuid>, Option<Uuid>, Vec<String>)> {
+        let table_name = Self::new_table_name(collection_id);
+        let query_stats = postgres_record::stats(&table_name);
+        let query_indexes = postgres_record::select_indexes(&table_name);
+
+        let ((count, oldest_id, newest_id, size), indexes) = tokio::try_join!(
+            db.fetch_one::<(i64, Option<Uuid>, Option<Uuid>, Option<i64>)>(sqlx::query_as(
+                &query_stats
+            )),
+            db.fetch_all::<(String,)>(sqlx::query_as(&query_indexes))
+        )?;
+
+        Ok((
+            count,
+            size,
+            oldest_id,
+            newest_id,
+            indexes.into_iter().map(|(index,)| index).collect(),
+        ))
+    }
+
     async fn postgresdb_delete_expired(
         db: &PostgresDb,
         collection_id: &Uuid,
         ttl_seconds: &i64,
-    ) -> Result<()> {
-        db.execute(
-            sqlx::query(&postgres_record::delete_expired(&Self::new_table_name(
-                collection_id,
-            )))
-            .bind(
-                Utc::now()
-                    .checked_sub_signed(
-                        Duration::try_seconds(*ttl_seconds)
-                            .ok_or_else(|| Error::msg("collection ttl is out of range."))?,
-                    )
-                    .ok_or_else(|| Error::msg("collection ttl is out of range."))?,
-            ),
-        )
-        .await?;
-        Ok(())
+        limit: &i64,
+    ) -> Result<u64> {
+        Ok(db
+            .execute(
+                sqlx::query(&postgres_record::delete_expired(&Self::new_table_name(
+                    collection_id,
+                )))
+                .bind(Self::ttl_cutoff(&Utc::now(), ttl_seconds)?)
+                .bind(limit),
+            )
+            .await?
+            .rows_affected())
+    }
+
+    async fn postgresdb_delete_expired_by_record(
+        db: &PostgresDb,
+        collection_id: &Uuid,
+        limit: &i64,
+    ) -> Result<u64> {
+        Ok(db
+            .execute(
+                sqlx::query(&postgres_record::delete_expired_by_record(
+                    &Self::new_table_name(collection_id),
+                ))
+                .bind(Utc::now())
+                .bind(limit),
+            )
+            .await?
+            .rows_affected())
     }
 
     async fn mysqldb_create_table(
@@ -1748,6 +3364,23 @@ impl RecordDao {
         Ok(())
     }
 
+    async fn mysqldb_rename_columns(
+        db: &MysqlDb,
+        collection_id: &Uuid,
+        columns: &HashMap<String, String>,
+    ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_id);
+        for (column, new_column) in columns {
+            db.execute_unprepared(sqlx::query(&mysql_record::rename_column(
+                &table_name,
+                column,
+                new_column,
+            )))
+            .await?;
+        }
+        Ok(())
+    }
+
     async fn mysqldb_change_columns_type(
         db: &MysqlDb,
         collection_id: &Uuid,
@@ -1786,14 +3419,14 @@ impl RecordDao {
     async fn mysqldb_create_unique_index(
         db: &MysqlDb,
         collection_id: &Uuid,
-        index: &str,
+        columns: &Vec<&str>,
     ) -> Result<()> {
         let record_table = Self::new_table_name(collection_id);
 
         let does_index_exist =
             db.fetch_one::<(i64,)>(sqlx::query_as(&mysql_record::count_unique_index(
                 &record_table,
-                index,
+                columns,
             )))
             .await?
             .0 > 0;
@@ -1801,7 +3434,7 @@ impl RecordDao {
         if !does_index_exist {
             db.execute_unprepared(sqlx::query(&mysql_record::create_unique_index(
                 &record_table,
-                index,
+                columns,
             )))
             .await?;
         }
@@ -1834,14 +3467,14 @@ impl RecordDao {
     async fn mysqldb_drop_unique_index(
         db: &MysqlDb,
         collection_id: &Uuid,
-        index: &str,
+        columns: &Vec<&str>,
     ) -> Result<()> {
         let record_table = Self::new_table_name(collection_id);
 
         let does_index_exist =
             db.fetch_one::<(i64,)>(sqlx::query_as(&mysql_record::count_unique_index(
                 &record_table,
-                index,
+                columns,
             )))
             .await?
             .0 > 0;
@@ -1849,7 +3482,7 @@ impl RecordDao {
         if does_index_exist {
             db.execute_unprepared(sqlx::query(&mysql_record::drop_unique_index(
                 &Self::new_table_name(collection_id),
-                index,
+                columns,
             )))
             .await?;
         }
@@ -1873,25 +3506,80 @@ impl RecordDao {
         Ok(())
     }
 
+    async fn mysqldb_insert_many(
+        db: &MysqlDb,
+        columns: &Vec<&str>,
+        records: &[Self],
+    ) -> Result<()> {
+        let query = mysql_record::insert_many(&records[0].table_name, columns, &records.len());
+        let mut query = sqlx::query(&query);
+        for record in records {
+            for col in columns {
+                query = record.data[*col].to_mysqldb_model(query)?;
+            }
+        }
+        db.execute(query).await?;
+        Ok(())
+    }
+
+    async fn mysqldb_copy_records(
+        db: &MysqlDb,
+        columns: &Vec<&str>,
+        source_collection: &CollectionDao,
+        target_collection: &CollectionDao,
+    ) -> Result<()> {
+        let target_table = Self::new_table_name(target_collection.id());
+        let source_table = Self::new_table_name(source_collection.id());
+        let query = mysql_record::copy_visible(
+            &target_table,
+            &source_table,
+            columns,
+            source_collection.opt_soft_delete(),
+            &source_collection.opt_ttl().is_some(),
+            source_collection.opt_per_record_ttl(),
+        );
+        let mut query = sqlx::query(&query);
+        for cutoff in Self::expiry_cutoffs(source_collection)? {
+            query = query.bind(cutoff);
+        }
+        db.execute(query).await?;
+        Ok(())
+    }
+
     async fn mysqldb_select(
         db: &MysqlDb,
         table_name: &str,
         columns: &Vec<&str>,
         id: &Uuid,
         created_by: &Option<Uuid>,
+        collection_data: &CollectionDao,
     ) -> Result<sqlx::mysql::MySqlRow> {
+        let ttl = collection_data.opt_ttl().is_some();
+        let cutoffs = Self::expiry_cutoffs(collection_data)?;
         Ok(if let Some(created_by) = created_by {
-            db.fetch_one_row(
-                sqlx::query(&mysql_record::select_by_id_and_created_by(
-                    table_name, columns,
-                ))
-                .bind(id)
-                .bind(created_by),
-            )
-            .await?
+            let mut query = if *collection_data.opt_soft_delete() {
+                mysql_record::select_not_deleted_by_id_and_created_by(table_name, columns)
+            } else {
+                mysql_record::select_by_id_and_created_by(table_name, columns)
+            };
+            query += &mysql_record::and_not_expired(&ttl, collection_data.opt_per_record_ttl());
+            let mut query = sqlx::query(&query).bind(id).bind(created_by);
+            for cutoff in cutoffs {
+                query = query.bind(cutoff);
+            }
+            db.fetch_one_row(query).await?
         } else {
-            db.fetch_one_row(sqlx::query(&mysql_record::select(table_name, columns)).bind(id))
-                .await?
+            let mut query = if *collection_data.opt_soft_delete() {
+                mysql_record::select_not_deleted(table_name, columns)
+            } else {
+                mysql_record::select(table_name, columns)
+            };
+            query += &mysql_record::and_not_expired(&ttl, collection_data.opt_per_record_ttl());
+            let mut query = sqlx::query(&query).bind(id);
+            for cutoff in cutoffs {
+                query = query.bind(cutoff);
+            }
+            db.fetch_one_row(query).await?
         })
     }
 
@@ -1905,6 +3593,94 @@ impl RecordDao {
         orders: &Vec<RecordOrder>,
         pagination: &RecordPagination,
     ) -> Result<(Vec<sqlx::mysql::MySqlRow>, i64)> {
+        let (query_select_many, query_total) = Self::mysqldb_select_many_query(
+            table_name, columns, created_by, filters, groups, orders, pagination,
+        )?;
+
+        let mut query_select_many = sqlx::query(&query_select_many);
+        let mut query_total = sqlx::query_as(&query_total);
+
+        if let Some(created_by) = created_by {
+            query_select_many = query_select_many.bind(created_by);
+            query_total = query_total.bind(created_by);
+        }
+        query_select_many = filters.mysqldb_values(query_select_many)?;
+        if let Some(limit) = pagination.limit() {
+            query_select_many = query_select_many.bind(limit);
+        }
+        query_total = filters.mysqldb_values_as(query_total)?;
+
+        let (rows, total) = tokio::try_join!(
+            db.fetch_all_rows(query_select_many),
+            db.fetch_one::<(i64,)>(query_total)
+        )?;
+
+        Ok((rows, total.0))
+    }
+
+    async fn mysqldb_select_many_stream(
+        db: &MysqlDb,
+        table_name: &str,
+        columns: &Vec<&str>,
+        kinds: &Vec<&ColumnKind>,
+        collection_id: &Uuid,
+        created_by: &Option<Uuid>,
+        filters: &RecordFilters,
+        orders: &Vec<RecordOrder>,
+        pagination: &RecordPagination,
+        tx: &mpsc::Sender<Self>,
+    ) -> Result<()> {
+        let (query_select_many, _) = Self::mysqldb_select_many_query(
+            table_name,
+            columns,
+            created_by,
+            filters,
+            &Vec::new(),
+            orders,
+            pagination,
+        )?;
+
+        let mut query_select_many = sqlx::query(&query_select_many);
+
+        if let Some(created_by) = created_by {
+            query_select_many = query_select_many.bind(created_by);
+        }
+        query_select_many = filters.mysqldb_values(query_select_many)?;
+        if let Some(limit) = pagination.limit() {
+            query_select_many = query_select_many.bind(limit);
+        }
+
+        let mut rows = db.fetch_rows(query_select_many);
+        while let Some(row) = rows.try_next().await? {
+            let mut data = HashMap::with_capacity(columns.len());
+            for (column, kind) in columns.iter().zip(kinds) {
+                data.insert(
+                    (*column).to_owned(),
+                    ColumnValue::from_mysqldb_model(kind, column, &row)?,
+                );
+            }
+            let record = Self {
+                table_name: table_name.to_owned(),
+                collection_id: *collection_id,
+                data,
+            };
+            if tx.send(record).await.is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn mysqldb_select_many_query(
+        table_name: &str,
+        columns: &Vec<&str>,
+        created_by: &Option<Uuid>,
+        filters: &RecordFilters,
+        groups: &Vec<&str>,
+        orders: &Vec<RecordOrder>,
+        pagination: &RecordPagination,
+    ) -> Result<(String, String)> {
         let mut filter = filters.mysqldb_filter_query(&None, 0)?;
         if created_by.is_some() {
             if filter.len() > 0 {
@@ -1917,7 +3693,14 @@ impl RecordDao {
         let mut order = Vec::with_capacity(orders.len());
         for o in orders {
             if MYSQL_ORDER_TYPE.contains(&o.kind.to_uppercase().as_str()) {
-                order.push((o.field.as_str(), o.kind.as_str()));
+                if let Some((lat, lng)) = o.near {
+                    order.push((
+                        mysql_record::distance(&o.field, &lat.to_string(), &lng.to_string()),
+                        o.kind.as_str(),
+                    ));
+                } else {
+                    order.push((format!("`{}`", o.field), o.kind.as_str()));
+                }
             } else {
                 return Err(Error::msg(format!(
                     "Order type '{}' is not supported",
@@ -1936,32 +3719,14 @@ impl RecordDao {
         );
         let query_total = mysql_record::count(table_name, &filter, groups);
 
-        let mut query_select_many = sqlx::query(&query_select_many);
-        let mut query_total = sqlx::query_as(&query_total);
-
-        if let Some(created_by) = created_by {
-            query_select_many = query_select_many.bind(created_by);
-            query_total = query_total.bind(created_by);
-        }
-        query_select_many = filters.mysqldb_values(query_select_many)?;
-        if let Some(limit) = pagination.limit() {
-            query_select_many = query_select_many.bind(limit);
-        }
-        query_total = filters.mysqldb_values_as(query_total)?;
-
-        let (rows, total) = tokio::try_join!(
-            db.fetch_all_rows(query_select_many),
-            db.fetch_one::<(i64,)>(query_total)
-        )?;
-
-        Ok((rows, total.0))
+        Ok((query_select_many, query_total))
     }
 
     async fn mysqldb_update(&self, db: &MysqlDb) -> Result<()> {
         let mut columns = Vec::with_capacity(self.data.len());
         let mut values = Vec::with_capacity(self.data.len());
         for (col, val) in &self.data {
-            if col != "_id" && col != "_updated_at" {
+            if col != "_id" && col != "_updated_at" && !matches!(val, ColumnValue::Counter(_)) {
                 columns.push(col.as_str());
                 values.push(val);
             }
@@ -1986,6 +3751,58 @@ impl RecordDao {
         Ok(())
     }
 
+    async fn mysqldb_increment_counter(
+        db: &MysqlDb,
+        collection_id: &Uuid,
+        id: &Uuid,
+        created_by: &Option<Uuid>,
+        field: &str,
+        delta: &i64,
+    ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_id);
+        let result = if let Some(created_by) = created_by {
+            db.execute(
+                sqlx::query(&mysql_record::increment_counter_by_created_by(
+                    &table_name,
+                    field,
+                ))
+                .bind(delta)
+                .bind(Utc::now())
+                .bind(id)
+                .bind(created_by),
+            )
+            .await?
+        } else {
+            db.execute(
+                sqlx::query(&mysql_record::increment_counter(&table_name, field))
+                    .bind(delta)
+                    .bind(Utc::now())
+                    .bind(id),
+            )
+            .await?
+        };
+        if result.rows_affected() == 0 {
+            return Err(DaoError::NotFound(format!("Record '{id}' is not found")).into());
+        }
+        Ok(())
+    }
+
+    async fn mysqldb_backfill_column(
+        db: &MysqlDb,
+        collection_id: &Uuid,
+        column: &str,
+        value: &ColumnValue,
+    ) -> Result<()> {
+        db.execute(
+            value.to_mysqldb_model(sqlx::query(&mysql_record::backfill_column(
+                &Self::new_table_name(collection_id),
+                column,
+            )))?,
+        )
+        .await?;
+        Ok(())
+    }
+
     async fn mysqldb_delete(
         db: &MysqlDb,
         collection_id: &Uuid,
@@ -2013,31 +3830,145 @@ impl RecordDao {
                 ))
                 .bind(id),
             )
-            .await?;
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn mysqldb_soft_delete(
+        db: &MysqlDb,
+        collection_id: &Uuid,
+        id: &Uuid,
+        created_by: &Option<Uuid>,
+    ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_id);
+        let now = Utc::now();
+        if let Some(created_by) = created_by {
+            db.execute(
+                sqlx::query(&mysql_record::soft_delete_by_created_by(&table_name))
+                    .bind(now)
+                    .bind(now)
+                    .bind(id)
+                    .bind(created_by),
+            )
+            .await?;
+        } else {
+            db.execute(
+                sqlx::query(&mysql_record::soft_delete(&table_name))
+                    .bind(now)
+                    .bind(now)
+                    .bind(id),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn mysqldb_restore(
+        db: &MysqlDb,
+        collection_id: &Uuid,
+        id: &Uuid,
+        created_by: &Option<Uuid>,
+    ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_id);
+        let result = if let Some(created_by) = created_by {
+            db.execute(
+                sqlx::query(&mysql_record::restore_by_created_by(&table_name))
+                    .bind(Utc::now())
+                    .bind(id)
+                    .bind(created_by),
+            )
+            .await?
+        } else {
+            db.execute(
+                sqlx::query(&mysql_record::restore(&table_name))
+                    .bind(Utc::now())
+                    .bind(id),
+            )
+            .await?
+        };
+        if result.rows_affected() == 0 {
+            return Err(DaoError::NotFound(format!("Deleted record '{id}' is not found")).into());
         }
         Ok(())
     }
 
+    async fn mysqldb_purge_deleted(db: &MysqlDb, collection_id: &Uuid) -> Result<()> {
+        db.execute(sqlx::query(&mysql_record::purge_deleted(
+            &Self::new_table_name(collection_id),
+        )))
+        .await?;
+        Ok(())
+    }
+
+    async fn mysqldb_count(db: &MysqlDb, collection_id: &Uuid) -> Result<i64> {
+        Ok(db
+            .fetch_one::<(i64,)>(sqlx::query_as(&mysql_record::count(
+                &Self::new_table_name(collection_id),
+                "",
+                &Vec::new(),
+            )))
+            .await?
+            .0)
+    }
+
+    async fn mysqldb_stats(
+        db: &MysqlDb,
+        collection_id: &Uuid,
+    ) -> Result<(i64, Option<i64>, Option<Uuid>, Option<Uuid>, Vec<String>)> {
+        let table_name = Self::new_table_name(collection_id);
+        let query_stats = mysql_record::stats(&table_name);
+        let query_indexes = mysql_record::select_indexes(&table_name);
+
+        let ((count, oldest_id, newest_id, size), indexes) = tokio::try_join!(
+            db.fetch_one::<(i64, Option<Uuid>, Option<Uuid>, Option<i64>)>(sqlx::query_as(
+                &query_stats
+            )),
+            db.fetch_all::<(String,)>(sqlx::query_as(&query_indexes))
+        )?;
+
+        Ok((
+            count,
+            size,
+            oldest_id,
+            newest_id,
+            indexes.into_iter().map(|(index,)| index).collect(),
+        ))
+    }
+
     async fn mysqldb_delete_expired(
         db: &MysqlDb,
         collection_id: &Uuid,
         ttl_seconds: &i64,
-    ) -> Result<()> {
-        db.execute(
-            sqlx::query(&mysql_record::delete_expired(&Self::new_table_name(
-                collection_id,
-            )))
-            .bind(
-                Utc::now()
-                    .checked_sub_signed(
-                        Duration::try_seconds(*ttl_seconds)
-                            .ok_or_else(|| Error::msg("collection ttl is out of range."))?,
-                    )
-                    .ok_or_else(|| Error::msg("collection ttl is out of range."))?,
-            ),
-        )
-        .await?;
-        Ok(())
+        limit: &i64,
+    ) -> Result<u64> {
+        Ok(db
+            .execute(
+                sqlx::query(&mysql_record::delete_expired(&Self::new_table_name(
+                    collection_id,
+                )))
+                .bind(Self::ttl_cutoff(&Utc::now(), ttl_seconds)?)
+                .bind(limit),
+            )
+            .await?
+            .rows_affected())
+    }
+
+    async fn mysqldb_delete_expired_by_record(
+        db: &MysqlDb,
+        collection_id: &Uuid,
+        limit: &i64,
+    ) -> Result<u64> {
+        Ok(db
+            .execute(
+                sqlx::query(&mysql_record::delete_expired_by_record(
+                    &Self::new_table_name(collection_id),
+                ))
+                .bind(Utc::now())
+                .bind(limit),
+            )
+            .await?
+            .rows_affected())
     }
 
     async fn sqlitedb_create_table(
@@ -2097,6 +4028,23 @@ impl RecordDao {
         Ok(())
     }
 
+    async fn sqlitedb_rename_columns(
+        db: &SqliteDb,
+        collection_id: &Uuid,
+        columns: &HashMap<String, String>,
+    ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_id);
+        for (column, new_column) in columns {
+            db.execute_unprepared(sqlx::query(&sqlite_record::rename_column(
+                &table_name,
+                column,
+                new_column,
+            )))
+            .await?;
+        }
+        Ok(())
+    }
+
     async fn sqlitedb_change_columns_type(
         db: &SqliteDb,
         collection_id: &Uuid,
@@ -2122,11 +4070,11 @@ impl RecordDao {
     async fn sqlitedb_create_unique_index(
         db: &SqliteDb,
         collection_id: &Uuid,
-        index: &str,
+        columns: &Vec<&str>,
     ) -> Result<()> {
         db.execute_unprepared(sqlx::query(&sqlite_record::create_unique_index(
             &Self::new_table_name(collection_id),
-            index,
+            columns,
         )))
         .await?;
         Ok(())
@@ -2144,11 +4092,11 @@ impl RecordDao {
     async fn sqlitedb_drop_unique_index(
         db: &SqliteDb,
         collection_id: &Uuid,
-        index: &str,
+        columns: &Vec<&str>,
     ) -> Result<()> {
         db.execute_unprepared(sqlx::query(&sqlite_record::drop_unique_index(
             &Self::new_table_name(collection_id),
-            index,
+            columns,
         )))
         .await?;
         Ok(())
@@ -2170,25 +4118,80 @@ impl RecordDao {
         Ok(())
     }
 
+    async fn sqlitedb_insert_many(
+        db: &SqliteDb,
+        columns: &Vec<&str>,
+        records: &[Self],
+    ) -> Result<()> {
+        let query = sqlite_record::insert_many(&records[0].table_name, columns, &records.len());
+        let mut query = sqlx::query(&query);
+        for record in records {
+            for col in columns {
+                query = record.data[*col].to_sqlitedb_model(query)?;
+            }
+        }
+        db.execute(query).await?;
+        Ok(())
+    }
+
+    async fn sqlitedb_copy_records(
+        db: &SqliteDb,
+        columns: &Vec<&str>,
+        source_collection: &CollectionDao,
+        target_collection: &CollectionDao,
+    ) -> Result<()> {
+        let target_table = Self::new_table_name(target_collection.id());
+        let source_table = Self::new_table_name(source_collection.id());
+        let query = sqlite_record::copy_visible(
+            &target_table,
+            &source_table,
+            columns,
+            source_collection.opt_soft_delete(),
+            &source_collection.opt_ttl().is_some(),
+            source_collection.opt_per_record_ttl(),
+        );
+        let mut query = sqlx::query(&query);
+        for cutoff in Self::expiry_cutoffs(source_collection)? {
+            query = query.bind(cutoff);
+        }
+        db.execute(query).await?;
+        Ok(())
+    }
+
     async fn sqlitedb_select(
         db: &SqliteDb,
         table_name: &str,
         columns: &Vec<&str>,
         id: &Uuid,
         created_by: &Option<Uuid>,
+        collection_data: &CollectionDao,
     ) -> Result<sqlx::sqlite::SqliteRow> {
+        let ttl = collection_data.opt_ttl().is_some();
+        let cutoffs = Self::expiry_cutoffs(collection_data)?;
         Ok(if let Some(created_by) = created_by {
-            db.fetch_one_row(
-                sqlx::query(&sqlite_record::select_by_id_and_created_by(
-                    table_name, columns,
-                ))
-                .bind(id)
-                .bind(created_by),
-            )
-            .await?
+            let mut query = if *collection_data.opt_soft_delete() {
+                sqlite_record::select_not_deleted_by_id_and_created_by(table_name, columns)
+            } else {
+                sqlite_record::select_by_id_and_created_by(table_name, columns)
+            };
+            query += &sqlite_record::and_not_expired(&ttl, collection_data.opt_per_record_ttl());
+            let mut query = sqlx::query(&query).bind(id).bind(created_by);
+            for cutoff in cutoffs {
+                query = query.bind(cutoff);
+            }
+            db.fetch_one_row(query).await?
         } else {
-            db.fetch_one_row(sqlx::query(&sqlite_record::select(table_name, columns)).bind(id))
-                .await?
+            let mut query = if *collection_data.opt_soft_delete() {
+                sqlite_record::select_not_deleted(table_name, columns)
+            } else {
+                sqlite_record::select(table_name, columns)
+            };
+            query += &sqlite_record::and_not_expired(&ttl, collection_data.opt_per_record_ttl());
+            let mut query = sqlx::query(&query).bind(id);
+            for cutoff in cutoffs {
+                query = query.bind(cutoff);
+            }
+            db.fetch_one_row(query).await?
         })
     }
 
@@ -2202,6 +4205,94 @@ impl RecordDao {
         orders: &Vec<RecordOrder>,
         pagination: &RecordPagination,
     ) -> Result<(Vec<sqlx::sqlite::SqliteRow>, i64)> {
+        let (query_select_many, query_total) = Self::sqlitedb_select_many_query(
+            table_name, columns, created_by, filters, groups, orders, pagination,
+        )?;
+
+        let mut query_select_many = sqlx::query(&query_select_many);
+        let mut query_total = sqlx::query_as(&query_total);
+
+        if let Some(created_by) = created_by {
+            query_select_many = query_select_many.bind(created_by);
+            query_total = query_total.bind(created_by);
+        }
+        query_select_many = filters.sqlitedb_values(query_select_many)?;
+        if let Some(limit) = pagination.limit() {
+            query_select_many = query_select_many.bind(limit);
+        }
+        query_total = filters.sqlitedb_values_as(query_total)?;
+
+        let (rows, total) = tokio::try_join!(
+            db.fetch_all_rows(query_select_many),
+            db.fetch_one::<(i64,)>(query_total)
+        )?;
+
+        Ok((rows, total.0))
+    }
+
+    async fn sqlitedb_select_many_stream(
+        db: &SqliteDb,
+        table_name: &str,
+        columns: &Vec<&str>,
+        kinds: &Vec<&ColumnKind>,
+        collection_id: &Uuid,
+        created_by: &Option<Uuid>,
+        filters: &RecordFilters,
+        orders: &Vec<RecordOrder>,
+        pagination: &RecordPagination,
+        tx: &mpsc::Sender<Self>,
+    ) -> Result<()> {
+        let (query_select_many, _) = Self::sqlitedb_select_many_query(
+            table_name,
+            columns,
+            created_by,
+            filters,
+            &Vec::new(),
+            orders,
+            pagination,
+        )?;
+
+        let mut query_select_many = sqlx::query(&query_select_many);
+
+        if let Some(created_by) = created_by {
+            query_select_many = query_select_many.bind(created_by);
+        }
+        query_select_many = filters.sqlitedb_values(query_select_many)?;
+        if let Some(limit) = pagination.limit() {
+            query_select_many = query_select_many.bind(limit);
+        }
+
+        let mut rows = db.fetch_rows(query_select_many);
+        while let Some(row) = rows.try_next().await? {
+            let mut data = HashMap::with_capacity(columns.len());
+            for (column, kind) in columns.iter().zip(kinds) {
+                data.insert(
+                    (*column).to_owned(),
+                    ColumnValue::from_sqlitedb_model(kind, column, &row)?,
+                );
+            }
+            let record = Self {
+                table_name: table_name.to_owned(),
+                collection_id: *collection_id,
+                data,
+            };
+            if tx.send(record).await.is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn sqlitedb_select_many_query(
+        table_name: &str,
+        columns: &Vec<&str>,
+        created_by: &Option<Uuid>,
+        filters: &RecordFilters,
+        groups: &Vec<&str>,
+        orders: &Vec<RecordOrder>,
+        pagination: &RecordPagination,
+    ) -> Result<(String, String)> {
         let mut filter = filters.sqlitedb_filter_query(&None, 0)?;
         if created_by.is_some() {
             if filter.len() > 0 {
@@ -2214,7 +4305,20 @@ impl RecordDao {
         let mut order = Vec::with_capacity(orders.len());
         for o in orders {
             if SQLITE_ORDER_TYPE.contains(&o.kind.to_uppercase().as_str()) {
-                order.push((o.field.as_str(), o.kind.as_str()));
+                if let Some((lat, lng)) = o.near {
+                    order.push((
+                        sqlite_record::squared_distance(
+                            &o.field,
+                            &lat.to_string(),
+                            &lng.to_string(),
+                            &METERS_PER_DEGREE.to_string(),
+                            &(METERS_PER_DEGREE * lat.to_radians().cos()).to_string(),
+                        ),
+                        o.kind.as_str(),
+                    ));
+                } else {
+                    order.push((format!("\"{}\"", o.field), o.kind.as_str()));
+                }
             } else {
                 return Err(Error::msg(format!(
                     "Order type '{}' is not supported",
@@ -2233,32 +4337,14 @@ impl RecordDao {
         );
         let query_total = sqlite_record::count(table_name, &filter, groups);
 
-        let mut query_select_many = sqlx::query(&query_select_many);
-        let mut query_total = sqlx::query_as(&query_total);
-
-        if let Some(created_by) = created_by {
-            query_select_many = query_select_many.bind(created_by);
-            query_total = query_total.bind(created_by);
-        }
-        query_select_many = filters.sqlitedb_values(query_select_many)?;
-        if let Some(limit) = pagination.limit() {
-            query_select_many = query_select_many.bind(limit);
-        }
-        query_total = filters.sqlitedb_values_as(query_total)?;
-
-        let (rows, total) = tokio::try_join!(
-            db.fetch_all_rows(query_select_many),
-            db.fetch_one::<(i64,)>(query_total)
-        )?;
-
-        Ok((rows, total.0))
+        Ok((query_select_many, query_total))
     }
 
     async fn sqlitedb_update(&self, db: &SqliteDb) -> Result<()> {
         let mut columns = Vec::with_capacity(self.data.len());
         let mut values = Vec::with_capacity(self.data.len());
         for (col, val) in &self.data {
-            if col != "_id" && col != "_updated_at" {
+            if col != "_id" && col != "_updated_at" && !matches!(val, ColumnValue::Counter(_)) {
                 columns.push(col.as_str());
                 values.push(val);
             }
@@ -2283,6 +4369,58 @@ impl RecordDao {
         Ok(())
     }
 
+    async fn sqlitedb_increment_counter(
+        db: &SqliteDb,
+        collection_id: &Uuid,
+        id: &Uuid,
+        created_by: &Option<Uuid>,
+        field: &str,
+        delta: &i64,
+    ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_id);
+        let result = if let Some(created_by) = created_by {
+            db.execute(
+                sqlx::query(&sqlite_record::increment_counter_by_created_by(
+                    &table_name,
+                    field,
+                ))
+                .bind(delta)
+                .bind(Utc::now())
+                .bind(id)
+                .bind(created_by),
+            )
+            .await?
+        } else {
+            db.execute(
+                sqlx::query(&sqlite_record::increment_counter(&table_name, field))
+                    .bind(delta)
+                    .bind(Utc::now())
+                    .bind(id),
+            )
+            .await?
+        };
+        if result.rows_affected() == 0 {
+            return Err(DaoError::NotFound(format!("Record '{id}' is not found")).into());
+        }
+        Ok(())
+    }
+
+    async fn sqlitedb_backfill_column(
+        db: &SqliteDb,
+        collection_id: &Uuid,
+        column: &str,
+        value: &ColumnValue,
+    ) -> Result<()> {
+        db.execute(
+            value.to_sqlitedb_model(sqlx::query(&sqlite_record::backfill_column(
+                &Self::new_table_name(collection_id),
+                column,
+            )))?,
+        )
+        .await?;
+        Ok(())
+    }
+
     async fn sqlitedb_delete(
         db: &SqliteDb,
         collection_id: &Uuid,
@@ -2315,26 +4453,173 @@ impl RecordDao {
         Ok(())
     }
 
+    async fn sqlitedb_soft_delete(
+        db: &SqliteDb,
+        collection_id: &Uuid,
+        id: &Uuid,
+        created_by: &Option<Uuid>,
+    ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_id);
+        let now = Utc::now();
+        if let Some(created_by) = created_by {
+            db.execute(
+                sqlx::query(&sqlite_record::soft_delete_by_created_by(&table_name))
+                    .bind(now)
+                    .bind(now)
+                    .bind(id)
+                    .bind(created_by),
+            )
+            .await?;
+        } else {
+            db.execute(
+                sqlx::query(&sqlite_record::soft_delete(&table_name))
+                    .bind(now)
+                    .bind(now)
+                    .bind(id),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn sqlitedb_restore(
+        db: &SqliteDb,
+        collection_id: &Uuid,
+        id: &Uuid,
+        created_by: &Option<Uuid>,
+    ) -> Result<()> {
+        let table_name = Self::new_table_name(collection_id);
+        let result = if let Some(created_by) = created_by {
+            db.execute(
+                sqlx::query(&sqlite_record::restore_by_created_by(&table_name))
+                    .bind(Utc::now())
+                    .bind(id)
+                    .bind(created_by),
+            )
+            .await?
+        } else {
+            db.execute(
+                sqlx::query(&sqlite_record::restore(&table_name))
+                    .bind(Utc::now())
+                    .bind(id),
+            )
+            .await?
+        };
+        if result.rows_affected() == 0 {
+            return Err(DaoError::NotFound(format!("Deleted record '{id}' is not found")).into());
+        }
+        Ok(())
+    }
+
+    async fn sqlitedb_purge_deleted(db: &SqliteDb, collection_id: &Uuid) -> Result<()> {
+        db.execute(sqlx::query(&sqlite_record::purge_deleted(
+            &Self::new_table_name(collection_id),
+        )))
+        .await?;
+        Ok(())
+    }
+
+    async fn sqlitedb_count(db: &SqliteDb, collection_id: &Uuid) -> Result<i64> {
+        Ok(db
+            .fetch_one::<(i64,)>(sqlx::query_as(&sqlite_record::count(
+                &Self::new_table_name(collection_id),
+                "",
+                &Vec::new(),
+            )))
+            .await?
+            .0)
+    }
+
+    async fn sqlitedb_stats(
+        db: &SqliteDb,
+        collection_id: &Uuid,
+    ) -> Result<(i64, Option<i64>, Option<Uuid>, Option<Uuid>, Vec<String>)> {
+        let table_name = Self::new_table_name(collection_id);
+        let query_stats = sqlite_record::stats(&table_name);
+        let query_indexes = sqlite_record::select_indexes(&table_name);
+
+        let ((count, oldest_id, newest_id, size), indexes) = tokio::try_join!(
+            db.fetch_one::<(i64, Option<Uuid>, Option<Uuid>, Option<i64>)>(sqlx::query_as(
+                &query_stats
+            )),
+            db.fetch_all::<(String,)>(sqlx::query_as(&query_indexes))
+        )?;
+
+        Ok((
+            count,
+            size,
+            oldest_id,
+            newest_id,
+            indexes.into_iter().map(|(index,)| index).collect(),
+        ))
+    }
+
     async fn sqlitedb_delete_expired(
         db: &SqliteDb,
         collection_id: &Uuid,
         ttl_seconds: &i64,
-    ) -> Result<()> {
-        db.execute(
-            sqlx::query(&sqlite_record::delete_expired(&Self::new_table_name(
-                collection_id,
-            )))
-            .bind(
-                Utc::now()
-                    .checked_sub_signed(
-                        Duration::try_seconds(*ttl_seconds)
-                            .ok_or_else(|| Error::msg("collection ttl is out of range."))?,
-                    )
-                    .ok_or_else(|| Error::msg("collection ttl is out of range."))?,
-            ),
-        )
-        .await?;
-        Ok(())
+        limit: &i64,
+    ) -> Result<u64> {
+        Ok(db
+            .execute(
+                sqlx::query(&sqlite_record::delete_expired(&Self::new_table_name(
+                    collection_id,
+                )))
+                .bind(Self::ttl_cutoff(&Utc::now(), ttl_seconds)?)
+                .bind(limit),
+            )
+            .await?
+            .rows_affected())
+    }
+
+    async fn sqlitedb_delete_expired_by_record(
+        db: &SqliteDb,
+        collection_id: &Uuid,
+        limit: &i64,
+    ) -> Result<u64> {
+        Ok(db
+            .execute(
+                sqlx::query(&sqlite_record::delete_expired_by_record(
+                    &Self::new_table_name(collection_id),
+                ))
+                .bind(Utc::now())
+                .bind(limit),
+            )
+            .await?
+            .rows_affected())
+    }
+}
+
+pub struct RecordStats {
+    count: i64,
+    size: Option<i64>,
+    oldest_created_at: Option<DateTime<Utc>>,
+    newest_created_at: Option<DateTime<Utc>>,
+    indexes: Vec<String>,
+}
+
+impl RecordStats {
+    // Soft-deleted records are counted too, since they still take up space
+    pub fn count(&self) -> &i64 {
+        &self.count
+    }
+
+    // Approximate size in bytes of the records and their indexes, if the database can tell
+    pub fn size(&self) -> &Option<i64> {
+        &self.size
+    }
+
+    // Creation time of the record with the lowest _id, empty if that _id isn't a UUIDv7
+    pub fn oldest_created_at(&self) -> &Option<DateTime<Utc>> {
+        &self.oldest_created_at
+    }
+
+    pub fn newest_created_at(&self) -> &Option<DateTime<Utc>> {
+        &self.newest_created_at
+    }
+
+    pub fn indexes(&self) -> &Vec<String> {
+        &self.indexes
     }
 }
 
@@ -2346,6 +4631,20 @@ impl RecordFilters {
         Self(data.to_vec())
     }
 
+    // Every field compared by the filters, the ones nested under a logical operator included
+    pub fn fields(&self) -> Vec<&str> {
+        let mut fields = Vec::new();
+        for f in &self.0 {
+            if let Some(field) = &f.field {
+                fields.push(field.as_str());
+            }
+            if let Some(children) = &f.children {
+                fields.append(&mut children.fields());
+            }
+        }
+        fields
+    }
+
     pub fn scylladb_filter_query(
         &self,
         logical_operator: &Option<&str>,
@@ -2377,7 +4676,26 @@ impl RecordFilters {
                 }
             } else {
                 let field = f.field.as_ref().unwrap();
-                if SCYLLA_COMPARISON_OPERATOR.contains(&op.as_str()) {
+                if f.path.is_some() {
+                    return Err(Error::msg(format!(
+                        "Filtering by a JSON path on field '{field}' is not supported in ScyllaDB because json fields are stored as blobs"
+                    )));
+                } else if let Some(values) = &f.values {
+                    if SCYLLA_COMPARISON_OPERATOR.contains(&op.as_str()) {
+                        filter += &f.values_query(
+                            &format!("\"{field}\""),
+                            &vec!["?".to_owned(); values.len()],
+                        );
+                    } else {
+                        return Err(Error::msg(format!(
+                            "Operator '{op}' is not supported as a comparison operator in ScyllaDB"
+                        )));
+                    }
+                } else if op == "$NEAR" {
+                    return Err(Error::msg(
+                        "Operator '$near' is not supported in ScyllaDB because it has no geospatial functions",
+                    ));
+                } else if SCYLLA_COMPARISON_OPERATOR.contains(&op.as_str()) {
                     if f.value.is_some() {
                         filter += &format!("\"{}\" {} ?", field, &op);
                     } else {
@@ -2399,6 +4717,11 @@ impl RecordFilters {
             if let Some(value) = &f.value {
                 values.push(value.to_scylladb_model()?)
             }
+            if let Some(filter_values) = &f.values {
+                for value in filter_values {
+                    values.push(value.to_scylladb_model()?)
+                }
+            }
             if let Some(children) = &f.children {
                 values.append(&mut children.scylladb_values()?)
             }
@@ -2439,7 +4762,65 @@ impl RecordFilters {
                     )));
                 }
             } else {
-                if POSTGRES_COMPARISON_OPERATOR.contains(&op.as_str()) {
+                if let Some(path) = &f.path {
+                    if POSTGRES_COMPARISON_OPERATOR.contains(&op.as_str()) {
+                        filter += &format!(
+                            "(\"{}\" #>> ${}) {}",
+                            f.field.as_ref().unwrap(),
+                            first_argument_idx,
+                            &op
+                        );
+                        *first_argument_idx += 1;
+                        if f.value.is_some() {
+                            filter += &format!(" ${}", first_argument_idx);
+                            *first_argument_idx += 1;
+                        }
+                    } else {
+                        return Err(Error::msg(format!(
+                            "Operator '{op}' is not supported on JSON path '{}' in PostgreSQL",
+                            path.join(".")
+                        )));
+                    }
+                } else if let Some(values) = &f.values {
+                    if !POSTGRES_COMPARISON_OPERATOR.contains(&op.as_str()) {
+                        return Err(Error::msg(format!(
+                            "Operator '{op}' is not supported as a comparison operator in PostgreSQL"
+                        )));
+                    }
+                    filter += &f.values_query(
+                        &format!("\"{}\"", f.field.as_ref().unwrap()),
+                        &(0..values.len())
+                            .map(|idx| format!("${}", *first_argument_idx + idx))
+                            .collect::<Vec<_>>(),
+                    );
+                    *first_argument_idx += values.len();
+                } else if op == "$NEAR" {
+                    if f.near_point().is_some() {
+                        filter += &format!(
+                            "{} <= ${}",
+                            postgres_record::distance(
+                                f.field.as_ref().unwrap(),
+                                &format!("${}", first_argument_idx),
+                                &format!("${}", *first_argument_idx + 1)
+                            ),
+                            *first_argument_idx + 2
+                        );
+                        *first_argument_idx += 3;
+                    } else {
+                        return Err(Error::msg(NEAR_FILTER_REQUIRED_ERROR));
+                    }
+                } else if op == "CONTAINS" {
+                    if f.value.is_some() {
+                        filter += &format!(
+                            "${} = ANY(\"{}\")",
+                            first_argument_idx,
+                            f.field.as_ref().unwrap()
+                        );
+                        *first_argument_idx += 1;
+                    } else {
+                        return Err(Error::msg(format!("The 'value' field is required")));
+                    }
+                } else if POSTGRES_COMPARISON_OPERATOR.contains(&op.as_str()) {
                     filter += &format!("\"{}\" {}", f.field.as_ref().unwrap(), &op);
                     if f.value.is_some() {
                         filter += &format!(" ${}", first_argument_idx);
@@ -2464,8 +4845,20 @@ impl RecordFilters {
     ) -> Result<sqlx::query::Query<'a, sqlx::Postgres, sqlx::postgres::PgArguments>> {
         let mut query = query;
         for f in &self.0 {
-            if let Some(value) = &f.value {
-                query = value.to_postgresdb_model(query)?
+            if let Some((lat, lng, radius)) = f.near_point() {
+                query = query.bind(lat).bind(lng).bind(radius)
+            } else {
+                if let Some(path) = &f.path {
+                    query = query.bind(path.to_owned())
+                }
+                if let Some(value) = &f.value {
+                    query = value.to_postgresdb_model(query)?
+                }
+                if let Some(values) = &f.values {
+                    for value in values {
+                        query = value.to_postgresdb_model(query)?
+                    }
+                }
             }
             if let Some(children) = &f.children {
                 query = children.postgresdb_values(query)?
@@ -2480,8 +4873,20 @@ impl RecordFilters {
     ) -> Result<sqlx::query::QueryAs<'a, sqlx::Postgres, T, sqlx::postgres::PgArguments>> {
         let mut query = query;
         for f in &self.0 {
-            if let Some(value) = &f.value {
-                query = value.to_postgresdb_model_as(query)?
+            if let Some((lat, lng, radius)) = f.near_point() {
+                query = query.bind(lat).bind(lng).bind(radius)
+            } else {
+                if let Some(path) = &f.path {
+                    query = query.bind(path.to_owned())
+                }
+                if let Some(value) = &f.value {
+                    query = value.to_postgresdb_model_as(query)?
+                }
+                if let Some(values) = &f.values {
+                    for value in values {
+                        query = value.to_postgresdb_model_as(query)?
+                    }
+                }
             }
             if let Some(children) = &f.children {
                 query = children.postgresdb_values_as(query)?
@@ -2518,7 +4923,51 @@ impl RecordFilters {
                     )));
                 }
             } else {
-                if MYSQL_COMPARISON_OPERATOR.contains(&op.as_str()) {
+                if let Some(path) = &f.path {
+                    if MYSQL_COMPARISON_OPERATOR.contains(&op.as_str()) {
+                        filter += &format!(
+                            "JSON_UNQUOTE(JSON_EXTRACT(`{}`, ?)) {}",
+                            f.field.as_ref().unwrap(),
+                            &op
+                        );
+                        if f.value.is_some() {
+                            filter += " ?";
+                        }
+                    } else {
+                        return Err(Error::msg(format!(
+                            "Operator '{op}' is not supported on JSON path '{}' in MySQL",
+                            path.join(".")
+                        )));
+                    }
+                } else if let Some(values) = &f.values {
+                    if !MYSQL_COMPARISON_OPERATOR.contains(&op.as_str()) {
+                        return Err(Error::msg(format!(
+                            "Operator '{op}' is not supported as a comparison operator in MySQL"
+                        )));
+                    }
+                    filter += &f.values_query(
+                        &format!("`{}`", f.field.as_ref().unwrap()),
+                        &vec!["?".to_owned(); values.len()],
+                    );
+                } else if op == "$NEAR" {
+                    if f.near_point().is_some() {
+                        filter += &format!(
+                            "{} <= ?",
+                            mysql_record::distance(f.field.as_ref().unwrap(), "?", "?")
+                        );
+                    } else {
+                        return Err(Error::msg(NEAR_FILTER_REQUIRED_ERROR));
+                    }
+                } else if op == "CONTAINS" {
+                    if f.value.is_some() {
+                        filter += &format!(
+                            "JSON_CONTAINS(`{}`, JSON_ARRAY(?))",
+                            f.field.as_ref().unwrap()
+                        );
+                    } else {
+                        return Err(Error::msg(format!("The 'value' field is required")));
+                    }
+                } else if MYSQL_COMPARISON_OPERATOR.contains(&op.as_str()) {
                     filter += &format!("`{}` {}", f.field.as_ref().unwrap(), &op);
                     if f.value.is_some() {
                         filter += " ?";
@@ -2542,8 +4991,20 @@ impl RecordFilters {
     ) -> Result<sqlx::query::Query<'a, sqlx::MySql, sqlx::mysql::MySqlArguments>> {
         let mut query = query;
         for f in &self.0 {
-            if let Some(value) = &f.value {
-                query = value.to_mysqldb_model(query)?
+            if let Some((lat, lng, radius)) = f.near_point() {
+                query = query.bind(lng).bind(lat).bind(radius)
+            } else {
+                if f.path.is_some() {
+                    query = query.bind(f.json_path_expr())
+                }
+                if let Some(value) = &f.value {
+                    query = value.to_mysqldb_model(query)?
+                }
+                if let Some(values) = &f.values {
+                    for value in values {
+                        query = value.to_mysqldb_model(query)?
+                    }
+                }
             }
             if let Some(children) = &f.children {
                 query = children.mysqldb_values(query)?
@@ -2558,8 +5019,20 @@ impl RecordFilters {
     ) -> Result<sqlx::query::QueryAs<'a, sqlx::MySql, T, sqlx::mysql::MySqlArguments>> {
         let mut query = query;
         for f in &self.0 {
-            if let Some(value) = &f.value {
-                query = value.to_mysqldb_model_as(query)?
+            if let Some((lat, lng, radius)) = f.near_point() {
+                query = query.bind(lng).bind(lat).bind(radius)
+            } else {
+                if f.path.is_some() {
+                    query = query.bind(f.json_path_expr())
+                }
+                if let Some(value) = &f.value {
+                    query = value.to_mysqldb_model_as(query)?
+                }
+                if let Some(values) = &f.values {
+                    for value in values {
+                        query = value.to_mysqldb_model_as(query)?
+                    }
+                }
             }
             if let Some(children) = &f.children {
                 query = children.mysqldb_values_as(query)?
@@ -2596,7 +5069,59 @@ impl RecordFilters {
                     )));
                 }
             } else {
-                if SQLITE_COMPARISON_OPERATOR.contains(&op.as_str()) {
+                if let Some(path) = &f.path {
+                    if SQLITE_COMPARISON_OPERATOR.contains(&op.as_str()) {
+                        filter += &format!(
+                            "CAST(json_extract(CAST(`{}` AS TEXT), ?) AS TEXT) {}",
+                            f.field.as_ref().unwrap(),
+                            &op
+                        );
+                        if f.value.is_some() {
+                            filter += " ?";
+                        }
+                    } else {
+                        return Err(Error::msg(format!(
+                            "Operator '{op}' is not supported on JSON path '{}' in SQLite",
+                            path.join(".")
+                        )));
+                    }
+                } else if let Some(values) = &f.values {
+                    if !SQLITE_COMPARISON_OPERATOR.contains(&op.as_str()) {
+                        return Err(Error::msg(format!(
+                            "Operator '{op}' is not supported as a comparison operator in SQLite"
+                        )));
+                    }
+                    filter += &f.values_query(
+                        &format!("`{}`", f.field.as_ref().unwrap()),
+                        &vec!["?".to_owned(); values.len()],
+                    );
+                } else if op == "$NEAR" {
+                    // Coordinates are inlined because the approximation needs each of them twice
+                    if let Some((lat, lng, radius)) = f.near_point() {
+                        filter += &format!(
+                            "{} <= {}",
+                            sqlite_record::squared_distance(
+                                f.field.as_ref().unwrap(),
+                                &lat.to_string(),
+                                &lng.to_string(),
+                                &METERS_PER_DEGREE.to_string(),
+                                &(METERS_PER_DEGREE * lat.to_radians().cos()).to_string()
+                            ),
+                            radius * radius
+                        );
+                    } else {
+                        return Err(Error::msg(NEAR_FILTER_REQUIRED_ERROR));
+                    }
+                } else if op == "CONTAINS" {
+                    if f.value.is_some() {
+                        filter += &format!(
+                            "EXISTS (SELECT 1 FROM json_each(CAST(`{}` AS TEXT)) WHERE \"value\" = ?)",
+                            f.field.as_ref().unwrap()
+                        );
+                    } else {
+                        return Err(Error::msg(format!("The 'value' field is required")));
+                    }
+                } else if SQLITE_COMPARISON_OPERATOR.contains(&op.as_str()) {
                     filter += &format!("`{}` {}", f.field.as_ref().unwrap(), &op);
                     if f.value.is_some() {
                         filter += " ?";
@@ -2620,9 +5145,20 @@ impl RecordFilters {
     ) -> Result<sqlx::query::Query<'a, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'a>>> {
         let mut query = query;
         for f in &self.0 {
+            if f.near_point().is_some() {
+                continue;
+            }
+            if f.path.is_some() {
+                query = query.bind(f.json_path_expr())
+            }
             if let Some(value) = &f.value {
                 query = value.to_sqlitedb_model(query)?
             }
+            if let Some(values) = &f.values {
+                for value in values {
+                    query = value.to_sqlitedb_model(query)?
+                }
+            }
             if let Some(children) = &f.children {
                 query = children.sqlitedb_values(query)?
             }
@@ -2636,9 +5172,20 @@ impl RecordFilters {
     ) -> Result<sqlx::query::QueryAs<'a, sqlx::Sqlite, T, sqlx::sqlite::SqliteArguments<'a>>> {
         let mut query = query;
         for f in &self.0 {
+            if f.near_point().is_some() {
+                continue;
+            }
+            if f.path.is_some() {
+                query = query.bind(f.json_path_expr())
+            }
             if let Some(value) = &f.value {
                 query = value.to_sqlitedb_model_as(query)?
             }
+            if let Some(values) = &f.values {
+                for value in values {
+                    query = value.to_sqlitedb_model_as(query)?
+                }
+            }
             if let Some(children) = &f.children {
                 query = children.sqlitedb_values_as(query)?
             }
@@ -2652,6 +5199,9 @@ impl RecordFilters {
             if let Some(children) = &f.children {
                 capacity += children.values_capacity()
             }
+            if let Some(values) = &f.values {
+                capacity += values.len()
+            }
         }
         capacity
     }
@@ -2663,6 +5213,9 @@ pub struct RecordFilter {
     op: String,
     value: Option<ColumnValue>,
     children: Option<RecordFilters>,
+    radius: Option<f64>,
+    path: Option<Vec<String>>,
+    values: Option<Vec<ColumnValue>>,
 }
 
 impl RecordFilter {
@@ -2671,12 +5224,157 @@ impl RecordFilter {
         op: &str,
         value: &Option<ColumnValue>,
         children: &Option<RecordFilters>,
-    ) -> Self {
-        Self {
+    ) -> Result<Self> {
+        match (field, children) {
+            (Some(field), None) => Self::validate_value(field, op, value.is_some())?,
+            (_, Some(children)) if children.0.is_empty() => {
+                return Err(Error::msg(format!(
+                    "Operator '{op}' requires at least one filter in the 'children' field"
+                )))
+            }
+            (None, None) => {
+                return Err(Error::msg(format!(
+                    "Operator '{op}' requires either a 'field' or a 'children' field"
+                )))
+            }
+            _ => (),
+        }
+        Ok(Self {
             field: field.to_owned(),
             op: op.to_owned(),
             value: value.clone(),
             children: children.clone(),
+            radius: None,
+            path: None,
+            values: None,
+        })
+    }
+
+    // Matches records whose point field lies within `radius` meters of the given coordinates
+    pub fn near(field: &str, lat: &f64, lng: &f64, radius: &f64) -> Self {
+        Self {
+            field: Some(field.to_owned()),
+            op: "$NEAR".to_owned(),
+            value: Some(ColumnValue::Point(Some((*lat, *lng)))),
+            children: None,
+            radius: Some(*radius),
+            path: None,
+            values: None,
+        }
+    }
+
+    // Compares the text found at `path` inside a json field, the value is always bound as text
+    pub fn json_path(
+        field: &str,
+        path: &[String],
+        op: &str,
+        value: &Option<String>,
+    ) -> Result<Self> {
+        Self::validate_value(field, op, value.is_some())?;
+        Ok(Self {
+            field: Some(field.to_owned()),
+            op: op.to_owned(),
+            value: value
+                .as_ref()
+                .map(|v| ColumnValue::String(Some(v.to_owned()))),
+            children: None,
+            radius: None,
+            path: Some(path.to_vec()),
+            values: None,
+        })
+    }
+
+    // Matches records whose field is equal to any of the given values
+    pub fn any_of(field: &str, values: &Vec<ColumnValue>) -> Result<Self> {
+        Self::many(field, "IN", values)
+    }
+
+    // Compares the field with a list of values, which IN and NOT IN need at least one of and the
+    // BETWEEN operators exactly two of
+    pub fn many(field: &str, op: &str, values: &[ColumnValue]) -> Result<Self> {
+        let upper_op = op.to_uppercase();
+        if LIST_OPERATOR.contains(&upper_op.as_str()) {
+            if values.is_empty() {
+                return Err(Error::msg(format!(
+                    "Operator '{op}' on field '{field}' requires at least one value"
+                )));
+            }
+        } else if RANGE_OPERATOR.contains(&upper_op.as_str()) {
+            if values.len() != 2 {
+                return Err(Error::msg(format!(
+                    "Operator '{op}' on field '{field}' requires exactly two values, the lower and upper bound"
+                )));
+            }
+        } else {
+            return Err(Error::msg(format!(
+                "Operator '{op}' on field '{field}' doesn't take a list of values"
+            )));
+        }
+        Ok(Self {
+            field: Some(field.to_owned()),
+            op: op.to_owned(),
+            value: None,
+            children: None,
+            radius: None,
+            path: None,
+            values: Some(values.to_vec()),
+        })
+    }
+
+    // Whether the operator compares the field with a list of values, see many
+    pub fn takes_values(op: &str) -> bool {
+        let op = op.to_uppercase();
+        LIST_OPERATOR.contains(&op.as_str()) || RANGE_OPERATOR.contains(&op.as_str())
+    }
+
+    // Filters on the virtual _created_at field become a range on _id, since a UUIDv7 sorts by the
+    // millisecond it was generated in. Timestamps are compared at that millisecond precision, and
+    // records whose _id isn't a UUIDv7 don't line up with these ranges.
+    pub fn created_at(op: &str, value: &Option<ColumnValue>) -> Result<Self> {
+        let id = Some("_id".to_owned());
+        let upper_op = op.to_uppercase();
+        if VALUELESS_OPERATOR.contains(&upper_op.as_str()) {
+            return Self::new(&id, op, value, &None);
+        }
+        let ms = match value {
+            Some(ColumnValue::Timestamp(Some(value))) => u64::try_from(value.timestamp_millis())
+                .ok()
+                .filter(|ms| *ms < 1 << 48)
+                .ok_or_else(|| Error::msg("Value of field '_created_at' is out of range"))?,
+            _ => {
+                return Err(Error::msg(format!(
+                    "Operator '{op}' on field '_created_at' requires a 'value' field"
+                )))
+            }
+        };
+        let first = Some(ColumnValue::Uuid(Some(Self::uuid_v7_bound(&ms, &false))));
+        let last = Some(ColumnValue::Uuid(Some(Self::uuid_v7_bound(&ms, &true))));
+        match upper_op.as_str() {
+            "=" => Self::new(
+                &None,
+                "AND",
+                &None,
+                &Some(RecordFilters(vec![
+                    Self::new(&id, ">=", &first, &None)?,
+                    Self::new(&id, "<=", &last, &None)?,
+                ])),
+            ),
+            "!=" | "<>" => Self::new(
+                &None,
+                "OR",
+                &None,
+                &Some(RecordFilters(vec![
+                    Self::new(&id, "<", &first, &None)?,
+                    Self::new(&id, ">", &last, &None)?,
+                ])),
+            ),
+            ">" => Self::new(&id, ">", &last, &None),
+            ">=" => Self::new(&id, ">=", &first, &None),
+            "<" => Self::new(&id, "<", &first, &None),
+            "<=" => Self::new(&id, "<=", &last, &None),
+            _ => Err(Error::msg(format!(
+                "Operator '{op}' is not supported on field '_created_at'"
+            ))),
         }
     }
 
@@ -2695,11 +5393,86 @@ impl RecordFilter {
     pub fn children(&self) -> &Option<RecordFilters> {
         &self.children
     }
+
+    pub fn radius(&self) -> &Option<f64> {
+        &self.radius
+    }
+
+    pub fn path(&self) -> &Option<Vec<String>> {
+        &self.path
+    }
+
+    pub fn values(&self) -> &Option<Vec<ColumnValue>> {
+        &self.values
+    }
+
+    fn validate_value(field: &str, op: &str, has_value: bool) -> Result<()> {
+        let op = op.to_uppercase();
+        if VALUELESS_OPERATOR.contains(&op.as_str()) {
+            if has_value {
+                return Err(Error::msg(format!(
+                    "Operator '{op}' on field '{field}' must not have a 'value' field"
+                )));
+            }
+        } else if !has_value {
+            return Err(Error::msg(format!(
+                "Operator '{op}' on field '{field}' requires a 'value' field"
+            )));
+        } else if Self::takes_values(&op) {
+            return Err(Error::msg(format!(
+                "Operator '{op}' on field '{field}' requires a list of values"
+            )));
+        }
+        Ok(())
+    }
+
+    // The comparison of a filter made by many, given the quoted column and the placeholders of
+    // its values
+    fn values_query(&self, column: &str, placeholders: &[String]) -> String {
+        let op = self.op.to_uppercase();
+        if RANGE_OPERATOR.contains(&op.as_str()) {
+            format!("{column} {op} {} AND {}", placeholders[0], placeholders[1])
+        } else {
+            format!("{column} {op} ({})", placeholders.join(", "))
+        }
+    }
+
+    // Lowest or highest UUIDv7 generated in the given millisecond. The version nibble is kept
+    // because ScyllaDB compares UUID versions before their bytes.
+    fn uuid_v7_bound(ms: &u64, last: &bool) -> Uuid {
+        let fill = if *last { 0xff } else { 0x00 };
+        let mut bytes = [fill; 16];
+        bytes[..6].copy_from_slice(&ms.to_be_bytes()[2..]);
+        bytes[6] = 0x70 | (fill & 0x0f);
+        Uuid::from_bytes(bytes)
+    }
+
+    // Path expression understood by JSON_EXTRACT on MySQL and json_extract on SQLite
+    fn json_path_expr(&self) -> String {
+        let mut json_path = "$".to_owned();
+        if let Some(path) = &self.path {
+            for key in path {
+                json_path += &format!(".\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\""));
+            }
+        }
+        json_path
+    }
+
+    fn near_point(&self) -> Option<(f64, f64, f64)> {
+        if let (Some(ColumnValue::Point(Some((lat, lng)))), Some(radius)) =
+            (&self.value, &self.radius)
+        {
+            Some((*lat, *lng, *radius))
+        } else {
+            None
+        }
+    }
 }
 
 pub struct RecordOrder {
     field: String,
     kind: String,
+    near: Option<(f64, f64)>,
 }
 
 impl RecordOrder {
@@ -2707,6 +5480,16 @@ impl RecordOrder {
         Self {
             field: field.to_owned(),
             kind: kind.to_owned(),
+            near: None,
+        }
+    }
+
+    // Orders records by the distance between their point field and the given coordinates
+    pub fn near(field: &str, kind: &str, lat: &f64, lng: &f64) -> Self {
+        Self {
+            field: field.to_owned(),
+            kind: kind.to_owned(),
+            near: Some((*lat, *lng)),
         }
     }
 
@@ -2717,6 +5500,10 @@ impl RecordOrder {
     pub fn kind(&self) -> &str {
         &self.kind
     }
+
+    pub fn near_point(&self) -> &Option<(f64, f64)> {
+        &self.near
+    }
 }
 
 pub struct RecordPagination {
@@ -2732,3 +5519,289 @@ impl RecordPagination {
         &self.limit
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json_path_filters() -> RecordFilters {
+        RecordFilters::new(&vec![RecordFilter::json_path(
+            "meta",
+            &["address".to_owned(), "city".to_owned()],
+            "=",
+            &Some("Bandung".to_owned()),
+        )
+        .unwrap()])
+    }
+
+    #[test]
+    fn postgresdb_json_path_filter_reads_the_document() {
+        let mut first_argument_idx = 1;
+        let filter = json_path_filters()
+            .postgresdb_filter_query(&None, 1, &mut first_argument_idx)
+            .unwrap();
+        assert_eq!(filter, "(\"meta\" #>> $1) = $2");
+        assert_eq!(first_argument_idx, 3);
+    }
+
+    #[test]
+    fn mysqldb_json_path_filter_reads_the_document() {
+        let filters = json_path_filters();
+        let filter = filters.mysqldb_filter_query(&None, 1).unwrap();
+        assert_eq!(filter, "JSON_UNQUOTE(JSON_EXTRACT(`meta`, ?)) = ?");
+        assert_eq!(filters.0[0].json_path_expr(), "$.\"address\".\"city\"");
+    }
+
+    #[test]
+    fn sqlitedb_json_path_filter_reads_the_document() {
+        let filter = json_path_filters().sqlitedb_filter_query(&None, 1).unwrap();
+        assert_eq!(
+            filter,
+            "CAST(json_extract(CAST(`meta` AS TEXT), ?) AS TEXT) = ?"
+        );
+    }
+
+    #[test]
+    fn json_path_filter_rejects_unsupported_operators() {
+        let filters = RecordFilters::new(&vec![RecordFilter::json_path(
+            "meta",
+            &["tags".to_owned()],
+            "CONTAINS",
+            &Some("a".to_owned()),
+        )
+        .unwrap()]);
+        assert!(filters.postgresdb_filter_query(&None, 1, &mut 1).is_err());
+        assert!(filters.mysqldb_filter_query(&None, 1).is_err());
+    }
+
+    #[test]
+    fn filter_value_is_required_unless_the_operator_is_valueless() {
+        let field = Some("name".to_owned());
+        let value = Some(ColumnValue::String(Some("a".to_owned())));
+        let cases = [
+            ("=", true, true),
+            ("=", false, false),
+            ("<>", false, false),
+            ("LIKE", false, false),
+            ("IS", false, false),
+            ("IS DISTINCT FROM", false, false),
+            ("IS NULL", false, true),
+            ("is null", false, true),
+            ("IS NOT NULL", false, true),
+            ("ISNULL", false, true),
+            ("NotNull", false, true),
+            ("IS TRUE", false, true),
+            ("IS NOT FALSE", false, true),
+            ("IS UNKNOWN", false, true),
+            ("IS NULL", true, false),
+            ("IS NOT TRUE", true, false),
+        ];
+        for (op, has_value, accepted) in cases {
+            let value = if has_value { &value } else { &None };
+            assert_eq!(
+                RecordFilter::new(&field, op, value, &None).is_ok(),
+                accepted,
+                "{op} with value: {has_value}"
+            );
+        }
+    }
+
+    #[test]
+    fn logical_filter_needs_no_value() {
+        let children = RecordFilters::new(&vec![RecordFilter::new(
+            &Some("name".to_owned()),
+            "IS NULL",
+            &None,
+            &None,
+        )
+        .unwrap()]);
+        assert!(RecordFilter::new(&None, "OR", &None, &Some(children)).is_ok());
+    }
+
+    #[test]
+    fn valueless_filter_binds_nothing() {
+        let filters = RecordFilters::new(&vec![RecordFilter::new(
+            &Some("name".to_owned()),
+            "IS NOT NULL",
+            &None,
+            &None,
+        )
+        .unwrap()]);
+        let mut first_argument_idx = 1;
+        let filter = filters
+            .postgresdb_filter_query(&None, 1, &mut first_argument_idx)
+            .unwrap();
+        assert_eq!(filter, "\"name\" IS NOT NULL");
+        assert_eq!(first_argument_idx, 1);
+    }
+
+    #[test]
+    fn list_and_range_filters_need_their_values() {
+        let value = |idx: i32| ColumnValue::Integer(Some(idx));
+        let cases = [
+            ("IN", 0, false),
+            ("IN", 1, true),
+            ("not in", 3, true),
+            ("BETWEEN", 1, false),
+            ("BETWEEN", 2, true),
+            ("NOT BETWEEN", 3, false),
+            ("BETWEEN SYMMETRIC", 2, true),
+            ("=", 2, false),
+            ("IS NULL", 1, false),
+        ];
+        for (op, count, accepted) in cases {
+            let values: Vec<_> = (0..count).map(value).collect();
+            assert_eq!(
+                RecordFilter::many("age", op, &values).is_ok(),
+                accepted,
+                "{op} with {count} values"
+            );
+        }
+
+        // A single value can't be bound to an operator that compares with a list
+        for op in ["IN", "NOT IN", "BETWEEN", "not between"] {
+            assert!(
+                RecordFilter::new(&Some("age".to_owned()), op, &Some(value(1)), &None).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn logical_filter_needs_children() {
+        for op in ["AND", "OR"] {
+            assert!(
+                RecordFilter::new(&None, op, &None, &Some(RecordFilters::new(&Vec::new())))
+                    .is_err()
+            );
+        }
+    }
+
+    // Built the way the REST API builds the filters of its requests
+    fn filter_from_json(json: &serde_json::Value) -> Result<RecordFilter> {
+        let field = json["field"].as_str().map(str::to_owned);
+        let op = json["op"].as_str().unwrap_or_default();
+        let children = match json.get("children") {
+            Some(serde_json::Value::Array(children)) => Some(RecordFilters(
+                children
+                    .iter()
+                    .map(filter_from_json)
+                    .collect::<Result<_>>()?,
+            )),
+            _ => None,
+        };
+        match (&field, json.get("value")) {
+            (Some(field), Some(serde_json::Value::Array(values)))
+                if RecordFilter::takes_values(op) =>
+            {
+                let values = values
+                    .iter()
+                    .map(|value| ColumnValue::from_serde_json(&ColumnKind::Int, value))
+                    .collect::<Result<Vec<_>>>()?;
+                RecordFilter::many(field, op, &values)
+            }
+            (_, value) => {
+                let value = value
+                    .map(|value| ColumnValue::from_serde_json(&ColumnKind::Int, value))
+                    .transpose()?;
+                RecordFilter::new(&field, op, &value, &children)
+            }
+        }
+    }
+
+    fn bound_values(filters: &RecordFilters) -> usize {
+        filters
+            .0
+            .iter()
+            .map(|f| {
+                usize::from(f.value.is_some())
+                    + f.values.as_ref().map_or(0, Vec::len)
+                    + f.children.as_ref().map_or(0, bound_values)
+            })
+            .sum()
+    }
+
+    // Every operator with every shape of value, alone and nested under a logical operator, either
+    // fails to build or builds a query that binds each of its values exactly once. SQLite also
+    // has to accept the query it gets.
+    #[tokio::test]
+    async fn malformed_filters_are_rejected_by_every_builder() {
+        use sqlx::{Connection, Executor};
+
+        let mut sqlite = sqlx::SqliteConnection::connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlite
+            .execute("CREATE TABLE \"records\" (\"age\" integer)")
+            .await
+            .unwrap();
+
+        let ops = [
+            "=",
+            "<",
+            "<>",
+            "IN",
+            "NOT IN",
+            "BETWEEN",
+            "NOT BETWEEN",
+            "IS NULL",
+            "LIKE",
+            "AND",
+            "OR",
+            "",
+            "DROP",
+        ];
+        let values = [
+            None,
+            Some(serde_json::json!(1)),
+            Some(serde_json::json!([])),
+            Some(serde_json::json!([1])),
+            Some(serde_json::json!([1, 2])),
+            Some(serde_json::json!([1, 2, 3])),
+            Some(serde_json::json!({ "a": 1 })),
+        ];
+        let mut jsons = Vec::new();
+        for op in ops {
+            for value in &values {
+                let mut json = serde_json::json!({ "field": "age", "op": op });
+                if let Some(value) = value {
+                    json["value"] = value.clone();
+                }
+                jsons.push(serde_json::json!({ "op": "AND", "children": [json.clone()] }));
+                jsons.push(
+                    serde_json::json!({ "op": "OR", "children": [json.clone(), json.clone()] }),
+                );
+                jsons.push(json);
+            }
+            jsons.push(serde_json::json!({ "op": op, "children": [] }));
+            jsons.push(serde_json::json!({ "op": op }));
+        }
+
+        for json in &jsons {
+            let filters = match filter_from_json(json) {
+                Ok(filter) => RecordFilters::new(&vec![filter]),
+                Err(_) => continue,
+            };
+            let values = bound_values(&filters);
+
+            if let Ok(filter) = filters.scylladb_filter_query(&None, 0) {
+                assert_eq!(filter.matches('?').count(), values, "ScyllaDB: {json}");
+            }
+            let mut first_argument_idx = 1;
+            if let Ok(filter) = filters.postgresdb_filter_query(&None, 1, &mut first_argument_idx) {
+                assert!(!filter.contains("()"), "PostgreSQL: {json}");
+                assert_eq!(first_argument_idx - 1, values, "PostgreSQL: {json}");
+            }
+            if let Ok(filter) = filters.mysqldb_filter_query(&None, 1) {
+                assert!(!filter.contains("()"), "MySQL: {json}");
+                assert_eq!(filter.matches('?').count(), values, "MySQL: {json}");
+            }
+            if let Ok(filter) = filters.sqlitedb_filter_query(&None, 1) {
+                assert_eq!(filter.matches('?').count(), values, "SQLite: {json}");
+                let query = format!("SELECT 1 FROM \"records\" WHERE {filter}");
+                if let Err(err) = sqlite.prepare(query.as_str()).await {
+                    panic!("SQLite: {json}: {query}: {err}");
+                }
+            }
+        }
+    }
+}
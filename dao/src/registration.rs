@@ -5,6 +5,7 @@ use hb_db_postgresql::model::registration::RegistrationModel as RegistrationPost
 use hb_db_scylladb::model::registration::RegistrationModel as RegistrationScyllaModel;
 use hb_db_sqlite::model::registration::RegistrationModel as RegistrationSqliteModel;
 use rand::{thread_rng, Rng};
+use tracing::instrument;
 use uuid::Uuid;
 
 use crate::{util::conversion, Db};
@@ -59,6 +60,7 @@ impl RegistrationDao {
         self.code = thread_rng().gen_range(100000..=999999).to_string();
     }
 
+    #[instrument(name = "RegistrationDao::db_insert", skip_all)]
     pub async fn db_insert(&self, db: &Db) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.insert_registration(&self.to_scylladb_model()).await,
@@ -68,6 +70,7 @@ impl RegistrationDao {
         }
     }
 
+    #[instrument(name = "RegistrationDao::db_select", skip_all)]
     pub async fn db_select(db: &Db, id: &Uuid) -> Result<Self> {
         match db {
             Db::ScyllaDb(db) => Self::from_scylladb_model(&db.select_registration(id).await?),
@@ -81,6 +84,7 @@ impl RegistrationDao {
         }
     }
 
+    #[instrument(name = "RegistrationDao::db_select_by_email", skip_all)]
     pub async fn db_select_by_email(db: &Db, email: &str) -> Result<Self> {
         match db {
             Db::ScyllaDb(db) => {
@@ -98,6 +102,7 @@ impl RegistrationDao {
         }
     }
 
+    #[instrument(name = "RegistrationDao::db_update", skip_all)]
     pub async fn db_update(&mut self, db: &Db) -> Result<()> {
         self.updated_at = Utc::now();
         match db {
@@ -108,6 +113,7 @@ impl RegistrationDao {
         }
     }
 
+    #[instrument(name = "RegistrationDao::db_delete", skip_all)]
     pub async fn db_delete(&self, db: &Db) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.delete_registration(&self.id).await,
@@ -0,0 +1,211 @@
+use anyhow::{Error, Result};
+use chrono::{DateTime, Duration, Utc};
+use hb_db_mysql::model::sign_in_attempt::SignInAttemptModel as SignInAttemptMysqlModel;
+use hb_db_postgresql::model::sign_in_attempt::SignInAttemptModel as SignInAttemptPostgresModel;
+use hb_db_scylladb::model::sign_in_attempt::SignInAttemptModel as SignInAttemptScyllaModel;
+use hb_db_sqlite::model::sign_in_attempt::SignInAttemptModel as SignInAttemptSqliteModel;
+use tracing::instrument;
+
+use crate::{error::DaoError, util::conversion, Db};
+
+// Failed sign-ins counted under a key, which is either an admin email or a source IP
+pub struct SignInAttemptDao {
+    key: String,
+    failed_count: i32,
+    locked_until: Option<DateTime<Utc>>,
+    updated_at: DateTime<Utc>,
+}
+
+impl SignInAttemptDao {
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.to_owned(),
+            failed_count: 0,
+            locked_until: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn failed_count(&self) -> &i32 {
+        &self.failed_count
+    }
+
+    pub fn locked_until(&self) -> &Option<DateTime<Utc>> {
+        &self.locked_until
+    }
+
+    pub fn updated_at(&self) -> &DateTime<Utc> {
+        &self.updated_at
+    }
+
+    // Seconds left until the lock is lifted, none when it isn't locked
+    pub fn locked_for(&self) -> Option<i64> {
+        let locked_until = self.locked_until?;
+        let seconds = (locked_until - Utc::now()).num_seconds();
+        if seconds > 0 {
+            Some(seconds)
+        } else {
+            None
+        }
+    }
+
+    // Counts a failed sign-in and returns whether it's the one that locks the key. Failures older
+    // than the lockout duration, or from before an expired lock, start a new count.
+    pub fn record_failure(&mut self, max_attempts: &u32, lockout_duration: &u32) -> Result<bool> {
+        let now = Utc::now();
+        let lockout_duration = Duration::try_seconds(i64::from(*lockout_duration))
+            .ok_or_else(|| Error::msg("sign_in_lockout_duration is out of range."))?;
+
+        if self
+            .locked_until
+            .is_some_and(|locked_until| locked_until <= now)
+            || self.updated_at + lockout_duration < now
+        {
+            self.failed_count = 0;
+            self.locked_until = None;
+        }
+
+        self.failed_count += 1;
+        self.updated_at = now;
+
+        if self.locked_until.is_none() && i64::from(self.failed_count) >= i64::from(*max_attempts) {
+            self.locked_until = Some(now + lockout_duration);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    #[instrument(name = "SignInAttemptDao::db_upsert", skip_all)]
+    pub async fn db_upsert(&self, db: &Db) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => db.upsert_sign_in_attempt(&self.to_scylladb_model()).await,
+            Db::PostgresqlDb(db) => db.upsert_sign_in_attempt(&self.to_postgresdb_model()).await,
+            Db::MysqlDb(db) => db.upsert_sign_in_attempt(&self.to_mysqldb_model()).await,
+            Db::SqliteDb(db) => db.upsert_sign_in_attempt(&self.to_sqlitedb_model()).await,
+        }
+    }
+
+    #[instrument(name = "SignInAttemptDao::db_select", skip_all)]
+    pub async fn db_select(db: &Db, key: &str) -> Result<Self> {
+        let not_found =
+            |err| DaoError::from_no_rows(err, format!("Sign-in attempts of '{key}' are not found"));
+        match db {
+            Db::ScyllaDb(db) => {
+                Self::from_scylladb_model(&db.select_sign_in_attempt(key).await.map_err(not_found)?)
+            }
+            Db::PostgresqlDb(db) => Ok(Self::from_postgresdb_model(
+                &db.select_sign_in_attempt(key).await.map_err(not_found)?,
+            )),
+            Db::MysqlDb(db) => Ok(Self::from_mysqldb_model(
+                &db.select_sign_in_attempt(key).await.map_err(not_found)?,
+            )),
+            Db::SqliteDb(db) => Ok(Self::from_sqlitedb_model(
+                &db.select_sign_in_attempt(key).await.map_err(not_found)?,
+            )),
+        }
+    }
+
+    // Falls back to an empty count for keys without any failed sign-in yet
+    #[instrument(name = "SignInAttemptDao::db_select_or_new", skip_all)]
+    pub async fn db_select_or_new(db: &Db, key: &str) -> Result<Self> {
+        match Self::db_select(db, key).await {
+            Ok(data) => Ok(data),
+            Err(err) if DaoError::is_not_found(&err) => Ok(Self::new(key)),
+            Err(err) => Err(err),
+        }
+    }
+
+    #[instrument(name = "SignInAttemptDao::db_delete", skip_all)]
+    pub async fn db_delete(db: &Db, key: &str) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => db.delete_sign_in_attempt(key).await,
+            Db::PostgresqlDb(db) => db.delete_sign_in_attempt(key).await,
+            Db::MysqlDb(db) => db.delete_sign_in_attempt(key).await,
+            Db::SqliteDb(db) => db.delete_sign_in_attempt(key).await,
+        }
+    }
+
+    fn from_scylladb_model(model: &SignInAttemptScyllaModel) -> Result<Self> {
+        Ok(Self {
+            key: model.key().to_owned(),
+            failed_count: *model.failed_count(),
+            locked_until: match model.locked_until() {
+                Some(locked_until) => Some(conversion::scylla_cql_timestamp_to_datetime_utc(
+                    locked_until,
+                )?),
+                None => None,
+            },
+            updated_at: conversion::scylla_cql_timestamp_to_datetime_utc(model.updated_at())?,
+        })
+    }
+
+    fn to_scylladb_model(&self) -> SignInAttemptScyllaModel {
+        SignInAttemptScyllaModel::new(
+            &self.key,
+            &self.failed_count,
+            &self
+                .locked_until
+                .as_ref()
+                .map(conversion::datetime_utc_to_scylla_cql_timestamp),
+            &conversion::datetime_utc_to_scylla_cql_timestamp(&self.updated_at),
+        )
+    }
+
+    fn from_postgresdb_model(model: &SignInAttemptPostgresModel) -> Self {
+        Self {
+            key: model.key().to_owned(),
+            failed_count: *model.failed_count(),
+            locked_until: *model.locked_until(),
+            updated_at: *model.updated_at(),
+        }
+    }
+
+    fn to_postgresdb_model(&self) -> SignInAttemptPostgresModel {
+        SignInAttemptPostgresModel::new(
+            &self.key,
+            &self.failed_count,
+            &self.locked_until,
+            &self.updated_at,
+        )
+    }
+
+    fn from_mysqldb_model(model: &SignInAttemptMysqlModel) -> Self {
+        Self {
+            key: model.key().to_owned(),
+            failed_count: *model.failed_count(),
+            locked_until: *model.locked_until(),
+            updated_at: *model.updated_at(),
+        }
+    }
+
+    fn to_mysqldb_model(&self) -> SignInAttemptMysqlModel {
+        SignInAttemptMysqlModel::new(
+            &self.key,
+            &self.failed_count,
+            &self.locked_until,
+            &self.updated_at,
+        )
+    }
+
+    fn from_sqlitedb_model(model: &SignInAttemptSqliteModel) -> Self {
+        Self {
+            key: model.key().to_owned(),
+            failed_count: *model.failed_count(),
+            locked_until: *model.locked_until(),
+            updated_at: *model.updated_at(),
+        }
+    }
+
+    fn to_sqlitedb_model(&self) -> SignInAttemptSqliteModel {
+        SignInAttemptSqliteModel::new(
+            &self.key,
+            &self.failed_count,
+            &self.locked_until,
+            &self.updated_at,
+        )
+    }
+}
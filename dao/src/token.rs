@@ -6,16 +6,26 @@ use hb_db_scylladb::model::token::TokenModel as TokenScyllaModel;
 use hb_db_sqlite::model::token::TokenModel as TokenSqliteModel;
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::instrument;
 use uuid::Uuid;
 
 use crate::{
-    bucket_rule::{BucketPermission, BucketRuleDao},
-    collection_rule::{CollectionPermission, CollectionRuleDao},
+    bucket::BucketDao,
+    bucket_rule::{BucketPermission, BucketRuleDao, ALL_BUCKETS_ID},
+    cache::TtlCache,
+    collection::CollectionDao,
+    collection_rule::{CollectionPermission, CollectionRuleDao, ALL_COLLECTIONS_ID},
+    error::DaoError,
+    log::{LogDao, LogKind},
     util::conversion,
     Db,
 };
 
-#[derive(Deserialize, Serialize)]
+// Every request made with a token looks it up, see cache::init
+static CACHE: TtlCache<TokenDao> = TtlCache::new();
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct TokenDao {
     id: Uuid,
     created_at: DateTime<Utc>,
@@ -26,6 +36,14 @@ pub struct TokenDao {
     token: String,
     allow_anonymous: bool,
     expired_at: Option<DateTime<Utc>>,
+    disabled_at: Option<DateTime<Utc>>,
+    last_used_at: Option<DateTime<Utc>>,
+    request_count: i64,
+
+    // Only the hash of the token is stored, the plain one is kept here right after it is
+    // generated so it can be shown once
+    #[serde(skip)]
+    plain_token: Option<String>,
 }
 
 impl TokenDao {
@@ -38,6 +56,7 @@ impl TokenDao {
         expired_at: &Option<DateTime<Utc>>,
     ) -> Self {
         let now = Utc::now();
+        let plain_token = Self::generate_token(token_length);
         Self {
             id: Uuid::now_v7(),
             created_at: now,
@@ -45,13 +64,13 @@ impl TokenDao {
             project_id: *project_id,
             admin_id: *admin_id,
             name: name.to_owned(),
-            token: thread_rng()
-                .sample_iter(&Alphanumeric)
-                .take(*token_length)
-                .map(char::from)
-                .collect(),
+            token: Self::hash_token(&plain_token),
             allow_anonymous: *allow_anonymous,
             expired_at: *expired_at,
+            disabled_at: None,
+            last_used_at: None,
+            request_count: 0,
+            plain_token: Some(plain_token),
         }
     }
 
@@ -90,8 +109,12 @@ impl TokenDao {
         &self.name
     }
 
-    pub fn token(&self) -> &str {
-        &self.token
+    pub fn disabled_at(&self) -> &Option<DateTime<Utc>> {
+        &self.disabled_at
+    }
+
+    pub fn plain_token(&self) -> &Option<String> {
+        &self.plain_token
     }
 
     pub fn allow_anonymous(&self) -> &bool {
@@ -102,6 +125,29 @@ impl TokenDao {
         &self.expired_at
     }
 
+    pub fn last_used_at(&self) -> &Option<DateTime<Utc>> {
+        &self.last_used_at
+    }
+
+    pub fn request_count(&self) -> &i64 {
+        &self.request_count
+    }
+
+    pub fn is_token_matched(&self, token: &str) -> bool {
+        Self::hash_token(token) == self.token
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match &self.expired_at {
+            Some(expired_at) => expired_at <= &Utc::now(),
+            None => false,
+        }
+    }
+
+    pub fn set_id(&mut self, id: &Uuid) {
+        self.id = *id;
+    }
+
     pub fn set_admin_id(&mut self, admin_id: &Uuid) {
         self.admin_id = *admin_id;
     }
@@ -114,147 +160,122 @@ impl TokenDao {
         self.allow_anonymous = *allow_anonymous;
     }
 
+    // A new expiry date enables the token again if it was disabled after expiring
     pub fn set_expired_at(&mut self, expired_at: &Option<DateTime<Utc>>) {
         self.expired_at = *expired_at;
+        self.disabled_at = None;
+    }
+
+    // Replaces the token so the previous one can't be used anymore
+    pub fn regenerate_token(&mut self, token_length: &usize) {
+        let plain_token = Self::generate_token(token_length);
+        self.token = Self::hash_token(&plain_token);
+        self.plain_token = Some(plain_token);
     }
 
+    #[instrument(name = "TokenDao::is_allow_find_one_file", skip_all)]
     pub async fn is_allow_find_one_file(
         &self,
         db: &Db,
         bucket_id: &Uuid,
     ) -> Option<BucketPermission> {
-        if let Ok(bucket_rule_data) =
-            BucketRuleDao::db_select_by_token_id_and_bucket_id(db, &self.id, bucket_id).await
-        {
-            Some(*bucket_rule_data.find_one())
-        } else {
-            None
-        }
+        self.bucket_rule(db, bucket_id)
+            .await
+            .map(|bucket_rule_data| *bucket_rule_data.find_one())
     }
 
+    #[instrument(name = "TokenDao::is_allow_find_many_files", skip_all)]
     pub async fn is_allow_find_many_files(
         &self,
         db: &Db,
         bucket_id: &Uuid,
     ) -> Option<BucketPermission> {
-        if let Ok(bucket_rule_data) =
-            BucketRuleDao::db_select_by_token_id_and_bucket_id(db, &self.id, bucket_id).await
-        {
-            Some(*bucket_rule_data.find_many())
-        } else {
-            None
-        }
+        self.bucket_rule(db, bucket_id)
+            .await
+            .map(|bucket_rule_data| *bucket_rule_data.find_many())
     }
 
+    #[instrument(name = "TokenDao::is_allow_insert_file", skip_all)]
     pub async fn is_allow_insert_file(&self, db: &Db, bucket_id: &Uuid) -> bool {
-        if let Ok(bucket_rule_data) =
-            BucketRuleDao::db_select_by_token_id_and_bucket_id(db, &self.id, bucket_id).await
-        {
-            *bucket_rule_data.insert_one()
-        } else {
-            false
-        }
+        self.bucket_rule(db, bucket_id)
+            .await
+            .is_some_and(|bucket_rule_data| *bucket_rule_data.insert_one())
     }
 
+    #[instrument(name = "TokenDao::is_allow_update_file", skip_all)]
     pub async fn is_allow_update_file(
         &self,
         db: &Db,
         bucket_id: &Uuid,
     ) -> Option<BucketPermission> {
-        if let Ok(bucket_rule_data) =
-            BucketRuleDao::db_select_by_token_id_and_bucket_id(db, &self.id, bucket_id).await
-        {
-            Some(*bucket_rule_data.update_one())
-        } else {
-            None
-        }
+        self.bucket_rule(db, bucket_id)
+            .await
+            .map(|bucket_rule_data| *bucket_rule_data.update_one())
     }
 
+    #[instrument(name = "TokenDao::is_allow_delete_file", skip_all)]
     pub async fn is_allow_delete_file(
         &self,
         db: &Db,
         bucket_id: &Uuid,
     ) -> Option<BucketPermission> {
-        if let Ok(bucket_rule_data) =
-            BucketRuleDao::db_select_by_token_id_and_bucket_id(db, &self.id, bucket_id).await
-        {
-            Some(*bucket_rule_data.update_one())
-        } else {
-            None
-        }
+        self.bucket_rule(db, bucket_id)
+            .await
+            .map(|bucket_rule_data| *bucket_rule_data.update_one())
     }
 
+    #[instrument(name = "TokenDao::is_allow_find_one_record", skip_all)]
     pub async fn is_allow_find_one_record(
         &self,
         db: &Db,
         collection_id: &Uuid,
     ) -> Option<CollectionPermission> {
-        if let Ok(collection_rule_data) =
-            CollectionRuleDao::db_select_by_token_id_and_collection_id(db, &self.id, collection_id)
-                .await
-        {
-            Some(*collection_rule_data.find_one())
-        } else {
-            None
-        }
+        self.collection_rule(db, collection_id)
+            .await
+            .map(|collection_rule_data| *collection_rule_data.find_one())
     }
 
+    #[instrument(name = "TokenDao::is_allow_find_many_records", skip_all)]
     pub async fn is_allow_find_many_records(
         &self,
         db: &Db,
         collection_id: &Uuid,
     ) -> Option<CollectionPermission> {
-        if let Ok(collection_rule_data) =
-            CollectionRuleDao::db_select_by_token_id_and_collection_id(db, &self.id, collection_id)
-                .await
-        {
-            Some(*collection_rule_data.find_many())
-        } else {
-            None
-        }
+        self.collection_rule(db, collection_id)
+            .await
+            .map(|collection_rule_data| *collection_rule_data.find_many())
     }
 
+    #[instrument(name = "TokenDao::is_allow_insert_record", skip_all)]
     pub async fn is_allow_insert_record(&self, db: &Db, collection_id: &Uuid) -> bool {
-        if let Ok(collection_rule_data) =
-            CollectionRuleDao::db_select_by_token_id_and_collection_id(db, &self.id, collection_id)
-                .await
-        {
-            *collection_rule_data.insert_one()
-        } else {
-            false
-        }
+        self.collection_rule(db, collection_id)
+            .await
+            .is_some_and(|collection_rule_data| *collection_rule_data.insert_one())
     }
 
+    #[instrument(name = "TokenDao::is_allow_update_record", skip_all)]
     pub async fn is_allow_update_record(
         &self,
         db: &Db,
         collection_id: &Uuid,
     ) -> Option<CollectionPermission> {
-        if let Ok(collection_rule_data) =
-            CollectionRuleDao::db_select_by_token_id_and_collection_id(db, &self.id, collection_id)
-                .await
-        {
-            Some(*collection_rule_data.update_one())
-        } else {
-            None
-        }
+        self.collection_rule(db, collection_id)
+            .await
+            .map(|collection_rule_data| *collection_rule_data.update_one())
     }
 
+    #[instrument(name = "TokenDao::is_allow_delete_record", skip_all)]
     pub async fn is_allow_delete_record(
         &self,
         db: &Db,
         collection_id: &Uuid,
     ) -> Option<CollectionPermission> {
-        if let Ok(collection_rule_data) =
-            CollectionRuleDao::db_select_by_token_id_and_collection_id(db, &self.id, collection_id)
-                .await
-        {
-            Some(*collection_rule_data.delete_one())
-        } else {
-            None
-        }
+        self.collection_rule(db, collection_id)
+            .await
+            .map(|collection_rule_data| *collection_rule_data.delete_one())
     }
 
+    #[instrument(name = "TokenDao::db_insert", skip_all)]
     pub async fn db_insert(&self, db: &Db) -> Result<()> {
         match db {
             Db::ScyllaDb(db) => db.insert_token(&self.to_scylladb_model()).await,
@@ -264,15 +285,114 @@ impl TokenDao {
         }
     }
 
+    // The token and its rules are inserted in one transaction, so a token is never left with only
+    // some of its rules. ScyllaDB has no transactions, there a failure halfway leaves the token
+    // and the rules before it inserted.
+    #[instrument(name = "TokenDao::db_insert_with_rules", skip_all)]
+    pub async fn db_insert_with_rules(
+        &self,
+        db: &Db,
+        collection_rules_data: &[CollectionRuleDao],
+        bucket_rules_data: &[BucketRuleDao],
+    ) -> Result<()> {
+        let tx = db.begin().await?;
+        self.db_insert(&tx).await?;
+        for collection_rule_data in collection_rules_data {
+            collection_rule_data.db_insert(&tx).await?;
+        }
+        for bucket_rule_data in bucket_rules_data {
+            bucket_rule_data.db_insert(&tx).await?;
+        }
+        tx.commit().await
+    }
+
+    #[instrument(name = "TokenDao::db_select", skip_all)]
     pub async fn db_select(db: &Db, id: &Uuid) -> Result<Self> {
+        if let Some(token_data) = CACHE.get(id) {
+            return Ok(token_data);
+        }
+        let token_data = Self::select(db, id).await?;
+        CACHE.insert(id, &token_data);
+        Ok(token_data)
+    }
+
+    async fn select(db: &Db, id: &Uuid) -> Result<Self> {
+        let not_found = |err| DaoError::from_no_rows(err, format!("Token '{id}' is not found"));
         match db {
-            Db::ScyllaDb(db) => Self::from_scylladb_model(&db.select_token(id).await?),
-            Db::PostgresqlDb(db) => Ok(Self::from_postgresdb_model(&db.select_token(id).await?)),
-            Db::MysqlDb(db) => Ok(Self::from_mysqldb_model(&db.select_token(id).await?)),
-            Db::SqliteDb(db) => Ok(Self::from_sqlitedb_model(&db.select_token(id).await?)),
+            Db::ScyllaDb(db) => {
+                let (token, request_count) =
+                    tokio::try_join!(db.select_token(id), db.select_token_request_count(id))
+                        .map_err(not_found)?;
+                Self::from_scylladb_model(&token, &request_count)
+            }
+            Db::PostgresqlDb(db) => Ok(Self::from_postgresdb_model(
+                &db.select_token(id).await.map_err(not_found)?,
+            )),
+            Db::MysqlDb(db) => Ok(Self::from_mysqldb_model(
+                &db.select_token(id).await.map_err(not_found)?,
+            )),
+            Db::SqliteDb(db) => Ok(Self::from_sqlitedb_model(
+                &db.select_token(id).await.map_err(not_found)?,
+            )),
+        }
+    }
+
+    #[instrument(name = "TokenDao::db_select_unexpired", skip_all)]
+    pub async fn db_select_unexpired(db: &Db, id: &Uuid) -> Result<Self> {
+        let mut token_data = Self::db_select(db, id).await?;
+        token_data.check_expired(db).await?;
+        Ok(token_data)
+    }
+
+    #[instrument(name = "TokenDao::db_select_by_token", skip_all)]
+    pub async fn db_select_by_token(db: &Db, token: &str) -> Result<Self> {
+        let token = Self::hash_token(token);
+        match db {
+            Db::ScyllaDb(db) => {
+                let token = db.select_token_by_token(&token).await?;
+                let request_count = db.select_token_request_count(token.id()).await?;
+                Self::from_scylladb_model(&token, &request_count)
+            }
+            Db::PostgresqlDb(db) => Ok(Self::from_postgresdb_model(
+                &db.select_token_by_token(&token).await?,
+            )),
+            Db::MysqlDb(db) => Ok(Self::from_mysqldb_model(
+                &db.select_token_by_token(&token).await?,
+            )),
+            Db::SqliteDb(db) => Ok(Self::from_sqlitedb_model(
+                &db.select_token_by_token(&token).await?,
+            )),
         }
     }
 
+    // Fails with DaoError::Expired once the token is past its expiry date. The first use after
+    // that disables the token and warns about it in the project logs
+    #[instrument(name = "TokenDao::check_expired", skip_all)]
+    pub async fn check_expired(&mut self, db: &Db) -> Result<()> {
+        if !self.is_expired() {
+            return Ok(());
+        }
+
+        if self.disabled_at.is_none() {
+            self.disabled_at = Some(Utc::now());
+            self.db_update(db).await?;
+            LogDao::new(
+                &self.admin_id,
+                &self.project_id,
+                &LogKind::Warn,
+                &format!(
+                    "Token '{}' ({}) was used after it expired and has been disabled",
+                    self.name, self.id
+                ),
+            )
+            .db_insert(db)
+            .await?;
+        }
+
+        Err(DaoError::Expired(format!("Token {} has expired", self.id)).into())
+    }
+
+    #[instrument(name = "TokenDao::db_select_many_by_admin_id_and_project_id", skip_all)]
     pub async fn db_select_many_by_admin_id_and_project_id(
         db: &Db,
         admin_id: &Uuid,
@@ -285,7 +405,9 @@ impl TokenDao {
                     .select_many_tokens_by_admin_id_and_project_id(admin_id, project_id)
                     .await?
                 {
-                    tokens_data.push(Self::from_scylladb_model(&token?)?);
+                    let token = token?;
+                    let request_count = db.select_token_request_count(token.id()).await?;
+                    tokens_data.push(Self::from_scylladb_model(&token, &request_count)?);
                 }
                 Ok(tokens_data)
             }
@@ -310,12 +432,15 @@ impl TokenDao {
         }
     }
 
+    #[instrument(name = "TokenDao::db_select_many_by_project_id", skip_all)]
     pub async fn db_select_many_by_project_id(db: &Db, project_id: &Uuid) -> Result<Vec<Self>> {
         match db {
             Db::ScyllaDb(db) => {
                 let mut tokens_data = Vec::new();
                 for token in db.select_many_tokens_by_project_id(project_id).await? {
-                    tokens_data.push(Self::from_scylladb_model(&token?)?);
+                    let token = token?;
+                    let request_count = db.select_token_request_count(token.id()).await?;
+                    tokens_data.push(Self::from_scylladb_model(&token, &request_count)?);
                 }
                 Ok(tokens_data)
             }
@@ -340,8 +465,10 @@ impl TokenDao {
         }
     }
 
+    #[instrument(name = "TokenDao::db_update", skip_all)]
     pub async fn db_update(&mut self, db: &Db) -> Result<()> {
         self.updated_at = Utc::now();
+        CACHE.remove(&self.id);
         match db {
             Db::ScyllaDb(db) => db.update_token(&self.to_scylladb_model()).await,
             Db::PostgresqlDb(db) => db.update_token(&self.to_postgresdb_model()).await,
@@ -350,21 +477,111 @@ impl TokenDao {
         }
     }
 
-    pub async fn db_delete(db: &Db, id: &Uuid) -> Result<()> {
-        tokio::try_join!(
-            CollectionRuleDao::db_delete_many_by_token_id(db, id),
-            BucketRuleDao::db_delete_many_by_token_id(db, id)
-        )?;
+    // Adds the requests counted since the last flush, see TokenUsage
+    #[instrument(name = "TokenDao::db_increment_usage", skip_all)]
+    pub async fn db_increment_usage(
+        db: &Db,
+        id: &Uuid,
+        last_used_at: &DateTime<Utc>,
+        requests: &i64,
+    ) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => {
+                db.increment_token_usage(
+                    id,
+                    &conversion::datetime_utc_to_scylla_cql_timestamp(last_used_at),
+                    requests,
+                )
+                .await
+            }
+            Db::PostgresqlDb(db) => db.increment_token_usage(id, last_used_at, requests).await,
+            Db::MysqlDb(db) => db.increment_token_usage(id, last_used_at, requests).await,
+            Db::SqliteDb(db) => db.increment_token_usage(id, last_used_at, requests).await,
+        }
+    }
 
+    // The SQL backends delete the rules of the token in the same transaction
+    #[instrument(name = "TokenDao::db_delete", skip_all)]
+    pub async fn db_delete(db: &Db, id: &Uuid) -> Result<()> {
+        CACHE.remove(id);
         match db {
-            Db::ScyllaDb(db) => db.delete_token(id).await,
+            Db::ScyllaDb(scylla_db) => {
+                tokio::try_join!(
+                    CollectionRuleDao::db_delete_many_by_token_id(db, id),
+                    BucketRuleDao::db_delete_many_by_token_id(db, id)
+                )?;
+                scylla_db.delete_token(id).await
+            }
             Db::PostgresqlDb(db) => db.delete_token(id).await,
             Db::MysqlDb(db) => db.delete_token(id).await,
             Db::SqliteDb(db) => db.delete_token(id).await,
         }
     }
 
-    fn from_scylladb_model(model: &TokenScyllaModel) -> Result<Self> {
+    fn generate_token(token_length: &usize) -> String {
+        thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(*token_length)
+            .map(char::from)
+            .collect()
+    }
+
+    fn hash_token(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+
+    // The rule of the bucket itself, or else the wildcard rule of the token as long as the bucket
+    // is in the same project as the token
+    async fn bucket_rule(&self, db: &Db, bucket_id: &Uuid) -> Option<BucketRuleDao> {
+        if let Ok(bucket_rule_data) =
+            BucketRuleDao::db_select_by_token_id_and_bucket_id(db, &self.id, bucket_id).await
+        {
+            return Some(bucket_rule_data);
+        }
+
+        let bucket_rule_data =
+            BucketRuleDao::db_select_by_token_id_and_bucket_id(db, &self.id, &ALL_BUCKETS_ID)
+                .await
+                .ok()?;
+        let bucket_data = BucketDao::db_select(db, bucket_id).await.ok()?;
+        if bucket_data.project_id() == &self.project_id {
+            Some(bucket_rule_data)
+        } else {
+            None
+        }
+    }
+
+    // Same fallback as for buckets. Public since record handlers also need the readable and
+    // writable fields of the rule.
+    #[instrument(name = "TokenDao::collection_rule", skip_all)]
+    pub async fn collection_rule(
+        &self,
+        db: &Db,
+        collection_id: &Uuid,
+    ) -> Option<CollectionRuleDao> {
+        if let Ok(collection_rule_data) =
+            CollectionRuleDao::db_select_by_token_id_and_collection_id(db, &self.id, collection_id)
+                .await
+        {
+            return Some(collection_rule_data);
+        }
+
+        let collection_rule_data = CollectionRuleDao::db_select_by_token_id_and_collection_id(
+            db,
+            &self.id,
+            &ALL_COLLECTIONS_ID,
+        )
+        .await
+        .ok()?;
+        let collection_data = CollectionDao::db_select(db, collection_id).await.ok()?;
+        if collection_data.project_id() == &self.project_id {
+            Some(collection_rule_data)
+        } else {
+            None
+        }
+    }
+
+    fn from_scylladb_model(model: &TokenScyllaModel, request_count: &i64) -> Result<Self> {
         Ok(Self {
             id: *model.id(),
             created_at: conversion::scylla_cql_timestamp_to_datetime_utc(model.created_at())?,
@@ -380,6 +597,20 @@ impl TokenDao {
                 )?),
                 None => None,
             },
+            disabled_at: match &model.disabled_at() {
+                Some(disabled_at) => Some(conversion::scylla_cql_timestamp_to_datetime_utc(
+                    disabled_at,
+                )?),
+                None => None,
+            },
+            last_used_at: match &model.last_used_at() {
+                Some(last_used_at) => Some(conversion::scylla_cql_timestamp_to_datetime_utc(
+                    last_used_at,
+                )?),
+                None => None,
+            },
+            request_count: *request_count,
+            plain_token: None,
         })
     }
 
@@ -399,6 +630,18 @@ impl TokenDao {
                 }
                 None => None,
             },
+            &match &self.disabled_at {
+                Some(disabled_at) => Some(conversion::datetime_utc_to_scylla_cql_timestamp(
+                    disabled_at,
+                )),
+                None => None,
+            },
+            &match &self.last_used_at {
+                Some(last_used_at) => Some(conversion::datetime_utc_to_scylla_cql_timestamp(
+                    last_used_at,
+                )),
+                None => None,
+            },
         )
     }
 
@@ -413,6 +656,10 @@ impl TokenDao {
             token: model.token().to_owned(),
             allow_anonymous: *model.allow_anonymous(),
             expired_at: *model.expired_at(),
+            disabled_at: *model.disabled_at(),
+            last_used_at: *model.last_used_at(),
+            request_count: *model.request_count(),
+            plain_token: None,
         }
     }
 
@@ -427,6 +674,9 @@ impl TokenDao {
             &self.token,
             &self.allow_anonymous,
             &self.expired_at,
+            &self.disabled_at,
+            &self.last_used_at,
+            &self.request_count,
         )
     }
 
@@ -441,6 +691,10 @@ impl TokenDao {
             token: model.token().to_owned(),
             allow_anonymous: *model.allow_anonymous(),
             expired_at: *model.expired_at(),
+            disabled_at: *model.disabled_at(),
+            last_used_at: *model.last_used_at(),
+            request_count: *model.request_count(),
+            plain_token: None,
         }
     }
 
@@ -455,6 +709,9 @@ impl TokenDao {
             &self.token,
             &self.allow_anonymous,
             &self.expired_at,
+            &self.disabled_at,
+            &self.last_used_at,
+            &self.request_count,
         )
     }
 
@@ -469,6 +726,10 @@ impl TokenDao {
             token: model.token().to_owned(),
             allow_anonymous: *model.allow_anonymous(),
             expired_at: *model.expired_at(),
+            disabled_at: *model.disabled_at(),
+            last_used_at: *model.last_used_at(),
+            request_count: *model.request_count(),
+            plain_token: None,
         }
     }
 
@@ -483,6 +744,9 @@ impl TokenDao {
             &self.token,
             &self.allow_anonymous,
             &self.expired_at,
+            &self.disabled_at,
+            &self.last_used_at,
+            &self.request_count,
         )
     }
 }
@@ -0,0 +1,56 @@
+use std::sync::Mutex;
+
+use ahash::{HashMap, HashMapExt};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{token::TokenDao, Db};
+
+// Counts token requests in memory so they don't cost a write each, the counts are added to the
+// tokens whenever they are flushed
+pub struct TokenUsage {
+    pending: Mutex<HashMap<Uuid, (DateTime<Utc>, i64)>>,
+}
+
+impl TokenUsage {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record(&self, token_id: &Uuid) {
+        let now = Utc::now();
+        let mut pending = self.pending.lock().unwrap();
+        let usage = pending.entry(*token_id).or_insert((now, 0));
+        usage.0 = now;
+        usage.1 += 1;
+    }
+
+    // Counts that fail to be written are dropped rather than kept around, the usage is only
+    // informational
+    pub async fn flush(&self, db: &Db) -> Result<()> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+
+        let mut last_err = None;
+        for (token_id, (last_used_at, requests)) in pending {
+            if let Err(err) =
+                TokenDao::db_increment_usage(db, &token_id, &last_used_at, &requests).await
+            {
+                last_err = Some(err);
+            }
+        }
+
+        match last_err {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for TokenUsage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -20,22 +20,28 @@ use crate::util::conversion;
 
 #[derive(Deserialize, Serialize, EnumIter, PartialEq, Clone, Copy)]
 pub enum ColumnKind {
-    Boolean,   // boolean
-    TinyInt,   // 8-bit signed int
-    SmallInt,  // 16-bit signed int
-    Int,       // 32-bit signed int
-    BigInt,    // 64-bit signed long
-    Varint,    // Arbitrary-precision integer
-    Float,     // 32-bit IEEE-754 floating point
-    Double,    // 64-bit IEEE-754 floating point
-    Decimal,   // Variable-precision decimal
-    String,    // UTF8 encoded string
-    Binary,    // Arbitrary bytes
-    Uuid,      // A UUID (of any version)
-    Date,      // A date (with no corresponding time value)
-    Time,      // A time (with no corresponding date value)
-    Timestamp, // A timestamp (date and time)
-    Json,      // A json data format
+    Boolean,     // boolean
+    TinyInt,     // 8-bit signed int
+    SmallInt,    // 16-bit signed int
+    Int,         // 32-bit signed int
+    BigInt,      // 64-bit signed long
+    Varint,      // Arbitrary-precision integer
+    Float,       // 32-bit IEEE-754 floating point
+    Double,      // 64-bit IEEE-754 floating point
+    Decimal,     // Variable-precision decimal
+    String,      // UTF8 encoded string
+    Binary,      // Arbitrary bytes
+    Uuid,        // A UUID (of any version)
+    Date,        // A date (with no corresponding time value)
+    Time,        // A time (with no corresponding date value)
+    Timestamp,   // A timestamp (date and time)
+    Json,        // A json data format
+    StringArray, // A list of UTF8 encoded strings
+    IntArray,    // A list of 32-bit signed ints
+    Counter,     // 64-bit signed counter, only changed through increments
+    Point,       // A geographic point (latitude and longitude in degrees)
+    // A UUID of a record in another collection
+    Reference { collection_id: Uuid },
 }
 
 impl ColumnKind {
@@ -57,6 +63,11 @@ impl ColumnKind {
             Self::Time => "time",
             Self::Timestamp => "timestamp",
             Self::Json => "json",
+            Self::StringArray => "string_array",
+            Self::IntArray => "int_array",
+            Self::Counter => "counter",
+            Self::Point => "point",
+            Self::Reference { .. } => "reference",
         }
     }
 
@@ -78,10 +89,47 @@ impl ColumnKind {
             "time" => Ok(Self::Time),
             "timestamp" => Ok(Self::Timestamp),
             "json" => Ok(Self::Json),
+            "string_array" => Ok(Self::StringArray),
+            "int_array" => Ok(Self::IntArray),
+            "counter" => Ok(Self::Counter),
+            "point" => Ok(Self::Point),
+            "reference" => Err(Error::msg(
+                "Schema field kind 'reference' requires the collection it references",
+            )),
             _ => Err(Error::msg(format!("Unknown schema field kind '{str}'"))),
         }
     }
 
+    pub fn from_str_with_reference(
+        str: &str,
+        reference_collection_id: &Option<Uuid>,
+    ) -> Result<Self> {
+        match (str, reference_collection_id) {
+            ("reference", Some(collection_id)) => Ok(Self::Reference {
+                collection_id: *collection_id,
+            }),
+            (_, None) => Self::from_str(str),
+            (_, Some(_)) => Err(Error::msg(format!(
+                "Schema field kind '{str}' can't reference a collection"
+            ))),
+        }
+    }
+
+    pub fn reference_collection_id(&self) -> Option<&Uuid> {
+        match self {
+            Self::Reference { collection_id } => Some(collection_id),
+            _ => None,
+        }
+    }
+
+    pub fn element_kind(&self) -> Option<Self> {
+        match self {
+            Self::StringArray => Some(Self::String),
+            Self::IntArray => Some(Self::Int),
+            _ => None,
+        }
+    }
+
     pub fn to_scylladb_model(&self) -> ColumnKindScylla {
         match self {
             Self::Boolean => ColumnKindScylla::Boolean,
@@ -95,10 +143,14 @@ impl ColumnKind {
             Self::Decimal => ColumnKindScylla::Decimal,
             Self::String => ColumnKindScylla::Text,
             Self::Binary | Self::Json => ColumnKindScylla::Blob,
-            Self::Uuid => ColumnKindScylla::Uuid,
+            Self::Uuid | Self::Reference { .. } => ColumnKindScylla::Uuid,
             Self::Date => ColumnKindScylla::Date,
             Self::Time => ColumnKindScylla::Time,
             Self::Timestamp => ColumnKindScylla::Timestamp,
+            Self::StringArray => ColumnKindScylla::ListText,
+            Self::IntArray => ColumnKindScylla::ListInt,
+            Self::Counter => ColumnKindScylla::BigInt,
+            Self::Point => ColumnKindScylla::ListDouble,
         }
     }
 
@@ -108,18 +160,21 @@ impl ColumnKind {
             Self::TinyInt => ColumnKindPostgres::Char,
             Self::SmallInt => ColumnKindPostgres::Smallint,
             Self::Int => ColumnKindPostgres::Integer,
-            Self::BigInt => ColumnKindPostgres::Bigint,
+            Self::BigInt | Self::Counter => ColumnKindPostgres::Bigint,
             Self::Varint => ColumnKindPostgres::Numeric,
             Self::Float => ColumnKindPostgres::Real,
             Self::Double => ColumnKindPostgres::DoublePrecision,
             Self::Decimal => ColumnKindPostgres::Numeric,
             Self::String => ColumnKindPostgres::Varchar,
             Self::Binary => ColumnKindPostgres::Bytea,
-            Self::Uuid => ColumnKindPostgres::Uuid,
+            Self::Uuid | Self::Reference { .. } => ColumnKindPostgres::Uuid,
             Self::Date => ColumnKindPostgres::Date,
             Self::Time => ColumnKindPostgres::Time,
             Self::Timestamp => ColumnKindPostgres::Timestamptz6,
             Self::Json => ColumnKindPostgres::Jsonb,
+            Self::StringArray => ColumnKindPostgres::VarcharArray,
+            Self::IntArray => ColumnKindPostgres::IntegerArray,
+            Self::Point => ColumnKindPostgres::DoublePrecisionArray,
         }
     }
 
@@ -129,16 +184,16 @@ impl ColumnKind {
             Self::TinyInt => ColumnKindMysql::Tinyint,
             Self::SmallInt => ColumnKindMysql::Smallint,
             Self::Int => ColumnKindMysql::Int,
-            Self::BigInt => ColumnKindMysql::Bigint,
+            Self::BigInt | Self::Counter => ColumnKindMysql::Bigint,
             Self::Binary | Self::Varint | Self::Decimal => ColumnKindMysql::Blob,
             Self::Float => ColumnKindMysql::Float,
             Self::Double => ColumnKindMysql::Double,
             Self::String => ColumnKindMysql::Varchar255,
-            Self::Uuid => ColumnKindMysql::Binary16,
+            Self::Uuid | Self::Reference { .. } => ColumnKindMysql::Binary16,
             Self::Date => ColumnKindMysql::Date,
             Self::Time => ColumnKindMysql::Time,
             Self::Timestamp => ColumnKindMysql::Timestamp6,
-            Self::Json => ColumnKindMysql::Json,
+            Self::Json | Self::StringArray | Self::IntArray | Self::Point => ColumnKindMysql::Json,
         }
     }
 
@@ -146,10 +201,16 @@ impl ColumnKind {
         match self {
             Self::Boolean => ColumnKindSqlite::Boolean,
             Self::TinyInt | Self::SmallInt | Self::Int => ColumnKindSqlite::Integer,
-            Self::BigInt => ColumnKindSqlite::Bigint,
-            Self::Binary | Self::Varint | Self::Decimal | Self::Uuid | Self::Json => {
-                ColumnKindSqlite::Blob
-            }
+            Self::BigInt | Self::Counter => ColumnKindSqlite::Bigint,
+            Self::Binary
+            | Self::Varint
+            | Self::Decimal
+            | Self::Uuid
+            | Self::Reference { .. }
+            | Self::Json
+            | Self::StringArray
+            | Self::IntArray
+            | Self::Point => ColumnKindSqlite::Blob,
             Self::Float | Self::Double => ColumnKindSqlite::Real,
             Self::String => ColumnKindSqlite::Text,
             Self::Date => ColumnKindSqlite::Date,
@@ -159,7 +220,7 @@ impl ColumnKind {
     }
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
 pub enum ColumnValue {
     Boolean(Option<bool>),
     TinyInteger(Option<i8>),
@@ -177,6 +238,10 @@ pub enum ColumnValue {
     Time(Option<NaiveTime>),
     Timestamp(Option<DateTime<Utc>>),
     Json(Option<String>),
+    StringArray(Option<Vec<String>>),
+    IntArray(Option<Vec<i32>>),
+    Counter(Option<i64>),
+    Point(Option<(f64, f64)>),
 }
 
 impl ColumnValue {
@@ -193,11 +258,32 @@ impl ColumnValue {
             ColumnKind::Decimal => Self::Decimal(None),
             ColumnKind::String => Self::String(None),
             ColumnKind::Binary => Self::Binary(None),
-            ColumnKind::Uuid => Self::Uuid(None),
+            ColumnKind::Uuid | ColumnKind::Reference { .. } => Self::Uuid(None),
             ColumnKind::Date => Self::Date(None),
             ColumnKind::Time => Self::Time(None),
             ColumnKind::Timestamp => Self::Timestamp(None),
             ColumnKind::Json => Self::Json(None),
+            ColumnKind::StringArray => Self::StringArray(None),
+            ColumnKind::IntArray => Self::IntArray(None),
+            ColumnKind::Counter => Self::Counter(None),
+            ColumnKind::Point => Self::Point(None),
+        }
+    }
+
+    pub fn validate_point(lat: f64, lng: f64) -> Result<(f64, f64)> {
+        if !(-90.0..=90.0).contains(&lat) {
+            return Err(Error::msg("Point 'lat' must be between -90 and 90"));
+        }
+        if !(-180.0..=180.0).contains(&lng) {
+            return Err(Error::msg("Point 'lng' must be between -180 and 180"));
+        }
+        Ok((lat, lng))
+    }
+
+    fn point_from_vec(value: &[f64]) -> Result<(f64, f64)> {
+        match value {
+            [lat, lng] => Ok((*lat, *lng)),
+            _ => Err(Error::msg("Point must consist of exactly 2 coordinates")),
         }
     }
 
@@ -236,6 +322,10 @@ impl ColumnValue {
                     Some(value) => Ok(Self::BigInteger(Some(value))),
                     None => Err(Error::msg("Wrong value type")),
                 },
+                ColumnKind::Counter => match value.as_i64() {
+                    Some(value) => Ok(Self::Counter(Some(value))),
+                    None => Err(Error::msg("Wrong value type")),
+                },
                 ColumnKind::Float => match value.as_f64() {
                     Some(value) => {
                         let value = value as f32;
@@ -264,7 +354,7 @@ impl ColumnValue {
                 )?))),
                 ColumnKind::String => Ok(Self::String(Some(value.to_owned()))),
                 ColumnKind::Binary => Ok(Self::Binary(Some(value.as_bytes().to_vec()))),
-                ColumnKind::Uuid => match Uuid::parse_str(value) {
+                ColumnKind::Uuid | ColumnKind::Reference { .. } => match Uuid::parse_str(value) {
                     Ok(uuid) => Ok(Self::Uuid(Some(uuid))),
                     Err(err) => Err(err.into()),
                 },
@@ -306,12 +396,43 @@ impl ColumnValue {
                     Ok(Self::Binary(Some(bytes)))
                 }
                 ColumnKind::Json => Ok(Self::Json(Some(serde_json::json!(value).to_string()))),
+                ColumnKind::StringArray => {
+                    let mut strings = Vec::with_capacity(value.len());
+                    for value in value.iter() {
+                        match value.as_str() {
+                            Some(string) => strings.push(string.to_owned()),
+                            None => return Err(Error::msg("Wrong value type")),
+                        }
+                    }
+                    Ok(Self::StringArray(Some(strings)))
+                }
+                ColumnKind::IntArray => {
+                    let mut ints = Vec::with_capacity(value.len());
+                    for value in value.iter() {
+                        match value.as_i64() {
+                            Some(int) => ints.push(i32::try_from(int)?),
+                            None => return Err(Error::msg("Wrong value type")),
+                        }
+                    }
+                    Ok(Self::IntArray(Some(ints)))
+                }
                 _ => return Err(Error::msg("Wrong value type")),
             },
             serde_json::Value::Object(value) => match kind {
                 ColumnKind::Binary => Ok(Self::Binary(Some(
                     serde_json::json!(value).to_string().into_bytes(),
                 ))),
+                ColumnKind::Point => {
+                    let lat = match value.get("lat").and_then(|lat| lat.as_f64()) {
+                        Some(lat) => lat,
+                        None => return Err(Error::msg("Point must have a numeric 'lat' field")),
+                    };
+                    let lng = match value.get("lng").and_then(|lng| lng.as_f64()) {
+                        Some(lng) => lng,
+                        None => return Err(Error::msg("Point must have a numeric 'lng' field")),
+                    };
+                    Ok(Self::Point(Some(Self::validate_point(lat, lng)?)))
+                }
                 ColumnKind::Json => Ok(Self::Json(Some(serde_json::json!(value).to_string()))),
                 _ => return Err(Error::msg("Wrong value type")),
             },
@@ -387,6 +508,22 @@ impl ColumnValue {
                 },
                 None => Ok(serde_json::Value::Null),
             },
+            Self::StringArray(data) => match data {
+                Some(data) => Ok(serde_json::json!(data)),
+                None => Ok(serde_json::Value::Null),
+            },
+            Self::IntArray(data) => match data {
+                Some(data) => Ok(serde_json::json!(data)),
+                None => Ok(serde_json::Value::Null),
+            },
+            Self::Counter(data) => match data {
+                Some(data) => Ok(serde_json::json!(data)),
+                None => Ok(serde_json::json!(0)),
+            },
+            Self::Point(data) => match data {
+                Some((lat, lng)) => Ok(serde_json::json!({ "lat": lat, "lng": lng })),
+                None => Ok(serde_json::Value::Null),
+            },
         }
     }
 
@@ -397,6 +534,22 @@ impl ColumnValue {
             ColumnKind::SmallInt => Ok(Self::SmallInteger(value.as_smallint())),
             ColumnKind::Int => Ok(Self::Integer(value.as_int())),
             ColumnKind::BigInt => Ok(Self::BigInteger(value.as_bigint())),
+            ColumnKind::Counter => Ok(Self::Counter(match value {
+                ScyllaCqlValue::Counter(value) => Some(value.0),
+                _ => value.as_bigint(),
+            })),
+            ColumnKind::Point => Ok(Self::Point(match value.as_list() {
+                Some(value) => Some(Self::point_from_vec(
+                    &value
+                        .iter()
+                        .map(|value| match value.as_double() {
+                            Some(value) => Ok(value),
+                            None => Err(Error::msg("Wrong value type")),
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                )?),
+                None => None,
+            })),
             ColumnKind::Varint => Ok(Self::VarInteger(match value.clone().into_cql_varint() {
                 Some(value) => Some(BigInt::from_signed_bytes_be(
                     value.as_signed_bytes_be_slice(),
@@ -421,7 +574,7 @@ impl ColumnValue {
                 Some(value) => Some(value.to_vec()),
                 None => None,
             })),
-            ColumnKind::Uuid => Ok(Self::Uuid(value.as_uuid())),
+            ColumnKind::Uuid | ColumnKind::Reference { .. } => Ok(Self::Uuid(value.as_uuid())),
             ColumnKind::Date => Ok(Self::Date(match value.as_cql_date() {
                 Some(value) => Some(conversion::scylla_cql_date_to_naivedate(&value)?),
                 None => None,
@@ -438,6 +591,30 @@ impl ColumnValue {
                 Some(value) => Some(std::str::from_utf8(&value)?.to_owned()),
                 None => None,
             })),
+            ColumnKind::StringArray => Ok(Self::StringArray(match value.as_list() {
+                Some(value) => Some(
+                    value
+                        .iter()
+                        .map(|value| match value.as_text() {
+                            Some(value) => Ok(value.to_owned()),
+                            None => Err(Error::msg("Wrong value type")),
+                        })
+                        .collect::<Result<_>>()?,
+                ),
+                None => None,
+            })),
+            ColumnKind::IntArray => Ok(Self::IntArray(match value.as_list() {
+                Some(value) => Some(
+                    value
+                        .iter()
+                        .map(|value| match value.as_int() {
+                            Some(value) => Ok(value),
+                            None => Err(Error::msg("Wrong value type")),
+                        })
+                        .collect::<Result<_>>()?,
+                ),
+                None => None,
+            })),
         }
     }
 
@@ -448,6 +625,11 @@ impl ColumnValue {
             Self::SmallInteger(data) => Ok(Box::new(*data)),
             Self::Integer(data) => Ok(Box::new(*data)),
             Self::BigInteger(data) => Ok(Box::new(*data)),
+            Self::Counter(data) => Ok(Box::new(*data)),
+            Self::Point(data) => Ok(Box::new(match data {
+                Some((lat, lng)) => Some(vec![*lat, *lng]),
+                None => None,
+            })),
             Self::VarInteger(data) => Ok(Box::new(match data {
                 Some(data) => Some(BigInt::from_signed_bytes_be(&data.to_signed_bytes_be())),
                 None => None,
@@ -477,6 +659,8 @@ impl ColumnValue {
                 Some(data) => Some(data.to_owned().into_bytes()),
                 None => None,
             })),
+            Self::StringArray(data) => Ok(Box::new(data.to_owned())),
+            Self::IntArray(data) => Ok(Box::new(data.to_owned())),
         }
     }
 
@@ -491,6 +675,13 @@ impl ColumnValue {
             ColumnKind::SmallInt => Ok(Self::SmallInteger(sqlx::Row::try_get(value, index)?)),
             ColumnKind::Int => Ok(Self::Integer(sqlx::Row::try_get(value, index)?)),
             ColumnKind::BigInt => Ok(Self::BigInteger(sqlx::Row::try_get(value, index)?)),
+            ColumnKind::Counter => Ok(Self::Counter(sqlx::Row::try_get(value, index)?)),
+            ColumnKind::Point => Ok(Self::Point(
+                match sqlx::Row::try_get::<Option<Vec<f64>>, _>(value, index)? {
+                    Some(value) => Some(Self::point_from_vec(&value)?),
+                    None => None,
+                },
+            )),
             ColumnKind::Varint => Ok(Self::VarInteger(
                 match sqlx::Row::try_get::<Option<sqlx::types::BigDecimal>, _>(value, index)? {
                     Some(value) => Some(BigInt::from_str(&value.to_string())?),
@@ -507,16 +698,22 @@ impl ColumnValue {
             )),
             ColumnKind::String => Ok(Self::String(sqlx::Row::try_get(value, index)?)),
             ColumnKind::Binary => Ok(Self::Binary(sqlx::Row::try_get(value, index)?)),
-            ColumnKind::Uuid => Ok(Self::Uuid(sqlx::Row::try_get(value, index)?)),
+            ColumnKind::Uuid | ColumnKind::Reference { .. } => {
+                Ok(Self::Uuid(sqlx::Row::try_get(value, index)?))
+            }
             ColumnKind::Date => Ok(Self::Date(sqlx::Row::try_get(value, index)?)),
             ColumnKind::Time => Ok(Self::Time(sqlx::Row::try_get(value, index)?)),
             ColumnKind::Timestamp => Ok(Self::Timestamp(sqlx::Row::try_get(value, index)?)),
             ColumnKind::Json => Ok(Self::Json(
-                match sqlx::Row::try_get::<Option<sqlx::types::Json<Vec<u8>>>, _>(value, index)? {
-                    Some(value) => Some(std::str::from_utf8(&value.0)?.to_string()),
+                match sqlx::Row::try_get::<Option<sqlx::types::Json<serde_json::Value>>, _>(
+                    value, index,
+                )? {
+                    Some(value) => Some(value.0.to_string()),
                     None => None,
                 },
             )),
+            ColumnKind::StringArray => Ok(Self::StringArray(sqlx::Row::try_get(value, index)?)),
+            ColumnKind::IntArray => Ok(Self::IntArray(sqlx::Row::try_get(value, index)?)),
         }
     }
 
@@ -530,6 +727,11 @@ impl ColumnValue {
             Self::SmallInteger(data) => Ok(query.bind(*data)),
             Self::Integer(data) => Ok(query.bind(*data)),
             Self::BigInteger(data) => Ok(query.bind(*data)),
+            Self::Counter(data) => Ok(query.bind(*data)),
+            Self::Point(data) => Ok(query.bind(match data {
+                Some((lat, lng)) => Some(vec![*lat, *lng]),
+                None => None,
+            })),
             Self::VarInteger(data) => Ok(query.bind(match data {
                 Some(data) => Some(sqlx::types::BigDecimal::from_str(&data.to_string())?),
                 None => None,
@@ -547,9 +749,13 @@ impl ColumnValue {
             Self::Time(data) => Ok(query.bind(*data)),
             Self::Timestamp(data) => Ok(query.bind(*data)),
             Self::Json(data) => Ok(query.bind(match data {
-                Some(data) => Some(sqlx::types::Json(data.to_owned().into_bytes())),
+                Some(data) => Some(sqlx::types::Json(
+                    serde_json::from_str::<serde_json::Value>(data)?,
+                )),
                 None => None,
             })),
+            Self::StringArray(data) => Ok(query.bind(data.to_owned())),
+            Self::IntArray(data) => Ok(query.bind(data.to_owned())),
         }
     }
 
@@ -563,6 +769,11 @@ impl ColumnValue {
             Self::SmallInteger(data) => Ok(query.bind(*data)),
             Self::Integer(data) => Ok(query.bind(*data)),
             Self::BigInteger(data) => Ok(query.bind(*data)),
+            Self::Counter(data) => Ok(query.bind(*data)),
+            Self::Point(data) => Ok(query.bind(match data {
+                Some((lat, lng)) => Some(vec![*lat, *lng]),
+                None => None,
+            })),
             Self::VarInteger(data) => Ok(query.bind(match data {
                 Some(data) => Some(sqlx::types::BigDecimal::from_str(&data.to_string())?),
                 None => None,
@@ -580,9 +791,13 @@ impl ColumnValue {
             Self::Time(data) => Ok(query.bind(*data)),
             Self::Timestamp(data) => Ok(query.bind(*data)),
             Self::Json(data) => Ok(query.bind(match data {
-                Some(data) => Some(sqlx::types::Json(data.to_owned().into_bytes())),
+                Some(data) => Some(sqlx::types::Json(
+                    serde_json::from_str::<serde_json::Value>(data)?,
+                )),
                 None => None,
             })),
+            Self::StringArray(data) => Ok(query.bind(data.to_owned())),
+            Self::IntArray(data) => Ok(query.bind(data.to_owned())),
         }
     }
 
@@ -597,6 +812,13 @@ impl ColumnValue {
             ColumnKind::SmallInt => Ok(Self::SmallInteger(sqlx::Row::try_get(value, index)?)),
             ColumnKind::Int => Ok(Self::Integer(sqlx::Row::try_get(value, index)?)),
             ColumnKind::BigInt => Ok(Self::BigInteger(sqlx::Row::try_get(value, index)?)),
+            ColumnKind::Counter => Ok(Self::Counter(sqlx::Row::try_get(value, index)?)),
+            ColumnKind::Point => Ok(Self::Point(
+                match sqlx::Row::try_get::<Option<sqlx::types::Json<Vec<f64>>>, _>(value, index)? {
+                    Some(value) => Some(Self::point_from_vec(&value.0)?),
+                    None => None,
+                },
+            )),
             ColumnKind::Varint => Ok(Self::VarInteger(
                 match sqlx::Row::try_get::<Option<&[u8]>, _>(value, index)? {
                     Some(value) => Some(BigInt::from_signed_bytes_be(value)),
@@ -613,15 +835,32 @@ impl ColumnValue {
             )),
             ColumnKind::String => Ok(Self::String(sqlx::Row::try_get(value, index)?)),
             ColumnKind::Binary => Ok(Self::Binary(sqlx::Row::try_get(value, index)?)),
-            ColumnKind::Uuid => Ok(Self::Uuid(sqlx::Row::try_get(value, index)?)),
+            ColumnKind::Uuid | ColumnKind::Reference { .. } => {
+                Ok(Self::Uuid(sqlx::Row::try_get(value, index)?))
+            }
             ColumnKind::Date => Ok(Self::Date(sqlx::Row::try_get(value, index)?)),
             ColumnKind::Time => Ok(Self::Time(sqlx::Row::try_get(value, index)?)),
             ColumnKind::Timestamp => Ok(Self::Timestamp(
                 sqlx::Row::try_get::<DateTime<Utc>, _>(value, index)?.into(),
             )),
             ColumnKind::Json => Ok(Self::Json(
-                match sqlx::Row::try_get::<Option<sqlx::types::Json<Vec<u8>>>, _>(value, index)? {
-                    Some(value) => Some(std::str::from_utf8(&value.0)?.to_owned()),
+                match sqlx::Row::try_get::<Option<sqlx::types::Json<serde_json::Value>>, _>(
+                    value, index,
+                )? {
+                    Some(value) => Some(value.0.to_string()),
+                    None => None,
+                },
+            )),
+            ColumnKind::StringArray => Ok(Self::StringArray(
+                match sqlx::Row::try_get::<Option<sqlx::types::Json<Vec<String>>>, _>(value, index)?
+                {
+                    Some(value) => Some(value.0),
+                    None => None,
+                },
+            )),
+            ColumnKind::IntArray => Ok(Self::IntArray(
+                match sqlx::Row::try_get::<Option<sqlx::types::Json<Vec<i32>>>, _>(value, index)? {
+                    Some(value) => Some(value.0),
                     None => None,
                 },
             )),
@@ -638,6 +877,11 @@ impl ColumnValue {
             Self::SmallInteger(data) => Ok(query.bind(*data)),
             Self::Integer(data) => Ok(query.bind(*data)),
             Self::BigInteger(data) => Ok(query.bind(*data)),
+            Self::Counter(data) => Ok(query.bind(*data)),
+            Self::Point(data) => Ok(query.bind(match data {
+                Some((lat, lng)) => Some(sqlx::types::Json(vec![*lat, *lng])),
+                None => None,
+            })),
             Self::VarInteger(data) => Ok(query.bind(match data {
                 Some(data) => Some(data.to_signed_bytes_be()),
                 None => None,
@@ -655,7 +899,17 @@ impl ColumnValue {
             Self::Time(data) => Ok(query.bind(*data)),
             Self::Timestamp(data) => Ok(query.bind(*data)),
             Self::Json(data) => Ok(query.bind(match data {
-                Some(data) => Some(sqlx::types::Json(data.to_owned().into_bytes())),
+                Some(data) => Some(sqlx::types::Json(
+                    serde_json::from_str::<serde_json::Value>(data)?,
+                )),
+                None => None,
+            })),
+            Self::StringArray(data) => Ok(query.bind(match data {
+                Some(data) => Some(sqlx::types::Json(data.to_owned())),
+                None => None,
+            })),
+            Self::IntArray(data) => Ok(query.bind(match data {
+                Some(data) => Some(sqlx::types::Json(data.to_owned())),
                 None => None,
             })),
         }
@@ -671,6 +925,11 @@ impl ColumnValue {
             Self::SmallInteger(data) => Ok(query.bind(*data)),
             Self::Integer(data) => Ok(query.bind(*data)),
             Self::BigInteger(data) => Ok(query.bind(*data)),
+            Self::Counter(data) => Ok(query.bind(*data)),
+            Self::Point(data) => Ok(query.bind(match data {
+                Some((lat, lng)) => Some(sqlx::types::Json(vec![*lat, *lng])),
+                None => None,
+            })),
             Self::VarInteger(data) => Ok(query.bind(match data {
                 Some(data) => Some(data.to_signed_bytes_be()),
                 None => None,
@@ -688,7 +947,17 @@ impl ColumnValue {
             Self::Time(data) => Ok(query.bind(*data)),
             Self::Timestamp(data) => Ok(query.bind(*data)),
             Self::Json(data) => Ok(query.bind(match data {
-                Some(data) => Some(sqlx::types::Json(data.to_owned().into_bytes())),
+                Some(data) => Some(sqlx::types::Json(
+                    serde_json::from_str::<serde_json::Value>(data)?,
+                )),
+                None => None,
+            })),
+            Self::StringArray(data) => Ok(query.bind(match data {
+                Some(data) => Some(sqlx::types::Json(data.to_owned())),
+                None => None,
+            })),
+            Self::IntArray(data) => Ok(query.bind(match data {
+                Some(data) => Some(sqlx::types::Json(data.to_owned())),
                 None => None,
             })),
         }
@@ -705,6 +974,15 @@ impl ColumnValue {
             ColumnKind::SmallInt => Ok(Self::SmallInteger(sqlx::Row::try_get(value, index)?)),
             ColumnKind::Int => Ok(Self::Integer(sqlx::Row::try_get(value, index)?)),
             ColumnKind::BigInt => Ok(Self::BigInteger(sqlx::Row::try_get(value, index)?)),
+            ColumnKind::Counter => Ok(Self::Counter(sqlx::Row::try_get(value, index)?)),
+            ColumnKind::Point => Ok(Self::Point(
+                match sqlx::Row::try_get::<Option<Vec<u8>>, _>(value, index)? {
+                    Some(value) => Some(Self::point_from_vec(
+                        &serde_json::from_slice::<Vec<f64>>(&value)?,
+                    )?),
+                    None => None,
+                },
+            )),
             ColumnKind::Varint => Ok(Self::VarInteger(
                 match sqlx::Row::try_get::<Option<&[u8]>, _>(value, index)? {
                     Some(value) => Some(BigInt::from_signed_bytes_be(value)),
@@ -721,7 +999,9 @@ impl ColumnValue {
             )),
             ColumnKind::String => Ok(Self::String(sqlx::Row::try_get(value, index)?)),
             ColumnKind::Binary => Ok(Self::Binary(sqlx::Row::try_get(value, index)?)),
-            ColumnKind::Uuid => Ok(Self::Uuid(sqlx::Row::try_get(value, index)?)),
+            ColumnKind::Uuid | ColumnKind::Reference { .. } => {
+                Ok(Self::Uuid(sqlx::Row::try_get(value, index)?))
+            }
             ColumnKind::Date => Ok(Self::Date(sqlx::Row::try_get(value, index)?)),
             ColumnKind::Time => Ok(Self::Time(sqlx::Row::try_get(value, index)?)),
             ColumnKind::Timestamp => Ok(Self::Timestamp(sqlx::Row::try_get(value, index)?)),
@@ -731,6 +1011,18 @@ impl ColumnValue {
                     None => None,
                 },
             )),
+            ColumnKind::StringArray => Ok(Self::StringArray(
+                match sqlx::Row::try_get::<Option<Vec<u8>>, _>(value, index)? {
+                    Some(value) => Some(serde_json::from_slice(&value)?),
+                    None => None,
+                },
+            )),
+            ColumnKind::IntArray => Ok(Self::IntArray(
+                match sqlx::Row::try_get::<Option<Vec<u8>>, _>(value, index)? {
+                    Some(value) => Some(serde_json::from_slice(&value)?),
+                    None => None,
+                },
+            )),
         }
     }
 
@@ -744,6 +1036,11 @@ impl ColumnValue {
             Self::SmallInteger(data) => Ok(query.bind(*data)),
             Self::Integer(data) => Ok(query.bind(*data)),
             Self::BigInteger(data) => Ok(query.bind(*data)),
+            Self::Counter(data) => Ok(query.bind(*data)),
+            Self::Point(data) => Ok(query.bind(match data {
+                Some((lat, lng)) => Some(serde_json::to_vec(&[*lat, *lng])?),
+                None => None,
+            })),
             Self::VarInteger(data) => Ok(query.bind(match data {
                 Some(data) => Some(data.to_signed_bytes_be()),
                 None => None,
@@ -764,6 +1061,14 @@ impl ColumnValue {
                 Some(data) => Some(data.to_owned().into_bytes()),
                 None => None,
             })),
+            Self::StringArray(data) => Ok(query.bind(match data {
+                Some(data) => Some(serde_json::to_vec(data)?),
+                None => None,
+            })),
+            Self::IntArray(data) => Ok(query.bind(match data {
+                Some(data) => Some(serde_json::to_vec(data)?),
+                None => None,
+            })),
         }
     }
 
@@ -777,6 +1082,11 @@ impl ColumnValue {
             Self::SmallInteger(data) => Ok(query.bind(*data)),
             Self::Integer(data) => Ok(query.bind(*data)),
             Self::BigInteger(data) => Ok(query.bind(*data)),
+            Self::Counter(data) => Ok(query.bind(*data)),
+            Self::Point(data) => Ok(query.bind(match data {
+                Some((lat, lng)) => Some(serde_json::to_vec(&[*lat, *lng])?),
+                None => None,
+            })),
             Self::VarInteger(data) => Ok(query.bind(match data {
                 Some(data) => Some(data.to_signed_bytes_be()),
                 None => None,
@@ -797,6 +1107,14 @@ impl ColumnValue {
                 Some(data) => Some(data.to_owned().into_bytes()),
                 None => None,
             })),
+            Self::StringArray(data) => Ok(query.bind(match data {
+                Some(data) => Some(serde_json::to_vec(data)?),
+                None => None,
+            })),
+            Self::IntArray(data) => Ok(query.bind(match data {
+                Some(data) => Some(serde_json::to_vec(data)?),
+                None => None,
+            })),
         }
     }
 }
@@ -0,0 +1,90 @@
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use futures::{stream, StreamExt};
+
+use crate::{collection::CollectionDao, record::RecordDao, Db};
+
+const PREPARE_CONCURRENCY: usize = 16;
+
+pub struct Warmup {
+    started_at: Instant,
+    prepare_limit: usize,
+    readiness_timeout: Duration,
+    collections_loaded: AtomicUsize,
+    tables_total: AtomicUsize,
+    tables_prepared: AtomicUsize,
+    tables_failed: AtomicUsize,
+    finished: AtomicBool,
+}
+
+impl Warmup {
+    pub fn new(prepare_limit: &usize, readiness_timeout: &Duration) -> Self {
+        Self {
+            started_at: Instant::now(),
+            prepare_limit: *prepare_limit,
+            readiness_timeout: *readiness_timeout,
+            collections_loaded: AtomicUsize::new(0),
+            tables_total: AtomicUsize::new(0),
+            tables_prepared: AtomicUsize::new(0),
+            tables_failed: AtomicUsize::new(0),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    pub fn collections_loaded(&self) -> usize {
+        self.collections_loaded.load(Ordering::Relaxed)
+    }
+
+    pub fn tables_total(&self) -> usize {
+        self.tables_total.load(Ordering::Relaxed)
+    }
+
+    pub fn tables_prepared(&self) -> usize {
+        self.tables_prepared.load(Ordering::Relaxed)
+    }
+
+    pub fn tables_failed(&self) -> usize {
+        self.tables_failed.load(Ordering::Relaxed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    // Readiness only waits for the warm-up up to the configured timeout
+    pub fn is_ready(&self) -> bool {
+        self.is_finished() || self.started_at.elapsed() >= self.readiness_timeout
+    }
+
+    pub async fn run(&self, db: &Db) -> Result<()> {
+        let result = self.load(db).await;
+        self.finished.store(true, Ordering::Release);
+        result
+    }
+
+    async fn load(&self, db: &Db) -> Result<()> {
+        let collections_data = CollectionDao::db_select_many(db).await?;
+        self.collections_loaded
+            .store(collections_data.len(), Ordering::Relaxed);
+
+        let collections_data = &collections_data[..collections_data.len().min(self.prepare_limit)];
+        self.tables_total
+            .store(collections_data.len(), Ordering::Relaxed);
+
+        // A table that can't be prepared now will simply be prepared on its first query
+        stream::iter(collections_data)
+            .for_each_concurrent(PREPARE_CONCURRENCY, |collection_data| async move {
+                match RecordDao::db_prepare_statements(db, collection_data).await {
+                    Ok(_) => self.tables_prepared.fetch_add(1, Ordering::Relaxed),
+                    Err(_) => self.tables_failed.fetch_add(1, Ordering::Relaxed),
+                };
+            })
+            .await;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,448 @@
+use anyhow::{Error, Result};
+use chrono::{DateTime, Utc};
+use hb_db_mysql::model::webhook::WebhookModel as WebhookMysqlModel;
+use hb_db_postgresql::model::webhook::WebhookModel as WebhookPostgresModel;
+use hb_db_scylladb::model::webhook::WebhookModel as WebhookScyllaModel;
+use hb_db_sqlite::model::webhook::WebhookModel as WebhookSqliteModel;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{error::DaoError, util::conversion, Db};
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct WebhookDao {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    admin_id: Uuid,
+    project_id: Uuid,
+    collection_id: Uuid,
+    url: String,
+    events: Vec<WebhookEvent>,
+    secret: String,
+    enabled: bool,
+}
+
+impl WebhookDao {
+    pub fn new(
+        admin_id: &Uuid,
+        project_id: &Uuid,
+        collection_id: &Uuid,
+        url: &str,
+        events: &Vec<WebhookEvent>,
+        secret: &str,
+        enabled: &bool,
+    ) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: Uuid::now_v7(),
+            created_at: now,
+            updated_at: now,
+            admin_id: *admin_id,
+            project_id: *project_id,
+            collection_id: *collection_id,
+            url: url.to_owned(),
+            events: events.clone(),
+            secret: secret.to_owned(),
+            enabled: *enabled,
+        }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        &self.created_at
+    }
+
+    pub fn updated_at(&self) -> &DateTime<Utc> {
+        &self.updated_at
+    }
+
+    pub fn admin_id(&self) -> &Uuid {
+        &self.admin_id
+    }
+
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn events(&self) -> &Vec<WebhookEvent> {
+        &self.events
+    }
+
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    pub fn enabled(&self) -> &bool {
+        &self.enabled
+    }
+
+    pub fn set_url(&mut self, url: &str) {
+        self.url = url.to_owned();
+    }
+
+    pub fn set_events(&mut self, events: &Vec<WebhookEvent>) {
+        self.events = events.clone();
+    }
+
+    pub fn set_secret(&mut self, secret: &str) {
+        self.secret = secret.to_owned();
+    }
+
+    pub fn set_enabled(&mut self, enabled: &bool) {
+        self.enabled = *enabled;
+    }
+
+    #[instrument(name = "WebhookDao::db_insert", skip_all)]
+    pub async fn db_insert(&self, db: &Db) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => db.insert_webhook(&self.to_scylladb_model()).await,
+            Db::PostgresqlDb(db) => db.insert_webhook(&self.to_postgresdb_model()).await,
+            Db::MysqlDb(db) => db.insert_webhook(&self.to_mysqldb_model()).await,
+            Db::SqliteDb(db) => db.insert_webhook(&self.to_sqlitedb_model()).await,
+        }
+    }
+
+    #[instrument(name = "WebhookDao::db_select", skip_all)]
+    pub async fn db_select(db: &Db, id: &Uuid) -> Result<Self> {
+        let not_found = |err| DaoError::from_no_rows(err, format!("Webhook '{id}' is not found"));
+        match db {
+            Db::ScyllaDb(db) => {
+                Self::from_scylladb_model(&db.select_webhook(id).await.map_err(not_found)?)
+            }
+            Db::PostgresqlDb(db) => {
+                Self::from_postgresdb_model(&db.select_webhook(id).await.map_err(not_found)?)
+            }
+            Db::MysqlDb(db) => {
+                Self::from_mysqldb_model(&db.select_webhook(id).await.map_err(not_found)?)
+            }
+            Db::SqliteDb(db) => {
+                Self::from_sqlitedb_model(&db.select_webhook(id).await.map_err(not_found)?)
+            }
+        }
+    }
+
+    #[instrument(name = "WebhookDao::db_select_many_by_project_id", skip_all)]
+    pub async fn db_select_many_by_project_id(db: &Db, project_id: &Uuid) -> Result<Vec<Self>> {
+        match db {
+            Db::ScyllaDb(db) => {
+                let mut webhooks_data = Vec::new();
+                let webhooks = db.select_many_webhooks_by_project_id(project_id).await?;
+                for webhook in webhooks {
+                    webhooks_data.push(Self::from_scylladb_model(&webhook?)?);
+                }
+                Ok(webhooks_data)
+            }
+            Db::PostgresqlDb(db) => {
+                let webhooks = db.select_many_webhooks_by_project_id(project_id).await?;
+                let mut webhooks_data = Vec::with_capacity(webhooks.len());
+                for webhook in &webhooks {
+                    webhooks_data.push(Self::from_postgresdb_model(webhook)?);
+                }
+                Ok(webhooks_data)
+            }
+            Db::MysqlDb(db) => {
+                let webhooks = db.select_many_webhooks_by_project_id(project_id).await?;
+                let mut webhooks_data = Vec::with_capacity(webhooks.len());
+                for webhook in &webhooks {
+                    webhooks_data.push(Self::from_mysqldb_model(webhook)?);
+                }
+                Ok(webhooks_data)
+            }
+            Db::SqliteDb(db) => {
+                let webhooks = db.select_many_webhooks_by_project_id(project_id).await?;
+                let mut webhooks_data = Vec::with_capacity(webhooks.len());
+                for webhook in &webhooks {
+                    webhooks_data.push(Self::from_sqlitedb_model(webhook)?);
+                }
+                Ok(webhooks_data)
+            }
+        }
+    }
+
+    #[instrument(name = "WebhookDao::db_select_many_by_collection_id", skip_all)]
+    pub async fn db_select_many_by_collection_id(
+        db: &Db,
+        collection_id: &Uuid,
+    ) -> Result<Vec<Self>> {
+        match db {
+            Db::ScyllaDb(db) => {
+                let mut webhooks_data = Vec::new();
+                let webhooks = db
+                    .select_many_webhooks_by_collection_id(collection_id)
+                    .await?;
+                for webhook in webhooks {
+                    webhooks_data.push(Self::from_scylladb_model(&webhook?)?);
+                }
+                Ok(webhooks_data)
+            }
+            Db::PostgresqlDb(db) => {
+                let webhooks = db
+                    .select_many_webhooks_by_collection_id(collection_id)
+                    .await?;
+                let mut webhooks_data = Vec::with_capacity(webhooks.len());
+                for webhook in &webhooks {
+                    webhooks_data.push(Self::from_postgresdb_model(webhook)?);
+                }
+                Ok(webhooks_data)
+            }
+            Db::MysqlDb(db) => {
+                let webhooks = db
+                    .select_many_webhooks_by_collection_id(collection_id)
+                    .await?;
+                let mut webhooks_data = Vec::with_capacity(webhooks.len());
+                for webhook in &webhooks {
+                    webhooks_data.push(Self::from_mysqldb_model(webhook)?);
+                }
+                Ok(webhooks_data)
+            }
+            Db::SqliteDb(db) => {
+                let webhooks = db
+                    .select_many_webhooks_by_collection_id(collection_id)
+                    .await?;
+                let mut webhooks_data = Vec::with_capacity(webhooks.len());
+                for webhook in &webhooks {
+                    webhooks_data.push(Self::from_sqlitedb_model(webhook)?);
+                }
+                Ok(webhooks_data)
+            }
+        }
+    }
+
+    #[instrument(name = "WebhookDao::db_update", skip_all)]
+    pub async fn db_update(&mut self, db: &Db) -> Result<()> {
+        self.updated_at = Utc::now();
+        match db {
+            Db::ScyllaDb(db) => db.update_webhook(&self.to_scylladb_model()).await,
+            Db::PostgresqlDb(db) => db.update_webhook(&self.to_postgresdb_model()).await,
+            Db::MysqlDb(db) => db.update_webhook(&self.to_mysqldb_model()).await,
+            Db::SqliteDb(db) => db.update_webhook(&self.to_sqlitedb_model()).await,
+        }
+    }
+
+    #[instrument(name = "WebhookDao::db_delete", skip_all)]
+    pub async fn db_delete(db: &Db, id: &Uuid) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => db.delete_webhook(id).await,
+            Db::PostgresqlDb(db) => db.delete_webhook(id).await,
+            Db::MysqlDb(db) => db.delete_webhook(id).await,
+            Db::SqliteDb(db) => db.delete_webhook(id).await,
+        }
+    }
+
+    #[instrument(name = "WebhookDao::db_delete_many_by_collection_id", skip_all)]
+    pub async fn db_delete_many_by_collection_id(db: &Db, collection_id: &Uuid) -> Result<()> {
+        match db {
+            Db::ScyllaDb(db) => {
+                db.delete_many_webhooks_by_collection_id(collection_id)
+                    .await
+            }
+            Db::PostgresqlDb(db) => {
+                db.delete_many_webhooks_by_collection_id(collection_id)
+                    .await
+            }
+            Db::MysqlDb(db) => {
+                db.delete_many_webhooks_by_collection_id(collection_id)
+                    .await
+            }
+            Db::SqliteDb(db) => {
+                db.delete_many_webhooks_by_collection_id(collection_id)
+                    .await
+            }
+        }
+    }
+
+    fn from_scylladb_model(model: &WebhookScyllaModel) -> Result<Self> {
+        let mut events = Vec::with_capacity(model.events().len());
+        for event in model.events() {
+            events.push(WebhookEvent::from_str(event)?);
+        }
+        Ok(Self {
+            id: *model.id(),
+            created_at: conversion::scylla_cql_timestamp_to_datetime_utc(model.created_at())?,
+            updated_at: conversion::scylla_cql_timestamp_to_datetime_utc(model.updated_at())?,
+            admin_id: *model.admin_id(),
+            project_id: *model.project_id(),
+            collection_id: *model.collection_id(),
+            url: model.url().to_owned(),
+            events,
+            secret: model.secret().to_owned(),
+            enabled: *model.enabled(),
+        })
+    }
+
+    fn to_scylladb_model(&self) -> WebhookScyllaModel {
+        WebhookScyllaModel::new(
+            &self.id,
+            &conversion::datetime_utc_to_scylla_cql_timestamp(&self.created_at),
+            &conversion::datetime_utc_to_scylla_cql_timestamp(&self.updated_at),
+            &self.admin_id,
+            &self.project_id,
+            &self.collection_id,
+            &self.url,
+            &self
+                .events
+                .iter()
+                .map(|event| event.to_str().to_owned())
+                .collect(),
+            &self.secret,
+            &self.enabled,
+        )
+    }
+
+    fn from_postgresdb_model(model: &WebhookPostgresModel) -> Result<Self> {
+        let mut events = Vec::with_capacity(model.events().len());
+        for event in model.events().iter() {
+            events.push(WebhookEvent::from_str(event)?);
+        }
+        Ok(Self {
+            id: *model.id(),
+            created_at: *model.created_at(),
+            updated_at: *model.updated_at(),
+            admin_id: *model.admin_id(),
+            project_id: *model.project_id(),
+            collection_id: *model.collection_id(),
+            url: model.url().to_owned(),
+            events,
+            secret: model.secret().to_owned(),
+            enabled: *model.enabled(),
+        })
+    }
+
+    fn to_postgresdb_model(&self) -> WebhookPostgresModel {
+        WebhookPostgresModel::new(
+            &self.id,
+            &self.created_at,
+            &self.updated_at,
+            &self.admin_id,
+            &self.project_id,
+            &self.collection_id,
+            &self.url,
+            &sqlx::types::Json(
+                self.events
+                    .iter()
+                    .map(|event| event.to_str().to_owned())
+                    .collect(),
+            ),
+            &self.secret,
+            &self.enabled,
+        )
+    }
+
+    fn from_mysqldb_model(model: &WebhookMysqlModel) -> Result<Self> {
+        let mut events = Vec::with_capacity(model.events().len());
+        for event in model.events().iter() {
+            events.push(WebhookEvent::from_str(event)?);
+        }
+        Ok(Self {
+            id: *model.id(),
+            created_at: *model.created_at(),
+            updated_at: *model.updated_at(),
+            admin_id: *model.admin_id(),
+            project_id: *model.project_id(),
+            collection_id: *model.collection_id(),
+            url: model.url().to_owned(),
+            events,
+            secret: model.secret().to_owned(),
+            enabled: *model.enabled(),
+        })
+    }
+
+    fn to_mysqldb_model(&self) -> WebhookMysqlModel {
+        WebhookMysqlModel::new(
+            &self.id,
+            &self.created_at,
+            &self.updated_at,
+            &self.admin_id,
+            &self.project_id,
+            &self.collection_id,
+            &self.url,
+            &sqlx::types::Json(
+                self.events
+                    .iter()
+                    .map(|event| event.to_str().to_owned())
+                    .collect(),
+            ),
+            &self.secret,
+            &self.enabled,
+        )
+    }
+
+    fn from_sqlitedb_model(model: &WebhookSqliteModel) -> Result<Self> {
+        let mut events = Vec::with_capacity(model.events().len());
+        for event in model.events().iter() {
+            events.push(WebhookEvent::from_str(event)?);
+        }
+        Ok(Self {
+            id: *model.id(),
+            created_at: *model.created_at(),
+            updated_at: *model.updated_at(),
+            admin_id: *model.admin_id(),
+            project_id: *model.project_id(),
+            collection_id: *model.collection_id(),
+            url: model.url().to_owned(),
+            events,
+            secret: model.secret().to_owned(),
+            enabled: *model.enabled(),
+        })
+    }
+
+    fn to_sqlitedb_model(&self) -> WebhookSqliteModel {
+        WebhookSqliteModel::new(
+            &self.id,
+            &self.created_at,
+            &self.updated_at,
+            &self.admin_id,
+            &self.project_id,
+            &self.collection_id,
+            &self.url,
+            &sqlx::types::Json(
+                self.events
+                    .iter()
+                    .map(|event| event.to_str().to_owned())
+                    .collect(),
+            ),
+            &self.secret,
+            &self.enabled,
+        )
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum WebhookEvent {
+    InsertOne,
+    UpdateOne,
+    DeleteOne,
+}
+
+impl WebhookEvent {
+    pub fn from_str(str: &str) -> Result<Self> {
+        match str {
+            "insert_one" => Ok(Self::InsertOne),
+            "update_one" => Ok(Self::UpdateOne),
+            "delete_one" => Ok(Self::DeleteOne),
+            _ => Err(Error::msg(format!("Unknown webhook event '{str}'"))),
+        }
+    }
+
+    pub fn to_str(&self) -> &str {
+        match self {
+            Self::InsertOne => "insert_one",
+            Self::UpdateOne => "update_one",
+            Self::DeleteOne => "delete_one",
+        }
+    }
+}
@@ -1,19 +1,47 @@
+use std::{
+    fs,
+    ops::{Deref, DerefMut},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::{
+    future,
+    stream::{self, BoxStream},
+    StreamExt,
+};
 use sqlx::{
-    mysql::{MySqlArguments, MySqlPoolOptions, MySqlQueryResult, MySqlRow},
+    mysql::{
+        MySqlArguments, MySqlConnectOptions, MySqlConnection, MySqlPoolOptions, MySqlQueryResult,
+        MySqlRow, MySqlSslMode,
+    },
+    pool::PoolConnection,
     query::{Query, QueryAs},
-    Error, MySql, Pool,
+    Error, Execute, Executor, MySql, Pool, Transaction,
 };
+use tokio::sync::{Mutex, MutexGuard};
+use tracing::Instrument;
 
 use crate::query::{
-    admin, admin_password_reset, bucket, bucket_rule, collection, collection_rule, file, log,
-    project, registration, token,
+    admin, admin_password_reset, admin_project_member, audit, bucket, bucket_rule, collection,
+    collection_rule, file, idempotency, log, migration, project, registration, sign_in_attempt,
+    system, token, webhook,
 };
 
 pub struct MysqlDb {
     pool: Pool<MySql>,
+    slow_query_threshold: Option<Duration>,
+    slow_queries: Arc<AtomicU64>,
+    in_flight: Arc<AtomicU32>,
     table_registration_ttl: i64,
     table_reset_password_ttl: i64,
     table_log_ttl: i64,
+    table_idempotency_ttl: i64,
+    tx: Option<Mutex<Transaction<'static, MySql>>>,
 }
 
 impl MysqlDb {
@@ -24,26 +52,132 @@ impl MysqlDb {
         port: &str,
         db_name: &str,
         max_connections: &u32,
+        min_connections: &Option<u32>,
+        acquire_timeout: &Option<Duration>,
+        idle_timeout: &Option<Duration>,
+        max_lifetime: &Option<Duration>,
+        ssl_mode: &Option<String>,
+        ssl_root_cert: &Option<String>,
+        ssl_client_cert: &Option<String>,
+        ssl_client_key: &Option<String>,
+        statement_timeout: &Option<Duration>,
+        slow_query_threshold: &Option<Duration>,
         table_registration_ttl: &i64,
         table_reset_password_ttl: &i64,
         table_log_ttl: &i64,
+        table_idempotency_ttl: &i64,
     ) -> Self {
         hb_log::info(Some("⚡"), "[MySQL] Initializing component");
 
         let url = format!("mysql://{user}:{password}@{host}:{port}/{db_name}");
-        let pool = MySqlPoolOptions::new()
-            .max_connections(*max_connections)
-            .connect(&url)
+        let mut connect_opts = match MySqlConnectOptions::from_str(&url) {
+            Ok(connect_opts) => connect_opts,
+            Err(err) => {
+                hb_log::panic(None, &format!("[MySQL] Invalid connection options: {err}"));
+                unreachable!()
+            }
+        };
+        connect_opts = connect_opts.ssl_mode(match ssl_mode.as_deref() {
+            None | Some("prefer") => MySqlSslMode::Preferred,
+            Some("disable") => MySqlSslMode::Disabled,
+            Some("require") => MySqlSslMode::Required,
+            Some("verify-ca") => MySqlSslMode::VerifyCa,
+            Some("verify-full") => MySqlSslMode::VerifyIdentity,
+            Some(ssl_mode) => {
+                hb_log::panic(
+                    None,
+                    &format!(
+                        "[MySQL] Unknown ssl_mode '{ssl_mode}', expected disable, prefer, require, verify-ca or verify-full"
+                    ),
+                );
+                unreachable!()
+            }
+        });
+
+        // The certificates are only read when connecting, so missing ones are caught here to tell
+        // them apart from network problems
+        for (field, path) in [
+            ("ssl_root_cert", ssl_root_cert),
+            ("ssl_client_cert", ssl_client_cert),
+            ("ssl_client_key", ssl_client_key),
+        ] {
+            if let Some(path) = path {
+                if let Err(err) = fs::metadata(path) {
+                    hb_log::panic(None, &format!("[MySQL] Can't read {field} '{path}': {err}"));
+                }
+            }
+        }
+        if let Some(ssl_root_cert) = ssl_root_cert {
+            connect_opts = connect_opts.ssl_ca(ssl_root_cert);
+        }
+        if let Some(ssl_client_cert) = ssl_client_cert {
+            connect_opts = connect_opts.ssl_client_cert(ssl_client_cert);
+        }
+        if let Some(ssl_client_key) = ssl_client_key {
+            connect_opts = connect_opts.ssl_client_key(ssl_client_key);
+        }
+
+        let mut pool_opts = MySqlPoolOptions::new().max_connections(*max_connections);
+        if let Some(min_connections) = min_connections {
+            pool_opts = pool_opts.min_connections(*min_connections);
+        }
+        if let Some(acquire_timeout) = acquire_timeout {
+            pool_opts = pool_opts.acquire_timeout(*acquire_timeout);
+        }
+        if let Some(idle_timeout) = idle_timeout {
+            pool_opts = pool_opts.idle_timeout(*idle_timeout);
+        }
+        if let Some(max_lifetime) = max_lifetime {
+            pool_opts = pool_opts.max_lifetime(*max_lifetime);
+        }
+
+        // max_execution_time only bounds SELECT statements, writes aren't interrupted halfway
+        let statement_timeout_ms = statement_timeout.map(|timeout| timeout.as_millis());
+        let pool = match pool_opts
+            .after_connect(move |conn, _| {
+                Box::pin(async move {
+                    if let Some(statement_timeout_ms) = statement_timeout_ms {
+                        conn.execute(
+                            format!("SET SESSION max_execution_time = {statement_timeout_ms}")
+                                .as_str(),
+                        )
+                        .await?;
+                    }
+                    Ok(())
+                })
+            })
+            .connect_with(connect_opts)
             .await
-            .unwrap();
+        {
+            Ok(pool) => pool,
+            Err(err) => {
+                let msg = match err {
+                    Error::Tls(err) => format!(
+                        "TLS handshake with {host}:{port} failed, check ssl_mode and the certificates: {err}"
+                    ),
+                    Error::Configuration(err) => {
+                        format!("Invalid TLS or connection configuration: {err}")
+                    }
+                    Error::Io(err) => format!("Can't reach {host}:{port}: {err}"),
+                    err => format!("Failed to connect to {host}:{port}: {err}"),
+                };
+                hb_log::panic(None, &format!("[MySQL] {msg}"));
+                unreachable!()
+            }
+        };
 
         Self::init(&pool).await;
 
         Self {
             pool,
+            slow_query_threshold: *slow_query_threshold,
+            slow_queries: Arc::new(AtomicU64::new(0)),
+            in_flight: Arc::new(AtomicU32::new(0)),
             table_registration_ttl: *table_registration_ttl,
             table_reset_password_ttl: *table_reset_password_ttl,
             table_log_ttl: *table_log_ttl,
+            table_idempotency_ttl: *table_idempotency_ttl,
+            tx: None,
         }
     }
 
@@ -51,49 +185,224 @@ impl MysqlDb {
         &self,
         query: Query<'_, MySql, MySqlArguments>,
     ) -> Result<MySqlQueryResult, Error> {
-        query.persistent(false).execute(&self.pool).await
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .persistent(false)
+            .execute(&mut *conn)
+            .instrument(hb_log::db_span("mysql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn execute(
         &self,
         query: Query<'_, MySql, MySqlArguments>,
     ) -> Result<MySqlQueryResult, Error> {
-        query.execute(&self.pool).await
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .execute(&mut *conn)
+            .instrument(hb_log::db_span("mysql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_one_unprepared<T: Send + Unpin + for<'r> sqlx::FromRow<'r, MySqlRow>>(
         &self,
         query: QueryAs<'_, MySql, T, MySqlArguments>,
     ) -> Result<T, Error> {
-        Ok(query.persistent(false).fetch_one(&self.pool).await?)
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .persistent(false)
+            .fetch_one(&mut *conn)
+            .instrument(hb_log::db_span("mysql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_one<T: Send + Unpin + for<'r> sqlx::FromRow<'r, MySqlRow>>(
         &self,
         query: QueryAs<'_, MySql, T, MySqlArguments>,
     ) -> Result<T, Error> {
-        Ok(query.fetch_one(&self.pool).await?)
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .fetch_one(&mut *conn)
+            .instrument(hb_log::db_span("mysql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_one_row(
         &self,
         query: Query<'_, MySql, MySqlArguments>,
     ) -> Result<MySqlRow, Error> {
-        Ok(query.fetch_one(&self.pool).await?)
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .fetch_one(&mut *conn)
+            .instrument(hb_log::db_span("mysql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_all<T: Send + Unpin + for<'r> sqlx::FromRow<'r, MySqlRow>>(
         &self,
         query: QueryAs<'_, MySql, T, MySqlArguments>,
     ) -> Result<Vec<T>, Error> {
-        query.fetch_all(&self.pool).await
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .fetch_all(&mut *conn)
+            .instrument(hb_log::db_span("mysql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_all_rows(
         &self,
         query: Query<'_, MySql, MySqlArguments>,
     ) -> Result<Vec<MySqlRow>, Error> {
-        query.fetch_all(&self.pool).await
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .fetch_all(&mut *conn)
+            .instrument(hb_log::db_span("mysql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
+    }
+
+    pub fn fetch_rows<'a>(
+        &'a self,
+        query: Query<'a, MySql, MySqlArguments>,
+    ) -> BoxStream<'a, Result<MySqlRow, Error>> {
+        match &self.tx {
+            // The stream can't hold on to the connection of the transaction, so the rows are
+            // fetched all at once instead
+            Some(_) => stream::once(self.fetch_all_rows(query))
+                .flat_map(|result| match result {
+                    Ok(rows) => stream::iter(rows.into_iter().map(Ok)).left_stream(),
+                    Err(err) => stream::once(future::ready(Err(err))).right_stream(),
+                })
+                .boxed(),
+            None => query.fetch(&self.pool),
+        }
+    }
+
+    // Used by the statements that have to be written together. They can't be run on a handle made
+    // by transaction, which already is one.
+    pub async fn begin(&self) -> anyhow::Result<Transaction<'_, MySql>> {
+        if self.tx.is_some() {
+            return Err(anyhow::Error::msg(
+                "[MySQL] Can't begin a transaction inside another one",
+            ));
+        }
+        Ok(self.pool.begin().await?)
+    }
+
+    // Makes a handle that runs every statement in one transaction, which is rolled back when the
+    // handle is dropped without being committed
+    pub async fn transaction(&self) -> anyhow::Result<Self> {
+        if self.tx.is_some() {
+            return Err(anyhow::Error::msg(
+                "[MySQL] Can't begin a transaction inside another one",
+            ));
+        }
+        Ok(Self {
+            pool: self.pool.clone(),
+            slow_query_threshold: self.slow_query_threshold,
+            slow_queries: self.slow_queries.clone(),
+            in_flight: self.in_flight.clone(),
+            table_registration_ttl: self.table_registration_ttl,
+            table_reset_password_ttl: self.table_reset_password_ttl,
+            table_log_ttl: self.table_log_ttl,
+            table_idempotency_ttl: self.table_idempotency_ttl,
+            tx: Some(Mutex::new(self.pool.begin().await?)),
+        })
+    }
+
+    pub async fn commit(self) -> anyhow::Result<()> {
+        match self.tx {
+            Some(tx) => Ok(tx.into_inner().commit().await?),
+            None => Err(anyhow::Error::msg("[MySQL] Not a transaction")),
+        }
+    }
+
+    // Statements of a handle made by transaction go through its connection, any other through a
+    // connection of the pool
+    async fn connection(&self) -> Result<Connection<'_>, Error> {
+        match &self.tx {
+            Some(tx) => Ok(Connection::Transaction(tx.lock().await)),
+            None => Ok(Connection::Pool(self.pool.acquire().await?)),
+        }
+    }
+
+    pub async fn ping(&self) -> Result<(), Error> {
+        sqlx::query(system::PING)
+            .persistent(false)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // Costs a single Instant::now() per statement when no threshold is set. Bind values are never
+    // part of the statement text, so nothing sensitive ends up in the log.
+    fn check_slow_query(&self, sql: &str, start: &Instant) {
+        if let Some(slow_query_threshold) = self.slow_query_threshold {
+            let elapsed = start.elapsed();
+            if elapsed >= slow_query_threshold {
+                self.slow_queries.fetch_add(1, Ordering::Relaxed);
+                hb_log::warn(
+                    None,
+                    &format!(
+                        "[MySQL] Slow query on table '{}' took {elapsed:?}: {sql}",
+                        table_name(sql)
+                    ),
+                );
+            }
+        }
+    }
+
+    pub fn slow_queries(&self) -> u64 {
+        self.slow_queries.load(Ordering::Relaxed)
+    }
+
+    pub fn pool_size(&self) -> u32 {
+        self.pool.size()
+    }
+
+    pub fn pool_idle(&self) -> u32 {
+        self.pool.num_idle() as u32
+    }
+
+    // Statements in flight beyond the connections in use are the ones waiting for a connection.
+    // Transactions hold a connection without being counted, so this can fall short under load.
+    pub fn pool_waiting(&self) -> u32 {
+        let busy = self.pool_size().saturating_sub(self.pool_idle());
+        self.in_flight.load(Ordering::Relaxed).saturating_sub(busy)
     }
 
     pub fn table_registration_ttl(&self) -> &i64 {
@@ -108,7 +417,13 @@ impl MysqlDb {
         &self.table_log_ttl
     }
 
+    pub fn table_idempotency_ttl(&self) -> &i64 {
+        &self.table_idempotency_ttl
+    }
+
     async fn init(pool: &Pool<MySql>) {
+        migration::init(pool).await;
+
         tokio::join!(
             admin::init(pool),
             project::init(pool),
@@ -117,10 +432,77 @@ impl MysqlDb {
             file::init(pool),
             token::init(pool),
             collection_rule::init(pool),
+            admin_project_member::init(pool),
             bucket_rule::init(pool),
             registration::init(pool),
             admin_password_reset::init(pool),
             log::init(pool),
+            audit::init(pool),
+            idempotency::init(pool),
+            sign_in_attempt::init(pool),
+            webhook::init(pool),
         );
     }
 }
+
+enum Connection<'a> {
+    Pool(PoolConnection<MySql>),
+    Transaction(MutexGuard<'a, Transaction<'static, MySql>>),
+}
+
+impl Deref for Connection<'_> {
+    type Target = MySqlConnection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Pool(conn) => conn,
+            Self::Transaction(tx) => tx,
+        }
+    }
+}
+
+impl DerefMut for Connection<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Pool(conn) => conn,
+            Self::Transaction(tx) => tx,
+        }
+    }
+}
+
+// Counts a statement as in flight until it finishes or is cancelled
+struct InFlight<'a>(&'a AtomicU32);
+
+impl<'a> InFlight<'a> {
+    fn new(count: &'a AtomicU32) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self(count)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// The first table a statement names, which tells the collection a record query is on
+fn table_name(sql: &str) -> &str {
+    let mut words = sql.split_whitespace();
+    while let Some(word) = words.next() {
+        if ["FROM", "INTO", "UPDATE", "TABLE", "JOIN"]
+            .iter()
+            .any(|keyword| word.eq_ignore_ascii_case(keyword))
+        {
+            for word in words.by_ref() {
+                if !["IF", "NOT", "EXISTS", "ONLY"]
+                    .iter()
+                    .any(|keyword| word.eq_ignore_ascii_case(keyword))
+                {
+                    return word.trim_matches(|c| c == '"' || c == '`' || c == '(');
+                }
+            }
+        }
+    }
+    "-"
+}
@@ -15,6 +15,9 @@ pub struct TokenModel {
     token: String,
     allow_anonymous: bool,
     expired_at: Option<DateTime<Utc>>,
+    disabled_at: Option<DateTime<Utc>>,
+    last_used_at: Option<DateTime<Utc>>,
+    request_count: i64,
 }
 
 impl TokenModel {
@@ -28,6 +31,9 @@ impl TokenModel {
         token: &str,
         allow_anonymous: &bool,
         expired_at: &Option<DateTime<Utc>>,
+        disabled_at: &Option<DateTime<Utc>>,
+        last_used_at: &Option<DateTime<Utc>>,
+        request_count: &i64,
     ) -> Self {
         Self {
             id: *id,
@@ -39,6 +45,9 @@ impl TokenModel {
             token: token.to_owned(),
             allow_anonymous: *allow_anonymous,
             expired_at: *expired_at,
+            disabled_at: *disabled_at,
+            last_used_at: *last_used_at,
+            request_count: *request_count,
         }
     }
 
@@ -77,4 +86,16 @@ impl TokenModel {
     pub fn expired_at(&self) -> &Option<DateTime<Utc>> {
         &self.expired_at
     }
+
+    pub fn disabled_at(&self) -> &Option<DateTime<Utc>> {
+        &self.disabled_at
+    }
+
+    pub fn last_used_at(&self) -> &Option<DateTime<Utc>> {
+        &self.last_used_at
+    }
+
+    pub fn request_count(&self) -> &i64 {
+        &self.request_count
+    }
 }
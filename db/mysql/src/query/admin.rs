@@ -4,16 +4,16 @@ use uuid::Uuid;
 
 use crate::{db::MysqlDb, model::admin::AdminModel};
 
-const INSERT: &str = "INSERT INTO `admins` (`id`, `created_at`, `updated_at`, `email`, `password_hash`) VALUES (?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT `id`, `created_at`, `updated_at`, `email`, `password_hash` FROM `admins` WHERE `id` = ?";
-const SELECT_BY_EMAIL: &str= "SELECT `id`, `created_at`, `updated_at`, `email`, `password_hash` FROM `admins` WHERE `email` = ?";
-const UPDATE: &str = "UPDATE `admins` SET `updated_at` = ?, `email` = ?, `password_hash` = ? WHERE `id` = ?";
+const INSERT: &str = "INSERT INTO `admins` (`id`, `created_at`, `updated_at`, `email`, `password_hash`, `totp_secret`, `totp_enabled_at`, `totp_backup_codes`) VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT `id`, `created_at`, `updated_at`, `email`, `password_hash`, `totp_secret`, `totp_enabled_at`, `totp_backup_codes` FROM `admins` WHERE `id` = ?";
+const SELECT_BY_EMAIL: &str= "SELECT `id`, `created_at`, `updated_at`, `email`, `password_hash`, `totp_secret`, `totp_enabled_at`, `totp_backup_codes` FROM `admins` WHERE `email` = ?";
+const UPDATE: &str = "UPDATE `admins` SET `updated_at` = ?, `email` = ?, `password_hash` = ?, `totp_secret` = ?, `totp_enabled_at` = ?, `totp_backup_codes` = ? WHERE `id` = ?";
 const DELETE: &str = "DELETE FROM `admins` WHERE `id` = ?";
 
 pub async fn init(pool: &Pool<MySql>) {
     hb_log::info(Some("🔧"), "[MySQL] Setting up admins table");
 
-    pool.execute("CREATE TABLE IF NOT EXISTS `admins` (`id` binary(16), `created_at` timestamp(6), `updated_at` timestamp(6), `email` text, `password_hash` text, PRIMARY KEY (`id`))").await.unwrap();
+    pool.execute("CREATE TABLE IF NOT EXISTS `admins` (`id` binary(16), `created_at` timestamp(6), `updated_at` timestamp(6), `email` text, `password_hash` text, `totp_secret` text, `totp_enabled_at` timestamp(6), `totp_backup_codes` json, PRIMARY KEY (`id`))").await.unwrap();
 
     tokio::try_join!(
         pool.prepare(INSERT),
@@ -33,7 +33,10 @@ impl MysqlDb {
                 .bind(model.created_at())
                 .bind(model.updated_at())
                 .bind(model.email())
-                .bind(model.password_hash()),
+                .bind(model.password_hash())
+                .bind(model.totp_secret())
+                .bind(model.totp_enabled_at())
+                .bind(model.totp_backup_codes()),
         )
         .await?;
         Ok(())
@@ -55,6 +58,9 @@ impl MysqlDb {
                 .bind(model.updated_at())
                 .bind(model.email())
                 .bind(model.password_hash())
+                .bind(model.totp_secret())
+                .bind(model.totp_enabled_at())
+                .bind(model.totp_backup_codes())
                 .bind(model.id()),
         )
         .await?;
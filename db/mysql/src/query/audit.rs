@@ -0,0 +1,136 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, MySql, Pool};
+use uuid::Uuid;
+
+use crate::{db::MysqlDb, model::audit::AuditModel};
+
+const INSERT: &str = "INSERT INTO `audits` (`id`, `created_at`, `admin_id`, `project_id`, `action`, `entity_type`, `entity_id`, `diff`, `source_ip`) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT `id`, `created_at`, `admin_id`, `project_id`, `action`, `entity_type`, `entity_id`, `diff`, `source_ip` FROM `audits` WHERE `project_id` = ?";
+const COUNT_MANY_BY_PROJECT_ID: &str = "SELECT COUNT(1) FROM `audits` WHERE `project_id` = ?";
+
+pub async fn init(pool: &Pool<MySql>) {
+    hb_log::info(Some("🔧"), "[MySQL] Setting up audits table");
+
+    pool.execute("CREATE TABLE IF NOT EXISTS `audits` (`id` binary(16), `created_at` timestamp(6), `admin_id` binary(16), `project_id` binary(16), `action` text, `entity_type` text, `entity_id` binary(16), `diff` text, `source_ip` text, PRIMARY KEY (`id`))").await.unwrap();
+
+    tokio::try_join!(
+        pool.prepare(INSERT),
+        pool.prepare(SELECT_MANY_BY_PROJECT_ID),
+        pool.prepare(COUNT_MANY_BY_PROJECT_ID),
+    )
+    .unwrap();
+}
+
+impl MysqlDb {
+    pub async fn insert_audit(&self, value: &AuditModel) -> Result<()> {
+        self.execute(
+            sqlx::query(INSERT)
+                .bind(value.id())
+                .bind(value.created_at())
+                .bind(value.admin_id())
+                .bind(value.project_id())
+                .bind(value.action())
+                .bind(value.entity_type())
+                .bind(value.entity_id())
+                .bind(value.diff())
+                .bind(value.source_ip()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn select_many_audits_by_project_id(
+        &self,
+        project_id: &Uuid,
+        entity_type: &Option<&str>,
+        entity_id: &Option<Uuid>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        before_id: &Option<Uuid>,
+        limit: &Option<i32>,
+    ) -> Result<Vec<AuditModel>> {
+        let mut sql = SELECT_MANY_BY_PROJECT_ID.to_owned();
+        sql += &Self::audits_filter(entity_type, entity_id, from, to);
+        if before_id.is_some() {
+            sql += " AND `id` < ?";
+        }
+        sql += " ORDER BY `id` DESC";
+        if limit.is_some() {
+            sql += " LIMIT ?";
+        }
+
+        let mut query = sqlx::query_as(&sql).bind(project_id);
+        if let Some(entity_type) = entity_type {
+            query = query.bind(entity_type);
+        }
+        if let Some(entity_id) = entity_id {
+            query = query.bind(entity_id);
+        }
+        if let Some(from) = from {
+            query = query.bind(from);
+        }
+        if let Some(to) = to {
+            query = query.bind(to);
+        }
+        if let Some(before_id) = before_id {
+            query = query.bind(before_id);
+        }
+        if let Some(limit) = limit {
+            query = query.bind(limit);
+        }
+
+        Ok(self.fetch_all(query).await?)
+    }
+
+    pub async fn count_many_audits_by_project_id(
+        &self,
+        project_id: &Uuid,
+        entity_type: &Option<&str>,
+        entity_id: &Option<Uuid>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+    ) -> Result<i64> {
+        let sql = COUNT_MANY_BY_PROJECT_ID.to_owned()
+            + &Self::audits_filter(entity_type, entity_id, from, to);
+
+        let mut query = sqlx::query_as(&sql).bind(project_id);
+        if let Some(entity_type) = entity_type {
+            query = query.bind(entity_type);
+        }
+        if let Some(entity_id) = entity_id {
+            query = query.bind(entity_id);
+        }
+        if let Some(from) = from {
+            query = query.bind(from);
+        }
+        if let Some(to) = to {
+            query = query.bind(to);
+        }
+
+        Ok(self.fetch_one::<(i64,)>(query).await?.0)
+    }
+
+    // The values are bound in the same order, right after the project id
+    fn audits_filter(
+        entity_type: &Option<&str>,
+        entity_id: &Option<Uuid>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+    ) -> String {
+        let mut sql = String::new();
+        if entity_type.is_some() {
+            sql += " AND `entity_type` = ?";
+        }
+        if entity_id.is_some() {
+            sql += " AND `entity_id` = ?";
+        }
+        if from.is_some() {
+            sql += " AND `created_at` >= ?";
+        }
+        if to.is_some() {
+            sql += " AND `created_at` <= ?";
+        }
+        sql
+    }
+}
@@ -4,21 +4,27 @@ use uuid::Uuid;
 
 use crate::{db::MysqlDb, model::collection::CollectionModel};
 
-const INSERT: &str = "INSERT INTO `collections` (`id`, `created_at`, `updated_at`, `project_id`, `name`, `schema_fields`, `opt_auth_column_id`, `opt_ttl`) VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `name`, `schema_fields`, `opt_auth_column_id`, `opt_ttl` FROM `collections` WHERE `id` = ?";
-const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `name`, `schema_fields`, `opt_auth_column_id`, `opt_ttl` FROM `collections` WHERE `project_id` = ? ORDER BY `id` DESC";
-const UPDATE: &str = "UPDATE `collections` SET `updated_at` = ?, `name` = ?, `schema_fields` = ?, `opt_auth_column_id` = ?, `opt_ttl` = ? WHERE `id` = ?";
+const INSERT: &str = "INSERT INTO `collections` (`id`, `created_at`, `updated_at`, `project_id`, `name`, `schema_fields`, `unique_indexes`, `opt_auth_column_id`, `opt_ttl`, `opt_soft_delete`, `opt_per_record_ttl`, `opt_mqtt_publish`) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `name`, `schema_fields`, `unique_indexes`, `opt_auth_column_id`, `opt_ttl`, `opt_soft_delete`, `opt_per_record_ttl`, `opt_mqtt_publish` FROM `collections` WHERE `id` = ?";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `name`, `schema_fields`, `unique_indexes`, `opt_auth_column_id`, `opt_ttl`, `opt_soft_delete`, `opt_per_record_ttl`, `opt_mqtt_publish` FROM `collections` WHERE `project_id` = ?";
+const COUNT_MANY_BY_PROJECT_ID: &str = "SELECT COUNT(1) FROM `collections` WHERE `project_id` = ?";
+const SELECT_MANY: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `name`, `schema_fields`, `unique_indexes`, `opt_auth_column_id`, `opt_ttl`, `opt_soft_delete`, `opt_per_record_ttl`, `opt_mqtt_publish` FROM `collections` ORDER BY `id` DESC";
+const UPDATE: &str = "UPDATE `collections` SET `updated_at` = ?, `name` = ?, `schema_fields` = ?, `unique_indexes` = ?, `opt_auth_column_id` = ?, `opt_ttl` = ?, `opt_soft_delete` = ?, `opt_per_record_ttl` = ?, `opt_mqtt_publish` = ? WHERE `id` = ?";
 const DELETE: &str = "DELETE FROM `collections` WHERE `id` = ?";
+const DELETE_COLLECTION_RULES: &str = "DELETE FROM `collection_rules` WHERE `collection_id` = ?";
+const DELETE_WEBHOOKS: &str = "DELETE FROM `webhooks` WHERE `collection_id` = ?";
 
 pub async fn init(pool: &Pool<MySql>) {
     hb_log::info(Some("🔧"), "[MySQL] Setting up collections table");
 
-    pool.execute("CREATE TABLE IF NOT EXISTS `collections` (`id` binary(16), `created_at` timestamp(6), `updated_at` timestamp(6), `project_id` binary(16), `name` text, `schema_fields` json, `opt_auth_column_id` boolean, `opt_ttl` bigint, PRIMARY KEY (`id`))").await.unwrap();
+    pool.execute("CREATE TABLE IF NOT EXISTS `collections` (`id` binary(16), `created_at` timestamp(6), `updated_at` timestamp(6), `project_id` binary(16), `name` text, `schema_fields` json, `unique_indexes` json, `opt_auth_column_id` boolean, `opt_ttl` bigint, `opt_soft_delete` boolean, `opt_per_record_ttl` boolean, `opt_mqtt_publish` boolean, PRIMARY KEY (`id`))").await.unwrap();
 
     tokio::try_join!(
         pool.prepare(INSERT),
         pool.prepare(SELECT),
         pool.prepare(SELECT_MANY_BY_PROJECT_ID),
+        pool.prepare(COUNT_MANY_BY_PROJECT_ID),
+        pool.prepare(SELECT_MANY),
         pool.prepare(UPDATE),
         pool.prepare(DELETE),
     )
@@ -35,8 +41,12 @@ impl MysqlDb {
                 .bind(value.project_id())
                 .bind(value.name())
                 .bind(value.schema_fields())
+                .bind(value.unique_indexes())
                 .bind(value.opt_auth_column_id())
-                .bind(value.opt_ttl()),
+                .bind(value.opt_ttl())
+                .bind(value.opt_soft_delete())
+                .bind(value.opt_per_record_ttl())
+                .bind(value.opt_mqtt_publish()),
         )
         .await?;
         Ok(())
@@ -49,10 +59,72 @@ impl MysqlDb {
     pub async fn select_many_collections_by_project_id(
         &self,
         project_id: &Uuid,
+        after_id: &Option<Uuid>,
+        search: &Option<String>,
+        order_by_name: &bool,
+        order_desc: &bool,
+        limit: &Option<i32>,
     ) -> Result<Vec<CollectionModel>> {
-        Ok(self
-            .fetch_all(sqlx::query_as(SELECT_MANY_BY_PROJECT_ID).bind(project_id))
-            .await?)
+        let (comparison, order_kind) = if *order_desc {
+            ("<", "DESC")
+        } else {
+            (">", "ASC")
+        };
+
+        let mut sql = SELECT_MANY_BY_PROJECT_ID.to_owned();
+        if search.is_some() {
+            sql += " AND INSTR(LOWER(`name`), LOWER(?)) > 0";
+        }
+        if after_id.is_some() {
+            if *order_by_name {
+                sql += &format!(" AND (`name`, `id`) {comparison} (SELECT `name`, `id` FROM `collections` WHERE `id` = ?)");
+            } else {
+                sql += &format!(" AND `id` {comparison} ?");
+            }
+        }
+        if *order_by_name {
+            sql += &format!(" ORDER BY `name` {order_kind}, `id` {order_kind}");
+        } else {
+            sql += &format!(" ORDER BY `id` {order_kind}");
+        }
+        if limit.is_some() {
+            sql += " LIMIT ?";
+        }
+
+        let mut query = sqlx::query_as(&sql).bind(project_id);
+        if let Some(search) = search {
+            query = query.bind(search);
+        }
+        if let Some(after_id) = after_id {
+            query = query.bind(after_id);
+        }
+        if let Some(limit) = limit {
+            query = query.bind(limit);
+        }
+
+        Ok(self.fetch_all(query).await?)
+    }
+
+    pub async fn count_many_collections_by_project_id(
+        &self,
+        project_id: &Uuid,
+        search: &Option<String>,
+    ) -> Result<i64> {
+        let mut sql = COUNT_MANY_BY_PROJECT_ID.to_owned();
+        if search.is_some() {
+            sql += " AND INSTR(LOWER(`name`), LOWER(?)) > 0";
+        }
+
+        let mut query = sqlx::query_as(&sql).bind(project_id);
+        if let Some(search) = search {
+            query = query.bind(search);
+        }
+
+        Ok(self.fetch_one::<(i64,)>(query).await?.0)
+    }
+
+    pub async fn select_many_collections(&self) -> Result<Vec<CollectionModel>> {
+        Ok(self.fetch_all(sqlx::query_as(SELECT_MANY)).await?)
     }
 
     pub async fn update_collection(&self, value: &CollectionModel) -> Result<()> {
@@ -61,16 +133,32 @@ impl MysqlDb {
                 .bind(value.updated_at())
                 .bind(value.name())
                 .bind(value.schema_fields())
+                .bind(value.unique_indexes())
                 .bind(value.opt_auth_column_id())
                 .bind(value.opt_ttl())
+                .bind(value.opt_soft_delete())
+                .bind(value.opt_per_record_ttl())
+                .bind(value.opt_mqtt_publish())
                 .bind(value.id()),
         )
         .await?;
         Ok(())
     }
 
+    // The rules and webhooks of the collection are deleted together with it. Its record table is
+    // dropped beforehand by the caller, since MySQL commits any open transaction on DDL
     pub async fn delete_collection(&self, id: &Uuid) -> Result<()> {
-        self.execute(sqlx::query(DELETE).bind(id)).await?;
+        let mut tx = self.begin().await?;
+        sqlx::query(DELETE_COLLECTION_RULES)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(DELETE_WEBHOOKS)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(DELETE).bind(id).execute(&mut *tx).await?;
+        tx.commit().await?;
         Ok(())
     }
 }
@@ -4,19 +4,20 @@ use uuid::Uuid;
 
 use crate::{db::MysqlDb, model::collection_rule::CollectionRuleModel};
 
-const INSERT: &str = "INSERT INTO `collection_rules` (`id`, `created_at`, `updated_at`, `project_id`, `token_id`, `collection_id`, `find_one`, `find_many`, `insert_one`, `update_one`, `delete_one`) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `token_id`, `collection_id`, `find_one`, `find_many`, `insert_one`, `update_one`, `delete_one` FROM `collection_rules` WHERE `id` = ?";
-const SELECT_BY_TOKEN_ID_AND_COLLECTION_ID: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `token_id`, `collection_id`, `find_one`, `find_many`, `insert_one`, `update_one`, `delete_one` FROM `collection_rules` WHERE `token_id` = ? AND `collection_id` = ?";
-const SELECT_MANY_BY_TOKEN_ID: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `token_id`, `collection_id`, `find_one`, `find_many`, `insert_one`, `update_one`, `delete_one` FROM `collection_rules` WHERE `token_id` = ? ORDER BY `id` DESC";
-const UPDATE: &str = "UPDATE `collection_rules` SET `updated_at` = ?, `find_one` = ?, `find_many` = ?, `insert_one` = ?, `update_one` = ?, `delete_one` = ? WHERE `id` = ?";
+const INSERT: &str = "INSERT INTO `collection_rules` (`id`, `created_at`, `updated_at`, `project_id`, `token_id`, `collection_id`, `find_one`, `find_many`, `insert_one`, `update_one`, `delete_one`, `readable_fields`, `writable_fields`) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `token_id`, `collection_id`, `find_one`, `find_many`, `insert_one`, `update_one`, `delete_one`, `readable_fields`, `writable_fields` FROM `collection_rules` WHERE `id` = ?";
+const SELECT_BY_TOKEN_ID_AND_COLLECTION_ID: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `token_id`, `collection_id`, `find_one`, `find_many`, `insert_one`, `update_one`, `delete_one`, `readable_fields`, `writable_fields` FROM `collection_rules` WHERE `token_id` = ? AND `collection_id` = ?";
+const SELECT_MANY_BY_TOKEN_ID: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `token_id`, `collection_id`, `find_one`, `find_many`, `insert_one`, `update_one`, `delete_one`, `readable_fields`, `writable_fields` FROM `collection_rules` WHERE `token_id` = ? ORDER BY `id` DESC";
+const UPDATE: &str = "UPDATE `collection_rules` SET `updated_at` = ?, `find_one` = ?, `find_many` = ?, `insert_one` = ?, `update_one` = ?, `delete_one` = ?, `readable_fields` = ?, `writable_fields` = ? WHERE `id` = ?";
 const DELETE: &str = "DELETE FROM `collection_rules` WHERE `id` = ?";
 const DELETE_MANY_BY_TOKEN_ID: &str = "DELETE FROM `collection_rules` WHERE `token_id` = ?";
-const DELETE_MANY_BY_COLLECTION_ID: &str = "DELETE FROM `collection_rules` WHERE `collection_id` = ?";
+const DELETE_MANY_BY_COLLECTION_ID: &str =
+    "DELETE FROM `collection_rules` WHERE `collection_id` = ?";
 
 pub async fn init(pool: &Pool<MySql>) {
     hb_log::info(Some("🔧"), "[MySQL] Setting up collection_rules table");
 
-    pool.execute("CREATE TABLE IF NOT EXISTS `collection_rules` (`id` binary(16), `created_at` timestamp(6), `updated_at` timestamp(6), `project_id` binary(16), `token_id` binary(16), `collection_id` binary(16), `find_one` text, `find_many` text, `insert_one` boolean, `update_one` text, `delete_one` text, PRIMARY KEY (`id`))").await.unwrap();
+    pool.execute("CREATE TABLE IF NOT EXISTS `collection_rules` (`id` binary(16), `created_at` timestamp(6), `updated_at` timestamp(6), `project_id` binary(16), `token_id` binary(16), `collection_id` binary(16), `find_one` text, `find_many` text, `insert_one` boolean, `update_one` text, `delete_one` text, `readable_fields` json, `writable_fields` json, PRIMARY KEY (`id`))").await.unwrap();
 
     tokio::try_join!(
         pool.prepare(INSERT),
@@ -45,7 +46,9 @@ impl MysqlDb {
                 .bind(value.find_many())
                 .bind(value.insert_one())
                 .bind(value.update_one())
-                .bind(value.delete_one()),
+                .bind(value.delete_one())
+                .bind(value.readable_fields())
+                .bind(value.writable_fields()),
         )
         .await?;
         Ok(())
@@ -87,6 +90,8 @@ impl MysqlDb {
                 .bind(value.insert_one())
                 .bind(value.update_one())
                 .bind(value.delete_one())
+                .bind(value.readable_fields())
+                .bind(value.writable_fields())
                 .bind(value.id()),
         )
         .await?;
@@ -1,24 +1,35 @@
 use anyhow::{Error, Result};
 use chrono::{Duration, Utc};
-use sqlx::{Executor, MySql, Pool};
+use sqlx::{mysql::MySqlArguments, query::QueryAs, Executor, MySql, Pool};
 use uuid::Uuid;
 
-use crate::{db::MysqlDb, model::file::FileModel};
+use crate::{
+    db::MysqlDb,
+    model::file::{FileFilterModel, FileModel, FileOrderModel},
+};
 
-const INSERT: &str = "INSERT INTO `files` (`id`, `created_by`, `created_at`, `updated_at`, `bucket_id`, `file_name`, `content_type`, `size`, `public`) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT `id`, `created_by`, `created_at`, `updated_at`, `bucket_id`, `file_name`, `content_type`, `size`, `public` FROM `files` WHERE `id` = ?";
-const SELECT_MANY_BY_BUCKET_ID: &str = "SELECT `id`, `created_by`, `created_at`, `updated_at`, `bucket_id`, `file_name`, `content_type`, `size`, `public` FROM `files` WHERE `bucket_id` = ?";
+const INSERT: &str = "INSERT INTO `files` (`id`, `created_by`, `created_at`, `updated_at`, `bucket_id`, `file_name`, `content_type`, `size`, `public`, `pending`, `checksum`) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT `id`, `created_by`, `created_at`, `updated_at`, `bucket_id`, `file_name`, `content_type`, `size`, `public`, `pending`, `checksum` FROM `files` WHERE `id` = ?";
+const SELECT_MANY_BY_BUCKET_ID: &str = "SELECT `id`, `created_by`, `created_at`, `updated_at`, `bucket_id`, `file_name`, `content_type`, `size`, `public`, `pending`, `checksum` FROM `files` WHERE `bucket_id` = ?";
 const COUNT_MANY_BY_BUCKET_ID: &str = "SELECT COUNT(1) FROM `files` WHERE `bucket_id` = ?";
-const SELECT_MANY_BY_CREATED_BY_AND_BUCKET_ID: &str = "SELECT `id`, `created_by`, `created_at`, `updated_at`, `bucket_id`, `file_name`, `content_type`, `size`, `public` FROM `files` WHERE `created_by` = ? AND `bucket_id` = ?";
-const COUNT_MANY_BY_CREATED_BY_AND_BUCKET_ID: &str = "SELECT COUNT(1) FROM `files` WHERE `created_by` = ? AND `bucket_id` = ?";
-const SELECT_MANY_EXPIRE: &str = "SELECT `id`, `created_by`, `created_at`, `updated_at`, `bucket_id`, `file_name`, `content_type`, `size`, `public` FROM `files` WHERE `bucket_id` = ? AND `updated_at` < ?";
-const UPDATE: &str = "UPDATE `files` SET `created_by` = ?, `updated_at` = ?, `file_name` = ?, `public` = ? WHERE `id` = ?";
+const SELECT_MANY_BY_CREATED_BY_AND_BUCKET_ID: &str = "SELECT `id`, `created_by`, `created_at`, `updated_at`, `bucket_id`, `file_name`, `content_type`, `size`, `public`, `pending`, `checksum` FROM `files` WHERE `created_by` = ? AND `bucket_id` = ?";
+const COUNT_MANY_BY_CREATED_BY_AND_BUCKET_ID: &str =
+    "SELECT COUNT(1) FROM `files` WHERE `created_by` = ? AND `bucket_id` = ?";
+const SELECT_MANY_EXPIRE: &str = "SELECT `id`, `created_by`, `created_at`, `updated_at`, `bucket_id`, `file_name`, `content_type`, `size`, `public`, `pending`, `checksum` FROM `files` WHERE `bucket_id` = ? AND `updated_at` < ?";
+const SELECT_MANY_STALE_UPLOAD: &str = "SELECT `id`, `created_by`, `created_at`, `updated_at`, `bucket_id`, `file_name`, `content_type`, `size`, `public`, `pending`, `checksum` FROM `files` WHERE `pending` = true AND `updated_at` < ?";
+const UPDATE: &str = "UPDATE `files` SET `created_by` = ?, `updated_at` = ?, `file_name` = ?, `public` = ?, `pending` = ?, `checksum` = ? WHERE `id` = ?";
+const UPDATE_CONTENT: &str = "UPDATE `files` SET `updated_at` = ?, `content_type` = ?, `size` = ?, `checksum` = ? WHERE `id` = ?";
 const DELETE: &str = "DELETE FROM `files` WHERE `id` = ?";
+const INCREMENT_BUCKET_USED_BYTES: &str =
+    "UPDATE `buckets` SET `used_bytes` = `used_bytes` + ? WHERE `id` = ?";
+// Only succeeds while the bucket stays within its quota, so concurrent uploads can't overshoot it
+const RESERVE_BUCKET_USED_BYTES: &str =
+    "UPDATE `buckets` SET `used_bytes` = `used_bytes` + ? WHERE `id` = ? AND (? <= 0 OR `quota_bytes` IS NULL OR `used_bytes` + ? <= `quota_bytes`)";
 
 pub async fn init(pool: &Pool<MySql>) {
     hb_log::info(Some("🔧"), "[MySQL] Setting up files table");
 
-    pool.execute("CREATE TABLE IF NOT EXISTS `files` (`id` binary(16), `created_by` binary(16), `created_at` timestamp(6), `updated_at` timestamp(6), `bucket_id` binary(16), `file_name` text, `content_type` text, `size` bigint, `public` boolean, PRIMARY KEY (`id`))").await.unwrap();
+    pool.execute("CREATE TABLE IF NOT EXISTS `files` (`id` binary(16), `created_by` binary(16), `created_at` timestamp(6), `updated_at` timestamp(6), `bucket_id` binary(16), `file_name` text, `content_type` text, `size` bigint, `public` boolean, `pending` boolean, `checksum` text, PRIMARY KEY (`id`))").await.unwrap();
 
     tokio::try_join!(
         pool.prepare(INSERT),
@@ -28,28 +39,46 @@ pub async fn init(pool: &Pool<MySql>) {
         pool.prepare(SELECT_MANY_BY_CREATED_BY_AND_BUCKET_ID),
         pool.prepare(COUNT_MANY_BY_CREATED_BY_AND_BUCKET_ID),
         pool.prepare(SELECT_MANY_EXPIRE),
+        pool.prepare(SELECT_MANY_STALE_UPLOAD),
         pool.prepare(UPDATE),
+        pool.prepare(UPDATE_CONTENT),
         pool.prepare(DELETE),
     )
     .unwrap();
 }
 
 impl MysqlDb {
-    pub async fn insert_file(&self, value: &FileModel) -> Result<()> {
-        self.execute(
-            sqlx::query(INSERT)
-                .bind(value.id())
-                .bind(value.created_by())
-                .bind(value.created_at())
-                .bind(value.updated_at())
-                .bind(value.bucket_id())
-                .bind(value.file_name())
-                .bind(value.content_type())
-                .bind(value.size())
-                .bind(value.public()),
-        )
-        .await?;
-        Ok(())
+    // Returns false, writing nothing, when the file doesn't fit in the quota of its bucket
+    pub async fn insert_file(&self, value: &FileModel) -> Result<bool> {
+        // The file and the usage of its bucket are written together, so the usage never drifts
+        let mut tx = self.begin().await?;
+        sqlx::query(INSERT)
+            .bind(value.id())
+            .bind(value.created_by())
+            .bind(value.created_at())
+            .bind(value.updated_at())
+            .bind(value.bucket_id())
+            .bind(value.file_name())
+            .bind(value.content_type())
+            .bind(value.size())
+            .bind(value.public())
+            .bind(value.pending())
+            .bind(value.checksum())
+            .execute(&mut *tx)
+            .await?;
+        let reserved = sqlx::query(RESERVE_BUCKET_USED_BYTES)
+            .bind(value.size())
+            .bind(value.bucket_id())
+            .bind(value.size())
+            .bind(value.size())
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if reserved == 0 {
+            return Ok(false);
+        }
+        tx.commit().await?;
+        Ok(true)
     }
 
     pub async fn select_file(&self, id: &Uuid) -> Result<FileModel> {
@@ -59,19 +88,19 @@ impl MysqlDb {
     pub async fn select_many_files_by_bucket_id(
         &self,
         bucket_id: &Uuid,
+        filter: &FileFilterModel,
+        order: &FileOrderModel,
         before_id: &Option<Uuid>,
         limit: &Option<i32>,
     ) -> Result<Vec<FileModel>> {
         let mut sql = SELECT_MANY_BY_BUCKET_ID.to_owned();
-        if before_id.is_some() {
-            sql += " AND `id` < ?";
-        }
-        sql += " ORDER BY `id` DESC";
+        push_filter(&mut sql, filter);
+        push_order(&mut sql, order, before_id);
         if limit.is_some() {
             sql += " LIMIT ?";
         }
 
-        let mut query = sqlx::query_as(&sql).bind(bucket_id);
+        let mut query = bind_filter(sqlx::query_as(&sql).bind(bucket_id), filter);
         if let Some(before_id) = before_id {
             query = query.bind(before_id);
         }
@@ -82,9 +111,34 @@ impl MysqlDb {
         Ok(self.fetch_all(query).await?)
     }
 
-    pub async fn count_many_files_by_bucket_id(&self, bucket_id: &Uuid) -> Result<i64> {
+    pub async fn select_many_files_by_bucket_id_and_ids(
+        &self,
+        bucket_id: &Uuid,
+        ids: &[Uuid],
+    ) -> Result<Vec<FileModel>> {
+        let sql = format!(
+            "{SELECT_MANY_BY_BUCKET_ID} AND `id` IN ({})",
+            vec!["?"; ids.len()].join(", ")
+        );
+
+        let mut query = sqlx::query_as(&sql).bind(bucket_id);
+        for id in ids {
+            query = query.bind(id);
+        }
+
+        Ok(self.fetch_all(query).await?)
+    }
+
+    pub async fn count_many_files_by_bucket_id(
+        &self,
+        bucket_id: &Uuid,
+        filter: &FileFilterModel,
+    ) -> Result<i64> {
+        let mut sql = COUNT_MANY_BY_BUCKET_ID.to_owned();
+        push_filter(&mut sql, filter);
+
         Ok(self
-            .fetch_one::<(i64,)>(sqlx::query_as(COUNT_MANY_BY_BUCKET_ID).bind(bucket_id))
+            .fetch_one::<(i64,)>(bind_filter(sqlx::query_as(&sql).bind(bucket_id), filter))
             .await?
             .0)
     }
@@ -93,19 +147,22 @@ impl MysqlDb {
         &self,
         created_by: &Uuid,
         bucket_id: &Uuid,
+        filter: &FileFilterModel,
+        order: &FileOrderModel,
         before_id: &Option<Uuid>,
         limit: &Option<i32>,
     ) -> Result<Vec<FileModel>> {
         let mut sql = SELECT_MANY_BY_CREATED_BY_AND_BUCKET_ID.to_owned();
-        if before_id.is_some() {
-            sql += " AND `id` < ?";
-        }
-        sql += " ORDER BY `id` DESC";
+        push_filter(&mut sql, filter);
+        push_order(&mut sql, order, before_id);
         if limit.is_some() {
             sql += " LIMIT ?";
         }
 
-        let mut query = sqlx::query_as(&sql).bind(created_by).bind(bucket_id);
+        let mut query = bind_filter(
+            sqlx::query_as(&sql).bind(created_by).bind(bucket_id),
+            filter,
+        );
         if let Some(before_id) = before_id {
             query = query.bind(before_id);
         }
@@ -120,13 +177,16 @@ impl MysqlDb {
         &self,
         created_by: &Uuid,
         bucket_id: &Uuid,
+        filter: &FileFilterModel,
     ) -> Result<i64> {
+        let mut sql = COUNT_MANY_BY_CREATED_BY_AND_BUCKET_ID.to_owned();
+        push_filter(&mut sql, filter);
+
         Ok(self
-            .fetch_one::<(i64,)>(
-                sqlx::query_as(COUNT_MANY_BY_CREATED_BY_AND_BUCKET_ID)
-                    .bind(created_by)
-                    .bind(bucket_id),
-            )
+            .fetch_one::<(i64,)>(bind_filter(
+                sqlx::query_as(&sql).bind(created_by).bind(bucket_id),
+                filter,
+            ))
             .await?
             .0)
     }
@@ -150,6 +210,24 @@ impl MysqlDb {
             .await?)
     }
 
+    pub async fn select_many_stale_upload_files(
+        &self,
+        ttl_seconds: &i64,
+    ) -> Result<Vec<FileModel>> {
+        Ok(self
+            .fetch_all(
+                sqlx::query_as(SELECT_MANY_STALE_UPLOAD).bind(
+                    Utc::now()
+                        .checked_sub_signed(
+                            Duration::try_seconds(*ttl_seconds)
+                                .ok_or_else(|| Error::msg("upload session ttl is out of range."))?,
+                        )
+                        .ok_or_else(|| Error::msg("upload session ttl is out of range."))?,
+                ),
+            )
+            .await?)
+    }
+
     pub async fn update_file(&self, value: &FileModel) -> Result<()> {
         self.execute(
             sqlx::query(UPDATE)
@@ -157,14 +235,131 @@ impl MysqlDb {
                 .bind(value.updated_at())
                 .bind(value.file_name())
                 .bind(value.public())
+                .bind(value.pending())
+                .bind(value.checksum())
                 .bind(value.id()),
         )
         .await?;
         Ok(())
     }
 
-    pub async fn delete_file(&self, id: &Uuid) -> Result<()> {
-        self.execute(sqlx::query(DELETE).bind(id)).await?;
+    // Returns false, writing nothing, when the new content doesn't fit in the quota of the bucket
+    pub async fn update_file_content(
+        &self,
+        value: &FileModel,
+        previous_size: &i64,
+    ) -> Result<bool> {
+        let mut tx = self.begin().await?;
+        sqlx::query(UPDATE_CONTENT)
+            .bind(value.updated_at())
+            .bind(value.content_type())
+            .bind(value.size())
+            .bind(value.checksum())
+            .bind(value.id())
+            .execute(&mut *tx)
+            .await?;
+        let reserved = sqlx::query(RESERVE_BUCKET_USED_BYTES)
+            .bind(value.size() - previous_size)
+            .bind(value.bucket_id())
+            .bind(value.size() - previous_size)
+            .bind(value.size() - previous_size)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if reserved == 0 {
+            return Ok(false);
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn delete_file(&self, bucket_id: &Uuid, id: &Uuid, size: &i64) -> Result<()> {
+        let mut tx = self.begin().await?;
+        // Only the delete that actually removed the row gives its size back to the bucket
+        if sqlx::query(DELETE)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+            > 0
+        {
+            sqlx::query(INCREMENT_BUCKET_USED_BYTES)
+                .bind(-size)
+                .bind(bucket_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
         Ok(())
     }
 }
+
+// Appends the conditions of the filter, whose values are bound in the same order by bind_filter
+fn push_filter(sql: &mut String, filter: &FileFilterModel) {
+    if filter.file_name().is_some() {
+        *sql += " AND INSTR(LOWER(`file_name`), LOWER(?)) > 0";
+    }
+    if filter.content_type().is_some() {
+        *sql += " AND INSTR(LOWER(`content_type`), LOWER(?)) = 1";
+    }
+    if filter.public().is_some() {
+        *sql += " AND `public` = ?";
+    }
+    if filter.created_by().is_some() {
+        *sql += " AND `created_by` = ?";
+    }
+    if filter.min_size().is_some() {
+        *sql += " AND `size` >= ?";
+    }
+    if filter.max_size().is_some() {
+        *sql += " AND `size` <= ?";
+    }
+}
+
+fn bind_filter<'q, O>(
+    mut query: QueryAs<'q, MySql, O, MySqlArguments>,
+    filter: &FileFilterModel,
+) -> QueryAs<'q, MySql, O, MySqlArguments> {
+    if let Some(file_name) = filter.file_name() {
+        query = query.bind(file_name.to_owned());
+    }
+    if let Some(content_type) = filter.content_type() {
+        query = query.bind(content_type.to_owned());
+    }
+    if let Some(public) = filter.public() {
+        query = query.bind(*public);
+    }
+    if let Some(created_by) = filter.created_by() {
+        query = query.bind(*created_by);
+    }
+    if let Some(min_size) = filter.min_size() {
+        query = query.bind(*min_size);
+    }
+    if let Some(max_size) = filter.max_size() {
+        query = query.bind(*max_size);
+    }
+    query
+}
+
+// Files are paged by the id of the last file of the previous page. When ordering by another
+// column, the files after it are found by comparing that column first and the id on ties
+fn push_order(sql: &mut String, order: &FileOrderModel, before_id: &Option<Uuid>) {
+    let column = order.column().column_name();
+    let (comparison, order_kind) = if *order.descending() {
+        ("<", "DESC")
+    } else {
+        (">", "ASC")
+    };
+    if before_id.is_some() {
+        if column == "id" {
+            *sql += &format!(" AND `id` {comparison} ?");
+        } else {
+            *sql += &format!(" AND (`{column}`, `id`) {comparison} (SELECT `{column}`, `id` FROM `files` WHERE `id` = ?)");
+        }
+    }
+    if column == "id" {
+        *sql += &format!(" ORDER BY `id` {order_kind}");
+    } else {
+        *sql += &format!(" ORDER BY `{column}` {order_kind}, `id` {order_kind}");
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::{Error, Result};
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use sqlx::{Executor, MySql, Pool};
 use uuid::Uuid;
 
@@ -7,7 +7,8 @@ use crate::{db::MysqlDb, model::log::LogModel};
 
 const INSERT: &str = "INSERT INTO `logs` (`id`, `created_at`, `admin_id`, `project_id`, `kind`, `message`) VALUES (?, ?, ?, ?, ?, ?)";
 const SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str = "SELECT `id`, `created_at`, `admin_id`, `project_id`, `kind`, `message` FROM `logs` WHERE `admin_id` = ? AND `project_id` = ?";
-const COUNT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str = "SELECT COUNT(1) FROM `logs` WHERE `admin_id` = ? AND `project_id` = ?";
+const COUNT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str =
+    "SELECT COUNT(1) FROM `logs` WHERE `admin_id` = ? AND `project_id` = ?";
 const DELETE_EXPIRE: &str = "DELETE FROM `logs` WHERE `created_at` < ?";
 
 pub async fn init(pool: &Pool<MySql>) {
@@ -45,12 +46,17 @@ impl MysqlDb {
         &self,
         admin_id: &Uuid,
         project_id: &Uuid,
+        kind: &Option<&str>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        message: &Option<String>,
         before_id: &Option<Uuid>,
         limit: &Option<i32>,
     ) -> Result<Vec<LogModel>> {
         let _ = self.delete_expired_logs().await?;
 
         let mut sql = SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID.to_owned();
+        sql += &Self::logs_filter(kind, from, to, message);
         if before_id.is_some() {
             sql += " AND `id` < ?";
         }
@@ -60,6 +66,18 @@ impl MysqlDb {
         }
 
         let mut query = sqlx::query_as(&sql).bind(admin_id).bind(project_id);
+        if let Some(kind) = kind {
+            query = query.bind(kind);
+        }
+        if let Some(from) = from {
+            query = query.bind(from);
+        }
+        if let Some(to) = to {
+            query = query.bind(to);
+        }
+        if let Some(message) = message {
+            query = query.bind(message_pattern(message));
+        }
         if let Some(before_id) = before_id {
             query = query.bind(before_id);
         }
@@ -74,17 +92,55 @@ impl MysqlDb {
         &self,
         admin_id: &Uuid,
         project_id: &Uuid,
+        kind: &Option<&str>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        message: &Option<String>,
     ) -> Result<i64> {
         let _ = self.delete_expired_logs().await?;
 
-        Ok(self
-            .fetch_one::<(i64,)>(
-                sqlx::query_as(COUNT_MANY_BY_ADMIN_ID_AND_PROJECT_ID)
-                    .bind(admin_id)
-                    .bind(project_id),
-            )
-            .await?
-            .0)
+        let sql = COUNT_MANY_BY_ADMIN_ID_AND_PROJECT_ID.to_owned()
+            + &Self::logs_filter(kind, from, to, message);
+
+        let mut query = sqlx::query_as(&sql).bind(admin_id).bind(project_id);
+        if let Some(kind) = kind {
+            query = query.bind(kind);
+        }
+        if let Some(from) = from {
+            query = query.bind(from);
+        }
+        if let Some(to) = to {
+            query = query.bind(to);
+        }
+        if let Some(message) = message {
+            query = query.bind(message_pattern(message));
+        }
+
+        Ok(self.fetch_one::<(i64,)>(query).await?.0)
+    }
+
+    // The values are bound in the same order, right after the admin and project ids
+    fn logs_filter(
+        kind: &Option<&str>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        message: &Option<String>,
+    ) -> String {
+        let mut sql = String::new();
+        if kind.is_some() {
+            sql += " AND `kind` = ?";
+        }
+        if from.is_some() {
+            sql += " AND `created_at` >= ?";
+        }
+        if to.is_some() {
+            sql += " AND `created_at` <= ?";
+        }
+        if message.is_some() {
+            // Case-insensitive under the default collation, with '\\' as the default escape
+            sql += " AND `message` LIKE ?";
+        }
+        sql
     }
 
     async fn delete_expired_logs(&self) -> Result<()> {
@@ -102,3 +158,14 @@ impl MysqlDb {
         Ok(())
     }
 }
+
+// Matches the message anywhere, with the wildcards in it taken literally
+fn message_pattern(message: &str) -> String {
+    format!(
+        "%{}%",
+        message
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    )
+}
@@ -47,6 +47,10 @@ pub fn drop_columns(record_table: &str, column_names: &HashSet<String>) -> Strin
     )
 }
 
+pub fn rename_column(record_table: &str, column: &str, new_column: &str) -> String {
+    format!("ALTER TABLE `{record_table}` RENAME COLUMN `{column}` TO `{new_column}`")
+}
+
 pub fn change_columns_type(
     record_table: &str,
     columns: &HashMap<String, SchemaFieldPropsModel>,
@@ -69,24 +73,37 @@ pub fn count_index(record_table: &str, index: &str) -> String {
     format!("SELECT COUNT(1) FROM `information_schema`.`STATISTICS` WHERE `TABLE_NAME` = '{record_table}' and `INDEX_NAME` = '{record_table}_{index}'")
 }
 
-pub fn count_unique_index(record_table: &str, index: &str) -> String {
-    format!("SELECT COUNT(1) FROM `information_schema`.`STATISTICS` WHERE `TABLE_NAME` = '{record_table}' and `INDEX_NAME` = '{record_table}_{index}_unique'")
+// A composite index has a row per column in STATISTICS, so only its first column is counted
+pub fn count_unique_index(record_table: &str, columns: &Vec<&str>) -> String {
+    format!("SELECT COUNT(1) FROM `information_schema`.`STATISTICS` WHERE `TABLE_NAME` = '{record_table}' and `INDEX_NAME` = '{record_table}_{}_unique' and `SEQ_IN_INDEX` = 1", columns.join("_"))
 }
 
 pub fn create_index(record_table: &str, index: &str) -> String {
     format!("CREATE INDEX `{record_table}_{index}` ON `{record_table}` (`{index}`)")
 }
 
-pub fn create_unique_index(record_table: &str, index: &str) -> String {
-    format!("CREATE UNIQUE INDEX `{record_table}_{index}_unique` ON `{record_table}` (`{index}`)")
+// The index is named after its columns, so a single column gets the same name as before
+pub fn create_unique_index(record_table: &str, columns: &Vec<&str>) -> String {
+    format!(
+        "CREATE UNIQUE INDEX `{record_table}_{}_unique` ON `{record_table}` ({})",
+        columns.join("_"),
+        columns
+            .iter()
+            .map(|col| format!("`{col}`"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
 }
 
 pub fn drop_index(record_table: &str, index: &str) -> String {
-    format!("DROP INDEX `{record_table}_{index}`")
+    format!("DROP INDEX `{record_table}_{index}` ON `{record_table}`")
 }
 
-pub fn drop_unique_index(record_table: &str, index: &str) -> String {
-    format!("DROP INDEX `{record_table}_{index}_unique`")
+pub fn drop_unique_index(record_table: &str, columns: &Vec<&str>) -> String {
+    format!(
+        "DROP INDEX `{record_table}_{}_unique` ON `{record_table}`",
+        columns.join("_")
+    )
 }
 
 pub fn insert(record_table: &str, columns: &Vec<&str>) -> String {
@@ -103,6 +120,42 @@ pub fn insert(record_table: &str, columns: &Vec<&str>) -> String {
     format!("INSERT INTO `{record_table}` ({cols}) VALUES ({vals})")
 }
 
+pub fn insert_many(record_table: &str, columns: &Vec<&str>, rows: &usize) -> String {
+    let cols = columns.iter().map(|col| format!("`{col}`")).join(", ");
+    // Every row binds the same columns, so their placeholders are all alike
+    let vals = format!("({})", vec!["?"; columns.len()].join(", "));
+    format!(
+        "INSERT INTO `{record_table}` ({cols}) VALUES {}",
+        vec![vals.as_str(); *rows].join(", ")
+    )
+}
+
+pub fn copy(record_table: &str, source_table: &str, columns: &Vec<&str>) -> String {
+    let cols = columns.iter().map(|col| format!("`{col}`")).join(", ");
+    format!("INSERT INTO `{record_table}` ({cols}) SELECT {cols} FROM `{source_table}`")
+}
+
+// Copies the records that are neither soft-deleted nor expired, the cut-off times of the TTLs are
+// bound as in and_not_expired
+pub fn copy_visible(
+    record_table: &str,
+    source_table: &str,
+    columns: &Vec<&str>,
+    soft_delete: &bool,
+    ttl: &bool,
+    per_record_ttl: &bool,
+) -> String {
+    let mut conditions = not_expired_conditions(ttl, per_record_ttl);
+    if *soft_delete {
+        conditions.insert(0, "`_deleted_at` IS NULL".to_owned());
+    }
+    let mut query = copy(record_table, source_table, columns);
+    if !conditions.is_empty() {
+        query += &format!(" WHERE {}", conditions.join(" AND "));
+    }
+    query
+}
+
 pub fn select(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
         "SELECT {} FROM `{}` WHERE `_id` = ?",
@@ -115,12 +168,40 @@ pub fn select_by_id_and_created_by(record_table: &str, columns: &Vec<&str>) -> S
     select(record_table, columns) + " AND `_created_by` = ?"
 }
 
+pub fn select_not_deleted(record_table: &str, columns: &Vec<&str>) -> String {
+    select(record_table, columns) + " AND `_deleted_at` IS NULL"
+}
+
+pub fn select_not_deleted_by_id_and_created_by(record_table: &str, columns: &Vec<&str>) -> String {
+    select_not_deleted(record_table, columns) + " AND `_created_by` = ?"
+}
+
+// Leaves out the records that outlived the collection TTL or their own '_expired_at'. The cut-off
+// times are bound after the other arguments, the one of the collection TTL first.
+pub fn and_not_expired(ttl: &bool, per_record_ttl: &bool) -> String {
+    not_expired_conditions(ttl, per_record_ttl)
+        .iter()
+        .map(|condition| format!(" AND {condition}"))
+        .join("")
+}
+
+fn not_expired_conditions(ttl: &bool, per_record_ttl: &bool) -> Vec<String> {
+    let mut conditions = Vec::with_capacity(2);
+    if *ttl {
+        conditions.push("`_updated_at` >= ?".to_owned());
+    }
+    if *per_record_ttl {
+        conditions.push("(`_expired_at` IS NULL OR `_expired_at` >= ?)".to_owned());
+    }
+    conditions
+}
+
 pub fn select_many(
     record_table: &str,
     columns: &Vec<&str>,
     filter: &str,
     groups: &Vec<&str>,
-    orders: &Vec<(&str, &str)>,
+    orders: &Vec<(String, &str)>,
     with_query_limit: &bool,
 ) -> String {
     let mut query = format!(
@@ -158,7 +239,7 @@ pub fn select_many(
             if count > 0 {
                 query += ","
             }
-            query += &format!(" `{field}` {kind}");
+            query += &format!(" {field} {kind}");
             count += 1;
         }
     }
@@ -168,6 +249,11 @@ pub fn select_many(
     query
 }
 
+// Great-circle distance in meters between a point column and the given coordinates
+pub fn distance(column: &str, lat: &str, lng: &str) -> String {
+    format!("ST_Distance_Sphere(POINT(JSON_EXTRACT(`{column}`, '$[1]'), JSON_EXTRACT(`{column}`, '$[0]')), POINT({lng}, {lat}))")
+}
+
 pub fn update(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
         "UPDATE `{}` SET {} WHERE `_id` = ?",
@@ -176,6 +262,19 @@ pub fn update(record_table: &str, columns: &Vec<&str>) -> String {
     )
 }
 
+pub fn backfill_column(record_table: &str, column: &str) -> String {
+    format!("UPDATE `{record_table}` SET `{column}` = ? WHERE `{column}` IS NULL")
+}
+
+// The row lock taken by UPDATE serializes concurrent increments on the same record
+pub fn increment_counter(record_table: &str, column: &str) -> String {
+    format!("UPDATE `{record_table}` SET `{column}` = COALESCE(`{column}`, 0) + ?, `_updated_at` = ? WHERE `_id` = ?")
+}
+
+pub fn increment_counter_by_created_by(record_table: &str, column: &str) -> String {
+    increment_counter(record_table, column) + " AND `_created_by` = ?"
+}
+
 pub fn delete(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
         "DELETE FROM `{}` WHERE {}",
@@ -187,8 +286,35 @@ pub fn delete(record_table: &str, columns: &Vec<&str>) -> String {
     )
 }
 
+// Soft-deleted records keep their row and are left for the sweep of expired records to purge
+pub fn soft_delete(record_table: &str) -> String {
+    format!("UPDATE `{record_table}` SET `_deleted_at` = ?, `_updated_at` = ? WHERE `_id` = ? AND `_deleted_at` IS NULL")
+}
+
+pub fn soft_delete_by_created_by(record_table: &str) -> String {
+    soft_delete(record_table) + " AND `_created_by` = ?"
+}
+
+pub fn restore(record_table: &str) -> String {
+    format!("UPDATE `{record_table}` SET `_deleted_at` = NULL, `_updated_at` = ? WHERE `_id` = ? AND `_deleted_at` IS NOT NULL")
+}
+
+pub fn restore_by_created_by(record_table: &str) -> String {
+    restore(record_table) + " AND `_created_by` = ?"
+}
+
+pub fn purge_deleted(record_table: &str) -> String {
+    format!("DELETE FROM `{record_table}` WHERE `_deleted_at` IS NOT NULL")
+}
+
+// Deletes a limited batch of expired records so a sweep never holds the table for long
 pub fn delete_expired(record_table: &str) -> String {
-    format!("DELETE FROM `{record_table}` WHERE `_updated_at` < ?")
+    format!("DELETE FROM `{record_table}` WHERE `_updated_at` < ? LIMIT ?")
+}
+
+// Records of a collection with per-record TTL that have reached their own expiry time
+pub fn delete_expired_by_record(record_table: &str) -> String {
+    format!("DELETE FROM `{record_table}` WHERE `_expired_at` < ? LIMIT ?")
 }
 
 pub fn count(record_table: &str, filter: &str, groups: &Vec<&str>) -> String {
@@ -210,3 +336,13 @@ pub fn count(record_table: &str, filter: &str, groups: &Vec<&str>) -> String {
     query += ")";
     query
 }
+
+// Returns the number of rows, soft-deleted ones included, the lowest and highest _id, and the
+// size of the table together with its indexes as estimated by SHOW TABLE STATUS
+pub fn stats(record_table: &str) -> String {
+    format!("SELECT COUNT(1), (SELECT `_id` FROM `{record_table}` ORDER BY `_id` ASC LIMIT 1), (SELECT `_id` FROM `{record_table}` ORDER BY `_id` DESC LIMIT 1), (SELECT CAST(`DATA_LENGTH` + `INDEX_LENGTH` AS SIGNED) FROM `information_schema`.`TABLES` WHERE `TABLE_SCHEMA` = DATABASE() AND `TABLE_NAME` = '{record_table}') FROM `{record_table}`")
+}
+
+pub fn select_indexes(record_table: &str) -> String {
+    format!("SELECT DISTINCT `INDEX_NAME` FROM `information_schema`.`STATISTICS` WHERE `TABLE_SCHEMA` = DATABASE() AND `TABLE_NAME` = '{record_table}' ORDER BY `INDEX_NAME`")
+}
@@ -0,0 +1,44 @@
+use anyhow::Result;
+use sqlx::{Executor, MySql, Pool};
+
+use crate::{db::MysqlDb, model::sign_in_attempt::SignInAttemptModel};
+
+const UPSERT: &str = "INSERT INTO `sign_in_attempts` (`key`, `failed_count`, `locked_until`, `updated_at`) VALUES (?, ?, ?, ?) ON DUPLICATE KEY UPDATE `failed_count` = VALUES(`failed_count`), `locked_until` = VALUES(`locked_until`), `updated_at` = VALUES(`updated_at`)";
+const SELECT: &str = "SELECT `key`, `failed_count`, `locked_until`, `updated_at` FROM `sign_in_attempts` WHERE `key` = ?";
+const DELETE: &str = "DELETE FROM `sign_in_attempts` WHERE `key` = ?";
+
+pub async fn init(pool: &Pool<MySql>) {
+    hb_log::info(Some("🔧"), "[MySQL] Setting up sign_in_attempts table");
+
+    pool.execute("CREATE TABLE IF NOT EXISTS `sign_in_attempts` (`key` varchar(255), `failed_count` int, `locked_until` timestamp(6), `updated_at` timestamp(6), PRIMARY KEY (`key`))").await.unwrap();
+
+    tokio::try_join!(
+        pool.prepare(UPSERT),
+        pool.prepare(SELECT),
+        pool.prepare(DELETE),
+    )
+    .unwrap();
+}
+
+impl MysqlDb {
+    pub async fn upsert_sign_in_attempt(&self, value: &SignInAttemptModel) -> Result<()> {
+        self.execute(
+            sqlx::query(UPSERT)
+                .bind(value.key())
+                .bind(value.failed_count())
+                .bind(value.locked_until())
+                .bind(value.updated_at()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn select_sign_in_attempt(&self, key: &str) -> Result<SignInAttemptModel> {
+        Ok(self.fetch_one(sqlx::query_as(SELECT).bind(key)).await?)
+    }
+
+    pub async fn delete_sign_in_attempt(&self, key: &str) -> Result<()> {
+        self.execute(sqlx::query(DELETE).bind(key)).await?;
+        Ok(())
+    }
+}
@@ -1 +1,3 @@
-pub const COUNT_TABLE: &str = "SELECT COUNT(1) FROM `information_schema`.`tables` WHERE `table_name` = ?";
+pub const COUNT_TABLE: &str =
+    "SELECT COUNT(1) FROM `information_schema`.`tables` WHERE `table_name` = ?";
+pub const PING: &str = "SELECT 1";
@@ -1,27 +1,35 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use sqlx::{Executor, MySql, Pool};
 use uuid::Uuid;
 
 use crate::{db::MysqlDb, model::token::TokenModel};
 
-const INSERT: &str = "INSERT INTO `tokens` (`id`, `created_at`, `updated_at`, `project_id`, `admin_id`, `name`, `token`, `allow_anonymous`, `expired_at`) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `admin_id`, `name`, `token`, `allow_anonymous`, `expired_at` FROM `tokens` WHERE `id` = ?";
-const SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `admin_id`, `name`, `token`, `allow_anonymous`, `expired_at` FROM `tokens` WHERE `admin_id` = ? AND `project_id` = ? ORDER BY `id` DESC";
-const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `admin_id`, `name`, `token`, `allow_anonymous`, `expired_at` FROM `tokens` WHERE `project_id` = ? ORDER BY `id` DESC";
-const UPDATE: &str = "UPDATE `tokens` SET `updated_at` = ?, `admin_id` = ?, `name` = ?, `allow_anonymous` = ?, `expired_at` = ? WHERE `id` = ?";
+const INSERT: &str = "INSERT INTO `tokens` (`id`, `created_at`, `updated_at`, `project_id`, `admin_id`, `name`, `token`, `allow_anonymous`, `expired_at`, `disabled_at`, `last_used_at`, `request_count`) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `admin_id`, `name`, `token`, `allow_anonymous`, `expired_at`, `disabled_at`, `last_used_at`, `request_count` FROM `tokens` WHERE `id` = ?";
+const SELECT_BY_TOKEN: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `admin_id`, `name`, `token`, `allow_anonymous`, `expired_at`, `disabled_at`, `last_used_at`, `request_count` FROM `tokens` WHERE `token` = ?";
+const SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `admin_id`, `name`, `token`, `allow_anonymous`, `expired_at`, `disabled_at`, `last_used_at`, `request_count` FROM `tokens` WHERE `admin_id` = ? AND `project_id` = ? ORDER BY `id` DESC";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT `id`, `created_at`, `updated_at`, `project_id`, `admin_id`, `name`, `token`, `allow_anonymous`, `expired_at`, `disabled_at`, `last_used_at`, `request_count` FROM `tokens` WHERE `project_id` = ? ORDER BY `id` DESC";
+const UPDATE: &str = "UPDATE `tokens` SET `updated_at` = ?, `admin_id` = ?, `name` = ?, `token` = ?, `allow_anonymous` = ?, `expired_at` = ?, `disabled_at` = ? WHERE `id` = ?";
+const UPDATE_USAGE: &str =
+    "UPDATE `tokens` SET `last_used_at` = ?, `request_count` = `request_count` + ? WHERE `id` = ?";
 const DELETE: &str = "DELETE FROM `tokens` WHERE `id` = ?";
+const DELETE_COLLECTION_RULES: &str = "DELETE FROM `collection_rules` WHERE `token_id` = ?";
+const DELETE_BUCKET_RULES: &str = "DELETE FROM `bucket_rules` WHERE `token_id` = ?";
 
 pub async fn init(pool: &Pool<MySql>) {
     hb_log::info(Some("🔧"), "[MySQL] Setting up tokens table");
 
-    pool.execute("CREATE TABLE IF NOT EXISTS `tokens` (`id` binary(16), `created_at` timestamp(6), `updated_at` timestamp(6), `project_id` binary(16), `admin_id` binary(16), `name` text, `token` text, `allow_anonymous` boolean, `expired_at` timestamp(6), PRIMARY KEY (`id`))").await.unwrap();
+    pool.execute("CREATE TABLE IF NOT EXISTS `tokens` (`id` binary(16), `created_at` timestamp(6), `updated_at` timestamp(6), `project_id` binary(16), `admin_id` binary(16), `name` text, `token` text, `allow_anonymous` boolean, `expired_at` timestamp(6), `disabled_at` timestamp(6), `last_used_at` timestamp(6), `request_count` bigint, PRIMARY KEY (`id`))").await.unwrap();
 
     tokio::try_join!(
         pool.prepare(INSERT),
         pool.prepare(SELECT),
+        pool.prepare(SELECT_BY_TOKEN),
         pool.prepare(SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID),
         pool.prepare(SELECT_MANY_BY_PROJECT_ID),
         pool.prepare(UPDATE),
+        pool.prepare(UPDATE_USAGE),
         pool.prepare(DELETE),
     )
     .unwrap();
@@ -39,7 +47,10 @@ impl MysqlDb {
                 .bind(value.name())
                 .bind(value.token())
                 .bind(value.allow_anonymous())
-                .bind(value.expired_at()),
+                .bind(value.expired_at())
+                .bind(value.disabled_at())
+                .bind(value.last_used_at())
+                .bind(value.request_count()),
         )
         .await?;
         Ok(())
@@ -49,6 +60,12 @@ impl MysqlDb {
         Ok(self.fetch_one(sqlx::query_as(SELECT).bind(id)).await?)
     }
 
+    pub async fn select_token_by_token(&self, token: &str) -> Result<TokenModel> {
+        Ok(self
+            .fetch_one(sqlx::query_as(SELECT_BY_TOKEN).bind(token))
+            .await?)
+    }
+
     pub async fn select_many_tokens_by_admin_id_and_project_id(
         &self,
         admin_id: &Uuid,
@@ -78,16 +95,45 @@ impl MysqlDb {
                 .bind(value.updated_at())
                 .bind(value.admin_id())
                 .bind(value.name())
+                .bind(value.token())
                 .bind(value.allow_anonymous())
                 .bind(value.expired_at())
+                .bind(value.disabled_at())
                 .bind(value.id()),
         )
         .await?;
         Ok(())
     }
 
+    pub async fn increment_token_usage(
+        &self,
+        id: &Uuid,
+        last_used_at: &DateTime<Utc>,
+        requests: &i64,
+    ) -> Result<()> {
+        self.execute(
+            sqlx::query(UPDATE_USAGE)
+                .bind(last_used_at)
+                .bind(requests)
+                .bind(id),
+        )
+        .await?;
+        Ok(())
+    }
+
+    // The rules of the token are deleted together with it
     pub async fn delete_token(&self, id: &Uuid) -> Result<()> {
-        self.execute(sqlx::query(DELETE).bind(id)).await?;
+        let mut tx = self.begin().await?;
+        sqlx::query(DELETE_COLLECTION_RULES)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(DELETE_BUCKET_RULES)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(DELETE).bind(id).execute(&mut *tx).await?;
+        tx.commit().await?;
         Ok(())
     }
 }
@@ -1,19 +1,46 @@
+use std::{
+    fs,
+    ops::{Deref, DerefMut},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::{
+    future,
+    stream::{self, BoxStream},
+    StreamExt,
+};
 use sqlx::{
-    postgres::{PgArguments, PgPoolOptions, PgQueryResult, PgRow},
+    pool::PoolConnection,
+    postgres::{
+        PgArguments, PgConnectOptions, PgConnection, PgPoolOptions, PgQueryResult, PgRow, PgSslMode,
+    },
     query::{Query, QueryAs},
-    Error, Pool, Postgres,
+    Error, Execute, Pool, Postgres, Transaction,
 };
+use tokio::sync::{Mutex, MutexGuard};
+use tracing::Instrument;
 
 use crate::query::{
-    admin, admin_password_reset, bucket, bucket_rule, collection, collection_rule, file, log,
-    project, registration, token,
+    admin, admin_password_reset, admin_project_member, audit, bucket, bucket_rule, collection,
+    collection_rule, file, idempotency, log, migration, project, registration, sign_in_attempt,
+    system, token, webhook,
 };
 
 pub struct PostgresDb {
     pool: Pool<Postgres>,
+    slow_query_threshold: Option<Duration>,
+    slow_queries: Arc<AtomicU64>,
+    in_flight: Arc<AtomicU32>,
     table_registration_ttl: i64,
     table_reset_password_ttl: i64,
     table_log_ttl: i64,
+    table_idempotency_ttl: i64,
+    tx: Option<Mutex<Transaction<'static, Postgres>>>,
 }
 
 impl PostgresDb {
@@ -24,26 +51,128 @@ impl PostgresDb {
         port: &str,
         db_name: &str,
         max_connections: &u32,
+        min_connections: &Option<u32>,
+        acquire_timeout: &Option<Duration>,
+        idle_timeout: &Option<Duration>,
+        max_lifetime: &Option<Duration>,
+        ssl_mode: &Option<String>,
+        ssl_root_cert: &Option<String>,
+        ssl_client_cert: &Option<String>,
+        ssl_client_key: &Option<String>,
+        statement_timeout: &Option<Duration>,
+        slow_query_threshold: &Option<Duration>,
         table_registration_ttl: &i64,
         table_reset_password_ttl: &i64,
         table_log_ttl: &i64,
+        table_idempotency_ttl: &i64,
     ) -> Self {
         hb_log::info(Some("⚡"), "[PostgreSQL] Initializing component");
 
         let url = format!("postgres://{user}:{password}@{host}:{port}/{db_name}");
-        let pool = PgPoolOptions::new()
-            .max_connections(*max_connections)
-            .connect(&url)
-            .await
-            .unwrap();
+        let mut connect_opts = match PgConnectOptions::from_str(&url) {
+            Ok(connect_opts) => connect_opts,
+            Err(err) => {
+                hb_log::panic(
+                    None,
+                    &format!("[PostgreSQL] Invalid connection options: {err}"),
+                );
+                unreachable!()
+            }
+        };
+        connect_opts = connect_opts.ssl_mode(match ssl_mode.as_deref() {
+            None | Some("prefer") => PgSslMode::Prefer,
+            Some("disable") => PgSslMode::Disable,
+            Some("require") => PgSslMode::Require,
+            Some("verify-ca") => PgSslMode::VerifyCa,
+            Some("verify-full") => PgSslMode::VerifyFull,
+            Some(ssl_mode) => {
+                hb_log::panic(
+                    None,
+                    &format!(
+                        "[PostgreSQL] Unknown ssl_mode '{ssl_mode}', expected disable, prefer, require, verify-ca or verify-full"
+                    ),
+                );
+                unreachable!()
+            }
+        });
+
+        // The certificates are only read when connecting, so missing ones are caught here to tell
+        // them apart from network problems
+        for (field, path) in [
+            ("ssl_root_cert", ssl_root_cert),
+            ("ssl_client_cert", ssl_client_cert),
+            ("ssl_client_key", ssl_client_key),
+        ] {
+            if let Some(path) = path {
+                if let Err(err) = fs::metadata(path) {
+                    hb_log::panic(
+                        None,
+                        &format!("[PostgreSQL] Can't read {field} '{path}': {err}"),
+                    );
+                }
+            }
+        }
+        if let Some(ssl_root_cert) = ssl_root_cert {
+            connect_opts = connect_opts.ssl_root_cert(ssl_root_cert);
+        }
+        if let Some(ssl_client_cert) = ssl_client_cert {
+            connect_opts = connect_opts.ssl_client_cert(ssl_client_cert);
+        }
+        if let Some(ssl_client_key) = ssl_client_key {
+            connect_opts = connect_opts.ssl_client_key(ssl_client_key);
+        }
+
+        // Enforced by the server, which cancels any statement running longer on its own
+        if let Some(statement_timeout) = statement_timeout {
+            connect_opts = connect_opts.options([(
+                "statement_timeout",
+                statement_timeout.as_millis().to_string(),
+            )]);
+        }
+
+        let mut pool_opts = PgPoolOptions::new().max_connections(*max_connections);
+        if let Some(min_connections) = min_connections {
+            pool_opts = pool_opts.min_connections(*min_connections);
+        }
+        if let Some(acquire_timeout) = acquire_timeout {
+            pool_opts = pool_opts.acquire_timeout(*acquire_timeout);
+        }
+        if let Some(idle_timeout) = idle_timeout {
+            pool_opts = pool_opts.idle_timeout(*idle_timeout);
+        }
+        if let Some(max_lifetime) = max_lifetime {
+            pool_opts = pool_opts.max_lifetime(*max_lifetime);
+        }
+        let pool = match pool_opts.connect_with(connect_opts).await {
+            Ok(pool) => pool,
+            Err(err) => {
+                let msg = match err {
+                    Error::Tls(err) => format!(
+                        "TLS handshake with {host}:{port} failed, check ssl_mode and the certificates: {err}"
+                    ),
+                    Error::Configuration(err) => {
+                        format!("Invalid TLS or connection configuration: {err}")
+                    }
+                    Error::Io(err) => format!("Can't reach {host}:{port}: {err}"),
+                    err => format!("Failed to connect to {host}:{port}: {err}"),
+                };
+                hb_log::panic(None, &format!("[PostgreSQL] {msg}"));
+                unreachable!()
+            }
+        };
 
         Self::init(&pool).await;
 
         Self {
             pool,
+            slow_query_threshold: *slow_query_threshold,
+            slow_queries: Arc::new(AtomicU64::new(0)),
+            in_flight: Arc::new(AtomicU32::new(0)),
             table_registration_ttl: *table_registration_ttl,
             table_reset_password_ttl: *table_reset_password_ttl,
             table_log_ttl: *table_log_ttl,
+            table_idempotency_ttl: *table_idempotency_ttl,
+            tx: None,
         }
     }
 
@@ -51,49 +180,224 @@ impl PostgresDb {
         &self,
         query: Query<'_, Postgres, PgArguments>,
     ) -> Result<PgQueryResult, Error> {
-        query.persistent(false).execute(&self.pool).await
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .persistent(false)
+            .execute(&mut *conn)
+            .instrument(hb_log::db_span("postgresql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn execute(
         &self,
         query: Query<'_, Postgres, PgArguments>,
     ) -> Result<PgQueryResult, Error> {
-        query.execute(&self.pool).await
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .execute(&mut *conn)
+            .instrument(hb_log::db_span("postgresql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_one_unprepared<T: Send + Unpin + for<'r> sqlx::FromRow<'r, PgRow>>(
         &self,
         query: QueryAs<'_, Postgres, T, PgArguments>,
     ) -> Result<T, Error> {
-        Ok(query.persistent(false).fetch_one(&self.pool).await?)
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .persistent(false)
+            .fetch_one(&mut *conn)
+            .instrument(hb_log::db_span("postgresql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_one<T: Send + Unpin + for<'r> sqlx::FromRow<'r, PgRow>>(
         &self,
         query: QueryAs<'_, Postgres, T, PgArguments>,
     ) -> Result<T, Error> {
-        Ok(query.fetch_one(&self.pool).await?)
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .fetch_one(&mut *conn)
+            .instrument(hb_log::db_span("postgresql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_one_row(
         &self,
         query: Query<'_, Postgres, PgArguments>,
     ) -> Result<PgRow, Error> {
-        Ok(query.fetch_one(&self.pool).await?)
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .fetch_one(&mut *conn)
+            .instrument(hb_log::db_span("postgresql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_all<T: Send + Unpin + for<'r> sqlx::FromRow<'r, PgRow>>(
         &self,
         query: QueryAs<'_, Postgres, T, PgArguments>,
     ) -> Result<Vec<T>, Error> {
-        query.fetch_all(&self.pool).await
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .fetch_all(&mut *conn)
+            .instrument(hb_log::db_span("postgresql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_all_rows(
         &self,
         query: Query<'_, Postgres, PgArguments>,
     ) -> Result<Vec<PgRow>, Error> {
-        query.fetch_all(&self.pool).await
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = query
+            .fetch_all(&mut *conn)
+            .instrument(hb_log::db_span("postgresql", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
+    }
+
+    pub fn fetch_rows<'a>(
+        &'a self,
+        query: Query<'a, Postgres, PgArguments>,
+    ) -> BoxStream<'a, Result<PgRow, Error>> {
+        match &self.tx {
+            // The stream can't hold on to the connection of the transaction, so the rows are
+            // fetched all at once instead
+            Some(_) => stream::once(self.fetch_all_rows(query))
+                .flat_map(|result| match result {
+                    Ok(rows) => stream::iter(rows.into_iter().map(Ok)).left_stream(),
+                    Err(err) => stream::once(future::ready(Err(err))).right_stream(),
+                })
+                .boxed(),
+            None => query.fetch(&self.pool),
+        }
+    }
+
+    // Used by the statements that have to be written together. They can't be run on a handle made
+    // by transaction, which already is one.
+    pub async fn begin(&self) -> anyhow::Result<Transaction<'_, Postgres>> {
+        if self.tx.is_some() {
+            return Err(anyhow::Error::msg(
+                "[PostgreSQL] Can't begin a transaction inside another one",
+            ));
+        }
+        Ok(self.pool.begin().await?)
+    }
+
+    // Makes a handle that runs every statement in one transaction, which is rolled back when the
+    // handle is dropped without being committed
+    pub async fn transaction(&self) -> anyhow::Result<Self> {
+        if self.tx.is_some() {
+            return Err(anyhow::Error::msg(
+                "[PostgreSQL] Can't begin a transaction inside another one",
+            ));
+        }
+        Ok(Self {
+            pool: self.pool.clone(),
+            slow_query_threshold: self.slow_query_threshold,
+            slow_queries: self.slow_queries.clone(),
+            in_flight: self.in_flight.clone(),
+            table_registration_ttl: self.table_registration_ttl,
+            table_reset_password_ttl: self.table_reset_password_ttl,
+            table_log_ttl: self.table_log_ttl,
+            table_idempotency_ttl: self.table_idempotency_ttl,
+            tx: Some(Mutex::new(self.pool.begin().await?)),
+        })
+    }
+
+    pub async fn commit(self) -> anyhow::Result<()> {
+        match self.tx {
+            Some(tx) => Ok(tx.into_inner().commit().await?),
+            None => Err(anyhow::Error::msg("[PostgreSQL] Not a transaction")),
+        }
+    }
+
+    // Statements of a handle made by transaction go through its connection, any other through a
+    // connection of the pool
+    async fn connection(&self) -> Result<Connection<'_>, Error> {
+        match &self.tx {
+            Some(tx) => Ok(Connection::Transaction(tx.lock().await)),
+            None => Ok(Connection::Pool(self.pool.acquire().await?)),
+        }
+    }
+
+    pub async fn ping(&self) -> Result<(), Error> {
+        sqlx::query(system::PING)
+            .persistent(false)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // Costs a single Instant::now() per statement when no threshold is set. Bind values are never
+    // part of the statement text, so nothing sensitive ends up in the log.
+    fn check_slow_query(&self, sql: &str, start: &Instant) {
+        if let Some(slow_query_threshold) = self.slow_query_threshold {
+            let elapsed = start.elapsed();
+            if elapsed >= slow_query_threshold {
+                self.slow_queries.fetch_add(1, Ordering::Relaxed);
+                hb_log::warn(
+                    None,
+                    &format!(
+                        "[PostgreSQL] Slow query on table '{}' took {elapsed:?}: {sql}",
+                        table_name(sql)
+                    ),
+                );
+            }
+        }
+    }
+
+    pub fn slow_queries(&self) -> u64 {
+        self.slow_queries.load(Ordering::Relaxed)
+    }
+
+    pub fn pool_size(&self) -> u32 {
+        self.pool.size()
+    }
+
+    pub fn pool_idle(&self) -> u32 {
+        self.pool.num_idle() as u32
+    }
+
+    // Statements in flight beyond the connections in use are the ones waiting for a connection.
+    // Transactions hold a connection without being counted, so this can fall short under load.
+    pub fn pool_waiting(&self) -> u32 {
+        let busy = self.pool_size().saturating_sub(self.pool_idle());
+        self.in_flight.load(Ordering::Relaxed).saturating_sub(busy)
     }
 
     pub fn table_registration_ttl(&self) -> &i64 {
@@ -108,7 +412,13 @@ impl PostgresDb {
         &self.table_log_ttl
     }
 
+    pub fn table_idempotency_ttl(&self) -> &i64 {
+        &self.table_idempotency_ttl
+    }
+
     async fn init(pool: &Pool<Postgres>) {
+        migration::init(pool).await;
+
         tokio::join!(
             admin::init(pool),
             project::init(pool),
@@ -117,10 +427,77 @@ impl PostgresDb {
             file::init(pool),
             token::init(pool),
             collection_rule::init(pool),
+            admin_project_member::init(pool),
             bucket_rule::init(pool),
             registration::init(pool),
             admin_password_reset::init(pool),
             log::init(pool),
+            audit::init(pool),
+            idempotency::init(pool),
+            sign_in_attempt::init(pool),
+            webhook::init(pool),
         );
     }
 }
+
+enum Connection<'a> {
+    Pool(PoolConnection<Postgres>),
+    Transaction(MutexGuard<'a, Transaction<'static, Postgres>>),
+}
+
+impl Deref for Connection<'_> {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Pool(conn) => conn,
+            Self::Transaction(tx) => tx,
+        }
+    }
+}
+
+impl DerefMut for Connection<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Pool(conn) => conn,
+            Self::Transaction(tx) => tx,
+        }
+    }
+}
+
+// Counts a statement as in flight until it finishes or is cancelled
+struct InFlight<'a>(&'a AtomicU32);
+
+impl<'a> InFlight<'a> {
+    fn new(count: &'a AtomicU32) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self(count)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// The first table a statement names, which tells the collection a record query is on
+fn table_name(sql: &str) -> &str {
+    let mut words = sql.split_whitespace();
+    while let Some(word) = words.next() {
+        if ["FROM", "INTO", "UPDATE", "TABLE", "JOIN"]
+            .iter()
+            .any(|keyword| word.eq_ignore_ascii_case(keyword))
+        {
+            for word in words.by_ref() {
+                if !["IF", "NOT", "EXISTS", "ONLY"]
+                    .iter()
+                    .any(|keyword| word.eq_ignore_ascii_case(keyword))
+                {
+                    return word.trim_matches(|c| c == '"' || c == '`' || c == '(');
+                }
+            }
+        }
+    }
+    "-"
+}
@@ -19,8 +19,12 @@ pub struct CollectionModel {
     project_id: Uuid,
     name: String,
     schema_fields: Json<HashMap<String, SchemaFieldPropsModel>>,
+    unique_indexes: Json<Vec<Vec<String>>>,
     opt_auth_column_id: bool,
     opt_ttl: Option<i64>,
+    opt_soft_delete: bool,
+    opt_per_record_ttl: bool,
+    opt_mqtt_publish: bool,
 }
 
 impl CollectionModel {
@@ -31,8 +35,12 @@ impl CollectionModel {
         project_id: &Uuid,
         name: &str,
         schema_fields: &Json<HashMap<String, SchemaFieldPropsModel>>,
+        unique_indexes: &Json<Vec<Vec<String>>>,
         opt_auth_column_id: &bool,
         opt_ttl: &Option<i64>,
+        opt_soft_delete: &bool,
+        opt_per_record_ttl: &bool,
+        opt_mqtt_publish: &bool,
     ) -> Self {
         Self {
             id: *id,
@@ -41,8 +49,12 @@ impl CollectionModel {
             project_id: *project_id,
             name: name.to_owned(),
             schema_fields: schema_fields.clone(),
+            unique_indexes: unique_indexes.clone(),
             opt_auth_column_id: *opt_auth_column_id,
             opt_ttl: *opt_ttl,
+            opt_soft_delete: *opt_soft_delete,
+            opt_per_record_ttl: *opt_per_record_ttl,
+            opt_mqtt_publish: *opt_mqtt_publish,
         }
     }
 
@@ -70,6 +82,10 @@ impl CollectionModel {
         &self.schema_fields
     }
 
+    pub fn unique_indexes(&self) -> &Json<Vec<Vec<String>>> {
+        &self.unique_indexes
+    }
+
     pub fn opt_auth_column_id(&self) -> &bool {
         &self.opt_auth_column_id
     }
@@ -77,6 +93,18 @@ impl CollectionModel {
     pub fn opt_ttl(&self) -> &Option<i64> {
         &self.opt_ttl
     }
+
+    pub fn opt_soft_delete(&self) -> &bool {
+        &self.opt_soft_delete
+    }
+
+    pub fn opt_per_record_ttl(&self) -> &bool {
+        &self.opt_per_record_ttl
+    }
+
+    pub fn opt_mqtt_publish(&self) -> &bool {
+        &self.opt_mqtt_publish
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -89,6 +117,14 @@ pub struct SchemaFieldPropsModel {
     auth_column: bool,
     hashed: bool,
     hidden: bool,
+    default: Option<String>,
+    min: Option<f64>,
+    max: Option<f64>,
+    max_length: Option<i64>,
+    regex: Option<String>,
+    enum_values: Option<String>,
+    reference_collection_id: Option<Uuid>,
+    on_delete: Option<String>,
 }
 
 impl SchemaFieldPropsModel {
@@ -101,6 +137,14 @@ impl SchemaFieldPropsModel {
         auth_column: &bool,
         hashed: &bool,
         hidden: &bool,
+        default: &Option<String>,
+        min: &Option<f64>,
+        max: &Option<f64>,
+        max_length: &Option<i64>,
+        regex: &Option<String>,
+        enum_values: &Option<String>,
+        reference_collection_id: &Option<Uuid>,
+        on_delete: &Option<String>,
     ) -> Self {
         Self {
             kind: kind.to_owned(),
@@ -111,6 +155,14 @@ impl SchemaFieldPropsModel {
             auth_column: *auth_column,
             hashed: *hashed,
             hidden: *hidden,
+            default: default.clone(),
+            min: *min,
+            max: *max,
+            max_length: *max_length,
+            regex: regex.clone(),
+            enum_values: enum_values.clone(),
+            reference_collection_id: *reference_collection_id,
+            on_delete: on_delete.clone(),
         }
     }
 
@@ -145,4 +197,36 @@ impl SchemaFieldPropsModel {
     pub fn hidden(&self) -> &bool {
         &self.hidden
     }
+
+    pub fn default(&self) -> &Option<String> {
+        &self.default
+    }
+
+    pub fn min(&self) -> &Option<f64> {
+        &self.min
+    }
+
+    pub fn max(&self) -> &Option<f64> {
+        &self.max
+    }
+
+    pub fn max_length(&self) -> &Option<i64> {
+        &self.max_length
+    }
+
+    pub fn regex(&self) -> &Option<String> {
+        &self.regex
+    }
+
+    pub fn enum_values(&self) -> &Option<String> {
+        &self.enum_values
+    }
+
+    pub fn reference_collection_id(&self) -> &Option<Uuid> {
+        &self.reference_collection_id
+    }
+
+    pub fn on_delete(&self) -> &Option<String> {
+        &self.on_delete
+    }
 }
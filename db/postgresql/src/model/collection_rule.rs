@@ -1,6 +1,9 @@
 use sqlx::{
     prelude::FromRow,
-    types::chrono::{DateTime, Utc},
+    types::{
+        chrono::{DateTime, Utc},
+        Json,
+    },
 };
 use uuid::Uuid;
 
@@ -17,6 +20,8 @@ pub struct CollectionRuleModel {
     insert_one: bool,
     update_one: String,
     delete_one: String,
+    readable_fields: Option<Json<Vec<String>>>,
+    writable_fields: Option<Json<Vec<String>>>,
 }
 
 impl CollectionRuleModel {
@@ -32,6 +37,8 @@ impl CollectionRuleModel {
         insert_one: &bool,
         update_one: &str,
         delete_one: &str,
+        readable_fields: &Option<Json<Vec<String>>>,
+        writable_fields: &Option<Json<Vec<String>>>,
     ) -> Self {
         Self {
             id: *id,
@@ -45,6 +52,8 @@ impl CollectionRuleModel {
             insert_one: *insert_one,
             update_one: update_one.to_owned(),
             delete_one: delete_one.to_owned(),
+            readable_fields: readable_fields.clone(),
+            writable_fields: writable_fields.clone(),
         }
     }
 
@@ -91,4 +100,12 @@ impl CollectionRuleModel {
     pub fn delete_one(&self) -> &str {
         &self.delete_one
     }
+
+    pub fn readable_fields(&self) -> &Option<Json<Vec<String>>> {
+        &self.readable_fields
+    }
+
+    pub fn writable_fields(&self) -> &Option<Json<Vec<String>>> {
+        &self.writable_fields
+    }
 }
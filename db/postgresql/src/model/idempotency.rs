@@ -0,0 +1,52 @@
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    FromRow,
+};
+use uuid::Uuid;
+
+#[derive(FromRow)]
+pub struct IdempotencyModel {
+    collection_id: Uuid,
+    owner_id: Uuid,
+    key: String,
+    created_at: DateTime<Utc>,
+    record_id: Uuid,
+}
+
+impl IdempotencyModel {
+    pub fn new(
+        collection_id: &Uuid,
+        owner_id: &Uuid,
+        key: &str,
+        created_at: &DateTime<Utc>,
+        record_id: &Uuid,
+    ) -> Self {
+        Self {
+            collection_id: *collection_id,
+            owner_id: *owner_id,
+            key: key.to_owned(),
+            created_at: *created_at,
+            record_id: *record_id,
+        }
+    }
+
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+
+    pub fn owner_id(&self) -> &Uuid {
+        &self.owner_id
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        &self.created_at
+    }
+
+    pub fn record_id(&self) -> &Uuid {
+        &self.record_id
+    }
+}
@@ -50,6 +50,9 @@ pub enum ColumnKind {
     Ltree,
     Lquery,
     Citext_,
+    VarcharArray,
+    IntegerArray,
+    DoublePrecisionArray,
 }
 
 impl ColumnKind {
@@ -102,6 +105,9 @@ impl ColumnKind {
             Self::Ltree => "ltree",
             Self::Lquery => "lquery",
             Self::Citext_ => "citext_",
+            Self::VarcharArray => "varchar[]",
+            Self::IntegerArray => "integer[]",
+            Self::DoublePrecisionArray => "double precision[]",
         }
     }
 
@@ -154,6 +160,9 @@ impl ColumnKind {
             "ltree" => Ok(Self::Ltree),
             "lquery" => Ok(Self::Lquery),
             "citext_" => Ok(Self::Citext_),
+            "varchar[]" => Ok(Self::VarcharArray),
+            "integer[]" => Ok(Self::IntegerArray),
+            "double precision[]" => Ok(Self::DoublePrecisionArray),
             _ => Err(Error::msg(format!("Unknown schema field kind '{str}'"))),
         }
     }
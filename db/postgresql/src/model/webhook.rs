@@ -0,0 +1,90 @@
+use sqlx::{
+    prelude::FromRow,
+    types::{
+        chrono::{DateTime, Utc},
+        Json,
+    },
+};
+use uuid::Uuid;
+
+#[derive(FromRow)]
+pub struct WebhookModel {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    admin_id: Uuid,
+    project_id: Uuid,
+    collection_id: Uuid,
+    url: String,
+    events: Json<Vec<String>>,
+    secret: String,
+    enabled: bool,
+}
+
+impl WebhookModel {
+    pub fn new(
+        id: &Uuid,
+        created_at: &DateTime<Utc>,
+        updated_at: &DateTime<Utc>,
+        admin_id: &Uuid,
+        project_id: &Uuid,
+        collection_id: &Uuid,
+        url: &str,
+        events: &Json<Vec<String>>,
+        secret: &str,
+        enabled: &bool,
+    ) -> Self {
+        Self {
+            id: *id,
+            created_at: *created_at,
+            updated_at: *updated_at,
+            admin_id: *admin_id,
+            project_id: *project_id,
+            collection_id: *collection_id,
+            url: url.to_owned(),
+            events: events.clone(),
+            secret: secret.to_owned(),
+            enabled: *enabled,
+        }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        &self.created_at
+    }
+
+    pub fn updated_at(&self) -> &DateTime<Utc> {
+        &self.updated_at
+    }
+
+    pub fn admin_id(&self) -> &Uuid {
+        &self.admin_id
+    }
+
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn collection_id(&self) -> &Uuid {
+        &self.collection_id
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn events(&self) -> &Json<Vec<String>> {
+        &self.events
+    }
+
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    pub fn enabled(&self) -> &bool {
+        &self.enabled
+    }
+}
@@ -1,13 +1,19 @@
 pub mod admin;
 pub mod admin_password_reset;
+pub mod admin_project_member;
+pub mod audit;
 pub mod bucket;
 pub mod bucket_rule;
 pub mod collection;
 pub mod collection_rule;
 pub mod file;
+pub mod idempotency;
 pub mod log;
+pub mod migration;
 pub mod project;
 pub mod record;
 pub mod registration;
+pub mod sign_in_attempt;
 pub mod system;
 pub mod token;
+pub mod webhook;
@@ -4,16 +4,16 @@ use uuid::Uuid;
 
 use crate::{db::PostgresDb, model::admin::AdminModel};
 
-const INSERT: &str = "INSERT INTO \"admins\" (\"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\") VALUES ($1, $2, $3, $4, $5)";
-const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\" FROM \"admins\" WHERE \"id\" = $1";
-const SELECT_BY_EMAIL: &str= "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\" FROM \"admins\" WHERE \"email\" = $1";
-const UPDATE: &str = "UPDATE \"admins\" SET \"updated_at\" = $1, \"email\" = $2, \"password_hash\" = $3 WHERE \"id\" = $4";
+const INSERT: &str = "INSERT INTO \"admins\" (\"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"totp_secret\", \"totp_enabled_at\", \"totp_backup_codes\") VALUES ($1, $2, $3, $4, $5, $6, $7, $8)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"totp_secret\", \"totp_enabled_at\", \"totp_backup_codes\" FROM \"admins\" WHERE \"id\" = $1";
+const SELECT_BY_EMAIL: &str= "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"totp_secret\", \"totp_enabled_at\", \"totp_backup_codes\" FROM \"admins\" WHERE \"email\" = $1";
+const UPDATE: &str = "UPDATE \"admins\" SET \"updated_at\" = $1, \"email\" = $2, \"password_hash\" = $3, \"totp_secret\" = $4, \"totp_enabled_at\" = $5, \"totp_backup_codes\" = $6 WHERE \"id\" = $7";
 const DELETE: &str = "DELETE FROM \"admins\" WHERE \"id\" = $1";
 
 pub async fn init(pool: &Pool<Postgres>) {
     hb_log::info(Some("🔧"), "[PostgreSQL] Setting up admins table");
 
-    pool.execute("CREATE TABLE IF NOT EXISTS \"admins\" (\"id\" uuid, \"created_at\" timestamptz(6), \"updated_at\" timestamptz(6), \"email\" text, \"password_hash\" text, PRIMARY KEY (\"id\"))").await.unwrap();
+    pool.execute("CREATE TABLE IF NOT EXISTS \"admins\" (\"id\" uuid, \"created_at\" timestamptz(6), \"updated_at\" timestamptz(6), \"email\" text, \"password_hash\" text, \"totp_secret\" text, \"totp_enabled_at\" timestamptz(6), \"totp_backup_codes\" jsonb, PRIMARY KEY (\"id\"))").await.unwrap();
 
     tokio::try_join!(
         pool.prepare(INSERT),
@@ -33,7 +33,10 @@ impl PostgresDb {
                 .bind(value.created_at())
                 .bind(value.updated_at())
                 .bind(value.email())
-                .bind(value.password_hash()),
+                .bind(value.password_hash())
+                .bind(value.totp_secret())
+                .bind(value.totp_enabled_at())
+                .bind(value.totp_backup_codes()),
         )
         .await?;
         Ok(())
@@ -55,6 +58,9 @@ impl PostgresDb {
                 .bind(value.updated_at())
                 .bind(value.email())
                 .bind(value.password_hash())
+                .bind(value.totp_secret())
+                .bind(value.totp_enabled_at())
+                .bind(value.totp_backup_codes())
                 .bind(value.id()),
         )
         .await?;
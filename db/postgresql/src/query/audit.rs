@@ -0,0 +1,144 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, Pool, Postgres};
+use uuid::Uuid;
+
+use crate::{db::PostgresDb, model::audit::AuditModel};
+
+const INSERT: &str = "INSERT INTO \"audits\" (\"id\", \"created_at\", \"admin_id\", \"project_id\", \"action\", \"entity_type\", \"entity_id\", \"diff\", \"source_ip\") VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"admin_id\", \"project_id\", \"action\", \"entity_type\", \"entity_id\", \"diff\", \"source_ip\" FROM \"audits\" WHERE \"project_id\" = $1";
+const COUNT_MANY_BY_PROJECT_ID: &str = "SELECT COUNT(1) FROM \"audits\" WHERE \"project_id\" = $1";
+
+pub async fn init(pool: &Pool<Postgres>) {
+    hb_log::info(Some("🔧"), "[PostgreSQL] Setting up audits table");
+
+    pool.execute("CREATE TABLE IF NOT EXISTS \"audits\" (\"id\" uuid, \"created_at\" timestamptz(6), \"admin_id\" uuid, \"project_id\" uuid, \"action\" text, \"entity_type\" text, \"entity_id\" uuid, \"diff\" text, \"source_ip\" text, PRIMARY KEY (\"id\"))").await.unwrap();
+
+    tokio::try_join!(
+        pool.prepare(INSERT),
+        pool.prepare(SELECT_MANY_BY_PROJECT_ID),
+        pool.prepare(COUNT_MANY_BY_PROJECT_ID),
+    )
+    .unwrap();
+}
+
+impl PostgresDb {
+    pub async fn insert_audit(&self, value: &AuditModel) -> Result<()> {
+        self.execute(
+            sqlx::query(INSERT)
+                .bind(value.id())
+                .bind(value.created_at())
+                .bind(value.admin_id())
+                .bind(value.project_id())
+                .bind(value.action())
+                .bind(value.entity_type())
+                .bind(value.entity_id())
+                .bind(value.diff())
+                .bind(value.source_ip()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn select_many_audits_by_project_id(
+        &self,
+        project_id: &Uuid,
+        entity_type: &Option<&str>,
+        entity_id: &Option<Uuid>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        before_id: &Option<Uuid>,
+        limit: &Option<i32>,
+    ) -> Result<Vec<AuditModel>> {
+        let mut sql = SELECT_MANY_BY_PROJECT_ID.to_owned();
+        let mut count_values = 1;
+        sql += &Self::audits_filter(entity_type, entity_id, from, to, &mut count_values);
+        if before_id.is_some() {
+            count_values += 1;
+            sql += &format!(" AND \"id\" < ${count_values}");
+        }
+        sql += " ORDER BY \"id\" DESC";
+        if limit.is_some() {
+            count_values += 1;
+            sql += &format!(" LIMIT ${count_values}");
+        }
+
+        let mut query = sqlx::query_as(&sql).bind(project_id);
+        if let Some(entity_type) = entity_type {
+            query = query.bind(entity_type);
+        }
+        if let Some(entity_id) = entity_id {
+            query = query.bind(entity_id);
+        }
+        if let Some(from) = from {
+            query = query.bind(from);
+        }
+        if let Some(to) = to {
+            query = query.bind(to);
+        }
+        if let Some(before_id) = before_id {
+            query = query.bind(before_id);
+        }
+        if let Some(limit) = limit {
+            query = query.bind(limit);
+        }
+
+        Ok(self.fetch_all(query).await?)
+    }
+
+    pub async fn count_many_audits_by_project_id(
+        &self,
+        project_id: &Uuid,
+        entity_type: &Option<&str>,
+        entity_id: &Option<Uuid>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+    ) -> Result<i64> {
+        let sql = COUNT_MANY_BY_PROJECT_ID.to_owned()
+            + &Self::audits_filter(entity_type, entity_id, from, to, &mut 1);
+
+        let mut query = sqlx::query_as(&sql).bind(project_id);
+        if let Some(entity_type) = entity_type {
+            query = query.bind(entity_type);
+        }
+        if let Some(entity_id) = entity_id {
+            query = query.bind(entity_id);
+        }
+        if let Some(from) = from {
+            query = query.bind(from);
+        }
+        if let Some(to) = to {
+            query = query.bind(to);
+        }
+
+        Ok(self.fetch_one::<(i64,)>(query).await?.0)
+    }
+
+    // The values are bound in the same order, right after the project id
+    fn audits_filter(
+        entity_type: &Option<&str>,
+        entity_id: &Option<Uuid>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        count_values: &mut usize,
+    ) -> String {
+        let mut sql = String::new();
+        if entity_type.is_some() {
+            *count_values += 1;
+            sql += &format!(" AND \"entity_type\" = ${count_values}");
+        }
+        if entity_id.is_some() {
+            *count_values += 1;
+            sql += &format!(" AND \"entity_id\" = ${count_values}");
+        }
+        if from.is_some() {
+            *count_values += 1;
+            sql += &format!(" AND \"created_at\" >= ${count_values}");
+        }
+        if to.is_some() {
+            *count_values += 1;
+            sql += &format!(" AND \"created_at\" <= ${count_values}");
+        }
+        sql
+    }
+}
@@ -4,19 +4,20 @@ use uuid::Uuid;
 
 use crate::{db::PostgresDb, model::collection_rule::CollectionRuleModel};
 
-const INSERT: &str = "INSERT INTO \"collection_rules\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\") VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)";
-const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\" FROM \"collection_rules\" WHERE \"id\" = $1";
-const SELECT_BY_TOKEN_ID_AND_COLLECTION_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\" FROM \"collection_rules\" WHERE \"token_id\" = $1 AND \"collection_id\" = $2";
-const SELECT_MANY_BY_TOKEN_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\" FROM \"collection_rules\" WHERE \"token_id\" = $1 ORDER BY \"id\" DESC";
-const UPDATE: &str = "UPDATE \"collection_rules\" SET \"updated_at\" = $1, \"find_one\" = $2, \"find_many\" = $3, \"insert_one\" = $4, \"update_one\" = $5, \"delete_one\" = $6 WHERE \"id\" = $7";
+const INSERT: &str = "INSERT INTO \"collection_rules\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\", \"readable_fields\", \"writable_fields\") VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\", \"readable_fields\", \"writable_fields\" FROM \"collection_rules\" WHERE \"id\" = $1";
+const SELECT_BY_TOKEN_ID_AND_COLLECTION_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\", \"readable_fields\", \"writable_fields\" FROM \"collection_rules\" WHERE \"token_id\" = $1 AND \"collection_id\" = $2";
+const SELECT_MANY_BY_TOKEN_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\", \"readable_fields\", \"writable_fields\" FROM \"collection_rules\" WHERE \"token_id\" = $1 ORDER BY \"id\" DESC";
+const UPDATE: &str = "UPDATE \"collection_rules\" SET \"updated_at\" = $1, \"find_one\" = $2, \"find_many\" = $3, \"insert_one\" = $4, \"update_one\" = $5, \"delete_one\" = $6, \"readable_fields\" = $7, \"writable_fields\" = $8 WHERE \"id\" = $9";
 const DELETE: &str = "DELETE FROM \"collection_rules\" WHERE \"id\" = $1";
 const DELETE_MANY_BY_TOKEN_ID: &str = "DELETE FROM \"collection_rules\" WHERE \"token_id\" = $1";
-const DELETE_MANY_BY_COLLECTION_ID: &str = "DELETE FROM \"collection_rules\" WHERE \"collection_id\" = $1";
+const DELETE_MANY_BY_COLLECTION_ID: &str =
+    "DELETE FROM \"collection_rules\" WHERE \"collection_id\" = $1";
 
 pub async fn init(pool: &Pool<Postgres>) {
     hb_log::info(Some("🔧"), "[PostgreSQL] Setting up collection_rules table");
 
-    pool.execute("CREATE TABLE IF NOT EXISTS \"collection_rules\" (\"id\" uuid, \"created_at\" timestamptz(6), \"updated_at\" timestamptz(6), \"project_id\" uuid, \"token_id\" uuid, \"collection_id\" uuid, \"find_one\" text, \"find_many\" text, \"insert_one\" boolean, \"update_one\" text, \"delete_one\" text, PRIMARY KEY (\"id\"))").await.unwrap();
+    pool.execute("CREATE TABLE IF NOT EXISTS \"collection_rules\" (\"id\" uuid, \"created_at\" timestamptz(6), \"updated_at\" timestamptz(6), \"project_id\" uuid, \"token_id\" uuid, \"collection_id\" uuid, \"find_one\" text, \"find_many\" text, \"insert_one\" boolean, \"update_one\" text, \"delete_one\" text, \"readable_fields\" jsonb, \"writable_fields\" jsonb, PRIMARY KEY (\"id\"))").await.unwrap();
 
     tokio::try_join!(
         pool.prepare(INSERT),
@@ -45,7 +46,9 @@ impl PostgresDb {
                 .bind(value.find_many())
                 .bind(value.insert_one())
                 .bind(value.update_one())
-                .bind(value.delete_one()),
+                .bind(value.delete_one())
+                .bind(value.readable_fields())
+                .bind(value.writable_fields()),
         )
         .await?;
         Ok(())
@@ -87,6 +90,8 @@ impl PostgresDb {
                 .bind(value.insert_one())
                 .bind(value.update_one())
                 .bind(value.delete_one())
+                .bind(value.readable_fields())
+                .bind(value.writable_fields())
                 .bind(value.id()),
         )
         .await?;
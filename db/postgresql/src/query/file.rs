@@ -1,55 +1,84 @@
 use anyhow::{Error, Result};
 use chrono::{Duration, Utc};
-use sqlx::{Executor, Pool, Postgres};
+use sqlx::{postgres::PgArguments, query::QueryAs, Executor, Pool, Postgres};
 use uuid::Uuid;
 
-use crate::{db::PostgresDb, model::file::FileModel};
+use crate::{
+    db::PostgresDb,
+    model::file::{FileFilterModel, FileModel, FileOrderModel},
+};
 
-const INSERT: &str = "INSERT INTO \"files\" (\"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\") VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)";
-const SELECT: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\" FROM \"files\" WHERE \"id\" = $1";
-const SELECT_MANY_BY_BUCKET_ID: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\" FROM \"files\" WHERE \"bucket_id\" = $1";
+const INSERT: &str = "INSERT INTO \"files\" (\"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\") VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)";
+const SELECT: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\" FROM \"files\" WHERE \"id\" = $1";
+const SELECT_MANY_BY_BUCKET_ID: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\" FROM \"files\" WHERE \"bucket_id\" = $1";
+const SELECT_MANY_BY_BUCKET_ID_AND_IDS: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\" FROM \"files\" WHERE \"bucket_id\" = $1 AND \"id\" = ANY($2)";
 const COUNT_MANY_BY_BUCKET_ID: &str = "SELECT COUNT(1) FROM \"files\" WHERE \"bucket_id\" = $1";
-const SELECT_MANY_BY_CREATED_BY_AND_BUCKET_ID: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\" FROM \"files\" WHERE \"created_by\" = $1 AND \"bucket_id\" = $2";
-const COUNT_MANY_BY_CREATED_BY_AND_BUCKET_ID: &str = "SELECT COUNT(1) FROM \"files\" WHERE \"created_by\" = $1 AND \"bucket_id\" = $2";
-const SELECT_MANY_EXPIRE: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\" FROM \"files\" WHERE \"bucket_id\" = $1 AND \"updated_at\" < $2";
-const UPDATE: &str = "UPDATE \"files\" SET \"created_by\" = $1, \"updated_at\" = $2, \"file_name\" = $3, \"public\" = $4 WHERE \"id\" = $5";
+const SELECT_MANY_BY_CREATED_BY_AND_BUCKET_ID: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\" FROM \"files\" WHERE \"created_by\" = $1 AND \"bucket_id\" = $2";
+const COUNT_MANY_BY_CREATED_BY_AND_BUCKET_ID: &str =
+    "SELECT COUNT(1) FROM \"files\" WHERE \"created_by\" = $1 AND \"bucket_id\" = $2";
+const SELECT_MANY_EXPIRE: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\" FROM \"files\" WHERE \"bucket_id\" = $1 AND \"updated_at\" < $2";
+const SELECT_MANY_STALE_UPLOAD: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\" FROM \"files\" WHERE \"pending\" = true AND \"updated_at\" < $1";
+const UPDATE: &str = "UPDATE \"files\" SET \"created_by\" = $1, \"updated_at\" = $2, \"file_name\" = $3, \"public\" = $4, \"pending\" = $5, \"checksum\" = $6 WHERE \"id\" = $7";
+const UPDATE_CONTENT: &str = "UPDATE \"files\" SET \"updated_at\" = $1, \"content_type\" = $2, \"size\" = $3, \"checksum\" = $4 WHERE \"id\" = $5";
 const DELETE: &str = "DELETE FROM \"files\" WHERE \"id\" = $1";
+const INCREMENT_BUCKET_USED_BYTES: &str =
+    "UPDATE \"buckets\" SET \"used_bytes\" = \"used_bytes\" + $1 WHERE \"id\" = $2";
+// Only succeeds while the bucket stays within its quota, so concurrent uploads can't overshoot it
+const RESERVE_BUCKET_USED_BYTES: &str =
+    "UPDATE \"buckets\" SET \"used_bytes\" = \"used_bytes\" + $1 WHERE \"id\" = $2 AND ($1 <= 0 OR \"quota_bytes\" IS NULL OR \"used_bytes\" + $1 <= \"quota_bytes\")";
 
 pub async fn init(pool: &Pool<Postgres>) {
     hb_log::info(Some("🔧"), "[PostgreSQL] Setting up files table");
 
-    pool.execute("CREATE TABLE IF NOT EXISTS \"files\" (\"id\" uuid, \"created_by\" uuid, \"created_at\" timestamptz(6), \"updated_at\" timestamptz(6), \"bucket_id\" uuid, \"file_name\" text, \"content_type\" text, \"size\" bigint, \"public\" boolean, PRIMARY KEY (\"id\"))").await.unwrap();
+    pool.execute("CREATE TABLE IF NOT EXISTS \"files\" (\"id\" uuid, \"created_by\" uuid, \"created_at\" timestamptz(6), \"updated_at\" timestamptz(6), \"bucket_id\" uuid, \"file_name\" text, \"content_type\" text, \"size\" bigint, \"public\" boolean, \"pending\" boolean, \"checksum\" text, PRIMARY KEY (\"id\"))").await.unwrap();
 
     tokio::try_join!(
         pool.prepare(INSERT),
         pool.prepare(SELECT),
         pool.prepare(SELECT_MANY_BY_BUCKET_ID),
+        pool.prepare(SELECT_MANY_BY_BUCKET_ID_AND_IDS),
         pool.prepare(COUNT_MANY_BY_BUCKET_ID),
         pool.prepare(SELECT_MANY_BY_CREATED_BY_AND_BUCKET_ID),
         pool.prepare(COUNT_MANY_BY_CREATED_BY_AND_BUCKET_ID),
         pool.prepare(SELECT_MANY_EXPIRE),
+        pool.prepare(SELECT_MANY_STALE_UPLOAD),
         pool.prepare(UPDATE),
+        pool.prepare(UPDATE_CONTENT),
         pool.prepare(DELETE),
     )
     .unwrap();
 }
 
 impl PostgresDb {
-    pub async fn insert_file(&self, value: &FileModel) -> Result<()> {
-        self.execute(
-            sqlx::query(INSERT)
-                .bind(value.id())
-                .bind(value.created_by())
-                .bind(value.created_at())
-                .bind(value.updated_at())
-                .bind(value.bucket_id())
-                .bind(value.file_name())
-                .bind(value.content_type())
-                .bind(value.size())
-                .bind(value.public()),
-        )
-        .await?;
-        Ok(())
+    // Returns false, writing nothing, when the file doesn't fit in the quota of its bucket
+    pub async fn insert_file(&self, value: &FileModel) -> Result<bool> {
+        // The file and the usage of its bucket are written together, so the usage never drifts
+        let mut tx = self.begin().await?;
+        sqlx::query(INSERT)
+            .bind(value.id())
+            .bind(value.created_by())
+            .bind(value.created_at())
+            .bind(value.updated_at())
+            .bind(value.bucket_id())
+            .bind(value.file_name())
+            .bind(value.content_type())
+            .bind(value.size())
+            .bind(value.public())
+            .bind(value.pending())
+            .bind(value.checksum())
+            .execute(&mut *tx)
+            .await?;
+        let reserved = sqlx::query(RESERVE_BUCKET_USED_BYTES)
+            .bind(value.size())
+            .bind(value.bucket_id())
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if reserved == 0 {
+            return Ok(false);
+        }
+        tx.commit().await?;
+        Ok(true)
     }
 
     pub async fn select_file(&self, id: &Uuid) -> Result<FileModel> {
@@ -59,22 +88,21 @@ impl PostgresDb {
     pub async fn select_many_files_by_bucket_id(
         &self,
         bucket_id: &Uuid,
+        filter: &FileFilterModel,
+        order: &FileOrderModel,
         before_id: &Option<Uuid>,
         limit: &Option<i32>,
     ) -> Result<Vec<FileModel>> {
         let mut sql = SELECT_MANY_BY_BUCKET_ID.to_owned();
         let mut count_values = 1;
-        if before_id.is_some() {
-            count_values += 1;
-            sql += &format!(" AND \"id\" < ${count_values}");
-        }
-        sql += " ORDER BY \"id\" DESC";
+        push_filter(&mut sql, &mut count_values, filter);
+        push_order(&mut sql, &mut count_values, order, before_id);
         if limit.is_some() {
             count_values += 1;
             sql += &format!(" LIMIT ${count_values}");
         }
 
-        let mut query = sqlx::query_as(&sql).bind(bucket_id);
+        let mut query = bind_filter(sqlx::query_as(&sql).bind(bucket_id), filter);
         if let Some(before_id) = before_id {
             query = query.bind(before_id);
         }
@@ -85,9 +113,30 @@ impl PostgresDb {
         Ok(self.fetch_all(query).await?)
     }
 
-    pub async fn count_many_files_by_bucket_id(&self, bucket_id: &Uuid) -> Result<i64> {
+    pub async fn select_many_files_by_bucket_id_and_ids(
+        &self,
+        bucket_id: &Uuid,
+        ids: &[Uuid],
+    ) -> Result<Vec<FileModel>> {
         Ok(self
-            .fetch_one::<(i64,)>(sqlx::query_as(COUNT_MANY_BY_BUCKET_ID).bind(bucket_id))
+            .fetch_all(
+                sqlx::query_as(SELECT_MANY_BY_BUCKET_ID_AND_IDS)
+                    .bind(bucket_id)
+                    .bind(ids),
+            )
+            .await?)
+    }
+
+    pub async fn count_many_files_by_bucket_id(
+        &self,
+        bucket_id: &Uuid,
+        filter: &FileFilterModel,
+    ) -> Result<i64> {
+        let mut sql = COUNT_MANY_BY_BUCKET_ID.to_owned();
+        push_filter(&mut sql, &mut 1, filter);
+
+        Ok(self
+            .fetch_one::<(i64,)>(bind_filter(sqlx::query_as(&sql).bind(bucket_id), filter))
             .await?
             .0)
     }
@@ -96,22 +145,24 @@ impl PostgresDb {
         &self,
         created_by: &Uuid,
         bucket_id: &Uuid,
+        filter: &FileFilterModel,
+        order: &FileOrderModel,
         before_id: &Option<Uuid>,
         limit: &Option<i32>,
     ) -> Result<Vec<FileModel>> {
         let mut sql = SELECT_MANY_BY_CREATED_BY_AND_BUCKET_ID.to_owned();
         let mut count_values = 2;
-        if before_id.is_some() {
-            count_values += 1;
-            sql += &format!(" AND \"id\" < ${count_values}");
-        }
-        sql += " ORDER BY \"id\" DESC";
+        push_filter(&mut sql, &mut count_values, filter);
+        push_order(&mut sql, &mut count_values, order, before_id);
         if limit.is_some() {
             count_values += 1;
             sql += &format!(" LIMIT ${count_values}");
         }
 
-        let mut query = sqlx::query_as(&sql).bind(created_by).bind(bucket_id);
+        let mut query = bind_filter(
+            sqlx::query_as(&sql).bind(created_by).bind(bucket_id),
+            filter,
+        );
         if let Some(before_id) = before_id {
             query = query.bind(before_id);
         }
@@ -126,13 +177,16 @@ impl PostgresDb {
         &self,
         created_by: &Uuid,
         bucket_id: &Uuid,
+        filter: &FileFilterModel,
     ) -> Result<i64> {
+        let mut sql = COUNT_MANY_BY_CREATED_BY_AND_BUCKET_ID.to_owned();
+        push_filter(&mut sql, &mut 2, filter);
+
         Ok(self
-            .fetch_one::<(i64,)>(
-                sqlx::query_as(COUNT_MANY_BY_CREATED_BY_AND_BUCKET_ID)
-                    .bind(created_by)
-                    .bind(bucket_id),
-            )
+            .fetch_one::<(i64,)>(bind_filter(
+                sqlx::query_as(&sql).bind(created_by).bind(bucket_id),
+                filter,
+            ))
             .await?
             .0)
     }
@@ -156,6 +210,24 @@ impl PostgresDb {
             .await?)
     }
 
+    pub async fn select_many_stale_upload_files(
+        &self,
+        ttl_seconds: &i64,
+    ) -> Result<Vec<FileModel>> {
+        Ok(self
+            .fetch_all(
+                sqlx::query_as(SELECT_MANY_STALE_UPLOAD).bind(
+                    Utc::now()
+                        .checked_sub_signed(
+                            Duration::try_seconds(*ttl_seconds)
+                                .ok_or_else(|| Error::msg("upload session ttl is out of range."))?,
+                        )
+                        .ok_or_else(|| Error::msg("upload session ttl is out of range."))?,
+                ),
+            )
+            .await?)
+    }
+
     pub async fn update_file(&self, value: &FileModel) -> Result<()> {
         self.execute(
             sqlx::query(UPDATE)
@@ -163,14 +235,142 @@ impl PostgresDb {
                 .bind(value.updated_at())
                 .bind(value.file_name())
                 .bind(value.public())
+                .bind(value.pending())
+                .bind(value.checksum())
                 .bind(value.id()),
         )
         .await?;
         Ok(())
     }
 
-    pub async fn delete_file(&self, id: &Uuid) -> Result<()> {
-        self.execute(sqlx::query(DELETE).bind(id)).await?;
+    // Returns false, writing nothing, when the new content doesn't fit in the quota of the bucket
+    pub async fn update_file_content(
+        &self,
+        value: &FileModel,
+        previous_size: &i64,
+    ) -> Result<bool> {
+        let mut tx = self.begin().await?;
+        sqlx::query(UPDATE_CONTENT)
+            .bind(value.updated_at())
+            .bind(value.content_type())
+            .bind(value.size())
+            .bind(value.checksum())
+            .bind(value.id())
+            .execute(&mut *tx)
+            .await?;
+        let reserved = sqlx::query(RESERVE_BUCKET_USED_BYTES)
+            .bind(value.size() - previous_size)
+            .bind(value.bucket_id())
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if reserved == 0 {
+            return Ok(false);
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn delete_file(&self, bucket_id: &Uuid, id: &Uuid, size: &i64) -> Result<()> {
+        let mut tx = self.begin().await?;
+        // Only the delete that actually removed the row gives its size back to the bucket
+        if sqlx::query(DELETE)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+            > 0
+        {
+            sqlx::query(INCREMENT_BUCKET_USED_BYTES)
+                .bind(-size)
+                .bind(bucket_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
         Ok(())
     }
 }
+
+// Appends the conditions of the filter, numbering their placeholders after the ones already in
+// the query. The values are bound in the same order by bind_filter
+fn push_filter(sql: &mut String, count_values: &mut usize, filter: &FileFilterModel) {
+    if filter.file_name().is_some() {
+        *count_values += 1;
+        *sql += &format!(" AND strpos(lower(\"file_name\"), lower(${count_values})) > 0");
+    }
+    if filter.content_type().is_some() {
+        *count_values += 1;
+        *sql += &format!(" AND strpos(lower(\"content_type\"), lower(${count_values})) = 1");
+    }
+    if filter.public().is_some() {
+        *count_values += 1;
+        *sql += &format!(" AND \"public\" = ${count_values}");
+    }
+    if filter.created_by().is_some() {
+        *count_values += 1;
+        *sql += &format!(" AND \"created_by\" = ${count_values}");
+    }
+    if filter.min_size().is_some() {
+        *count_values += 1;
+        *sql += &format!(" AND \"size\" >= ${count_values}");
+    }
+    if filter.max_size().is_some() {
+        *count_values += 1;
+        *sql += &format!(" AND \"size\" <= ${count_values}");
+    }
+}
+
+fn bind_filter<'q, O>(
+    mut query: QueryAs<'q, Postgres, O, PgArguments>,
+    filter: &FileFilterModel,
+) -> QueryAs<'q, Postgres, O, PgArguments> {
+    if let Some(file_name) = filter.file_name() {
+        query = query.bind(file_name.to_owned());
+    }
+    if let Some(content_type) = filter.content_type() {
+        query = query.bind(content_type.to_owned());
+    }
+    if let Some(public) = filter.public() {
+        query = query.bind(*public);
+    }
+    if let Some(created_by) = filter.created_by() {
+        query = query.bind(*created_by);
+    }
+    if let Some(min_size) = filter.min_size() {
+        query = query.bind(*min_size);
+    }
+    if let Some(max_size) = filter.max_size() {
+        query = query.bind(*max_size);
+    }
+    query
+}
+
+// Files are paged by the id of the last file of the previous page. When ordering by another
+// column, the files after it are found by comparing that column first and the id on ties
+fn push_order(
+    sql: &mut String,
+    count_values: &mut usize,
+    order: &FileOrderModel,
+    before_id: &Option<Uuid>,
+) {
+    let column = order.column().column_name();
+    let (comparison, order_kind) = if *order.descending() {
+        ("<", "DESC")
+    } else {
+        (">", "ASC")
+    };
+    if before_id.is_some() {
+        *count_values += 1;
+        if column == "id" {
+            *sql += &format!(" AND \"id\" {comparison} ${count_values}");
+        } else {
+            *sql += &format!(" AND (\"{column}\", \"id\") {comparison} (SELECT \"{column}\", \"id\" FROM \"files\" WHERE \"id\" = ${count_values})");
+        }
+    }
+    if column == "id" {
+        *sql += &format!(" ORDER BY \"id\" {order_kind}");
+    } else {
+        *sql += &format!(" ORDER BY \"{column}\" {order_kind}, \"id\" {order_kind}");
+    }
+}
@@ -0,0 +1,97 @@
+use anyhow::{Error, Result};
+use chrono::{Duration, Utc};
+use sqlx::{types::chrono::DateTime, Executor, Pool, Postgres};
+use uuid::Uuid;
+
+use crate::{db::PostgresDb, model::idempotency::IdempotencyModel};
+
+const INSERT: &str = "INSERT INTO \"idempotency_keys\" (\"collection_id\", \"owner_id\", \"key\", \"created_at\", \"record_id\") VALUES ($1, $2, $3, $4, $5)";
+const SELECT: &str = "SELECT \"collection_id\", \"owner_id\", \"key\", \"created_at\", \"record_id\" FROM \"idempotency_keys\" WHERE \"collection_id\" = $1 AND \"owner_id\" = $2 AND \"key\" = $3 AND \"created_at\" >= $4";
+const DELETE: &str = "DELETE FROM \"idempotency_keys\" WHERE \"collection_id\" = $1 AND \"owner_id\" = $2 AND \"key\" = $3";
+const DELETE_EXPIRE: &str = "DELETE FROM \"idempotency_keys\" WHERE \"created_at\" < $1";
+
+pub async fn init(pool: &Pool<Postgres>) {
+    hb_log::info(Some("🔧"), "[PostgreSQL] Setting up idempotency_keys table");
+
+    pool.execute("CREATE TABLE IF NOT EXISTS \"idempotency_keys\" (\"collection_id\" uuid, \"owner_id\" uuid, \"key\" text, \"created_at\" timestamptz(6), \"record_id\" uuid, PRIMARY KEY (\"collection_id\", \"owner_id\", \"key\"))").await.unwrap();
+
+    tokio::try_join!(
+        pool.prepare(INSERT),
+        pool.prepare(SELECT),
+        pool.prepare(DELETE),
+        pool.prepare(DELETE_EXPIRE),
+    )
+    .unwrap();
+}
+
+impl PostgresDb {
+    pub async fn insert_idempotency(&self, value: &IdempotencyModel) -> Result<()> {
+        let _ = self.delete_expired_idempotencies().await;
+        self.execute(
+            sqlx::query(INSERT)
+                .bind(value.collection_id())
+                .bind(value.owner_id())
+                .bind(value.key())
+                .bind(value.created_at())
+                .bind(value.record_id()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn select_idempotency(
+        &self,
+        collection_id: &Uuid,
+        owner_id: &Uuid,
+        key: &str,
+    ) -> Result<IdempotencyModel> {
+        let _ = self.delete_expired_idempotencies().await;
+        Ok(self
+            .fetch_one(
+                sqlx::query_as(SELECT)
+                    .bind(collection_id)
+                    .bind(owner_id)
+                    .bind(key)
+                    .bind(&{
+                        let now = Utc::now();
+                        DateTime::from_timestamp(
+                            now.timestamp() - self.table_idempotency_ttl(),
+                            now.timestamp_subsec_nanos(),
+                        )
+                        .ok_or_else(|| Error::msg("timestamp is out of range."))?
+                    }),
+            )
+            .await?)
+    }
+
+    pub async fn delete_idempotency(
+        &self,
+        collection_id: &Uuid,
+        owner_id: &Uuid,
+        key: &str,
+    ) -> Result<()> {
+        self.execute(
+            sqlx::query(DELETE)
+                .bind(collection_id)
+                .bind(owner_id)
+                .bind(key),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_expired_idempotencies(&self) -> Result<()> {
+        self.execute(
+            sqlx::query(DELETE_EXPIRE).bind(
+                Utc::now()
+                    .checked_sub_signed(
+                        Duration::try_seconds(*self.table_idempotency_ttl())
+                            .ok_or_else(|| Error::msg("table_idempotency_ttl is out of range."))?,
+                    )
+                    .ok_or_else(|| Error::msg("table_idempotency_ttl is out of range."))?,
+            ),
+        )
+        .await?;
+        Ok(())
+    }
+}
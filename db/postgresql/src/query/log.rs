@@ -1,5 +1,5 @@
 use anyhow::{Error, Result};
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use sqlx::{Executor, Pool, Postgres};
 use uuid::Uuid;
 
@@ -7,7 +7,8 @@ use crate::{db::PostgresDb, model::log::LogModel};
 
 const INSERT: &str = "INSERT INTO \"logs\" (\"id\", \"created_at\", \"admin_id\", \"project_id\", \"kind\", \"message\") VALUES ($1, $2, $3, $4, $5, $6)";
 const SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"admin_id\", \"project_id\", \"kind\", \"message\" FROM \"logs\" WHERE \"admin_id\" = $1 AND \"project_id\" = $2";
-const COUNT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str = "SELECT COUNT(1) FROM \"logs\" WHERE \"admin_id\" = $1 AND \"project_id\" = $2";
+const COUNT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str =
+    "SELECT COUNT(1) FROM \"logs\" WHERE \"admin_id\" = $1 AND \"project_id\" = $2";
 const DELETE_EXPIRE: &str = "DELETE FROM \"logs\" WHERE \"created_at\" < $1";
 
 pub async fn init(pool: &Pool<Postgres>) {
@@ -45,6 +46,10 @@ impl PostgresDb {
         &self,
         admin_id: &Uuid,
         project_id: &Uuid,
+        kind: &Option<&str>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        message: &Option<String>,
         before_id: &Option<Uuid>,
         limit: &Option<i32>,
     ) -> Result<Vec<LogModel>> {
@@ -52,6 +57,7 @@ impl PostgresDb {
 
         let mut sql = SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID.to_owned();
         let mut count_values = 2;
+        sql += &Self::logs_filter(kind, from, to, message, &mut count_values);
         if before_id.is_some() {
             count_values += 1;
             sql += &format!(" AND \"id\" < ${count_values}");
@@ -63,6 +69,18 @@ impl PostgresDb {
         }
 
         let mut query = sqlx::query_as(&sql).bind(admin_id).bind(project_id);
+        if let Some(kind) = kind {
+            query = query.bind(kind);
+        }
+        if let Some(from) = from {
+            query = query.bind(from);
+        }
+        if let Some(to) = to {
+            query = query.bind(to);
+        }
+        if let Some(message) = message {
+            query = query.bind(message_pattern(message));
+        }
         if let Some(before_id) = before_id {
             query = query.bind(before_id);
         }
@@ -77,17 +95,59 @@ impl PostgresDb {
         &self,
         admin_id: &Uuid,
         project_id: &Uuid,
+        kind: &Option<&str>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        message: &Option<String>,
     ) -> Result<i64> {
         let _ = self.delete_expired_logs().await?;
 
-        Ok(self
-            .fetch_one::<(i64,)>(
-                sqlx::query_as(COUNT_MANY_BY_ADMIN_ID_AND_PROJECT_ID)
-                    .bind(admin_id)
-                    .bind(project_id),
-            )
-            .await?
-            .0)
+        let sql = COUNT_MANY_BY_ADMIN_ID_AND_PROJECT_ID.to_owned()
+            + &Self::logs_filter(kind, from, to, message, &mut 2);
+
+        let mut query = sqlx::query_as(&sql).bind(admin_id).bind(project_id);
+        if let Some(kind) = kind {
+            query = query.bind(kind);
+        }
+        if let Some(from) = from {
+            query = query.bind(from);
+        }
+        if let Some(to) = to {
+            query = query.bind(to);
+        }
+        if let Some(message) = message {
+            query = query.bind(message_pattern(message));
+        }
+
+        Ok(self.fetch_one::<(i64,)>(query).await?.0)
+    }
+
+    // The values are bound in the same order, right after the admin and project ids
+    fn logs_filter(
+        kind: &Option<&str>,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        message: &Option<String>,
+        count_values: &mut usize,
+    ) -> String {
+        let mut sql = String::new();
+        if kind.is_some() {
+            *count_values += 1;
+            sql += &format!(" AND \"kind\" = ${count_values}");
+        }
+        if from.is_some() {
+            *count_values += 1;
+            sql += &format!(" AND \"created_at\" >= ${count_values}");
+        }
+        if to.is_some() {
+            *count_values += 1;
+            sql += &format!(" AND \"created_at\" <= ${count_values}");
+        }
+        if message.is_some() {
+            *count_values += 1;
+            sql += &format!(" AND \"message\" ILIKE ${count_values}");
+        }
+        sql
     }
 
     async fn delete_expired_logs(&self) -> Result<()> {
@@ -105,3 +165,14 @@ impl PostgresDb {
         Ok(())
     }
 }
+
+// Matches the message anywhere, with the wildcards in it taken literally
+fn message_pattern(message: &str) -> String {
+    format!(
+        "%{}%",
+        message
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    )
+}
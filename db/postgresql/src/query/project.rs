@@ -6,6 +6,7 @@ use crate::{db::PostgresDb, model::project::ProjectModel};
 
 const INSERT: &str = "INSERT INTO \"projects\" (\"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\") VALUES ($1, $2, $3, $4, $5)";
 const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\" FROM \"projects\" WHERE \"id\" = $1";
+const SELECT_MANY: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\" FROM \"projects\" ORDER BY \"id\" DESC";
 const SELECT_MANY_BY_ADMIN_ID:  &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\" FROM \"projects\" WHERE \"admin_id\" = $1 ORDER BY \"id\" DESC";
 const UPDATE: &str = "UPDATE \"projects\" SET \"updated_at\" = $1, \"admin_id\" = $2, \"name\" = $3 WHERE \"id\" = $4";
 const DELETE: &str = "DELETE FROM \"projects\" WHERE \"id\" = $1";
@@ -18,6 +19,7 @@ pub async fn init(pool: &Pool<Postgres>) {
     tokio::try_join!(
         pool.prepare(INSERT),
         pool.prepare(SELECT),
+        pool.prepare(SELECT_MANY),
         pool.prepare(SELECT_MANY_BY_ADMIN_ID),
         pool.prepare(UPDATE),
         pool.prepare(DELETE),
@@ -43,6 +45,10 @@ impl PostgresDb {
         Ok(self.fetch_one(sqlx::query_as(SELECT).bind(id)).await?)
     }
 
+    pub async fn select_many_projects(&self) -> Result<Vec<ProjectModel>> {
+        Ok(self.fetch_all(sqlx::query_as(SELECT_MANY)).await?)
+    }
+
     pub async fn select_many_projects_by_admin_id(
         &self,
         admin_id: &Uuid,
@@ -47,6 +47,10 @@ pub fn drop_columns(record_table: &str, column_names: &HashSet<String>) -> Strin
     )
 }
 
+pub fn rename_column(record_table: &str, column: &str, new_column: &str) -> String {
+    format!("ALTER TABLE \"{record_table}\" RENAME COLUMN \"{column}\" TO \"{new_column}\"")
+}
+
 pub fn change_columns_type(
     record_table: &str,
     columns: &HashMap<String, SchemaFieldPropsModel>,
@@ -71,9 +75,16 @@ pub fn create_index(record_table: &str, index: &str) -> String {
     )
 }
 
-pub fn create_unique_index(record_table: &str, index: &str) -> String {
+// The index is named after its columns, so a single column gets the same name as before
+pub fn create_unique_index(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
-        "CREATE UNIQUE INDEX IF NOT EXISTS \"{record_table}_{index}_unique\" ON \"{record_table}\" (\"{index}\")"
+        "CREATE UNIQUE INDEX IF NOT EXISTS \"{record_table}_{}_unique\" ON \"{record_table}\" ({})",
+        columns.join("_"),
+        columns
+            .iter()
+            .map(|col| format!("\"{col}\""))
+            .collect::<Vec<_>>()
+            .join(", ")
     )
 }
 
@@ -81,8 +92,11 @@ pub fn drop_index(record_table: &str, index: &str) -> String {
     format!("DROP INDEX IF EXISTS \"{record_table}_{index}\"")
 }
 
-pub fn drop_unique_index(record_table: &str, index: &str) -> String {
-    format!("DROP INDEX IF EXISTS \"{record_table}_{index}_unique\"")
+pub fn drop_unique_index(record_table: &str, columns: &Vec<&str>) -> String {
+    format!(
+        "DROP INDEX IF EXISTS \"{record_table}_{}_unique\"",
+        columns.join("_")
+    )
 }
 
 pub fn insert(record_table: &str, columns: &Vec<&str>) -> String {
@@ -99,6 +113,52 @@ pub fn insert(record_table: &str, columns: &Vec<&str>) -> String {
     format!("INSERT INTO \"{record_table}\" ({cols}) VALUES ({vals})")
 }
 
+pub fn insert_many(record_table: &str, columns: &Vec<&str>, rows: &usize) -> String {
+    let cols = columns.iter().map(|col| format!("\"{col}\"")).join(", ");
+    let mut rows_vals = Vec::with_capacity(*rows);
+    for row in 0..*rows {
+        let mut vals = "(".to_owned();
+        for idx in 0..columns.len() {
+            vals += &format!("${}", row * columns.len() + idx + 1);
+            if idx < columns.len() - 1 {
+                vals += ", ";
+            }
+        }
+        vals += ")";
+        rows_vals.push(vals);
+    }
+    format!(
+        "INSERT INTO \"{record_table}\" ({cols}) VALUES {}",
+        rows_vals.join(", ")
+    )
+}
+
+pub fn copy(record_table: &str, source_table: &str, columns: &Vec<&str>) -> String {
+    let cols = columns.iter().map(|col| format!("\"{col}\"")).join(", ");
+    format!("INSERT INTO \"{record_table}\" ({cols}) SELECT {cols} FROM \"{source_table}\"")
+}
+
+// Copies the records that are neither soft-deleted nor expired, the cut-off times of the TTLs are
+// bound as in and_not_expired
+pub fn copy_visible(
+    record_table: &str,
+    source_table: &str,
+    columns: &Vec<&str>,
+    soft_delete: &bool,
+    ttl: &bool,
+    per_record_ttl: &bool,
+) -> String {
+    let mut conditions = not_expired_conditions(ttl, per_record_ttl, &1);
+    if *soft_delete {
+        conditions.insert(0, "\"_deleted_at\" IS NULL".to_owned());
+    }
+    let mut query = copy(record_table, source_table, columns);
+    if !conditions.is_empty() {
+        query += &format!(" WHERE {}", conditions.join(" AND "));
+    }
+    query
+}
+
 pub fn select(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
         "SELECT {} FROM \"{}\" WHERE \"_id\" = $1",
@@ -111,12 +171,48 @@ pub fn select_by_id_and_created_by(record_table: &str, columns: &Vec<&str>) -> S
     select(record_table, columns) + " AND \"_created_by\" = $2"
 }
 
+pub fn select_not_deleted(record_table: &str, columns: &Vec<&str>) -> String {
+    select(record_table, columns) + " AND \"_deleted_at\" IS NULL"
+}
+
+pub fn select_not_deleted_by_id_and_created_by(record_table: &str, columns: &Vec<&str>) -> String {
+    select_not_deleted(record_table, columns) + " AND \"_created_by\" = $2"
+}
+
+// Leaves out the records that outlived the collection TTL or their own '_expired_at'. The cut-off
+// times are bound from `first_argument_idx` on, the one of the collection TTL first.
+pub fn and_not_expired(ttl: &bool, per_record_ttl: &bool, first_argument_idx: &usize) -> String {
+    not_expired_conditions(ttl, per_record_ttl, first_argument_idx)
+        .iter()
+        .map(|condition| format!(" AND {condition}"))
+        .join("")
+}
+
+fn not_expired_conditions(
+    ttl: &bool,
+    per_record_ttl: &bool,
+    first_argument_idx: &usize,
+) -> Vec<String> {
+    let mut conditions = Vec::with_capacity(2);
+    let mut argument_idx = *first_argument_idx;
+    if *ttl {
+        conditions.push(format!("\"_updated_at\" >= ${argument_idx}"));
+        argument_idx += 1;
+    }
+    if *per_record_ttl {
+        conditions.push(format!(
+            "(\"_expired_at\" IS NULL OR \"_expired_at\" >= ${argument_idx})"
+        ));
+    }
+    conditions
+}
+
 pub fn select_many(
     record_table: &str,
     columns: &Vec<&str>,
     filter: &str,
     groups: &Vec<&str>,
-    orders: &Vec<(&str, &str)>,
+    orders: &Vec<(String, &str)>,
     with_query_limit: &bool,
     argument_idx: &usize,
 ) -> String {
@@ -155,7 +251,7 @@ pub fn select_many(
             if count > 0 {
                 query += ","
             }
-            query += &format!(" \"{field}\" {kind}");
+            query += &format!(" {field} {kind}");
             count += 1;
         }
     }
@@ -165,6 +261,11 @@ pub fn select_many(
     query
 }
 
+// Great-circle distance in meters (haversine) between a point column and the given coordinates
+pub fn distance(column: &str, lat: &str, lng: &str) -> String {
+    format!("(2 * 6371008.8 * ASIN(SQRT(POWER(SIN(RADIANS(\"{column}\"[1] - {lat}) / 2), 2) + COS(RADIANS({lat})) * COS(RADIANS(\"{column}\"[1])) * POWER(SIN(RADIANS(\"{column}\"[2] - {lng}) / 2), 2))))")
+}
+
 pub fn update(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
         "UPDATE \"{}\" SET {} WHERE \"_id\" = ${}",
@@ -178,6 +279,19 @@ pub fn update(record_table: &str, columns: &Vec<&str>) -> String {
     )
 }
 
+pub fn backfill_column(record_table: &str, column: &str) -> String {
+    format!("UPDATE \"{record_table}\" SET \"{column}\" = $1 WHERE \"{column}\" IS NULL")
+}
+
+// The row lock taken by UPDATE serializes concurrent increments on the same record
+pub fn increment_counter(record_table: &str, column: &str) -> String {
+    format!("UPDATE \"{record_table}\" SET \"{column}\" = COALESCE(\"{column}\", 0) + $1, \"_updated_at\" = $2 WHERE \"_id\" = $3")
+}
+
+pub fn increment_counter_by_created_by(record_table: &str, column: &str) -> String {
+    increment_counter(record_table, column) + " AND \"_created_by\" = $4"
+}
+
 pub fn delete(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
         "DELETE FROM \"{}\" WHERE {}",
@@ -190,8 +304,35 @@ pub fn delete(record_table: &str, columns: &Vec<&str>) -> String {
     )
 }
 
+// Soft-deleted records keep their row and are left for the sweep of expired records to purge
+pub fn soft_delete(record_table: &str) -> String {
+    format!("UPDATE \"{record_table}\" SET \"_deleted_at\" = $1, \"_updated_at\" = $2 WHERE \"_id\" = $3 AND \"_deleted_at\" IS NULL")
+}
+
+pub fn soft_delete_by_created_by(record_table: &str) -> String {
+    soft_delete(record_table) + " AND \"_created_by\" = $4"
+}
+
+pub fn restore(record_table: &str) -> String {
+    format!("UPDATE \"{record_table}\" SET \"_deleted_at\" = NULL, \"_updated_at\" = $1 WHERE \"_id\" = $2 AND \"_deleted_at\" IS NOT NULL")
+}
+
+pub fn restore_by_created_by(record_table: &str) -> String {
+    restore(record_table) + " AND \"_created_by\" = $3"
+}
+
+pub fn purge_deleted(record_table: &str) -> String {
+    format!("DELETE FROM \"{record_table}\" WHERE \"_deleted_at\" IS NOT NULL")
+}
+
+// Deletes a limited batch of expired records so a sweep never holds the table for long
 pub fn delete_expired(record_table: &str) -> String {
-    format!("DELETE FROM \"{record_table}\" WHERE \"_updated_at\" < $1")
+    format!("DELETE FROM \"{record_table}\" WHERE \"_id\" IN (SELECT \"_id\" FROM \"{record_table}\" WHERE \"_updated_at\" < $1 LIMIT $2)")
+}
+
+// Records of a collection with per-record TTL that have reached their own expiry time
+pub fn delete_expired_by_record(record_table: &str) -> String {
+    format!("DELETE FROM \"{record_table}\" WHERE \"_id\" IN (SELECT \"_id\" FROM \"{record_table}\" WHERE \"_expired_at\" < $1 LIMIT $2)")
 }
 
 pub fn count(record_table: &str, filter: &str, groups: &Vec<&str>) -> String {
@@ -213,3 +354,13 @@ pub fn count(record_table: &str, filter: &str, groups: &Vec<&str>) -> String {
     query += ")";
     query
 }
+
+// Returns the number of rows, soft-deleted ones included, the lowest and highest _id, and the
+// size of the table together with its indexes
+pub fn stats(record_table: &str) -> String {
+    format!("SELECT COUNT(1), (SELECT \"_id\" FROM \"{record_table}\" ORDER BY \"_id\" ASC LIMIT 1), (SELECT \"_id\" FROM \"{record_table}\" ORDER BY \"_id\" DESC LIMIT 1), pg_total_relation_size('\"{record_table}\"') FROM \"{record_table}\"")
+}
+
+pub fn select_indexes(record_table: &str) -> String {
+    format!("SELECT \"indexname\" FROM \"pg_indexes\" WHERE \"tablename\" = '{record_table}' ORDER BY \"indexname\"")
+}
@@ -0,0 +1,44 @@
+use anyhow::Result;
+use sqlx::{Executor, Pool, Postgres};
+
+use crate::{db::PostgresDb, model::sign_in_attempt::SignInAttemptModel};
+
+const UPSERT: &str = "INSERT INTO \"sign_in_attempts\" (\"key\", \"failed_count\", \"locked_until\", \"updated_at\") VALUES ($1, $2, $3, $4) ON CONFLICT (\"key\") DO UPDATE SET \"failed_count\" = EXCLUDED.\"failed_count\", \"locked_until\" = EXCLUDED.\"locked_until\", \"updated_at\" = EXCLUDED.\"updated_at\"";
+const SELECT: &str = "SELECT \"key\", \"failed_count\", \"locked_until\", \"updated_at\" FROM \"sign_in_attempts\" WHERE \"key\" = $1";
+const DELETE: &str = "DELETE FROM \"sign_in_attempts\" WHERE \"key\" = $1";
+
+pub async fn init(pool: &Pool<Postgres>) {
+    hb_log::info(Some("🔧"), "[PostgreSQL] Setting up sign_in_attempts table");
+
+    pool.execute("CREATE TABLE IF NOT EXISTS \"sign_in_attempts\" (\"key\" text, \"failed_count\" integer, \"locked_until\" timestamptz(6), \"updated_at\" timestamptz(6), PRIMARY KEY (\"key\"))").await.unwrap();
+
+    tokio::try_join!(
+        pool.prepare(UPSERT),
+        pool.prepare(SELECT),
+        pool.prepare(DELETE),
+    )
+    .unwrap();
+}
+
+impl PostgresDb {
+    pub async fn upsert_sign_in_attempt(&self, value: &SignInAttemptModel) -> Result<()> {
+        self.execute(
+            sqlx::query(UPSERT)
+                .bind(value.key())
+                .bind(value.failed_count())
+                .bind(value.locked_until())
+                .bind(value.updated_at()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn select_sign_in_attempt(&self, key: &str) -> Result<SignInAttemptModel> {
+        Ok(self.fetch_one(sqlx::query_as(SELECT).bind(key)).await?)
+    }
+
+    pub async fn delete_sign_in_attempt(&self, key: &str) -> Result<()> {
+        self.execute(sqlx::query(DELETE).bind(key)).await?;
+        Ok(())
+    }
+}
@@ -1 +1,3 @@
-pub const COUNT_TABLE: &str = "SELECT COUNT(1) FROM \"information_schema\".\"tables\" WHERE \"table_name\" = $1";
+pub const COUNT_TABLE: &str =
+    "SELECT COUNT(1) FROM \"information_schema\".\"tables\" WHERE \"table_name\" = $1";
+pub const PING: &str = "SELECT 1";
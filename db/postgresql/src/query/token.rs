@@ -1,27 +1,34 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use sqlx::{Executor, Pool, Postgres};
 use uuid::Uuid;
 
 use crate::{db::PostgresDb, model::token::TokenModel};
 
-const INSERT: &str = "INSERT INTO \"tokens\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\") VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)";
-const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\" FROM \"tokens\" WHERE \"id\" = $1";
-const SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\" FROM \"tokens\" WHERE \"admin_id\" = $1 AND \"project_id\" = $2 ORDER BY \"id\" DESC";
-const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\" FROM \"tokens\" WHERE \"project_id\" = $1 ORDER BY \"id\" DESC";
-const UPDATE: &str = "UPDATE \"tokens\" SET \"updated_at\" = $1, \"admin_id\" = $2, \"name\" = $3, \"allow_anonymous\" = $4, \"expired_at\" = $5 WHERE \"id\" = $6";
+const INSERT: &str = "INSERT INTO \"tokens\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\", \"disabled_at\", \"last_used_at\", \"request_count\") VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\", \"disabled_at\", \"last_used_at\", \"request_count\" FROM \"tokens\" WHERE \"id\" = $1";
+const SELECT_BY_TOKEN: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\", \"disabled_at\", \"last_used_at\", \"request_count\" FROM \"tokens\" WHERE \"token\" = $1";
+const SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\", \"disabled_at\", \"last_used_at\", \"request_count\" FROM \"tokens\" WHERE \"admin_id\" = $1 AND \"project_id\" = $2 ORDER BY \"id\" DESC";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\", \"disabled_at\", \"last_used_at\", \"request_count\" FROM \"tokens\" WHERE \"project_id\" = $1 ORDER BY \"id\" DESC";
+const UPDATE: &str = "UPDATE \"tokens\" SET \"updated_at\" = $1, \"admin_id\" = $2, \"name\" = $3, \"token\" = $4, \"allow_anonymous\" = $5, \"expired_at\" = $6, \"disabled_at\" = $7 WHERE \"id\" = $8";
+const UPDATE_USAGE: &str = "UPDATE \"tokens\" SET \"last_used_at\" = $1, \"request_count\" = \"request_count\" + $2 WHERE \"id\" = $3";
 const DELETE: &str = "DELETE FROM \"tokens\" WHERE \"id\" = $1";
+const DELETE_COLLECTION_RULES: &str = "DELETE FROM \"collection_rules\" WHERE \"token_id\" = $1";
+const DELETE_BUCKET_RULES: &str = "DELETE FROM \"bucket_rules\" WHERE \"token_id\" = $1";
 
 pub async fn init(pool: &Pool<Postgres>) {
     hb_log::info(Some("🔧"), "[PostgreSQL] Setting up tokens table");
 
-    pool.execute("CREATE TABLE IF NOT EXISTS \"tokens\" (\"id\" uuid, \"created_at\" timestamptz(6), \"updated_at\" timestamptz(6), \"project_id\" uuid, \"admin_id\" uuid, \"name\" text, \"token\" text, \"allow_anonymous\" boolean, \"expired_at\" timestamptz(6), PRIMARY KEY (\"id\"))").await.unwrap();
+    pool.execute("CREATE TABLE IF NOT EXISTS \"tokens\" (\"id\" uuid, \"created_at\" timestamptz(6), \"updated_at\" timestamptz(6), \"project_id\" uuid, \"admin_id\" uuid, \"name\" text, \"token\" text, \"allow_anonymous\" boolean, \"expired_at\" timestamptz(6), \"disabled_at\" timestamptz(6), \"last_used_at\" timestamptz(6), \"request_count\" bigint, PRIMARY KEY (\"id\"))").await.unwrap();
 
     tokio::try_join!(
         pool.prepare(INSERT),
         pool.prepare(SELECT),
+        pool.prepare(SELECT_BY_TOKEN),
         pool.prepare(SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID),
         pool.prepare(SELECT_MANY_BY_PROJECT_ID),
         pool.prepare(UPDATE),
+        pool.prepare(UPDATE_USAGE),
         pool.prepare(DELETE),
     )
     .unwrap();
@@ -39,7 +46,10 @@ impl PostgresDb {
                 .bind(value.name())
                 .bind(value.token())
                 .bind(value.allow_anonymous())
-                .bind(value.expired_at()),
+                .bind(value.expired_at())
+                .bind(value.disabled_at())
+                .bind(value.last_used_at())
+                .bind(value.request_count()),
         )
         .await?;
         Ok(())
@@ -49,6 +59,12 @@ impl PostgresDb {
         Ok(self.fetch_one(sqlx::query_as(SELECT).bind(id)).await?)
     }
 
+    pub async fn select_token_by_token(&self, token: &str) -> Result<TokenModel> {
+        Ok(self
+            .fetch_one(sqlx::query_as(SELECT_BY_TOKEN).bind(token))
+            .await?)
+    }
+
     pub async fn select_many_tokens_by_admin_id_and_project_id(
         &self,
         admin_id: &Uuid,
@@ -78,16 +94,45 @@ impl PostgresDb {
                 .bind(value.updated_at())
                 .bind(value.admin_id())
                 .bind(value.name())
+                .bind(value.token())
                 .bind(value.allow_anonymous())
                 .bind(value.expired_at())
+                .bind(value.disabled_at())
                 .bind(value.id()),
         )
         .await?;
         Ok(())
     }
 
+    pub async fn increment_token_usage(
+        &self,
+        id: &Uuid,
+        last_used_at: &DateTime<Utc>,
+        requests: &i64,
+    ) -> Result<()> {
+        self.execute(
+            sqlx::query(UPDATE_USAGE)
+                .bind(last_used_at)
+                .bind(requests)
+                .bind(id),
+        )
+        .await?;
+        Ok(())
+    }
+
+    // The rules of the token are deleted together with it
     pub async fn delete_token(&self, id: &Uuid) -> Result<()> {
-        self.execute(sqlx::query(DELETE).bind(id)).await?;
+        let mut tx = self.begin().await?;
+        sqlx::query(DELETE_COLLECTION_RULES)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(DELETE_BUCKET_RULES)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(DELETE).bind(id).execute(&mut *tx).await?;
+        tx.commit().await?;
         Ok(())
     }
 }
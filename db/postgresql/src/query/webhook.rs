@@ -0,0 +1,97 @@
+use anyhow::Result;
+use sqlx::{Executor, Pool, Postgres};
+use uuid::Uuid;
+
+use crate::{db::PostgresDb, model::webhook::WebhookModel};
+
+const INSERT: &str = "INSERT INTO \"webhooks\" (\"id\", \"created_at\", \"updated_at\", \"admin_id\", \"project_id\", \"collection_id\", \"url\", \"events\", \"secret\", \"enabled\") VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"project_id\", \"collection_id\", \"url\", \"events\", \"secret\", \"enabled\" FROM \"webhooks\" WHERE \"id\" = $1";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"project_id\", \"collection_id\", \"url\", \"events\", \"secret\", \"enabled\" FROM \"webhooks\" WHERE \"project_id\" = $1 ORDER BY \"id\" DESC";
+const SELECT_MANY_BY_COLLECTION_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"project_id\", \"collection_id\", \"url\", \"events\", \"secret\", \"enabled\" FROM \"webhooks\" WHERE \"collection_id\" = $1";
+const UPDATE: &str = "UPDATE \"webhooks\" SET \"updated_at\" = $1, \"url\" = $2, \"events\" = $3, \"secret\" = $4, \"enabled\" = $5 WHERE \"id\" = $6";
+const DELETE: &str = "DELETE FROM \"webhooks\" WHERE \"id\" = $1";
+const DELETE_MANY_BY_COLLECTION_ID: &str = "DELETE FROM \"webhooks\" WHERE \"collection_id\" = $1";
+
+pub async fn init(pool: &Pool<Postgres>) {
+    hb_log::info(Some("🔧"), "[PostgreSQL] Setting up webhooks table");
+
+    pool.execute("CREATE TABLE IF NOT EXISTS \"webhooks\" (\"id\" uuid, \"created_at\" timestamptz(6), \"updated_at\" timestamptz(6), \"admin_id\" uuid, \"project_id\" uuid, \"collection_id\" uuid, \"url\" text, \"events\" jsonb, \"secret\" text, \"enabled\" boolean, PRIMARY KEY (\"id\"))").await.unwrap();
+
+    tokio::try_join!(
+        pool.prepare(INSERT),
+        pool.prepare(SELECT),
+        pool.prepare(SELECT_MANY_BY_PROJECT_ID),
+        pool.prepare(SELECT_MANY_BY_COLLECTION_ID),
+        pool.prepare(UPDATE),
+        pool.prepare(DELETE),
+        pool.prepare(DELETE_MANY_BY_COLLECTION_ID),
+    )
+    .unwrap();
+}
+
+impl PostgresDb {
+    pub async fn insert_webhook(&self, value: &WebhookModel) -> Result<()> {
+        self.execute(
+            sqlx::query(INSERT)
+                .bind(value.id())
+                .bind(value.created_at())
+                .bind(value.updated_at())
+                .bind(value.admin_id())
+                .bind(value.project_id())
+                .bind(value.collection_id())
+                .bind(value.url())
+                .bind(value.events())
+                .bind(value.secret())
+                .bind(value.enabled()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn select_webhook(&self, id: &Uuid) -> Result<WebhookModel> {
+        Ok(self.fetch_one(sqlx::query_as(SELECT).bind(id)).await?)
+    }
+
+    pub async fn select_many_webhooks_by_project_id(
+        &self,
+        project_id: &Uuid,
+    ) -> Result<Vec<WebhookModel>> {
+        Ok(self
+            .fetch_all(sqlx::query_as(SELECT_MANY_BY_PROJECT_ID).bind(project_id))
+            .await?)
+    }
+
+    pub async fn select_many_webhooks_by_collection_id(
+        &self,
+        collection_id: &Uuid,
+    ) -> Result<Vec<WebhookModel>> {
+        Ok(self
+            .fetch_all(sqlx::query_as(SELECT_MANY_BY_COLLECTION_ID).bind(collection_id))
+            .await?)
+    }
+
+    pub async fn update_webhook(&self, value: &WebhookModel) -> Result<()> {
+        self.execute(
+            sqlx::query(UPDATE)
+                .bind(value.updated_at())
+                .bind(value.url())
+                .bind(value.events())
+                .bind(value.secret())
+                .bind(value.enabled())
+                .bind(value.id()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_webhook(&self, id: &Uuid) -> Result<()> {
+        self.execute(sqlx::query(DELETE).bind(id)).await?;
+        Ok(())
+    }
+
+    pub async fn delete_many_webhooks_by_collection_id(&self, collection_id: &Uuid) -> Result<()> {
+        self.execute(sqlx::query(DELETE_MANY_BY_COLLECTION_ID).bind(collection_id))
+            .await?;
+        Ok(())
+    }
+}
@@ -1,37 +1,88 @@
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
 use scylla::{
     serialize::row::SerializeRow,
-    transport::{errors::QueryError, iterator::RowIterator},
+    statement::Consistency,
+    transport::{
+        errors::QueryError, iterator::RowIterator, load_balancing::DefaultPolicy, ExecutionProfile,
+    },
     Bytes, CachingSession, QueryResult, SessionBuilder,
 };
+use tracing::Instrument;
 
 use crate::query::{
-    admin, admin_password_reset, bucket, bucket_rule, collection, collection_rule, file, keyspace,
-    log, project, registration, token,
+    admin, admin_password_reset, admin_project_member, audit, bucket, bucket_rule, collection,
+    collection_rule, file, idempotency, keyspace, log, migration, project, registration,
+    sign_in_attempt, system, token, webhook,
 };
 
 pub struct ScyllaDb {
     cached_session: CachingSession,
+    keyspace: String,
+    slow_query_threshold: Option<Duration>,
+    slow_queries: AtomicU64,
 }
 
 impl ScyllaDb {
     pub async fn new(
         user: &str,
         password: &str,
-        host: &str,
+        hosts: &[String],
         port: &str,
+        keyspace: &str,
         replication_factor: &i64,
+        datacenter_replication_factors: &Option<BTreeMap<String, i64>>,
         cache_size: &usize,
+        local_datacenter: &Option<String>,
+        consistency: &Option<String>,
+        statement_timeout: &Option<Duration>,
+        slow_query_threshold: &Option<Duration>,
         table_registration_ttl: &u32,
         table_reset_password_ttl: &u32,
         table_log_ttl: &u32,
+        table_idempotency_ttl: &u32,
     ) -> Self {
         hb_log::info(Some("⚡"), "[ScyllaDB] Initializing component");
 
-        let hostname = format!("{host}:{port}");
+        let mut load_balancing = DefaultPolicy::builder().token_aware(true);
+        if let Some(local_datacenter) = local_datacenter {
+            load_balancing = load_balancing.prefer_datacenter(local_datacenter.to_owned());
+        }
+        let mut execution_profile =
+            ExecutionProfile::builder().load_balancing_policy(load_balancing.build());
+        if let Some(consistency) = consistency {
+            execution_profile = execution_profile.consistency(match consistency.as_str() {
+                "one" => Consistency::One,
+                "two" => Consistency::Two,
+                "three" => Consistency::Three,
+                "quorum" => Consistency::Quorum,
+                "all" => Consistency::All,
+                "local_one" => Consistency::LocalOne,
+                "local_quorum" => Consistency::LocalQuorum,
+                "each_quorum" => Consistency::EachQuorum,
+                _ => {
+                    hb_log::panic(
+                        None,
+                        &format!("[ScyllaDB] Unknown consistency '{consistency}'"),
+                    );
+                    unreachable!()
+                }
+            });
+        }
+        // The driver stops waiting after this, the coordinator may still finish the request
+        if let Some(statement_timeout) = statement_timeout {
+            execution_profile = execution_profile.request_timeout(Some(*statement_timeout));
+        }
+
         let cached_session: CachingSession = CachingSession::from(
             SessionBuilder::new()
-                .known_node(&hostname)
+                .known_nodes(hosts.iter().map(|host| format!("{host}:{port}")))
                 .user(user, password)
+                .default_execution_profile_handle(execution_profile.build().into_handle())
                 .build()
                 .await
                 .unwrap(),
@@ -40,14 +91,26 @@ impl ScyllaDb {
 
         Self::init(
             &cached_session,
+            keyspace,
             replication_factor,
+            datacenter_replication_factors,
             table_registration_ttl,
             table_reset_password_ttl,
             table_log_ttl,
+            table_idempotency_ttl,
         )
         .await;
 
-        Self { cached_session }
+        Self {
+            cached_session,
+            keyspace: keyspace.to_owned(),
+            slow_query_threshold: *slow_query_threshold,
+            slow_queries: AtomicU64::new(0),
+        }
+    }
+
+    pub fn keyspace(&self) -> &str {
+        &self.keyspace
     }
 
     pub async fn session_query(
@@ -55,7 +118,15 @@ impl ScyllaDb {
         query: &str,
         values: impl SerializeRow,
     ) -> Result<QueryResult, QueryError> {
-        self.cached_session.get_session().query(query, values).await
+        let start = Instant::now();
+        let result = self
+            .cached_session
+            .get_session()
+            .query(query, values)
+            .instrument(hb_log::db_span("scylladb", query))
+            .await;
+        self.check_slow_query(query, &start);
+        result
     }
 
     pub async fn execute(
@@ -63,7 +134,14 @@ impl ScyllaDb {
         query: &str,
         values: impl SerializeRow,
     ) -> Result<QueryResult, QueryError> {
-        self.cached_session.execute(query, values).await
+        let start = Instant::now();
+        let result = self
+            .cached_session
+            .execute(query, values)
+            .instrument(hb_log::db_span("scylladb", query))
+            .await;
+        self.check_slow_query(query, &start);
+        result
     }
 
     pub async fn execute_iter(
@@ -80,34 +158,147 @@ impl ScyllaDb {
         values: impl SerializeRow,
         paging_state: Option<Bytes>,
     ) -> Result<QueryResult, QueryError> {
-        self.cached_session
+        let start = Instant::now();
+        let result = self
+            .cached_session
             .execute_paged(query, values, paging_state)
-            .await
+            .instrument(hb_log::db_span("scylladb", query))
+            .await;
+        self.check_slow_query(query, &start);
+        result
+    }
+
+    pub async fn ping(&self) -> Result<(), QueryError> {
+        self.cached_session
+            .get_session()
+            .query(system::PING, &[])
+            .await?;
+        Ok(())
+    }
+
+    // Costs a single Instant::now() per statement when no threshold is set. Bind values are never
+    // part of the statement text, so nothing sensitive ends up in the log.
+    fn check_slow_query(&self, query: &str, start: &Instant) {
+        if let Some(slow_query_threshold) = self.slow_query_threshold {
+            let elapsed = start.elapsed();
+            if elapsed >= slow_query_threshold {
+                self.slow_queries.fetch_add(1, Ordering::Relaxed);
+                hb_log::warn(
+                    None,
+                    &format!(
+                        "[ScyllaDB] Slow query on table '{}' took {elapsed:?}: {query}",
+                        table_name(query)
+                    ),
+                );
+            }
+        }
+    }
+
+    pub fn slow_queries(&self) -> u64 {
+        self.slow_queries.load(Ordering::Relaxed)
+    }
+
+    pub fn pool_nodes(&self) -> u32 {
+        self.cached_session
+            .get_session()
+            .get_cluster_data()
+            .get_nodes_info()
+            .len() as u32
+    }
+
+    pub fn pool_connected_nodes(&self) -> u32 {
+        self.cached_session
+            .get_session()
+            .get_cluster_data()
+            .get_nodes_info()
+            .iter()
+            .filter(|node| !node.is_down())
+            .count() as u32
+    }
+
+    // The driver keeps one connection per shard of every node that is up, a node that didn't
+    // report its shards counts as one
+    pub fn pool_connections(&self) -> u32 {
+        self.cached_session
+            .get_session()
+            .get_cluster_data()
+            .get_nodes_info()
+            .iter()
+            .filter(|node| !node.is_down())
+            .map(|node| match node.sharder() {
+                Some(sharder) => u32::from(sharder.nr_shards.get()),
+                None => 1,
+            })
+            .sum()
+    }
+
+    pub async fn prepare(&self, query: &str) -> Result<(), QueryError> {
+        self.cached_session
+            .add_prepared_statement(&query.into())
+            .await?;
+        Ok(())
     }
 
     async fn init(
         cached_session: &CachingSession,
+        keyspace: &str,
         replication_factor: &i64,
+        datacenter_replication_factors: &Option<BTreeMap<String, i64>>,
         table_registration_ttl: &u32,
         table_reset_password_ttl: &u32,
         table_log_ttl: &u32,
+        table_idempotency_ttl: &u32,
     ) {
         // Create keyspace
-        keyspace::init(cached_session, replication_factor).await;
+        keyspace::init(
+            cached_session,
+            keyspace,
+            replication_factor,
+            datacenter_replication_factors,
+        )
+        .await;
+
+        migration::init(cached_session, keyspace).await;
 
         // Create tables
         tokio::join!(
             admin::init(cached_session),
             project::init(cached_session),
-            collection::init(cached_session),
+            collection::init(cached_session, keyspace),
             bucket::init(cached_session),
             file::init(cached_session),
             token::init(cached_session),
             collection_rule::init(cached_session),
+            admin_project_member::init(cached_session),
             bucket_rule::init(cached_session),
             registration::init(cached_session, table_registration_ttl),
             admin_password_reset::init(cached_session, table_reset_password_ttl),
             log::init(cached_session, table_log_ttl),
+            audit::init(cached_session),
+            idempotency::init(cached_session, table_idempotency_ttl),
+            sign_in_attempt::init(cached_session),
+            webhook::init(cached_session),
         );
     }
 }
+
+// The first table a statement names, which tells the collection a record query is on
+fn table_name(sql: &str) -> &str {
+    let mut words = sql.split_whitespace();
+    while let Some(word) = words.next() {
+        if ["FROM", "INTO", "UPDATE", "TABLE", "JOIN"]
+            .iter()
+            .any(|keyword| word.eq_ignore_ascii_case(keyword))
+        {
+            for word in words.by_ref() {
+                if !["IF", "NOT", "EXISTS", "ONLY"]
+                    .iter()
+                    .any(|keyword| word.eq_ignore_ascii_case(keyword))
+                {
+                    return word.trim_matches(|c| c == '"' || c == '`' || c == '(');
+                }
+            }
+        }
+    }
+    "-"
+}
@@ -8,6 +8,9 @@ pub struct AdminModel {
     updated_at: CqlTimestamp,
     email: String,
     password_hash: String,
+    totp_secret: Option<String>,
+    totp_enabled_at: Option<CqlTimestamp>,
+    totp_backup_codes: Option<Vec<String>>,
 }
 
 impl AdminModel {
@@ -17,6 +20,9 @@ impl AdminModel {
         updated_at: &CqlTimestamp,
         email: &str,
         password_hash: &str,
+        totp_secret: &Option<String>,
+        totp_enabled_at: &Option<CqlTimestamp>,
+        totp_backup_codes: &Option<Vec<String>>,
     ) -> Self {
         Self {
             id: *id,
@@ -24,6 +30,9 @@ impl AdminModel {
             updated_at: *updated_at,
             email: email.to_owned(),
             password_hash: password_hash.to_owned(),
+            totp_secret: totp_secret.clone(),
+            totp_enabled_at: *totp_enabled_at,
+            totp_backup_codes: totp_backup_codes.clone(),
         }
     }
 
@@ -46,4 +55,16 @@ impl AdminModel {
     pub fn password_hash(&self) -> &str {
         &self.password_hash
     }
+
+    pub fn totp_secret(&self) -> &Option<String> {
+        &self.totp_secret
+    }
+
+    pub fn totp_enabled_at(&self) -> &Option<CqlTimestamp> {
+        &self.totp_enabled_at
+    }
+
+    pub fn totp_backup_codes(&self) -> &Option<Vec<String>> {
+        &self.totp_backup_codes
+    }
 }
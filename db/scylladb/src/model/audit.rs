@@ -0,0 +1,77 @@
+use scylla::{frame::value::CqlTimestamp, FromRow, SerializeRow};
+use uuid::Uuid;
+
+#[derive(FromRow, SerializeRow)]
+pub struct AuditModel {
+    id: Uuid,
+    created_at: CqlTimestamp,
+    admin_id: Uuid,
+    project_id: Uuid,
+    action: String,
+    entity_type: String,
+    entity_id: Uuid,
+    diff: String,
+    source_ip: Option<String>,
+}
+
+impl AuditModel {
+    pub fn new(
+        id: &Uuid,
+        created_at: &CqlTimestamp,
+        admin_id: &Uuid,
+        project_id: &Uuid,
+        action: &str,
+        entity_type: &str,
+        entity_id: &Uuid,
+        diff: &str,
+        source_ip: &Option<String>,
+    ) -> Self {
+        Self {
+            id: *id,
+            created_at: *created_at,
+            admin_id: *admin_id,
+            project_id: *project_id,
+            action: action.to_owned(),
+            entity_type: entity_type.to_owned(),
+            entity_id: *entity_id,
+            diff: diff.to_owned(),
+            source_ip: source_ip.to_owned(),
+        }
+    }
+
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn created_at(&self) -> &CqlTimestamp {
+        &self.created_at
+    }
+
+    pub fn admin_id(&self) -> &Uuid {
+        &self.admin_id
+    }
+
+    pub fn project_id(&self) -> &Uuid {
+        &self.project_id
+    }
+
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    pub fn entity_type(&self) -> &str {
+        &self.entity_type
+    }
+
+    pub fn entity_id(&self) -> &Uuid {
+        &self.entity_id
+    }
+
+    pub fn diff(&self) -> &str {
+        &self.diff
+    }
+
+    pub fn source_ip(&self) -> &Option<String> {
+        &self.source_ip
+    }
+}
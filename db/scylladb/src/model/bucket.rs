@@ -10,6 +10,9 @@ pub struct BucketModel {
     name: String,
     path: String,
     opt_ttl: Option<i64>,
+    max_file_size: Option<i64>,
+    allowed_content_types: Option<Vec<String>>,
+    quota_bytes: Option<i64>,
 }
 
 impl BucketModel {
@@ -21,6 +24,9 @@ impl BucketModel {
         name: &str,
         path: &str,
         opt_ttl: &Option<i64>,
+        max_file_size: &Option<i64>,
+        allowed_content_types: &Option<Vec<String>>,
+        quota_bytes: &Option<i64>,
     ) -> Self {
         Self {
             id: *id,
@@ -30,6 +36,9 @@ impl BucketModel {
             name: name.to_owned(),
             path: path.to_owned(),
             opt_ttl: *opt_ttl,
+            max_file_size: *max_file_size,
+            allowed_content_types: allowed_content_types.clone(),
+            quota_bytes: *quota_bytes,
         }
     }
 
@@ -60,4 +69,16 @@ impl BucketModel {
     pub fn opt_ttl(&self) -> &Option<i64> {
         &self.opt_ttl
     }
+
+    pub fn max_file_size(&self) -> &Option<i64> {
+        &self.max_file_size
+    }
+
+    pub fn allowed_content_types(&self) -> &Option<Vec<String>> {
+        &self.allowed_content_types
+    }
+
+    pub fn quota_bytes(&self) -> &Option<i64> {
+        &self.quota_bytes
+    }
 }
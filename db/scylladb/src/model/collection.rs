@@ -14,6 +14,7 @@ pub struct CollectionModel {
     schema_fields: Option<HashMap<String, SchemaFieldPropsModel>>,
     opt_auth_column_id: bool,
     opt_ttl: Option<i64>,
+    opt_mqtt_publish: bool,
 }
 
 impl CollectionModel {
@@ -26,6 +27,7 @@ impl CollectionModel {
         schema_fields: &Option<HashMap<String, SchemaFieldPropsModel>>,
         opt_auth_column_id: &bool,
         opt_ttl: &Option<i64>,
+        opt_mqtt_publish: &bool,
     ) -> Self {
         Self {
             id: *id,
@@ -36,6 +38,7 @@ impl CollectionModel {
             schema_fields: schema_fields.clone(),
             opt_auth_column_id: *opt_auth_column_id,
             opt_ttl: *opt_ttl,
+            opt_mqtt_publish: *opt_mqtt_publish,
         }
     }
 
@@ -70,6 +73,10 @@ impl CollectionModel {
     pub fn opt_ttl(&self) -> &Option<i64> {
         &self.opt_ttl
     }
+
+    pub fn opt_mqtt_publish(&self) -> &bool {
+        &self.opt_mqtt_publish
+    }
 }
 
 #[derive(FromUserType, SerializeCql, Clone)]
@@ -82,6 +89,14 @@ pub struct SchemaFieldPropsModel {
     auth_column: bool,
     hashed: bool,
     hidden: bool,
+    default: Option<String>,
+    min: Option<f64>,
+    max: Option<f64>,
+    max_length: Option<i64>,
+    regex: Option<String>,
+    enum_values: Option<String>,
+    reference_collection_id: Option<Uuid>,
+    on_delete: Option<String>,
 }
 
 impl SchemaFieldPropsModel {
@@ -94,6 +109,14 @@ impl SchemaFieldPropsModel {
         auth_column: &bool,
         hashed: &bool,
         hidden: &bool,
+        default: &Option<String>,
+        min: &Option<f64>,
+        max: &Option<f64>,
+        max_length: &Option<i64>,
+        regex: &Option<String>,
+        enum_values: &Option<String>,
+        reference_collection_id: &Option<Uuid>,
+        on_delete: &Option<String>,
     ) -> Self {
         Self {
             kind: kind.to_owned(),
@@ -104,6 +127,14 @@ impl SchemaFieldPropsModel {
             auth_column: *auth_column,
             hashed: *hashed,
             hidden: *hidden,
+            default: default.clone(),
+            min: *min,
+            max: *max,
+            max_length: *max_length,
+            regex: regex.clone(),
+            enum_values: enum_values.clone(),
+            reference_collection_id: *reference_collection_id,
+            on_delete: on_delete.clone(),
         }
     }
 
@@ -138,4 +169,36 @@ impl SchemaFieldPropsModel {
     pub fn hidden(&self) -> &bool {
         &self.hidden
     }
+
+    pub fn default(&self) -> &Option<String> {
+        &self.default
+    }
+
+    pub fn min(&self) -> &Option<f64> {
+        &self.min
+    }
+
+    pub fn max(&self) -> &Option<f64> {
+        &self.max
+    }
+
+    pub fn max_length(&self) -> &Option<i64> {
+        &self.max_length
+    }
+
+    pub fn regex(&self) -> &Option<String> {
+        &self.regex
+    }
+
+    pub fn enum_values(&self) -> &Option<String> {
+        &self.enum_values
+    }
+
+    pub fn reference_collection_id(&self) -> &Option<Uuid> {
+        &self.reference_collection_id
+    }
+
+    pub fn on_delete(&self) -> &Option<String> {
+        &self.on_delete
+    }
 }
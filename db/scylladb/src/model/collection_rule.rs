@@ -14,6 +14,8 @@ pub struct CollectionRuleModel {
     insert_one: bool,
     update_one: String,
     delete_one: String,
+    readable_fields: Option<Vec<String>>,
+    writable_fields: Option<Vec<String>>,
 }
 
 impl CollectionRuleModel {
@@ -29,6 +31,8 @@ impl CollectionRuleModel {
         insert_one: &bool,
         update_one: &str,
         delete_one: &str,
+        readable_fields: &Option<Vec<String>>,
+        writable_fields: &Option<Vec<String>>,
     ) -> Self {
         Self {
             id: *id,
@@ -42,6 +46,8 @@ impl CollectionRuleModel {
             insert_one: *insert_one,
             update_one: update_one.to_owned(),
             delete_one: delete_one.to_owned(),
+            readable_fields: readable_fields.clone(),
+            writable_fields: writable_fields.clone(),
         }
     }
 
@@ -88,4 +94,12 @@ impl CollectionRuleModel {
     pub fn delete_one(&self) -> &str {
         &self.delete_one
     }
+
+    pub fn readable_fields(&self) -> &Option<Vec<String>> {
+        &self.readable_fields
+    }
+
+    pub fn writable_fields(&self) -> &Option<Vec<String>> {
+        &self.writable_fields
+    }
 }
@@ -12,6 +12,8 @@ pub struct FileModel {
     content_type: String,
     size: i64,
     public: bool,
+    pending: bool,
+    checksum: Option<String>,
 }
 
 impl FileModel {
@@ -25,6 +27,8 @@ impl FileModel {
         content_type: &str,
         size: &i64,
         public: &bool,
+        pending: &bool,
+        checksum: &Option<String>,
     ) -> Self {
         Self {
             id: *id,
@@ -36,6 +40,8 @@ impl FileModel {
             content_type: content_type.to_owned(),
             size: *size,
             public: *public,
+            pending: *pending,
+            checksum: checksum.clone(),
         }
     }
 
@@ -74,4 +80,12 @@ impl FileModel {
     pub fn public(&self) -> &bool {
         &self.public
     }
+
+    pub fn pending(&self) -> &bool {
+        &self.pending
+    }
+
+    pub fn checksum(&self) -> &Option<String> {
+        &self.checksum
+    }
 }
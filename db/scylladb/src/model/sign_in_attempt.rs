@@ -0,0 +1,41 @@
+use scylla::{frame::value::CqlTimestamp, FromRow, SerializeRow};
+
+#[derive(FromRow, SerializeRow)]
+pub struct SignInAttemptModel {
+    key: String,
+    failed_count: i32,
+    locked_until: Option<CqlTimestamp>,
+    updated_at: CqlTimestamp,
+}
+
+impl SignInAttemptModel {
+    pub fn new(
+        key: &str,
+        failed_count: &i32,
+        locked_until: &Option<CqlTimestamp>,
+        updated_at: &CqlTimestamp,
+    ) -> Self {
+        Self {
+            key: key.to_owned(),
+            failed_count: *failed_count,
+            locked_until: *locked_until,
+            updated_at: *updated_at,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn failed_count(&self) -> &i32 {
+        &self.failed_count
+    }
+
+    pub fn locked_until(&self) -> &Option<CqlTimestamp> {
+        &self.locked_until
+    }
+
+    pub fn updated_at(&self) -> &CqlTimestamp {
+        &self.updated_at
+    }
+}
@@ -12,6 +12,8 @@ pub struct TokenModel {
     token: String,
     allow_anonymous: bool,
     expired_at: Option<CqlTimestamp>,
+    disabled_at: Option<CqlTimestamp>,
+    last_used_at: Option<CqlTimestamp>,
 }
 
 impl TokenModel {
@@ -25,6 +27,8 @@ impl TokenModel {
         token: &str,
         allow_anonymous: &bool,
         expired_at: &Option<CqlTimestamp>,
+        disabled_at: &Option<CqlTimestamp>,
+        last_used_at: &Option<CqlTimestamp>,
     ) -> Self {
         Self {
             id: *id,
@@ -36,6 +40,8 @@ impl TokenModel {
             token: token.to_owned(),
             allow_anonymous: *allow_anonymous,
             expired_at: *expired_at,
+            disabled_at: *disabled_at,
+            last_used_at: *last_used_at,
         }
     }
 
@@ -74,4 +80,12 @@ impl TokenModel {
     pub fn expired_at(&self) -> &Option<CqlTimestamp> {
         &self.expired_at
     }
+
+    pub fn disabled_at(&self) -> &Option<CqlTimestamp> {
+        &self.disabled_at
+    }
+
+    pub fn last_used_at(&self) -> &Option<CqlTimestamp> {
+        &self.last_used_at
+    }
 }
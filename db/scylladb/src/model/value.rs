@@ -39,6 +39,9 @@ pub enum ColumnKind {
     Tuple,
     Uuid,
     Varint,
+    ListText,
+    ListInt,
+    ListDouble,
 }
 
 impl ColumnKind {
@@ -70,6 +73,9 @@ impl ColumnKind {
             Self::Tuple => "tuple",
             Self::Uuid => "uuid",
             Self::Varint => "varint",
+            Self::ListText => "list<text>",
+            Self::ListInt => "list<int>",
+            Self::ListDouble => "list<double>",
         }
     }
 
@@ -101,6 +107,9 @@ impl ColumnKind {
             "tuple" => Ok(Self::Tuple),
             "uuid" => Ok(Self::Uuid),
             "varint" => Ok(Self::Varint),
+            "list<text>" => Ok(Self::ListText),
+            "list<int>" => Ok(Self::ListInt),
+            "list<double>" => Ok(Self::ListDouble),
             _ => Err(Error::msg(format!("Unknown schema field kind '{str}'"))),
         }
     }
@@ -4,22 +4,19 @@ use uuid::Uuid;
 
 use crate::{db::ScyllaDb, model::admin::AdminModel};
 
-const INSERT: &str = "INSERT INTO \"hyperbase\".\"admins\" (\"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\") VALUES (?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\" FROM \"hyperbase\".\"admins\" WHERE \"id\" = ?";
-const SELECT_BY_EMAIL: &str= "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\" FROM \"hyperbase\".\"admins\" WHERE \"email\" = ?";
-const UPDATE: &str = "UPDATE \"hyperbase\".\"admins\" SET \"updated_at\" = ?, \"email\" = ?, \"password_hash\" = ? WHERE \"id\" = ?";
-const DELETE: &str = "DELETE FROM \"hyperbase\".\"admins\" WHERE \"id\" = ?";
+const INSERT: &str = "INSERT INTO \"admins\" (\"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"totp_secret\", \"totp_enabled_at\", \"totp_backup_codes\") VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"totp_secret\", \"totp_enabled_at\", \"totp_backup_codes\" FROM \"admins\" WHERE \"id\" = ?";
+const SELECT_BY_EMAIL: &str= "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"totp_secret\", \"totp_enabled_at\", \"totp_backup_codes\" FROM \"admins\" WHERE \"email\" = ?";
+const UPDATE: &str = "UPDATE \"admins\" SET \"updated_at\" = ?, \"email\" = ?, \"password_hash\" = ?, \"totp_secret\" = ?, \"totp_enabled_at\" = ?, \"totp_backup_codes\" = ? WHERE \"id\" = ?";
+const DELETE: &str = "DELETE FROM \"admins\" WHERE \"id\" = ?";
 
 pub async fn init(cached_session: &CachingSession) {
     hb_log::info(Some("🔧"), "[ScyllaDB] Setting up admins table");
 
-    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"hyperbase\".\"admins\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"email\" text, \"password_hash\" text, PRIMARY KEY (\"id\"))",&[]).await.unwrap();
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"admins\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"email\" text, \"password_hash\" text, \"totp_secret\" text, \"totp_enabled_at\" timestamp, \"totp_backup_codes\" list<text>, PRIMARY KEY (\"id\"))",&[]).await.unwrap();
     cached_session
         .get_session()
-        .query(
-            "CREATE INDEX IF NOT EXISTS ON \"hyperbase\".\"admins\" (\"email\")",
-            &[],
-        )
+        .query("CREATE INDEX IF NOT EXISTS ON \"admins\" (\"email\")", &[])
         .await
         .unwrap();
 
@@ -72,6 +69,9 @@ impl ScyllaDb {
                 value.updated_at(),
                 value.email(),
                 value.password_hash(),
+                value.totp_secret(),
+                value.totp_enabled_at(),
+                value.totp_backup_codes(),
                 value.id(),
             ),
         )
@@ -4,10 +4,11 @@ use uuid::Uuid;
 
 use crate::{db::ScyllaDb, model::admin_password_reset::AdminPasswordResetModel};
 
-const INSERT: &str = "INSERT INTO \"hyperbase\".\"admin_password_resets\" (\"id\", \"created_at\", \"updated_at\", \"admin_id\", \"code\") VALUES (?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"code\" FROM \"hyperbase\".\"admin_password_resets\" WHERE \"id\" = ?";
-const UPDATE: &str = "UPDATE \"hyperbase\".\"admin_password_resets\" SET \"updated_at\" = ?, \"code\" = ? WHERE \"id\" = ?";
-const DELETE: &str = "DELETE FROM \"hyperbase\".\"admin_password_resets\" WHERE \"id\" = ?";
+const INSERT: &str = "INSERT INTO \"admin_password_resets\" (\"id\", \"created_at\", \"updated_at\", \"admin_id\", \"code\") VALUES (?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"code\" FROM \"admin_password_resets\" WHERE \"id\" = ?";
+const UPDATE: &str =
+    "UPDATE \"admin_password_resets\" SET \"updated_at\" = ?, \"code\" = ? WHERE \"id\" = ?";
+const DELETE: &str = "DELETE FROM \"admin_password_resets\" WHERE \"id\" = ?";
 
 pub async fn init(cached_session: &CachingSession, ttl: &u32) {
     hb_log::info(
@@ -15,7 +16,7 @@ pub async fn init(cached_session: &CachingSession, ttl: &u32) {
         "[ScyllaDB] Setting up admin_password_resets table",
     );
 
-    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"hyperbase\".\"admin_password_resets\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"admin_id\" uuid, \"code\" text, PRIMARY KEY (\"id\")) WITH default_time_to_live = ".to_owned() + &ttl.to_string(), &[]).await.unwrap();
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"admin_password_resets\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"admin_id\" uuid, \"code\" text, PRIMARY KEY (\"id\")) WITH default_time_to_live = ".to_owned() + &ttl.to_string(), &[]).await.unwrap();
 
     cached_session
         .add_prepared_statement(&INSERT.into())
@@ -0,0 +1,157 @@
+use anyhow::Result;
+use scylla::{transport::session::TypedRowIter, CachingSession};
+use uuid::Uuid;
+
+use crate::{db::ScyllaDb, model::admin_project_member::AdminProjectMemberModel};
+
+const INSERT: &str = "INSERT INTO \"admin_project_members\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"role\") VALUES (?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"role\" FROM \"admin_project_members\" WHERE \"id\" = ?";
+const SELECT_BY_PROJECT_ID_AND_ADMIN_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"role\" FROM \"admin_project_members\" WHERE \"project_id\" = ? AND \"admin_id\" = ? ALLOW FILTERING";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"role\" FROM \"admin_project_members\" WHERE \"project_id\" = ?";
+const SELECT_MANY_BY_ADMIN_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"role\" FROM \"admin_project_members\" WHERE \"admin_id\" = ?";
+const UPDATE: &str =
+    "UPDATE \"admin_project_members\" SET \"updated_at\" = ?, \"role\" = ? WHERE \"id\" = ?";
+const DELETE: &str = "DELETE FROM \"admin_project_members\" WHERE \"id\" = ?";
+
+pub async fn init(cached_session: &CachingSession) {
+    hb_log::info(
+        Some("🔧"),
+        "[ScyllaDB] Setting up admin_project_members table",
+    );
+
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"admin_project_members\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"project_id\" uuid, \"admin_id\" uuid, \"role\" text, PRIMARY KEY (\"id\"))", &[]).await.unwrap();
+    cached_session
+        .get_session()
+        .query(
+            "CREATE INDEX IF NOT EXISTS ON \"admin_project_members\" (\"project_id\")",
+            &[],
+        )
+        .await
+        .unwrap();
+    cached_session
+        .get_session()
+        .query(
+            "CREATE INDEX IF NOT EXISTS ON \"admin_project_members\" (\"admin_id\")",
+            &[],
+        )
+        .await
+        .unwrap();
+
+    cached_session
+        .add_prepared_statement(&INSERT.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT_BY_PROJECT_ID_AND_ADMIN_ID.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT_MANY_BY_PROJECT_ID.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT_MANY_BY_ADMIN_ID.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&UPDATE.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&DELETE.into())
+        .await
+        .unwrap();
+}
+
+impl ScyllaDb {
+    pub async fn insert_admin_project_member(&self, value: &AdminProjectMemberModel) -> Result<()> {
+        self.execute(INSERT, value).await?;
+        Ok(())
+    }
+
+    pub async fn select_admin_project_member(&self, id: &Uuid) -> Result<AdminProjectMemberModel> {
+        Ok(self
+            .execute(SELECT, [id].as_ref())
+            .await?
+            .first_row_typed()?)
+    }
+
+    pub async fn select_admin_project_member_by_project_id_and_admin_id(
+        &self,
+        project_id: &Uuid,
+        admin_id: &Uuid,
+    ) -> Result<AdminProjectMemberModel> {
+        Ok(self
+            .execute(
+                SELECT_BY_PROJECT_ID_AND_ADMIN_ID,
+                [project_id, admin_id].as_ref(),
+            )
+            .await?
+            .first_row_typed()?)
+    }
+
+    pub async fn select_many_admin_project_members_by_project_id(
+        &self,
+        project_id: &Uuid,
+    ) -> Result<TypedRowIter<AdminProjectMemberModel>> {
+        Ok(self
+            .execute(SELECT_MANY_BY_PROJECT_ID, [project_id].as_ref())
+            .await?
+            .rows_typed()?)
+    }
+
+    pub async fn select_many_admin_project_members_by_admin_id(
+        &self,
+        admin_id: &Uuid,
+    ) -> Result<TypedRowIter<AdminProjectMemberModel>> {
+        Ok(self
+            .execute(SELECT_MANY_BY_ADMIN_ID, [admin_id].as_ref())
+            .await?
+            .rows_typed()?)
+    }
+
+    pub async fn update_admin_project_member(&self, value: &AdminProjectMemberModel) -> Result<()> {
+        self.execute(UPDATE, &(value.updated_at(), value.role(), value.id()))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_admin_project_member(&self, id: &Uuid) -> Result<()> {
+        self.execute(DELETE, [id].as_ref()).await?;
+        Ok(())
+    }
+
+    pub async fn delete_many_admin_project_members_by_project_id(
+        &self,
+        project_id: &Uuid,
+    ) -> Result<()> {
+        let members_data = self
+            .select_many_admin_project_members_by_project_id(project_id)
+            .await?;
+        let mut deletes = Vec::new();
+        for member_data in members_data {
+            deletes.push(self.execute(DELETE, (*member_data?.id(),)));
+        }
+        futures::future::join_all(deletes).await;
+        Ok(())
+    }
+
+    pub async fn delete_many_admin_project_members_by_admin_id(
+        &self,
+        admin_id: &Uuid,
+    ) -> Result<()> {
+        let members_data = self
+            .select_many_admin_project_members_by_admin_id(admin_id)
+            .await?;
+        let mut deletes = Vec::new();
+        for member_data in members_data {
+            deletes.push(self.execute(DELETE, (*member_data?.id(),)));
+        }
+        futures::future::join_all(deletes).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::Result;
+use scylla::{
+    frame::value::CqlTimestamp, serialize::value::SerializeCql, transport::session::TypedRowIter,
+    CachingSession,
+};
+use uuid::Uuid;
+
+use crate::{db::ScyllaDb, model::audit::AuditModel};
+
+const INSERT: &str = "INSERT INTO \"audits\" (\"id\", \"created_at\", \"admin_id\", \"project_id\", \"action\", \"entity_type\", \"entity_id\", \"diff\", \"source_ip\") VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"admin_id\", \"project_id\", \"action\", \"entity_type\", \"entity_id\", \"diff\", \"source_ip\" FROM \"audits\" WHERE \"project_id\" = ?";
+const COUNT_MANY_BY_PROJECT_ID: &str = "SELECT COUNT(1) FROM \"audits\" WHERE \"project_id\" = ?";
+
+pub async fn init(cached_session: &CachingSession) {
+    hb_log::info(Some("🔧"), "[ScyllaDB] Setting up audits table");
+
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"audits\" (\"id\" uuid, \"created_at\" timestamp, \"admin_id\" uuid, \"project_id\" uuid, \"action\" text, \"entity_type\" text, \"entity_id\" uuid, \"diff\" text, \"source_ip\" text, PRIMARY KEY ((\"project_id\"), \"id\")) WITH CLUSTERING ORDER BY (\"id\" DESC)", &[]).await.unwrap();
+
+    cached_session
+        .add_prepared_statement(&INSERT.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT_MANY_BY_PROJECT_ID.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&COUNT_MANY_BY_PROJECT_ID.into())
+        .await
+        .unwrap();
+}
+
+impl ScyllaDb {
+    pub async fn insert_audit(&self, value: &AuditModel) -> Result<()> {
+        self.execute(INSERT, value).await?;
+        Ok(())
+    }
+
+    pub async fn select_many_audits_by_project_id(
+        &self,
+        project_id: &Uuid,
+        entity_type: &Option<&str>,
+        entity_id: &Option<Uuid>,
+        from: &Option<CqlTimestamp>,
+        to: &Option<CqlTimestamp>,
+        before_id: &Option<Uuid>,
+        limit: &Option<i32>,
+    ) -> Result<TypedRowIter<AuditModel>> {
+        let mut query = SELECT_MANY_BY_PROJECT_ID.to_owned();
+        let mut values: Vec<Box<dyn SerializeCql + Send + Sync>> = Vec::with_capacity(7);
+        values.push(Box::new(*project_id));
+        let filtered =
+            Self::audits_filter(entity_type, entity_id, from, to, &mut query, &mut values);
+        if let Some(before_id) = before_id {
+            query += " AND \"id\" < ?";
+            values.push(Box::new(*before_id));
+        }
+        if let Some(limit) = limit {
+            query += " LIMIT ?";
+            values.push(Box::new(*limit));
+        }
+        if filtered {
+            query += " ALLOW FILTERING";
+        }
+        Ok(self.execute(&query, &values).await?.rows_typed()?)
+    }
+
+    pub async fn count_many_audits_by_project_id(
+        &self,
+        project_id: &Uuid,
+        entity_type: &Option<&str>,
+        entity_id: &Option<Uuid>,
+        from: &Option<CqlTimestamp>,
+        to: &Option<CqlTimestamp>,
+    ) -> Result<i64> {
+        let mut query = COUNT_MANY_BY_PROJECT_ID.to_owned();
+        let mut values: Vec<Box<dyn SerializeCql + Send + Sync>> = Vec::with_capacity(5);
+        values.push(Box::new(*project_id));
+        if Self::audits_filter(entity_type, entity_id, from, to, &mut query, &mut values) {
+            query += " ALLOW FILTERING";
+        }
+        Ok(self
+            .execute(&query, &values)
+            .await?
+            .first_row_typed::<(i64,)>()?
+            .0)
+    }
+
+    // These columns aren't part of the key, so filtering by them is done within the partition of
+    // the project. Returns whether any of them is filtered by.
+    fn audits_filter(
+        entity_type: &Option<&str>,
+        entity_id: &Option<Uuid>,
+        from: &Option<CqlTimestamp>,
+        to: &Option<CqlTimestamp>,
+        query: &mut String,
+        values: &mut Vec<Box<dyn SerializeCql + Send + Sync>>,
+    ) -> bool {
+        let len = values.len();
+        if let Some(entity_type) = entity_type {
+            *query += " AND \"entity_type\" = ?";
+            values.push(Box::new((*entity_type).to_owned()));
+        }
+        if let Some(entity_id) = entity_id {
+            *query += " AND \"entity_id\" = ?";
+            values.push(Box::new(*entity_id));
+        }
+        if let Some(from) = from {
+            *query += " AND \"created_at\" >= ?";
+            values.push(Box::new(*from));
+        }
+        if let Some(to) = to {
+            *query += " AND \"created_at\" <= ?";
+            values.push(Box::new(*to));
+        }
+        values.len() > len
+    }
+}
@@ -1,27 +1,35 @@
 use anyhow::Result;
-use scylla::{transport::session::TypedRowIter, CachingSession};
+use scylla::{frame::value::Counter, transport::session::TypedRowIter, CachingSession};
 use uuid::Uuid;
 
 use crate::{db::ScyllaDb, model::bucket::BucketModel};
 
-pub const INSERT: &str = "INSERT INTO \"hyperbase\".\"buckets\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"path\", \"opt_ttl\") VALUES (?, ?, ?, ?, ?, ?, ?)";
-pub const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"path\", \"opt_ttl\" FROM \"hyperbase\".\"buckets\" WHERE \"id\" = ?";
-pub const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"path\", \"opt_ttl\" FROM \"hyperbase\".\"buckets\" WHERE \"project_id\" = ?";
-pub const UPDATE: &str = "UPDATE \"hyperbase\".\"buckets\" SET \"updated_at\" = ?, \"name\" = ?, \"opt_ttl\" = ? WHERE \"id\" = ?";
-pub const DELETE: &str = "DELETE FROM \"hyperbase\".\"buckets\" WHERE \"id\" = ?";
+pub const INSERT: &str = "INSERT INTO \"buckets\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"path\", \"opt_ttl\", \"max_file_size\", \"allowed_content_types\", \"quota_bytes\") VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+pub const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"path\", \"opt_ttl\", \"max_file_size\", \"allowed_content_types\", \"quota_bytes\" FROM \"buckets\" WHERE \"id\" = ?";
+pub const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"path\", \"opt_ttl\", \"max_file_size\", \"allowed_content_types\", \"quota_bytes\" FROM \"buckets\" WHERE \"project_id\" = ?";
+pub const UPDATE: &str = "UPDATE \"buckets\" SET \"updated_at\" = ?, \"name\" = ?, \"opt_ttl\" = ?, \"max_file_size\" = ?, \"allowed_content_types\" = ?, \"quota_bytes\" = ? WHERE \"id\" = ?";
+pub const DELETE: &str = "DELETE FROM \"buckets\" WHERE \"id\" = ?";
+pub const INCREMENT_USED_BYTES: &str =
+    "UPDATE \"bucket_usages\" SET \"used_bytes\" = \"used_bytes\" + ? WHERE \"bucket_id\" = ?";
+pub const SELECT_USED_BYTES: &str =
+    "SELECT \"used_bytes\" FROM \"bucket_usages\" WHERE \"bucket_id\" = ?";
+pub const DELETE_USED_BYTES: &str = "DELETE FROM \"bucket_usages\" WHERE \"bucket_id\" = ?";
 
 pub async fn init(cached_session: &CachingSession) {
     hb_log::info(Some("🔧"), "[ScyllaDB] Setting up buckets table");
 
-    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"hyperbase\".\"buckets\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"project_id\" uuid, \"name\" text, \"path\" text, \"opt_ttl\" bigint, PRIMARY KEY (\"id\"))", &[]).await.unwrap();
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"buckets\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"project_id\" uuid, \"name\" text, \"path\" text, \"opt_ttl\" bigint, \"max_file_size\" bigint, \"allowed_content_types\" list<text>, \"quota_bytes\" bigint, PRIMARY KEY (\"id\"))", &[]).await.unwrap();
     cached_session
         .get_session()
         .query(
-            "CREATE INDEX IF NOT EXISTS ON \"hyperbase\".\"buckets\" (\"project_id\")",
+            "CREATE INDEX IF NOT EXISTS ON \"buckets\" (\"project_id\")",
             &[],
         )
         .await
         .unwrap();
+    // Counter columns can't live next to regular columns, so the usage of a bucket is kept in its
+    // own table
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"bucket_usages\" (\"bucket_id\" uuid, \"used_bytes\" counter, PRIMARY KEY (\"bucket_id\"))", &[]).await.unwrap();
 
     cached_session
         .add_prepared_statement(&INSERT.into())
@@ -43,6 +51,18 @@ pub async fn init(cached_session: &CachingSession) {
         .add_prepared_statement(&DELETE.into())
         .await
         .unwrap();
+    cached_session
+        .add_prepared_statement(&INCREMENT_USED_BYTES.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT_USED_BYTES.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&DELETE_USED_BYTES.into())
+        .await
+        .unwrap();
 }
 
 impl ScyllaDb {
@@ -75,6 +95,9 @@ impl ScyllaDb {
                 value.updated_at(),
                 value.name(),
                 value.opt_ttl(),
+                value.max_file_size(),
+                value.allowed_content_types(),
+                value.quota_bytes(),
                 value.id(),
             ),
         )
@@ -82,8 +105,52 @@ impl ScyllaDb {
         Ok(())
     }
 
+    pub async fn increment_bucket_used_bytes(&self, id: &Uuid, delta: &i64) -> Result<()> {
+        self.execute(INCREMENT_USED_BYTES, (Counter(*delta), id))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn select_bucket_used_bytes(&self, id: &Uuid) -> Result<i64> {
+        Ok(self
+            .execute(SELECT_USED_BYTES, [id].as_ref())
+            .await?
+            .maybe_first_row_typed::<(Counter,)>()?
+            .map(|(used_bytes,)| used_bytes.0)
+            .unwrap_or(0))
+    }
+
+    // Counters can't be updated conditionally, so the usage is incremented first and taken back
+    // when it went over the quota. Uploads racing each other may both be turned away, but never
+    // both let through.
+    pub async fn reserve_bucket_used_bytes(&self, id: &Uuid, delta: &i64) -> Result<bool> {
+        self.increment_bucket_used_bytes(id, delta).await?;
+        if *delta <= 0 {
+            return Ok(true);
+        }
+        let quota_bytes = match self.select_bucket(id).await?.quota_bytes() {
+            Some(quota_bytes) => *quota_bytes,
+            None => return Ok(true),
+        };
+        if self.select_bucket_used_bytes(id).await? <= quota_bytes {
+            return Ok(true);
+        }
+        self.increment_bucket_used_bytes(id, &-delta).await?;
+        Ok(false)
+    }
+
+    // Counters can only be incremented, so the difference between the sizes of the files and the
+    // current usage is added instead
+    pub async fn recalculate_bucket_used_bytes(&self, id: &Uuid) -> Result<()> {
+        let size = self.sum_files_size_by_bucket_id(id).await?;
+        let used_bytes = self.select_bucket_used_bytes(id).await?;
+        self.increment_bucket_used_bytes(id, &(size - used_bytes))
+            .await
+    }
+
     pub async fn delete_bucket(&self, id: &Uuid) -> Result<()> {
         self.execute(DELETE, [id].as_ref()).await?;
+        self.execute(DELETE_USED_BYTES, [id].as_ref()).await?;
         Ok(())
     }
 }
@@ -1,39 +1,77 @@
 use anyhow::Result;
-use scylla::{transport::session::TypedRowIter, CachingSession};
+use scylla::{
+    transport::{iterator::TypedRowIterator, session::TypedRowIter},
+    CachingSession,
+};
 use uuid::Uuid;
 
 use crate::{db::ScyllaDb, model::collection::CollectionModel};
 
-const INSERT: &str = "INSERT INTO \"hyperbase\".\"collections\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"schema_fields\", \"opt_auth_column_id\", \"opt_ttl\") VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"schema_fields\", \"opt_auth_column_id\", \"opt_ttl\" FROM \"hyperbase\".\"collections\" WHERE \"id\" = ?";
-const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"schema_fields\", \"opt_auth_column_id\", \"opt_ttl\" FROM \"hyperbase\".\"collections\" WHERE \"project_id\" = ?";
-const UPDATE: &str = "UPDATE \"hyperbase\".\"collections\" SET \"updated_at\" = ?, \"name\" = ?, \"schema_fields\" = ?, \"opt_auth_column_id\" = ?, \"opt_ttl\" = ? WHERE \"id\" = ?";
-const DELETE: &str = "DELETE FROM \"hyperbase\".\"collections\" WHERE \"id\" = ?";
+const INSERT: &str = "INSERT INTO \"collections\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"schema_fields\", \"opt_auth_column_id\", \"opt_ttl\", \"opt_mqtt_publish\") VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"schema_fields\", \"opt_auth_column_id\", \"opt_ttl\", \"opt_mqtt_publish\" FROM \"collections\" WHERE \"id\" = ?";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"schema_fields\", \"opt_auth_column_id\", \"opt_ttl\", \"opt_mqtt_publish\" FROM \"collections\" WHERE \"project_id\" = ?";
+const SELECT_MANY: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"schema_fields\", \"opt_auth_column_id\", \"opt_ttl\", \"opt_mqtt_publish\" FROM \"collections\"";
+const UPDATE: &str = "UPDATE \"collections\" SET \"updated_at\" = ?, \"name\" = ?, \"schema_fields\" = ?, \"opt_auth_column_id\" = ?, \"opt_ttl\" = ?, \"opt_mqtt_publish\" = ? WHERE \"id\" = ?";
+const DELETE: &str = "DELETE FROM \"collections\" WHERE \"id\" = ?";
+
+const SCHEMA_FIELD_PROPS_ADDED_FIELDS: [(&str, &str); 8] = [
+    ("default", "text"),
+    ("min", "double"),
+    ("max", "double"),
+    ("max_length", "bigint"),
+    ("regex", "text"),
+    ("enum_values", "text"),
+    ("reference_collection_id", "uuid"),
+    ("on_delete", "text"),
+];
 
 fn update_default_time_to_live(id: &Uuid, ttl: &i64) -> String {
     format!(
-        "ALTER TABLE \"hyperbase\".\"records_{}\" WITH default_time_to_live = {}",
+        "ALTER TABLE \"records_{}\" WITH default_time_to_live = {}",
         id.to_string().replace("-", ""),
         ttl
     )
 }
 
-pub async fn init(cached_session: &CachingSession) {
+pub async fn init(cached_session: &CachingSession, keyspace: &str) {
     hb_log::info(Some("🔧"), "[ScyllaDB] Setting up collections table");
 
     cached_session
         .get_session()
         .query(
-            "CREATE TYPE IF NOT EXISTS \"hyperbase\".\"schema_field_props\" (\"kind\" text, \"internal_kind\" text, \"required\" boolean, \"unique\" boolean, \"indexed\" boolean, \"auth_column\" boolean, \"hashed\" boolean, \"hidden\" boolean)",
+            "CREATE TYPE IF NOT EXISTS \"schema_field_props\" (\"kind\" text, \"internal_kind\" text, \"required\" boolean, \"unique\" boolean, \"indexed\" boolean, \"auth_column\" boolean, \"hashed\" boolean, \"hidden\" boolean, \"default\" text, \"min\" double, \"max\" double, \"max_length\" bigint, \"regex\" text, \"enum_values\" text, \"reference_collection_id\" uuid, \"on_delete\" text)",
             &[],
         )
         .await
         .unwrap();
-    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"hyperbase\".\"collections\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"project_id\" uuid, \"name\" text, \"schema_fields\" map<text, frozen<schema_field_props>>, \"opt_auth_column_id\" boolean, \"opt_ttl\" bigint, PRIMARY KEY (\"id\"))", &[]).await.unwrap();
+    // Types created by older versions lack the fields that were added to it since then
+    let (field_names,) = cached_session
+        .get_session()
+        .query(
+            "SELECT \"field_names\" FROM \"system_schema\".\"types\" WHERE \"keyspace_name\" = ? AND \"type_name\" = 'schema_field_props'",
+            (keyspace,),
+        )
+        .await
+        .unwrap()
+        .first_row_typed::<(Vec<String>,)>()
+        .unwrap();
+    for (field_name, field_kind) in SCHEMA_FIELD_PROPS_ADDED_FIELDS {
+        if !field_names.iter().any(|name| name == field_name) {
+            cached_session
+                .get_session()
+                .query(
+                    format!("ALTER TYPE \"schema_field_props\" ADD \"{field_name}\" {field_kind}"),
+                    &[],
+                )
+                .await
+                .unwrap();
+        }
+    }
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"collections\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"project_id\" uuid, \"name\" text, \"schema_fields\" map<text, frozen<schema_field_props>>, \"opt_auth_column_id\" boolean, \"opt_ttl\" bigint, \"opt_mqtt_publish\" boolean, PRIMARY KEY (\"id\"))", &[]).await.unwrap();
     cached_session
         .get_session()
         .query(
-            "CREATE INDEX IF NOT EXISTS ON \"hyperbase\".\"collections\" (\"project_id\")",
+            "CREATE INDEX IF NOT EXISTS ON \"collections\" (\"project_id\")",
             &[],
         )
         .await
@@ -51,6 +89,10 @@ pub async fn init(cached_session: &CachingSession) {
         .add_prepared_statement(&SELECT_MANY_BY_PROJECT_ID.into())
         .await
         .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT_MANY.into())
+        .await
+        .unwrap();
     cached_session
         .add_prepared_statement(&UPDATE.into())
         .await
@@ -84,6 +126,10 @@ impl ScyllaDb {
             .rows_typed()?)
     }
 
+    pub async fn select_many_collections(&self) -> Result<TypedRowIterator<CollectionModel>> {
+        Ok(self.execute_iter(SELECT_MANY, &[]).await?.into_typed())
+    }
+
     pub async fn update_collection(&self, value: &CollectionModel) -> Result<()> {
         self.execute(
             UPDATE,
@@ -93,6 +139,7 @@ impl ScyllaDb {
                 value.schema_fields(),
                 value.opt_auth_column_id(),
                 value.opt_ttl(),
+                value.opt_mqtt_publish(),
                 value.id(),
             ),
         )
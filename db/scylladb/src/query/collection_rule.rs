@@ -4,22 +4,22 @@ use uuid::Uuid;
 
 use crate::{db::ScyllaDb, model::collection_rule::CollectionRuleModel};
 
-const INSERT: &str = "INSERT INTO \"hyperbase\".\"collection_rules\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\") VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\" FROM \"hyperbase\".\"collection_rules\" WHERE \"id\" = ?";
-const SELECT_BY_TOKEN_ID_AND_COLLECTION_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\" FROM \"hyperbase\".\"collection_rules\" WHERE \"token_id\" = ? AND \"collection_id\" = ? ALLOW FILTERING";
-const SELECT_MANY_BY_TOKEN_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\" FROM \"hyperbase\".\"collection_rules\" WHERE \"token_id\" = ?";
-const SELECT_MANY_BY_COLLECTION_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\" FROM \"hyperbase\".\"collection_rules\" WHERE \"collection_id\" = ?";
-const UPDATE: &str = "UPDATE \"hyperbase\".\"collection_rules\" SET \"updated_at\" = ?, \"find_one\" = ?, \"find_many\" = ?, \"insert_one\" = ?, \"update_one\" = ?, \"delete_one\" = ? WHERE \"id\" = ?";
-const DELETE: &str = "DELETE FROM \"hyperbase\".\"collection_rules\" WHERE \"id\" = ?";
+const INSERT: &str = "INSERT INTO \"collection_rules\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\", \"readable_fields\", \"writable_fields\") VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\", \"readable_fields\", \"writable_fields\" FROM \"collection_rules\" WHERE \"id\" = ?";
+const SELECT_BY_TOKEN_ID_AND_COLLECTION_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\", \"readable_fields\", \"writable_fields\" FROM \"collection_rules\" WHERE \"token_id\" = ? AND \"collection_id\" = ? ALLOW FILTERING";
+const SELECT_MANY_BY_TOKEN_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\", \"readable_fields\", \"writable_fields\" FROM \"collection_rules\" WHERE \"token_id\" = ?";
+const SELECT_MANY_BY_COLLECTION_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"token_id\", \"collection_id\", \"find_one\", \"find_many\", \"insert_one\", \"update_one\", \"delete_one\", \"readable_fields\", \"writable_fields\" FROM \"collection_rules\" WHERE \"collection_id\" = ?";
+const UPDATE: &str = "UPDATE \"collection_rules\" SET \"updated_at\" = ?, \"find_one\" = ?, \"find_many\" = ?, \"insert_one\" = ?, \"update_one\" = ?, \"delete_one\" = ?, \"readable_fields\" = ?, \"writable_fields\" = ? WHERE \"id\" = ?";
+const DELETE: &str = "DELETE FROM \"collection_rules\" WHERE \"id\" = ?";
 
 pub async fn init(cached_session: &CachingSession) {
     hb_log::info(Some("🔧"), "[ScyllaDB] Setting up collection_rules table");
 
-    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"hyperbase\".\"collection_rules\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"project_id\" uuid, \"token_id\" uuid, \"collection_id\" uuid, \"find_one\" text, \"find_many\" text, \"insert_one\" boolean, \"update_one\" text, \"delete_one\" text, PRIMARY KEY (\"id\"))", &[]).await.unwrap();
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"collection_rules\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"project_id\" uuid, \"token_id\" uuid, \"collection_id\" uuid, \"find_one\" text, \"find_many\" text, \"insert_one\" boolean, \"update_one\" text, \"delete_one\" text, \"readable_fields\" list<text>, \"writable_fields\" list<text>, PRIMARY KEY (\"id\"))", &[]).await.unwrap();
     cached_session
         .get_session()
         .query(
-            "CREATE INDEX IF NOT EXISTS ON \"hyperbase\".\"collection_rules\" (\"token_id\")",
+            "CREATE INDEX IF NOT EXISTS ON \"collection_rules\" (\"token_id\")",
             &[],
         )
         .await
@@ -27,7 +27,7 @@ pub async fn init(cached_session: &CachingSession) {
     cached_session
         .get_session()
         .query(
-            "CREATE INDEX IF NOT EXISTS ON \"hyperbase\".\"collection_rules\" (\"collection_id\")",
+            "CREATE INDEX IF NOT EXISTS ON \"collection_rules\" (\"collection_id\")",
             &[],
         )
         .await
@@ -120,6 +120,8 @@ impl ScyllaDb {
                 value.insert_one(),
                 value.update_one(),
                 value.delete_one(),
+                value.readable_fields(),
+                value.writable_fields(),
                 value.id(),
             ),
         )
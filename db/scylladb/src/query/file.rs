@@ -8,28 +8,33 @@ use uuid::Uuid;
 
 use crate::{db::ScyllaDb, model::file::FileModel};
 
-const INSERT: &str = "INSERT INTO \"hyperbase\".\"files\" (\"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\") VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\" FROM \"hyperbase\".\"files\" WHERE \"id\" = ?";
-const SELECT_MANY_BY_BUCKET_ID: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\" FROM \"hyperbase\".\"files\" WHERE \"bucket_id\" = ?";
-const COUNT_MANY_BY_BUCKET_ID: &str = "SELECT COUNT(1) FROM \"hyperbase\".\"files\" WHERE \"bucket_id\" = ?";
-const SELECT_MANY_BY_CREATED_BY_AND_BUCKET_ID: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\" FROM \"hyperbase\".\"files\" WHERE \"bucket_id\" = ? AND \"created_by\" = ?";
-const COUNT_MANY_BY_CREATED_BY_AND_BUCKET_ID: &str = "SELECT COUNT(1) FROM \"hyperbase\".\"files\" WHERE \"bucket_id\" = ? AND \"created_by\" = ? ALLOW FILTERING";
-const SELECT_MANY_EXPIRE: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\" FROM \"hyperbase\".\"files\" WHERE \"bucket_id\" = ? AND \"updated_at\" < ? ALLOW FILTERING";
-const UPDATE: &str = "UPDATE \"hyperbase\".\"files\" SET \"created_by\" = ?, \"updated_at\" = ?, \"file_name\" = ?, \"public\" = ? WHERE \"bucket_id\" = ? AND \"id\" = ?";
-const DELETE: &str = "DELETE FROM \"hyperbase\".\"files\" WHERE \"bucket_id\" = ? AND \"id\" = ?";
+const INSERT: &str = "INSERT INTO \"files\" (\"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\") VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\" FROM \"files\" WHERE \"id\" = ?";
+const SELECT_MANY_BY_BUCKET_ID: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\" FROM \"files\" WHERE \"bucket_id\" = ?";
+const SELECT_MANY_BY_BUCKET_ID_AND_IDS: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\" FROM \"files\" WHERE \"bucket_id\" = ? AND \"id\" IN ?";
+const COUNT_MANY_BY_BUCKET_ID: &str = "SELECT COUNT(1) FROM \"files\" WHERE \"bucket_id\" = ?";
+const SELECT_MANY_BY_CREATED_BY_AND_BUCKET_ID: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\" FROM \"files\" WHERE \"bucket_id\" = ? AND \"created_by\" = ?";
+const COUNT_MANY_BY_CREATED_BY_AND_BUCKET_ID: &str =
+    "SELECT COUNT(1) FROM \"files\" WHERE \"bucket_id\" = ? AND \"created_by\" = ? ALLOW FILTERING";
+const SELECT_MANY_EXPIRE: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\" FROM \"files\" WHERE \"bucket_id\" = ? AND \"updated_at\" < ? ALLOW FILTERING";
+const SELECT_MANY_STALE_UPLOAD: &str = "SELECT \"id\", \"created_by\", \"created_at\", \"updated_at\", \"bucket_id\", \"file_name\", \"content_type\", \"size\", \"public\", \"pending\", \"checksum\" FROM \"files\" WHERE \"pending\" = true AND \"updated_at\" < ? ALLOW FILTERING";
+const UPDATE: &str = "UPDATE \"files\" SET \"created_by\" = ?, \"updated_at\" = ?, \"file_name\" = ?, \"public\" = ?, \"pending\" = ?, \"checksum\" = ? WHERE \"bucket_id\" = ? AND \"id\" = ?";
+const UPDATE_CONTENT: &str = "UPDATE \"files\" SET \"updated_at\" = ?, \"content_type\" = ?, \"size\" = ?, \"checksum\" = ? WHERE \"bucket_id\" = ? AND \"id\" = ?";
+const DELETE: &str = "DELETE FROM \"files\" WHERE \"bucket_id\" = ? AND \"id\" = ?";
+const SUM_SIZE_BY_BUCKET_ID: &str = "SELECT SUM(\"size\") FROM \"files\" WHERE \"bucket_id\" = ?";
 
 pub async fn init(cached_session: &CachingSession) {
     hb_log::info(Some("🔧"), "[ScyllaDB] Setting up files table");
 
-    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"hyperbase\".\"files\" (\"id\" uuid, \"created_by\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"bucket_id\" uuid, \"file_name\" text, \"content_type\" text, \"size\" bigint, \"public\" boolean, PRIMARY KEY (\"bucket_id\", \"id\")) WITH CLUSTERING ORDER BY (\"id\" DESC)", &[]).await.unwrap();
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"files\" (\"id\" uuid, \"created_by\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"bucket_id\" uuid, \"file_name\" text, \"content_type\" text, \"size\" bigint, \"public\" boolean, \"pending\" boolean, \"checksum\" text, PRIMARY KEY (\"bucket_id\", \"id\")) WITH CLUSTERING ORDER BY (\"id\" DESC)", &[]).await.unwrap();
     cached_session
-    .get_session()
-    .query(
-        "CREATE INDEX IF NOT EXISTS ON \"hyperbase\".\"files\" ((\"bucket_id\"), \"created_by\")",
-        &[],
-    )
-    .await
-    .unwrap();
+        .get_session()
+        .query(
+            "CREATE INDEX IF NOT EXISTS ON \"files\" ((\"bucket_id\"), \"created_by\")",
+            &[],
+        )
+        .await
+        .unwrap();
 
     cached_session
         .add_prepared_statement(&INSERT.into())
@@ -43,6 +48,10 @@ pub async fn init(cached_session: &CachingSession) {
         .add_prepared_statement(&SELECT_MANY_BY_BUCKET_ID.into())
         .await
         .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT_MANY_BY_BUCKET_ID_AND_IDS.into())
+        .await
+        .unwrap();
     cached_session
         .add_prepared_statement(&COUNT_MANY_BY_BUCKET_ID.into())
         .await
@@ -59,20 +68,45 @@ pub async fn init(cached_session: &CachingSession) {
         .add_prepared_statement(&SELECT_MANY_EXPIRE.into())
         .await
         .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT_MANY_STALE_UPLOAD.into())
+        .await
+        .unwrap();
     cached_session
         .add_prepared_statement(&UPDATE.into())
         .await
         .unwrap();
+    cached_session
+        .add_prepared_statement(&UPDATE_CONTENT.into())
+        .await
+        .unwrap();
     cached_session
         .add_prepared_statement(&DELETE.into())
         .await
         .unwrap();
+    cached_session
+        .add_prepared_statement(&SUM_SIZE_BY_BUCKET_ID.into())
+        .await
+        .unwrap();
 }
 
 impl ScyllaDb {
-    pub async fn insert_file(&self, value: &FileModel) -> Result<()> {
-        self.execute(INSERT, value).await?;
-        Ok(())
+    // Scylla can't write both tables in a transaction, so the usage of the bucket may drift when
+    // one of the writes fails, which is fixed by recalculating it. Returns false, writing nothing,
+    // when the file doesn't fit in the quota of its bucket.
+    pub async fn insert_file(&self, value: &FileModel) -> Result<bool> {
+        if !self
+            .reserve_bucket_used_bytes(value.bucket_id(), value.size())
+            .await?
+        {
+            return Ok(false);
+        }
+        if let Err(err) = self.execute(INSERT, value).await {
+            self.increment_bucket_used_bytes(value.bucket_id(), &-value.size())
+                .await?;
+            return Err(err.into());
+        }
+        Ok(true)
     }
 
     pub async fn select_file(&self, id: &Uuid) -> Result<FileModel> {
@@ -103,6 +137,17 @@ impl ScyllaDb {
         Ok(self.execute(&query, &values).await?.rows_typed()?)
     }
 
+    pub async fn select_many_files_by_bucket_id_and_ids(
+        &self,
+        bucket_id: &Uuid,
+        ids: &[Uuid],
+    ) -> Result<TypedRowIter<FileModel>> {
+        Ok(self
+            .execute(SELECT_MANY_BY_BUCKET_ID_AND_IDS, &(bucket_id, ids.to_vec()))
+            .await?
+            .rows_typed()?)
+    }
+
     pub async fn count_many_files_by_bucket_id(&self, bucket_id: &Uuid) -> Result<i64> {
         Ok(self
             .execute(COUNT_MANY_BY_BUCKET_ID, [bucket_id].as_ref())
@@ -120,8 +165,8 @@ impl ScyllaDb {
     ) -> Result<TypedRowIter<FileModel>> {
         let mut query = SELECT_MANY_BY_CREATED_BY_AND_BUCKET_ID.to_owned();
         let mut values: Vec<Box<dyn SerializeCql + Send + Sync>> = Vec::new();
-        values.push(Box::new(*created_by));
         values.push(Box::new(*bucket_id));
+        values.push(Box::new(*created_by));
         if let Some(before_id) = before_id {
             query += " AND \"id\" < ?";
             values.push(Box::new(*before_id));
@@ -142,7 +187,7 @@ impl ScyllaDb {
         Ok(self
             .execute(
                 COUNT_MANY_BY_CREATED_BY_AND_BUCKET_ID,
-                [created_by, bucket_id].as_ref(),
+                [bucket_id, created_by].as_ref(),
             )
             .await?
             .first_row_typed::<(i64,)>()?
@@ -174,6 +219,27 @@ impl ScyllaDb {
             .rows_typed()?)
     }
 
+    pub async fn select_many_stale_upload_files(
+        &self,
+        ttl_seconds: &i64,
+    ) -> Result<TypedRowIter<FileModel>> {
+        Ok(self
+            .execute(
+                SELECT_MANY_STALE_UPLOAD,
+                &(CqlTimestamp(
+                    Utc::now()
+                        .checked_sub_signed(
+                            Duration::try_seconds(*ttl_seconds)
+                                .ok_or_else(|| Error::msg("upload session ttl is out of range."))?,
+                        )
+                        .ok_or_else(|| Error::msg("upload session ttl is out of range."))?
+                        .timestamp_millis(),
+                ),),
+            )
+            .await?
+            .rows_typed()?)
+    }
+
     pub async fn update_file(&self, value: &FileModel) -> Result<()> {
         self.execute(
             UPDATE,
@@ -182,6 +248,8 @@ impl ScyllaDb {
                 value.updated_at(),
                 value.file_name(),
                 value.public(),
+                value.pending(),
+                value.checksum(),
                 value.bucket_id(),
                 value.id(),
             ),
@@ -190,8 +258,51 @@ impl ScyllaDb {
         Ok(())
     }
 
-    pub async fn delete_file(&self, bucket_id: &Uuid, id: &Uuid) -> Result<()> {
+    // The same as insert_file, the usage of the bucket may drift when one of the writes fails
+    pub async fn update_file_content(
+        &self,
+        value: &FileModel,
+        previous_size: &i64,
+    ) -> Result<bool> {
+        let delta = value.size() - previous_size;
+        if !self
+            .reserve_bucket_used_bytes(value.bucket_id(), &delta)
+            .await?
+        {
+            return Ok(false);
+        }
+        if let Err(err) = self
+            .execute(
+                UPDATE_CONTENT,
+                &(
+                    value.updated_at(),
+                    value.content_type(),
+                    value.size(),
+                    value.checksum(),
+                    value.bucket_id(),
+                    value.id(),
+                ),
+            )
+            .await
+        {
+            self.increment_bucket_used_bytes(value.bucket_id(), &-delta)
+                .await?;
+            return Err(err.into());
+        }
+        Ok(true)
+    }
+
+    pub async fn sum_files_size_by_bucket_id(&self, bucket_id: &Uuid) -> Result<i64> {
+        Ok(self
+            .execute(SUM_SIZE_BY_BUCKET_ID, [bucket_id].as_ref())
+            .await?
+            .first_row_typed::<(Option<i64>,)>()?
+            .0
+            .unwrap_or(0))
+    }
+
+    pub async fn delete_file(&self, bucket_id: &Uuid, id: &Uuid, size: &i64) -> Result<()> {
         self.execute(DELETE, [bucket_id, id].as_ref()).await?;
-        Ok(())
+        self.increment_bucket_used_bytes(bucket_id, &-size).await
     }
 }
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use scylla::CachingSession;
+use uuid::Uuid;
+
+use crate::{db::ScyllaDb, model::idempotency::IdempotencyModel};
+
+const INSERT: &str = "INSERT INTO \"idempotency_keys\" (\"collection_id\", \"owner_id\", \"key\", \"created_at\", \"record_id\") VALUES (?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"collection_id\", \"owner_id\", \"key\", \"created_at\", \"record_id\" FROM \"idempotency_keys\" WHERE \"collection_id\" = ? AND \"owner_id\" = ? AND \"key\" = ?";
+const DELETE: &str = "DELETE FROM \"idempotency_keys\" WHERE \"collection_id\" = ? AND \"owner_id\" = ? AND \"key\" = ?";
+
+pub async fn init(cached_session: &CachingSession, ttl: &u32) {
+    hb_log::info(Some("🔧"), "[ScyllaDB] Setting up idempotency_keys table");
+
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"idempotency_keys\" (\"collection_id\" uuid, \"owner_id\" uuid, \"key\" text, \"created_at\" timestamp, \"record_id\" uuid, PRIMARY KEY ((\"collection_id\", \"owner_id\", \"key\"))) WITH default_time_to_live = ".to_owned() + &ttl.to_string(), &[]).await.unwrap();
+
+    cached_session
+        .add_prepared_statement(&INSERT.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&DELETE.into())
+        .await
+        .unwrap();
+}
+
+impl ScyllaDb {
+    pub async fn insert_idempotency(&self, value: &IdempotencyModel) -> Result<()> {
+        self.execute(INSERT, value).await?;
+        Ok(())
+    }
+
+    pub async fn select_idempotency(
+        &self,
+        collection_id: &Uuid,
+        owner_id: &Uuid,
+        key: &str,
+    ) -> Result<IdempotencyModel> {
+        Ok(self
+            .execute(SELECT, &(collection_id, owner_id, key))
+            .await?
+            .first_row_typed()?)
+    }
+
+    pub async fn delete_idempotency(
+        &self,
+        collection_id: &Uuid,
+        owner_id: &Uuid,
+        key: &str,
+    ) -> Result<()> {
+        self.execute(DELETE, &(collection_id, owner_id, key))
+            .await?;
+        Ok(())
+    }
+}
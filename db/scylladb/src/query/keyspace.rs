@@ -1,7 +1,57 @@
+use std::collections::BTreeMap;
+
 use scylla::CachingSession;
 
-pub async fn init(cached_session: &CachingSession, replication_factor: &i64) {
-    hb_log::info(Some("🔧"), "[ScyllaDB] Setting up hyperbase keyspace");
+// Queries leave out the keyspace of their tables, so the session is switched to it once it exists
+pub async fn init(
+    cached_session: &CachingSession,
+    keyspace: &str,
+    replication_factor: &i64,
+    datacenter_replication_factors: &Option<BTreeMap<String, i64>>,
+) {
+    hb_log::info(
+        Some("🔧"),
+        &format!("[ScyllaDB] Setting up {keyspace} keyspace"),
+    );
+
+    if keyspace.is_empty()
+        || keyspace.len() > 48
+        || !keyspace
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        hb_log::panic(
+            None,
+            &format!(
+                "[ScyllaDB] Keyspace '{keyspace}' must be 1 to 48 letters, digits or underscores"
+            ),
+        );
+    }
+
+    let mut replication =
+        format!("'class' : 'NetworkTopologyStrategy', 'replication_factor' : {replication_factor}");
+    if let Some(datacenter_replication_factors) = datacenter_replication_factors {
+        for (datacenter, replication_factor) in datacenter_replication_factors {
+            replication += &format!(
+                ", '{}' : {replication_factor}",
+                datacenter.replace('\'', "''")
+            );
+        }
+    }
 
-    cached_session.get_session().query("CREATE KEYSPACE IF NOT EXISTS \"hyperbase\" WITH REPLICATION = {'class' : 'NetworkTopologyStrategy', 'replication_factor' : ".to_owned() + &replication_factor.to_string() + "}", &[]).await.unwrap();
+    cached_session
+        .get_session()
+        .query(
+            format!(
+                "CREATE KEYSPACE IF NOT EXISTS \"{keyspace}\" WITH REPLICATION = {{{replication}}}"
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    cached_session
+        .get_session()
+        .use_keyspace(keyspace, true)
+        .await
+        .unwrap();
 }
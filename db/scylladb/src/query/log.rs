@@ -1,17 +1,21 @@
 use anyhow::Result;
-use scylla::{serialize::value::SerializeCql, transport::session::TypedRowIter, CachingSession};
+use scylla::{
+    frame::value::CqlTimestamp, serialize::value::SerializeCql, transport::session::TypedRowIter,
+    CachingSession,
+};
 use uuid::Uuid;
 
 use crate::{db::ScyllaDb, model::log::LogModel};
 
-const INSERT: &str = "INSERT INTO \"hyperbase\".\"logs\" (\"id\", \"created_at\", \"admin_id\", \"project_id\", \"kind\", \"message\") VALUES (?, ?, ?, ?, ?, ?)";
-const SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"admin_id\", \"project_id\", \"kind\", \"message\" FROM \"hyperbase\".\"logs\" WHERE \"admin_id\" = ? AND \"project_id\" = ?";
-const COUNT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str = "SELECT COUNT(1) FROM \"hyperbase\".\"logs\" WHERE \"admin_id\" = ? AND \"project_id\" = ?";
+const INSERT: &str = "INSERT INTO \"logs\" (\"id\", \"created_at\", \"admin_id\", \"project_id\", \"kind\", \"message\") VALUES (?, ?, ?, ?, ?, ?)";
+const SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"admin_id\", \"project_id\", \"kind\", \"message\" FROM \"logs\" WHERE \"admin_id\" = ? AND \"project_id\" = ?";
+const COUNT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str =
+    "SELECT COUNT(1) FROM \"logs\" WHERE \"admin_id\" = ? AND \"project_id\" = ?";
 
 pub async fn init(cached_session: &CachingSession, ttl: &u32) {
     hb_log::info(Some("🔧"), "[ScyllaDB] Setting up logs table");
 
-    cached_session.get_session().query(format!("CREATE TABLE IF NOT EXISTS \"hyperbase\".\"logs\" (\"id\" uuid, \"created_at\" timestamp, \"admin_id\" uuid, \"project_id\" uuid, \"kind\" text, \"message\" text, PRIMARY KEY ((\"admin_id\", \"project_id\"), \"id\")) WITH default_time_to_live = {} AND CLUSTERING ORDER BY (\"id\" DESC)", ttl), &[]).await.unwrap();
+    cached_session.get_session().query(format!("CREATE TABLE IF NOT EXISTS \"logs\" (\"id\" uuid, \"created_at\" timestamp, \"admin_id\" uuid, \"project_id\" uuid, \"kind\" text, \"message\" text, PRIMARY KEY ((\"admin_id\", \"project_id\"), \"id\")) WITH default_time_to_live = {} AND CLUSTERING ORDER BY (\"id\" DESC)", ttl), &[]).await.unwrap();
 
     cached_session
         .add_prepared_statement(&INSERT.into())
@@ -37,13 +41,18 @@ impl ScyllaDb {
         &self,
         admin_id: &Uuid,
         project_id: &Uuid,
+        kind: &Option<&str>,
+        from: &Option<CqlTimestamp>,
+        to: &Option<CqlTimestamp>,
+        message: &Option<String>,
         before_id: &Option<Uuid>,
         limit: &Option<i32>,
     ) -> Result<TypedRowIter<LogModel>> {
         let mut query = SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID.to_owned();
-        let mut values: Vec<Box<dyn SerializeCql + Send + Sync>> = Vec::with_capacity(4);
+        let mut values: Vec<Box<dyn SerializeCql + Send + Sync>> = Vec::with_capacity(8);
         values.push(Box::new(*admin_id));
         values.push(Box::new(*project_id));
+        let filtered = Self::logs_filter(kind, from, to, message, &mut query, &mut values);
         if let Some(before_id) = before_id {
             query += " AND \"id\" < ?";
             values.push(Box::new(*before_id));
@@ -52,6 +61,9 @@ impl ScyllaDb {
             query += " LIMIT ?";
             values.push(Box::new(*limit));
         }
+        if filtered {
+            query += " ALLOW FILTERING";
+        }
         Ok(self.execute(&query, &values).await?.rows_typed()?)
     }
 
@@ -59,14 +71,53 @@ impl ScyllaDb {
         &self,
         admin_id: &Uuid,
         project_id: &Uuid,
+        kind: &Option<&str>,
+        from: &Option<CqlTimestamp>,
+        to: &Option<CqlTimestamp>,
+        message: &Option<String>,
     ) -> Result<i64> {
+        let mut query = COUNT_MANY_BY_ADMIN_ID_AND_PROJECT_ID.to_owned();
+        let mut values: Vec<Box<dyn SerializeCql + Send + Sync>> = Vec::with_capacity(6);
+        values.push(Box::new(*admin_id));
+        values.push(Box::new(*project_id));
+        if Self::logs_filter(kind, from, to, message, &mut query, &mut values) {
+            query += " ALLOW FILTERING";
+        }
         Ok(self
-            .execute(
-                COUNT_MANY_BY_ADMIN_ID_AND_PROJECT_ID,
-                [admin_id, project_id].as_ref(),
-            )
+            .execute(&query, &values)
             .await?
             .first_row_typed::<(i64,)>()?
             .0)
     }
+
+    // These columns aren't part of the key, so filtering by them is done within the partition of
+    // the project. Returns whether any of them is filtered by.
+    fn logs_filter(
+        kind: &Option<&str>,
+        from: &Option<CqlTimestamp>,
+        to: &Option<CqlTimestamp>,
+        message: &Option<String>,
+        query: &mut String,
+        values: &mut Vec<Box<dyn SerializeCql + Send + Sync>>,
+    ) -> bool {
+        let len = values.len();
+        if let Some(kind) = kind {
+            *query += " AND \"kind\" = ?";
+            values.push(Box::new((*kind).to_owned()));
+        }
+        if let Some(from) = from {
+            *query += " AND \"created_at\" >= ?";
+            values.push(Box::new(*from));
+        }
+        if let Some(to) = to {
+            *query += " AND \"created_at\" <= ?";
+            values.push(Box::new(*to));
+        }
+        // LIKE is case-sensitive here and has no escape character
+        if let Some(message) = message {
+            *query += " AND \"message\" LIKE ?";
+            values.push(Box::new(format!("%{message}%")));
+        }
+        values.len() > len
+    }
 }
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use scylla::{
+    frame::value::{Counter, CqlTimestamp},
+    CachingSession,
+};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::query::{record, system::COUNT_TABLE};
+
+// Every entry moves the schema one version ahead, the version being its position counting from 1.
+// Version 1 is the schema the tables had when versions started to be tracked, so it has nothing
+// to run. Entries are only ever appended, a released one is never changed.
+const MIGRATIONS: &[Migration] = &[
+    Migration::Statements(&[]),
+    // Permission checks filter records on _created_by. Expired records are removed by the
+    // table's own TTL here, so unlike the SQL backends _updated_at needs no index.
+    Migration::RecordIndexes(&["_created_by"]),
+    // Chunked uploads, the files already there are complete. Columns are added in an entry of
+    // their own because adding one twice fails, while filling it again when rerun does not.
+    Migration::Statements(&["ALTER TABLE \"files\" ADD \"pending\" boolean"]),
+    Migration::FillColumn {
+        table: "files",
+        keys: &["bucket_id", "id"],
+        column: "pending",
+        value: "false",
+    },
+    // Upload limits of buckets
+    Migration::Statements(&[
+        "ALTER TABLE \"buckets\" ADD (\"max_file_size\" bigint, \"allowed_content_types\" list<text>)",
+    ]),
+    // Storage quota of buckets, starting from the size of the files they already hold
+    Migration::Statements(&["ALTER TABLE \"buckets\" ADD \"quota_bytes\" bigint"]),
+    Migration::BucketUsages,
+    // Checksums of files, left empty for the ones uploaded before
+    Migration::Statements(&["ALTER TABLE \"files\" ADD \"checksum\" text"]),
+    // Disabling expired tokens
+    Migration::Statements(&["ALTER TABLE \"tokens\" ADD \"disabled_at\" timestamp"]),
+    // Two-factor authentication of admins
+    Migration::Statements(&[
+        "ALTER TABLE \"admins\" ADD (\"totp_secret\" text, \"totp_enabled_at\" timestamp, \"totp_backup_codes\" list<text>)",
+    ]),
+    // Field lists of collection rules, every field stays readable and writable when left empty
+    Migration::Statements(&[
+        "ALTER TABLE \"collection_rules\" ADD (\"readable_fields\" list<text>, \"writable_fields\" list<text>)",
+    ]),
+    // Usage of tokens, the request counts live in the token_usages table and start at 0 there
+    Migration::Statements(&["ALTER TABLE \"tokens\" ADD \"last_used_at\" timestamp"]),
+    // Publishing record changes over MQTT
+    Migration::Statements(&["ALTER TABLE \"collections\" ADD \"opt_mqtt_publish\" boolean"]),
+    Migration::FillColumn {
+        table: "collections",
+        keys: &["id"],
+        column: "opt_mqtt_publish",
+        value: "false",
+    },
+    // Tokens are stored as their SHA-256 hash. Databases set up after that change but before
+    // versions were tracked already hold hashes, which are told apart from the alphanumeric
+    // plaintext ones by being 64 lowercase hex digits.
+    Migration::HashTokens,
+];
+
+enum Migration {
+    Statements(&'static [&'static str]),
+    // Indexes every existing record table on the given columns
+    RecordIndexes(&'static [&'static str]),
+    // Sets the column of every existing row, ScyllaDB has no UPDATE without the whole key
+    FillColumn {
+        table: &'static str,
+        keys: &'static [&'static str],
+        column: &'static str,
+        value: &'static str,
+    },
+    // Brings the used_bytes counter of every bucket to the size of its files
+    BucketUsages,
+    // Replaces every plaintext token with its hash, CQL has no SHA-256 function
+    HashTokens,
+}
+
+const SELECT_COLLECTION_IDS: &str = "SELECT \"id\" FROM \"collections\"";
+const SELECT_FILES_SIZE: &str = "SELECT \"bucket_id\", \"size\" FROM \"files\"";
+const SELECT_TOKENS: &str = "SELECT \"id\", \"token\" FROM \"tokens\"";
+const SELECT_BUCKET_USAGES: &str = "SELECT \"bucket_id\", \"used_bytes\" FROM \"bucket_usages\"";
+
+const INSERT: &str = "INSERT INTO \"schema_versions\" (\"version\", \"applied_at\") VALUES (?, ?)";
+const SELECT_LATEST: &str = "SELECT MAX(\"version\") FROM \"schema_versions\"";
+
+pub const LATEST_VERSION: i32 = MIGRATIONS.len() as i32;
+
+// Runs before the other tables are set up, so the statements they prepare see the latest schema.
+// ScyllaDB has no transactions, a migration that fails halfway is run again from its start.
+pub async fn init(cached_session: &CachingSession, keyspace: &str) {
+    hb_log::info(Some("🔧"), "[ScyllaDB] Setting up schema_versions table");
+
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"schema_versions\" (\"version\" int, \"applied_at\" timestamp, PRIMARY KEY (\"version\"))", &[]).await.unwrap();
+
+    let (version,) = cached_session
+        .get_session()
+        .query(SELECT_LATEST, &[])
+        .await
+        .unwrap()
+        .first_row_typed::<(Option<i32>,)>()
+        .unwrap();
+    let version = match version {
+        Some(version) => version,
+        None => {
+            // A keyspace without the collections table is fresh and gets the latest schema right
+            // away, one that has it was set up before versions were tracked
+            let (count,) = cached_session
+                .get_session()
+                .query(COUNT_TABLE, (keyspace, "collections"))
+                .await
+                .unwrap()
+                .first_row_typed::<(i64,)>()
+                .unwrap();
+            let version = if count > 0 { 1 } else { LATEST_VERSION };
+            cached_session
+                .get_session()
+                .query(
+                    INSERT,
+                    (version, CqlTimestamp(Utc::now().timestamp_millis())),
+                )
+                .await
+                .unwrap();
+            version
+        }
+    };
+
+    if version > LATEST_VERSION {
+        hb_log::panic(
+            None,
+            &format!(
+                "[ScyllaDB] Database schema is at version {version}, newer than the latest version {LATEST_VERSION} this build knows about. Refusing to start to not break it."
+            ),
+        );
+    }
+
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let version = idx as i32 + 1;
+        hb_log::info(
+            Some("🔧"),
+            &format!("[ScyllaDB] Migrating schema to version {version}"),
+        );
+        let statements = match migration {
+            Migration::Statements(statements) => statements
+                .iter()
+                .map(|statement| statement.to_string())
+                .collect(),
+            Migration::RecordIndexes(columns) => {
+                record_indexes(cached_session, keyspace, columns).await
+            }
+            Migration::FillColumn {
+                table,
+                keys,
+                column,
+                value,
+            } => fill_column(cached_session, table, keys, column, value).await,
+            Migration::BucketUsages => bucket_usages(cached_session).await,
+            Migration::HashTokens => hash_tokens(cached_session).await,
+        };
+        for statement in &statements {
+            cached_session
+                .get_session()
+                .query(statement.as_str(), &[])
+                .await
+                .unwrap();
+        }
+        cached_session
+            .get_session()
+            .query(
+                INSERT,
+                (version, CqlTimestamp(Utc::now().timestamp_millis())),
+            )
+            .await
+            .unwrap();
+    }
+}
+
+async fn record_indexes(
+    cached_session: &CachingSession,
+    keyspace: &str,
+    columns: &[&str],
+) -> Vec<String> {
+    let collection_ids = cached_session
+        .get_session()
+        .query(SELECT_COLLECTION_IDS, &[])
+        .await
+        .unwrap()
+        .rows_typed::<(Uuid,)>()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    let mut statements = Vec::with_capacity(collection_ids.len() * columns.len());
+    for (collection_id,) in collection_ids {
+        // Named the way RecordDao names the table of a collection
+        let record_table = format!("records_{}", collection_id.simple());
+        let (count,) = cached_session
+            .get_session()
+            .query(COUNT_TABLE, (keyspace, &record_table))
+            .await
+            .unwrap()
+            .first_row_typed::<(i64,)>()
+            .unwrap();
+        if count == 0 {
+            continue;
+        }
+        for column in columns {
+            statements.push(record::create_index(&record_table, column));
+        }
+    }
+    statements
+}
+
+async fn fill_column(
+    cached_session: &CachingSession,
+    table: &str,
+    keys: &[&str],
+    column: &str,
+    value: &str,
+) -> Vec<String> {
+    let select = format!(
+        "SELECT {} FROM \"{table}\"",
+        keys.iter()
+            .map(|key| format!("\"{key}\""))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let rows = cached_session
+        .get_session()
+        .query(select, &[])
+        .await
+        .unwrap()
+        .rows
+        .unwrap_or_default();
+
+    let mut statements = Vec::with_capacity(rows.len());
+    for row in rows {
+        // Every key column of the tables filled here is a uuid
+        let conditions = keys
+            .iter()
+            .zip(row.columns)
+            .map(|(key, value)| {
+                let id = value.and_then(|value| value.as_uuid()).unwrap();
+                format!("\"{key}\" = {id}")
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        statements.push(format!(
+            "UPDATE \"{table}\" SET \"{column}\" = {value} WHERE {conditions}"
+        ));
+    }
+    statements
+}
+
+// The counters can only be moved by a delta, which is taken against their current value so a
+// rerun doesn't count the files twice
+async fn bucket_usages(cached_session: &CachingSession) -> Vec<String> {
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"bucket_usages\" (\"bucket_id\" uuid, \"used_bytes\" counter, PRIMARY KEY (\"bucket_id\"))", &[]).await.unwrap();
+
+    let mut sizes = HashMap::<Uuid, i64>::new();
+    for row in cached_session
+        .get_session()
+        .query(SELECT_FILES_SIZE, &[])
+        .await
+        .unwrap()
+        .rows_typed::<(Uuid, Option<i64>)>()
+        .unwrap()
+    {
+        let (bucket_id, size) = row.unwrap();
+        *sizes.entry(bucket_id).or_default() += size.unwrap_or_default();
+    }
+    for row in cached_session
+        .get_session()
+        .query(SELECT_BUCKET_USAGES, &[])
+        .await
+        .unwrap()
+        .rows_typed::<(Uuid, Counter)>()
+        .unwrap()
+    {
+        let (bucket_id, Counter(used_bytes)) = row.unwrap();
+        *sizes.entry(bucket_id).or_default() -= used_bytes;
+    }
+
+    sizes
+        .into_iter()
+        .filter(|(_, delta)| *delta != 0)
+        .map(|(bucket_id, delta)| {
+            format!("UPDATE \"bucket_usages\" SET \"used_bytes\" = \"used_bytes\" + {delta} WHERE \"bucket_id\" = {bucket_id}")
+        })
+        .collect()
+}
+
+async fn hash_tokens(cached_session: &CachingSession) -> Vec<String> {
+    let mut statements = Vec::new();
+    for row in cached_session
+        .get_session()
+        .query(SELECT_TOKENS, &[])
+        .await
+        .unwrap()
+        .rows_typed::<(Uuid, String)>()
+        .unwrap()
+    {
+        let (id, token) = row.unwrap();
+        if is_hashed_token(&token) {
+            continue;
+        }
+        let hash = hex::encode(Sha256::digest(token.as_bytes()));
+        statements.push(format!(
+            "UPDATE \"tokens\" SET \"token\" = '{hash}' WHERE \"id\" = {id}"
+        ));
+    }
+    statements
+}
+
+fn is_hashed_token(token: &str) -> bool {
+    token.len() == 64 && token.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+}
@@ -1,23 +1,29 @@
 use anyhow::Result;
-use scylla::{transport::session::TypedRowIter, CachingSession};
+use scylla::{
+    transport::{iterator::TypedRowIterator, session::TypedRowIter},
+    CachingSession,
+};
 use uuid::Uuid;
 
 use crate::{db::ScyllaDb, model::project::ProjectModel};
 
-const INSERT: &str = "INSERT INTO \"hyperbase\".\"projects\" (\"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\") VALUES (?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\" FROM \"hyperbase\".\"projects\" WHERE \"id\" = ?";
-const SELECT_MANY_BY_ADMIN_ID:  &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\" FROM \"hyperbase\".\"projects\" WHERE \"admin_id\" = ?";
-const UPDATE: &str = "UPDATE \"hyperbase\".\"projects\" SET \"updated_at\" = ?, \"admin_id\" = ?, \"name\" = ? WHERE \"id\" = ?";
-const DELETE: &str = "DELETE FROM \"hyperbase\".\"projects\" WHERE \"id\" = ?";
+const INSERT: &str = "INSERT INTO \"projects\" (\"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\") VALUES (?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\" FROM \"projects\" WHERE \"id\" = ?";
+const SELECT_MANY: &str =
+    "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\" FROM \"projects\"";
+const SELECT_MANY_BY_ADMIN_ID:  &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\" FROM \"projects\" WHERE \"admin_id\" = ?";
+const UPDATE: &str =
+    "UPDATE \"projects\" SET \"updated_at\" = ?, \"admin_id\" = ?, \"name\" = ? WHERE \"id\" = ?";
+const DELETE: &str = "DELETE FROM \"projects\" WHERE \"id\" = ?";
 
 pub async fn init(cached_session: &CachingSession) {
     hb_log::info(Some("🔧"), "[ScyllaDB] Setting up projects table");
 
-    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"hyperbase\".\"projects\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"admin_id\" uuid, \"name\" text, PRIMARY KEY (\"id\"))", &[]).await.unwrap();
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"projects\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"admin_id\" uuid, \"name\" text, PRIMARY KEY (\"id\"))", &[]).await.unwrap();
     cached_session
         .get_session()
         .query(
-            "CREATE INDEX IF NOT EXISTS ON \"hyperbase\".\"projects\" (\"admin_id\")",
+            "CREATE INDEX IF NOT EXISTS ON \"projects\" (\"admin_id\")",
             &[],
         )
         .await
@@ -31,6 +37,10 @@ pub async fn init(cached_session: &CachingSession) {
         .add_prepared_statement(&SELECT.into())
         .await
         .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT_MANY.into())
+        .await
+        .unwrap();
     cached_session
         .add_prepared_statement(&SELECT_MANY_BY_ADMIN_ID.into())
         .await
@@ -58,6 +68,10 @@ impl ScyllaDb {
             .first_row_typed()?)
     }
 
+    pub async fn select_many_projects(&self) -> Result<TypedRowIterator<ProjectModel>> {
+        Ok(self.execute_iter(SELECT_MANY, &[]).await?.into_typed())
+    }
+
     pub async fn select_many_projects_by_admin_id(
         &self,
         admin_id: &Uuid,
@@ -8,7 +8,7 @@ pub fn create_table(
     columns: &HashMap<String, SchemaFieldPropsModel>,
 ) -> String {
     format!(
-        "CREATE TABLE IF NOT EXISTS \"hyperbase\".\"{}\" (\"_collection_id\" uuid, \"_id\" uuid, \"_created_by\" uuid, \"_updated_at\" timestamp, {}PRIMARY KEY (\"_collection_id\", \"_id\")) WITH CLUSTERING ORDER BY (\"_id\" DESC)",
+        "CREATE TABLE IF NOT EXISTS \"{}\" (\"_collection_id\" uuid, \"_id\" uuid, \"_created_by\" uuid, \"_updated_at\" timestamp, {}PRIMARY KEY (\"_collection_id\", \"_id\")) WITH CLUSTERING ORDER BY (\"_id\" DESC)",
         record_table,
         columns
             .iter()
@@ -18,20 +18,44 @@ pub fn create_table(
 }
 
 pub fn drop_table(record_table: &str) -> String {
-    format!("DROP TABLE IF EXISTS \"hyperbase\".\"{record_table}\"")
+    format!("DROP TABLE IF EXISTS \"{record_table}\"")
+}
+
+// Scylla doesn't allow counter columns to live next to regular columns, so counter fields of a
+// collection are stored in a companion table keyed by record id and field name
+pub fn create_counter_table(record_table: &str) -> String {
+    format!("CREATE TABLE IF NOT EXISTS \"{record_table}_counters\" (\"_id\" uuid, \"_field\" text, \"_value\" counter, PRIMARY KEY (\"_id\", \"_field\"))")
+}
+
+pub fn drop_counter_table(record_table: &str) -> String {
+    format!("DROP TABLE IF EXISTS \"{record_table}_counters\"")
+}
+
+pub fn increment_counter(record_table: &str) -> String {
+    format!("UPDATE \"{record_table}_counters\" SET \"_value\" = \"_value\" + ? WHERE \"_id\" = ? AND \"_field\" = ?")
+}
+
+pub fn select_counters(record_table: &str) -> String {
+    format!("SELECT \"_id\", \"_field\", \"_value\" FROM \"{record_table}_counters\" WHERE \"_id\" IN ?")
+}
+
+pub fn delete_counters(record_table: &str) -> String {
+    format!("DELETE FROM \"{record_table}_counters\" WHERE \"_id\" = ?")
 }
 
 pub fn create_index(record_table: &str, index: &str) -> String {
-    format!("CREATE INDEX IF NOT EXISTS \"{record_table}_{index}\" ON \"hyperbase\".\"{record_table}\" (\"{index}\")")
+    format!(
+        "CREATE INDEX IF NOT EXISTS \"{record_table}_{index}\" ON \"{record_table}\" (\"{index}\")"
+    )
 }
 
 pub fn drop_index(record_table: &str, index: &str) -> String {
-    format!("DROP INDEX IF EXISTS \"hyperbase\".\"{record_table}_{index}\"")
+    format!("DROP INDEX IF EXISTS \"{record_table}_{index}\"")
 }
 
 pub fn add_columns(record_table: &str, columns: &HashMap<String, SchemaFieldPropsModel>) -> String {
     format!(
-        "ALTER TABLE \"hyperbase\".\"{}\" ADD ({})",
+        "ALTER TABLE \"{}\" ADD ({})",
         record_table,
         columns
             .iter()
@@ -42,7 +66,7 @@ pub fn add_columns(record_table: &str, columns: &HashMap<String, SchemaFieldProp
 
 pub fn drop_columns(record_table: &str, column_names: &HashSet<String>) -> String {
     format!(
-        "ALTER TABLE \"hyperbase\".\"{}\" DROP ({})",
+        "ALTER TABLE \"{}\" DROP ({})",
         record_table,
         &column_names
             .iter()
@@ -56,7 +80,7 @@ pub fn change_columns_type(
     columns: &HashMap<String, SchemaFieldPropsModel>,
 ) -> String {
     format!(
-        "ALTER TABLE \"hyperbase\".\"{}\" {}",
+        "ALTER TABLE \"{}\" {}",
         record_table,
         columns
             .iter()
@@ -80,12 +104,12 @@ pub fn insert(record_table: &str, columns: &Vec<&str>) -> String {
             vals += ", ";
         }
     }
-    format!("INSERT INTO \"hyperbase\".\"{record_table}\" ({cols}) VALUES ({vals})")
+    format!("INSERT INTO \"{record_table}\" ({cols}) VALUES ({vals})")
 }
 
 pub fn select(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
-        "SELECT {} FROM \"hyperbase\".\"{}\" WHERE \"_collection_id\" = ? AND \"_id\" = ?",
+        "SELECT {} FROM \"{}\" WHERE \"_collection_id\" = ? AND \"_id\" = ?",
         columns.iter().map(|col| format!("\"{col}\"")).join(", "),
         record_table
     )
@@ -103,7 +127,7 @@ pub fn select_many(
     with_query_limit: &bool,
 ) -> String {
     let mut query = format!(
-        "SELECT {} FROM \"hyperbase\".\"{}\"",
+        "SELECT {} FROM \"{}\"",
         columns
             .iter()
             .map(|col| {
@@ -141,7 +165,7 @@ pub fn select_many(
 
 pub fn update(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
-        "UPDATE \"hyperbase\".\"{}\" SET {} WHERE \"_collection_id\" = ? AND \"_id\" = ?",
+        "UPDATE \"{}\" SET {} WHERE \"_collection_id\" = ? AND \"_id\" = ?",
         record_table,
         columns
             .iter()
@@ -150,9 +174,13 @@ pub fn update(record_table: &str, columns: &Vec<&str>) -> String {
     )
 }
 
+pub fn select_ids(record_table: &str) -> String {
+    format!("SELECT \"_id\" FROM \"{record_table}\" WHERE \"_collection_id\" = ?")
+}
+
 pub fn delete(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
-        "DELETE FROM \"hyperbase\".\"{}\" WHERE {}",
+        "DELETE FROM \"{}\" WHERE {}",
         record_table,
         columns
             .iter()
@@ -162,7 +190,7 @@ pub fn delete(record_table: &str, columns: &Vec<&str>) -> String {
 }
 
 pub fn count(record_table: &str, filter: &str, groups: &Vec<&str>) -> String {
-    let mut query = format!("SELECT COUNT(1) FROM \"hyperbase\".\"{}\"", record_table);
+    let mut query = format!("SELECT COUNT(1) FROM \"{}\"", record_table);
     if filter.len() > 0 {
         query += &format!(" WHERE {filter}")
     }
@@ -182,3 +210,21 @@ pub fn count(record_table: &str, filter: &str, groups: &Vec<&str>) -> String {
     }
     query
 }
+
+// Records of a collection share a single partition clustered by _id in descending order
+pub fn select_newest_id(record_table: &str) -> String {
+    format!("SELECT \"_id\" FROM \"{record_table}\" LIMIT 1")
+}
+
+pub fn select_oldest_id(record_table: &str) -> String {
+    format!("SELECT \"_id\" FROM \"{record_table}\" WHERE \"_collection_id\" = ? ORDER BY \"_id\" ASC LIMIT 1")
+}
+
+// Scylla keeps no exact table size, only the estimates it refreshes every few minutes
+pub fn select_size_estimates(keyspace: &str, record_table: &str) -> String {
+    format!("SELECT \"mean_partition_size\", \"partitions_count\" FROM \"system\".\"size_estimates\" WHERE \"keyspace_name\" = '{keyspace}' AND \"table_name\" = '{record_table}'")
+}
+
+pub fn select_indexes(keyspace: &str, record_table: &str) -> String {
+    format!("SELECT \"index_name\" FROM \"system_schema\".\"indexes\" WHERE \"keyspace_name\" = '{keyspace}' AND \"table_name\" = '{record_table}'")
+}
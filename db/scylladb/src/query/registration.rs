@@ -4,20 +4,21 @@ use uuid::Uuid;
 
 use crate::{db::ScyllaDb, model::registration::RegistrationModel};
 
-const INSERT: &str = "INSERT INTO \"hyperbase\".\"registrations\" (\"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"code\") VALUES (?, ?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"code\" FROM \"hyperbase\".\"registrations\" WHERE \"id\" = ?";
-const SELECT_BY_EMAIL: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"code\" FROM \"hyperbase\".\"registrations\" WHERE \"email\" = ?";
-const UPDATE: &str = "UPDATE \"hyperbase\".\"registrations\" SET \"updated_at\" = ?, \"code\" = ? WHERE \"id\" = ?";
-const DELETE: &str = "DELETE FROM \"hyperbase\".\"registrations\" WHERE \"id\" = ?";
+const INSERT: &str = "INSERT INTO \"registrations\" (\"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"code\") VALUES (?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"code\" FROM \"registrations\" WHERE \"id\" = ?";
+const SELECT_BY_EMAIL: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"code\" FROM \"registrations\" WHERE \"email\" = ?";
+const UPDATE: &str =
+    "UPDATE \"registrations\" SET \"updated_at\" = ?, \"code\" = ? WHERE \"id\" = ?";
+const DELETE: &str = "DELETE FROM \"registrations\" WHERE \"id\" = ?";
 
 pub async fn init(cached_session: &CachingSession, ttl: &u32) {
     hb_log::info(Some("🔧"), "[ScyllaDB] Setting up registrations table");
 
-    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"hyperbase\".\"registrations\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"email\" text, \"password_hash\" text, \"code\" text, PRIMARY KEY (\"id\")) WITH default_time_to_live = ".to_owned() + &ttl.to_string(), &[]).await.unwrap();
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"registrations\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"email\" text, \"password_hash\" text, \"code\" text, PRIMARY KEY (\"id\")) WITH default_time_to_live = ".to_owned() + &ttl.to_string(), &[]).await.unwrap();
     cached_session
         .get_session()
         .query(
-            "CREATE INDEX IF NOT EXISTS ON \"hyperbase\".\"registrations\" (\"email\")",
+            "CREATE INDEX IF NOT EXISTS ON \"registrations\" (\"email\")",
             &[],
         )
         .await
@@ -0,0 +1,46 @@
+use anyhow::Result;
+use scylla::CachingSession;
+
+use crate::{db::ScyllaDb, model::sign_in_attempt::SignInAttemptModel};
+
+const UPSERT: &str = "INSERT INTO \"sign_in_attempts\" (\"key\", \"failed_count\", \"locked_until\", \"updated_at\") VALUES (?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"key\", \"failed_count\", \"locked_until\", \"updated_at\" FROM \"sign_in_attempts\" WHERE \"key\" = ?";
+const DELETE: &str = "DELETE FROM \"sign_in_attempts\" WHERE \"key\" = ?";
+
+pub async fn init(cached_session: &CachingSession) {
+    hb_log::info(Some("🔧"), "[ScyllaDB] Setting up sign_in_attempts table");
+
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"sign_in_attempts\" (\"key\" text, \"failed_count\" int, \"locked_until\" timestamp, \"updated_at\" timestamp, PRIMARY KEY (\"key\"))", &[]).await.unwrap();
+
+    cached_session
+        .add_prepared_statement(&UPSERT.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&DELETE.into())
+        .await
+        .unwrap();
+}
+
+impl ScyllaDb {
+    pub async fn upsert_sign_in_attempt(&self, value: &SignInAttemptModel) -> Result<()> {
+        self.execute(UPSERT, value).await?;
+        Ok(())
+    }
+
+    pub async fn select_sign_in_attempt(&self, key: &str) -> Result<SignInAttemptModel> {
+        Ok(self
+            .execute(SELECT, [key].as_ref())
+            .await?
+            .first_row_typed()?)
+    }
+
+    pub async fn delete_sign_in_attempt(&self, key: &str) -> Result<()> {
+        self.execute(DELETE, [key].as_ref()).await?;
+        Ok(())
+    }
+}
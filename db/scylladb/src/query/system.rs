@@ -1 +1,2 @@
-pub const COUNT_TABLE: &str = "SELECT COUNT(1) FROM \"system_schema\".\"tables\" WHERE \"keyspace_name\" = 'hyperbase' AND \"table_name\" = ?";
+pub const COUNT_TABLE: &str = "SELECT COUNT(1) FROM \"system_schema\".\"tables\" WHERE \"keyspace_name\" = ? AND \"table_name\" = ?";
+pub const PING: &str = "SELECT \"now\"() FROM \"system\".\"local\"";
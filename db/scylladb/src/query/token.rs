@@ -1,28 +1,47 @@
 use anyhow::Result;
-use scylla::{transport::session::TypedRowIter, CachingSession};
+use scylla::{
+    frame::value::{Counter, CqlTimestamp},
+    transport::session::TypedRowIter,
+    CachingSession,
+};
 use uuid::Uuid;
 
 use crate::{db::ScyllaDb, model::token::TokenModel};
 
-const INSERT: &str = "INSERT INTO \"hyperbase\".\"tokens\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\") VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\" FROM \"hyperbase\".\"tokens\" WHERE \"id\" = ?";
-const SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\" FROM \"hyperbase\".\"tokens\" WHERE \"admin_id\" = ? AND \"project_id\" = ? ALLOW FILTERING";
-const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\" FROM \"hyperbase\".\"tokens\" WHERE \"project_id\" = ?";
-const UPDATE: &str = "UPDATE \"hyperbase\".\"tokens\" SET \"updated_at\" = ?, \"admin_id\" = ?, \"name\" = ?, \"allow_anonymous\" = ?, \"expired_at\" = ? WHERE \"id\" = ?";
-const DELETE: &str = "DELETE FROM \"hyperbase\".\"tokens\" WHERE \"id\" = ?";
+const INSERT: &str = "INSERT INTO \"tokens\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\", \"disabled_at\", \"last_used_at\") VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\", \"disabled_at\", \"last_used_at\" FROM \"tokens\" WHERE \"id\" = ?";
+const SELECT_BY_TOKEN: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\", \"disabled_at\", \"last_used_at\" FROM \"tokens\" WHERE \"token\" = ?";
+const SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\", \"disabled_at\", \"last_used_at\" FROM \"tokens\" WHERE \"admin_id\" = ? AND \"project_id\" = ? ALLOW FILTERING";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"name\", \"token\", \"allow_anonymous\", \"expired_at\", \"disabled_at\", \"last_used_at\" FROM \"tokens\" WHERE \"project_id\" = ?";
+const UPDATE: &str = "UPDATE \"tokens\" SET \"updated_at\" = ?, \"admin_id\" = ?, \"name\" = ?, \"token\" = ?, \"allow_anonymous\" = ?, \"expired_at\" = ?, \"disabled_at\" = ? WHERE \"id\" = ?";
+const DELETE: &str = "DELETE FROM \"tokens\" WHERE \"id\" = ?";
+const UPDATE_LAST_USED_AT: &str = "UPDATE \"tokens\" SET \"last_used_at\" = ? WHERE \"id\" = ?";
+const INCREMENT_REQUEST_COUNT: &str =
+    "UPDATE \"token_usages\" SET \"request_count\" = \"request_count\" + ? WHERE \"token_id\" = ?";
+const SELECT_REQUEST_COUNT: &str =
+    "SELECT \"request_count\" FROM \"token_usages\" WHERE \"token_id\" = ?";
+const DELETE_REQUEST_COUNT: &str = "DELETE FROM \"token_usages\" WHERE \"token_id\" = ?";
 
 pub async fn init(cached_session: &CachingSession) {
     hb_log::info(Some("🔧"), "[ScyllaDB] Setting up tokens table");
 
-    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"hyperbase\".\"tokens\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"project_id\" uuid, \"admin_id\" uuid, \"name\" text, \"token\" text, \"allow_anonymous\" boolean, \"expired_at\" timestamp, PRIMARY KEY (\"id\"))", &[]).await.unwrap();
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"tokens\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"project_id\" uuid, \"admin_id\" uuid, \"name\" text, \"token\" text, \"allow_anonymous\" boolean, \"expired_at\" timestamp, \"disabled_at\" timestamp, \"last_used_at\" timestamp, PRIMARY KEY (\"id\"))", &[]).await.unwrap();
     cached_session
         .get_session()
         .query(
-            "CREATE INDEX IF NOT EXISTS ON \"hyperbase\".\"tokens\" (\"project_id\")",
+            "CREATE INDEX IF NOT EXISTS ON \"tokens\" (\"project_id\")",
             &[],
         )
         .await
         .unwrap();
+    cached_session
+        .get_session()
+        .query("CREATE INDEX IF NOT EXISTS ON \"tokens\" (\"token\")", &[])
+        .await
+        .unwrap();
+    // Counter columns can't live next to regular columns, so the request count of a token is kept
+    // in its own table
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"token_usages\" (\"token_id\" uuid, \"request_count\" counter, PRIMARY KEY (\"token_id\"))", &[]).await.unwrap();
 
     cached_session
         .add_prepared_statement(&INSERT.into())
@@ -32,6 +51,10 @@ pub async fn init(cached_session: &CachingSession) {
         .add_prepared_statement(&SELECT.into())
         .await
         .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT_BY_TOKEN.into())
+        .await
+        .unwrap();
     cached_session
         .add_prepared_statement(&SELECT_MANY_BY_ADMIN_ID_AND_PROJECT_ID.into())
         .await
@@ -48,6 +71,22 @@ pub async fn init(cached_session: &CachingSession) {
         .add_prepared_statement(&DELETE.into())
         .await
         .unwrap();
+    cached_session
+        .add_prepared_statement(&UPDATE_LAST_USED_AT.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&INCREMENT_REQUEST_COUNT.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT_REQUEST_COUNT.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&DELETE_REQUEST_COUNT.into())
+        .await
+        .unwrap();
 }
 
 impl ScyllaDb {
@@ -63,6 +102,13 @@ impl ScyllaDb {
             .first_row_typed()?)
     }
 
+    pub async fn select_token_by_token(&self, token: &str) -> Result<TokenModel> {
+        Ok(self
+            .execute(SELECT_BY_TOKEN, [token].as_ref())
+            .await?
+            .first_row_typed()?)
+    }
+
     pub async fn select_many_tokens_by_admin_id_and_project_id(
         &self,
         admin_id: &Uuid,
@@ -94,8 +140,10 @@ impl ScyllaDb {
                 value.updated_at(),
                 value.admin_id(),
                 value.name(),
+                value.token(),
                 value.allow_anonymous(),
                 value.expired_at(),
+                value.disabled_at(),
                 value.id(),
             ),
         )
@@ -103,8 +151,31 @@ impl ScyllaDb {
         Ok(())
     }
 
+    pub async fn increment_token_usage(
+        &self,
+        id: &Uuid,
+        last_used_at: &CqlTimestamp,
+        requests: &i64,
+    ) -> Result<()> {
+        tokio::try_join!(
+            self.execute(UPDATE_LAST_USED_AT, (last_used_at, id)),
+            self.execute(INCREMENT_REQUEST_COUNT, (Counter(*requests), id))
+        )?;
+        Ok(())
+    }
+
+    pub async fn select_token_request_count(&self, id: &Uuid) -> Result<i64> {
+        Ok(self
+            .execute(SELECT_REQUEST_COUNT, [id].as_ref())
+            .await?
+            .maybe_first_row_typed::<(Counter,)>()?
+            .map(|(request_count,)| request_count.0)
+            .unwrap_or(0))
+    }
+
     pub async fn delete_token(&self, id: &Uuid) -> Result<()> {
         self.execute(DELETE, [id].as_ref()).await?;
+        self.execute(DELETE_REQUEST_COUNT, [id].as_ref()).await?;
         Ok(())
     }
 }
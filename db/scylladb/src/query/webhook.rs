@@ -0,0 +1,126 @@
+use anyhow::Result;
+use scylla::{transport::session::TypedRowIter, CachingSession};
+use uuid::Uuid;
+
+use crate::{db::ScyllaDb, model::webhook::WebhookModel};
+
+const INSERT: &str = "INSERT INTO \"webhooks\" (\"id\", \"created_at\", \"updated_at\", \"admin_id\", \"project_id\", \"collection_id\", \"url\", \"events\", \"secret\", \"enabled\") VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"project_id\", \"collection_id\", \"url\", \"events\", \"secret\", \"enabled\" FROM \"webhooks\" WHERE \"id\" = ?";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"project_id\", \"collection_id\", \"url\", \"events\", \"secret\", \"enabled\" FROM \"webhooks\" WHERE \"project_id\" = ?";
+const SELECT_MANY_BY_COLLECTION_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"project_id\", \"collection_id\", \"url\", \"events\", \"secret\", \"enabled\" FROM \"webhooks\" WHERE \"collection_id\" = ?";
+const UPDATE: &str = "UPDATE \"webhooks\" SET \"updated_at\" = ?, \"url\" = ?, \"events\" = ?, \"secret\" = ?, \"enabled\" = ? WHERE \"id\" = ?";
+const DELETE: &str = "DELETE FROM \"webhooks\" WHERE \"id\" = ?";
+
+pub async fn init(cached_session: &CachingSession) {
+    hb_log::info(Some("🔧"), "[ScyllaDB] Setting up webhooks table");
+
+    cached_session.get_session().query("CREATE TABLE IF NOT EXISTS \"webhooks\" (\"id\" uuid, \"created_at\" timestamp, \"updated_at\" timestamp, \"admin_id\" uuid, \"project_id\" uuid, \"collection_id\" uuid, \"url\" text, \"events\" list<text>, \"secret\" text, \"enabled\" boolean, PRIMARY KEY (\"id\"))", &[]).await.unwrap();
+    cached_session
+        .get_session()
+        .query(
+            "CREATE INDEX IF NOT EXISTS ON \"webhooks\" (\"project_id\")",
+            &[],
+        )
+        .await
+        .unwrap();
+    cached_session
+        .get_session()
+        .query(
+            "CREATE INDEX IF NOT EXISTS ON \"webhooks\" (\"collection_id\")",
+            &[],
+        )
+        .await
+        .unwrap();
+
+    cached_session
+        .add_prepared_statement(&INSERT.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT_MANY_BY_PROJECT_ID.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&SELECT_MANY_BY_COLLECTION_ID.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&UPDATE.into())
+        .await
+        .unwrap();
+    cached_session
+        .add_prepared_statement(&DELETE.into())
+        .await
+        .unwrap();
+}
+
+impl ScyllaDb {
+    pub async fn insert_webhook(&self, value: &WebhookModel) -> Result<()> {
+        self.execute(INSERT, value).await?;
+        Ok(())
+    }
+
+    pub async fn select_webhook(&self, id: &Uuid) -> Result<WebhookModel> {
+        Ok(self
+            .execute(SELECT, [id].as_ref())
+            .await?
+            .first_row_typed()?)
+    }
+
+    pub async fn select_many_webhooks_by_project_id(
+        &self,
+        project_id: &Uuid,
+    ) -> Result<TypedRowIter<WebhookModel>> {
+        Ok(self
+            .execute(SELECT_MANY_BY_PROJECT_ID, [project_id].as_ref())
+            .await?
+            .rows_typed()?)
+    }
+
+    pub async fn select_many_webhooks_by_collection_id(
+        &self,
+        collection_id: &Uuid,
+    ) -> Result<TypedRowIter<WebhookModel>> {
+        Ok(self
+            .execute(SELECT_MANY_BY_COLLECTION_ID, [collection_id].as_ref())
+            .await?
+            .rows_typed()?)
+    }
+
+    pub async fn update_webhook(&self, value: &WebhookModel) -> Result<()> {
+        self.execute(
+            UPDATE,
+            &(
+                value.updated_at(),
+                value.url(),
+                value.events(),
+                value.secret(),
+                value.enabled(),
+                value.id(),
+            ),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_webhook(&self, id: &Uuid) -> Result<()> {
+        self.execute(DELETE, [id].as_ref()).await?;
+        Ok(())
+    }
+
+    pub async fn delete_many_webhooks_by_collection_id(&self, collection_id: &Uuid) -> Result<()> {
+        let webhooks_data = self
+            .select_many_webhooks_by_collection_id(collection_id)
+            .await?;
+        let mut deletes = Vec::new();
+        for webhook_data in webhooks_data {
+            deletes.push(self.execute(DELETE, (*webhook_data?.id(),)));
+        }
+        futures::future::join_all(deletes).await;
+        Ok(())
+    }
+}
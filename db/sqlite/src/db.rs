@@ -1,45 +1,157 @@
+use std::{
+    future::Future,
+    io::{self, ErrorKind},
+    ops::{Deref, DerefMut},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::{
+    future,
+    stream::{self, BoxStream},
+    StreamExt,
+};
 use sqlx::{
+    pool::PoolConnection,
     query::{Query, QueryAs},
-    sqlite::{SqliteArguments, SqlitePoolOptions, SqliteQueryResult, SqliteRow},
-    Error, Pool, Sqlite,
+    sqlite::{
+        SqliteArguments, SqliteConnectOptions, SqliteConnection, SqliteJournalMode,
+        SqlitePoolOptions, SqliteQueryResult, SqliteRow, SqliteSynchronous,
+    },
+    Error, Execute, Pool, Sqlite, Transaction,
 };
+use tokio::sync::{Mutex, MutexGuard};
+use tracing::Instrument;
 
 use crate::query::{
-    admin, admin_password_reset, bucket, bucket_rule, collection, collection_rule, file, log,
-    project, registration, token,
+    admin, admin_password_reset, admin_project_member, audit, bucket, bucket_rule, collection,
+    collection_rule, file, idempotency, log, migration, project, registration, sign_in_attempt,
+    system, token, webhook,
 };
 
 pub struct SqliteDb {
     pool: Pool<Sqlite>,
+    slow_query_threshold: Option<Duration>,
+    slow_queries: Arc<AtomicU64>,
+    in_flight: Arc<AtomicU32>,
+    statement_timeout: Option<Duration>,
     table_registration_ttl: i64,
     table_reset_password_ttl: i64,
     table_log_ttl: i64,
+    table_idempotency_ttl: i64,
+    tx: Option<Mutex<Transaction<'static, Sqlite>>>,
 }
 
 impl SqliteDb {
     pub async fn new(
         path: &str,
         max_connections: &u32,
+        min_connections: &Option<u32>,
+        acquire_timeout: &Option<Duration>,
+        idle_timeout: &Option<Duration>,
+        max_lifetime: &Option<Duration>,
+        journal_mode: &Option<String>,
+        busy_timeout_ms: &Option<u64>,
+        synchronous: &Option<String>,
+        foreign_keys: &Option<bool>,
+        statement_timeout: &Option<Duration>,
+        slow_query_threshold: &Option<Duration>,
         table_registration_ttl: &i64,
         table_reset_password_ttl: &i64,
         table_log_ttl: &i64,
+        table_idempotency_ttl: &i64,
     ) -> Self {
         hb_log::info(Some("⚡"), "[SQLite] Initializing component");
 
         let url = format!("sqlite:{path}?mode=rwc");
-        let pool = SqlitePoolOptions::new()
-            .max_connections(*max_connections)
-            .connect(&url)
-            .await
-            .unwrap();
+        let connect_opts = match SqliteConnectOptions::from_str(&url) {
+            Ok(connect_opts) => connect_opts,
+            Err(err) => {
+                hb_log::panic(None, &format!("[SQLite] Invalid connection options: {err}"));
+                unreachable!()
+            }
+        };
+
+        // WAL lets reads go on while a write is running, and the busy timeout makes concurrent
+        // writes wait for the lock instead of failing with "database is locked"
+        let journal_mode = match journal_mode.as_deref() {
+            None | Some("wal") => SqliteJournalMode::Wal,
+            Some("delete") => SqliteJournalMode::Delete,
+            Some("truncate") => SqliteJournalMode::Truncate,
+            Some("persist") => SqliteJournalMode::Persist,
+            Some("memory") => SqliteJournalMode::Memory,
+            Some("off") => SqliteJournalMode::Off,
+            Some(journal_mode) => {
+                hb_log::panic(
+                    None,
+                    &format!(
+                        "[SQLite] Unknown journal_mode '{journal_mode}', expected wal, delete, truncate, persist, memory or off"
+                    ),
+                );
+                unreachable!()
+            }
+        };
+        // Normal is durable enough in WAL mode, a crash can only lose the latest commits
+        let synchronous = match synchronous.as_deref() {
+            None | Some("normal") => SqliteSynchronous::Normal,
+            Some("off") => SqliteSynchronous::Off,
+            Some("full") => SqliteSynchronous::Full,
+            Some("extra") => SqliteSynchronous::Extra,
+            Some(synchronous) => {
+                hb_log::panic(
+                    None,
+                    &format!(
+                        "[SQLite] Unknown synchronous '{synchronous}', expected off, normal, full or extra"
+                    ),
+                );
+                unreachable!()
+            }
+        };
+        let connect_opts = connect_opts
+            .journal_mode(journal_mode)
+            .busy_timeout(Duration::from_millis(busy_timeout_ms.unwrap_or(5000)))
+            .synchronous(synchronous)
+            .foreign_keys(foreign_keys.unwrap_or(true));
+
+        let mut pool_opts = SqlitePoolOptions::new().max_connections(*max_connections);
+        if let Some(min_connections) = min_connections {
+            pool_opts = pool_opts.min_connections(*min_connections);
+        }
+        // Waiting for a connection counts against the statement timeout unless it has its own
+        if let Some(acquire_timeout) = acquire_timeout.or(*statement_timeout) {
+            pool_opts = pool_opts.acquire_timeout(acquire_timeout);
+        }
+        if let Some(idle_timeout) = idle_timeout {
+            pool_opts = pool_opts.idle_timeout(*idle_timeout);
+        }
+        if let Some(max_lifetime) = max_lifetime {
+            pool_opts = pool_opts.max_lifetime(*max_lifetime);
+        }
+        let pool = match pool_opts.connect_with(connect_opts).await {
+            Ok(pool) => pool,
+            Err(err) => {
+                hb_log::panic(None, &format!("[SQLite] Failed to open '{path}': {err}"));
+                unreachable!()
+            }
+        };
 
         Self::init(&pool).await;
 
         Self {
             pool,
+            slow_query_threshold: *slow_query_threshold,
+            slow_queries: Arc::new(AtomicU64::new(0)),
+            in_flight: Arc::new(AtomicU32::new(0)),
+            statement_timeout: *statement_timeout,
             table_registration_ttl: *table_registration_ttl,
             table_reset_password_ttl: *table_reset_password_ttl,
             table_log_ttl: *table_log_ttl,
+            table_idempotency_ttl: *table_idempotency_ttl,
+            tx: None,
         }
     }
 
@@ -47,14 +159,32 @@ impl SqliteDb {
         &self,
         query: Query<'a, Sqlite, SqliteArguments<'a>>,
     ) -> Result<SqliteQueryResult, Error> {
-        query.persistent(false).execute(&self.pool).await
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = self
+            .timeout(query.persistent(false).execute(&mut *conn))
+            .instrument(hb_log::db_span("sqlite", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn execute<'a>(
         &self,
         query: Query<'a, Sqlite, SqliteArguments<'a>>,
     ) -> Result<SqliteQueryResult, Error> {
-        query.execute(&self.pool).await
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = self
+            .timeout(query.execute(&mut *conn))
+            .instrument(hb_log::db_span("sqlite", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_one_unprepared<
@@ -64,35 +194,206 @@ impl SqliteDb {
         &self,
         query: QueryAs<'a, Sqlite, T, SqliteArguments<'a>>,
     ) -> Result<T, Error> {
-        Ok(query.persistent(false).fetch_one(&self.pool).await?)
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = self
+            .timeout(query.persistent(false).fetch_one(&mut *conn))
+            .instrument(hb_log::db_span("sqlite", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_one<'a, T: Send + Unpin + for<'r> sqlx::FromRow<'r, SqliteRow>>(
         &self,
         query: QueryAs<'a, Sqlite, T, SqliteArguments<'a>>,
     ) -> Result<T, Error> {
-        Ok(query.fetch_one(&self.pool).await?)
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = self
+            .timeout(query.fetch_one(&mut *conn))
+            .instrument(hb_log::db_span("sqlite", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_one_row<'a>(
         &self,
         query: Query<'a, Sqlite, SqliteArguments<'a>>,
     ) -> Result<SqliteRow, Error> {
-        Ok(query.fetch_one(&self.pool).await?)
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = self
+            .timeout(query.fetch_one(&mut *conn))
+            .instrument(hb_log::db_span("sqlite", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_all<'a, T: Send + Unpin + for<'r> sqlx::FromRow<'r, SqliteRow>>(
         &self,
         query: QueryAs<'a, Sqlite, T, SqliteArguments<'a>>,
     ) -> Result<Vec<T>, Error> {
-        query.fetch_all(&self.pool).await
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = self
+            .timeout(query.fetch_all(&mut *conn))
+            .instrument(hb_log::db_span("sqlite", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
     }
 
     pub async fn fetch_all_rows<'a>(
         &self,
         query: Query<'a, Sqlite, SqliteArguments<'a>>,
     ) -> Result<Vec<SqliteRow>, Error> {
-        query.fetch_all(&self.pool).await
+        let sql = query.sql();
+        let _in_flight = InFlight::new(&self.in_flight);
+        let start = Instant::now();
+        let mut conn = self.connection().await?;
+        let result = self
+            .timeout(query.fetch_all(&mut *conn))
+            .instrument(hb_log::db_span("sqlite", sql))
+            .await;
+        self.check_slow_query(sql, &start);
+        result
+    }
+
+    pub fn fetch_rows<'a>(
+        &'a self,
+        query: Query<'a, Sqlite, SqliteArguments<'a>>,
+    ) -> BoxStream<'a, Result<SqliteRow, Error>> {
+        match &self.tx {
+            // The stream can't hold on to the connection of the transaction, so the rows are
+            // fetched all at once instead
+            Some(_) => stream::once(self.fetch_all_rows(query))
+                .flat_map(|result| match result {
+                    Ok(rows) => stream::iter(rows.into_iter().map(Ok)).left_stream(),
+                    Err(err) => stream::once(future::ready(Err(err))).right_stream(),
+                })
+                .boxed(),
+            None => query.fetch(&self.pool),
+        }
+    }
+
+    // Used by the statements that have to be written together. They can't be run on a handle made
+    // by transaction, which already is one.
+    pub async fn begin(&self) -> anyhow::Result<Transaction<'_, Sqlite>> {
+        if self.tx.is_some() {
+            return Err(anyhow::Error::msg(
+                "[SQLite] Can't begin a transaction inside another one",
+            ));
+        }
+        Ok(self.pool.begin().await?)
+    }
+
+    // Makes a handle that runs every statement in one transaction, which is rolled back when the
+    // handle is dropped without being committed
+    pub async fn transaction(&self) -> anyhow::Result<Self> {
+        if self.tx.is_some() {
+            return Err(anyhow::Error::msg(
+                "[SQLite] Can't begin a transaction inside another one",
+            ));
+        }
+        Ok(Self {
+            pool: self.pool.clone(),
+            slow_query_threshold: self.slow_query_threshold,
+            slow_queries: self.slow_queries.clone(),
+            in_flight: self.in_flight.clone(),
+            statement_timeout: self.statement_timeout,
+            table_registration_ttl: self.table_registration_ttl,
+            table_reset_password_ttl: self.table_reset_password_ttl,
+            table_log_ttl: self.table_log_ttl,
+            table_idempotency_ttl: self.table_idempotency_ttl,
+            tx: Some(Mutex::new(self.pool.begin().await?)),
+        })
+    }
+
+    pub async fn commit(self) -> anyhow::Result<()> {
+        match self.tx {
+            Some(tx) => Ok(tx.into_inner().commit().await?),
+            None => Err(anyhow::Error::msg("[SQLite] Not a transaction")),
+        }
+    }
+
+    // Statements of a handle made by transaction go through its connection, any other through a
+    // connection of the pool
+    async fn connection(&self) -> Result<Connection<'_>, Error> {
+        match &self.tx {
+            Some(tx) => Ok(Connection::Transaction(tx.lock().await)),
+            None => Ok(Connection::Pool(self.pool.acquire().await?)),
+        }
+    }
+
+    pub async fn ping(&self) -> Result<(), Error> {
+        sqlx::query(system::PING)
+            .persistent(false)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // SQLite has no statement timeout of its own. The caller stops waiting once it's up, the
+    // statement itself still runs to the end on the connection's worker thread.
+    async fn timeout<T>(&self, query: impl Future<Output = Result<T, Error>>) -> Result<T, Error> {
+        match self.statement_timeout {
+            Some(statement_timeout) => match tokio::time::timeout(statement_timeout, query).await {
+                Ok(result) => result,
+                Err(_) => Err(Error::Io(io::Error::new(
+                    ErrorKind::TimedOut,
+                    format!("Query timed out after {statement_timeout:?}"),
+                ))),
+            },
+            None => query.await,
+        }
+    }
+
+    // Costs a single Instant::now() per statement when no threshold is set. Bind values are never
+    // part of the statement text, so nothing sensitive ends up in the log.
+    fn check_slow_query(&self, sql: &str, start: &Instant) {
+        if let Some(slow_query_threshold) = self.slow_query_threshold {
+            let elapsed = start.elapsed();
+            if elapsed >= slow_query_threshold {
+                self.slow_queries.fetch_add(1, Ordering::Relaxed);
+                hb_log::warn(
+                    None,
+                    &format!(
+                        "[SQLite] Slow query on table '{}' took {elapsed:?}: {sql}",
+                        table_name(sql)
+                    ),
+                );
+            }
+        }
+    }
+
+    pub fn slow_queries(&self) -> u64 {
+        self.slow_queries.load(Ordering::Relaxed)
+    }
+
+    pub fn pool_size(&self) -> u32 {
+        self.pool.size()
+    }
+
+    pub fn pool_idle(&self) -> u32 {
+        self.pool.num_idle() as u32
+    }
+
+    // Statements in flight beyond the connections in use are the ones waiting for a connection.
+    // Transactions hold a connection without being counted, so this can fall short under load.
+    pub fn pool_waiting(&self) -> u32 {
+        let busy = self.pool_size().saturating_sub(self.pool_idle());
+        self.in_flight.load(Ordering::Relaxed).saturating_sub(busy)
     }
 
     pub fn table_registration_ttl(&self) -> &i64 {
@@ -107,7 +408,13 @@ impl SqliteDb {
         &self.table_log_ttl
     }
 
+    pub fn table_idempotency_ttl(&self) -> &i64 {
+        &self.table_idempotency_ttl
+    }
+
     async fn init(pool: &Pool<Sqlite>) {
+        migration::init(pool).await;
+
         tokio::join!(
             admin::init(pool),
             project::init(pool),
@@ -116,10 +423,77 @@ impl SqliteDb {
             file::init(pool),
             token::init(pool),
             collection_rule::init(pool),
+            admin_project_member::init(pool),
             bucket_rule::init(pool),
             registration::init(pool),
             admin_password_reset::init(pool),
             log::init(pool),
+            audit::init(pool),
+            idempotency::init(pool),
+            sign_in_attempt::init(pool),
+            webhook::init(pool),
         );
     }
 }
+
+enum Connection<'a> {
+    Pool(PoolConnection<Sqlite>),
+    Transaction(MutexGuard<'a, Transaction<'static, Sqlite>>),
+}
+
+impl Deref for Connection<'_> {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Pool(conn) => conn,
+            Self::Transaction(tx) => tx,
+        }
+    }
+}
+
+impl DerefMut for Connection<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Pool(conn) => conn,
+            Self::Transaction(tx) => tx,
+        }
+    }
+}
+
+// Counts a statement as in flight until it finishes or is cancelled
+struct InFlight<'a>(&'a AtomicU32);
+
+impl<'a> InFlight<'a> {
+    fn new(count: &'a AtomicU32) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self(count)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// The first table a statement names, which tells the collection a record query is on
+fn table_name(sql: &str) -> &str {
+    let mut words = sql.split_whitespace();
+    while let Some(word) = words.next() {
+        if ["FROM", "INTO", "UPDATE", "TABLE", "JOIN"]
+            .iter()
+            .any(|keyword| word.eq_ignore_ascii_case(keyword))
+        {
+            for word in words.by_ref() {
+                if !["IF", "NOT", "EXISTS", "ONLY"]
+                    .iter()
+                    .any(|keyword| word.eq_ignore_ascii_case(keyword))
+                {
+                    return word.trim_matches(|c| c == '"' || c == '`' || c == '(');
+                }
+            }
+        }
+    }
+    "-"
+}
@@ -1,6 +1,9 @@
 use sqlx::{
     prelude::FromRow,
-    types::chrono::{DateTime, Utc},
+    types::{
+        chrono::{DateTime, Utc},
+        Json,
+    },
 };
 use uuid::Uuid;
 
@@ -11,6 +14,9 @@ pub struct AdminModel {
     updated_at: DateTime<Utc>,
     email: String,
     password_hash: String,
+    totp_secret: Option<String>,
+    totp_enabled_at: Option<DateTime<Utc>>,
+    totp_backup_codes: Option<Json<Vec<String>>>,
 }
 
 impl AdminModel {
@@ -20,6 +26,9 @@ impl AdminModel {
         updated_at: &DateTime<Utc>,
         email: &str,
         password_hash: &str,
+        totp_secret: &Option<String>,
+        totp_enabled_at: &Option<DateTime<Utc>>,
+        totp_backup_codes: &Option<Json<Vec<String>>>,
     ) -> Self {
         Self {
             id: *id,
@@ -27,6 +36,9 @@ impl AdminModel {
             updated_at: *updated_at,
             email: email.to_owned(),
             password_hash: password_hash.to_owned(),
+            totp_secret: totp_secret.clone(),
+            totp_enabled_at: *totp_enabled_at,
+            totp_backup_codes: totp_backup_codes.clone(),
         }
     }
 
@@ -49,4 +61,16 @@ impl AdminModel {
     pub fn password_hash(&self) -> &str {
         &self.password_hash
     }
+
+    pub fn totp_secret(&self) -> &Option<String> {
+        &self.totp_secret
+    }
+
+    pub fn totp_enabled_at(&self) -> &Option<DateTime<Utc>> {
+        &self.totp_enabled_at
+    }
+
+    pub fn totp_backup_codes(&self) -> &Option<Json<Vec<String>>> {
+        &self.totp_backup_codes
+    }
 }
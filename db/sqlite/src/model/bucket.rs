@@ -1,6 +1,9 @@
 use sqlx::{
     prelude::FromRow,
-    types::chrono::{DateTime, Utc},
+    types::{
+        chrono::{DateTime, Utc},
+        Json,
+    },
 };
 use uuid::Uuid;
 
@@ -13,6 +16,10 @@ pub struct BucketModel {
     name: String,
     path: String,
     opt_ttl: Option<i64>,
+    max_file_size: Option<i64>,
+    allowed_content_types: Option<Json<Vec<String>>>,
+    quota_bytes: Option<i64>,
+    used_bytes: i64,
 }
 
 impl BucketModel {
@@ -24,6 +31,10 @@ impl BucketModel {
         name: &str,
         path: &str,
         opt_ttl: &Option<i64>,
+        max_file_size: &Option<i64>,
+        allowed_content_types: &Option<Json<Vec<String>>>,
+        quota_bytes: &Option<i64>,
+        used_bytes: &i64,
     ) -> Self {
         Self {
             id: *id,
@@ -33,6 +44,10 @@ impl BucketModel {
             name: name.to_owned(),
             path: path.to_owned(),
             opt_ttl: *opt_ttl,
+            max_file_size: *max_file_size,
+            allowed_content_types: allowed_content_types.clone(),
+            quota_bytes: *quota_bytes,
+            used_bytes: *used_bytes,
         }
     }
 
@@ -63,4 +78,20 @@ impl BucketModel {
     pub fn opt_ttl(&self) -> &Option<i64> {
         &self.opt_ttl
     }
+
+    pub fn max_file_size(&self) -> &Option<i64> {
+        &self.max_file_size
+    }
+
+    pub fn allowed_content_types(&self) -> &Option<Json<Vec<String>>> {
+        &self.allowed_content_types
+    }
+
+    pub fn quota_bytes(&self) -> &Option<i64> {
+        &self.quota_bytes
+    }
+
+    pub fn used_bytes(&self) -> &i64 {
+        &self.used_bytes
+    }
 }
@@ -15,6 +15,8 @@ pub struct FileModel {
     content_type: String,
     size: i64,
     public: bool,
+    pending: bool,
+    checksum: Option<String>,
 }
 
 impl FileModel {
@@ -28,6 +30,8 @@ impl FileModel {
         content_type: &str,
         size: &i64,
         public: &bool,
+        pending: &bool,
+        checksum: &Option<String>,
     ) -> Self {
         Self {
             id: *id,
@@ -39,6 +43,8 @@ impl FileModel {
             content_type: content_type.to_owned(),
             size: *size,
             public: *public,
+            pending: *pending,
+            checksum: checksum.clone(),
         }
     }
 
@@ -77,4 +83,105 @@ impl FileModel {
     pub fn public(&self) -> &bool {
         &self.public
     }
+
+    pub fn pending(&self) -> &bool {
+        &self.pending
+    }
+
+    pub fn checksum(&self) -> &Option<String> {
+        &self.checksum
+    }
+}
+
+pub struct FileFilterModel {
+    file_name: Option<String>,
+    content_type: Option<String>,
+    public: Option<bool>,
+    created_by: Option<Uuid>,
+    min_size: Option<i64>,
+    max_size: Option<i64>,
+}
+
+impl FileFilterModel {
+    pub fn new(
+        file_name: &Option<String>,
+        content_type: &Option<String>,
+        public: &Option<bool>,
+        created_by: &Option<Uuid>,
+        min_size: &Option<i64>,
+        max_size: &Option<i64>,
+    ) -> Self {
+        Self {
+            file_name: file_name.clone(),
+            content_type: content_type.clone(),
+            public: *public,
+            created_by: *created_by,
+            min_size: *min_size,
+            max_size: *max_size,
+        }
+    }
+
+    pub fn file_name(&self) -> &Option<String> {
+        &self.file_name
+    }
+
+    pub fn content_type(&self) -> &Option<String> {
+        &self.content_type
+    }
+
+    pub fn public(&self) -> &Option<bool> {
+        &self.public
+    }
+
+    pub fn created_by(&self) -> &Option<Uuid> {
+        &self.created_by
+    }
+
+    pub fn min_size(&self) -> &Option<i64> {
+        &self.min_size
+    }
+
+    pub fn max_size(&self) -> &Option<i64> {
+        &self.max_size
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum FileOrderColumn {
+    CreatedAt,
+    Size,
+    FileName,
+}
+
+impl FileOrderColumn {
+    // File ids are UUIDv7, so ordering by id is ordering by creation time
+    pub fn column_name(&self) -> &str {
+        match self {
+            Self::CreatedAt => "id",
+            Self::Size => "size",
+            Self::FileName => "file_name",
+        }
+    }
+}
+
+pub struct FileOrderModel {
+    column: FileOrderColumn,
+    descending: bool,
+}
+
+impl FileOrderModel {
+    pub fn new(column: &FileOrderColumn, descending: &bool) -> Self {
+        Self {
+            column: *column,
+            descending: *descending,
+        }
+    }
+
+    pub fn column(&self) -> &FileOrderColumn {
+        &self.column
+    }
+
+    pub fn descending(&self) -> &bool {
+        &self.descending
+    }
 }
@@ -0,0 +1,44 @@
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    FromRow,
+};
+
+#[derive(FromRow)]
+pub struct SignInAttemptModel {
+    key: String,
+    failed_count: i32,
+    locked_until: Option<DateTime<Utc>>,
+    updated_at: DateTime<Utc>,
+}
+
+impl SignInAttemptModel {
+    pub fn new(
+        key: &str,
+        failed_count: &i32,
+        locked_until: &Option<DateTime<Utc>>,
+        updated_at: &DateTime<Utc>,
+    ) -> Self {
+        Self {
+            key: key.to_owned(),
+            failed_count: *failed_count,
+            locked_until: *locked_until,
+            updated_at: *updated_at,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn failed_count(&self) -> &i32 {
+        &self.failed_count
+    }
+
+    pub fn locked_until(&self) -> &Option<DateTime<Utc>> {
+        &self.locked_until
+    }
+
+    pub fn updated_at(&self) -> &DateTime<Utc> {
+        &self.updated_at
+    }
+}
@@ -4,16 +4,16 @@ use uuid::Uuid;
 
 use crate::{db::SqliteDb, model::admin::AdminModel};
 
-const INSERT: &str = "INSERT INTO \"admins\" (\"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\") VALUES (?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\" FROM \"admins\" WHERE \"id\" = ?";
-const SELECT_BY_EMAIL: &str= "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\" FROM \"admins\" WHERE \"email\" = ?";
-const UPDATE: &str = "UPDATE \"admins\" SET \"updated_at\" = ?, \"email\" = ?, \"password_hash\" = ? WHERE \"id\" = ?";
+const INSERT: &str = "INSERT INTO \"admins\" (\"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"totp_secret\", \"totp_enabled_at\", \"totp_backup_codes\") VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"totp_secret\", \"totp_enabled_at\", \"totp_backup_codes\" FROM \"admins\" WHERE \"id\" = ?";
+const SELECT_BY_EMAIL: &str= "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"totp_secret\", \"totp_enabled_at\", \"totp_backup_codes\" FROM \"admins\" WHERE \"email\" = ?";
+const UPDATE: &str = "UPDATE \"admins\" SET \"updated_at\" = ?, \"email\" = ?, \"password_hash\" = ?, \"totp_secret\" = ?, \"totp_enabled_at\" = ?, \"totp_backup_codes\" = ? WHERE \"id\" = ?";
 const DELETE: &str = "DELETE FROM \"admins\" WHERE \"id\" = ?";
 
 pub async fn init(pool: &Pool<Sqlite>) {
     hb_log::info(Some("🔧"), "[SQLite] Setting up admins table");
 
-    pool.execute("CREATE TABLE IF NOT EXISTS \"admins\" (\"id\" blob, \"created_at\" timestamp, \"updated_at\" timestamp, \"email\" text, \"password_hash\" text, PRIMARY KEY (\"id\"))").await.unwrap();
+    pool.execute("CREATE TABLE IF NOT EXISTS \"admins\" (\"id\" blob, \"created_at\" timestamp, \"updated_at\" timestamp, \"email\" text, \"password_hash\" text, \"totp_secret\" text, \"totp_enabled_at\" timestamp, \"totp_backup_codes\" blob, PRIMARY KEY (\"id\"))").await.unwrap();
 
     tokio::try_join!(
         pool.prepare(INSERT),
@@ -33,7 +33,10 @@ impl SqliteDb {
                 .bind(value.created_at())
                 .bind(value.updated_at())
                 .bind(value.email())
-                .bind(value.password_hash()),
+                .bind(value.password_hash())
+                .bind(value.totp_secret())
+                .bind(value.totp_enabled_at())
+                .bind(value.totp_backup_codes()),
         )
         .await?;
         Ok(())
@@ -55,6 +58,9 @@ impl SqliteDb {
                 .bind(value.updated_at())
                 .bind(value.email())
                 .bind(value.password_hash())
+                .bind(value.totp_secret())
+                .bind(value.totp_enabled_at())
+                .bind(value.totp_backup_codes())
                 .bind(value.id()),
         )
         .await?;
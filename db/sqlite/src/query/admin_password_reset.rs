@@ -12,7 +12,10 @@ const DELETE: &str = "DELETE FROM \"admin_password_resets\" WHERE \"id\" = ?";
 const DELETE_EXPIRE: &str = "DELETE FROM \"admin_password_resets\" WHERE \"updated_at\" < ?";
 
 pub async fn init(pool: &Pool<Sqlite>) {
-    hb_log::info(Some("🔧"), "[SQLite] Setting up admin_password_resets table");
+    hb_log::info(
+        Some("🔧"),
+        "[SQLite] Setting up admin_password_resets table",
+    );
 
     pool.execute("CREATE TABLE IF NOT EXISTS \"admin_password_resets\" (\"id\" blob, \"created_at\" timestamp, \"updated_at\" timestamp, \"admin_id\" blob, \"code\" text, PRIMARY KEY (\"id\"))").await.unwrap();
 
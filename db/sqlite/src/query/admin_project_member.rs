@@ -0,0 +1,126 @@
+use anyhow::Result;
+use sqlx::{Executor, Pool, Sqlite};
+use uuid::Uuid;
+
+use crate::{db::SqliteDb, model::admin_project_member::AdminProjectMemberModel};
+
+const INSERT: &str = "INSERT INTO \"admin_project_members\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"role\") VALUES (?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"role\" FROM \"admin_project_members\" WHERE \"id\" = ?";
+const SELECT_BY_PROJECT_ID_AND_ADMIN_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"role\" FROM \"admin_project_members\" WHERE \"project_id\" = ? AND \"admin_id\" = ?";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"role\" FROM \"admin_project_members\" WHERE \"project_id\" = ? ORDER BY \"id\" DESC";
+const SELECT_MANY_BY_ADMIN_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"admin_id\", \"role\" FROM \"admin_project_members\" WHERE \"admin_id\" = ? ORDER BY \"id\" DESC";
+const UPDATE: &str =
+    "UPDATE \"admin_project_members\" SET \"updated_at\" = ?, \"role\" = ? WHERE \"id\" = ?";
+const DELETE: &str = "DELETE FROM \"admin_project_members\" WHERE \"id\" = ?";
+const DELETE_MANY_BY_PROJECT_ID: &str =
+    "DELETE FROM \"admin_project_members\" WHERE \"project_id\" = ?";
+const DELETE_MANY_BY_ADMIN_ID: &str =
+    "DELETE FROM \"admin_project_members\" WHERE \"admin_id\" = ?";
+
+pub async fn init(pool: &Pool<Sqlite>) {
+    hb_log::info(
+        Some("🔧"),
+        "[SQLite] Setting up admin_project_members table",
+    );
+
+    pool.execute("CREATE TABLE IF NOT EXISTS \"admin_project_members\" (\"id\" blob, \"created_at\" timestamp, \"updated_at\" timestamp, \"project_id\" blob, \"admin_id\" blob, \"role\" text, PRIMARY KEY (\"id\"))").await.unwrap();
+
+    tokio::try_join!(
+        pool.prepare(INSERT),
+        pool.prepare(SELECT),
+        pool.prepare(SELECT_BY_PROJECT_ID_AND_ADMIN_ID),
+        pool.prepare(SELECT_MANY_BY_PROJECT_ID),
+        pool.prepare(SELECT_MANY_BY_ADMIN_ID),
+        pool.prepare(UPDATE),
+        pool.prepare(DELETE),
+        pool.prepare(DELETE_MANY_BY_PROJECT_ID),
+        pool.prepare(DELETE_MANY_BY_ADMIN_ID),
+    )
+    .unwrap();
+}
+
+impl SqliteDb {
+    pub async fn insert_admin_project_member(&self, value: &AdminProjectMemberModel) -> Result<()> {
+        self.execute(
+            sqlx::query(INSERT)
+                .bind(value.id())
+                .bind(value.created_at())
+                .bind(value.updated_at())
+                .bind(value.project_id())
+                .bind(value.admin_id())
+                .bind(value.role()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn select_admin_project_member(&self, id: &Uuid) -> Result<AdminProjectMemberModel> {
+        Ok(self.fetch_one(sqlx::query_as(SELECT).bind(id)).await?)
+    }
+
+    pub async fn select_admin_project_member_by_project_id_and_admin_id(
+        &self,
+        project_id: &Uuid,
+        admin_id: &Uuid,
+    ) -> Result<AdminProjectMemberModel> {
+        Ok(self
+            .fetch_one(
+                sqlx::query_as(SELECT_BY_PROJECT_ID_AND_ADMIN_ID)
+                    .bind(project_id)
+                    .bind(admin_id),
+            )
+            .await?)
+    }
+
+    pub async fn select_many_admin_project_members_by_project_id(
+        &self,
+        project_id: &Uuid,
+    ) -> Result<Vec<AdminProjectMemberModel>> {
+        Ok(self
+            .fetch_all(sqlx::query_as(SELECT_MANY_BY_PROJECT_ID).bind(project_id))
+            .await?)
+    }
+
+    pub async fn select_many_admin_project_members_by_admin_id(
+        &self,
+        admin_id: &Uuid,
+    ) -> Result<Vec<AdminProjectMemberModel>> {
+        Ok(self
+            .fetch_all(sqlx::query_as(SELECT_MANY_BY_ADMIN_ID).bind(admin_id))
+            .await?)
+    }
+
+    pub async fn update_admin_project_member(&self, value: &AdminProjectMemberModel) -> Result<()> {
+        self.execute(
+            sqlx::query(UPDATE)
+                .bind(value.updated_at())
+                .bind(value.role())
+                .bind(value.id()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_admin_project_member(&self, id: &Uuid) -> Result<()> {
+        self.execute(sqlx::query(DELETE).bind(id)).await?;
+        Ok(())
+    }
+
+    pub async fn delete_many_admin_project_members_by_project_id(
+        &self,
+        project_id: &Uuid,
+    ) -> Result<()> {
+        self.execute(sqlx::query(DELETE_MANY_BY_PROJECT_ID).bind(project_id))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_many_admin_project_members_by_admin_id(
+        &self,
+        admin_id: &Uuid,
+    ) -> Result<()> {
+        self.execute(sqlx::query(DELETE_MANY_BY_ADMIN_ID).bind(admin_id))
+            .await?;
+        Ok(())
+    }
+}
@@ -4,16 +4,17 @@ use uuid::Uuid;
 
 use crate::{db::SqliteDb, model::bucket::BucketModel};
 
-const INSERT: &str = "INSERT INTO \"buckets\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"path\", \"opt_ttl\") VALUES (?, ?, ?, ?, ?, ?, ?)";
-const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"path\", \"opt_ttl\" FROM \"buckets\" WHERE \"id\" = ?";
-const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"path\", \"opt_ttl\" FROM \"buckets\" WHERE \"project_id\" = ? ORDER BY \"id\" DESC";
-const UPDATE: &str = "UPDATE \"buckets\" SET \"updated_at\" = ?, \"name\" = ?, \"opt_ttl\" = ? WHERE \"id\" = ?";
+const INSERT: &str = "INSERT INTO \"buckets\" (\"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"path\", \"opt_ttl\", \"max_file_size\", \"allowed_content_types\", \"quota_bytes\", \"used_bytes\") VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"path\", \"opt_ttl\", \"max_file_size\", \"allowed_content_types\", \"quota_bytes\", \"used_bytes\" FROM \"buckets\" WHERE \"id\" = ?";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"project_id\", \"name\", \"path\", \"opt_ttl\", \"max_file_size\", \"allowed_content_types\", \"quota_bytes\", \"used_bytes\" FROM \"buckets\" WHERE \"project_id\" = ? ORDER BY \"id\" DESC";
+const UPDATE: &str = "UPDATE \"buckets\" SET \"updated_at\" = ?, \"name\" = ?, \"opt_ttl\" = ?, \"max_file_size\" = ?, \"allowed_content_types\" = ?, \"quota_bytes\" = ? WHERE \"id\" = ?";
 const DELETE: &str = "DELETE FROM \"buckets\" WHERE \"id\" = ?";
+const RECALCULATE_USED_BYTES: &str = "UPDATE \"buckets\" SET \"used_bytes\" = (SELECT COALESCE(SUM(\"size\"), 0) FROM \"files\" WHERE \"bucket_id\" = ?) WHERE \"id\" = ?";
 
 pub async fn init(pool: &Pool<Sqlite>) {
     hb_log::info(Some("🔧"), "[SQLite] Setting up buckets table");
 
-    pool.execute("CREATE TABLE IF NOT EXISTS \"buckets\" (\"id\" blob, \"created_at\" timestamp, \"updated_at\" timestamp, \"project_id\" blob, \"name\" text, \"path\" text, \"opt_ttl\" bigint, PRIMARY KEY (\"id\"))").await.unwrap();
+    pool.execute("CREATE TABLE IF NOT EXISTS \"buckets\" (\"id\" blob, \"created_at\" timestamp, \"updated_at\" timestamp, \"project_id\" blob, \"name\" text, \"path\" text, \"opt_ttl\" bigint, \"max_file_size\" bigint, \"allowed_content_types\" blob, \"quota_bytes\" bigint, \"used_bytes\" bigint, PRIMARY KEY (\"id\"))").await.unwrap();
 
     tokio::try_join!(
         pool.prepare(INSERT),
@@ -35,7 +36,11 @@ impl SqliteDb {
                 .bind(value.project_id())
                 .bind(value.name())
                 .bind(value.path())
-                .bind(value.opt_ttl()),
+                .bind(value.opt_ttl())
+                .bind(value.max_file_size())
+                .bind(value.allowed_content_types())
+                .bind(value.quota_bytes())
+                .bind(value.used_bytes()),
         )
         .await?;
         Ok(())
@@ -60,12 +65,22 @@ impl SqliteDb {
                 .bind(value.updated_at())
                 .bind(value.name())
                 .bind(value.opt_ttl())
+                .bind(value.max_file_size())
+                .bind(value.allowed_content_types())
+                .bind(value.quota_bytes())
                 .bind(value.id()),
         )
         .await?;
         Ok(())
     }
 
+    // Rebuilds the usage counter of the bucket out of the sizes of its files
+    pub async fn recalculate_bucket_used_bytes(&self, id: &Uuid) -> Result<()> {
+        self.execute(sqlx::query(RECALCULATE_USED_BYTES).bind(id).bind(id))
+            .await?;
+        Ok(())
+    }
+
     pub async fn delete_bucket(&self, id: &Uuid) -> Result<()> {
         self.execute(sqlx::query(DELETE).bind(id)).await?;
         Ok(())
@@ -0,0 +1,97 @@
+use anyhow::{Error, Result};
+use chrono::{Duration, Utc};
+use sqlx::{types::chrono::DateTime, Executor, Pool, Sqlite};
+use uuid::Uuid;
+
+use crate::{db::SqliteDb, model::idempotency::IdempotencyModel};
+
+const INSERT: &str = "INSERT INTO \"idempotency_keys\" (\"collection_id\", \"owner_id\", \"key\", \"created_at\", \"record_id\") VALUES (?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"collection_id\", \"owner_id\", \"key\", \"created_at\", \"record_id\" FROM \"idempotency_keys\" WHERE \"collection_id\" = ? AND \"owner_id\" = ? AND \"key\" = ? AND \"created_at\" >= ?";
+const DELETE: &str = "DELETE FROM \"idempotency_keys\" WHERE \"collection_id\" = ? AND \"owner_id\" = ? AND \"key\" = ?";
+const DELETE_EXPIRE: &str = "DELETE FROM \"idempotency_keys\" WHERE \"created_at\" < ?";
+
+pub async fn init(pool: &Pool<Sqlite>) {
+    hb_log::info(Some("🔧"), "[SQLite] Setting up idempotency_keys table");
+
+    pool.execute("CREATE TABLE IF NOT EXISTS \"idempotency_keys\" (\"collection_id\" blob, \"owner_id\" blob, \"key\" text, \"created_at\" timestamp, \"record_id\" blob, PRIMARY KEY (\"collection_id\", \"owner_id\", \"key\"))").await.unwrap();
+
+    tokio::try_join!(
+        pool.prepare(INSERT),
+        pool.prepare(SELECT),
+        pool.prepare(DELETE),
+        pool.prepare(DELETE_EXPIRE),
+    )
+    .unwrap();
+}
+
+impl SqliteDb {
+    pub async fn insert_idempotency(&self, value: &IdempotencyModel) -> Result<()> {
+        let _ = self.delete_expired_idempotencies().await;
+        self.execute(
+            sqlx::query(INSERT)
+                .bind(value.collection_id())
+                .bind(value.owner_id())
+                .bind(value.key())
+                .bind(value.created_at())
+                .bind(value.record_id()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn select_idempotency(
+        &self,
+        collection_id: &Uuid,
+        owner_id: &Uuid,
+        key: &str,
+    ) -> Result<IdempotencyModel> {
+        let _ = self.delete_expired_idempotencies().await;
+        Ok(self
+            .fetch_one(
+                sqlx::query_as(SELECT)
+                    .bind(collection_id)
+                    .bind(owner_id)
+                    .bind(key)
+                    .bind(&{
+                        let now = Utc::now();
+                        DateTime::from_timestamp(
+                            now.timestamp() - self.table_idempotency_ttl(),
+                            now.timestamp_subsec_nanos(),
+                        )
+                        .ok_or_else(|| Error::msg("timestamp is out of range."))?
+                    }),
+            )
+            .await?)
+    }
+
+    pub async fn delete_idempotency(
+        &self,
+        collection_id: &Uuid,
+        owner_id: &Uuid,
+        key: &str,
+    ) -> Result<()> {
+        self.execute(
+            sqlx::query(DELETE)
+                .bind(collection_id)
+                .bind(owner_id)
+                .bind(key),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_expired_idempotencies(&self) -> Result<()> {
+        self.execute(
+            sqlx::query(DELETE_EXPIRE).bind(
+                Utc::now()
+                    .checked_sub_signed(
+                        Duration::try_seconds(*self.table_idempotency_ttl())
+                            .ok_or_else(|| Error::msg("table_idempotency_ttl is out of range."))?,
+                    )
+                    .ok_or_else(|| Error::msg("table_idempotency_ttl is out of range."))?,
+            ),
+        )
+        .await?;
+        Ok(())
+    }
+}
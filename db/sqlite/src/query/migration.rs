@@ -0,0 +1,207 @@
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use sqlx::{Executor, Pool, Sqlite};
+use uuid::Uuid;
+
+use crate::query::{record, system::COUNT_TABLE};
+
+// Every entry moves the schema one version ahead, the version being its position counting from 1.
+// Version 1 is the schema the tables had when versions started to be tracked, so it has nothing
+// to run. Entries are only ever appended, a released one is never changed.
+const MIGRATIONS: &[Migration] = &[
+    Migration::Statements(&[]),
+    // Permission checks filter records on _created_by and the TTL sweep on _updated_at
+    Migration::RecordIndexes(&["_created_by", "_updated_at"]),
+    // Soft delete of records
+    Migration::Statements(&[
+        "ALTER TABLE \"collections\" ADD COLUMN \"opt_soft_delete\" boolean",
+        "UPDATE \"collections\" SET \"opt_soft_delete\" = false",
+    ]),
+    // Unique indexes across several fields
+    Migration::Statements(&[
+        "ALTER TABLE \"collections\" ADD COLUMN \"unique_indexes\" blob",
+        "UPDATE \"collections\" SET \"unique_indexes\" = '[]'",
+    ]),
+    // TTL of each record
+    Migration::Statements(&[
+        "ALTER TABLE \"collections\" ADD COLUMN \"opt_per_record_ttl\" boolean",
+        "UPDATE \"collections\" SET \"opt_per_record_ttl\" = false",
+    ]),
+    // Chunked uploads, the files already there are complete
+    Migration::Statements(&[
+        "ALTER TABLE \"files\" ADD COLUMN \"pending\" boolean",
+        "UPDATE \"files\" SET \"pending\" = false",
+    ]),
+    // Upload limits of buckets
+    Migration::Statements(&[
+        "ALTER TABLE \"buckets\" ADD COLUMN \"max_file_size\" bigint",
+        "ALTER TABLE \"buckets\" ADD COLUMN \"allowed_content_types\" blob",
+    ]),
+    // Storage quota of buckets, starting from the size of the files they already hold
+    Migration::Statements(&[
+        "ALTER TABLE \"buckets\" ADD COLUMN \"quota_bytes\" bigint",
+        "ALTER TABLE \"buckets\" ADD COLUMN \"used_bytes\" bigint",
+        "UPDATE \"buckets\" SET \"used_bytes\" = (SELECT COALESCE(SUM(\"size\"), 0) FROM \"files\" WHERE \"files\".\"bucket_id\" = \"buckets\".\"id\")",
+    ]),
+    // Checksums of files, left empty for the ones uploaded before
+    Migration::Statements(&["ALTER TABLE \"files\" ADD COLUMN \"checksum\" text"]),
+    // Disabling expired tokens
+    Migration::Statements(&["ALTER TABLE \"tokens\" ADD COLUMN \"disabled_at\" timestamp"]),
+    // Two-factor authentication of admins
+    Migration::Statements(&[
+        "ALTER TABLE \"admins\" ADD COLUMN \"totp_secret\" text",
+        "ALTER TABLE \"admins\" ADD COLUMN \"totp_enabled_at\" timestamp",
+        "ALTER TABLE \"admins\" ADD COLUMN \"totp_backup_codes\" blob",
+    ]),
+    // Field lists of collection rules, every field stays readable and writable when left empty
+    Migration::Statements(&[
+        "ALTER TABLE \"collection_rules\" ADD COLUMN \"readable_fields\" blob",
+        "ALTER TABLE \"collection_rules\" ADD COLUMN \"writable_fields\" blob",
+    ]),
+    // Usage of tokens
+    Migration::Statements(&[
+        "ALTER TABLE \"tokens\" ADD COLUMN \"last_used_at\" timestamp",
+        "ALTER TABLE \"tokens\" ADD COLUMN \"request_count\" bigint",
+        "UPDATE \"tokens\" SET \"request_count\" = 0",
+    ]),
+    // Publishing record changes over MQTT
+    Migration::Statements(&[
+        "ALTER TABLE \"collections\" ADD COLUMN \"opt_mqtt_publish\" boolean",
+        "UPDATE \"collections\" SET \"opt_mqtt_publish\" = false",
+    ]),
+    // Tokens are stored as their SHA-256 hash. Databases set up after that change but before
+    // versions were tracked already hold hashes, which are told apart from the alphanumeric
+    // plaintext ones by being 64 lowercase hex digits.
+    Migration::HashTokens,
+];
+
+enum Migration {
+    Statements(&'static [&'static str]),
+    // Indexes every existing record table on the given columns
+    RecordIndexes(&'static [&'static str]),
+    // Replaces every plaintext token with its hash, SQLite has no SHA-256 function of its own
+    HashTokens,
+}
+
+const SELECT_COLLECTION_IDS: &str = "SELECT \"id\" FROM \"collections\"";
+const SELECT_TOKENS: &str = "SELECT \"token\" FROM \"tokens\"";
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS \"schema_versions\" (\"version\" integer, \"applied_at\" timestamp, PRIMARY KEY (\"version\"))";
+const INSERT: &str = "INSERT INTO \"schema_versions\" (\"version\", \"applied_at\") VALUES (?, ?)";
+const SELECT_LATEST: &str = "SELECT MAX(\"version\") FROM \"schema_versions\"";
+
+pub const LATEST_VERSION: i32 = MIGRATIONS.len() as i32;
+
+// Runs before the other tables are set up, so the statements they prepare see the latest schema
+pub async fn init(pool: &Pool<Sqlite>) {
+    hb_log::info(Some("🔧"), "[SQLite] Setting up schema_versions table");
+
+    pool.execute(CREATE_TABLE).await.unwrap();
+
+    let (version,) = sqlx::query_as::<_, (Option<i32>,)>(SELECT_LATEST)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    let version = match version {
+        Some(version) => version,
+        None => {
+            // A database without the collections table is fresh and gets the latest schema right
+            // away, one that has it was set up before versions were tracked
+            let (count,) = sqlx::query_as::<_, (i64,)>(COUNT_TABLE)
+                .bind("collections")
+                .fetch_one(pool)
+                .await
+                .unwrap();
+            let version = if count > 0 { 1 } else { LATEST_VERSION };
+            sqlx::query(INSERT)
+                .bind(version)
+                .bind(Utc::now())
+                .execute(pool)
+                .await
+                .unwrap();
+            version
+        }
+    };
+
+    if version > LATEST_VERSION {
+        hb_log::panic(
+            None,
+            &format!(
+                "[SQLite] Database schema is at version {version}, newer than the latest version {LATEST_VERSION} this build knows about. Refusing to start to not break it."
+            ),
+        );
+    }
+
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let version = idx as i32 + 1;
+        hb_log::info(
+            Some("🔧"),
+            &format!("[SQLite] Migrating schema to version {version}"),
+        );
+        let statements = match migration {
+            Migration::Statements(statements) => statements
+                .iter()
+                .map(|statement| statement.to_string())
+                .collect(),
+            Migration::RecordIndexes(columns) => record_indexes(pool, columns).await,
+            Migration::HashTokens => hash_tokens(pool).await,
+        };
+        let mut tx = pool.begin().await.unwrap();
+        for statement in &statements {
+            (&mut *tx).execute(statement.as_str()).await.unwrap();
+        }
+        sqlx::query(INSERT)
+            .bind(version)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+    }
+}
+
+async fn record_indexes(pool: &Pool<Sqlite>, columns: &[&str]) -> Vec<String> {
+    let collection_ids = sqlx::query_as::<_, (Uuid,)>(SELECT_COLLECTION_IDS)
+        .fetch_all(pool)
+        .await
+        .unwrap();
+
+    let mut statements = Vec::with_capacity(collection_ids.len() * columns.len());
+    for (collection_id,) in collection_ids {
+        // Named the way RecordDao names the table of a collection
+        let record_table = format!("records_{}", collection_id.simple());
+        let (count,) = sqlx::query_as::<_, (i64,)>(COUNT_TABLE)
+            .bind(&record_table)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        if count == 0 {
+            continue;
+        }
+        for column in columns {
+            statements.push(record::create_index(&record_table, column));
+        }
+    }
+    statements
+}
+
+async fn hash_tokens(pool: &Pool<Sqlite>) -> Vec<String> {
+    let tokens = sqlx::query_as::<_, (String,)>(SELECT_TOKENS)
+        .fetch_all(pool)
+        .await
+        .unwrap();
+
+    tokens
+        .into_iter()
+        .filter(|(token,)| !is_hashed_token(token))
+        .map(|(token,)| {
+            let hash = hex::encode(Sha256::digest(token.as_bytes()));
+            let token = token.replace('\'', "''");
+            format!("UPDATE \"tokens\" SET \"token\" = '{hash}' WHERE \"token\" = '{token}'")
+        })
+        .collect()
+}
+
+fn is_hashed_token(token: &str) -> bool {
+    token.len() == 64 && token.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+}
@@ -6,8 +6,10 @@ use crate::{db::SqliteDb, model::project::ProjectModel};
 
 const INSERT: &str = "INSERT INTO \"projects\" (\"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\") VALUES (?, ?, ?, ?, ?)";
 const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\" FROM \"projects\" WHERE \"id\" = ?";
+const SELECT_MANY: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\" FROM \"projects\" ORDER BY \"id\" DESC";
 const SELECT_MANY_BY_ADMIN_ID:  &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"name\" FROM \"projects\" WHERE \"admin_id\" = ? ORDER BY \"id\" DESC";
-const UPDATE: &str = "UPDATE \"projects\" SET \"updated_at\" = ?, \"admin_id\" = ?, \"name\" = ? WHERE \"id\" = ?";
+const UPDATE: &str =
+    "UPDATE \"projects\" SET \"updated_at\" = ?, \"admin_id\" = ?, \"name\" = ? WHERE \"id\" = ?";
 const DELETE: &str = "DELETE FROM \"projects\" WHERE \"id\" = ?";
 
 pub async fn init(pool: &Pool<Sqlite>) {
@@ -18,6 +20,7 @@ pub async fn init(pool: &Pool<Sqlite>) {
     tokio::try_join!(
         pool.prepare(INSERT),
         pool.prepare(SELECT),
+        pool.prepare(SELECT_MANY),
         pool.prepare(SELECT_MANY_BY_ADMIN_ID),
         pool.prepare(UPDATE),
         pool.prepare(DELETE),
@@ -43,6 +46,10 @@ impl SqliteDb {
         Ok(self.fetch_one(sqlx::query_as(SELECT).bind(id)).await?)
     }
 
+    pub async fn select_many_projects(&self) -> Result<Vec<ProjectModel>> {
+        Ok(self.fetch_all(sqlx::query_as(SELECT_MANY)).await?)
+    }
+
     pub async fn select_many_projects_by_admin_id(
         &self,
         admin_id: &Uuid,
@@ -47,6 +47,10 @@ pub fn drop_columns(record_table: &str, column_names: &HashSet<String>) -> Strin
     )
 }
 
+pub fn rename_column(record_table: &str, column: &str, new_column: &str) -> String {
+    format!("ALTER TABLE \"{record_table}\" RENAME COLUMN \"{column}\" TO \"{new_column}\"")
+}
+
 pub fn change_columns_type(
     record_table: &str,
     columns: &HashMap<String, SchemaFieldPropsModel>,
@@ -71,9 +75,16 @@ pub fn create_index(record_table: &str, index: &str) -> String {
     )
 }
 
-pub fn create_unique_index(record_table: &str, index: &str) -> String {
+// The index is named after its columns, so a single column gets the same name as before
+pub fn create_unique_index(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
-        "CREATE UNIQUE INDEX IF NOT EXISTS \"{record_table}_{index}_unique\" ON \"{record_table}\" (\"{index}\")"
+        "CREATE UNIQUE INDEX IF NOT EXISTS \"{record_table}_{}_unique\" ON \"{record_table}\" ({})",
+        columns.join("_"),
+        columns
+            .iter()
+            .map(|col| format!("\"{col}\""))
+            .collect::<Vec<_>>()
+            .join(", ")
     )
 }
 
@@ -81,8 +92,11 @@ pub fn drop_index(record_table: &str, index: &str) -> String {
     format!("DROP INDEX IF EXISTS \"{record_table}_{index}\"")
 }
 
-pub fn drop_unique_index(record_table: &str, index: &str) -> String {
-    format!("DROP INDEX IF EXISTS \"{record_table}_{index}_unique\"")
+pub fn drop_unique_index(record_table: &str, columns: &Vec<&str>) -> String {
+    format!(
+        "DROP INDEX IF EXISTS \"{record_table}_{}_unique\"",
+        columns.join("_")
+    )
 }
 
 pub fn insert(record_table: &str, columns: &Vec<&str>) -> String {
@@ -99,6 +113,42 @@ pub fn insert(record_table: &str, columns: &Vec<&str>) -> String {
     format!("INSERT INTO \"{record_table}\" ({cols}) VALUES ({vals})")
 }
 
+pub fn insert_many(record_table: &str, columns: &Vec<&str>, rows: &usize) -> String {
+    let cols = columns.iter().map(|col| format!("\"{col}\"")).join(", ");
+    // Every row binds the same columns, so their placeholders are all alike
+    let vals = format!("({})", vec!["?"; columns.len()].join(", "));
+    format!(
+        "INSERT INTO \"{record_table}\" ({cols}) VALUES {}",
+        vec![vals.as_str(); *rows].join(", ")
+    )
+}
+
+pub fn copy(record_table: &str, source_table: &str, columns: &Vec<&str>) -> String {
+    let cols = columns.iter().map(|col| format!("\"{col}\"")).join(", ");
+    format!("INSERT INTO \"{record_table}\" ({cols}) SELECT {cols} FROM \"{source_table}\"")
+}
+
+// Copies the records that are neither soft-deleted nor expired, the cut-off times of the TTLs are
+// bound as in and_not_expired
+pub fn copy_visible(
+    record_table: &str,
+    source_table: &str,
+    columns: &Vec<&str>,
+    soft_delete: &bool,
+    ttl: &bool,
+    per_record_ttl: &bool,
+) -> String {
+    let mut conditions = not_expired_conditions(ttl, per_record_ttl);
+    if *soft_delete {
+        conditions.insert(0, "\"_deleted_at\" IS NULL".to_owned());
+    }
+    let mut query = copy(record_table, source_table, columns);
+    if !conditions.is_empty() {
+        query += &format!(" WHERE {}", conditions.join(" AND "));
+    }
+    query
+}
+
 pub fn select(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
         "SELECT {} FROM \"{}\" WHERE \"_id\" = ?",
@@ -111,12 +161,40 @@ pub fn select_by_id_and_created_by(record_table: &str, columns: &Vec<&str>) -> S
     select(record_table, columns) + " AND \"_created_by\" = ?"
 }
 
+pub fn select_not_deleted(record_table: &str, columns: &Vec<&str>) -> String {
+    select(record_table, columns) + " AND \"_deleted_at\" IS NULL"
+}
+
+pub fn select_not_deleted_by_id_and_created_by(record_table: &str, columns: &Vec<&str>) -> String {
+    select_not_deleted(record_table, columns) + " AND \"_created_by\" = ?"
+}
+
+// Leaves out the records that outlived the collection TTL or their own '_expired_at'. The cut-off
+// times are bound after the other arguments, the one of the collection TTL first.
+pub fn and_not_expired(ttl: &bool, per_record_ttl: &bool) -> String {
+    not_expired_conditions(ttl, per_record_ttl)
+        .iter()
+        .map(|condition| format!(" AND {condition}"))
+        .join("")
+}
+
+fn not_expired_conditions(ttl: &bool, per_record_ttl: &bool) -> Vec<String> {
+    let mut conditions = Vec::with_capacity(2);
+    if *ttl {
+        conditions.push("\"_updated_at\" >= ?".to_owned());
+    }
+    if *per_record_ttl {
+        conditions.push("(\"_expired_at\" IS NULL OR \"_expired_at\" >= ?)".to_owned());
+    }
+    conditions
+}
+
 pub fn select_many(
     record_table: &str,
     columns: &Vec<&str>,
     filter: &str,
     groups: &Vec<&str>,
-    orders: &Vec<(&str, &str)>,
+    orders: &Vec<(String, &str)>,
     with_query_limit: &bool,
 ) -> String {
     let mut query = format!(
@@ -154,7 +232,7 @@ pub fn select_many(
             if count > 0 {
                 query += ","
             }
-            query += &format!(" \"{field}\" {kind}");
+            query += &format!(" {field} {kind}");
             count += 1;
         }
     }
@@ -164,6 +242,23 @@ pub fn select_many(
     query
 }
 
+// SQLite is built without math functions, so the distance is approximated with an equirectangular
+// projection. The result is the squared distance in square meters and both scales (meters per
+// degree) are computed by the caller.
+pub fn squared_distance(
+    column: &str,
+    lat: &str,
+    lng: &str,
+    lat_scale: &str,
+    lng_scale: &str,
+) -> String {
+    let lat_delta =
+        format!("((json_extract(CAST(\"{column}\" AS TEXT), '$[0]') - {lat}) * {lat_scale})");
+    let lng_delta =
+        format!("((json_extract(CAST(\"{column}\" AS TEXT), '$[1]') - {lng}) * {lng_scale})");
+    format!("({lat_delta} * {lat_delta} + {lng_delta} * {lng_delta})")
+}
+
 pub fn update(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
         "UPDATE \"{}\" SET {} WHERE \"_id\" = ?",
@@ -175,6 +270,19 @@ pub fn update(record_table: &str, columns: &Vec<&str>) -> String {
     )
 }
 
+pub fn backfill_column(record_table: &str, column: &str) -> String {
+    format!("UPDATE \"{record_table}\" SET \"{column}\" = ? WHERE \"{column}\" IS NULL")
+}
+
+// SQLite serializes writers on the whole database, so the increment is applied atomically
+pub fn increment_counter(record_table: &str, column: &str) -> String {
+    format!("UPDATE \"{record_table}\" SET \"{column}\" = COALESCE(\"{column}\", 0) + ?, \"_updated_at\" = ? WHERE \"_id\" = ?")
+}
+
+pub fn increment_counter_by_created_by(record_table: &str, column: &str) -> String {
+    increment_counter(record_table, column) + " AND \"_created_by\" = ?"
+}
+
 pub fn delete(record_table: &str, columns: &Vec<&str>) -> String {
     format!(
         "DELETE FROM \"{}\" WHERE {}",
@@ -186,8 +294,35 @@ pub fn delete(record_table: &str, columns: &Vec<&str>) -> String {
     )
 }
 
+// Soft-deleted records keep their row and are left for the sweep of expired records to purge
+pub fn soft_delete(record_table: &str) -> String {
+    format!("UPDATE \"{record_table}\" SET \"_deleted_at\" = ?, \"_updated_at\" = ? WHERE \"_id\" = ? AND \"_deleted_at\" IS NULL")
+}
+
+pub fn soft_delete_by_created_by(record_table: &str) -> String {
+    soft_delete(record_table) + " AND \"_created_by\" = ?"
+}
+
+pub fn restore(record_table: &str) -> String {
+    format!("UPDATE \"{record_table}\" SET \"_deleted_at\" = NULL, \"_updated_at\" = ? WHERE \"_id\" = ? AND \"_deleted_at\" IS NOT NULL")
+}
+
+pub fn restore_by_created_by(record_table: &str) -> String {
+    restore(record_table) + " AND \"_created_by\" = ?"
+}
+
+pub fn purge_deleted(record_table: &str) -> String {
+    format!("DELETE FROM \"{record_table}\" WHERE \"_deleted_at\" IS NOT NULL")
+}
+
+// Deletes a limited batch of expired records so a sweep never holds the table for long
 pub fn delete_expired(record_table: &str) -> String {
-    format!("DELETE FROM \"{record_table}\" WHERE \"_updated_at\" < ?")
+    format!("DELETE FROM \"{record_table}\" WHERE \"_id\" IN (SELECT \"_id\" FROM \"{record_table}\" WHERE \"_updated_at\" < ? LIMIT ?)")
+}
+
+// Records of a collection with per-record TTL that have reached their own expiry time
+pub fn delete_expired_by_record(record_table: &str) -> String {
+    format!("DELETE FROM \"{record_table}\" WHERE \"_id\" IN (SELECT \"_id\" FROM \"{record_table}\" WHERE \"_expired_at\" < ? LIMIT ?)")
 }
 
 pub fn count(record_table: &str, filter: &str, groups: &Vec<&str>) -> String {
@@ -209,3 +344,13 @@ pub fn count(record_table: &str, filter: &str, groups: &Vec<&str>) -> String {
     query += ")";
     query
 }
+
+// Returns the number of rows, soft-deleted ones included, the lowest and highest _id, and the
+// size of the table together with its indexes, which needs SQLite built with the dbstat table
+pub fn stats(record_table: &str) -> String {
+    format!("SELECT COUNT(1), (SELECT \"_id\" FROM \"{record_table}\" ORDER BY \"_id\" ASC LIMIT 1), (SELECT \"_id\" FROM \"{record_table}\" ORDER BY \"_id\" DESC LIMIT 1), (SELECT SUM(\"pgsize\") FROM \"dbstat\" WHERE \"name\" IN (SELECT \"name\" FROM \"sqlite_master\" WHERE \"tbl_name\" = '{record_table}')) FROM \"{record_table}\"")
+}
+
+pub fn select_indexes(record_table: &str) -> String {
+    format!("SELECT \"name\" FROM \"sqlite_master\" WHERE \"type\" = 'index' AND \"tbl_name\" = '{record_table}' ORDER BY \"name\"")
+}
@@ -8,7 +8,8 @@ use crate::{db::SqliteDb, model::registration::RegistrationModel};
 const INSERT: &str = "INSERT INTO \"registrations\" (\"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"code\") VALUES (?, ?, ?, ?, ?, ?)";
 const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"code\" FROM \"registrations\" WHERE \"id\" = ? AND \"updated_at\" >= ?";
 const SELECT_BY_EMAIL: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"email\", \"password_hash\", \"code\" FROM \"registrations\" WHERE \"email\" = ? AND \"updated_at\" >= ?";
-const UPDATE: &str = "UPDATE \"registrations\" SET \"updated_at\" = ?, \"code\" = ? WHERE \"id\" = ?";
+const UPDATE: &str =
+    "UPDATE \"registrations\" SET \"updated_at\" = ?, \"code\" = ? WHERE \"id\" = ?";
 const DELETE: &str = "DELETE FROM \"registrations\" WHERE \"id\" = ?";
 const DELETE_EXPIRE: &str = "DELETE FROM \"registrations\" WHERE \"updated_at\" < ?";
 
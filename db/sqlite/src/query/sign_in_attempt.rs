@@ -0,0 +1,44 @@
+use anyhow::Result;
+use sqlx::{Executor, Pool, Sqlite};
+
+use crate::{db::SqliteDb, model::sign_in_attempt::SignInAttemptModel};
+
+const UPSERT: &str = "INSERT INTO \"sign_in_attempts\" (\"key\", \"failed_count\", \"locked_until\", \"updated_at\") VALUES (?, ?, ?, ?) ON CONFLICT (\"key\") DO UPDATE SET \"failed_count\" = excluded.\"failed_count\", \"locked_until\" = excluded.\"locked_until\", \"updated_at\" = excluded.\"updated_at\"";
+const SELECT: &str = "SELECT \"key\", \"failed_count\", \"locked_until\", \"updated_at\" FROM \"sign_in_attempts\" WHERE \"key\" = ?";
+const DELETE: &str = "DELETE FROM \"sign_in_attempts\" WHERE \"key\" = ?";
+
+pub async fn init(pool: &Pool<Sqlite>) {
+    hb_log::info(Some("🔧"), "[SQLite] Setting up sign_in_attempts table");
+
+    pool.execute("CREATE TABLE IF NOT EXISTS \"sign_in_attempts\" (\"key\" text, \"failed_count\" integer, \"locked_until\" timestamp, \"updated_at\" timestamp, PRIMARY KEY (\"key\"))").await.unwrap();
+
+    tokio::try_join!(
+        pool.prepare(UPSERT),
+        pool.prepare(SELECT),
+        pool.prepare(DELETE),
+    )
+    .unwrap();
+}
+
+impl SqliteDb {
+    pub async fn upsert_sign_in_attempt(&self, value: &SignInAttemptModel) -> Result<()> {
+        self.execute(
+            sqlx::query(UPSERT)
+                .bind(value.key())
+                .bind(value.failed_count())
+                .bind(value.locked_until())
+                .bind(value.updated_at()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn select_sign_in_attempt(&self, key: &str) -> Result<SignInAttemptModel> {
+        Ok(self.fetch_one(sqlx::query_as(SELECT).bind(key)).await?)
+    }
+
+    pub async fn delete_sign_in_attempt(&self, key: &str) -> Result<()> {
+        self.execute(sqlx::query(DELETE).bind(key)).await?;
+        Ok(())
+    }
+}
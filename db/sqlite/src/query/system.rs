@@ -1 +1,3 @@
-pub const COUNT_TABLE: &str = "SELECT COUNT(1) FROM \"sqlite_master\" WHERE \"type\" = 'table' AND \"name\" = ?";
+pub const COUNT_TABLE: &str =
+    "SELECT COUNT(1) FROM \"sqlite_master\" WHERE \"type\" = 'table' AND \"name\" = ?";
+pub const PING: &str = "SELECT 1";
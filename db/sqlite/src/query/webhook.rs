@@ -0,0 +1,97 @@
+use anyhow::Result;
+use sqlx::{Executor, Pool, Sqlite};
+use uuid::Uuid;
+
+use crate::{db::SqliteDb, model::webhook::WebhookModel};
+
+const INSERT: &str = "INSERT INTO \"webhooks\" (\"id\", \"created_at\", \"updated_at\", \"admin_id\", \"project_id\", \"collection_id\", \"url\", \"events\", \"secret\", \"enabled\") VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+const SELECT: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"project_id\", \"collection_id\", \"url\", \"events\", \"secret\", \"enabled\" FROM \"webhooks\" WHERE \"id\" = ?";
+const SELECT_MANY_BY_PROJECT_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"project_id\", \"collection_id\", \"url\", \"events\", \"secret\", \"enabled\" FROM \"webhooks\" WHERE \"project_id\" = ? ORDER BY \"id\" DESC";
+const SELECT_MANY_BY_COLLECTION_ID: &str = "SELECT \"id\", \"created_at\", \"updated_at\", \"admin_id\", \"project_id\", \"collection_id\", \"url\", \"events\", \"secret\", \"enabled\" FROM \"webhooks\" WHERE \"collection_id\" = ?";
+const UPDATE: &str = "UPDATE \"webhooks\" SET \"updated_at\" = ?, \"url\" = ?, \"events\" = ?, \"secret\" = ?, \"enabled\" = ? WHERE \"id\" = ?";
+const DELETE: &str = "DELETE FROM \"webhooks\" WHERE \"id\" = ?";
+const DELETE_MANY_BY_COLLECTION_ID: &str = "DELETE FROM \"webhooks\" WHERE \"collection_id\" = ?";
+
+pub async fn init(pool: &Pool<Sqlite>) {
+    hb_log::info(Some("🔧"), "[SQLite] Setting up webhooks table");
+
+    pool.execute("CREATE TABLE IF NOT EXISTS \"webhooks\" (\"id\" blob, \"created_at\" timestamp, \"updated_at\" timestamp, \"admin_id\" blob, \"project_id\" blob, \"collection_id\" blob, \"url\" text, \"events\" blob, \"secret\" text, \"enabled\" boolean, PRIMARY KEY (\"id\"))").await.unwrap();
+
+    tokio::try_join!(
+        pool.prepare(INSERT),
+        pool.prepare(SELECT),
+        pool.prepare(SELECT_MANY_BY_PROJECT_ID),
+        pool.prepare(SELECT_MANY_BY_COLLECTION_ID),
+        pool.prepare(UPDATE),
+        pool.prepare(DELETE),
+        pool.prepare(DELETE_MANY_BY_COLLECTION_ID),
+    )
+    .unwrap();
+}
+
+impl SqliteDb {
+    pub async fn insert_webhook(&self, value: &WebhookModel) -> Result<()> {
+        self.execute(
+            sqlx::query(INSERT)
+                .bind(value.id())
+                .bind(value.created_at())
+                .bind(value.updated_at())
+                .bind(value.admin_id())
+                .bind(value.project_id())
+                .bind(value.collection_id())
+                .bind(value.url())
+                .bind(value.events())
+                .bind(value.secret())
+                .bind(value.enabled()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn select_webhook(&self, id: &Uuid) -> Result<WebhookModel> {
+        Ok(self.fetch_one(sqlx::query_as(SELECT).bind(id)).await?)
+    }
+
+    pub async fn select_many_webhooks_by_project_id(
+        &self,
+        project_id: &Uuid,
+    ) -> Result<Vec<WebhookModel>> {
+        Ok(self
+            .fetch_all(sqlx::query_as(SELECT_MANY_BY_PROJECT_ID).bind(project_id))
+            .await?)
+    }
+
+    pub async fn select_many_webhooks_by_collection_id(
+        &self,
+        collection_id: &Uuid,
+    ) -> Result<Vec<WebhookModel>> {
+        Ok(self
+            .fetch_all(sqlx::query_as(SELECT_MANY_BY_COLLECTION_ID).bind(collection_id))
+            .await?)
+    }
+
+    pub async fn update_webhook(&self, value: &WebhookModel) -> Result<()> {
+        self.execute(
+            sqlx::query(UPDATE)
+                .bind(value.updated_at())
+                .bind(value.url())
+                .bind(value.events())
+                .bind(value.secret())
+                .bind(value.enabled())
+                .bind(value.id()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_webhook(&self, id: &Uuid) -> Result<()> {
+        self.execute(sqlx::query(DELETE).bind(id)).await?;
+        Ok(())
+    }
+
+    pub async fn delete_many_webhooks_by_collection_id(&self, collection_id: &Uuid) -> Result<()> {
+        self.execute(sqlx::query(DELETE_MANY_BY_COLLECTION_ID).bind(collection_id))
+            .await?;
+        Ok(())
+    }
+}
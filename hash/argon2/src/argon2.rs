@@ -1,15 +1,29 @@
+use std::time::{Duration, Instant};
+
 use argon2::{
-    password_hash::{self, SaltString},
+    password_hash::{self, rand_core::OsRng, SaltString},
     Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
 };
 
 pub struct Argon2Hash {
     argon2: Argon2<'static>,
-    salt: SaltString,
+    algorithm: Algorithm,
+    version: Version,
+    params: Params,
+    // Salt every password used to be hashed with, only kept to recognize those hashes
+    legacy_salt: SaltString,
 }
 
 impl Argon2Hash {
-    pub fn new(algorithm: &str, version: &str, salt: &str) -> Self {
+    pub fn new(
+        algorithm: &str,
+        version: &str,
+        salt: &str,
+        memory_kib: &u32,
+        iterations: &u32,
+        parallelism: &u32,
+        time_budget: &Option<Duration>,
+    ) -> Self {
         hb_log::info(Some("⚡"), "[Argon2Hash] Initializing component");
 
         let algorithm = match algorithm {
@@ -25,18 +39,127 @@ impl Argon2Hash {
             _ => panic!("Unknown argon2 version"),
         };
 
-        Self {
-            argon2: Argon2::new(algorithm, version, Params::DEFAULT),
-            salt: SaltString::from_b64(salt).unwrap(),
+        // Memory has to be at least 8 KiB per lane, and iterations and parallelism at least 1
+        let params = match Params::new(*memory_kib, *iterations, *parallelism, None) {
+            Ok(params) => params,
+            Err(err) => panic!("Invalid argon2 parameters: {err}"),
+        };
+
+        let hash = Self {
+            argon2: Argon2::new(algorithm, version, params.clone()),
+            algorithm,
+            version,
+            params,
+            legacy_salt: SaltString::from_b64(salt).unwrap(),
+        };
+
+        // Hashing a sample shows operators how long each sign-in spends on it with these parameters
+        let started_at = Instant::now();
+        if let Err(err) = hash.hash_password(b"hyperbase") {
+            panic!("Failed to hash with the argon2 parameters: {err}");
+        }
+        let elapsed = started_at.elapsed();
+        hb_log::info(
+            None,
+            &format!(
+                "[Argon2Hash] Hashing takes {elapsed:?} with {memory_kib} KiB of memory, {iterations} iterations and a parallelism of {parallelism}"
+            ),
+        );
+        if let Some(time_budget) = time_budget {
+            if elapsed > *time_budget {
+                hb_log::warn(
+                    None,
+                    &format!(
+                        "[Argon2Hash] Hashing takes longer than the time budget of {time_budget:?}, consider lowering the parameters"
+                    ),
+                );
+            }
         }
+
+        hash
     }
 
-    pub fn hash_password(&self, password: &[u8]) -> Result<PasswordHash<'_>, password_hash::Error> {
-        self.argon2.hash_password(password, &self.salt)
+    // Every password gets its own random salt, which is stored in the returned PHC string
+    pub fn hash_password(&self, password: &[u8]) -> Result<String, password_hash::Error> {
+        let salt = SaltString::generate(&mut OsRng);
+        Ok(self.argon2.hash_password(password, &salt)?.to_string())
     }
 
+    // The salt and parameters are read from the stored hash, so hashes made with the configured
+    // salt or other parameters keep verifying as they are
     pub fn verify_password(&self, password: &str, hash: &str) -> Result<(), password_hash::Error> {
         let hash = PasswordHash::new(hash)?;
         self.argon2.verify_password(password.as_bytes(), &hash)
     }
+
+    // Whether the hash was made with the configured salt, another algorithm or version, or weaker
+    // parameters than the current ones, and should be replaced once the password is known again
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        let hash = match PasswordHash::new(hash) {
+            Ok(hash) => hash,
+            Err(_) => return false,
+        };
+
+        if hash
+            .salt
+            .is_some_and(|salt| salt.as_str() == self.legacy_salt.as_str())
+        {
+            return true;
+        }
+
+        if hash.algorithm != self.algorithm.ident() || hash.version != Some(self.version.into()) {
+            return true;
+        }
+
+        match Params::try_from(&hash) {
+            Ok(params) => {
+                params.m_cost() < self.params.m_cost()
+                    || params.t_cost() < self.params.t_cost()
+                    || params.p_cost() < self.params.p_cost()
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The defaults and the time budget suggested in config.yml.example
+    const MEMORY_KIB: u32 = 19456;
+    const ITERATIONS: u32 = 2;
+    const PARALLELISM: u32 = 1;
+    const TIME_BUDGET: Duration = Duration::from_millis(500);
+
+    #[test]
+    fn hashing_stays_within_the_time_budget() {
+        let hash = Argon2Hash::new(
+            "Argon2id",
+            "V0x13",
+            "c29tZXNhbHQ",
+            &MEMORY_KIB,
+            &ITERATIONS,
+            &PARALLELISM,
+            &Some(TIME_BUDGET),
+        );
+
+        // The fastest of a few runs, so a busy machine doesn't fail it
+        let mut fastest = Duration::MAX;
+        let mut hashed = String::new();
+        for _ in 0..3 {
+            let started_at = Instant::now();
+            hashed = hash.hash_password(b"correct horse battery staple").unwrap();
+            fastest = fastest.min(started_at.elapsed());
+        }
+        assert!(
+            fastest <= TIME_BUDGET,
+            "Hashing took {fastest:?}, over the budget of {TIME_BUDGET:?}"
+        );
+
+        assert!(hash
+            .verify_password("correct horse battery staple", &hashed)
+            .is_ok());
+        assert!(!hash.needs_rehash(&hashed));
+    }
 }
@@ -0,0 +1,120 @@
+use anyhow::Result;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+// Waits for a component to stop and cancels the shared token so the other components follow.
+// Returns false if the component stopped because of an error or a panic.
+pub async fn watch(
+    name: &str,
+    handle: JoinHandle<Result<()>>,
+    cancel_token: &CancellationToken,
+) -> bool {
+    let ok = match handle.await {
+        Ok(Ok(_)) => {
+            hb_log::info(None, &format!("[Hyperbase] Component {name} stopped"));
+            true
+        }
+        Ok(Err(err)) => {
+            hb_log::error(
+                None,
+                &format!("[Hyperbase] Component {name} stopped with error: {err}"),
+            );
+            false
+        }
+        Err(err) => {
+            if err.is_panic() {
+                hb_log::error(
+                    None,
+                    &format!(
+                        "[Hyperbase] Component {name} panicked: {}",
+                        hb_log::panic_payload(err.into_panic().as_ref())
+                    ),
+                );
+            } else {
+                hb_log::error(
+                    None,
+                    &format!("[Hyperbase] Component {name} was cancelled: {err}"),
+                );
+            }
+            false
+        }
+    };
+
+    if !cancel_token.is_cancelled() {
+        hb_log::warn(None, "[Hyperbase] Shutting down all running components");
+        cancel_token.cancel();
+    }
+
+    ok
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::Error;
+
+    use super::*;
+
+    // Stands in for a component that keeps running until it's told to stop
+    fn running(cancel_token: &CancellationToken) -> JoinHandle<Result<()>> {
+        let cancel_token = cancel_token.clone();
+        tokio::spawn(async move {
+            cancel_token.cancelled().await;
+            Ok(())
+        })
+    }
+
+    async fn watch_all(
+        stopping: JoinHandle<Result<()>>,
+        cancel_token: &CancellationToken,
+    ) -> [bool; 3] {
+        let results = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::join!(
+                watch("Before", running(cancel_token), cancel_token),
+                watch("Stopping", stopping, cancel_token),
+                watch("After", running(cancel_token), cancel_token),
+            )
+        })
+        .await
+        .expect("the other components kept running");
+        [results.0, results.1, results.2]
+    }
+
+    #[tokio::test]
+    async fn component_error_shuts_everything_down() {
+        let cancel_token = CancellationToken::new();
+        let failing: JoinHandle<Result<()>> = tokio::spawn(async { Err(Error::msg("failed")) });
+
+        assert_eq!(watch_all(failing, &cancel_token).await, [true, false, true]);
+        assert!(cancel_token.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn component_panic_shuts_everything_down() {
+        let cancel_token = CancellationToken::new();
+        let failing: JoinHandle<Result<()>> = tokio::spawn(async { panic!("killed") });
+
+        assert_eq!(watch_all(failing, &cancel_token).await, [true, false, true]);
+        assert!(cancel_token.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn component_stopping_cleanly_shuts_everything_down() {
+        let cancel_token = CancellationToken::new();
+        let stopped: JoinHandle<Result<()>> = tokio::spawn(async { Ok(()) });
+
+        assert_eq!(watch_all(stopped, &cancel_token).await, [true, true, true]);
+        assert!(cancel_token.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn aborted_component_shuts_everything_down() {
+        let cancel_token = CancellationToken::new();
+        let aborted = running(&CancellationToken::new());
+        aborted.abort();
+
+        assert_eq!(watch_all(aborted, &cancel_token).await, [true, false, true]);
+        assert!(cancel_token.is_cancelled());
+    }
+}
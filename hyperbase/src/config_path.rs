@@ -1,14 +1,18 @@
 use std::{fs, path::PathBuf, str::FromStr};
 
-pub fn get() -> PathBuf {
-    let config_path = match std::env::var("HB_CONFIG_PATH") {
-        Ok(path) => path,
-        Err(_) => "config.yml".to_owned(),
-    };
-
-    if let Err(_) = fs::metadata(&config_path) {
-        panic!("config.yml file specified in HB_CONFIG_PATH environment variable or current directory must exist")
+// Without HB_CONFIG_PATH, a missing config.yml in the current directory is fine as long as the
+// environment variables give the whole configuration
+pub fn get() -> Option<PathBuf> {
+    match std::env::var("HB_CONFIG_PATH") {
+        Ok(config_path) => {
+            if let Err(_) = fs::metadata(&config_path) {
+                panic!("config file specified in HB_CONFIG_PATH environment variable must exist")
+            }
+            Some(PathBuf::from_str(&config_path).unwrap())
+        }
+        Err(_) => match fs::metadata("config.yml") {
+            Ok(_) => Some(PathBuf::from_str("config.yml").unwrap()),
+            Err(_) => None,
+        },
     }
-
-    PathBuf::from_str(&config_path).unwrap()
 }
@@ -0,0 +1,163 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Result;
+use hb_api_rest::context::ConfigReloadReply;
+use hb_config::{mailer::MailerConfig, Config};
+use hb_mailer::{http::HttpTransport, MailerTransport};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::{mpsc, watch},
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+
+// Keys that take effect without a restart. The mailer ones only do when the mailer was already
+// configured on startup. The auth TTLs aren't among them, the databases take them on startup.
+const RELOADABLE_KEYS: [&str; 2] = ["log.level_filter", "api.rest.allowed_origin"];
+const RELOADABLE_MAILER_PREFIXES: [&str; 2] = ["mailer.smtp_", "mailer.http."];
+
+// Reads the config file again on SIGHUP or when asked through the REST API, and hands the
+// settings that can change at runtime to the components using them. Every other key that
+// changed is only logged, it keeps its old value until the next restart.
+pub struct ConfigReload {
+    config_path: Option<PathBuf>,
+    config: Config,
+    allowed_origin: watch::Sender<Option<String>>,
+    mailer_transport: Option<watch::Sender<MailerTransport>>,
+    channel_receiver: mpsc::Receiver<ConfigReloadReply>,
+}
+
+impl ConfigReload {
+    pub fn new(
+        config_path: Option<PathBuf>,
+        config: Config,
+        allowed_origin: watch::Sender<Option<String>>,
+        mailer_transport: Option<watch::Sender<MailerTransport>>,
+        channel_receiver: mpsc::Receiver<ConfigReloadReply>,
+    ) -> Self {
+        hb_log::info(Some("⚡"), "[ConfigReload] Initializing component");
+
+        Self {
+            config_path,
+            config,
+            allowed_origin,
+            mailer_transport,
+            channel_receiver,
+        }
+    }
+
+    pub fn run(mut self, cancel_token: CancellationToken) -> JoinHandle<Result<()>> {
+        hb_log::info(Some("💫"), "[ConfigReload] Running component");
+
+        tokio::spawn((|| async move {
+            let mut hangup = signal(SignalKind::hangup())?;
+
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        break;
+                    }
+                    _ = tokio::signal::ctrl_c() => {
+                        break;
+                    }
+                    _ = hangup.recv() => {
+                        let _ = self.reload();
+                    }
+                    recv = self.channel_receiver.recv() => {
+                        match recv {
+                            Some(reply) => {
+                                let _ = reply.send(self.reload());
+                            }
+                            None => {
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+
+            hb_log::info(None, "[ConfigReload] Shutting down component");
+
+            Ok(())
+        })())
+    }
+
+    // Nothing is applied when the new config has a problem, the old one stays in use as a whole
+    fn reload(&mut self) -> Result<Vec<String>, Vec<String>> {
+        hb_log::info(Some("🔄"), "[ConfigReload] Reloading the config");
+
+        let config = match hb_config::load(&self.config_path.as_deref()) {
+            Ok(config) => config,
+            Err(problems) => {
+                for problem in &problems {
+                    hb_log::error(
+                        None,
+                        &format!("[ConfigReload] Config not reloaded: {problem}"),
+                    );
+                }
+                return Err(problems);
+            }
+        };
+
+        if let Err(err) = hb_log::set_level_filter(config.log().level_filter()) {
+            hb_log::error(
+                None,
+                &format!("[ConfigReload] Failed to change the log level filter: {err}"),
+            );
+        }
+        self.allowed_origin
+            .send_replace(config.api().rest().allowed_origin().to_owned());
+        let mailer_reloadable = match (&self.mailer_transport, config.mailer()) {
+            (Some(transport_sender), Some(config_mailer)) => {
+                transport_sender.send_replace(mailer_transport_from(config_mailer));
+                true
+            }
+            _ => false,
+        };
+
+        let restart_required = config
+            .changed_keys(&self.config)
+            .into_iter()
+            .filter(|key| {
+                !RELOADABLE_KEYS.contains(&key.as_str())
+                    && !(mailer_reloadable
+                        && RELOADABLE_MAILER_PREFIXES
+                            .iter()
+                            .any(|prefix| key.starts_with(prefix)))
+            })
+            .collect::<Vec<_>>();
+        for key in &restart_required {
+            hb_log::warn(
+                None,
+                &format!("[ConfigReload] {key} changed, the change requires restart"),
+            );
+        }
+
+        self.config = config;
+
+        hb_log::info(Some("🔄"), "[ConfigReload] Config reloaded");
+
+        Ok(restart_required)
+    }
+}
+
+// Both the smtp_host and the http settings being left out is ruled out when the config is loaded
+pub fn mailer_transport_from(config_mailer: &MailerConfig) -> MailerTransport {
+    match (config_mailer.http(), config_mailer.smtp_host()) {
+        (Some(http), _) => MailerTransport::Http(HttpTransport::new(
+            http.preset(),
+            http.url(),
+            http.api_key(),
+            http.headers(),
+            &http.timeout().unwrap_or(Duration::from_secs(10)),
+        )),
+        (None, Some(smtp_host)) => MailerTransport::smtp(
+            smtp_host,
+            config_mailer.smtp_port(),
+            config_mailer.smtp_security(),
+            config_mailer.smtp_username(),
+            config_mailer.smtp_password(),
+        ),
+        (None, None) => unreachable!(),
+    }
+}
@@ -1,35 +1,87 @@
-use std::sync::Arc;
+use std::{
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
 
+use config_reload::{mailer_transport_from, ConfigReload};
 use hb_api_mqtt::{
     context::{ApiMqttCtx, ApiMqttDaoCtx, ApiMqttWsCtx},
-    ApiMqttClient,
+    topic::MqttTopic,
+    ApiMqttClient, ApiMqttPublish,
 };
 use hb_api_rest::{
+    backup::Backup,
     context::{
         ApiRestCtx, ApiRestDaoCtx, ApiRestHashCtx, ApiRestMailerCtx, ApiRestTokenCtx, ApiRestWsCtx,
-        MqttAdminCredential,
+        MqttAdminCredential, PasswordPolicy,
     },
     ApiRestServer,
 };
 use hb_api_websocket::{context::ApiWebSocketCtx, ApiWebSocketServer};
-use hb_dao::Db;
+use hb_config::app::AppConfigMode;
+use hb_dao::{file::FileDao, token_usage::TokenUsage, warmup::Warmup, Db};
 use hb_db_mysql::db::MysqlDb;
 use hb_db_postgresql::db::PostgresDb;
 use hb_db_scylladb::db::ScyllaDb;
 use hb_db_sqlite::db::SqliteDb;
 use hb_hash_argon2::argon2::Argon2Hash;
 use hb_mailer::Mailer;
+use hb_sweeper::Sweeper;
 use hb_token_jwt::token::JwtToken;
+use hb_token_totp::token::TotpToken;
+use hb_webhook::Webhook;
+use tokio::sync::{mpsc, watch};
 use tokio_util::sync::CancellationToken;
 
+mod component;
 mod config_path;
+mod config_reload;
 
 #[tokio::main]
 async fn main() {
+    // Only validates the config, without starting anything
+    let check_config = std::env::args().skip(1).any(|arg| arg == "--check-config");
+
     let config_path = config_path::get();
-    let config = hb_config::from_path(&config_path);
+    let config = match hb_config::load(&config_path.as_deref()) {
+        Ok(config) => config,
+        Err(problems) => {
+            eprintln!("Invalid configuration:");
+            for problem in &problems {
+                eprintln!("  - {problem}");
+            }
+            std::process::exit(1);
+        }
+    };
+
+    if check_config {
+        println!("Configuration is valid");
+        return;
+    }
+
+    hb_log::init(
+        config.log().display_level(),
+        config.log().level_filter(),
+        config.log().otlp().as_ref().map(|otlp| {
+            (
+                otlp.endpoint(),
+                otlp.service_name().as_deref().unwrap_or("hyperbase"),
+            )
+        }),
+    );
+    hb_log::set_panic_hook();
 
-    hb_log::init(config.log().display_level(), config.log().level_filter());
+    for source in config.sources() {
+        hb_log::debug(
+            None,
+            &format!(
+                "[Hyperbase] Config {} = {} (from {})",
+                source.key(),
+                source.value(),
+                source.origin().to_str()
+            ),
+        );
+    }
 
     hb_log::info(Some("🚀"), "[Hyperbase] Starting");
 
@@ -37,39 +89,95 @@ async fn main() {
         config.hash().argon2().algorithm(),
         config.hash().argon2().version(),
         config.hash().argon2().salt(),
+        &config.hash().argon2().memory_kib().unwrap_or(19456),
+        &config.hash().argon2().iterations().unwrap_or(2),
+        &config.hash().argon2().parallelism().unwrap_or(1),
+        config.hash().argon2().time_budget(),
     );
 
-    let jwt_token = JwtToken::new(
+    let jwt_token = Arc::new(JwtToken::new(
         config.token().jwt().secret(),
         config.token().jwt().expiry_duration(),
-    );
+    ));
+
+    let totp_token = match config.token().totp() {
+        Some(config_totp) => Some(TotpToken::new(config_totp.issuer(), config_totp.secret())),
+        None => None,
+    };
 
-    let (mailer, mailer_sender) = match config.mailer() {
+    let (mailer, mailer_sender, mailer_transport_sender) = match config.mailer() {
         Some(config_mailer) => {
+            let (transport_sender, transport_receiver) =
+                watch::channel(mailer_transport_from(config_mailer));
             let (mailer, mailer_sender) = Mailer::new(
-                config_mailer.smtp_host(),
-                config_mailer.smtp_username(),
-                config_mailer.smtp_password(),
+                transport_receiver,
                 config_mailer.sender_name(),
                 config_mailer.sender_email(),
+                &config_mailer.channel_capacity().unwrap_or(32),
+                &config_mailer.max_retries().unwrap_or(3),
+                &config_mailer
+                    .retry_interval()
+                    .unwrap_or(Duration::from_secs(1)),
+                config_mailer.dead_letter_path(),
             );
-            (Some(mailer), Some(mailer_sender))
+            (Some(mailer), Some(mailer_sender), Some(transport_sender))
         }
-        None => (None, None),
+        None => (None, None, None),
     };
 
+    let idempotency_ttl = config.api().rest().idempotency_ttl().unwrap_or(86400);
+
+    // Lookups are cached unless turned off
+    let (cache_enabled, cache_ttl, cache_capacity) = match config.db().cache() {
+        Some(config_cache) => (
+            config_cache.enabled().unwrap_or(true),
+            config_cache.ttl().unwrap_or(Duration::from_secs(2)),
+            config_cache.capacity().unwrap_or(10000),
+        ),
+        None => (true, Duration::from_secs(2), 10000),
+    };
+    if cache_enabled {
+        hb_dao::cache::init(&cache_ttl, &cache_capacity);
+    }
+
+    let statement_timeout = config
+        .db()
+        .statement_timeout_ms()
+        .map(Duration::from_millis);
+    let slow_query_threshold = config
+        .db()
+        .slow_query_threshold_ms()
+        .map(Duration::from_millis);
     let db = if let Some(scylla) = config.db().scylla() {
+        let hosts = match (scylla.hosts(), scylla.host()) {
+            (Some(hosts), _) if !hosts.is_empty() => hosts.clone(),
+            (_, Some(host)) => Vec::from([host.clone()]),
+            _ => {
+                hb_log::panic(
+                    None,
+                    "[Hyperbase] ScyllaDB configuration needs either host or hosts",
+                );
+                return;
+            }
+        };
         Arc::new(Db::ScyllaDb(
             ScyllaDb::new(
                 scylla.user(),
                 scylla.password(),
-                scylla.host(),
+                &hosts,
                 scylla.port(),
+                scylla.keyspace().as_deref().unwrap_or("hyperbase"),
                 scylla.replication_factor(),
+                scylla.datacenter_replication_factors(),
                 scylla.prepared_statement_cache_size(),
+                scylla.local_datacenter(),
+                scylla.consistency(),
+                &statement_timeout,
+                &slow_query_threshold,
                 config.auth().registration_ttl(),
                 config.auth().reset_password_ttl(),
                 config.log().db_ttl(),
+                &idempotency_ttl,
             )
             .await,
         ))
@@ -82,9 +190,20 @@ async fn main() {
                 postgres.port(),
                 postgres.db_name(),
                 postgres.max_connections(),
+                postgres.min_connections(),
+                postgres.acquire_timeout(),
+                postgres.idle_timeout(),
+                postgres.max_lifetime(),
+                postgres.ssl_mode(),
+                postgres.ssl_root_cert(),
+                postgres.ssl_client_cert(),
+                postgres.ssl_client_key(),
+                &statement_timeout,
+                &slow_query_threshold,
                 &i64::from(*config.auth().registration_ttl()),
                 &i64::from(*config.auth().reset_password_ttl()),
                 &i64::from(*config.log().db_ttl()),
+                &i64::from(idempotency_ttl),
             )
             .await,
         ))
@@ -97,9 +216,20 @@ async fn main() {
                 mysql.port(),
                 mysql.db_name(),
                 mysql.max_connections(),
+                mysql.min_connections(),
+                mysql.acquire_timeout(),
+                mysql.idle_timeout(),
+                mysql.max_lifetime(),
+                mysql.ssl_mode(),
+                mysql.ssl_root_cert(),
+                mysql.ssl_client_cert(),
+                mysql.ssl_client_key(),
+                &statement_timeout,
+                &slow_query_threshold,
                 &i64::from(*config.auth().registration_ttl()),
                 &i64::from(*config.auth().reset_password_ttl()),
                 &i64::from(*config.log().db_ttl()),
+                &i64::from(idempotency_ttl),
             )
             .await,
         ))
@@ -108,9 +238,20 @@ async fn main() {
             SqliteDb::new(
                 sqlite.path(),
                 sqlite.max_connections(),
+                sqlite.min_connections(),
+                sqlite.acquire_timeout(),
+                sqlite.idle_timeout(),
+                sqlite.max_lifetime(),
+                sqlite.journal_mode(),
+                sqlite.busy_timeout_ms(),
+                sqlite.synchronous(),
+                sqlite.foreign_keys(),
+                &statement_timeout,
+                &slow_query_threshold,
                 &i64::from(*config.auth().registration_ttl()),
                 &i64::from(*config.auth().reset_password_ttl()),
                 &i64::from(*config.log().db_ttl()),
+                &i64::from(idempotency_ttl),
             )
             .await,
         ))
@@ -119,81 +260,331 @@ async fn main() {
         return;
     };
 
+    // Warm-up runs alongside the startup of the other components
+    let warmup = match config.db().warmup() {
+        Some(config_warmup) => {
+            let warmup = Arc::new(Warmup::new(
+                config_warmup.prepare_limit(),
+                config_warmup.readiness_timeout(),
+            ));
+            tokio::spawn({
+                let warmup = warmup.clone();
+                let db = db.clone();
+                (|| async move {
+                    hb_log::info(Some("🔥"), "[Hyperbase] Warming up");
+                    match warmup.run(&db).await {
+                        Ok(_) => hb_log::info(
+                            Some("🔥"),
+                            &format!(
+                                "[Hyperbase] Warm-up finished: {} collection(s) loaded, statements prepared for {} of {} record table(s)",
+                                warmup.collections_loaded(),
+                                warmup.tables_prepared(),
+                                warmup.tables_total()
+                            ),
+                        ),
+                        Err(err) => hb_log::warn(
+                            None,
+                            &format!("[Hyperbase] Warm-up failed: {err}"),
+                        ),
+                    }
+                })()
+            });
+            Some(warmup)
+        }
+        None => None,
+    };
+
+    match FileDao::clean_partial_writes(config.bucket().path(), &Duration::ZERO).await {
+        Ok(count) => {
+            if count > 0 {
+                hb_log::warn(
+                    None,
+                    &format!("[Hyperbase] Removed {count} partially written file(s) from the bucket storage"),
+                );
+            }
+        }
+        Err(err) => hb_log::warn(
+            None,
+            &format!("[Hyperbase] Failed to clean partially written files from the bucket storage: {err}"),
+        ),
+    }
+
+    let (webhook, webhook_sender) = match config.webhook() {
+        Some(config_webhook) => {
+            let (webhook, webhook_sender) = Webhook::new(
+                db.clone(),
+                config_webhook.channel_capacity(),
+                config_webhook.timeout(),
+                config_webhook.max_retries(),
+                config_webhook.retry_interval(),
+            );
+            (Some(webhook), Some(webhook_sender))
+        }
+        None => (None, None),
+    };
+
+    let (mqtt_publish, mqtt_publish_sender) = match config.api().mqtt() {
+        Some(config_mqtt) => match config_mqtt.publish() {
+            Some(config_publish) => {
+                let (mqtt_publish, mqtt_publish_sender) = ApiMqttPublish::new(
+                    config_publish
+                        .topic_prefix()
+                        .as_deref()
+                        .unwrap_or("hyperbase/out"),
+                    &config_publish.qos().unwrap_or(1),
+                    config_mqtt.channel_capacity(),
+                );
+                (Some(mqtt_publish), Some(mqtt_publish_sender))
+            }
+            None => (None, None),
+        },
+        None => (None, None),
+    };
+
+    let token_usage = Arc::new(TokenUsage::new());
+
+    // Shared with the REST API, which can start a run on demand
+    let backup = config.backup().as_ref().map(|config_backup| {
+        Arc::new(Backup::new(
+            db.clone(),
+            config_backup.path(),
+            config_backup.interval(),
+            config_backup.retention(),
+            mailer_sender.clone(),
+            config_backup.notify_email(),
+        ))
+    });
+
+    let upload_session_ttl = i64::from(config.bucket().upload_session_ttl().unwrap_or(86400));
+    let sweeper = match config.db().option() {
+        Some(config_option) => Sweeper::new(
+            db.clone(),
+            config.bucket().path(),
+            token_usage.clone(),
+            config_option.ttl_sweep_interval(),
+            config_option.ttl_sweep_batch_size(),
+            &upload_session_ttl,
+        ),
+        None => Sweeper::new(
+            db.clone(),
+            config.bucket().path(),
+            token_usage.clone(),
+            &Duration::from_secs(60),
+            &1000,
+            &upload_session_ttl,
+        ),
+    };
+
     let (api_websocket_server, websocket_handler, websocket_publisher) = ApiWebSocketServer::new(
-        ApiWebSocketCtx::new(db.clone()),
+        ApiWebSocketCtx::new(
+            db.clone(),
+            jwt_token.clone(),
+            webhook_sender,
+            mqtt_publish_sender,
+        ),
         config.api().websocket().heartbeat_interval(),
         config.api().websocket().client_timeout(),
+        &config.api().websocket().replay_buffer_size().unwrap_or(100),
+        &config
+            .api()
+            .websocket()
+            .snapshot_max_records()
+            .unwrap_or(1000),
+        &config.api().websocket().send_buffer_size().unwrap_or(1024),
     );
 
+    let mqtt_topic = match config.api().mqtt() {
+        Some(config_mqtt) => {
+            match MqttTopic::new(config_mqtt.topic(), config_mqtt.shared_subscription_group()) {
+                Ok(mqtt_topic) => Some(mqtt_topic),
+                Err(err) => {
+                    hb_log::panic(None, &format!("[Hyperbase] Invalid MQTT topic: {err}"));
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+
+    // Shared with the REST health checks, which report whether the MQTT client is connected
+    let mqtt_connected = mqtt_topic
+        .as_ref()
+        .map(|_| Arc::new(AtomicBool::new(false)));
+
+    // Shared through a channel so a config reload can change it while running
+    let (allowed_origin_sender, allowed_origin_receiver) =
+        watch::channel(config.api().rest().allowed_origin().to_owned());
+    let (config_reload_sender, config_reload_receiver) = mpsc::channel(1);
+
     let api_rest_server = ApiRestServer::new(
         config.app().mode(),
         config.api().rest().host(),
         config.api().rest().port(),
-        config.api().rest().allowed_origin(),
+        allowed_origin_receiver.clone(),
+        config
+            .api()
+            .rest()
+            .trusted_proxies()
+            .as_ref()
+            .unwrap_or(&Vec::new()),
+        config.api().rest().tls(),
         ApiRestCtx::new(
             ApiRestHashCtx::new(argon2_hash),
-            ApiRestTokenCtx::new(jwt_token),
+            ApiRestTokenCtx::new(jwt_token, totp_token),
             match mailer_sender {
                 Some(mailer_sender) => Some(ApiRestMailerCtx::new(mailer_sender)),
                 None => None,
             },
-            ApiRestDaoCtx::new(db.clone()),
-            ApiRestWsCtx::new(websocket_handler),
-            match config.api().mqtt() {
-                Some(config_mqtt) => Some(MqttAdminCredential::new(
+            ApiRestDaoCtx::new(db.clone(), token_usage.clone()),
+            ApiRestWsCtx::new(
+                websocket_handler,
+                matches!(config.app().mode(), AppConfigMode::Production)
+                    && config.api().websocket().check_origin().unwrap_or(true),
+                allowed_origin_receiver,
+            ),
+            match (config.api().mqtt(), &mqtt_topic) {
+                (Some(config_mqtt), Some(mqtt_topic)) => Some(MqttAdminCredential::new(
                     config_mqtt.username(),
                     config_mqtt.password(),
-                    config_mqtt.topic(),
+                    mqtt_topic.subscription(),
                 )),
-                None => None,
+                _ => None,
             },
             *config.auth().admin_registration(),
             *config.auth().access_token_length(),
             *config.auth().registration_ttl(),
             *config.auth().reset_password_ttl(),
+            config.auth().sign_in_max_attempts().unwrap_or(5),
+            config.auth().sign_in_lockout_duration().unwrap_or(900),
+            match config.auth().password_policy() {
+                Some(config_password_policy) => PasswordPolicy::new(
+                    &config_password_policy.min_length().unwrap_or(8),
+                    &config_password_policy.require_lowercase().unwrap_or(false),
+                    &config_password_policy.require_uppercase().unwrap_or(false),
+                    &config_password_policy.require_digit().unwrap_or(false),
+                    &config_password_policy.require_symbol().unwrap_or(false),
+                ),
+                None => PasswordPolicy::new(&8, &false, &false, &false, &false),
+            },
             config.bucket().path().to_owned(),
+            warmup,
+            config.api().rest().import_batch_size().unwrap_or(500),
+            config.bucket().presign_max_expiry().unwrap_or(3600),
+            config.bucket().image_resize_max_dimension().unwrap_or(4096),
+            config.app().read_only().unwrap_or(false),
+            mqtt_connected.clone(),
+            backup.clone(),
+            config_reload_sender,
+            config
+                .auth()
+                .operator_admin_ids()
+                .to_owned()
+                .unwrap_or_default(),
         ),
     );
 
-    let api_mqtt_client = match config.api().mqtt() {
-        Some(config_mqtt) => Some(ApiMqttClient::new(
+    let api_mqtt_client = match (config.api().mqtt(), mqtt_topic, mqtt_connected) {
+        (Some(config_mqtt), Some(mqtt_topic), Some(mqtt_connected)) => Some(ApiMqttClient::new(
             config_mqtt.host(),
             config_mqtt.port(),
-            config_mqtt.topic(),
+            mqtt_topic,
             config_mqtt.username(),
             config_mqtt.password(),
             config_mqtt.channel_capacity(),
             config_mqtt.timeout(),
+            config_mqtt.reconnect_max_retries(),
+            &config_mqtt
+                .reconnect_max_interval()
+                .unwrap_or(Duration::from_secs(60)),
+            &config_mqtt.max_batch_items().unwrap_or(100),
+            mqtt_connected,
+            mqtt_publish,
             ApiMqttCtx::new(
-                ApiMqttDaoCtx::new(db),
+                ApiMqttDaoCtx::new(db, token_usage),
                 ApiMqttWsCtx::new(websocket_publisher),
+                config.app().read_only().unwrap_or(false),
             ),
         )),
-        None => None,
+        _ => None,
     };
 
+    // Takes the config over, as the one the next reload is compared with
+    let config_reload = ConfigReload::new(
+        config_path,
+        config,
+        allowed_origin_sender,
+        mailer_transport_sender,
+        config_reload_receiver,
+    );
+
     let cancel_token = CancellationToken::new();
 
-    match tokio::try_join!(
-        match mailer {
-            Some(mailer) => mailer.run(cancel_token.clone()),
-            None => Mailer::run_none(),
-        },
-        api_rest_server.run(cancel_token.clone()),
-        match api_mqtt_client {
-            Some(api_mqtt_client) => api_mqtt_client.run(cancel_token.clone()),
-            None => ApiMqttClient::run_none(),
-        },
-        api_websocket_server.run(cancel_token.clone())
-    ) {
-        Ok(_) => hb_log::info(Some("👋"), "[Hyperbase] Turned off"),
-        Err(err) => {
-            hb_log::warn(None, "[Hyperbase] Shutting down all running components");
-            cancel_token.cancel();
-            hb_log::warn(
-                Some("👋"),
-                format!("[Hyperbase] Turned off with error: {err}"),
-            );
-        }
+    // Any component stopping, for whatever reason, brings the rest down with it
+    let results = tokio::join!(
+        component::watch(
+            "Mailer",
+            match mailer {
+                Some(mailer) => mailer.run(cancel_token.clone()),
+                None => Mailer::run_none(cancel_token.clone()),
+            },
+            &cancel_token,
+        ),
+        component::watch(
+            "Webhook",
+            match webhook {
+                Some(webhook) => webhook.run(cancel_token.clone()),
+                None => Webhook::run_none(cancel_token.clone()),
+            },
+            &cancel_token,
+        ),
+        component::watch("Sweeper", sweeper.run(cancel_token.clone()), &cancel_token),
+        component::watch(
+            "Backup",
+            match backup {
+                Some(backup) => backup.run(cancel_token.clone()),
+                None => Backup::run_none(cancel_token.clone()),
+            },
+            &cancel_token,
+        ),
+        component::watch(
+            "ApiRestServer",
+            api_rest_server.run(cancel_token.clone()),
+            &cancel_token,
+        ),
+        component::watch(
+            "ApiMqttClient",
+            match api_mqtt_client {
+                Some(api_mqtt_client) => api_mqtt_client.run(cancel_token.clone()),
+                None => ApiMqttClient::run_none(cancel_token.clone()),
+            },
+            &cancel_token,
+        ),
+        component::watch(
+            "ApiWebSocketServer",
+            api_websocket_server.run(cancel_token.clone()),
+            &cancel_token,
+        ),
+        component::watch(
+            "ConfigReload",
+            config_reload.run(cancel_token.clone()),
+            &cancel_token,
+        ),
+    );
+
+    if results.0
+        && results.1
+        && results.2
+        && results.3
+        && results.4
+        && results.5
+        && results.6
+        && results.7
+    {
+        hb_log::info(Some("👋"), "[Hyperbase] Turned off");
+        hb_log::shutdown();
+    } else {
+        hb_log::warn(Some("👋"), "[Hyperbase] Turned off with error");
+        hb_log::shutdown();
+        std::process::exit(1);
     }
 }
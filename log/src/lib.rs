@@ -1,18 +1,173 @@
-use std::{fmt::Display, str::FromStr};
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt::Display,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
 
 use backtrace::Backtrace;
-use tracing::{debug, error, info, level_filters::LevelFilter, trace, warn};
+use opentelemetry::{global, trace::TracerProvider, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{propagation::TraceContextPropagator, runtime, trace::Config, Resource};
+use tracing::{
+    debug, error, field::Empty, info, info_span, level_filters::LevelFilter, trace, warn, Span,
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    filter::{filter_fn, FilterExt},
+    fmt,
+    layer::SubscriberExt,
+    reload,
+    util::SubscriberInitExt,
+    Layer, Registry,
+};
 
-pub fn init(display_level: &bool, level_filter: &str) {
+static LEVEL_FILTER: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+static TRACING: AtomicBool = AtomicBool::new(false);
+
+// Spans only ever reach the OpenTelemetry layer, so the log lines read the same with or without
+// it. Without an endpoint nothing is interested in spans, and creating one costs next to nothing.
+pub fn init(display_level: &bool, level_filter: &str, otlp: Option<(&str, &str)>) {
     let level_filter = match LevelFilter::from_str(level_filter) {
         Ok(level) => level,
         Err(err) => panic!("{err}"),
     };
 
-    tracing_subscriber::fmt()
-        .with_level(*display_level)
-        .with_max_level(level_filter)
+    let otel_layer =
+        otlp.map(|(endpoint, service_name)| {
+            let tracer_provider =
+                match opentelemetry_otlp::new_pipeline()
+                    .tracing()
+                    .with_exporter(
+                        opentelemetry_otlp::new_exporter()
+                            .tonic()
+                            .with_endpoint(endpoint),
+                    )
+                    .with_trace_config(Config::default().with_resource(Resource::new([
+                        KeyValue::new("service.name", service_name.to_owned()),
+                    ])))
+                    .install_batch(runtime::Tokio)
+                {
+                    Ok(tracer_provider) => tracer_provider,
+                    Err(err) => panic!("Failed to set up the OTLP exporter: {err}"),
+                };
+            global::set_text_map_propagator(TraceContextPropagator::new());
+            global::set_tracer_provider(tracer_provider.clone());
+            TRACING.store(true, Ordering::Relaxed);
+
+            // Messages logged inside a span are attached to it as events, whatever the level filter
+            // of the log lines is
+            tracing_opentelemetry::layer()
+                .with_tracer(tracer_provider.tracer("hyperbase"))
+                .with_filter(LevelFilter::INFO)
+        });
+
+    // The level filter can be swapped later on without restarting
+    let (level_filter, handle) = reload::Layer::new(level_filter);
+    tracing_subscriber::registry()
+        .with(
+            fmt::layer()
+                .with_level(*display_level)
+                .with_filter(level_filter.and(filter_fn(|metadata| !metadata.is_span()))),
+        )
+        .with(otel_layer)
         .init();
+    let _ = LEVEL_FILTER.set(handle);
+}
+
+// Sends the spans still buffered before the process exits
+pub fn shutdown() {
+    if TRACING.load(Ordering::Relaxed) {
+        global::shutdown_tracer_provider();
+    }
+}
+
+pub fn set_level_filter(level_filter: &str) -> Result<(), String> {
+    let level_filter = match LevelFilter::from_str(level_filter) {
+        Ok(level) => level,
+        Err(err) => return Err(err.to_string()),
+    };
+    match LEVEL_FILTER.get() {
+        Some(handle) => handle.reload(level_filter).map_err(|err| err.to_string()),
+        None => Err("Logging isn't initialized".to_owned()),
+    }
+}
+
+// Continues the trace of the caller when the request carries a W3C traceparent header. The route
+// and status code are recorded once the request is handled.
+pub fn http_span(
+    method: &str,
+    path: &str,
+    traceparent: Option<&str>,
+    tracestate: Option<&str>,
+) -> Span {
+    if !TRACING.load(Ordering::Relaxed) {
+        return Span::none();
+    }
+
+    let span = info_span!(
+        "http.request",
+        otel.name = Empty,
+        otel.kind = "server",
+        otel.status_code = Empty,
+        http.request.method = method,
+        url.path = path,
+        http.route = Empty,
+        http.response.status_code = Empty,
+    );
+    if let Some(traceparent) = traceparent {
+        let mut carrier = HashMap::from([("traceparent".to_owned(), traceparent.to_owned())]);
+        if let Some(tracestate) = tracestate {
+            carrier.insert("tracestate".to_owned(), tracestate.to_owned());
+        }
+        span.set_parent(global::get_text_map_propagator(|propagator| {
+            propagator.extract(&carrier)
+        }));
+    }
+    span
+}
+
+// The route is the pattern the request matched, like /api/rest/project/{project_id}, which keeps
+// the span names from growing with every id
+pub fn record_http_response(span: &Span, method: &str, route: Option<&str>, status_code: u16) {
+    match route {
+        Some(route) => {
+            span.record("otel.name", format!("{method} {route}").as_str());
+            span.record("http.route", route);
+        }
+        None => {
+            span.record("otel.name", method);
+        }
+    }
+    span.record("http.response.status_code", status_code);
+    if status_code >= 500 {
+        span.record("otel.status_code", "ERROR");
+    }
+}
+
+// Bind values are never part of the statement text, so nothing sensitive ends up in the span
+pub fn db_span(system: &str, statement: &str) -> Span {
+    if !TRACING.load(Ordering::Relaxed) {
+        return Span::none();
+    }
+
+    let operation = statement
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_uppercase();
+    info_span!(
+        "db.query",
+        otel.name = %format!("{system} {operation}"),
+        otel.kind = "client",
+        db.system = system,
+        db.operation = operation.as_str(),
+        db.statement = statement,
+    )
 }
 
 pub fn trace<T: Display>(prefix: Option<&str>, msg: T) {
@@ -62,6 +217,35 @@ pub fn error<T: Display>(prefix: Option<&str>, msg: T) {
     };
 }
 
+// Panics are reported through the logger so they aren't lost when they happen inside a task
+pub fn set_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        let thread = std::thread::current();
+        let location = match info.location() {
+            Some(location) => format!(" at {}:{}", location.file(), location.line()),
+            None => String::new(),
+        };
+        error(
+            Some("☠️"),
+            format!(
+                "Thread '{}' panicked{location}: {}",
+                thread.name().unwrap_or("<unnamed>"),
+                panic_payload(info.payload())
+            ),
+        );
+    }));
+}
+
+pub fn panic_payload(payload: &(dyn Any + Send)) -> String {
+    if let Some(payload) = payload.downcast_ref::<&str>() {
+        (*payload).to_owned()
+    } else if let Some(payload) = payload.downcast_ref::<String>() {
+        payload.to_owned()
+    } else {
+        "Box<dyn Any>".to_owned()
+    }
+}
+
 pub fn panic<T: Display>(prefix: Option<&str>, msg: T) {
     match prefix {
         Some(prefix) => panic!("{prefix} {msg}"),
@@ -0,0 +1,187 @@
+use std::{collections::HashMap, time::Duration};
+
+use lettre::message::Mailbox;
+use reqwest::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    Client, StatusCode,
+};
+
+use crate::{MailPayload, SendError};
+
+const SENDGRID_URL: &str = "https://api.sendgrid.com/v3/mail/send";
+
+#[derive(Clone, Copy)]
+enum HttpPreset {
+    Generic,
+    Sendgrid,
+    Mailgun,
+}
+
+impl HttpPreset {
+    fn from_str(preset: &str) -> Option<Self> {
+        match preset {
+            "generic" => Some(Self::Generic),
+            "sendgrid" => Some(Self::Sendgrid),
+            "mailgun" => Some(Self::Mailgun),
+            _ => None,
+        }
+    }
+}
+
+// Sends emails as a POST to an email API. The API key and the headers are only ever put on the
+// requests, where they're marked sensitive, and are left out of every error.
+#[derive(Clone)]
+pub struct HttpTransport {
+    client: Client,
+    preset: HttpPreset,
+    url: String,
+    api_key: Option<String>,
+    headers: HeaderMap,
+}
+
+impl HttpTransport {
+    pub fn new(
+        preset: &Option<String>,
+        url: &Option<String>,
+        api_key: &Option<String>,
+        headers: &Option<HashMap<String, String>>,
+        timeout: &Duration,
+    ) -> Self {
+        let preset = match HttpPreset::from_str(preset.as_deref().unwrap_or("generic")) {
+            Some(preset) => preset,
+            None => {
+                hb_log::panic(
+                    None,
+                    "[Mailer] Unknown http preset, expected generic, sendgrid or mailgun",
+                );
+                unreachable!()
+            }
+        };
+
+        let url = match (url, &preset) {
+            (Some(url), _) => url.to_owned(),
+            (None, HttpPreset::Sendgrid) => SENDGRID_URL.to_owned(),
+            (None, _) => {
+                hb_log::panic(
+                    None,
+                    "[Mailer] http url is required for the generic and mailgun presets",
+                );
+                unreachable!()
+            }
+        };
+
+        let mut header_map = HeaderMap::new();
+        if let Some(headers) = headers {
+            for (name, value) in headers {
+                let name = match HeaderName::from_bytes(name.as_bytes()) {
+                    Ok(name) => name,
+                    Err(_) => {
+                        hb_log::panic(None, &format!("[Mailer] Invalid http header name '{name}'"));
+                        unreachable!()
+                    }
+                };
+                let mut value = match HeaderValue::from_str(value) {
+                    Ok(value) => value,
+                    Err(_) => {
+                        hb_log::panic(
+                            None,
+                            &format!("[Mailer] Invalid value for http header '{name}'"),
+                        );
+                        unreachable!()
+                    }
+                };
+                value.set_sensitive(true);
+                header_map.insert(name, value);
+            }
+        }
+
+        Self {
+            client: Client::builder().timeout(*timeout).build().unwrap(),
+            preset,
+            url,
+            api_key: api_key.to_owned(),
+            headers: header_map,
+        }
+    }
+
+    pub(crate) async fn send(
+        &self,
+        sender: &Mailbox,
+        to: &Mailbox,
+        payload: &MailPayload,
+    ) -> Result<(), SendError> {
+        let request = match self.preset {
+            HttpPreset::Generic => self
+                .client
+                .post(&self.url)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(
+                    serde_json::json!({
+                        "from": {
+                            "name": sender.name,
+                            "email": sender.email.to_string(),
+                        },
+                        "to": to.email.to_string(),
+                        "subject": payload.subject,
+                        "body": payload.body,
+                    })
+                    .to_string(),
+                ),
+            HttpPreset::Sendgrid => self
+                .client
+                .post(&self.url)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(
+                    serde_json::json!({
+                        "personalizations": [{
+                            "to": [{ "email": to.email.to_string() }],
+                        }],
+                        "from": {
+                            "name": sender.name,
+                            "email": sender.email.to_string(),
+                        },
+                        "subject": payload.subject,
+                        "content": [{
+                            "type": "text/plain",
+                            "value": payload.body,
+                        }],
+                    })
+                    .to_string(),
+                ),
+            HttpPreset::Mailgun => self.client.post(&self.url).form(&[
+                ("from", sender.to_string()),
+                ("to", to.to_string()),
+                ("subject", payload.subject.to_owned()),
+                ("text", payload.body.to_owned()),
+            ]),
+        };
+
+        let request = request.headers(self.headers.clone());
+        let request = match (&self.api_key, self.preset) {
+            (Some(api_key), HttpPreset::Mailgun) => request.basic_auth("api", Some(api_key)),
+            (Some(api_key), _) => request.bearer_auth(api_key),
+            (None, _) => request,
+        };
+
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(err) => {
+                return Err(SendError::new(false, &err.without_url().to_string()));
+            }
+        };
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        // Rate limits and timeouts are worth another try, any other rejection of the request
+        // will be the same the next time
+        let permanent = status.is_client_error()
+            && status != StatusCode::REQUEST_TIMEOUT
+            && status != StatusCode::TOO_MANY_REQUESTS;
+        Err(SendError::new(
+            permanent,
+            &format!("Email provider responded with {status}"),
+        ))
+    }
+}
@@ -1,55 +1,98 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use chrono::Utc;
 use lettre::{
-    message::{Mailbox, MessageBuilder},
-    transport::smtp::authentication::Credentials,
-    Message, SmtpTransport, Transport,
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+use tokio::{
+    fs::OpenOptions,
+    io::AsyncWriteExt,
+    sync::{mpsc, watch, Semaphore},
+    task::JoinHandle,
 };
-use tokio::{sync::mpsc, task::JoinHandle};
 use tokio_util::sync::CancellationToken;
 
+use self::http::HttpTransport;
+
+pub mod http;
+
+// Emails being sent at the same time. Once they're all taken the queue isn't read any further.
+const MAX_CONCURRENT_SENDS: usize = 8;
+
+// The queue is bounded by the channel capacity. When it's full, sending a payload waits until
+// there's room instead of dropping it, so a slow SMTP server slows down the requests that send
+// emails rather than losing their emails.
 pub struct Mailer {
-    message_builder: MessageBuilder,
-    smtp_transport: SmtpTransport,
+    sender: Mailbox,
+    // Replaced when the config is reloaded, every email is sent with the latest one
+    transport: watch::Receiver<MailerTransport>,
+    max_retries: u32,
+    retry_interval: Duration,
+    dead_letter_path: Option<PathBuf>,
+    sends: Arc<Semaphore>,
     channel_receiver: mpsc::Receiver<MailPayload>,
 }
 
 impl Mailer {
     pub fn new(
-        smtp_host: &str,
-        smtp_username: &str,
-        smtp_password: &str,
+        transport: watch::Receiver<MailerTransport>,
         sender_name: &str,
         sender_email: &str,
+        channel_capacity: &usize,
+        max_retries: &u32,
+        retry_interval: &Duration,
+        dead_letter_path: &Option<String>,
     ) -> (Self, mpsc::Sender<MailPayload>) {
         hb_log::info(Some("⚡"), "[Mailer] Initializing component");
 
-        let (sender, receiver) = mpsc::channel::<MailPayload>(32);
+        let (sender, receiver) = mpsc::channel::<MailPayload>((*channel_capacity).max(1));
 
         (
             Self {
-                message_builder: Message::builder()
-                    .from(format!("{sender_name} <{sender_email}>").parse().unwrap()),
-                smtp_transport: SmtpTransport::relay(smtp_host)
-                    .unwrap()
-                    .credentials(Credentials::new(
-                        smtp_username.to_owned(),
-                        smtp_password.to_owned(),
-                    ))
-                    .build(),
+                sender: format!("{sender_name} <{sender_email}>").parse().unwrap(),
+                transport,
+                max_retries: *max_retries,
+                retry_interval: *retry_interval,
+                dead_letter_path: dead_letter_path.as_ref().map(PathBuf::from),
+                sends: Arc::new(Semaphore::new(MAX_CONCURRENT_SENDS)),
                 channel_receiver: receiver,
             },
             sender,
         )
     }
 
-    pub fn run_none() -> JoinHandle<()> {
+    pub fn run_none(cancel_token: CancellationToken) -> JoinHandle<Result<()>> {
         hb_log::info(Some("⏩"), "[Mailer] Skipping component");
 
-        tokio::spawn((|| async {})())
+        tokio::spawn((|| async move {
+            cancel_token.cancelled().await;
+            Ok(())
+        })())
     }
 
-    pub fn run(mut self, cancel_token: CancellationToken) -> JoinHandle<()> {
+    pub fn run(mut self, cancel_token: CancellationToken) -> JoinHandle<Result<()>> {
         hb_log::info(Some("💫"), "[Mailer] Running component");
 
+        // Emails are still queued when the server can't be reached, it may come up later
+        if let MailerTransport::Smtp(smtp_transport) = &*self.transport.borrow() {
+            let smtp_transport = smtp_transport.clone();
+            tokio::spawn((|| async move {
+                match smtp_transport.test_connection().await {
+                    Ok(true) => hb_log::info(None, "[Mailer] Connected to the SMTP server"),
+                    Ok(false) => hb_log::warn(
+                        None,
+                        "[Mailer] SMTP server didn't respond to the connection test",
+                    ),
+                    Err(err) => hb_log::warn(
+                        None,
+                        &format!("[Mailer] Failed to connect to the SMTP server: {err}"),
+                    ),
+                }
+            })());
+        }
+
         tokio::spawn((|| async move {
             loop {
                 tokio::select! {
@@ -61,33 +104,7 @@ impl Mailer {
                     }
                     recv = self.channel_receiver.recv() => {
                         match recv {
-                            Some(payload) => {
-                                let mailbox = match payload.to.parse::<Mailbox>() {
-                                    Ok(mailbox) => mailbox,
-                                    Err(err) => {
-                                        hb_log::error(None, &err);
-                                        continue;
-                                    }
-                                };
-
-                                let message = match self
-                                    .message_builder
-                                    .to_owned()
-                                    .to(mailbox)
-                                    .subject(payload.subject)
-                                    .body(payload.body)
-                                {
-                                    Ok(message) => message,
-                                    Err(err) => {
-                                        hb_log::error(None, &err);
-                                        continue;
-                                    }
-                                };
-
-                                if let Err(err) = self.smtp_transport.send(&message) {
-                                    hb_log::error(None, &err);
-                                }
-                            },
+                            Some(payload) => self.dispatch(payload).await,
                             None => {
                                 break;
                             }
@@ -97,8 +114,218 @@ impl Mailer {
             }
 
             hb_log::info(None, "[Mailer] Shutting down component");
+
+            Ok(())
         })())
     }
+
+    // Every email is sent on its own task so one being retried doesn't hold back the others. This
+    // waits for a free send first, which is what leaves the queue unread while the SMTP server is
+    // slow.
+    async fn dispatch(&self, payload: MailPayload) {
+        let permit = match self.sends.clone().acquire_owned().await {
+            Ok(permit) => permit,
+            Err(_) => return,
+        };
+
+        let to = match payload.to.parse::<Mailbox>() {
+            Ok(to) => to,
+            Err(err) => {
+                dead_letter(&self.dead_letter_path, &payload, &0, &err.to_string()).await;
+                return;
+            }
+        };
+
+        let transport = self.transport.borrow().clone();
+        let sender = self.sender.clone();
+        let max_retries = self.max_retries;
+        let retry_interval = self.retry_interval;
+        let dead_letter_path = self.dead_letter_path.clone();
+
+        tokio::spawn((|| async move {
+            if let Err((attempts, err)) = send(
+                &transport,
+                &sender,
+                &to,
+                &payload,
+                max_retries,
+                retry_interval,
+            )
+            .await
+            {
+                dead_letter(&dead_letter_path, &payload, &attempts, &err).await;
+            }
+            drop(permit);
+        })());
+    }
+}
+
+// Both transports take the same payloads from the same queue, and share the retries and the
+// dead-letter file
+#[derive(Clone)]
+pub enum MailerTransport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    Http(HttpTransport),
+}
+
+impl MailerTransport {
+    pub fn smtp(
+        smtp_host: &str,
+        smtp_port: &Option<u16>,
+        smtp_security: &Option<String>,
+        smtp_username: &Option<String>,
+        smtp_password: &Option<String>,
+    ) -> Self {
+        // Without a port, every mode connects to its usual one: 465 for tls, 587 for starttls and
+        // 25 for none
+        let mut smtp_transport = match smtp_security.as_deref() {
+            None | Some("tls") => AsyncSmtpTransport::<Tokio1Executor>::relay(smtp_host),
+            Some("starttls") => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(smtp_host),
+            Some("none") => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                smtp_host,
+            )),
+            Some(smtp_security) => {
+                hb_log::panic(
+                    None,
+                    &format!(
+                        "[Mailer] Unknown smtp_security '{smtp_security}', expected tls, starttls or none"
+                    ),
+                );
+                unreachable!()
+            }
+        }
+        .unwrap();
+        if let Some(smtp_port) = smtp_port {
+            smtp_transport = smtp_transport.port(*smtp_port);
+        }
+        if let (Some(smtp_username), Some(smtp_password)) = (smtp_username, smtp_password) {
+            smtp_transport = smtp_transport.credentials(Credentials::new(
+                smtp_username.to_owned(),
+                smtp_password.to_owned(),
+            ));
+        }
+
+        Self::Smtp(smtp_transport.build())
+    }
+
+    async fn send(
+        &self,
+        sender: &Mailbox,
+        to: &Mailbox,
+        payload: &MailPayload,
+    ) -> Result<(), SendError> {
+        match self {
+            Self::Smtp(smtp_transport) => {
+                let message = match Message::builder()
+                    .from(sender.clone())
+                    .to(to.clone())
+                    .subject(&payload.subject)
+                    .body(payload.body.clone())
+                {
+                    Ok(message) => message,
+                    Err(err) => return Err(SendError::new(true, &err.to_string())),
+                };
+                match smtp_transport.send(message).await {
+                    Ok(_) => Ok(()),
+                    Err(err) => Err(SendError::new(err.is_permanent(), &err.to_string())),
+                }
+            }
+            Self::Http(http_transport) => http_transport.send(sender, to, payload).await,
+        }
+    }
+}
+
+struct SendError {
+    permanent: bool,
+    message: String,
+}
+
+impl SendError {
+    fn new(permanent: bool, message: &str) -> Self {
+        Self {
+            permanent,
+            message: message.to_owned(),
+        }
+    }
+}
+
+// Retries with an interval that doubles after every failed attempt. A permanent rejection, like
+// an unknown recipient, isn't retried.
+async fn send(
+    transport: &MailerTransport,
+    sender: &Mailbox,
+    to: &Mailbox,
+    payload: &MailPayload,
+    max_retries: u32,
+    mut retry_interval: Duration,
+) -> Result<(), (u32, String)> {
+    let mut attempt = 0;
+    loop {
+        let err = match transport.send(sender, to, payload).await {
+            Ok(_) => return Ok(()),
+            Err(err) => err,
+        };
+        attempt += 1;
+        if err.permanent || attempt > max_retries {
+            return Err((attempt, err.message));
+        }
+        hb_log::warn(
+            None,
+            &format!(
+                "[Mailer] Attempt {attempt} to send an email failed, retrying: {}",
+                err.message
+            ),
+        );
+        tokio::time::sleep(retry_interval).await;
+        retry_interval *= 2;
+    }
+}
+
+// Emails that can't be sent are kept as JSON lines in the dead-letter file when there is one, so
+// they can be looked into and sent again by hand. They're only logged otherwise.
+async fn dead_letter(
+    dead_letter_path: &Option<PathBuf>,
+    payload: &MailPayload,
+    attempts: &u32,
+    err: &str,
+) {
+    hb_log::error(
+        None,
+        &format!(
+            "[Mailer] Failed to send an email to '{}' after {attempts} attempt(s): {err}",
+            payload.to
+        ),
+    );
+
+    let dead_letter_path = match dead_letter_path {
+        Some(dead_letter_path) => dead_letter_path,
+        None => return,
+    };
+    let line = serde_json::json!({
+        "failed_at": Utc::now(),
+        "to": payload.to,
+        "subject": payload.subject,
+        "body": payload.body,
+        "attempts": attempts,
+        "error": err,
+    })
+    .to_string()
+        + "\n";
+    let res = match OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dead_letter_path)
+        .await
+    {
+        Ok(mut file) => file.write_all(line.as_bytes()).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = res {
+        hb_log::error(
+            None,
+            &format!("[Mailer] Error when writing to the dead-letter file: {err}"),
+        );
+    }
 }
 
 pub struct MailPayload {
@@ -0,0 +1,194 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use hb_dao::{
+    collection::CollectionDao, file::FileDao, record::RecordDao, token_usage::TokenUsage, Db,
+};
+use tokio::{
+    task::JoinHandle,
+    time::{self, Instant, MissedTickBehavior},
+};
+use tokio_util::sync::CancellationToken;
+
+// The bucket storage is checked against the database far less often than records are swept, since
+// it goes through every file
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(3600);
+
+// A partial write untouched for this long belongs to a write that won't finish
+const PARTIAL_WRITE_MAX_AGE: Duration = Duration::from_secs(3600);
+
+// Deletes the expired records of the collections with a TTL in the background, so reads never
+// have to. Reads leave expired records out on their own until they are deleted. Upload sessions
+// that stopped receiving chunks are removed along the way, and the token usage counted in memory
+// is written out. Every RECONCILE_INTERVAL the bucket storage is reconciled with the database.
+pub struct Sweeper {
+    db: Arc<Db>,
+    bucket_path: String,
+    token_usage: Arc<TokenUsage>,
+    interval: Duration,
+    batch_size: i64,
+    upload_session_ttl: i64,
+}
+
+impl Sweeper {
+    pub fn new(
+        db: Arc<Db>,
+        bucket_path: &str,
+        token_usage: Arc<TokenUsage>,
+        interval: &Duration,
+        batch_size: &i64,
+        upload_session_ttl: &i64,
+    ) -> Self {
+        hb_log::info(Some("⚡"), "[Sweeper] Initializing component");
+
+        Self {
+            db,
+            bucket_path: bucket_path.to_owned(),
+            token_usage,
+            interval: *interval,
+            batch_size: (*batch_size).max(1),
+            upload_session_ttl: *upload_session_ttl,
+        }
+    }
+
+    pub fn run(self, cancel_token: CancellationToken) -> JoinHandle<Result<()>> {
+        hb_log::info(Some("💫"), "[Sweeper] Running component");
+
+        tokio::spawn((|| async move {
+            let mut interval = time::interval(self.interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // The partial writes were already cleaned at startup
+            let mut reconcile_interval =
+                time::interval_at(Instant::now() + RECONCILE_INTERVAL, RECONCILE_INTERVAL);
+            reconcile_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        break;
+                    }
+                    _ = tokio::signal::ctrl_c() => {
+                        break;
+                    }
+                    _ = interval.tick() => {
+                        if let Err(err) = self.sweep(&cancel_token).await {
+                            hb_log::error(
+                                None,
+                                &format!("[Sweeper] Error when getting collections data: {err}"),
+                            );
+                        }
+                        if let Err(err) =
+                            FileDao::delete_stale_uploads(&self.db, &self.upload_session_ttl).await
+                        {
+                            hb_log::error(
+                                None,
+                                &format!("[Sweeper] Error when deleting stale upload sessions: {err}"),
+                            );
+                        }
+                        self.flush_token_usage().await;
+                        hb_log::debug(
+                            None,
+                            &format!("[Sweeper] Database pool: {}", self.db.pool_status()),
+                        );
+                    }
+                    _ = reconcile_interval.tick() => {
+                        self.reconcile().await;
+                    }
+                }
+            }
+
+            // Don't lose the requests counted since the last tick
+            self.flush_token_usage().await;
+
+            hb_log::info(None, "[Sweeper] Shutting down component");
+
+            Ok(())
+        })())
+    }
+
+    // Every collection is swept batch by batch until a batch comes back short, checking for a
+    // shutdown in between so a large backlog doesn't hold it up
+    async fn sweep(&self, cancel_token: &CancellationToken) -> Result<()> {
+        let collections_data = CollectionDao::db_select_many(&self.db).await?;
+
+        for collection_data in collections_data {
+            if collection_data.opt_ttl().is_none() && !*collection_data.opt_per_record_ttl() {
+                continue;
+            }
+
+            loop {
+                if cancel_token.is_cancelled() {
+                    return Ok(());
+                }
+                match RecordDao::db_delete_expired(&self.db, &collection_data, &self.batch_size)
+                    .await
+                {
+                    Ok(deleted) => {
+                        if deleted < self.batch_size as u64 {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        hb_log::error(
+                            None,
+                            &format!(
+                                "[Sweeper] Error when deleting expired records of collection id '{}': {err}",
+                                collection_data.id()
+                            ),
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Partial writes that were left behind are removed. Files whose blob is gone are only reported,
+    // their rows are what an operator needs to restore the blobs from a backup.
+    async fn reconcile(&self) {
+        match FileDao::clean_partial_writes(&self.bucket_path, &PARTIAL_WRITE_MAX_AGE).await {
+            Ok(count) => {
+                if count > 0 {
+                    hb_log::warn(
+                        None,
+                        &format!("[Sweeper] Removed {count} partially written file(s) from the bucket storage"),
+                    );
+                }
+            }
+            Err(err) => hb_log::error(
+                None,
+                &format!("[Sweeper] Error when cleaning partially written files: {err}"),
+            ),
+        }
+
+        match FileDao::find_missing_blobs(&self.db, &self.bucket_path).await {
+            Ok(files_data) => {
+                for file_data in &files_data {
+                    hb_log::warn(
+                        None,
+                        &format!(
+                            "[Sweeper] File id '{}' of bucket id '{}' has no blob in the bucket storage",
+                            file_data.id(),
+                            file_data.bucket_id()
+                        ),
+                    );
+                }
+            }
+            Err(err) => hb_log::error(
+                None,
+                &format!("[Sweeper] Error when checking the blobs of files: {err}"),
+            ),
+        }
+    }
+
+    async fn flush_token_usage(&self) {
+        if let Err(err) = self.token_usage.flush(&self.db).await {
+            hb_log::error(
+                None,
+                &format!("[Sweeper] Error when writing token usage: {err}"),
+            );
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use std::time;
 
-use anyhow::Result;
+use anyhow::{Error, Result};
+use hmac::{Hmac, Mac};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use sha2::Sha256;
 
 use crate::claim::{Claim, ClaimId};
 
@@ -9,6 +11,7 @@ pub struct JwtToken {
     header: Header,
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
+    secret: Vec<u8>,
     expiry_duration: u64,
 }
 
@@ -21,6 +24,7 @@ impl JwtToken {
             header: Header::default(),
             encoding_key: EncodingKey::from_secret(secret),
             decoding_key: DecodingKey::from_secret(secret),
+            secret: secret.to_vec(),
             expiry_duration: *expiry_duration,
         }
     }
@@ -66,4 +70,26 @@ impl JwtToken {
     pub fn renew(&self, claim: &Claim) -> Result<String> {
         self.encode(claim.id())
     }
+
+    // Signs a message with the same secret as the tokens, for credentials that are shorter lived
+    // than a token such as presigned urls
+    pub fn sign(&self, message: &str) -> Result<String> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)
+            .map_err(|err| Error::msg(err.to_string()))?;
+        mac.update(message.as_bytes());
+        Ok(hex::encode(mac.finalize().into_bytes()))
+    }
+
+    pub fn verify_signature(&self, message: &str, signature: &str) -> bool {
+        let signature = match hex::decode(signature) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+        let mut mac = match Hmac::<Sha256>::new_from_slice(&self.secret) {
+            Ok(mac) => mac,
+            Err(_) => return false,
+        };
+        mac.update(message.as_bytes());
+        mac.verify_slice(&signature).is_ok()
+    }
 }
@@ -0,0 +1,162 @@
+use std::time;
+
+use anyhow::{Error, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    digest, hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+
+const SECRET_LEN: usize = 20;
+const DIGITS: u32 = 6;
+const PERIOD: u64 = 30;
+// Codes of the steps right before and after the current one are still accepted, so a small clock
+// drift between the server and the authenticator app doesn't lock anyone out
+const SKEW: i64 = 1;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+pub struct TotpToken {
+    issuer: String,
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl TotpToken {
+    pub fn new(issuer: &str, secret: &str) -> Self {
+        hb_log::info(Some("⚡"), "[TotpToken] Initializing component");
+
+        // The configured secret can be any string, its digest is a key of the length AES-256 needs
+        let key = digest::digest(&digest::SHA256, secret.as_bytes());
+        Self {
+            issuer: issuer.to_owned(),
+            key: LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key.as_ref()).unwrap()),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    pub fn generate_secret(&self) -> Result<Vec<u8>> {
+        let mut secret = vec![0; SECRET_LEN];
+        self.rng
+            .fill(&mut secret)
+            .map_err(|_| Error::msg("Failed to generate a TOTP secret"))?;
+        Ok(secret)
+    }
+
+    // Secrets are kept encrypted at rest, so a leaked database alone isn't enough to generate codes
+    pub fn encrypt_secret(&self, secret: &[u8]) -> Result<String> {
+        let mut nonce = [0; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| Error::msg("Failed to generate a nonce for the TOTP secret"))?;
+        let mut in_out = secret.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut in_out,
+            )
+            .map_err(|_| Error::msg("Failed to encrypt the TOTP secret"))?;
+        let mut encrypted = nonce.to_vec();
+        encrypted.append(&mut in_out);
+        Ok(STANDARD.encode(encrypted))
+    }
+
+    pub fn decrypt_secret(&self, encrypted: &str) -> Result<Vec<u8>> {
+        let encrypted = STANDARD.decode(encrypted)?;
+        if encrypted.len() < NONCE_LEN {
+            return Err(Error::msg("Encrypted TOTP secret is malformed"));
+        }
+        let (nonce, ciphertext) = encrypted.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| Error::msg("Encrypted TOTP secret is malformed"))?;
+        let mut in_out = ciphertext.to_vec();
+        let secret = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut in_out)
+            .map_err(|_| Error::msg("Failed to decrypt the TOTP secret"))?;
+        Ok(secret.to_vec())
+    }
+
+    // Key URI that authenticator apps accept, usually shown as a QR code
+    pub fn uri(&self, secret: &[u8], account: &str) -> String {
+        let issuer = percent_encode(&self.issuer);
+        format!(
+            "otpauth://totp/{issuer}:{}?secret={}&issuer={issuer}&algorithm=SHA1&digits={DIGITS}&period={PERIOD}",
+            percent_encode(account),
+            base32_encode(secret)
+        )
+    }
+
+    pub fn verify(&self, secret: &[u8], code: &str) -> Result<bool> {
+        let code = code.trim();
+        if code.len() != DIGITS as usize || !code.bytes().all(|c| c.is_ascii_digit()) {
+            return Ok(false);
+        }
+        let code = code.parse::<u32>()?;
+
+        let step = i64::try_from(
+            time::SystemTime::now()
+                .duration_since(time::UNIX_EPOCH)?
+                .as_secs()
+                / PERIOD,
+        )?;
+        let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret);
+        for offset in -SKEW..=SKEW {
+            let counter = u64::try_from(step + offset)?;
+            if Self::hotp(&key, &counter) == code {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    // RFC 4226 dynamic truncation of the HMAC of the counter
+    fn hotp(key: &hmac::Key, counter: &u64) -> u32 {
+        let tag = hmac::sign(key, &counter.to_be_bytes());
+        let hash = tag.as_ref();
+        let offset = usize::from(hash[hash.len() - 1] & 0x0f);
+        let binary = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+        binary % 10u32.pow(DIGITS)
+    }
+}
+
+// RFC 4648 base32 without padding, which is what the secret parameter of the key URI expects
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(char::from(
+                BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize],
+            ));
+        }
+    }
+    if bits > 0 {
+        encoded.push(char::from(
+            BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize],
+        ));
+    }
+    encoded
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~' | b'@') {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
@@ -0,0 +1,229 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Error, Result};
+use chrono::Utc;
+use hb_dao::{
+    log::{LogDao, LogKind},
+    webhook::{WebhookDao, WebhookEvent},
+    Db,
+};
+use hmac::{Hmac, Mac};
+use reqwest::{header, Client};
+use sha2::Sha256;
+use tokio::{sync::mpsc, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+pub struct Webhook {
+    db: Arc<Db>,
+    client: Client,
+    max_retries: u32,
+    retry_interval: Duration,
+    channel_receiver: mpsc::Receiver<WebhookPayload>,
+}
+
+impl Webhook {
+    pub fn new(
+        db: Arc<Db>,
+        channel_capacity: &usize,
+        timeout: &Duration,
+        max_retries: &u32,
+        retry_interval: &Duration,
+    ) -> (Self, mpsc::Sender<WebhookPayload>) {
+        hb_log::info(Some("⚡"), "[Webhook] Initializing component");
+
+        let (sender, receiver) = mpsc::channel::<WebhookPayload>(*channel_capacity);
+
+        (
+            Self {
+                db,
+                client: Client::builder().timeout(*timeout).build().unwrap(),
+                max_retries: *max_retries,
+                retry_interval: *retry_interval,
+                channel_receiver: receiver,
+            },
+            sender,
+        )
+    }
+
+    pub fn run_none(cancel_token: CancellationToken) -> JoinHandle<Result<()>> {
+        hb_log::info(Some("⏩"), "[Webhook] Skipping component");
+
+        tokio::spawn((|| async move {
+            cancel_token.cancelled().await;
+            Ok(())
+        })())
+    }
+
+    pub fn run(mut self, cancel_token: CancellationToken) -> JoinHandle<Result<()>> {
+        hb_log::info(Some("💫"), "[Webhook] Running component");
+
+        tokio::spawn((|| async move {
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {
+                        break;
+                    }
+                    _ = tokio::signal::ctrl_c() => {
+                        break;
+                    }
+                    recv = self.channel_receiver.recv() => {
+                        match recv {
+                            Some(payload) => self.dispatch(payload),
+                            None => {
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+
+            hb_log::info(None, "[Webhook] Shutting down component");
+
+            Ok(())
+        })())
+    }
+
+    // Every delivery runs on its own task so a slow endpoint doesn't hold back the others, which
+    // also means the deliveries of a webhook may arrive out of order
+    fn dispatch(&self, payload: WebhookPayload) {
+        let db = self.db.clone();
+        let client = self.client.clone();
+        let max_retries = self.max_retries;
+        let retry_interval = self.retry_interval;
+
+        tokio::spawn((|| async move {
+            let webhooks_data = match WebhookDao::db_select_many_by_collection_id(
+                &db,
+                &payload.collection_id,
+            )
+            .await
+            {
+                Ok(data) => data,
+                Err(err) => {
+                    hb_log::error(
+                        None,
+                        &format!("[Webhook] Error when getting webhooks data: {err}"),
+                    );
+                    return;
+                }
+            };
+
+            for webhook_data in webhooks_data {
+                if !*webhook_data.enabled() || !webhook_data.events().contains(&payload.event) {
+                    continue;
+                }
+
+                let body = serde_json::json!({
+                    "id": Uuid::now_v7(),
+                    "webhook_id": webhook_data.id(),
+                    "collection_id": payload.collection_id,
+                    "event": payload.event.to_str(),
+                    "timestamp": Utc::now(),
+                    "data": payload.data,
+                })
+                .to_string();
+
+                tokio::spawn({
+                    let db = db.clone();
+                    let client = client.clone();
+                    (|| async move {
+                        deliver(
+                            &db,
+                            &client,
+                            &webhook_data,
+                            body,
+                            max_retries,
+                            retry_interval,
+                        )
+                        .await
+                    })()
+                });
+            }
+        })());
+    }
+}
+
+async fn deliver(
+    db: &Db,
+    client: &Client,
+    webhook_data: &WebhookDao,
+    body: String,
+    max_retries: u32,
+    mut retry_interval: Duration,
+) {
+    let signature = match sign(webhook_data.secret(), &body) {
+        Ok(signature) => signature,
+        Err(err) => {
+            hb_log::error(
+                None,
+                &format!("[Webhook] Error when signing webhook payload: {err}"),
+            );
+            return;
+        }
+    };
+
+    let mut attempt = 0;
+    let err = loop {
+        let err = match client
+            .post(webhook_data.url())
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("X-Hyperbase-Signature", format!("sha256={signature}"))
+            .body(body.clone())
+            .send()
+            .await
+        {
+            Ok(res) if res.status().is_success() => return,
+            Ok(res) => format!("Endpoint responded with status {}", res.status()),
+            Err(err) => err.to_string(),
+        };
+        if attempt >= max_retries {
+            break err;
+        }
+        attempt += 1;
+        tokio::time::sleep(retry_interval).await;
+        retry_interval *= 2;
+    };
+
+    let log_data = LogDao::new(
+        webhook_data.admin_id(),
+        webhook_data.project_id(),
+        &LogKind::Error,
+        &format!(
+            "Webhook: Failed to deliver to webhook id '{}' after {} attempt(s): {}",
+            webhook_data.id(),
+            attempt + 1,
+            err
+        ),
+    );
+    if let Err(err) = log_data.db_insert(db).await {
+        hb_log::error(
+            None,
+            &format!("[Webhook] Error when inserting log data: {err}"),
+        );
+    }
+}
+
+// Receivers verify a delivery by computing the HMAC-SHA256 of the raw body with the webhook secret
+fn sign(secret: &str, body: &str) -> Result<String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|err| Error::msg(err.to_string()))?;
+    mac.update(body.as_bytes());
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+pub struct WebhookPayload {
+    collection_id: Uuid,
+    event: WebhookEvent,
+    data: serde_json::Value,
+}
+
+impl WebhookPayload {
+    pub fn new(collection_id: &Uuid, event: &WebhookEvent, data: &serde_json::Value) -> Self {
+        Self {
+            collection_id: *collection_id,
+            event: *event,
+            data: data.clone(),
+        }
+    }
+}